@@ -1,101 +1,528 @@
 //! Performance benchmarking for MANA
 //!
 //! Measures key performance metrics to ensure MANA meets latency targets:
-//! - Context injection: <10ms
+//! - Context injection: <10ms (cold process and via the daemon)
 //! - Pattern search: <0.5ms
-//! - Session-end parsing: <20ms
-
-use anyhow::Result;
+//! - Embedding search at 1k/10k vectors (and 100k with `--full`)
+//! - Batch insert throughput and reflection cycle time
+//!
+//! Each benchmark reports p50/p95/p99. Runs are saved as JSON under
+//! `.mana/bench/`, and `mana bench --compare <file>` diffs a run against an
+//! earlier one to catch regressions.
+//!
+//! `mana bench --markdown` also prints the run as a markdown table, and the
+//! command exits non-zero when a critical benchmark misses its target.
+//!
+//! `mana bench --insert` measures batch insert throughput for relearn-sized
+//! histories and appends its recommendation to bench-history.jsonl.
+
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::time::Instant;
 
-/// Run performance benchmarks
-pub async fn run_benchmarks() -> Result<BenchmarkResults> {
-    println!("MANA Performance Benchmarks");
-    println!("===========================");
-    println!();
+use crate::get_mana_dir;
+
+/// Directory under the data dir holding saved runs
+const BENCH_DIR: &str = "bench";
+
+/// Vector counts for the embedding search benchmark
+const EMBED_SCALES: [usize; 2] = [1_000, 10_000];
 
-    let mut results = BenchmarkResults::default();
-
-    // Benchmark 1: Context injection latency
-    println!("1. Context Injection Latency");
-    println!("   Target: <10ms");
-    let injection_times = benchmark_injection(10)?;
-    let avg_injection = injection_times.iter().sum::<u128>() as f64 / injection_times.len() as f64;
-    let min_injection = *injection_times.iter().min().unwrap_or(&0);
-    let max_injection = *injection_times.iter().max().unwrap_or(&0);
-    results.injection_avg_ms = avg_injection / 1000.0;
-    results.injection_min_ms = min_injection as f64 / 1000.0;
-    results.injection_max_ms = max_injection as f64 / 1000.0;
-
-    println!("   Avg: {:.2}ms  Min: {:.2}ms  Max: {:.2}ms",
-             results.injection_avg_ms, results.injection_min_ms, results.injection_max_ms);
-    if results.injection_avg_ms < 10.0 {
+/// Extra scale for `--full`; building its HNSW graph takes minutes
+const EMBED_FULL_SCALE: usize = 100_000;
+
+/// p95 growth (relative) flagged as a regression by `--compare`
+const REGRESSION_RATIO: f64 = 0.2;
+
+/// Differences smaller than this (ms) are noise, never regressions
+const REGRESSION_FLOOR_MS: f64 = 0.05;
+
+/// Latency distribution of one benchmark
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub samples: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    /// Items processed per second, for throughput benchmarks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_sec: Option<f64>,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles over timings in microseconds
+    pub fn from_micros(times: &[u128]) -> Self {
+        if times.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = times.to_vec();
+        sorted.sort_unstable();
+        let rank = |p: f64| {
+            let index = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+            sorted[index.clamp(1, sorted.len()) - 1] as f64 / 1000.0
+        };
+        Self {
+            samples: sorted.len(),
+            mean_ms: sorted.iter().sum::<u128>() as f64 / sorted.len() as f64 / 1000.0,
+            p50_ms: rank(50.0),
+            p95_ms: rank(95.0),
+            p99_ms: rank(99.0),
+            per_sec: None,
+        }
+    }
+}
+
+/// A saved benchmark run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub timestamp: String,
+    pub version: String,
+    /// Patterns in the database the injection and search benchmarks ran against
+    pub pattern_count: i64,
+    pub benchmarks: BTreeMap<String, Percentiles>,
+}
+
+impl BenchReport {
+    /// Check if all critical benchmarks pass
+    pub fn all_pass(&self) -> bool {
+        self.benchmarks.get("inject_cold").is_none_or(|b| b.p95_ms < 10.0)
+            && self.benchmarks.get("startup").is_none_or(|b| b.p95_ms < 50.0)
+    }
+
+    /// Format results as a markdown table (for GitHub issue updates)
+    pub fn to_markdown(&self) -> String {
+        let mut table = String::from("| Benchmark | p50 | p95 | p99 |\n|-----------|-----|-----|-----|\n");
+        for (name, b) in &self.benchmarks {
+            table.push_str(&format!("| {} | {:.3}ms | {:.3}ms | {:.3}ms |\n", name, b.p50_ms, b.p95_ms, b.p99_ms));
+        }
+        table.push_str(&format!("| Pattern count | {} | | |", self.pattern_count));
+        table
+    }
+}
+
+/// One benchmark's change against a baseline run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub name: String,
+    pub baseline_p95_ms: f64,
+    pub current_p95_ms: f64,
+    pub regression: bool,
+}
+
+/// Compare benchmarks present in both runs by p95
+pub fn compare(baseline: &BenchReport, current: &BenchReport) -> Vec<Comparison> {
+    current
+        .benchmarks
+        .iter()
+        .filter_map(|(name, now)| {
+            let before = baseline.benchmarks.get(name)?;
+            let delta = now.p95_ms - before.p95_ms;
+            Some(Comparison {
+                name: name.clone(),
+                baseline_p95_ms: before.p95_ms,
+                current_p95_ms: now.p95_ms,
+                regression: delta > REGRESSION_FLOOR_MS && delta > before.p95_ms * REGRESSION_RATIO,
+            })
+        })
+        .collect()
+}
+
+fn print_latency(latency: &Percentiles) {
+    println!("   p50: {:.3}ms  p95: {:.3}ms  p99: {:.3}ms  ({} samples)",
+             latency.p50_ms, latency.p95_ms, latency.p99_ms, latency.samples);
+}
+
+fn print_verdict(latency: &Percentiles, target_ms: f64, fail: &str) {
+    if latency.p95_ms < target_ms {
         println!("   ✅ PASS");
     } else {
-        println!("   ❌ FAIL (exceeds 10ms target)");
+        println!("   {}", fail);
     }
+}
+
+/// Run performance benchmarks, save the run, and optionally compare it to a baseline
+pub async fn run_benchmarks(baseline: Option<&Path>, full: bool) -> Result<BenchReport> {
+    // Read the baseline first so a bad path fails before minutes of benchmarking
+    let baseline: Option<BenchReport> = baseline
+        .map(|path| -> Result<BenchReport> {
+            let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&content).with_context(|| format!("{} is not a saved benchmark run", path.display()))
+        })
+        .transpose()?;
+
+    println!("MANA Performance Benchmarks");
+    println!("===========================");
     println!();
 
-    // Benchmark 2: Pattern search latency (just DB query, no stdin)
-    println!("2. Pattern Search Latency");
-    println!("   Target: <0.5ms");
-    let search_times = benchmark_pattern_search(20)?;
-    let avg_search = search_times.iter().sum::<u128>() as f64 / search_times.len() as f64;
-    let min_search = *search_times.iter().min().unwrap_or(&0);
-    let max_search = *search_times.iter().max().unwrap_or(&0);
-    results.search_avg_ms = avg_search / 1000.0;
-    results.search_min_ms = min_search as f64 / 1000.0;
-    results.search_max_ms = max_search as f64 / 1000.0;
-
-    println!("   Avg: {:.3}ms  Min: {:.3}ms  Max: {:.3}ms",
-             results.search_avg_ms, results.search_min_ms, results.search_max_ms);
-    if results.search_avg_ms < 0.5 {
-        println!("   ✅ PASS");
+    let mana_dir = get_mana_dir()?;
+    let config = crate::config::load_config(&mana_dir);
+    let mut benchmarks = BTreeMap::new();
+
+    // Benchmark 1: Context injection latency, a fresh process per hook call
+    println!("1. Context Injection Latency (cold process)");
+    println!("   Target: <10ms at p95");
+    let cold = Percentiles::from_micros(&benchmark_injection(30)?);
+    print_latency(&cold);
+    print_verdict(&cold, 10.0, "❌ FAIL (exceeds 10ms target)");
+    benchmarks.insert("inject_cold".to_string(), cold);
+    println!();
+
+    // Benchmark 2: The same call answered by a running daemon
+    println!("2. Context Injection Latency (daemon)");
+    println!("   Target: <10ms at p95");
+    if crate::daemon::is_running() {
+        let daemon = Percentiles::from_micros(&benchmark_daemon_injection(100)?);
+        print_latency(&daemon);
+        print_verdict(&daemon, 10.0, "❌ FAIL (exceeds 10ms target)");
+        benchmarks.insert("inject_daemon".to_string(), daemon);
     } else {
-        println!("   ⚠️  ABOVE TARGET (0.5ms) - still acceptable if injection passes");
+        println!("   ⏭️  Skipped: daemon not running (start it with 'mana daemon start')");
     }
     println!();
 
-    // Benchmark 3: Binary startup time
-    println!("3. Binary Startup Time");
-    println!("   Target: <50ms");
-    let startup_times = benchmark_startup(5)?;
-    let avg_startup = startup_times.iter().sum::<u128>() as f64 / startup_times.len() as f64;
-    results.startup_avg_ms = avg_startup / 1000.0;
+    // Benchmark 3: Pattern search latency (just DB query, no stdin)
+    println!("3. Pattern Search Latency");
+    println!("   Target: <0.5ms at p95");
+    let search = Percentiles::from_micros(&benchmark_pattern_search(100)?);
+    print_latency(&search);
+    print_verdict(&search, 0.5, "⚠️  ABOVE TARGET (0.5ms) - still acceptable if injection passes");
+    benchmarks.insert("pattern_search".to_string(), search);
+    println!();
 
-    println!("   Avg: {:.2}ms", results.startup_avg_ms);
-    if results.startup_avg_ms < 50.0 {
-        println!("   ✅ PASS");
-    } else {
-        println!("   ❌ FAIL (exceeds 50ms target)");
+    // Benchmark 4: Vector search at growing index sizes
+    println!("4. Embedding Search Latency (synthetic vectors)");
+    println!("   Exact below {} vectors, HNSW from there on, as configured", config.embeddings.ann_min_vectors);
+    let extra = full.then_some(EMBED_FULL_SCALE);
+    for scale in EMBED_SCALES.into_iter().chain(extra) {
+        let (latency, build_ms) = benchmark_embedding_search(scale, &config.embeddings, 200);
+        println!("   {} vectors{}:", scale, build_ms.map(|ms| format!(" (graph built in {:.0}ms)", ms)).unwrap_or_default());
+        print!("  ");
+        print_latency(&latency);
+        benchmarks.insert(format!("embed_search_{}k", scale / 1000), latency);
     }
     println!();
 
+    // Benchmark 5: Batch insert, timed per chunk so stalls show up in p99
+    println!("5. Batch Insert (10000 patterns)");
+    let insert = benchmark_insert_chunks(10_000, 100)?;
+    println!("   {:.0} patterns/sec; per 100-pattern chunk:", insert.per_sec.unwrap_or_default());
+    print_latency(&insert);
+    benchmarks.insert("batch_insert".to_string(), insert);
+    println!();
+
+    // Benchmark 6: Heuristic reflection over a batch of sessions
+    println!("6. Reflection Cycle (20 sessions, 1000 patterns)");
+    let reflection = benchmark_reflection(20)?;
+    print_latency(&reflection);
+    benchmarks.insert("reflection_cycle".to_string(), reflection);
+    println!();
+
+    // Benchmark 7: Binary startup time
+    println!("7. Binary Startup Time");
+    println!("   Target: <50ms at p95");
+    let startup = Percentiles::from_micros(&benchmark_startup(10)?);
+    print_latency(&startup);
+    print_verdict(&startup, 50.0, "❌ FAIL (exceeds 50ms target)");
+    benchmarks.insert("startup".to_string(), startup);
+    println!();
+
+    // Show pattern count for context
+    let db_path = mana_dir.join("metadata.sqlite");
+    let mut pattern_count = 0;
+    if db_path.exists() {
+        let conn = rusqlite::Connection::open(&db_path)?;
+        pattern_count = conn.query_row("SELECT COUNT(*) FROM patterns", [], |r| r.get(0))?;
+    }
+
+    let report = BenchReport {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pattern_count,
+        benchmarks,
+    };
+
     // Summary
     println!("Summary");
     println!("-------");
-    let all_pass = results.injection_avg_ms < 10.0 && results.startup_avg_ms < 50.0;
-    if all_pass {
+    if report.all_pass() {
         println!("✅ All critical benchmarks PASSED");
     } else {
         println!("❌ Some benchmarks FAILED - optimization needed");
     }
+    println!("Pattern count: {} (injection and search benchmarks run against this dataset)", report.pattern_count);
+
+    let bench_dir = mana_dir.join(BENCH_DIR);
+    std::fs::create_dir_all(&bench_dir)?;
+    let path = bench_dir.join(format!("{}.json", chrono::Utc::now().format("%Y%m%dT%H%M%SZ")));
+    std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+    println!("Results saved to {}", path.display());
+
+    if let Some(baseline) = &baseline {
+        println!();
+        println!("Compared to {} ({})", baseline.timestamp, baseline.version);
+        let comparisons = compare(baseline, &report);
+        for c in &comparisons {
+            let change = if c.baseline_p95_ms > 0.0 {
+                format!("{:+.0}%", (c.current_p95_ms / c.baseline_p95_ms - 1.0) * 100.0)
+            } else {
+                "n/a".to_string()
+            };
+            println!("   {:<20} p95 {:>9.3}ms -> {:>9.3}ms  {:>6}{}",
+                     c.name, c.baseline_p95_ms, c.current_p95_ms, change,
+                     if c.regression { "  ⚠️  regression" } else { "" });
+        }
+        let regressions = comparisons.iter().filter(|c| c.regression).count();
+        if regressions == 0 {
+            println!("✅ No regressions");
+        } else {
+            println!("❌ {} benchmark(s) regressed by more than {:.0}% at p95", regressions, REGRESSION_RATIO * 100.0);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Time `mana inject` requests answered by the running daemon
+fn benchmark_daemon_injection(iterations: usize) -> Result<Vec<u128>> {
+    let input = r#"{"tool":"Edit","input":{"file_path":"src/main.rs","old_string":"test"}}"#;
+    let mut times = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        crate::daemon::inject_via_daemon("edit", input, None)?;
+        times.push(start.elapsed().as_micros());
+    }
+    Ok(times)
+}
+
+/// Search a synthetic index of `scale` vectors; returns latency and graph build time
+fn benchmark_embedding_search(
+    scale: usize,
+    config: &crate::config::EmbeddingsConfig,
+    queries: usize,
+) -> (Percentiles, Option<f64>) {
+    use crate::embeddings::{VectorIndex, EMBEDDING_DIM};
+
+    let mut rng = StdRng::seed_from_u64(scale as u64);
+    let mut random_unit = || {
+        let v: Vec<f32> = (0..EMBEDDING_DIM).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt().max(f32::EPSILON);
+        v.into_iter().map(|x| x / norm).collect::<Vec<f32>>()
+    };
+
+    let mut index = VectorIndex::new(EMBEDDING_DIM);
+    for id in 0..scale {
+        // Synthetic vectors always have the index's dimensions
+        let _ = index.add(id as i64, &random_unit());
+    }
+    let build_ms = (scale >= config.ann_min_vectors).then(|| {
+        let start = Instant::now();
+        index.build_ann(config.hnsw);
+        start.elapsed().as_secs_f64() * 1000.0
+    });
+
+    let times: Vec<u128> = (0..queries)
+        .map(|_| {
+            let query = random_unit();
+            let start = Instant::now();
+            let _ = index.search(&query, 8);
+            start.elapsed().as_micros()
+        })
+        .collect();
+    (Percentiles::from_micros(&times), build_ms)
+}
+
+/// Insert `count` synthetic patterns chunk by chunk into a scratch database
+fn benchmark_insert_chunks(count: usize, chunk_size: usize) -> Result<Percentiles> {
+    let db_path = scratch_db("chunks")?;
+    let patterns = synthetic_patterns(count);
+    let mut store = crate::storage::PatternStore::open(&db_path)?;
+
+    let start = Instant::now();
+    let mut times = Vec::new();
+    for chunk in patterns.chunks(chunk_size) {
+        let chunk_start = Instant::now();
+        store.insert_batch(chunk, 0)?;
+        times.push(chunk_start.elapsed().as_micros());
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    drop(store);
+    let _ = std::fs::remove_file(&db_path);
+
+    Ok(Percentiles { per_sec: Some(count as f64 / elapsed.max(f64::EPSILON)), ..Percentiles::from_micros(&times) })
+}
+
+/// Run heuristic reflection cycles over synthetic sessions in a scratch database
+fn benchmark_reflection(cycles: usize) -> Result<Percentiles> {
+    use crate::learning::trajectory::{ToolCall, ToolResult, Trajectory};
+    use crate::reflection::{ReflectionConfig, ReflectionEngine};
+
+    let db_path = scratch_db("reflection")?;
+    crate::storage::PatternStore::open(&db_path)?.insert_batch(&synthetic_patterns(1_000), 0)?;
+    let conn = rusqlite::Connection::open(&db_path)?;
+    let engine = ReflectionEngine::with_db_path(ReflectionConfig::default(), &db_path);
+
+    const COMMANDS: [&str; 4] = ["cargo build", "cargo test", "npm test", "git status"];
+    let trajectories: Vec<Trajectory> = (0..20)
+        .map(|i| Trajectory {
+            session_id: format!("bench-{}", i),
+            user_query: format!("benchmark task {}", i),
+            assistant_content: "Done.".to_string(),
+            tool_calls: COMMANDS
+                .iter()
+                .map(|cmd| ToolCall { tool_name: "Bash".to_string(), tool_input: serde_json::json!({ "command": cmd }) })
+                .collect(),
+            tool_results: COMMANDS
+                .iter()
+                .enumerate()
+                .map(|(n, _)| ToolResult {
+                    tool_use_id: format!("tool-{}-{}", i, n),
+                    content: if (i + n) % 3 == 0 { "error: build failed".to_string() } else { "ok".to_string() },
+                    is_error: (i + n) % 3 == 0,
+                })
+                .collect(),
+            verdict: None,
+            started_at: None,
+            ended_at: None,
+            cwd: None,
+        })
+        .collect();
+
+    let mut times = Vec::with_capacity(cycles);
+    for _ in 0..cycles {
+        let start = Instant::now();
+        let verdicts = engine.reflect(&trajectories)?;
+        engine.apply_verdicts(&conn, &verdicts)?;
+        times.push(start.elapsed().as_micros());
+    }
+    drop(conn);
+    let _ = std::fs::remove_file(&db_path);
+
+    Ok(Percentiles::from_micros(&times))
+}
+
+/// Fresh database with the full schema in the temp directory
+fn scratch_db(name: &str) -> Result<PathBuf> {
+    let db_path = std::env::temp_dir().join(format!("mana-bench-{}-{}.sqlite", std::process::id(), name));
+    let _ = std::fs::remove_file(&db_path);
+    crate::storage::create_schema(&rusqlite::Connection::open(&db_path)?)?;
+    Ok(db_path)
+}
+
+/// Dataset sizes for the insert throughput benchmark
+const INSERT_SCALES: [usize; 2] = [10_000, 100_000];
+
+/// Transaction chunk sizes to compare (0 = single transaction)
+const INSERT_CHUNK_SIZES: [usize; 4] = [100, 1_000, 10_000, 0];
+
+/// One measured insert run
+#[derive(Debug, Serialize)]
+pub struct InsertRun {
+    pub scale: usize,
+    pub chunk_size: usize,
+    pub patterns_per_sec: f64,
+}
+
+/// Entry appended to bench-history.jsonl
+#[derive(Debug, Serialize)]
+struct InsertHistoryEntry {
+    benchmark: &'static str,
+    timestamp: String,
+    runs: Vec<InsertRun>,
+    recommended_chunk_size: usize,
+}
+
+/// Run batch insert throughput benchmarks against scratch databases
+pub fn run_insert_benchmarks() -> Result<Vec<InsertRun>> {
+    println!("MANA Batch Insert Throughput");
+    println!("============================");
     println!();
 
-    // Show pattern count for context
-    let mana_dir = get_mana_dir()?;
-    let db_path = mana_dir.join("metadata.sqlite");
-    if db_path.exists() {
-        let conn = rusqlite::Connection::open(&db_path)?;
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |r| r.get(0))?;
-        println!("Pattern count: {} (benchmarks run against this dataset)", count);
-        results.pattern_count = count;
+    let mut runs = Vec::new();
+    for &scale in &INSERT_SCALES {
+        println!("{} patterns:", scale);
+        let patterns = synthetic_patterns(scale);
+        for &chunk_size in &INSERT_CHUNK_SIZES {
+            let rate = benchmark_insert(&patterns, chunk_size)?;
+            let label = if chunk_size == 0 { "single tx".to_string() } else { chunk_size.to_string() };
+            println!("   chunk {:>9}: {:>10.0} patterns/sec", label, rate);
+            runs.push(InsertRun { scale, chunk_size, patterns_per_sec: rate });
+        }
+        println!();
+    }
+
+    let recommended = recommend_chunk_size(&runs);
+    println!("Recommendation: set [learning] batch_chunk_size = {} in config.toml", recommended);
+
+    let history_path = get_mana_dir()?.join("bench-history.jsonl");
+    let entry = InsertHistoryEntry {
+        benchmark: "insert",
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        recommended_chunk_size: recommended,
+        runs,
+    };
+    if let Some(parent) = history_path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&history_path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    println!("Results appended to {}", history_path.display());
+
+    Ok(entry.runs)
+}
+
+/// Insert `patterns` into a fresh scratch database and return patterns/sec
+fn benchmark_insert(patterns: &[crate::storage::Pattern], chunk_size: usize) -> Result<f64> {
+    let db_path = scratch_db(&chunk_size.to_string())?;
+
+    let mut store = crate::storage::PatternStore::open(&db_path)?;
+    let start = Instant::now();
+    let inserted = store.insert_batch(patterns, chunk_size)?;
+    let elapsed = start.elapsed().as_secs_f64();
+    drop(store);
+    let _ = std::fs::remove_file(&db_path);
+
+    Ok(inserted as f64 / elapsed.max(f64::EPSILON))
+}
+
+/// Pick the chunk size with the best throughput at the largest scale
+fn recommend_chunk_size(runs: &[InsertRun]) -> usize {
+    let largest = runs.iter().map(|r| r.scale).max().unwrap_or(0);
+    runs.iter()
+        .filter(|r| r.scale == largest)
+        .max_by(|a, b| a.patterns_per_sec.partial_cmp(&b.patterns_per_sec).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|r| r.chunk_size)
+        .unwrap_or(crate::storage::patterns::DEFAULT_BATCH_CHUNK_SIZE)
+}
 
-    Ok(results)
+/// Generate unique patterns shaped like real learned ones
+fn synthetic_patterns(count: usize) -> Vec<crate::storage::Pattern> {
+    const COMMANDS: [&str; 6] = ["cargo", "npm", "git", "pytest", "docker", "make"];
+    (0..count)
+        .map(|i| {
+            let cmd = COMMANDS[i % COMMANDS.len()];
+            crate::storage::Pattern {
+                id: 0,
+                pattern_hash: format!("bench-{:08x}", i),
+                tool_type: "Bash".to_string(),
+                command_category: Some(cmd.to_string()),
+                context_query: format!(
+                    "Task: benchmark task {}\nApproach: Bash - {} - run step {}\nOutcome: Success",
+                    i, cmd, i
+                ),
+                success_count: 1,
+                failure_count: 0,
+                embedding_id: None,
+                risky: false,
+            }
+        })
+        .collect()
 }
 
 /// Benchmark context injection latency
@@ -219,56 +646,33 @@ fn get_mana_binary() -> Result<PathBuf> {
     }
 }
 
-fn get_mana_dir() -> Result<PathBuf> {
-    let cwd = std::env::current_dir()?;
-    let project_mana = cwd.join(".mana");
-    if project_mana.exists() {
-        return Ok(project_mana);
-    }
-
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".mana"))
-}
-
-/// Benchmark results
-#[derive(Debug, Default)]
-pub struct BenchmarkResults {
-    pub injection_avg_ms: f64,
-    pub injection_min_ms: f64,
-    pub injection_max_ms: f64,
-    pub search_avg_ms: f64,
-    pub search_min_ms: f64,
-    pub search_max_ms: f64,
-    pub startup_avg_ms: f64,
-    pub pattern_count: i64,
-}
-
-impl BenchmarkResults {
-    /// Check if all critical benchmarks pass
-    #[allow(dead_code)]
-    pub fn all_pass(&self) -> bool {
-        self.injection_avg_ms < 10.0 && self.startup_avg_ms < 50.0
-    }
-
-    /// Format results as a markdown table (for GitHub issue updates)
-    #[allow(dead_code)]
-    pub fn to_markdown(&self) -> String {
-        format!(
-            r#"| Metric | Value | Target |
-|--------|-------|--------|
-| Injection latency (avg) | {:.2}ms | <10ms |
-| Injection latency (min) | {:.2}ms | - |
-| Injection latency (max) | {:.2}ms | - |
-| Search latency (avg) | {:.3}ms | <0.5ms |
-| Startup time (avg) | {:.2}ms | <50ms |
-| Pattern count | {} | - |"#,
-            self.injection_avg_ms,
-            self.injection_min_ms,
-            self.injection_max_ms,
-            self.search_avg_ms,
-            self.startup_avg_ms,
-            self.pattern_count
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_regressions() {
+        let times: Vec<u128> = (1..=100).map(|ms| ms * 1000).collect();
+        let latency = Percentiles::from_micros(&times);
+        assert_eq!((latency.p50_ms, latency.p95_ms, latency.p99_ms), (50.0, 95.0, 99.0));
+        assert_eq!(latency.samples, 100);
+        assert_eq!(Percentiles::from_micros(&[]), Percentiles::default());
+
+        let run = |search: f64, startup: f64| BenchReport {
+            timestamp: String::new(),
+            version: String::new(),
+            pattern_count: 0,
+            benchmarks: [("pattern_search", search), ("startup", startup)]
+                .into_iter()
+                .map(|(name, p95_ms)| (name.to_string(), Percentiles { p95_ms, ..Default::default() }))
+                .collect(),
+        };
+        let found = compare(&run(0.1, 20.0), &run(0.14, 30.0));
+        // +0.04ms is noise; +10ms on 20ms is a regression
+        assert_eq!(found.iter().map(|c| c.regression).collect::<Vec<_>>(), vec![false, true]);
+
+        assert!(run(0.1, 20.0).all_pass());
+        assert!(!run(0.1, 60.0).all_pass());
+        assert!(run(0.1, 20.0).to_markdown().contains("| startup | 0.000ms | 20.000ms | 0.000ms |"));
     }
 }
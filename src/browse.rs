@@ -0,0 +1,641 @@
+//! Interactive pattern browser (`mana patterns browse`)
+//!
+//! Lists patterns best first, with the selected one's full context, tags and
+//! reflection history alongside. `/` narrows the list with a fuzzy match over
+//! tool, category and context; `t`, `c` and `m` step through tool, category
+//! and minimum-score filters. Patterns can be deleted, archived (or
+//! reinstated), tagged and queued for sharing with a team without leaving
+//! the list. Everything shown comes from the [`Browser`] state, so rendering
+//! never touches the database.
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+use crate::embeddings;
+use crate::reflection::{MemoryDistiller, VerdictStats};
+use crate::storage::{self, PatternFilter};
+
+/// Minimum scores `m` steps through
+const MIN_SCORES: &[Option<i64>] = &[None, Some(0), Some(1), Some(3), Some(5), Some(10)];
+
+/// Verdicts listed in the preview
+const RECENT_VERDICTS: i64 = 5;
+
+/// A pattern as listed
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    pub id: i64,
+    pub hash: String,
+    pub tool: String,
+    pub category: Option<String>,
+    pub context: String,
+    pub success: i64,
+    pub failure: i64,
+    pub status: String,
+    pub quality: f64,
+}
+
+impl Entry {
+    fn score(&self) -> i64 {
+        self.success - self.failure
+    }
+}
+
+/// Details of the selected pattern
+#[derive(Debug, Default)]
+pub struct Preview {
+    pub id: i64,
+    pub tags: Vec<String>,
+    pub stats: VerdictStats,
+    /// (verdict, root cause, when), newest first
+    pub recent: Vec<(String, Option<String>, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+enum Mode {
+    #[default]
+    Normal,
+    Search,
+    /// Typing a tag for the selected pattern
+    Tag(String),
+    ConfirmDelete,
+}
+
+/// Browser state
+#[derive(Debug, Default)]
+pub struct Browser {
+    entries: Vec<Entry>,
+    /// Indexes into `entries` that pass the filters, in display order
+    visible: Vec<usize>,
+    selected: usize,
+    query: String,
+    tool: Option<String>,
+    category: Option<String>,
+    /// Index into [`MIN_SCORES`]
+    min_score: usize,
+    mode: Mode,
+    preview: Option<Preview>,
+    status: Option<String>,
+    team: Option<String>,
+    /// Hashes of patterns to share with `team` once the browser closes
+    pub shares: Vec<String>,
+    /// Whether anything was deleted, archived or reinstated
+    pub changed: bool,
+}
+
+/// Patterns matching `filter`, with their quality
+pub fn load(conn: &Connection, filter: &PatternFilter) -> Result<Vec<Entry>> {
+    let ranker = storage::ranking::Ranker::configured(conn)?;
+    let (conditions, values) = filter.where_clause();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.id, p.pattern_hash, p.tool_type, p.command_category, p.context_query,
+                p.success_count, p.failure_count, p.status, {}
+         FROM patterns p WHERE 1=1{}",
+        storage::ranking::AGE_DAYS,
+        conditions
+    ))?;
+    let entries = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+        let mut entry = Entry {
+            id: row.get(0)?,
+            hash: row.get(1)?,
+            tool: row.get(2)?,
+            category: row.get(3)?,
+            context: row.get(4)?,
+            success: row.get(5)?,
+            failure: row.get(6)?,
+            status: row.get(7)?,
+            quality: 0.0,
+        };
+        entry.quality =
+            ranker.quality(entry.id, &entry.tool, entry.category.as_deref(), entry.success, entry.failure, row.get(8)?);
+        Ok(entry)
+    })?;
+    Ok(entries.collect::<rusqlite::Result<_>>()?)
+}
+
+/// How well `query` fuzzily matches `text`, None if it doesn't
+///
+/// Every non-space character of the query has to appear in order, ignoring
+/// case. Runs of consecutive characters and matches at word starts score
+/// higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut wanted = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut run = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let Some(&next) = wanted.peek() else { break };
+        if c == next {
+            wanted.next();
+            score += 1;
+            if run {
+                score += 5;
+            }
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            run = true;
+        } else {
+            run = false;
+        }
+        previous = Some(c);
+    }
+    wanted.peek().is_none().then_some(score)
+}
+
+impl Browser {
+    /// A browser over `entries`; `team` is where `s` shares to
+    ///
+    /// The filter's tool, category and minimum score start the interactive
+    /// filters so they can be changed from the list.
+    pub fn new(entries: Vec<Entry>, filter: &PatternFilter, team: Option<String>) -> Self {
+        let mut browser = Self {
+            entries,
+            tool: filter.tool.clone(),
+            category: filter.category.clone(),
+            min_score: MIN_SCORES.iter().position(|&s| s == filter.min_score).unwrap_or(0),
+            team,
+            ..Default::default()
+        };
+        browser.refilter();
+        browser
+    }
+
+    fn selected_entry(&self) -> Option<&Entry> {
+        self.visible.get(self.selected).map(|&i| &self.entries[i])
+    }
+
+    fn passes(&self, entry: &Entry) -> bool {
+        let same = |want: &Option<String>, have: Option<&str>| {
+            want.as_deref().is_none_or(|w| have.is_some_and(|h| h.eq_ignore_ascii_case(w)))
+        };
+        same(&self.tool, Some(&entry.tool))
+            && same(&self.category, entry.category.as_deref())
+            && MIN_SCORES[self.min_score].is_none_or(|min| entry.score() >= min)
+    }
+
+    /// Recompute the visible list, keeping the selection on the same pattern if it's still there
+    fn refilter(&mut self) {
+        let keep = self.selected_entry().map(|e| e.id);
+        let mut scored: Vec<(i64, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| self.passes(e))
+            .filter_map(|(i, e)| {
+                if self.query.trim().is_empty() {
+                    return Some((0, i));
+                }
+                let text = format!("{} {} {}", e.tool, e.category.as_deref().unwrap_or_default(), e.context);
+                fuzzy_score(&self.query, &text).map(|score| (score, i))
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0).then_with(|| {
+                let (a, b) = (&self.entries[a.1], &self.entries[b.1]);
+                storage::ranking::best_first((a.quality, a.id), (b.quality, b.id))
+            })
+        });
+        self.visible = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = keep
+            .and_then(|id| self.visible.iter().position(|&i| self.entries[i].id == id))
+            .unwrap_or(0)
+            .min(self.visible.len().saturating_sub(1));
+    }
+
+    /// Load the preview if the selection moved
+    pub fn refresh_preview(&mut self, conn: &Connection) -> Result<()> {
+        let Some(id) = self.selected_entry().map(|e| e.id) else {
+            self.preview = None;
+            return Ok(());
+        };
+        if self.preview.as_ref().is_some_and(|p| p.id == id) {
+            return Ok(());
+        }
+        let mut preview = Preview { id, tags: storage::tags::for_pattern(conn, id)?, ..Default::default() };
+        // Reflection tables appear with the first reflection cycle
+        if storage::has_column(conn, "reflection_verdicts", "pattern_id") {
+            preview.stats = MemoryDistiller::get_pattern_stats(conn, id)?;
+            let mut stmt = conn.prepare(
+                "SELECT verdict, root_cause, created_at FROM reflection_verdicts
+                 WHERE pattern_id = ?1 ORDER BY created_at DESC, id DESC LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![id, RECENT_VERDICTS], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            preview.recent = rows.collect::<rusqlite::Result<_>>()?;
+        }
+        self.preview = Some(preview);
+        Ok(())
+    }
+
+    /// Handle a key press, returning true to quit
+    pub fn handle(&mut self, key: KeyEvent, conn: &Connection, mana_dir: &Path) -> Result<bool> {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(true);
+        }
+        match std::mem::take(&mut self.mode) {
+            Mode::Normal => return self.handle_normal(key, conn),
+            Mode::Search => match key.code {
+                KeyCode::Enter => {}
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.refilter();
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.refilter();
+                    self.mode = Mode::Search;
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.refilter();
+                    self.mode = Mode::Search;
+                }
+                _ => self.mode = Mode::Search,
+            },
+            Mode::Tag(mut tag) => match key.code {
+                KeyCode::Enter => {
+                    if let Err(e) = self.tag(conn, &tag) {
+                        self.status = Some(e.to_string());
+                    }
+                }
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    tag.pop();
+                    self.mode = Mode::Tag(tag);
+                }
+                KeyCode::Char(c) => {
+                    tag.push(c);
+                    self.mode = Mode::Tag(tag);
+                }
+                _ => self.mode = Mode::Tag(tag),
+            },
+            Mode::ConfirmDelete => {
+                if key.code == KeyCode::Char('y') {
+                    if let Err(e) = self.delete(conn, mana_dir) {
+                        self.status = Some(e.to_string());
+                    }
+                } else {
+                    self.status = Some("Delete cancelled".into());
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn handle_normal(&mut self, key: KeyEvent, conn: &Connection) -> Result<bool> {
+        self.status = None;
+        let last = self.visible.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc if self.query.is_empty() => return Ok(true),
+            KeyCode::Esc => {
+                self.query.clear();
+                self.refilter();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::PageDown => self.selected = (self.selected + 10).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('t') => {
+                let tools = self.values(|e| Some(e.tool.clone()));
+                self.tool = next_value(&tools, &self.tool);
+                self.refilter();
+            }
+            KeyCode::Char('c') => {
+                // Only categories of the chosen tool
+                let tool = self.tool.clone();
+                let categories = self.values(|e| {
+                    e.category.clone().filter(|_| tool.as_deref().is_none_or(|t| e.tool.eq_ignore_ascii_case(t)))
+                });
+                self.category = next_value(&categories, &self.category);
+                self.refilter();
+            }
+            KeyCode::Char('m') => {
+                self.min_score = (self.min_score + 1) % MIN_SCORES.len();
+                self.refilter();
+            }
+            KeyCode::Char('x') => {
+                (self.tool, self.category, self.min_score) = (None, None, 0);
+                self.query.clear();
+                self.refilter();
+            }
+            KeyCode::Char('d') if self.selected_entry().is_some() => self.mode = Mode::ConfirmDelete,
+            KeyCode::Char('g') if self.selected_entry().is_some() => self.mode = Mode::Tag(String::new()),
+            KeyCode::Char('a') => {
+                if let Err(e) = self.toggle_archived(conn) {
+                    self.status = Some(e.to_string());
+                }
+            }
+            KeyCode::Char('s') => self.queue_share(),
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Distinct values of a field across all patterns, sorted
+    fn values(&self, field: impl Fn(&Entry) -> Option<String>) -> Vec<String> {
+        let mut values: Vec<String> = self.entries.iter().filter_map(field).collect();
+        values.sort_by_key(|v| v.to_lowercase());
+        values.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        values
+    }
+
+    fn delete(&mut self, conn: &Connection, mana_dir: &Path) -> Result<()> {
+        let Some(&index) = self.visible.get(self.selected) else {
+            return Ok(());
+        };
+        let id = self.entries[index].id;
+        let tx = conn.unchecked_transaction()?;
+        storage::patterns::delete_with_dependents(&tx, &[id])?;
+        tx.commit()?;
+        if embeddings::is_available(mana_dir) {
+            let _ = embeddings::delete_from_index(mana_dir, id);
+        }
+
+        self.entries.remove(index);
+        // The next pattern down takes the deleted one's place
+        self.visible.remove(self.selected);
+        for i in &mut self.visible {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+        self.selected = self.selected.min(self.visible.len().saturating_sub(1));
+        self.changed = true;
+        self.status = Some(format!("Deleted pattern #{}", id));
+        Ok(())
+    }
+
+    fn toggle_archived(&mut self, conn: &Connection) -> Result<()> {
+        let Some(&index) = self.visible.get(self.selected) else {
+            return Ok(());
+        };
+        let entry = &mut self.entries[index];
+        let message = if entry.status == "active" {
+            storage::patterns::archive_patterns(conn, &[entry.id])?;
+            entry.status = "archived".into();
+            format!("Archived pattern #{}", entry.id)
+        } else {
+            storage::patterns::reinstate_patterns(conn, &[entry.id])?;
+            entry.status = "active".into();
+            format!("Reinstated pattern #{}", entry.id)
+        };
+        self.changed = true;
+        self.status = Some(message);
+        Ok(())
+    }
+
+    fn tag(&mut self, conn: &Connection, tag: &str) -> Result<()> {
+        let Some(id) = self.selected_entry().map(|e| e.id) else {
+            return Ok(());
+        };
+        let tag = storage::tags::normalize(tag)?;
+        storage::tags::add(conn, id, std::slice::from_ref(&tag))?;
+        self.status = Some(format!("Tagged pattern #{} {}", id, tag));
+        // Reload the tags shown
+        self.preview = None;
+        Ok(())
+    }
+
+    fn queue_share(&mut self) {
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        let (id, hash) = (entry.id, entry.hash.clone());
+        self.status = Some(match &self.team {
+            None => "Pass --team to share patterns".into(),
+            Some(_) if !crate::sync::is_supabase_available() => "Sharing needs a build with --features supabase".into(),
+            Some(_) if self.shares.contains(&hash) => format!("Pattern #{} is already queued", id),
+            Some(team) => {
+                let message = format!("Pattern #{} will be shared with {} on exit", id, team);
+                self.shares.push(hash);
+                message
+            }
+        });
+    }
+}
+
+/// The value after `current` in `values`, wrapping round through None
+fn next_value(values: &[String], current: &Option<String>) -> Option<String> {
+    let position = current.as_ref().and_then(|c| values.iter().position(|v| v.eq_ignore_ascii_case(c)));
+    match position {
+        Some(i) => values.get(i + 1).cloned(),
+        None if current.is_some() => None,
+        None => values.first().cloned(),
+    }
+}
+
+/// Run the browser until the user quits, returning its final state
+pub fn run(conn: &Connection, mana_dir: &Path, browser: Browser) -> Result<Browser> {
+    let mut terminal = ratatui::init();
+    let mut browser = browser;
+    let result = event_loop(&mut terminal, conn, mana_dir, &mut browser);
+    ratatui::restore();
+    result.map(|_| browser)
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, conn: &Connection, mana_dir: &Path, browser: &mut Browser) -> Result<()> {
+    loop {
+        browser.refresh_preview(conn)?;
+        terminal.draw(|frame| draw(frame, browser))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && browser.handle(key, conn, mana_dir)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Render one frame
+pub fn draw(frame: &mut Frame, browser: &Browser) {
+    let [header, body, footer] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(5), Constraint::Length(1)]).areas(frame.area());
+
+    let mut filters = Vec::new();
+    if let Some(tool) = &browser.tool {
+        filters.push(format!("tool={}", tool));
+    }
+    if let Some(category) = &browser.category {
+        filters.push(format!("category={}", category));
+    }
+    if let Some(min) = MIN_SCORES[browser.min_score] {
+        filters.push(format!("score>={}", min));
+    }
+    if !browser.query.is_empty() {
+        filters.push(format!("/{}", browser.query));
+    }
+    let title = vec![
+        Span::styled(" MANA ", Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+        Span::raw(format!(" {} of {} patterns  {}", browser.visible.len(), browser.entries.len(), filters.join("  "))),
+    ];
+    frame.render_widget(Paragraph::new(Line::from(title)), header);
+
+    let [list, preview] = Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(body);
+    draw_list(frame, list, browser);
+    draw_preview(frame, preview, browser);
+
+    let line = match &browser.mode {
+        Mode::Search => Line::from(format!("/{}█  (enter keep, esc clear)", browser.query)),
+        Mode::Tag(tag) => Line::from(format!("Tag: {}█  (enter add, esc cancel)", tag)),
+        Mode::ConfirmDelete => Line::from(Span::styled(
+            format!("Delete pattern #{}? (y/n)", browser.selected_entry().map_or(0, |e| e.id)),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Mode::Normal => match &browser.status {
+            Some(status) => Line::from(status.as_str()),
+            None => Line::from(
+                "↑↓ move  / search  t tool  c category  m min score  x clear  d delete  a archive  g tag  s share  q quit",
+            ),
+        },
+    };
+    frame.render_widget(Paragraph::new(line), footer);
+}
+
+fn panel(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(format!(" {} ", title))
+}
+
+fn draw_list(frame: &mut Frame, area: Rect, browser: &Browser) {
+    let items: Vec<ListItem> = browser
+        .visible
+        .iter()
+        .map(|&i| {
+            let e = &browser.entries[i];
+            let context = e.context.lines().next().unwrap_or_default();
+            let style = if e.status == "active" { Style::default() } else { Style::default().fg(Color::DarkGray) };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{:>5} ", e.id)),
+                Span::styled(format!("{:<6} ", e.tool), Style::default().fg(Color::Cyan)),
+                Span::raw(format!("{:.2} ", e.quality)),
+                Span::raw(context.to_string()),
+            ]))
+            .style(style)
+        })
+        .collect();
+    let list = List::new(items)
+        .block(panel("Patterns"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected((!browser.visible.is_empty()).then_some(browser.selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_preview(frame: &mut Frame, area: Rect, browser: &Browser) {
+    let Some(entry) = browser.selected_entry() else {
+        frame.render_widget(Paragraph::new("No patterns match.").block(panel("Preview")), area);
+        return;
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{} / {}", entry.tool, entry.category.as_deref().unwrap_or("-")),
+            bold,
+        )),
+        Line::from(format!(
+            "Score: {} ({} success, {} failure)  Quality: {:.2}",
+            entry.score(),
+            entry.success,
+            entry.failure,
+            entry.quality
+        )),
+        Line::from(format!("Status: {}", entry.status)),
+    ];
+    let preview = browser.preview.as_ref().filter(|p| p.id == entry.id);
+    if let Some(tags) = preview.map(|p| &p.tags).filter(|t| !t.is_empty()) {
+        lines.push(Line::from(format!("Tags: {}", tags.join(", "))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Context", bold)));
+    lines.extend(entry.context.lines().map(|l| Line::from(l.to_string())));
+
+    if let Some(preview) = preview.filter(|p| p.stats.total > 0) {
+        let s = &preview.stats;
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Reflection history", bold)));
+        lines.push(Line::from(format!(
+            "{} verdicts: {} effective, {} neutral, {} ineffective, {} harmful",
+            s.total, s.effective, s.neutral, s.ineffective, s.harmful
+        )));
+        for (verdict, cause, at) in &preview.recent {
+            let color = match verdict.to_uppercase().as_str() {
+                "EFFECTIVE" => Color::Green,
+                "HARMFUL" => Color::Red,
+                "INEFFECTIVE" => Color::Yellow,
+                _ => Color::Reset,
+            };
+            let mut spans = vec![Span::raw(format!("{}  ", at)), Span::styled(verdict.clone(), Style::default().fg(color))];
+            if let Some(cause) = cause.as_deref().filter(|c| !c.is_empty()) {
+                spans.push(Span::raw(format!("  {}", cause)));
+            }
+            lines.push(Line::from(spans));
+        }
+    }
+    let title = format!("Pattern #{}", entry.id);
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(panel(&title)), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn entry(id: i64, tool: &str, context: &str, success: i64) -> Entry {
+        Entry {
+            id,
+            hash: format!("h{}", id),
+            tool: tool.into(),
+            context: context.into(),
+            success,
+            status: "active".into(),
+            quality: success as f64 / 10.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_filter_and_draw() {
+        assert!(fuzzy_score("cbr", "cargo build --release").is_some());
+        assert!(fuzzy_score("xyz", "cargo build").is_none());
+        assert!(fuzzy_score("build", "cargo build").unwrap() > fuzzy_score("build", "b u i l d").unwrap());
+
+        let entries = vec![
+            entry(1, "Bash", "cargo build --release", 3),
+            entry(2, "Edit", "src/main.rs: fix imports", 5),
+            entry(3, "Bash", "npm test", 1),
+        ];
+        let mut browser = Browser::new(entries, &PatternFilter::default(), None);
+        assert_eq!(browser.selected_entry().unwrap().id, 2);
+
+        // Tool filter steps Bash, Edit, then off
+        browser.tool = next_value(&browser.values(|e| Some(e.tool.clone())), &None);
+        browser.refilter();
+        assert_eq!(browser.visible.len(), 2);
+        browser.query = "cbr".into();
+        browser.refilter();
+        assert_eq!(browser.selected_entry().unwrap().id, 1);
+        browser.preview = Some(Preview {
+            id: 1,
+            tags: vec!["ci".into()],
+            stats: VerdictStats { total: 1, harmful: 1, ..Default::default() },
+            recent: vec![("HARMFUL".into(), Some("stale flag".into()), "2026-01-02".into())],
+        });
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+        terminal.draw(|frame| draw(frame, &browser)).unwrap();
+        let text: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        for expected in ["1 of 3 patterns", "tool=Bash", "Pattern #1", "Tags: ci", "stale flag", "cargo build --release"] {
+            assert!(text.contains(expected), "missing {:?}", expected);
+        }
+        assert!(!text.contains("npm test"));
+    }
+}
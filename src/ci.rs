@@ -0,0 +1,217 @@
+//! Non-interactive CI mode and exit codes
+//!
+//! `mana --ci` (or `MANA_CI=1`) is meant for pipelines: printed text loses
+//! its emoji (status marks become `[ok]`, `[warn]`, `[fail]`), logs have no
+//! colors, nothing waits for input, and commands such as `prune` and
+//! `import` end with a `mana-summary key=value ...` line to grep for.
+//! Anything that would prompt fails with [`ExitCode::InputRequired`]
+//! instead; `delete` and `db restore` still need `--force`.
+//!
+//! Exit codes are the same in every mode:
+//!
+//! | code | meaning                                                        |
+//! |------|----------------------------------------------------------------|
+//! | 0    | success                                                        |
+//! | 1    | any other error                                                |
+//! | 2    | invalid arguments                                              |
+//! | 3    | not initialized: no database, run `mana init`                  |
+//! | 4    | busy: learning lock or database held by another process        |
+//! | 5    | verification failed: signature, checksum or integrity check    |
+//! | 6    | check failed: `doctor`, `config validate` or `--exit-code` found problems |
+//! | 7    | input required: the command would prompt                       |
+//! | 8    | read-only: the command writes and read-only mode is on, or the database refused a write |
+
+use std::fmt;
+
+/// Environment variable that turns on CI mode (`1` or `true`)
+pub const ENV: &str = "MANA_CI";
+
+/// Text that replaces status emoji in CI mode
+const MARKS: &[(char, &str)] = &[
+    ('✅', "[ok]"),
+    ('❌', "[fail]"),
+    ('⚠', "[warn]"),
+    ('➖', "[skip]"),
+    ('🟢', "[online]"),
+    ('⚪', "[offline]"),
+];
+
+/// Whether CI mode is on
+pub fn enabled() -> bool {
+    std::env::var(ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Documented process exit codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Error = 1,
+    // 2 is clap's exit code for invalid arguments
+    NotInitialized = 3,
+    Busy = 4,
+    Verification = 5,
+    CheckFailed = 6,
+    InputRequired = 7,
+    ReadOnly = 8,
+}
+
+/// An error that exits with a specific [`ExitCode`]
+#[derive(Debug)]
+pub struct Failure {
+    pub code: ExitCode,
+    message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// An error that makes `mana` exit with `code`
+pub fn fail(code: ExitCode, message: impl Into<String>) -> anyhow::Error {
+    Failure { code, message: message.into() }.into()
+}
+
+/// The "no database" error
+pub fn not_initialized() -> anyhow::Error {
+    fail(ExitCode::NotInitialized, "No database found. Run 'mana init' first.")
+}
+
+/// Exit code for an error that ended the command
+pub fn exit_code(err: &anyhow::Error) -> ExitCode {
+    for cause in err.chain() {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return failure.code;
+        }
+        if let Some(rusqlite::Error::SqliteFailure(e, _)) = cause.downcast_ref::<rusqlite::Error>() {
+            if matches!(e.code, rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) {
+                return ExitCode::Busy;
+            }
+            if e.code == rusqlite::ErrorCode::ReadOnly {
+                return ExitCode::ReadOnly;
+            }
+        }
+    }
+    ExitCode::Error
+}
+
+/// Exit the process with `code`
+pub fn exit(code: ExitCode) -> ! {
+    std::process::exit(code as i32)
+}
+
+/// Fail with [`ExitCode::InputRequired`] instead of prompting in CI mode
+pub fn ensure_interactive(what: &str) -> anyhow::Result<()> {
+    if enabled() {
+        return Err(fail(ExitCode::InputRequired, format!("{} needs input, which CI mode never waits for", what)));
+    }
+    Ok(())
+}
+
+/// Print a `mana-summary` line for pipelines (CI mode only)
+pub fn summary(command: &str, fields: &[(&str, &dyn fmt::Display)]) {
+    if !enabled() {
+        return;
+    }
+    let mut line = format!("mana-summary command={}", command);
+    for (key, value) in fields {
+        let value = value.to_string();
+        if value.is_empty() || value.contains(char::is_whitespace) || value.contains('"') {
+            line.push_str(&format!(" {}={:?}", key, value));
+        } else {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+    }
+    std::println!("{}", line);
+}
+
+/// Backs the crate's `println!`: plain text in CI mode
+pub fn print_line(args: fmt::Arguments) {
+    if enabled() {
+        std::println!("{}", plain(&args.to_string()));
+    } else {
+        std::println!("{}", args);
+    }
+}
+
+/// Backs the crate's `print!`
+pub fn print(args: fmt::Arguments) {
+    if enabled() {
+        std::print!("{}", plain(&args.to_string()));
+    } else {
+        std::print!("{}", args);
+    }
+}
+
+/// Backs the crate's `eprintln!`
+pub fn eprint_line(args: fmt::Arguments) {
+    if enabled() {
+        std::eprintln!("{}", plain(&args.to_string()));
+    } else {
+        std::eprintln!("{}", args);
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x26FF | 0x2700..=0x27BF)
+        // Check marks and crosses are plain text symbols
+        && !matches!(c, '✓' | '✗' | '✔')
+}
+
+/// `text` with status emoji spelled out and other emoji removed
+pub fn plain(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\u{FE0F}' {
+            continue;
+        }
+        if !is_emoji(c) {
+            out.push(c);
+            continue;
+        }
+        let mark = MARKS.iter().find(|(emoji, _)| *emoji == c).map(|(_, text)| *text);
+        // Collapse the padding after the emoji
+        while chars.next_if(|&next| next == ' ' || next == '\u{FE0F}').is_some() {}
+        let at_line_start = out.is_empty() || out.ends_with('\n') || out.ends_with(' ');
+        match mark {
+            Some(mark) => {
+                out.push_str(mark);
+                if chars.peek().is_some_and(|&next| next != '\n') {
+                    out.push(' ');
+                }
+            }
+            None if at_line_start => {}
+            None => {
+                if chars.peek().is_some_and(|&next| next != '\n') {
+                    out.push(' ');
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_spells_out_status_marks() {
+        assert_eq!(plain("✅ Exported 5 patterns"), "[ok] Exported 5 patterns");
+        assert_eq!(plain("⚠️  Unknown key x"), "[warn] Unknown key x");
+        assert_eq!(plain("Connected: ❌"), "Connected: [fail]");
+        assert_eq!(plain("📦 Bundle: 🔏 signed\n  ✓3 ✗1"), "Bundle: signed\n  ✓3 ✗1");
+        assert_eq!(plain("no emoji — here"), "no emoji — here");
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(exit_code(&not_initialized()), ExitCode::NotInitialized);
+        assert_eq!(exit_code(&not_initialized().context("while pruning")), ExitCode::NotInitialized);
+        assert_eq!(exit_code(&anyhow::anyhow!("boom")), ExitCode::Error);
+        assert_eq!(not_initialized().to_string(), "No database found. Run 'mana init' first.");
+    }
+}
@@ -0,0 +1,952 @@
+//! MANA configuration
+//!
+//! Typed view over `config.toml` in the MANA data directory.
+//! Unknown sections and keys are ignored so older config files keep working;
+//! `mana config validate` reports them.
+//!
+//! Any key can be overridden from the environment as
+//! `MANA_<SECTION>_<KEY>`, e.g. `MANA_INJECTION_MAX_PATTERNS=5`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, warn};
+
+use crate::embeddings::hnsw::HnswParams;
+use crate::storage::patterns::DEFAULT_BATCH_CHUNK_SIZE;
+
+/// Top-level configuration loaded from config.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManaConfig {
+    /// Context injection settings
+    #[serde(default)]
+    pub injection: InjectionConfig,
+    /// Learning pipeline settings
+    #[serde(default)]
+    pub learning: LearningConfig,
+    /// Embedding index settings
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+    /// Reflection settings
+    #[serde(default)]
+    pub reflection: ReflectionSettings,
+    /// Latency budgets
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+    /// Quality gate for pushed patterns
+    #[serde(default)]
+    pub sync: SyncSettings,
+    /// Self-update channel and release verification
+    #[serde(default)]
+    pub update: UpdateSettings,
+    /// Size and age caps applied during consolidation
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// When the daemon consolidates
+    #[serde(default)]
+    pub consolidation: ConsolidationConfig,
+    /// This machine's identity in synced patterns
+    #[serde(default)]
+    pub device: DeviceConfig,
+    /// Whether mana may write its data directory
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// How pattern quality is scored for ranking
+    #[serde(default)]
+    pub ranking: RankingConfig,
+    /// Opt-in anonymous usage metrics
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// Second-stage scoring of daemon search results
+    #[serde(default)]
+    pub rerank: RerankConfig,
+}
+
+/// Settings for context injection (hook and daemon paths)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InjectionConfig {
+    /// Maximum patterns to inject per context
+    #[serde(alias = "max_patterns_per_context")]
+    pub max_patterns: usize,
+    /// Approximate token budget for the injected context block
+    pub max_tokens: usize,
+    /// Tag wrapping the injected block (`<mana-context>` by default)
+    pub wrapper_tag: String,
+    /// Format of the injected context; `mana inject --adapter` overrides it
+    pub adapter: OutputAdapter,
+    /// Whether the default per-pattern format includes score and success rate
+    pub show_scores: bool,
+    /// Custom per-pattern format; placeholders: {id} {tool} {score} {rate} {insight}
+    pub pattern_format: Option<String>,
+    /// Share of sessions held out as an A/B control group, injected nothing (0 disables)
+    pub control_fraction: f64,
+    /// Add the working directory's stack markers and git branch to injection queries
+    pub repo_signals: bool,
+    /// Known pitfalls (failure patterns) injected in their own section (0 disables)
+    pub max_pitfalls: usize,
+    /// Minimum query similarity for a pitfall to be injected
+    pub pitfall_min_similarity: f64,
+    /// Minutes during which a pattern already injected into a session isn't
+    /// injected again; the next-best pattern takes its place (0 disables)
+    pub repeat_window_mins: u32,
+    /// Inject the session's goal, recently touched files and failed commands
+    pub session_state: bool,
+}
+
+impl Default for InjectionConfig {
+    fn default() -> Self {
+        Self {
+            max_patterns: 3,
+            max_tokens: 400,
+            wrapper_tag: "mana-context".to_string(),
+            adapter: OutputAdapter::Claude,
+            show_scores: true,
+            pattern_format: None,
+            control_fraction: 0.0,
+            repo_signals: true,
+            max_pitfalls: 2,
+            pitfall_min_similarity: 0.35,
+            repeat_window_mins: 30,
+            session_state: true,
+        }
+    }
+}
+
+impl InjectionConfig {
+    /// Start of the repeat window ending at `now`, None when disabled
+    pub fn repeat_since(&self, now: chrono::DateTime<chrono::Utc>) -> Option<chrono::DateTime<chrono::Utc>> {
+        (self.repeat_window_mins > 0).then(|| now - chrono::Duration::minutes(self.repeat_window_mins as i64))
+    }
+}
+
+/// How injected context is formatted for the assistant reading it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputAdapter {
+    /// Markdown inside the `wrapper_tag` block, for Claude Code
+    #[default]
+    Claude,
+    /// Markdown without a wrapper, for plain-text assistants such as Aider
+    PlainMarkdown,
+    /// One JSON line with the text and injected pattern ids, for custom agents
+    Json,
+}
+
+/// Settings for the learning pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LearningConfig {
+    /// Trajectories accumulated at session end before learning runs
+    pub threshold: u32,
+    /// Rows committed per transaction when batch-inserting patterns (0 = single transaction)
+    pub batch_chunk_size: usize,
+    /// Claude Code log roots (`~/.claude/projects` when empty); `~/` is expanded
+    pub log_dirs: Vec<String>,
+    /// Globs over session working directories never learned from (`~/` is expanded)
+    pub exclude_projects: Vec<String>,
+}
+
+impl Default for LearningConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 15,
+            batch_chunk_size: DEFAULT_BATCH_CHUNK_SIZE,
+            log_dirs: Vec::new(),
+            exclude_projects: Vec::new(),
+        }
+    }
+}
+
+/// Environment variable overriding `[learning] log_dirs` (path-list syntax, `:` separated on Unix)
+pub const LOG_DIRS_ENV: &str = "MANA_CLAUDE_LOGS";
+
+impl LearningConfig {
+    /// Claude Code log roots to learn from, in priority order
+    ///
+    /// `MANA_CLAUDE_LOGS` wins over the config file, which wins over the
+    /// default `~/.claude/projects`. Duplicates are dropped.
+    pub fn resolved_log_dirs(&self) -> Vec<PathBuf> {
+        resolve_log_dirs(
+            &self.log_dirs,
+            std::env::var_os(LOG_DIRS_ENV).as_deref(),
+            dirs::home_dir().as_deref(),
+        )
+    }
+}
+
+fn resolve_log_dirs(configured: &[String], env: Option<&OsStr>, home: Option<&Path>) -> Vec<PathBuf> {
+    let expand = |dir: &str| -> PathBuf {
+        match (dir.strip_prefix("~/"), home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ if dir == "~" => home.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(dir)),
+            _ => PathBuf::from(dir),
+        }
+    };
+
+    let mut dirs: Vec<PathBuf> = match env.filter(|v| !v.is_empty()) {
+        Some(value) => std::env::split_paths(value)
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| expand(&p.to_string_lossy()))
+            .collect(),
+        None => configured.iter().map(|d| expand(d.trim())).collect(),
+    };
+    if dirs.is_empty() {
+        dirs.push(home.map(|h| h.join(".claude/projects")).unwrap_or_else(|| PathBuf::from(".claude/projects")));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    dirs.retain(|d| seen.insert(d.clone()));
+    dirs
+}
+
+/// Settings for the embedding vector index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingsConfig {
+    /// HNSW parameters (m, ef_construction, ef_search)
+    #[serde(flatten)]
+    pub hnsw: HnswParams,
+    /// Minimum index size before approximate search replaces exact search
+    pub ann_min_vectors: usize,
+    /// Embed new patterns in the background after learning (once an index exists)
+    pub auto_embed: bool,
+    /// Patterns embedded per batch
+    pub auto_embed_batch: usize,
+    /// Share of one core background embedding may use, between batches
+    pub auto_embed_cpu: f64,
+    /// Embedding work per run in seconds; the rest waits for the next run
+    pub auto_embed_max_secs: u64,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            hnsw: HnswParams::default(),
+            ann_min_vectors: 10_000,
+            auto_embed: true,
+            auto_embed_batch: 32,
+            auto_embed_cpu: 0.5,
+            auto_embed_max_secs: 30,
+        }
+    }
+}
+
+/// Which LLM (if any) judges trajectories during reflection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JudgeKind {
+    /// Heuristic analyzer only
+    #[default]
+    None,
+    /// Anthropic Messages API (needs ANTHROPIC_API_KEY)
+    Claude,
+    /// Local Ollama server
+    Ollama,
+}
+
+/// Settings for reflection (`[reflection]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReflectionSettings {
+    /// Optional LLM judge; falls back to heuristics when unavailable
+    pub judge: JudgeKind,
+    /// Model name override for the judge
+    pub judge_model: Option<String>,
+    /// Ollama server URL
+    pub ollama_url: String,
+    /// Maximum LLM calls per reflection cycle (cached verdicts don't count)
+    pub judge_max_calls: usize,
+    /// Minimum delay between LLM calls in milliseconds
+    pub judge_interval_ms: u64,
+    /// Per-request timeout in seconds
+    pub judge_timeout_secs: u64,
+    /// Minimum verdict confidence before a pattern score changes
+    pub min_confidence: f64,
+    /// Largest score penalty for a HARMFUL verdict (zero or negative)
+    pub max_penalty: i32,
+    /// Largest score boost for an EFFECTIVE verdict
+    pub max_boost: i32,
+    /// Quarantine a pattern after this many confident HARMFUL verdicts (0 disables)
+    pub quarantine_after: u32,
+    /// Confidence a HARMFUL verdict needs to count toward quarantine
+    pub quarantine_min_confidence: f64,
+}
+
+impl Default for ReflectionSettings {
+    fn default() -> Self {
+        Self {
+            judge: JudgeKind::None,
+            judge_model: None,
+            ollama_url: "http://localhost:11434".to_string(),
+            judge_max_calls: 50,
+            judge_interval_ms: 500,
+            judge_timeout_secs: 30,
+            min_confidence: 0.6,
+            max_penalty: -5,
+            max_boost: 5,
+            quarantine_after: 3,
+            quarantine_min_confidence: 0.8,
+        }
+    }
+}
+
+/// Latency budgets (`[performance]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformanceConfig {
+    /// Injections slower than this are logged as over budget
+    pub injection_timeout_ms: u64,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self { injection_timeout_ms: 10 }
+    }
+}
+
+/// Quality gate applied before every sync push (`[sync]`)
+///
+/// Patterns that fail the gate stay in the local database; they just aren't
+/// shared with other machines.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// Withhold patterns whose score (successes minus failures) is below this
+    pub min_score: Option<i64>,
+    /// Never push patterns for these tools (case-insensitive)
+    pub exclude_tools: Vec<String>,
+    /// Push at most this many patterns, best-scoring first
+    pub max_patterns: Option<usize>,
+}
+
+/// Self-update settings (`[update]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// Release channel: "stable", or "beta" to include pre-releases
+    pub channel: String,
+    /// Public keys (`ssh-ed25519 AAAA...`) one of which must have signed a
+    /// release's SHA256SUMS; when empty only the checksum is verified
+    pub release_keys: Vec<String>,
+    /// Asset manifest for `mana update --assets`; defaults to the release's
+    /// `assets.json`
+    pub assets_url: Option<String>,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self { channel: "stable".to_string(), release_keys: Vec::new(), assets_url: None }
+    }
+}
+
+/// Retention caps applied during consolidation (`[retention]`, 0 disables each)
+///
+/// `max_patterns` falls back to the legacy `[storage] max_patterns`. The
+/// age caps delete history, so they are off until set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Evict the lowest-value patterns beyond this many
+    pub max_patterns: usize,
+    /// Vacuum, then evict patterns, while the database is larger than this
+    pub max_db_mb: u64,
+    /// Delete injection records and cached judgements older than this
+    pub max_trajectory_age_days: u32,
+    /// Delete reflection verdicts older than this
+    pub max_verdict_age_days: u32,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { max_patterns: 10_000, max_db_mb: 500, max_trajectory_age_days: 0, max_verdict_age_days: 0 }
+    }
+}
+
+/// Consolidation settings (`[consolidation]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsolidationConfig {
+    /// Local times of day the daemon consolidates at, e.g. "03:00" or
+    /// "03:00, 15:30"; unset leaves consolidation to learning runs
+    pub schedule: Option<String>,
+}
+
+/// Device identity (`[device]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// Name stamped into exports and shown for this machine's patterns;
+    /// defaults to the hostname
+    pub name: Option<String>,
+}
+
+/// Score formula for ranking patterns (see `storage::ranking`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RankFormula {
+    /// Lower bound of the Wilson score interval of the success rate
+    #[default]
+    Wilson,
+    /// Success rate smoothed toward the tool and category's rate
+    Bayesian,
+    /// Successes minus failures, without recency
+    Raw,
+}
+
+/// Ranking settings (`[ranking]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingConfig {
+    pub formula: RankFormula,
+    /// Pseudo-uses at the category's success rate added by `bayesian`
+    pub prior_weight: f64,
+    /// Days after which the recency-weighted share of a score halves (0 disables)
+    pub half_life_days: f64,
+    /// Share of a score that fades with time since last use
+    pub recency_weight: f64,
+    /// Verdicts a pattern's own track record counts as against reflection verdicts
+    pub score_weight: f64,
+    /// Weight of each verdict from this machine's reflection
+    pub own_weight: f64,
+    /// Weight of each verdict imported from a teammate's bundle
+    pub team_weight: f64,
+    /// Weight of each verdict from an installed pack
+    pub public_weight: f64,
+}
+
+impl Default for RankingConfig {
+    fn default() -> Self {
+        Self {
+            formula: RankFormula::Wilson,
+            prior_weight: 5.0,
+            half_life_days: 30.0,
+            recency_weight: 0.5,
+            score_weight: 5.0,
+            own_weight: 1.0,
+            team_weight: 0.5,
+            public_weight: 0.2,
+        }
+    }
+}
+
+/// Storage settings (`[storage]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Never write the database, index or state files; injection still
+    /// works, learning, reflection and sync fail (see `storage::read_only`)
+    pub read_only: bool,
+    /// Create the database encrypted with SQLCipher (see `storage::encryption`)
+    pub encrypt: bool,
+}
+
+/// Anonymous usage metrics (`[telemetry]`, off unless enabled)
+///
+/// See `telemetry` for what's sent; `mana telemetry preview` prints it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// Where reports are POSTed as JSON
+    pub endpoint: Option<String>,
+    /// Hours between reports
+    pub interval_hours: u32,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { enabled: false, endpoint: None, interval_hours: 24 }
+    }
+}
+
+/// Which model (if any) reranks the daemon's embedding search results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RerankKind {
+    /// Bi-encoder similarity order only
+    #[default]
+    None,
+    /// A local cross-encoder server with a `/rerank` endpoint
+    /// (text-embeddings-inference and compatible)
+    CrossEncoder,
+    /// Relevance scores from an LLM on a local Ollama server
+    Ollama,
+}
+
+/// Reranking settings (`[rerank]`, daemon only)
+///
+/// Reranking adds a network round trip per injection, so
+/// `[performance] injection_timeout_ms` has to leave room for `timeout_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RerankConfig {
+    pub backend: RerankKind,
+    /// Server URL; Ollama defaults to http://localhost:11434
+    pub url: Option<String>,
+    /// Model name override (Ollama defaults to llama3.2)
+    pub model: Option<String>,
+    /// Top bi-encoder results passed to the reranker
+    pub candidates: usize,
+    /// Longest a rerank may take; slower ones keep the bi-encoder order
+    pub timeout_ms: u64,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self { backend: RerankKind::None, url: None, model: None, candidates: 20, timeout_ms: 300 }
+    }
+}
+
+/// Prefix for environment overrides (`MANA_<SECTION>_<KEY>`)
+pub const ENV_PREFIX: &str = "MANA_";
+
+impl ManaConfig {
+    /// Problems with values that parse but make no sense
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, msg: &str| {
+            if !ok {
+                problems.push(msg.to_string());
+            }
+        };
+
+        let i = &self.injection;
+        check(i.max_patterns >= 1, "injection.max_patterns must be at least 1");
+        check(i.max_tokens >= 50, "injection.max_tokens must be at least 50");
+        check(
+            !i.wrapper_tag.is_empty() && i.wrapper_tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "injection.wrapper_tag must be a non-empty tag name (letters, digits, - and _)",
+        );
+        check(
+            (0.0..1.0).contains(&i.control_fraction),
+            "injection.control_fraction must be at least 0 and below 1",
+        );
+        check(i.pitfall_min_similarity >= 0.0, "injection.pitfall_min_similarity must be zero or positive");
+
+        check(self.learning.threshold >= 1, "learning.threshold must be at least 1");
+        check(
+            self.learning.exclude_projects.iter().all(|g| !g.trim().is_empty()),
+            "learning.exclude_projects must not contain empty globs",
+        );
+        check(self.performance.injection_timeout_ms >= 1, "performance.injection_timeout_ms must be at least 1");
+        check(self.sync.max_patterns != Some(0), "sync.max_patterns must be at least 1");
+
+        let h = &self.embeddings.hnsw;
+        check(h.m >= 2, "embeddings.m must be at least 2");
+        check(h.ef_construction >= h.m, "embeddings.ef_construction must be at least embeddings.m");
+        check(h.ef_search >= 1, "embeddings.ef_search must be at least 1");
+        let e = &self.embeddings;
+        check(e.auto_embed_batch >= 1, "embeddings.auto_embed_batch must be at least 1");
+        check(
+            e.auto_embed_cpu > 0.0 && e.auto_embed_cpu <= 1.0,
+            "embeddings.auto_embed_cpu must be above 0 and at most 1",
+        );
+
+        let r = &self.reflection;
+        check((0.0..=1.0).contains(&r.min_confidence), "reflection.min_confidence must be between 0 and 1");
+        check(r.max_penalty <= 0, "reflection.max_penalty must be zero or negative");
+        check(r.max_boost >= 0, "reflection.max_boost must be zero or positive");
+        check(
+            (0.0..=1.0).contains(&r.quarantine_min_confidence),
+            "reflection.quarantine_min_confidence must be between 0 and 1",
+        );
+        check(
+            matches!(self.update.channel.as_str(), "stable" | "beta"),
+            "update.channel must be \"stable\" or \"beta\"",
+        );
+        check(r.judge_timeout_secs >= 1, "reflection.judge_timeout_secs must be at least 1");
+        check(
+            r.ollama_url.starts_with("http://") || r.ollama_url.starts_with("https://"),
+            "reflection.ollama_url must be an http(s) URL",
+        );
+
+        let k = &self.ranking;
+        check(k.prior_weight >= 0.0, "ranking.prior_weight must be zero or positive");
+        check(k.half_life_days >= 0.0, "ranking.half_life_days must be zero or positive");
+        check((0.0..=1.0).contains(&k.recency_weight), "ranking.recency_weight must be between 0 and 1");
+        check(k.score_weight >= 0.0, "ranking.score_weight must be zero or positive");
+        check(
+            k.own_weight >= 0.0 && k.team_weight >= 0.0 && k.public_weight >= 0.0,
+            "ranking.own_weight, team_weight and public_weight must be zero or positive",
+        );
+
+        let t = &self.telemetry;
+        check(t.interval_hours >= 1, "telemetry.interval_hours must be at least 1");
+        check(
+            t.endpoint.as_ref().is_none_or(|url| url.starts_with("https://") || url.starts_with("http://")),
+            "telemetry.endpoint must be an http(s) URL",
+        );
+        check(!t.enabled || t.endpoint.is_some(), "telemetry.enabled needs telemetry.endpoint");
+
+        let rr = &self.rerank;
+        check(rr.candidates >= 1, "rerank.candidates must be at least 1");
+        check(rr.timeout_ms >= 1, "rerank.timeout_ms must be at least 1");
+        check(
+            rr.url.as_ref().is_none_or(|url| url.starts_with("https://") || url.starts_with("http://")),
+            "rerank.url must be an http(s) URL",
+        );
+        check(rr.backend != RerankKind::CrossEncoder || rr.url.is_some(), "rerank.backend = \"cross-encoder\" needs rerank.url");
+        check(
+            rr.backend == RerankKind::None || self.performance.injection_timeout_ms > rr.timeout_ms,
+            "performance.injection_timeout_ms must be above rerank.timeout_ms when reranking",
+        );
+
+        if let Some(schedule) = &self.consolidation.schedule {
+            check(
+                crate::learning::Schedule::parse(schedule).is_ok(),
+                "consolidation.schedule must be a list of HH:MM times, e.g. \"03:00\"",
+            );
+        }
+
+        problems
+    }
+}
+
+/// Every `section.key` the config understands
+pub fn known_keys() -> Vec<String> {
+    // Fill optional fields so they serialize and count as known
+    let mut sample = ManaConfig::default();
+    sample.injection.pattern_format = Some(String::new());
+    sample.reflection.judge_model = Some(String::new());
+    sample.sync.min_score = Some(0);
+    sample.sync.max_patterns = Some(0);
+    sample.update.assets_url = Some(String::new());
+    sample.consolidation.schedule = Some(String::new());
+    sample.device.name = Some(String::new());
+    sample.telemetry.endpoint = Some(String::new());
+    sample.rerank.url = Some(String::new());
+    sample.rerank.model = Some(String::new());
+
+    let mut keys = Vec::new();
+    if let Ok(toml::Value::Table(sections)) = toml::Value::try_from(&sample) {
+        for (section, table) in sections {
+            if let toml::Value::Table(table) = table {
+                keys.extend(table.keys().map(|key| format!("{}.{}", section, key)));
+            }
+        }
+    }
+    keys
+}
+
+/// Keys from older config files that are still read, as `(legacy, current)`
+const LEGACY_KEYS: &[(&str, &str)] = &[
+    ("storage.max_patterns", "retention.max_patterns"),
+    ("learning.max_patterns_per_context", "injection.max_patterns"),
+];
+
+/// Move legacy keys to their current place unless the current key is set too
+fn apply_legacy_keys(table: &mut toml::Table) {
+    for (legacy, current) in LEGACY_KEYS {
+        let (old_section, old_key) = legacy.split_once('.').expect("legacy keys are section.key");
+        let (section, key) = current.split_once('.').expect("known keys are section.key");
+        let Some(value) = table.get_mut(old_section).and_then(|s| s.as_table_mut()).and_then(|s| s.remove(old_key)) else {
+            continue;
+        };
+        let entry = table.entry(section.to_string()).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(section) = entry {
+            section.entry(key.to_string()).or_insert(value);
+        }
+    }
+}
+
+/// Keys in a config file that MANA does not recognize (typos, removed settings)
+pub fn unknown_keys(content: &str) -> Result<Vec<String>> {
+    let table: toml::Table = toml::from_str(content)?;
+    let known = known_keys();
+    let mut unknown = Vec::new();
+    for (section, value) in &table {
+        match value {
+            toml::Value::Table(keys) => unknown.extend(
+                keys.keys()
+                    .map(|key| format!("{}.{}", section, key))
+                    .filter(|path| !known.contains(path) && !LEGACY_KEYS.iter().any(|(legacy, _)| legacy == path)),
+            ),
+            _ => unknown.push(section.clone()),
+        }
+    }
+    Ok(unknown)
+}
+
+/// Apply `MANA_<SECTION>_<KEY>` overrides; returns the keys that were overridden
+///
+/// Values are read as TOML literals, falling back to plain strings.
+fn apply_env_overrides(table: &mut toml::Table, vars: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let known: HashMap<String, String> = known_keys()
+        .into_iter()
+        .map(|path| (format!("{}{}", ENV_PREFIX, path.replace('.', "_").to_uppercase()), path))
+        .collect();
+
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let Some(path) = known.get(&name) else { continue };
+        let (section, key) = path.split_once('.').expect("known keys are section.key");
+        let entry = table
+            .entry(section.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if let toml::Value::Table(section) = entry {
+            section.insert(key.to_string(), parse_literal(&raw));
+            applied.push(path.clone());
+        }
+    }
+    applied
+}
+
+/// Read a TOML literal (`5`, `true`, `"x"`, `[..]`), treating anything else as a string
+fn parse_literal(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn parse_config(content: &str, vars: impl Iterator<Item = (String, String)>) -> Result<ManaConfig> {
+    let mut table: toml::Table = toml::from_str(content)?;
+    apply_legacy_keys(&mut table);
+    let overridden = apply_env_overrides(&mut table, vars);
+    if !overridden.is_empty() {
+        debug!("Config overridden from environment: {}", overridden.join(", "));
+    }
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+fn config_cache() -> &'static Mutex<HashMap<PathBuf, ManaConfig>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, ManaConfig>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Load configuration from `<mana_dir>/config.toml` plus environment overrides
+///
+/// Read once per process and cached. Missing or malformed files fall back
+/// to defaults; injection must never fail because of a config typo.
+pub fn load_config(mana_dir: &Path) -> ManaConfig {
+    if let Some(config) = config_cache().lock().ok().and_then(|c| c.get(mana_dir).cloned()) {
+        return config;
+    }
+
+    let config = match load_config_strict(mana_dir) {
+        Ok(config) => config,
+        Err(e) => {
+            warn!("Invalid config at {:?}: {}, using defaults", mana_dir.join("config.toml"), e);
+            ManaConfig::default()
+        }
+    };
+    if let Ok(mut cache) = config_cache().lock() {
+        cache.insert(mana_dir.to_path_buf(), config.clone());
+    }
+    config
+}
+
+/// Load configuration, reporting parse errors instead of using defaults
+pub fn load_config_strict(mana_dir: &Path) -> Result<ManaConfig> {
+    let content = std::fs::read_to_string(mana_dir.join("config.toml")).unwrap_or_default();
+    parse_config(&content, std::env::vars())
+}
+
+/// Current value of `section.key`, including defaults and environment overrides
+pub fn get_value(config: &ManaConfig, path: &str) -> Result<Option<toml::Value>> {
+    if !known_keys().iter().any(|k| k == path) {
+        bail!("Unknown config key '{}' (see `mana config show`)", path);
+    }
+    let (section, key) = path.split_once('.').expect("known keys are section.key");
+    let value = toml::Value::try_from(config)?;
+    Ok(value.get(section).and_then(|s| s.get(key)).cloned())
+}
+
+/// Validate and write `section.key = value` to `<mana_dir>/config.toml`
+///
+/// `raw` may be any TOML literal; bare words are stored as strings.
+pub fn set_key(mana_dir: &Path, path: &str, raw: &str) -> Result<()> {
+    if !known_keys().iter().any(|k| k == path) {
+        bail!("Unknown config key '{}' (see `mana config show`)", path);
+    }
+    let (section, key) = path.split_once('.').expect("known keys are section.key");
+
+    let literal = parse_literal(raw).to_string();
+    let config_path = mana_dir.join("config.toml");
+    let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+    let updated = set_value_in(&content, section, key, &literal);
+
+    let parsed = parse_config(&updated, std::iter::empty())
+        .with_context(|| format!("Invalid value for {}: {}", path, raw))?;
+    let problems = parsed.validate();
+    if !problems.is_empty() {
+        bail!("{}", problems.join("; "));
+    }
+
+    std::fs::write(&config_path, updated)?;
+    if let Ok(mut cache) = config_cache().lock() {
+        cache.remove(mana_dir);
+    }
+    Ok(())
+}
+
+/// Set `key = value` inside `[section]` of `<mana_dir>/config.toml`
+///
+/// Line-based so comments and unrelated sections survive. `value` must
+/// already be a valid TOML literal (quote strings yourself). The section is
+/// appended if it doesn't exist yet.
+pub fn set_value(mana_dir: &Path, section: &str, key: &str, value: &str) -> Result<()> {
+    let config_path = mana_dir.join("config.toml");
+    let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+    let updated = set_value_in(&content, section, key, value);
+    std::fs::write(&config_path, updated)?;
+    if let Ok(mut cache) = config_cache().lock() {
+        cache.remove(mana_dir);
+    }
+    Ok(())
+}
+
+fn set_value_in(content: &str, section: &str, key: &str, value: &str) -> String {
+    let header = format!("[{}]", section);
+    let new_line = format!("{} = {}", key, value);
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+    let Some(start) = lines.iter().position(|l| l.trim() == header) else {
+        if !lines.is_empty() && !lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(header);
+        lines.push(new_line);
+        return lines.join("\n") + "\n";
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.trim_start().starts_with('['))
+        .map(|i| start + 1 + i)
+        .unwrap_or(lines.len());
+
+    let existing = lines[start + 1..end].iter().position(|l| {
+        l.split_once('=').is_some_and(|(k, _)| k.trim() == key)
+    });
+
+    match existing {
+        Some(i) => lines[start + 1 + i] = new_line,
+        None => {
+            // Insert after the last non-blank line of the section
+            let mut insert_at = end;
+            while insert_at > start + 1 && lines[insert_at - 1].trim().is_empty() {
+                insert_at -= 1;
+            }
+            lines.insert(insert_at, new_line);
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_config_uses_defaults() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let config = load_config(temp.path());
+        assert_eq!(config.injection.max_patterns, 3);
+        assert_eq!(config.injection.max_tokens, 400);
+    }
+
+    #[test]
+    fn test_partial_injection_section() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp.path().join("config.toml"),
+            "[learning]\nthreshold = 15\n\n[injection]\nmax_patterns = 5\n",
+        ).unwrap();
+
+        let config = load_config(temp.path());
+        assert_eq!(config.injection.max_patterns, 5);
+        assert_eq!(config.injection.max_tokens, 400);
+        assert_eq!(config.reflection.judge, JudgeKind::None);
+    }
+
+    #[test]
+    fn test_resolve_log_dirs() {
+        let home = Path::new("/home/u");
+        assert_eq!(resolve_log_dirs(&[], None, Some(home)), vec![PathBuf::from("/home/u/.claude/projects")]);
+
+        let configured = vec!["~/work/.claude/projects".to_string(), "/mnt/logs".to_string(), "/mnt/logs".to_string()];
+        assert_eq!(
+            resolve_log_dirs(&configured, None, Some(home)),
+            vec![PathBuf::from("/home/u/work/.claude/projects"), PathBuf::from("/mnt/logs")]
+        );
+
+        // The environment variable replaces the configured list
+        let env = std::env::join_paths(["/a", "~/b"]).unwrap();
+        assert_eq!(
+            resolve_log_dirs(&configured, Some(&env), Some(home)),
+            vec![PathBuf::from("/a"), PathBuf::from("/home/u/b")]
+        );
+    }
+
+    #[test]
+    fn test_set_value_preserves_comments() {
+        let content = "# MANA\n[injection]\n# budget\nmax_tokens = 400\n\n[learning]\nthreshold = 15\n";
+
+        let updated = set_value_in(content, "injection", "max_tokens", "200");
+        assert!(updated.contains("# budget\nmax_tokens = 200\n"));
+
+        let updated = set_value_in(&updated, "injection", "max_patterns", "5");
+        assert!(updated.contains("max_tokens = 200\nmax_patterns = 5\n\n[learning]"));
+
+        let updated = set_value_in(&updated, "embeddings", "m", "32");
+        assert!(updated.ends_with("threshold = 15\n\n[embeddings]\nm = 32\n"));
+
+        let parsed: ManaConfig = toml::from_str(&updated).unwrap();
+        assert_eq!(parsed.injection.max_tokens, 200);
+        assert_eq!(parsed.embeddings.hnsw.m, 32);
+    }
+
+    #[test]
+    fn test_env_overrides_and_validation() {
+        let vars = [
+            ("MANA_INJECTION_MAX_PATTERNS", "7"),
+            ("MANA_REFLECTION_JUDGE", "ollama"),
+            ("MANA_CLAUDE_LOGS", "/not/a/config/key"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let config = parse_config("[injection]\nmax_patterns = 2\n", vars.into_iter()).unwrap();
+        assert_eq!(config.injection.max_patterns, 7);
+        assert_eq!(config.reflection.judge, JudgeKind::Ollama);
+        assert!(config.validate().is_empty());
+
+        let config = parse_config("[embeddings]\nm = 1\n[reflection]\nmin_confidence = 1.5\n", std::iter::empty()).unwrap();
+        assert_eq!(config.validate().len(), 2);
+
+        let unknown = unknown_keys("[injection]\nmax_patterns = 2\nmax_patern = 3\n[storage]\nx = 1\nmax_patterns = 5\n").unwrap();
+        assert_eq!(unknown, vec!["injection.max_patern".to_string(), "storage.x".to_string()]);
+
+        // The legacy pattern cap still applies, unless [retention] sets its own
+        let config = parse_config("[storage]\nmax_patterns = 5000\n", std::iter::empty()).unwrap();
+        assert_eq!(config.retention.max_patterns, 5000);
+        let config = parse_config("[storage]\nmax_patterns = 5000\n[retention]\nmax_patterns = 200\n", std::iter::empty()).unwrap();
+        assert_eq!(config.retention.max_patterns, 200);
+
+        // So does the per-context cap from before the [injection] section
+        let config = parse_config("[learning]\nmax_patterns_per_context = 7\n", std::iter::empty()).unwrap();
+        assert_eq!(config.injection.max_patterns, 7);
+        let config = parse_config("[injection]\nmax_patterns_per_context = 4\n", std::iter::empty()).unwrap();
+        assert_eq!(config.injection.max_patterns, 4);
+    }
+
+    #[test]
+    fn test_set_key_validates_before_writing() {
+        let temp = tempfile::TempDir::new().unwrap();
+        set_key(temp.path(), "injection.wrapper_tag", "notes").unwrap();
+        set_key(temp.path(), "learning.threshold", "30").unwrap();
+
+        let config = load_config_strict(temp.path()).unwrap();
+        assert_eq!(config.injection.wrapper_tag, "notes");
+        assert_eq!(config.learning.threshold, 30);
+
+        assert!(set_key(temp.path(), "learning.threshold", "0").is_err());
+        assert!(set_key(temp.path(), "learning.threshhold", "3").is_err());
+        assert!(set_key(temp.path(), "injection.max_patterns", "lots").is_err());
+        assert_eq!(load_config_strict(temp.path()).unwrap().learning.threshold, 30);
+    }
+}
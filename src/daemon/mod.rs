@@ -6,26 +6,58 @@
 //! Architecture:
 //! - Unix socket server accepting JSON requests
 //! - In-memory pattern cache with lazy loading
-//! - Background learning and consolidation
+//! - Background learning, consolidation at `[consolidation] schedule`, and
+//!   embedding of new patterns (see `embeddings::backfill`)
 //!
 //! Protocol:
-//! - Request: JSON object with "command" field
+//! - Request: JSON object with "command" field (`inject`, `suggest`, `status`,
+//!   `ping`, `shutdown`)
 //! - Response: JSON object with "success" and "data" fields
 
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use chrono::Local;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
-use crate::embeddings::EmbeddingStore;
-use crate::storage::calculate_similarity;
+use crate::config::{load_config, InjectionConfig, OutputAdapter};
+use crate::embeddings::backfill;
+use crate::embeddings::{self, EmbeddingStore, IndexStamp, Reranker};
+use crate::hooks::budget::{BudgetExceeded, LatencyBudget, TokenBudget};
+use crate::hooks::template::{render_pattern, wrap_context, PatternFields};
+use crate::hooks::pitfalls;
+use crate::hooks::session_memory;
+use crate::hooks::repo_signals::RepoSignals;
+use crate::learning::{self, consolidate, Schedule, Stage, StageRun};
+use crate::storage::{terms, top_patterns, CausalStore, Scorer};
+use crate::storage::injection_log::{self, BudgetOverrun, InjectionRecord};
+
+/// Buffered injection records that trigger an immediate flush
+const INJECTION_LOG_BATCH: usize = 32;
+
+/// Maximum time buffered injection records wait before being flushed
+const INJECTION_LOG_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a scheduled consolidation waits for learning to finish
+const CONSOLIDATION_LOCK_WAIT: Duration = Duration::from_secs(60);
+
+/// How often the idle daemon looks for patterns waiting to be embedded
+const AUTO_EMBED_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the embedding index file is checked for saves by other processes
+const INDEX_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the daemon checks whether an opted-in telemetry report is due
+const TELEMETRY_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
 
 /// Socket path for daemon communication
 pub fn socket_path() -> PathBuf {
@@ -49,6 +81,12 @@ pub struct DaemonRequest {
     pub context: Option<String>,
     #[serde(default)]
     pub input: Option<String>,
+    /// Most results to return (`suggest`)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Output format for `inject`, overriding `injection.adapter`
+    #[serde(default)]
+    pub adapter: Option<OutputAdapter>,
 }
 
 /// Response from daemon to client
@@ -83,14 +121,26 @@ impl DaemonResponse {
 pub struct DaemonState {
     pub conn: Connection,
     pub embedding_store: Option<EmbeddingStore>,
-    #[allow(dead_code)]
+    /// The index file as loaded into `embedding_store`
+    index_stamp: Option<IndexStamp>,
+    pub causal_store: Option<CausalStore>,
+    pub injection: InjectionConfig,
+    /// Second-stage scorer for embedding results, when `[rerank]` is set
+    reranker: Option<Reranker>,
+    /// Latency budget per injection, counted from when the request arrives
+    pub budget_ms: u64,
     pub mana_dir: PathBuf,
+    /// Injection records waiting to be written in one batch
+    pending_log: RefCell<Vec<InjectionRecord>>,
+    /// Budget overruns waiting to be written with the next batch
+    pending_overruns: RefCell<Vec<BudgetOverrun>>,
 }
 
 impl DaemonState {
     pub fn new(mana_dir: &Path) -> Result<Self> {
         info!("Loading pattern store...");
         let db_path = mana_dir.join("metadata.sqlite");
+        crate::storage::ensure_schema(&db_path)?;
 
         // Open connection with mmap for fast repeated queries
         let conn = Connection::open_with_flags(
@@ -98,27 +148,134 @@ impl DaemonState {
             rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
                 | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        crate::storage::encryption::unlock(&conn)?;
         conn.pragma_update(None, "mmap_size", 2_097_152)?; // 2MB mmap
         conn.set_prepared_statement_cache_capacity(8);
 
         info!("Loading embedding store...");
-        let embedding_store = EmbeddingStore::open(mana_dir).ok();
+        let index_stamp = embeddings::index_stamp(mana_dir);
+        let mut embedding_store = EmbeddingStore::open(mana_dir).ok();
 
-        if embedding_store.is_some() {
+        if let Some(ref mut store) = embedding_store {
             info!("Embedding store loaded successfully");
+            if store.enable_ann() {
+                info!("HNSW graph built for {} vectors", store.index().len());
+            }
         } else {
             warn!("Embedding store not available");
         }
 
+        let causal_store = CausalStore::open_readonly(&db_path).ok();
+        let config = load_config(mana_dir);
+
         Ok(Self {
             conn,
             embedding_store,
+            index_stamp,
+            causal_store,
+            injection: config.injection,
+            reranker: Reranker::from_config(&config.rerank),
+            budget_ms: config.performance.injection_timeout_ms,
             mana_dir: mana_dir.to_path_buf(),
+            pending_log: RefCell::new(Vec::new()),
+            pending_overruns: RefCell::new(Vec::new()),
         })
     }
 
+    /// Reopen the embedding index if another process saved it since it was loaded
+    ///
+    /// `embed generate`, consolidation, backfill and deletes all save it.
+    fn reload_embeddings_if_changed(&mut self) {
+        let stamp = embeddings::index_stamp(&self.mana_dir);
+        if stamp == self.index_stamp {
+            return;
+        }
+        debug!("Embedding index changed on disk, reloading");
+        self.index_stamp = stamp;
+        self.embedding_store = EmbeddingStore::open(&self.mana_dir).ok();
+        if let Some(store) = self.embedding_store.as_mut() {
+            store.enable_ann();
+        }
+    }
+
+    /// Write buffered injection records and overruns, and any hook-spooled ones
+    ///
+    /// The daemon's main connection is read-only, so a short-lived writer
+    /// is opened per batch.
+    pub fn flush_injection_log(&self) {
+        let records: Vec<InjectionRecord> = self.pending_log.borrow_mut().drain(..).collect();
+        let overruns: Vec<BudgetOverrun> = self.pending_overruns.borrow_mut().drain(..).collect();
+        let spooled = [injection_log::SPOOL_FILE, injection_log::OVERRUN_SPOOL_FILE]
+            .iter()
+            .any(|file| self.mana_dir.join(file).exists());
+        if records.is_empty() && overruns.is_empty() && !spooled {
+            return;
+        }
+
+        // Shorter wait than other writers: queries are queued behind this flush
+        let result = crate::storage::db::open(&self.mana_dir.join("metadata.sqlite")).and_then(|conn| {
+            conn.busy_timeout(Duration::from_millis(500))?;
+            Ok(conn)
+        });
+
+        let mut conn = match result {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Failed to open database for injection log: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = injection_log::insert_records(&mut conn, &records) {
+            warn!("Failed to write {} injection records: {}", records.len(), e);
+        }
+        if let Err(e) = injection_log::insert_overruns(&mut conn, &overruns) {
+            warn!("Failed to write {} budget overruns: {}", overruns.len(), e);
+        }
+        if let Err(e) = injection_log::drain_spool(&self.mana_dir, &mut conn) {
+            warn!("Failed to drain injection spool: {}", e);
+        }
+    }
+
+    /// Buffer an injection record, flushing once the batch is full
+    ///
+    /// Returns whether the session is in the experiment control group, in
+    /// which case nothing should be shown.
+    fn record_injection(&self, input: &str, tool: &str, injected: &[(i64, f64)], latency: Duration) -> bool {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(input) else {
+            return false;
+        };
+        let Some(session_id) = json.get("session_id").and_then(|v| v.as_str()) else {
+            return false;
+        };
+        let logged_tool = json.get("tool_name").and_then(|v| v.as_str()).unwrap_or(tool);
+        let control = crate::reflection::in_control_group(session_id, self.injection.control_fraction);
+
+        let full = {
+            let mut pending = self.pending_log.borrow_mut();
+            pending.push(InjectionRecord::new(session_id, logged_tool, injected).with_latency(latency).with_control(control));
+            pending.len() >= INJECTION_LOG_BATCH
+        };
+        if !control {
+            let ids: Vec<i64> = injected.iter().map(|(id, _)| *id).collect();
+            if let Err(e) = session_memory::note_injected(&self.mana_dir, session_id, &ids, &self.injection) {
+                debug!("Failed to note injected patterns: {}", e);
+            }
+        }
+        if full {
+            self.flush_injection_log();
+        }
+        control
+    }
+
     /// Handle an inject request
-    pub fn handle_inject(&self, tool: &str, input: &str) -> Result<String> {
+    ///
+    /// Past the latency budget the input is returned unchanged and the
+    /// overrun is logged.
+    pub fn handle_inject(&self, tool: &str, input: &str, adapter: Option<OutputAdapter>) -> Result<String> {
+        let start = Instant::now();
+        let deadline = LatencyBudget::new(start, self.budget_ms);
+
         // Map tool argument to database tool_types
         let db_tool_type = match tool {
             "edit" => "Edit",
@@ -128,82 +285,257 @@ impl DaemonState {
             _ => tool,
         };
 
-        // Extract a query from the input for similarity matching
-        let query = extract_query_from_input(input, tool);
+        // Extract a query from the input for similarity matching, plus the repo's signals
+        let mut query = extract_query_from_input(input, tool);
+        if self.injection.repo_signals {
+            let cwd = serde_json::from_str::<serde_json::Value>(input)
+                .ok()
+                .and_then(|json| json.get("cwd").and_then(|v| v.as_str()).map(PathBuf::from));
+            if let Some(cwd) = cwd {
+                query = RepoSignals::for_dir(&self.mana_dir, &cwd).enrich(query);
+            }
+        }
+
+        let category = category_from_input(input, db_tool_type);
+        let repeated = self.recently_injected(input);
+
+        let patterns = match self.search(&query, db_tool_type, category.as_deref(), &repeated, &deadline) {
+            Ok(patterns) => patterns,
+            Err(exceeded) => {
+                warn!("Daemon {}, passing through", exceeded);
+                let logged_tool = serde_json::from_str::<serde_json::Value>(input)
+                    .ok()
+                    .and_then(|json| json.get("tool_name").and_then(|v| v.as_str()).map(str::to_string))
+                    .unwrap_or_else(|| db_tool_type.to_string());
+                self.pending_overruns.borrow_mut().push(BudgetOverrun::new(
+                    &logged_tool,
+                    exceeded.stage,
+                    exceeded.elapsed,
+                    exceeded.limit_ms,
+                ));
+                return Ok(input.to_string());
+            }
+        };
+
+        // Build response, trimming entries to the configured token budget;
+        // known pitfalls are charged first so success patterns can't crowd them out
+        // Session state is charged before pitfalls, which are charged before patterns
+        let json = serde_json::from_str::<serde_json::Value>(input).ok();
+        let session_id = json.as_ref().and_then(|json| json.get("session_id")).and_then(|v| v.as_str());
+        let state = session_memory::state_block(&self.mana_dir, session_id, &self.injection);
+        self.note_touched_file(session_id, json.as_ref());
+        let mut budget = TokenBudget::new(self.injection.max_tokens);
+        if let Some(state) = &state {
+            budget.consume(state);
+        }
+        let pitfall_section = pitfalls::section(&self.pitfalls(&query, &repeated), &mut budget);
+        let heading = "**Relevant patterns from previous successful operations:**";
+        budget.consume(heading);
+        let fitted: Vec<(i64, f64, String)> = patterns
+            .into_iter()
+            .map_while(|(id, score, entry)| budget.fit(&entry).map(|entry| (id, score, entry)))
+            .collect();
+
+        if fitted.is_empty() && pitfall_section.is_none() && state.is_none() {
+            Ok(input.to_string())
+        } else {
+            let mut injected: Vec<(i64, f64)> = fitted.iter().map(|(id, score, _)| (*id, *score)).collect();
+            let mut sections: Vec<String> = state.into_iter().collect();
+            if !fitted.is_empty() {
+                let entries: Vec<&str> = fitted.iter().map(|(_, _, entry)| entry.as_str()).collect();
+                sections.push(format!("{}\n\n{}", heading, entries.join("\n\n")));
+            }
+            if let Some((section, used)) = pitfall_section {
+                sections.push(section.trim_end().to_string());
+                injected.extend(used);
+            }
+            // Nothing to log with only session state, but the control group still gets nothing
+            let control = if injected.is_empty() {
+                session_id.is_some_and(|id| crate::reflection::in_control_group(id, self.injection.control_fraction))
+            } else {
+                self.record_injection(input, db_tool_type, &injected, start.elapsed())
+            };
+            if control {
+                return Ok(input.to_string());
+            }
+
+            let context_block = sections.join("\n\n");
+            let adapter = adapter.unwrap_or(self.injection.adapter);
+            Ok(format!("{}{}", wrap_context(&self.injection, adapter, &context_block, &injected), input))
+        }
+    }
+
+    /// Remember the file an Edit or Write is about to touch in the session's memory
+    fn note_touched_file(&self, session_id: Option<&str>, json: Option<&serde_json::Value>) {
+        if !self.injection.session_state || crate::storage::read_only::enabled() {
+            return;
+        }
+        let file = json.and_then(|json| {
+            ["/input/file_path", "/tool_input/file_path", "/file_path"]
+                .iter()
+                .find_map(|pointer| json.pointer(pointer).and_then(|v| v.as_str()))
+        });
+        if let (Some(session_id), Some(file)) = (session_id, file) {
+            if let Err(e) = session_memory::note_file(&self.mana_dir, session_id, file) {
+                debug!("Failed to update session memory: {}", e);
+            }
+        }
+    }
+
+    /// Patterns already shown in the input's session within the repeat window
+    ///
+    /// Read from the session memory file, which both the daemon and the
+    /// hook update on every injection.
+    fn recently_injected(&self, input: &str) -> HashSet<i64> {
+        let session_id = serde_json::from_str::<serde_json::Value>(input)
+            .ok()
+            .and_then(|json| json.get("session_id").and_then(|v| v.as_str()).map(str::to_string));
+        let (Some(session_id), Some(since)) = (session_id, self.injection.repeat_since(chrono::Utc::now())) else {
+            return HashSet::new();
+        };
+        session_memory::recently_injected(&self.mana_dir, &session_id, since)
+    }
 
-        // Search for relevant patterns
-        let mut patterns = Vec::new();
+    /// Find patterns for a query as (pattern id, score, formatted entry), best first
+    ///
+    /// Patterns in `repeated` are skipped so the next-best take their place.
+    fn search(
+        &self,
+        query: &str,
+        db_tool_type: &str,
+        category: Option<&str>,
+        repeated: &HashSet<i64>,
+        deadline: &LatencyBudget,
+    ) -> std::result::Result<Vec<(i64, f64, String)>, BudgetExceeded> {
+        let mut patterns: Vec<(i64, f64, String)> = Vec::new();
 
         // Try embedding search first
         if let Some(ref embed_store) = self.embedding_store {
-            if let Ok(results) = embed_store.search_with_context(&query, 5) {
+            let k = self.injection.max_patterns.max(5).max(self.reranker.as_ref().map_or(0, Reranker::candidates));
+            if let Ok(results) = embed_store.search_with_context(query, k + repeated.len(), &Default::default()) {
+                // Failure patterns only come in through the pitfalls section
+                let mut results: Vec<_> = results
+                    .into_iter()
+                    .filter(|m| m.tool_type != pitfalls::TOOL_TYPE && !repeated.contains(&m.id) && !self.is_withheld(m.id))
+                    .collect();
+                if let Some(reranker) = &self.reranker {
+                    deadline.check("embedding")?;
+                    results = reranker.rerank(query, results, |m| m.context_query.as_str(), deadline.remaining());
+                    deadline.check("rerank")?;
+                }
                 for m in results {
-                    let rate = m.success_rate() * 100.0;
-                    patterns.push(format!(
-                        "- **{}** (score: {}, {:.0}% success rate)\n  {}",
-                        m.tool_type,
-                        m.id,
-                        rate,
-                        truncate_context(&m.context_query, 100)
-                    ));
+                    let insight = truncate_context(&m.context_query, 100);
+                    patterns.push((m.id, m.similarity as f64, render_pattern(&self.injection, &PatternFields {
+                        id: m.id,
+                        tool: &m.tool_type,
+                        success_count: m.success_count,
+                        failure_count: m.failure_count,
+                        insight: &insight,
+                    })));
                 }
             }
+            deadline.check("embedding")?;
         }
 
-        // Fall back to similarity search
+        // Fall back to similarity search over the tool's top patterns
         if patterns.is_empty() {
-            if let Ok(mut stmt) = self.conn.prepare(
-                "SELECT tool_type, context_query, success_count, failure_count
-                 FROM patterns
-                 WHERE tool_type = ?1
-                 ORDER BY (success_count - failure_count) DESC
-                 LIMIT 10",
-            ) {
-                if let Ok(rows) = stmt.query_map([db_tool_type], |row| {
-                    Ok((
-                        row.get::<_, String>(0)?,
-                        row.get::<_, String>(1)?,
-                        row.get::<_, i64>(2)?,
-                        row.get::<_, i64>(3)?,
-                    ))
-                }) {
-                    for row in rows.flatten() {
-                        let (tool_type, context_query, success, failure) = row;
-                        let score = success - failure;
-                        let rate = if success + failure > 0 {
-                            (success as f64 / (success + failure) as f64) * 100.0
-                        } else {
-                            0.0
-                        };
-
-                        // Filter by similarity
-                        let sim = calculate_similarity(&query, &context_query);
-                        if sim > 0.35 {
-                            patterns.push(format!(
-                                "- **{}** (score: {}, {:.0}% success rate)\n  {}",
-                                tool_type, score, rate,
-                                truncate_context(&context_query, 100)
-                            ));
-
-                            if patterns.len() >= 3 {
-                                break;
-                            }
-                        }
-                    }
+            let scorer = Scorer::new(query, &terms::Corpus::new(&self.conn));
+            let top = self.top_patterns(db_tool_type, category, repeated.len());
+            for (id, tool_type, context_query, success, failure) in top.into_iter().filter(|p| !repeated.contains(&p.0)) {
+                // Filter by similarity
+                let sim = scorer.score(&context_query);
+                if sim > 0.35 {
+                    let insight = truncate_context(&context_query, 100);
+                    patterns.push((id, sim, render_pattern(&self.injection, &PatternFields {
+                        id,
+                        tool: &tool_type,
+                        success_count: success,
+                        failure_count: failure,
+                        insight: &insight,
+                    })));
                 }
             }
+            deadline.check("similarity")?;
         }
 
-        // Build response
-        if patterns.is_empty() {
-            Ok(input.to_string())
-        } else {
-            let context_block = format!(
-                "<mana-context>\n**Relevant patterns from previous successful operations:**\n\n{}\n</mana-context>\n\n{}",
-                patterns.join("\n\n"),
-                input
-            );
-            Ok(context_block)
+        let patterns = self.select_compatible(patterns);
+        deadline.check("causal")?;
+        Ok(patterns)
+    }
+
+    /// Known pitfalls relevant to `query`, best first
+    fn pitfalls(&self, query: &str, repeated: &HashSet<i64>) -> Vec<(crate::storage::Pattern, f64)> {
+        if self.injection.max_pitfalls == 0 {
+            return Vec::new();
+        }
+        let candidates = top_patterns::lookup(&self.conn, pitfalls::TOOL_TYPE, None, pitfalls::TO_SCORE).unwrap_or_default();
+        let scorer = Scorer::new(query, &terms::Corpus::new(&self.conn));
+        let candidates = candidates
+            .into_iter()
+            .map(|(p, _)| p)
+            .filter(|p| !repeated.contains(&p.id) && !self.is_withheld(p.id))
+            .collect();
+        pitfalls::rank(&scorer, candidates, &self.injection)
+    }
+
+    /// Best patterns for a tool as (id, tool_type, context_query, successes, failures)
+    ///
+    /// Reads the precomputed ranking, same command category first, and
+    /// sorts the tool's patterns directly if the cache has none. Unapproved
+    /// risky patterns and quarantined ones are left out; `extra` more than
+    /// the usual ten are read to make up for ones the caller will skip.
+    fn top_patterns(&self, tool_type: &str, category: Option<&str>, extra: usize) -> Vec<(i64, String, String, i64, i64)> {
+        let limit = 10 + extra;
+        let cached = top_patterns::lookup(&self.conn, tool_type, category, limit).unwrap_or_default();
+        if !cached.is_empty() {
+            return cached
+                .into_iter()
+                .filter(|(p, _)| !p.risky)
+                .map(|(p, _)| (p.id, p.tool_type, p.context_query, p.success_count, p.failure_count))
+                .collect();
+        }
+
+        let Ok(mut stmt) = self.conn.prepare_cached(
+            "SELECT id, tool_type, context_query, success_count, failure_count
+             FROM patterns
+             WHERE tool_type = ?1 AND (risky = 0 OR approved_at IS NOT NULL) AND status = 'active'
+             ORDER BY (success_count - failure_count) DESC
+             LIMIT ?2",
+        ) else {
+            return Vec::new();
+        };
+        stmt.query_map(rusqlite::params![tool_type, limit as i64], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))
+            .map(|rows| rows.flatten().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a pattern is an unapproved risky command or not active
+    fn is_withheld(&self, pattern_id: i64) -> bool {
+        self.conn
+            .query_row(
+                "SELECT (risky = 1 AND approved_at IS NULL) OR status != 'active' FROM patterns WHERE id = ?1",
+                [pattern_id],
+                |row| row.get(0),
+            )
+            .unwrap_or(false)
+    }
+
+    /// Drop causally conflicting candidates and cap the result at max_patterns
+    fn select_compatible(&self, candidates: Vec<(i64, f64, String)>) -> Vec<(i64, f64, String)> {
+        let max_patterns = self.injection.max_patterns;
+        let ranked: Vec<i64> = candidates.iter().map(|(id, _, _)| *id).collect();
+        let selected = self
+            .causal_store
+            .as_ref()
+            .and_then(|store| store.select_compatible(&ranked, max_patterns).ok());
+
+        match selected {
+            Some(ids) => {
+                let mut by_id: std::collections::HashMap<i64, (i64, f64, String)> =
+                    candidates.into_iter().map(|c| (c.0, c)).collect();
+                ids.iter().filter_map(|id| by_id.remove(id)).collect()
+            }
+            None => candidates.into_iter().take(max_patterns).collect(),
         }
     }
 
@@ -258,14 +590,37 @@ fn extract_query_from_input(input: &str, tool: &str) -> String {
     format!("Tool: {}", tool)
 }
 
+/// Command category of the tool input, as learning assigns it to patterns
+fn category_from_input(input: &str, tool_type: &str) -> Option<String> {
+    let json = serde_json::from_str::<serde_json::Value>(input).ok()?;
+    let fields = json.get("input").or_else(|| json.get("tool_input")).unwrap_or(&json);
+    crate::learning::extract_command_category(tool_type, fields)
+}
+
 /// Truncate context for display
+/// Pitfall/Advice lines are shown when present, otherwise the first line
 fn truncate_context(s: &str, max_len: usize) -> String {
+    let key_lines: Vec<String> = s
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("Pitfall:") || line.starts_with("Advice:"))
+        .map(|line| truncate_line(line, max_len))
+        .collect();
+    if !key_lines.is_empty() {
+        return key_lines.join("\n  ");
+    }
+
     // Take first line only for cleaner display
-    let first_line = s.lines().next().unwrap_or(s);
-    if first_line.len() <= max_len {
-        first_line.to_string()
+    truncate_line(s.lines().next().unwrap_or(s), max_len)
+}
+
+/// Truncate a single line to max_len characters
+fn truncate_line(line: &str, max_len: usize) -> String {
+    if line.chars().count() <= max_len {
+        line.to_string()
     } else {
-        format!("{}...", &first_line[..max_len.saturating_sub(3)])
+        let kept: String = line.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", kept)
     }
 }
 
@@ -328,7 +683,7 @@ fn handle_request(req: &DaemonRequest, state: &DaemonState) -> DaemonResponse {
             let tool = req.tool.as_deref().unwrap_or("Bash");
             let input = req.input.as_deref().unwrap_or("");
 
-            match state.handle_inject(tool, input) {
+            match state.handle_inject(tool, input, req.adapter) {
                 Ok(result) => DaemonResponse::ok(Some(result)),
                 Err(e) => DaemonResponse::err(format!("Inject failed: {}", e)),
             }
@@ -337,6 +692,17 @@ fn handle_request(req: &DaemonRequest, state: &DaemonState) -> DaemonResponse {
             Ok(status) => DaemonResponse::ok(Some(status)),
             Err(e) => DaemonResponse::err(format!("Status failed: {}", e)),
         },
+        "suggest" => {
+            let context = req.context.as_deref().unwrap_or("");
+            let limit = req.limit.unwrap_or(5);
+
+            match crate::suggest::plan(&state.conn, state.embedding_store.as_ref(), state.causal_store.as_ref(), context, limit)
+                .and_then(|plan| Ok(serde_json::to_string(&plan)?))
+            {
+                Ok(plan) => DaemonResponse::ok(Some(plan)),
+                Err(e) => DaemonResponse::err(format!("Suggest failed: {}", e)),
+            }
+        }
         "ping" => DaemonResponse::ok(Some("pong".to_string())),
         "shutdown" => {
             info!("Shutdown requested");
@@ -362,7 +728,24 @@ pub fn start_daemon(mana_dir: &Path) -> Result<()> {
 
     // Load state
     info!("Initializing daemon state...");
-    let state = DaemonState::new(mana_dir)?;
+    let mut state = DaemonState::new(mana_dir)?;
+
+    let schedule = load_config(mana_dir).consolidation.schedule.and_then(|spec| match Schedule::parse(&spec) {
+        Ok(schedule) => Some(schedule),
+        Err(e) => {
+            warn!("Ignoring consolidation schedule {:?}: {}", spec, e);
+            None
+        }
+    });
+    let mut next_consolidation = schedule.as_ref().and_then(|s| s.next_after(&Local::now()));
+    if let Some(at) = next_consolidation {
+        info!("Next scheduled consolidation at {}", at.format("%Y-%m-%d %H:%M"));
+    }
+    // Scheduled consolidation or background embedding, one at a time
+    let mut worker: Option<std::thread::JoinHandle<()>> = None;
+    let mut last_embed_check = Instant::now();
+    let mut last_index_check = Instant::now();
+    let mut last_telemetry_check = Instant::now();
 
     // Create socket
     info!("Starting daemon on {:?}", socket);
@@ -385,6 +768,7 @@ pub fn start_daemon(mana_dir: &Path) -> Result<()> {
         .set_nonblocking(true)
         .context("Failed to set non-blocking")?;
 
+    let mut last_log_flush = Instant::now();
     while running.load(Ordering::SeqCst) {
         match listener.accept() {
             Ok((stream, _)) => {
@@ -395,6 +779,37 @@ pub fn start_daemon(mana_dir: &Path) -> Result<()> {
                 handle_client(stream, &state);
             }
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                // Idle: write out buffered injection telemetry
+                if last_log_flush.elapsed() >= INJECTION_LOG_FLUSH_INTERVAL {
+                    state.flush_injection_log();
+                    last_log_flush = Instant::now();
+                }
+                if worker.as_ref().is_some_and(|handle| handle.is_finished()) {
+                    worker = None;
+                }
+                if last_index_check.elapsed() >= INDEX_CHECK_INTERVAL {
+                    state.reload_embeddings_if_changed();
+                    last_index_check = Instant::now();
+                }
+                if last_telemetry_check.elapsed() >= TELEMETRY_CHECK_INTERVAL {
+                    last_telemetry_check = Instant::now();
+                    spawn_telemetry(mana_dir.to_path_buf());
+                }
+                if let (Some(schedule), Some(at)) = (&schedule, next_consolidation) {
+                    let now = Local::now();
+                    if now >= at {
+                        if worker.is_none() {
+                            worker = Some(spawn_scheduled_consolidation(mana_dir.to_path_buf()));
+                        }
+                        next_consolidation = schedule.next_after(&now);
+                    }
+                }
+                if worker.is_none() && last_embed_check.elapsed() >= AUTO_EMBED_INTERVAL {
+                    last_embed_check = Instant::now();
+                    if backfill::enabled(mana_dir) && backfill::pending(&state.conn) > 0 {
+                        worker = Some(spawn_backfill(mana_dir.to_path_buf()));
+                    }
+                }
                 // No connection pending, sleep briefly
                 std::thread::sleep(Duration::from_millis(100));
             }
@@ -406,12 +821,66 @@ pub fn start_daemon(mana_dir: &Path) -> Result<()> {
 
     // Cleanup
     info!("Daemon shutting down");
+    state.flush_injection_log();
     let _ = std::fs::remove_file(&socket);
     let _ = std::fs::remove_file(&pid_file);
 
     Ok(())
 }
 
+/// Send a telemetry report if one is due, off the accept loop
+///
+/// Does nothing unless telemetry was enabled; a failed send is retried at
+/// the next check.
+fn spawn_telemetry(mana_dir: PathBuf) {
+    std::thread::spawn(move || {
+        if let Err(e) = crate::telemetry::send_if_due(&mana_dir) {
+            warn!("Telemetry report not sent: {}", e);
+        }
+    });
+}
+
+/// Consolidate on a worker thread so injections are still served
+fn spawn_scheduled_consolidation(mana_dir: PathBuf) -> std::thread::JoinHandle<()> {
+    info!("Starting scheduled consolidation");
+    std::thread::spawn(move || {
+        let _lock = match learning::lock::acquire(&mana_dir, CONSOLIDATION_LOCK_WAIT) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => {
+                warn!("Learning still in progress, skipping scheduled consolidation");
+                return;
+            }
+            Err(e) => {
+                error!("Scheduled consolidation failed: {}", e);
+                return;
+            }
+        };
+        let mut progress = |run: &StageRun, _: &_| info!("Consolidation stage {} done in {}ms", run.stage.name(), run.elapsed_ms);
+        if let Err(e) = consolidate(&mana_dir, &Stage::ALL, &mut progress) {
+            error!("Scheduled consolidation failed: {}", e);
+        }
+    })
+}
+
+/// Embed queued patterns on a worker thread, skipping the round if learning holds the lock
+fn spawn_backfill(mana_dir: PathBuf) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let _lock = match learning::lock::acquire(&mana_dir, Duration::ZERO) {
+            Ok(Some(lock)) => lock,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Background embedding failed: {}", e);
+                return;
+            }
+        };
+        let limits = backfill::Limits::from_config(&load_config(&mana_dir).embeddings);
+        match backfill::run(&mana_dir, limits) {
+            Ok(report) => info!("Embedded {} new patterns ({} left)", report.embedded, report.remaining),
+            Err(e) => error!("Background embedding failed: {}", e),
+        }
+    })
+}
+
 /// Check if daemon is running
 pub fn is_running() -> bool {
     let socket = socket_path();
@@ -475,6 +944,8 @@ pub fn stop_daemon() -> Result<()> {
         tool: None,
         context: None,
         input: None,
+        limit: None,
+        adapter: None,
     };
 
     match send_request(&req) {
@@ -508,6 +979,8 @@ pub fn daemon_status() -> String {
             tool: None,
             context: None,
             input: None,
+            limit: None,
+            adapter: None,
         };
 
         match send_request(&req) {
@@ -526,12 +999,14 @@ pub fn daemon_status() -> String {
 }
 
 /// Inject context via daemon (fast path)
-pub fn inject_via_daemon(tool: &str, input: &str) -> Result<String> {
+pub fn inject_via_daemon(tool: &str, input: &str, adapter: Option<OutputAdapter>) -> Result<String> {
     let req = DaemonRequest {
         command: "inject".to_string(),
         tool: Some(tool.to_string()),
         context: None,
         input: Some(input.to_string()),
+        limit: None,
+        adapter,
     };
 
     let resp = send_request(&req)?;
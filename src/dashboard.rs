@@ -0,0 +1,266 @@
+//! Live terminal dashboard (`mana stats --watch`)
+//!
+//! Polls the database on an interval and redraws pattern counts, recent
+//! learning events, reflection verdict trends, injection latency and sync
+//! state. Everything shown comes from a [`Snapshot`], so rendering never
+//! touches the database.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::reflection::{self, DailyVerdicts};
+use crate::storage::injection_log::{self, LatencySummary};
+use crate::storage::stats::{self, StatsReport};
+use crate::sync;
+
+/// Days of verdict history shown in the trend panel
+const TREND_DAYS: u32 = 7;
+
+/// Latency samples drawn in the sparkline
+const SPARKLINE_SAMPLES: usize = 60;
+
+/// Everything the dashboard displays, read in one pass
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub data_dir: PathBuf,
+    pub taken_at: Option<DateTime<Local>>,
+    pub stats: StatsReport,
+    pub trend: Vec<DailyVerdicts>,
+    /// Injection latency over the last 24 hours
+    pub latency: LatencySummary,
+    pub sync: Vec<String>,
+}
+
+/// Read a fresh snapshot from the MANA data directory
+pub fn load_snapshot(mana_dir: &Path) -> Result<Snapshot> {
+    let conn = crate::storage::db::open_readonly(&mana_dir.join("metadata.sqlite"))?;
+    let since = Utc::now() - chrono::Duration::hours(24);
+
+    Ok(Snapshot {
+        data_dir: mana_dir.to_path_buf(),
+        taken_at: Some(Local::now()),
+        stats: stats::collect_stats(&conn, 8)?,
+        trend: reflection::verdict_trend(&conn, TREND_DAYS).unwrap_or_default(),
+        latency: injection_log::latency_summary(&conn, since, SPARKLINE_SAMPLES).unwrap_or_default(),
+        sync: sync_lines(mana_dir),
+    })
+}
+
+/// Sync state without touching the network
+pub(crate) fn sync_lines(mana_dir: &Path) -> Vec<String> {
+    let config = match sync::load_sync_config(&mana_dir.join("sync.toml")) {
+        Ok(config) => config,
+        Err(e) => return vec![format!("Config error: {}", e)],
+    };
+
+    match &config.backend {
+        sync::SyncBackend::Git { .. } => match sync::sync_status(mana_dir) {
+            Ok(status) if status.configured => vec![
+                format!("Backend: git ({})", status.remote.as_deref().unwrap_or("local only")),
+                format!("Local changes: {}", if status.local_changes { "yes" } else { "none" }),
+                format!("Last sync: {}", status.last_sync.as_deref().unwrap_or("never")),
+            ],
+            Ok(_) => vec!["Not configured".into(), "Run 'mana sync init'".into()],
+            Err(e) => vec![format!("Status error: {}", e)],
+        },
+        sync::SyncBackend::S3 { bucket, .. } => vec![format!("Backend: s3 ({})", bucket)],
+        sync::SyncBackend::Supabase { url } => vec![format!("Backend: supabase ({})", url)],
+        sync::SyncBackend::Ssh { host, path } => vec![format!("Backend: ssh ({}:{})", host, path)],
+        sync::SyncBackend::P2P { peers, .. } => {
+            vec!["Backend: p2p".into(), format!("Peers: {}", peers.len())]
+        }
+    }
+}
+
+/// Run the dashboard until the user quits
+pub fn run(mana_dir: &Path, interval: Duration) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, mana_dir, interval);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, mana_dir: &Path, interval: Duration) -> Result<()> {
+    let mut snapshot = Snapshot { data_dir: mana_dir.to_path_buf(), ..Default::default() };
+    let mut error: Option<String> = None;
+    let mut last_refresh: Option<Instant> = None;
+
+    loop {
+        if last_refresh.is_none_or(|at| at.elapsed() >= interval) {
+            // Keep showing the last good snapshot if a refresh fails (e.g. database busy)
+            match load_snapshot(mana_dir) {
+                Ok(fresh) => {
+                    snapshot = fresh;
+                    error = None;
+                }
+                Err(e) => error = Some(e.to_string()),
+            }
+            last_refresh = Some(Instant::now());
+        }
+
+        terminal.draw(|frame| draw(frame, &snapshot, error.as_deref()))?;
+
+        let wait = last_refresh.map_or(Duration::ZERO, |at| interval.saturating_sub(at.elapsed()));
+        if event::poll(wait)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                    KeyCode::Char('r') => last_refresh = None,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Render one frame
+pub fn draw(frame: &mut Frame, snapshot: &Snapshot, error: Option<&str>) {
+    let [header, top, middle, bottom] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(8),
+        Constraint::Min(8),
+        Constraint::Min(6),
+    ])
+    .areas(frame.area());
+
+    let refreshed = snapshot.taken_at.map(|t| t.format("%H:%M:%S").to_string()).unwrap_or_else(|| "-".into());
+    let mut title = vec![
+        Span::styled(" MANA ", Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)),
+        Span::raw(format!(" {}  refreshed {}  (r refresh, q quit)", snapshot.data_dir.display(), refreshed)),
+    ];
+    if let Some(error) = error {
+        title.push(Span::styled(format!("  {}", error), Style::default().fg(Color::Red)));
+    }
+    frame.render_widget(Paragraph::new(Line::from(title)), header);
+
+    let [patterns, graph] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(top);
+    draw_patterns(frame, patterns, &snapshot.stats);
+    draw_graph(frame, graph, &snapshot.stats);
+
+    let [trend, latency] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(middle);
+    draw_trend(frame, trend, &snapshot.trend);
+    draw_latency(frame, latency, &snapshot.latency);
+
+    let [events, sync] = Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(bottom);
+    let lines: Vec<Line> = if snapshot.stats.recent_events.is_empty() {
+        vec![Line::from("No learning events recorded yet.")]
+    } else {
+        snapshot
+            .stats
+            .recent_events
+            .iter()
+            .map(|e| Line::from(format!("{}  {}", e.timestamp, e.event_type)))
+            .collect()
+    };
+    frame.render_widget(Paragraph::new(lines).block(panel("Learning events")), events);
+    let lines: Vec<Line> = snapshot.sync.iter().map(|l| Line::from(l.as_str())).collect();
+    frame.render_widget(Paragraph::new(lines).block(panel("Sync")), sync);
+}
+
+fn panel(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(format!(" {} ", title))
+}
+
+fn draw_patterns(frame: &mut Frame, area: Rect, stats: &StatsReport) {
+    let p = &stats.patterns;
+    let rate = p.success_rate.map(|r| format!("{:.1}%", r)).unwrap_or_else(|| "n/a".into());
+    let mut lines = vec![
+        Line::from(format!("Total: {}", p.total)),
+        Line::from(format!("Success rate: {} ({} ok / {} failed)", rate, p.successes, p.failures)),
+    ];
+    lines.extend(p.by_tool.iter().map(|t| Line::from(format!("  {:<10} {}", t.tool, t.count))));
+    frame.render_widget(Paragraph::new(lines).block(panel("Patterns")), area);
+}
+
+fn draw_graph(frame: &mut Frame, area: Rect, stats: &StatsReport) {
+    let c = &stats.causal;
+    let lines = vec![
+        Line::from(format!("Causal edges: {}", c.total_edges)),
+        Line::from(format!("  synergies {}  conflicts {}  uncertain {}", c.synergies, c.conflicts, c.uncertain)),
+        Line::from(""),
+        Line::from(format!("Skills: {}", stats.skills.total)),
+        Line::from(format!("  avg success {:.1}%", stats.skills.avg_success_rate)),
+    ];
+    frame.render_widget(Paragraph::new(lines).block(panel("Causal graph & skills")), area);
+}
+
+fn draw_trend(frame: &mut Frame, area: Rect, trend: &[DailyVerdicts]) {
+    let mut lines = vec![Line::from(Span::styled(
+        "day          eff  neu  inef harm",
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    if trend.is_empty() {
+        lines.push(Line::from("No verdicts in the last week."));
+    }
+    for d in trend {
+        let harm = if d.harmful > 0 { Style::default().fg(Color::Red) } else { Style::default() };
+        lines.push(Line::from(vec![
+            Span::raw(format!("{}  ", d.day)),
+            Span::styled(format!("{:>4} ", d.effective), Style::default().fg(Color::Green)),
+            Span::raw(format!("{:>4} {:>4} ", d.neutral, d.ineffective)),
+            Span::styled(format!("{:>4}", d.harmful), harm),
+        ]));
+    }
+    frame.render_widget(Paragraph::new(lines).block(panel("Reflection verdicts (7 days)")), area);
+}
+
+fn draw_latency(frame: &mut Frame, area: Rect, latency: &LatencySummary) {
+    let block = panel("Injection latency (24h)");
+    if latency.samples == 0 {
+        frame.render_widget(Paragraph::new("No injections measured yet.").block(block), area);
+        return;
+    }
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let [summary, spark] = Layout::vertical([Constraint::Length(2), Constraint::Min(1)]).areas(inner);
+
+    let ms = |us: u64| us as f64 / 1000.0;
+    let lines = vec![
+        Line::from(format!(
+            "p50 {:.1}ms  p95 {:.1}ms  max {:.1}ms",
+            ms(latency.p50_us),
+            ms(latency.p95_us),
+            ms(latency.max_us)
+        )),
+        Line::from(format!("{} injections", latency.samples)),
+    ];
+    frame.render_widget(Paragraph::new(lines), summary);
+    frame.render_widget(Sparkline::default().data(&latency.recent_us).style(Style::default().fg(Color::Cyan)), spark);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_draw_renders_all_panels() {
+        let mut snapshot = Snapshot { data_dir: PathBuf::from("/tmp/.mana"), ..Default::default() };
+        snapshot.stats.patterns.total = 42;
+        snapshot.trend.push(DailyVerdicts { day: "2026-01-02".into(), effective: 3, ..Default::default() });
+        snapshot.latency = LatencySummary { samples: 2, p50_us: 1500, p95_us: 4000, max_us: 4000, recent_us: vec![1500, 4000] };
+        snapshot.sync = vec!["Backend: git (local only)".into()];
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &snapshot, None)).unwrap();
+
+        let text: String = terminal.backend().buffer().content().iter().map(|c| c.symbol()).collect();
+        for expected in ["Total: 42", "2026-01-02", "p50 1.5ms", "Backend: git", "Learning events"] {
+            assert!(text.contains(expected), "missing {:?}", expected);
+        }
+    }
+}
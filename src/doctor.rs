@@ -0,0 +1,519 @@
+//! End-to-end health checks (`mana doctor`)
+//!
+//! Each check reports a status and a one-line detail. Anything that isn't
+//! passing carries a concrete fix: a command to run or a file to edit.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::config::{self, ManaConfig};
+use crate::embeddings::VectorIndex;
+use crate::hooks::protocol;
+use crate::hooks::settings::{self, HookRegistration};
+use crate::storage::{db, maintenance, migrations};
+use crate::sync::{self, SyncBackend};
+use crate::{daemon, update};
+
+/// Upper bound for each network check
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connect timeout per P2P peer
+const PEER_TIMEOUT: Duration = Duration::from_secs(2);
+
+const HOOKS_FIX: &str = "Run 'mana init --install-hooks'";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// Not applicable or not checked (e.g. optional feature not in use)
+    Skip,
+}
+
+/// Result of one health check
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix a failing or warning check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Pass, detail: detail.into(), fix: None }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Skip, detail: detail.into(), fix: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+/// Run every check against a MANA data directory
+///
+/// With `offline` set, sync connectivity and the latest-version lookup are
+/// skipped.
+pub async fn run_checks(mana_dir: &Path, offline: bool) -> Vec<Check> {
+    let mut checks = vec![check_data_dir(mana_dir)];
+    checks.extend(check_database(mana_dir));
+
+    let config = match config::load_config_strict(mana_dir) {
+        Ok(config) => {
+            checks.push(check_config(mana_dir, &config));
+            config
+        }
+        Err(e) => {
+            checks.push(Check::fail("config", e.to_string(), "Run 'mana config validate' for details"));
+            config::load_config(mana_dir)
+        }
+    };
+
+    checks.push(check_embeddings(mana_dir));
+    checks.push(match std::env::current_dir() {
+        Ok(cwd) => check_hooks(&settings::settings_paths(&cwd)),
+        Err(e) => Check::fail("hooks", format!("Cannot read working directory: {}", e), HOOKS_FIX),
+    });
+    checks.push(check_hook_input(mana_dir));
+    checks.push(check_daemon());
+    checks.push(check_sync(mana_dir, offline).await);
+    checks.push(check_log_dirs(&config));
+    checks.push(check_version(offline));
+    checks
+}
+
+fn check_data_dir(mana_dir: &Path) -> Check {
+    if mana_dir.is_dir() {
+        Check::pass("data dir", mana_dir.display().to_string())
+    } else {
+        Check::fail("data dir", format!("{} does not exist", mana_dir.display()), "Run 'mana init'")
+    }
+}
+
+fn check_database(mana_dir: &Path) -> Vec<Check> {
+    let db_path = mana_dir.join("metadata.sqlite");
+    if !db_path.exists() {
+        return vec![Check::fail("database", format!("No database at {}", db_path.display()), "Run 'mana init'")];
+    }
+    let conn = match db::open_readonly(&db_path) {
+        Ok(conn) => conn,
+        Err(e) => {
+            return vec![Check::fail(
+                "database",
+                format!("Cannot open {}: {}", db_path.display(), e),
+                "Restore the latest backup with 'mana db restore'",
+            )]
+        }
+    };
+
+    let (current, latest) = (migrations::current_version(&conn), migrations::latest_version());
+    let schema = if current < latest {
+        Check::warn("schema", format!("v{} (this binary expects v{})", current, latest), "Run 'mana db migrate'")
+    } else if current > latest {
+        Check::fail(
+            "schema",
+            format!("v{} is newer than this binary supports (v{})", current, latest),
+            "Run 'mana update --force' to install a newer binary",
+        )
+    } else {
+        Check::pass("schema", format!("v{} (current)", current))
+    };
+
+    let integrity = match maintenance::integrity_check(&conn) {
+        Ok(problems) if problems.is_empty() => Check::pass("integrity", "SQLite integrity check ok"),
+        Ok(problems) => Check::fail(
+            "integrity",
+            format!("{} problem(s), first: {}", problems.len(), problems[0]),
+            "Restore the latest backup with 'mana db restore'",
+        ),
+        Err(e) => Check::fail("integrity", e.to_string(), "Restore the latest backup with 'mana db restore'"),
+    };
+
+    vec![schema, integrity]
+}
+
+fn check_config(mana_dir: &Path, config: &ManaConfig) -> Check {
+    let problems = config.validate();
+    if let Some(first) = problems.first() {
+        return Check::fail("config", first.clone(), "Run 'mana config validate' and fix the listed values");
+    }
+
+    let content = std::fs::read_to_string(mana_dir.join("config.toml")).unwrap_or_default();
+    match config::unknown_keys(&content) {
+        Ok(unknown) if !unknown.is_empty() => Check::warn(
+            "config",
+            format!("Unknown keys are ignored: {}", unknown.join(", ")),
+            "Remove or rename them in config.toml",
+        ),
+        _ => Check::pass("config", "valid"),
+    }
+}
+
+fn check_embeddings(mana_dir: &Path) -> Check {
+    let index_path = mana_dir.join("vectors.usearch");
+    if !index_path.exists() {
+        return Check::skip("embeddings", "No vector index (optional; 'mana embed generate' enables it)");
+    }
+    let index = match VectorIndex::load(&index_path) {
+        Ok(index) => index,
+        Err(e) => return Check::fail("embeddings", format!("Cannot load vector index: {}", e), "Run 'mana embed rebuild'"),
+    };
+    let Ok(conn) = db::open_readonly(&mana_dir.join("metadata.sqlite")) else {
+        return Check::skip("embeddings", "Database unavailable");
+    };
+
+    let patterns: HashSet<i64> = match conn.prepare("SELECT id FROM patterns").and_then(|mut stmt| {
+        stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<HashSet<i64>>>()
+    }) {
+        Ok(ids) => ids,
+        Err(e) => return Check::fail("embeddings", e.to_string(), "Run 'mana db integrity-check'"),
+    };
+    let unembedded: i64 = conn
+        .query_row("SELECT COUNT(*) FROM patterns WHERE embedding IS NULL", [], |row| row.get(0))
+        .unwrap_or(0);
+
+    let indexed: HashSet<i64> = index.ids().iter().copied().collect();
+    let duplicates = index.len() - indexed.len();
+    let stale = indexed.difference(&patterns).count();
+    let missing = patterns.difference(&indexed).count();
+
+    if stale > 0 || duplicates > 0 {
+        Check::fail(
+            "embeddings",
+            format!("{} vectors for deleted patterns, {} duplicate vectors", stale, duplicates),
+            "Run 'mana embed rebuild'",
+        )
+    } else if unembedded > 0 {
+        Check::warn("embeddings", format!("{} patterns have no embedding", unembedded), "Run 'mana embed generate'")
+    } else if missing > 0 {
+        Check::warn("embeddings", format!("{} embedded patterns missing from the index", missing), "Run 'mana embed rebuild'")
+    } else {
+        Check::pass("embeddings", format!("{} vectors, consistent with the database", index.len()))
+    }
+}
+
+fn check_hooks(paths: &[std::path::PathBuf]) -> Check {
+    let mut found = HookRegistration::default();
+    let mut sources = Vec::new();
+
+    for path in paths.iter().filter(|p| p.exists()) {
+        let settings = match settings::read_settings(path) {
+            Ok(settings) => settings,
+            Err(e) => return Check::fail("hooks", format!("{:#}", e), format!("Fix the JSON in {}", path.display())),
+        };
+        let registration = settings::registered_hooks(&settings);
+        if registration.is_empty() {
+            continue;
+        }
+        sources.push(path.display().to_string());
+        for matcher in registration.inject_matchers {
+            if !found.inject_matchers.contains(&matcher) {
+                found.inject_matchers.push(matcher);
+            }
+        }
+        found.session_end |= registration.session_end;
+    }
+
+    if found.is_complete() {
+        Check::pass(
+            "hooks",
+            format!("inject ({}) and session-end in {}", found.inject_matchers.join(", "), sources.join(", ")),
+        )
+    } else if found.is_empty() {
+        Check::fail("hooks", "No MANA hooks in Claude Code settings", HOOKS_FIX)
+    } else if found.session_end {
+        Check::warn("hooks", "session-end is registered but no inject hook", HOOKS_FIX)
+    } else {
+        Check::warn("hooks", "inject is registered but no session-end hook, so learning never triggers", HOOKS_FIX)
+    }
+}
+
+fn check_hook_input(mana_dir: &Path) -> Check {
+    let failures = protocol::recent_failures(mana_dir, 24);
+    match failures.last() {
+        None => Check::pass("hook input", "No hook input passed through unprocessed in the last day"),
+        Some(last) => Check::warn(
+            "hook input",
+            format!("{} hook calls passed through without context in the last day; latest: {}", failures.len(), last.error),
+            format!("See {} for details", mana_dir.join(protocol::ERRORS_FILE).display()),
+        ),
+    }
+}
+
+fn check_daemon() -> Check {
+    let socket = daemon::socket_path();
+    if daemon::is_running() {
+        Check::pass("daemon", format!("running ({})", socket.display()))
+    } else if socket.exists() {
+        Check::warn(
+            "daemon",
+            format!("{} exists but nothing answers on it", socket.display()),
+            format!("Remove {} and run 'mana daemon start'", socket.display()),
+        )
+    } else {
+        Check::skip("daemon", "not running (optional; hooks use the direct path)")
+    }
+}
+
+async fn check_sync(mana_dir: &Path, offline: bool) -> Check {
+    let config_path = mana_dir.join("sync.toml");
+    if !config_path.exists() {
+        return Check::skip("sync", "not configured");
+    }
+    let config = match sync::load_sync_config(&config_path) {
+        Ok(config) => config,
+        Err(e) => return Check::fail("sync", format!("Invalid sync.toml: {}", e), "Fix sync.toml or re-run 'mana sync init'"),
+    };
+    if offline {
+        return Check::skip("sync", "connectivity not checked (--offline)");
+    }
+
+    match &config.backend {
+        SyncBackend::Git { .. } => match sync::sync_status(mana_dir) {
+            Ok(status) if !status.repo_initialized => Check::fail(
+                "sync",
+                "git backend configured but the local repository is missing",
+                "Run 'mana sync init --remote <url>'",
+            ),
+            Ok(status) => match status.remote {
+                None => Check::pass("sync", "git (local only, no remote)"),
+                Some(remote) => match git_ls_remote(mana_dir, &remote) {
+                    Ok(()) => Check::pass("sync", format!("git remote {} reachable", remote)),
+                    Err(e) => Check::fail(
+                        "sync",
+                        format!("Cannot reach {}: {}", remote, e),
+                        format!("Check network access and credentials with 'git ls-remote {}'", remote),
+                    ),
+                },
+            },
+            Err(e) => Check::fail("sync", e.to_string(), "Re-run 'mana sync init'"),
+        },
+        SyncBackend::S3 { bucket, .. } => {
+            if !sync::is_s3_available() {
+                return Check::fail(
+                    "sync",
+                    "s3 backend configured but this binary was built without S3 support",
+                    "Rebuild with 'cargo install --features s3'",
+                );
+            }
+            match tokio::time::timeout(NETWORK_TIMEOUT, sync::s3_status(mana_dir)).await {
+                Ok(Ok(status)) if status.object_exists => Check::pass("sync", format!("s3://{} reachable", bucket)),
+                Ok(Ok(_)) => Check::warn(
+                    "sync",
+                    format!("No patterns object in s3://{} (never pushed, or the bucket is unreachable)", bucket),
+                    "Check AWS credentials, then run 'mana sync push'",
+                ),
+                Ok(Err(e)) => Check::fail("sync", e.to_string(), "Check AWS credentials and the bucket region"),
+                Err(_) => Check::fail("sync", format!("s3://{} timed out", bucket), "Check network access to S3"),
+            }
+        }
+        SyncBackend::Supabase { url } => {
+            if !sync::is_supabase_available() {
+                return Check::fail(
+                    "sync",
+                    "supabase backend configured but this binary was built without Supabase support",
+                    "Rebuild with 'cargo install --features supabase'",
+                );
+            }
+            if std::env::var_os("MANA_SUPABASE_KEY").is_none() {
+                return Check::fail("sync", "MANA_SUPABASE_KEY is not set", "Export MANA_SUPABASE_KEY with your project's API key");
+            }
+            match tokio::time::timeout(NETWORK_TIMEOUT, sync::supabase_status(mana_dir)).await {
+                Ok(Ok(status)) if status.connected => Check::pass("sync", format!("supabase {} reachable", url)),
+                Ok(Ok(_)) => Check::fail("sync", format!("Not connected to {}", url), "Check the project URL in sync.toml"),
+                Ok(Err(e)) => Check::fail("sync", e.to_string(), "Check the project URL and MANA_SUPABASE_KEY"),
+                Err(_) => Check::fail("sync", format!("{} timed out", url), "Check network access to Supabase"),
+            }
+        }
+        SyncBackend::Ssh { host, path } => match sync::ssh_status(mana_dir) {
+            Ok(status) if status.reachable => Check::pass("sync", format!("ssh {}:{} reachable", host, path)),
+            Ok(status) => Check::fail(
+                "sync",
+                status.error.unwrap_or_else(|| format!("Cannot reach {}", host)),
+                format!("Check that 'ssh {}' works without a password prompt", host),
+            ),
+            Err(e) => Check::fail("sync", e.to_string(), "Re-run 'mana sync init --backend ssh --host <user@host:/path>'"),
+        },
+        SyncBackend::P2P { .. } => {
+            let peers = match sync::p2p_status(mana_dir) {
+                Ok(status) => status.peers,
+                Err(e) => return Check::fail("sync", e.to_string(), "Fix p2p.toml or re-run 'mana sync init --backend p2p'"),
+            };
+            if peers.is_empty() {
+                return Check::warn("sync", "p2p backend has no peers", "Add one with 'mana sync peer add <host:port>'");
+            }
+            let unreachable: Vec<&str> =
+                peers.iter().map(|p| p.address.as_str()).filter(|addr| !peer_reachable(addr)).collect();
+            if unreachable.is_empty() {
+                Check::pass("sync", format!("all {} p2p peers reachable", peers.len()))
+            } else {
+                let detail = format!("{}/{} peers unreachable: {}", unreachable.len(), peers.len(), unreachable.join(", "));
+                let fix = "Check the peers are running and the addresses are current ('mana sync peer list')";
+                if unreachable.len() == peers.len() {
+                    Check::fail("sync", detail, fix)
+                } else {
+                    Check::warn("sync", detail, fix)
+                }
+            }
+        }
+    }
+}
+
+/// Query a git remote without prompting for credentials
+fn git_ls_remote(cwd: &Path, remote: &str) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(cwd).args(["ls-remote", "--heads", remote]).env("GIT_TERMINAL_PROMPT", "0");
+    if std::env::var_os("GIT_SSH_COMMAND").is_none() {
+        cmd.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes -o ConnectTimeout=5");
+    }
+
+    let output = cmd.output().context("Failed to execute git")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{}", stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("git ls-remote failed").trim());
+    }
+    Ok(())
+}
+
+fn peer_reachable(address: &str) -> bool {
+    address
+        .to_socket_addrs()
+        .map(|mut addrs| addrs.any(|addr| TcpStream::connect_timeout(&addr, PEER_TIMEOUT).is_ok()))
+        .unwrap_or(false)
+}
+
+fn check_log_dirs(config: &ManaConfig) -> Check {
+    let (readable, problems): (Vec<_>, Vec<_>) = config
+        .learning
+        .resolved_log_dirs()
+        .into_iter()
+        .map(|dir| std::fs::read_dir(&dir).map(|_| dir.clone()).map_err(|e| format!("{}: {}", dir.display(), e)))
+        .partition(|r| r.is_ok());
+    let readable: Vec<String> = readable.into_iter().flatten().map(|d| d.display().to_string()).collect();
+    let problems: Vec<String> = problems.into_iter().filter_map(|r| r.err()).collect();
+
+    let fix = format!(
+        "Run a Claude Code session to create the log directory, or point learning.log_dirs / {} at your logs",
+        config::LOG_DIRS_ENV
+    );
+    if problems.is_empty() {
+        Check::pass("logs", readable.join(", "))
+    } else if readable.is_empty() {
+        Check::fail("logs", problems.join("; "), fix)
+    } else {
+        Check::warn("logs", problems.join("; "), fix)
+    }
+}
+
+fn check_version(offline: bool) -> Check {
+    let current = env!("CARGO_PKG_VERSION");
+    if offline {
+        return Check::skip("version", format!("v{} (latest not checked, --offline)", current));
+    }
+    match update::latest_version() {
+        Ok(latest) if update::is_newer_version(&latest, current) => Check::warn(
+            "version",
+            format!("v{} installed, v{} available", current, latest),
+            "Run 'mana update --force'",
+        ),
+        Ok(_) => Check::pass("version", format!("v{} (latest)", current)),
+        Err(e) => Check::warn(
+            "version",
+            format!("v{} (could not check latest release: {})", current, e),
+            "Install and authenticate the GitHub CLI (gh) to enable update checks",
+        ),
+    }
+}
+
+/// Print checks as a human-readable report
+pub fn print_report(checks: &[Check]) {
+    println!("MANA Doctor");
+    println!("===========");
+    println!();
+
+    for check in checks {
+        let icon = match check.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+            CheckStatus::Skip => "➖",
+        };
+        println!("{} {:<11} {}", icon, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("   → {}", fix);
+        }
+    }
+
+    let count = |status: CheckStatus| checks.iter().filter(|c| c.status == status).count();
+    println!();
+    println!(
+        "{} passed, {} warnings, {} failed",
+        count(CheckStatus::Pass),
+        count(CheckStatus::Warn),
+        count(CheckStatus::Fail)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_database_and_embedding_checks() {
+        let temp = TempDir::new().unwrap();
+        assert_eq!(check_database(temp.path())[0].status, CheckStatus::Fail);
+
+        let conn = db::open(&temp.path().join("metadata.sqlite")).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (1, 'a', 'Bash', 'cargo build');",
+        )
+        .unwrap();
+
+        let checks = check_database(temp.path());
+        assert!(checks.iter().all(|c| c.status == CheckStatus::Pass), "{:?}", checks);
+        assert_eq!(check_embeddings(temp.path()).status, CheckStatus::Skip);
+
+        // A vector for a pattern that no longer exists is flagged
+        let mut index = VectorIndex::new(2);
+        index.add(1, &[1.0, 0.0]).unwrap();
+        index.add(99, &[0.0, 1.0]).unwrap();
+        index.save(&temp.path().join("vectors.usearch")).unwrap();
+        let check = check_embeddings(temp.path());
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.starts_with("1 vectors for deleted patterns"), "{}", check.detail);
+    }
+
+    #[test]
+    fn test_hooks_check() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("settings.json");
+        assert_eq!(check_hooks(std::slice::from_ref(&path)).status, CheckStatus::Fail);
+
+        std::fs::write(
+            &path,
+            r#"{"hooks": {"PreToolUse": [{"matcher": "Bash", "hooks": [{"type": "command", "command": "mana inject --tool bash"}]}]}}"#,
+        )
+        .unwrap();
+        let check = check_hooks(std::slice::from_ref(&path));
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.fix.is_some());
+    }
+}
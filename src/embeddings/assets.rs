@@ -0,0 +1,189 @@
+//! Versioned embedding model assets
+//!
+//! Model files listed in an asset manifest are installed under
+//! `.mana/models/<name>/<version>/`, and `.mana/models/<name>/current` names
+//! the version in use. Every file is checked against its SHA-256 before the
+//! version directory is moved into place, so a partial download never
+//! becomes current.
+//!
+//! The embedding store reports the installed version as its model version,
+//! which is what `embedding_meta` records for the index. A version change
+//! therefore means the index was built by another model and must be rebuilt.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::update::sha256_file;
+
+/// Models a release ships weights for
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetManifest {
+    pub models: Vec<ModelAsset>,
+}
+
+impl AssetManifest {
+    pub fn parse(text: &str) -> Result<Self> {
+        serde_json::from_str(text).context("Invalid asset manifest")
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ModelAsset> {
+        self.models.iter().find(|m| m.name == name)
+    }
+}
+
+/// One version of a model's files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelAsset {
+    pub name: String,
+    pub version: String,
+    pub files: Vec<AssetFile>,
+}
+
+/// A model file and where to get it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetFile {
+    pub name: String,
+    /// http(s) or file:// URL, or an absolute path
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Outcome of [`install`]
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetInstall {
+    pub name: String,
+    pub version: String,
+    /// Version that was current before
+    pub previous: Option<String>,
+    /// Files downloaded (0 when the version was already installed)
+    pub downloaded: usize,
+}
+
+impl AssetInstall {
+    /// Whether the current version changed, invalidating the vector index
+    pub fn changed(&self) -> bool {
+        self.previous.as_deref() != Some(self.version.as_str())
+    }
+}
+
+/// Directory holding all versions of a model
+pub fn model_dir(mana_dir: &Path, name: &str) -> PathBuf {
+    mana_dir.join("models").join(name)
+}
+
+/// Version of `name` currently in use, if its assets are installed
+pub fn installed_version(mana_dir: &Path, name: &str) -> Option<String> {
+    let version = fs::read_to_string(model_dir(mana_dir, name).join("current")).ok()?;
+    let version = version.trim();
+    model_dir(mana_dir, name).join(version).is_dir().then(|| version.to_string())
+}
+
+/// Install a model version and make it current
+///
+/// A version that's already installed and intact isn't downloaded again.
+pub fn install(mana_dir: &Path, asset: &ModelAsset) -> Result<AssetInstall> {
+    let names = [&asset.name, &asset.version].into_iter().chain(asset.files.iter().map(|f| &f.name));
+    if let Some(bad) = names.into_iter().find(|name| !is_plain(name)) {
+        bail!("Invalid name {:?} in asset manifest", bad);
+    }
+    let dir = model_dir(mana_dir, &asset.name);
+    let target = dir.join(&asset.version);
+    let previous = installed_version(mana_dir, &asset.name);
+
+    let mut downloaded = 0;
+    if !is_intact(&target, asset) {
+        let staging = dir.join(format!(".{}.partial", asset.version));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(&staging)?;
+        for file in &asset.files {
+            let dest = staging.join(&file.name);
+            let fetched = download(&file.url, &dest).and_then(|_| check(&dest, file));
+            if let Err(e) = fetched {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(e);
+            }
+            downloaded += 1;
+        }
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(&staging, &target)?;
+    }
+    fs::write(dir.join("current"), format!("{}\n", asset.version))?;
+
+    Ok(AssetInstall { name: asset.name.clone(), version: asset.version.clone(), previous, downloaded })
+}
+
+/// Whether `name` is safe to use as a single path component
+fn is_plain(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
+
+/// Whether every file of `asset` is present in `dir` with the right checksum
+fn is_intact(dir: &Path, asset: &ModelAsset) -> bool {
+    dir.is_dir() && asset.files.iter().all(|file| check(&dir.join(&file.name), file).is_ok())
+}
+
+fn check(path: &Path, file: &AssetFile) -> Result<()> {
+    let actual = sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(&file.sha256) {
+        return Err(crate::ci::fail(
+            crate::ci::ExitCode::Verification,
+            format!("Checksum mismatch for {}: expected {}, got {}", file.name, file.sha256, actual),
+        ));
+    }
+    Ok(())
+}
+
+/// Fetch an http(s) URL, or copy a file:// URL or path
+pub(crate) fn download(url: &str, dest: &Path) -> Result<()> {
+    if url.starts_with("https://") || url.starts_with("http://") {
+        crate::net::download(url, dest).with_context(|| format!("Failed to download {}", url))?;
+        return Ok(());
+    }
+    let path = url.strip_prefix("file://").unwrap_or(url);
+    fs::copy(path, dest).with_context(|| format!("Failed to read {}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_install_verifies_and_switches_versions() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("weights.bin");
+        fs::write(&source, "weights").unwrap();
+        let asset = |version: &str, sha256: String| ModelAsset {
+            name: "gte-small".to_string(),
+            version: version.to_string(),
+            files: vec![AssetFile { name: "model.bin".to_string(), url: source.display().to_string(), sha256 }],
+        };
+        let good = sha256_file(&source).unwrap();
+        let mana_dir = temp.path().join(".mana");
+
+        let bad = install(&mana_dir, &asset("1.0.0", "0".repeat(64)));
+        assert!(bad.unwrap_err().to_string().contains("Checksum mismatch"));
+        assert_eq!(installed_version(&mana_dir, "gte-small"), None);
+
+        let first = install(&mana_dir, &asset("1.0.0", good.clone())).unwrap();
+        assert!(first.changed());
+        assert_eq!(first.downloaded, 1);
+        assert!(mana_dir.join("models/gte-small/1.0.0/model.bin").is_file());
+
+        let again = install(&mana_dir, &asset("1.0.0", good.clone())).unwrap();
+        assert_eq!((again.changed(), again.downloaded), (false, 0));
+
+        let upgrade = install(&mana_dir, &asset("1.1.0", good)).unwrap();
+        assert_eq!(upgrade.previous.as_deref(), Some("1.0.0"));
+        assert!(upgrade.changed());
+        assert_eq!(installed_version(&mana_dir, "gte-small").as_deref(), Some("1.1.0"));
+        assert!(install(&mana_dir, &asset("../escape", sha256_file(&source).unwrap())).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Background embedding of new patterns
+//!
+//! Learning inserts patterns without an embedding, and semantic search
+//! can't find them until they get one. Those patterns are the queue:
+//! consolidation's embed stage and the daemon work through it in batches of
+//! `[embeddings] auto_embed_batch`, pausing after each batch so embedding
+//! uses at most `auto_embed_cpu` of a core, and stop after
+//! `auto_embed_max_secs` of work. What's left waits for the next run.
+//!
+//! Nothing runs until an index exists: `mana embed generate` opts in.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use super::EmbeddingStore;
+use crate::config::EmbeddingsConfig;
+
+/// How much embedding a run may do
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub batch: usize,
+    /// Share of one core, between 0 (exclusive) and 1
+    pub cpu: f64,
+    /// Embedding time per run
+    pub max_busy: Duration,
+}
+
+impl Limits {
+    pub fn from_config(config: &EmbeddingsConfig) -> Self {
+        Self {
+            batch: config.auto_embed_batch.max(1),
+            cpu: config.auto_embed_cpu.clamp(0.01, 1.0),
+            max_busy: Duration::from_secs(config.auto_embed_max_secs),
+        }
+    }
+
+    /// Pause after a batch that took `busy`, keeping to the CPU share
+    fn pause_after(&self, busy: Duration) -> Duration {
+        busy.mul_f64(1.0 / self.cpu - 1.0)
+    }
+}
+
+/// What a run embedded
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackfillReport {
+    pub embedded: usize,
+    pub batches: usize,
+    /// Patterns still without an embedding
+    pub remaining: usize,
+    /// Time spent embedding, pauses excluded
+    pub busy_ms: u64,
+}
+
+/// Patterns waiting for an embedding
+pub fn pending(conn: &Connection) -> usize {
+    conn.query_row("SELECT COUNT(*) FROM patterns WHERE embedding IS NULL", [], |row| row.get::<_, i64>(0))
+        .map_or(0, |n| n.max(0) as usize)
+}
+
+/// Whether background embedding should run for `mana_dir`
+pub fn enabled(mana_dir: &Path) -> bool {
+    crate::config::load_config(mana_dir).embeddings.auto_embed && super::is_available(mana_dir)
+}
+
+/// Embed queued patterns within `limits`, saving the index once at the end
+pub fn run(mana_dir: &Path, limits: Limits) -> Result<BackfillReport> {
+    let _lock = super::lock::acquire(mana_dir)?;
+    let mut store = EmbeddingStore::open(mana_dir)?;
+    let mut report = BackfillReport::default();
+    let mut busy = Duration::ZERO;
+
+    while busy < limits.max_busy {
+        let started = Instant::now();
+        let embedded = store.embed_next(limits.batch)?;
+        let took = started.elapsed();
+        busy += took;
+        if embedded == 0 {
+            break;
+        }
+        report.embedded += embedded;
+        report.batches += 1;
+        if embedded < limits.batch {
+            break;
+        }
+        std::thread::sleep(limits.pause_after(took));
+    }
+
+    if report.embedded > 0 {
+        store.save_index()?;
+    }
+    report.busy_ms = busy.as_millis() as u64;
+    report.remaining = pending(&crate::storage::db::open_readonly(&mana_dir.join("metadata.sqlite"))?);
+    debug!(
+        "Embedded {} patterns in {} batches ({}ms), {} left",
+        report.embedded, report.batches, report.busy_ms, report.remaining
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backfill_embeds_in_batches() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let conn = crate::storage::db::open(&temp.path().join("metadata.sqlite")).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        for i in 0..5 {
+            conn.execute(
+                "INSERT INTO patterns (pattern_hash, tool_type, context_query) VALUES (?1, 'Bash', ?2)",
+                rusqlite::params![format!("h{}", i), format!("cargo build step {}", i)],
+            )
+            .unwrap();
+        }
+        super::super::init(temp.path(), &super::super::EmbeddingConfig::default()).unwrap();
+        assert_eq!(pending(&conn), 5);
+
+        let limits = Limits { batch: 2, cpu: 1.0, max_busy: Duration::from_secs(60) };
+        let report = run(temp.path(), limits).unwrap();
+        assert_eq!((report.embedded, report.batches, report.remaining), (5, 3, 0));
+        assert_eq!(EmbeddingStore::open(temp.path()).unwrap().index().len(), 5);
+
+        // A half-share pauses as long as it worked
+        let half = Limits { cpu: 0.5, ..limits };
+        assert_eq!(half.pause_after(Duration::from_millis(40)), Duration::from_millis(40));
+    }
+}
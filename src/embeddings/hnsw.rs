@@ -0,0 +1,263 @@
+//! Hierarchical navigable small world (HNSW) graph
+//!
+//! Approximate nearest neighbor search over the vectors held by
+//! `VectorIndex`. The graph is built in memory on demand (the daemon builds
+//! it once at startup) and is never persisted; the on-disk index format is
+//! unchanged.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+use super::model::cosine_similarity;
+
+/// Tunable HNSW parameters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HnswParams {
+    /// Max neighbors per node on upper layers (layer 0 keeps 2*M)
+    pub m: usize,
+    /// Candidate list size while inserting (build quality vs build time)
+    pub ef_construction: usize,
+    /// Candidate list size while searching (recall vs latency)
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+/// Distance-ordered candidate (smaller distance = closer)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .partial_cmp(&other.distance)
+            .unwrap_or(Ordering::Equal)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+/// HNSW graph over a flattened vector buffer
+pub struct HnswGraph {
+    params: HnswParams,
+    dimensions: usize,
+    /// neighbors[node][layer] = adjacent nodes
+    neighbors: Vec<Vec<Vec<usize>>>,
+    entry_point: Option<usize>,
+    max_level: usize,
+}
+
+impl HnswGraph {
+    /// Build a graph over `vectors` (flattened, `dimensions` floats per node)
+    pub fn build(vectors: &[f32], dimensions: usize, params: HnswParams) -> Self {
+        let mut graph = Self {
+            params,
+            dimensions,
+            neighbors: Vec::new(),
+            entry_point: None,
+            max_level: 0,
+        };
+
+        // Fixed seed keeps builds reproducible for tuning runs
+        let mut rng = StdRng::seed_from_u64(0x4d414e41);
+        let count = vectors.len().checked_div(dimensions).unwrap_or(0);
+        for node in 0..count {
+            graph.insert(vectors, node, &mut rng);
+        }
+        graph
+    }
+
+    /// Parameters the graph was built with
+    pub fn params(&self) -> HnswParams {
+        self.params
+    }
+
+    /// Search for the k nearest nodes, returning (node, similarity) best first
+    pub fn search(&self, vectors: &[f32], query: &[f32], k: usize, ef_search: usize) -> Vec<(usize, f32)> {
+        let Some(mut entry) = self.entry_point else {
+            return Vec::new();
+        };
+
+        for layer in (1..=self.max_level).rev() {
+            entry = self.search_layer(vectors, query, &[entry], 1, layer)[0].node;
+        }
+
+        let mut found = self.search_layer(vectors, query, &[entry], ef_search.max(k), 0);
+        found.truncate(k);
+        found.into_iter().map(|c| (c.node, 1.0 - c.distance)).collect()
+    }
+
+    fn vector<'a>(&self, vectors: &'a [f32], node: usize) -> &'a [f32] {
+        &vectors[node * self.dimensions..(node + 1) * self.dimensions]
+    }
+
+    fn distance(&self, vectors: &[f32], query: &[f32], node: usize) -> f32 {
+        1.0 - cosine_similarity(query, self.vector(vectors, node))
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 { self.params.m * 2 } else { self.params.m }
+    }
+
+    fn random_level(&self, rng: &mut StdRng) -> usize {
+        let ml = 1.0 / (self.params.m.max(2) as f64).ln();
+        let r: f64 = rng.gen_range(f64::EPSILON..1.0);
+        (-r.ln() * ml).floor() as usize
+    }
+
+    fn insert(&mut self, vectors: &[f32], node: usize, rng: &mut StdRng) {
+        let level = self.random_level(rng);
+        self.neighbors.push(vec![Vec::new(); level + 1]);
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(node);
+            self.max_level = level;
+            return;
+        };
+
+        let query = self.vector(vectors, node).to_vec();
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.search_layer(vectors, &query, &[entry], 1, layer)[0].node;
+        }
+
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let found = self.search_layer(vectors, &query, &[entry], self.params.ef_construction, layer);
+            let limit = self.max_neighbors(layer);
+            let selected: Vec<usize> = found.iter().take(limit).map(|c| c.node).collect();
+
+            for &neighbor in &selected {
+                self.neighbors[neighbor][layer].push(node);
+                if self.neighbors[neighbor][layer].len() > limit {
+                    self.prune(vectors, neighbor, layer, limit);
+                }
+            }
+            self.neighbors[node][layer] = selected;
+            entry = found[0].node;
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node);
+        }
+    }
+
+    /// Keep only the `limit` closest neighbors of a node on a layer
+    fn prune(&mut self, vectors: &[f32], node: usize, layer: usize, limit: usize) {
+        let base = self.vector(vectors, node).to_vec();
+        let mut scored: Vec<Candidate> = self.neighbors[node][layer]
+            .iter()
+            .map(|&n| Candidate { distance: self.distance(vectors, &base, n), node: n })
+            .collect();
+        scored.sort();
+        scored.truncate(limit);
+        self.neighbors[node][layer] = scored.into_iter().map(|c| c.node).collect();
+    }
+
+    /// Greedy beam search on one layer, returning up to `ef` nodes closest first
+    fn search_layer(&self, vectors: &[f32], query: &[f32], entries: &[usize], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut results: BinaryHeap<Candidate> = BinaryHeap::new();
+
+        for &node in entries {
+            let c = Candidate { distance: self.distance(vectors, query, node), node };
+            candidates.push(Reverse(c));
+            results.push(c);
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let worst = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+            if current.distance > worst && results.len() >= ef {
+                break;
+            }
+
+            let Some(adjacent) = self.neighbors[current.node].get(layer) else {
+                continue;
+            };
+            for &neighbor in adjacent {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let c = Candidate { distance: self.distance(vectors, query, neighbor), node: neighbor };
+                let worst = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+                if results.len() < ef || c.distance < worst {
+                    candidates.push(Reverse(c));
+                    results.push(c);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results.into_sorted_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dim: usize, seed: u64) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut vectors = Vec::with_capacity(count * dim);
+        for _ in 0..count {
+            let v: Vec<f32> = (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            vectors.extend(v.iter().map(|x| x / norm));
+        }
+        vectors
+    }
+
+    #[test]
+    fn test_finds_exact_match() {
+        let dim = 16;
+        let vectors = random_vectors(500, dim, 7);
+        let graph = HnswGraph::build(&vectors, dim, HnswParams::default());
+
+        let query = &vectors[42 * dim..43 * dim];
+        let results = graph.search(&vectors, query, 5, 64);
+        assert_eq!(results[0].0, 42);
+        assert!(results[0].1 > 0.999);
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let dim = 16;
+        let vectors = random_vectors(1000, dim, 7);
+        let graph = HnswGraph::build(&vectors, dim, HnswParams::default());
+
+        let query = random_vectors(1, dim, 99);
+        let mut exact: Vec<(usize, f32)> = (0..1000)
+            .map(|i| (i, cosine_similarity(&query, &vectors[i * dim..(i + 1) * dim])))
+            .collect();
+        exact.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let truth: HashSet<usize> = exact.iter().take(10).map(|(i, _)| *i).collect();
+
+        let found = graph.search(&vectors, &query, 10, 128);
+        let hits = found.iter().filter(|(i, _)| truth.contains(i)).count();
+        assert!(hits >= 8, "recall too low: {}/10", hits);
+    }
+}
@@ -12,6 +12,7 @@ use std::path::Path;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 
+use super::hnsw::{HnswGraph, HnswParams};
 use super::model::cosine_similarity;
 
 /// A match result from vector search
@@ -53,6 +54,8 @@ pub struct VectorIndex {
     vectors: Vec<f32>,
     /// Dimensions per vector
     dimensions: usize,
+    /// Optional HNSW graph for approximate search (in-memory only)
+    ann: Option<HnswGraph>,
 }
 
 impl VectorIndex {
@@ -62,6 +65,7 @@ impl VectorIndex {
             ids: Vec::new(),
             vectors: Vec::new(),
             dimensions,
+            ann: None,
         }
     }
 
@@ -103,11 +107,24 @@ impl VectorIndex {
             ids,
             vectors,
             dimensions,
+            ann: None,
         })
     }
 
     /// Save index to file
+    ///
+    /// Written to a temporary file beside it and renamed into place, so a
+    /// reader never sees a partial index.
     pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension(format!("usearch.{}.tmp", std::process::id()));
+        let result = self.write_to(&tmp).and_then(|()| Ok(std::fs::rename(&tmp, path)?));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn write_to(&self, path: &Path) -> Result<()> {
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
 
@@ -126,6 +143,7 @@ impl VectorIndex {
         }
 
         writer.flush()?;
+        writer.get_ref().sync_all()?;
         Ok(())
     }
 
@@ -139,6 +157,11 @@ impl VectorIndex {
         self.ids.is_empty()
     }
 
+    /// Pattern IDs in index order
+    pub fn ids(&self) -> &[i64] {
+        &self.ids
+    }
+
     /// Get the dimensions of vectors in this index
     pub fn dimensions(&self) -> usize {
         self.dimensions
@@ -156,6 +179,8 @@ impl VectorIndex {
 
         self.ids.push(id);
         self.vectors.extend_from_slice(vector);
+        // Graph doesn't support incremental updates; fall back to exact search
+        self.ann = None;
         Ok(())
     }
 
@@ -166,22 +191,82 @@ impl VectorIndex {
             let start = pos * self.dimensions;
             let end = start + self.dimensions;
             self.vectors.drain(start..end);
+            self.ann = None;
             true
         } else {
             false
         }
     }
 
+    /// Build an HNSW graph so searches become approximate
+    pub fn build_ann(&mut self, params: HnswParams) {
+        self.ann = Some(HnswGraph::build(&self.vectors, self.dimensions, params));
+    }
+
+    /// Whether searches currently use the HNSW graph
+    pub fn has_ann(&self) -> bool {
+        self.ann.is_some()
+    }
+
     /// Search for the k nearest neighbors
+    ///
+    /// Uses the HNSW graph when one has been built, otherwise scans exactly.
     pub fn search(&self, query: &[f32], k: usize) -> Vec<VectorMatch> {
         if query.len() != self.dimensions || self.is_empty() {
             return Vec::new();
         }
 
+        if let Some(ref graph) = self.ann {
+            return self.search_ann(graph, query, k, graph.params().ef_search);
+        }
+
+        self.search_exact(query, k)
+    }
+
+    /// Approximate search with an explicit ef_search (used by tuning)
+    pub fn search_with_ef(&self, graph: &HnswGraph, query: &[f32], k: usize, ef_search: usize) -> Vec<VectorMatch> {
+        self.search_ann(graph, query, k, ef_search)
+    }
+
+    /// Build a standalone graph over this index's vectors (used by tuning)
+    pub fn build_graph(&self, params: HnswParams) -> HnswGraph {
+        HnswGraph::build(&self.vectors, self.dimensions, params)
+    }
+
+    /// Get the vector stored at a position
+    pub fn vector_at(&self, pos: usize) -> Option<&[f32]> {
+        self.vectors.get(pos * self.dimensions..(pos + 1) * self.dimensions)
+    }
+
+    fn search_ann(&self, graph: &HnswGraph, query: &[f32], k: usize, ef_search: usize) -> Vec<VectorMatch> {
+        graph
+            .search(&self.vectors, query, k, ef_search)
+            .into_iter()
+            .map(|(node, similarity)| VectorMatch { id: self.ids[node], similarity })
+            .collect()
+    }
+
+    /// Exact brute-force search
+    pub fn search_exact(&self, query: &[f32], k: usize) -> Vec<VectorMatch> {
+        self.search_filtered(query, k, |_| true)
+    }
+
+    /// Exact search over the vectors whose ids `keep` accepts
+    ///
+    /// Skipped vectors aren't scored. Always a scan: the HNSW graph can't
+    /// route around excluded nodes without losing recall.
+    pub fn search_filtered(&self, query: &[f32], k: usize, keep: impl Fn(i64) -> bool) -> Vec<VectorMatch> {
+        if query.len() != self.dimensions || self.is_empty() {
+            return Vec::new();
+        }
+
         // Use a min-heap to keep track of top-k
         let mut heap: BinaryHeap<VectorMatch> = BinaryHeap::new();
 
         for (i, id) in self.ids.iter().enumerate() {
+            if !keep(*id) {
+                continue;
+            }
             let start = i * self.dimensions;
             let end = start + self.dimensions;
             let vec = &self.vectors[start..end];
@@ -0,0 +1,39 @@
+//! Advisory lock around embedding index writes
+//!
+//! `mana embed generate`, background backfill, consolidation and pattern
+//! deletes all load vectors.usearch, change it and save it back. Two of
+//! them overlapping would each save their own copy and drop the other's
+//! changes, so writers hold this lock from load to save. Saves themselves
+//! go to a temporary file renamed over the index, so readers (the daemon,
+//! searches) never see a half-written file and don't need the lock.
+//!
+//! Like the learning lock it is a [`FileLock`], on
+//! `<mana_dir>/vectors.usearch.lock`.
+
+use anyhow::Result;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::storage::lock::FileLock;
+
+/// Lock file name inside the MANA data directory
+pub const LOCK_FILE: &str = "vectors.usearch.lock";
+
+/// How long a writer waits for another to finish
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Acquire the index lock, waiting up to [`DEFAULT_TIMEOUT`]
+pub fn acquire(mana_dir: &Path) -> Result<FileLock> {
+    acquire_within(mana_dir, DEFAULT_TIMEOUT)
+}
+
+/// Acquire the index lock, failing as busy if it's still held after `timeout`
+pub fn acquire_within(mana_dir: &Path, timeout: Duration) -> Result<FileLock> {
+    crate::storage::read_only::ensure_writable("Saving the embedding index")?;
+    FileLock::acquire_or_busy(
+        mana_dir,
+        LOCK_FILE,
+        timeout,
+        &format!("The embedding index is still being written by another process after {:?}", timeout),
+    )
+}
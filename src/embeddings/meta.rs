@@ -0,0 +1,144 @@
+//! Pattern metadata beside the vector index
+//!
+//! vectors.usearch only holds ids and vectors, so a search restricted to a
+//! tool, category or minimum score would have to look every candidate up
+//! in SQLite. `vectors.meta.json` keeps each indexed pattern's tool type,
+//! category and score, so the index can skip non-matching vectors before
+//! scoring them. It's rewritten whenever the index is saved or the
+//! injection snapshot is rebuilt; scores change in between, so matches are
+//! still checked against the database before they're returned.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Sidecar file name inside the MANA data directory
+pub const META_FILE: &str = "vectors.meta.json";
+
+/// What the sidecar records per pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorMeta {
+    pub tool_type: String,
+    pub category: Option<String>,
+    /// Successes minus failures
+    pub score: i64,
+}
+
+/// Constraints on a semantic search
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    /// Tool type, case-insensitive
+    pub tool: Option<String>,
+    /// Command category, case-insensitive
+    pub category: Option<String>,
+    pub min_score: Option<i64>,
+}
+
+impl SearchFilter {
+    pub fn is_empty(&self) -> bool {
+        self.tool.is_none() && self.category.is_none() && self.min_score.is_none()
+    }
+
+    pub fn matches(&self, meta: &VectorMeta) -> bool {
+        let same = |want: &Option<String>, have: Option<&str>| {
+            want.as_deref().is_none_or(|want| have.is_some_and(|have| have.eq_ignore_ascii_case(want)))
+        };
+        same(&self.tool, Some(&meta.tool_type))
+            && same(&self.category, meta.category.as_deref())
+            && self.min_score.is_none_or(|min| meta.score >= min)
+    }
+}
+
+/// Metadata for every pattern, keyed by id
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub patterns: HashMap<i64, VectorMeta>,
+}
+
+impl Sidecar {
+    /// Read the sidecar, None if it's missing or unreadable
+    pub fn load(mana_dir: &Path) -> Option<Self> {
+        let bytes = std::fs::read(mana_dir.join(META_FILE)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Current metadata for every pattern in the database
+    pub fn build(conn: &Connection) -> Result<Self> {
+        let category = if crate::storage::has_column(conn, "patterns", "command_category") {
+            "command_category"
+        } else {
+            "NULL"
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, tool_type, {}, success_count - failure_count FROM patterns",
+            category
+        ))?;
+        let patterns = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, VectorMeta { tool_type: row.get(1)?, category: row.get(2)?, score: row.get(3)? }))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Self { patterns })
+    }
+
+    /// Write atomically, so searches never read half a file
+    pub fn save(&self, mana_dir: &Path) -> Result<()> {
+        let path = mana_dir.join(META_FILE);
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Whether a pattern may match; unknown patterns may, the database decides
+    pub fn admits(&self, id: i64, filter: &SearchFilter) -> bool {
+        self.patterns.get(&id).is_none_or(|meta| filter.matches(meta))
+    }
+}
+
+/// Rewrite the sidecar from the database, if there's an index to go with it
+/// and the database isn't encrypted
+pub fn refresh(mana_dir: &Path, conn: &Connection) -> Result<()> {
+    if !mana_dir.join("vectors.usearch").exists() {
+        return Ok(());
+    }
+    // Like the injection snapshot, nothing from an encrypted database is kept in the clear
+    if crate::storage::encryption::is_encrypted(&mana_dir.join("metadata.sqlite")) {
+        let _ = std::fs::remove_file(mana_dir.join(META_FILE));
+        return Ok(());
+    }
+    Sidecar::build(conn)?.save(mana_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_roundtrip_and_filter() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'cargo', 'cargo build', 5, 1), (2, 'b', 'Bash', 'npm', 'npm test', 0, 2), (3, 'c', 'Edit', 'rs', 'lib.rs', 3, 0);",
+        )
+        .unwrap();
+
+        // No index, no sidecar
+        refresh(temp.path(), &conn).unwrap();
+        assert!(Sidecar::load(temp.path()).is_none());
+        std::fs::write(temp.path().join("vectors.usearch"), b"").unwrap();
+        refresh(temp.path(), &conn).unwrap();
+        let sidecar = Sidecar::load(temp.path()).unwrap();
+        assert_eq!(sidecar.patterns[&1], VectorMeta { tool_type: "Bash".into(), category: Some("cargo".into()), score: 4 });
+
+        let filter = SearchFilter { tool: Some("bash".into()), min_score: Some(2), ..Default::default() };
+        let admitted: Vec<i64> = (1..=4).filter(|&id| sidecar.admits(id, &filter)).collect();
+        assert_eq!(admitted, vec![1, 4]);
+        let rs = SearchFilter { category: Some("RS".into()), ..Default::default() };
+        assert!(sidecar.admits(3, &rs) && !sidecar.admits(1, &rs));
+    }
+}
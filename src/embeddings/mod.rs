@@ -9,15 +9,26 @@
 //! - EmbeddingStore: Manages embedding persistence and caching
 
 use anyhow::Result;
+use std::collections::HashSet;
 use std::path::Path;
+use serde::Serialize;
 
 mod model;
 mod index;
 mod store;
+pub mod assets;
+pub mod backfill;
+pub mod hnsw;
+pub mod lock;
+pub mod meta;
+pub mod rerank;
+pub mod tune;
 
-pub use model::EmbeddingModel;
+pub use model::{cosine_similarity, EmbeddingModel};
 pub use index::VectorIndex;
 pub use store::EmbeddingStore;
+pub use meta::SearchFilter;
+pub use rerank::Reranker;
 
 /// Embedding dimensions for the default model (gte-small)
 pub const EMBEDDING_DIM: usize = 384;
@@ -35,6 +46,10 @@ pub struct EmbeddingConfig {
     /// Whether to cache embeddings
     #[allow(dead_code)] // Reserved for future cache configuration
     pub cache_embeddings: bool,
+    /// HNSW parameters for approximate search
+    pub hnsw: hnsw::HnswParams,
+    /// Minimum index size before the HNSW graph is used instead of exact search
+    pub ann_min_vectors: usize,
 }
 
 impl Default for EmbeddingConfig {
@@ -44,6 +59,8 @@ impl Default for EmbeddingConfig {
             dimensions: EMBEDDING_DIM,
             batch_size: 32,
             cache_embeddings: true,
+            hnsw: hnsw::HnswParams::default(),
+            ann_min_vectors: 10_000,
         }
     }
 }
@@ -66,7 +83,7 @@ pub fn status(mana_dir: &Path) -> Result<EmbeddingStatus> {
 }
 
 /// Embedding system status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct EmbeddingStatus {
     /// Whether the embedding system is initialized
     #[allow(dead_code)] // Available for status checks
@@ -93,6 +110,7 @@ pub fn search(mana_dir: &Path, query: &str, k: usize) -> Result<Vec<(i64, f32)>>
 
 /// Delete a pattern from the vector index
 pub fn delete_from_index(mana_dir: &Path, pattern_id: i64) -> Result<bool> {
+    let _lock = lock::acquire(mana_dir)?;
     let mut store = EmbeddingStore::open(mana_dir)?;
     let removed = store.remove_pattern(pattern_id);
     if removed {
@@ -101,6 +119,46 @@ pub fn delete_from_index(mana_dir: &Path, pattern_id: i64) -> Result<bool> {
     Ok(removed)
 }
 
+/// Drop vectors of patterns not in `live`, returning how many (or with `dry_run`, would be)
+pub fn remove_orphans(mana_dir: &Path, live: &HashSet<i64>, dry_run: bool) -> Result<usize> {
+    let _lock = if dry_run { None } else { Some(lock::acquire(mana_dir)?) };
+    let mut store = EmbeddingStore::open(mana_dir)?;
+    let orphans: Vec<i64> = store.index().ids().iter().copied().filter(|id| !live.contains(id)).collect();
+    if !dry_run && !orphans.is_empty() {
+        for id in &orphans {
+            store.remove_pattern(*id);
+        }
+        store.save_index()?;
+    }
+    Ok(orphans.len())
+}
+
+/// Identity of the index file as last saved, to notice other processes' saves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexStamp {
+    inode: u64,
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+}
+
+/// The index file's current stamp, None if there's no index
+///
+/// Saves rename a new file into place, so the inode alone tells them apart.
+pub fn index_stamp(mana_dir: &Path) -> Option<IndexStamp> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::metadata(mana_dir.join("vectors.usearch")).ok()?;
+    Some(IndexStamp { inode: meta.ino(), modified: meta.modified().ok(), len: meta.len() })
+}
+
+/// Replace a pattern's embedding after its content changed
+pub fn reembed_pattern(mana_dir: &Path, pattern_id: i64, context_query: &str) -> Result<()> {
+    let _lock = lock::acquire(mana_dir)?;
+    let mut store = EmbeddingStore::open(mana_dir)?;
+    store.remove_pattern(pattern_id);
+    store.add_pattern(pattern_id, context_query)?;
+    store.save_index()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
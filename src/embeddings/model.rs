@@ -55,6 +55,11 @@ impl EmbeddingModel {
         &self.version
     }
 
+    /// Report the version of the installed model assets
+    pub fn set_version(&mut self, version: String) {
+        self.version = version;
+    }
+
     /// Get embedding dimensions
     pub fn dimensions(&self) -> usize {
         self.dimensions
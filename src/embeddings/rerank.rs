@@ -0,0 +1,173 @@
+//! Second-stage reranking of embedding search results
+//!
+//! Bi-encoder similarity compares a query and a pattern that were embedded
+//! separately, so near-misses that share vocabulary rank close to real
+//! matches. When `[rerank] backend` is set, the daemon passes its top
+//! `candidates` results through a model that reads query and pattern
+//! together (a cross-encoder server, or an LLM on Ollama asked for
+//! relevance scores) and injects the best few by that score instead.
+//!
+//! Only the daemon reranks: the cold hook path can't afford a network
+//! round trip. Any failure or timeout keeps the bi-encoder order.
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::debug;
+
+use crate::config::{RerankConfig, RerankKind};
+
+const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+
+/// Characters of each candidate sent to the reranker
+const CANDIDATE_CHARS: usize = 300;
+
+/// One entry of a cross-encoder `/rerank` response
+#[derive(Debug, Deserialize)]
+struct RankedText {
+    index: usize,
+    score: f64,
+}
+
+/// Configured reranking backend
+#[derive(Debug, Clone)]
+pub struct Reranker {
+    kind: RerankKind,
+    endpoint: String,
+    model: String,
+    candidates: usize,
+    timeout: Duration,
+}
+
+impl Reranker {
+    /// Build a reranker from config, or None if reranking is off
+    pub fn from_config(config: &RerankConfig) -> Option<Self> {
+        let base = |default: &str| config.url.as_deref().unwrap_or(default).trim_end_matches('/').to_string();
+        let endpoint = match config.backend {
+            RerankKind::None => return None,
+            RerankKind::CrossEncoder => format!("{}/rerank", base("")),
+            RerankKind::Ollama => format!("{}/api/generate", base(DEFAULT_OLLAMA_URL)),
+        };
+        Some(Self {
+            kind: config.backend,
+            endpoint,
+            model: config.model.clone().unwrap_or_else(|| DEFAULT_OLLAMA_MODEL.to_string()),
+            candidates: config.candidates,
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+
+    /// How many search results to pass in
+    pub fn candidates(&self) -> usize {
+        self.candidates
+    }
+
+    /// Relevance of each document to `query`, in input order, higher is better
+    pub fn score(&self, query: &str, documents: &[&str], timeout: Duration) -> Result<Vec<f64>> {
+        let documents: Vec<String> = documents.iter().map(|d| d.chars().take(CANDIDATE_CHARS).collect()).collect();
+        let scores = match self.kind {
+            RerankKind::CrossEncoder => {
+                let body = json!({ "query": query, "texts": documents, "truncate": true });
+                let ranked: Vec<RankedText> =
+                    serde_json::from_value(crate::net::post_json(&self.endpoint, &[], &body, timeout)?)?;
+                let mut scores = vec![f64::NEG_INFINITY; documents.len()];
+                for r in ranked {
+                    if let Some(score) = scores.get_mut(r.index) {
+                        *score = r.score;
+                    }
+                }
+                scores
+            }
+            RerankKind::Ollama => {
+                let body = json!({
+                    "model": self.model,
+                    "prompt": build_prompt(query, &documents),
+                    "stream": false,
+                    "format": "json",
+                });
+                let response = crate::net::post_json(&self.endpoint, &[], &body, timeout)?;
+                let text = response
+                    .get("response")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow!("unexpected Ollama response shape"))?;
+                parse_scores(text, documents.len()).ok_or_else(|| anyhow!("unparseable rerank scores"))?
+            }
+            RerankKind::None => return Err(anyhow!("reranking disabled")),
+        };
+        Ok(scores)
+    }
+
+    /// Reorder `items` by reranker score, best first
+    ///
+    /// Waits at most the configured timeout or `remaining`, whichever is
+    /// shorter; on any failure the items come back in their original order.
+    pub fn rerank<T>(&self, query: &str, items: Vec<T>, text: impl Fn(&T) -> &str, remaining: Duration) -> Vec<T> {
+        if items.len() < 2 || remaining.is_zero() {
+            return items;
+        }
+        let documents: Vec<&str> = items.iter().map(&text).collect();
+        let scores = match self.score(query, &documents, self.timeout.min(remaining)) {
+            Ok(scores) => scores,
+            Err(e) => {
+                debug!("Rerank failed, keeping similarity order: {}", e);
+                return items;
+            }
+        };
+        let mut scored: Vec<(f64, T)> = scores.into_iter().zip(items).collect();
+        // Stable, so ties keep the bi-encoder order
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+/// Ask for one 0-10 relevance score per numbered candidate
+fn build_prompt(query: &str, documents: &[String]) -> String {
+    let mut prompt = format!(
+        "Rate how useful each stored coding pattern is for the current tool call, from 0 (irrelevant) \
+         to 10 (exactly what is needed).\n\nCurrent tool call: {}\n\nPatterns:\n",
+        query
+    );
+    for (i, document) in documents.iter().enumerate() {
+        prompt.push_str(&format!("{}. {}\n", i + 1, document.replace('\n', " ")));
+    }
+    prompt.push_str(&format!(
+        "\nRespond with only a JSON object holding {} scores in pattern order: {{\"scores\": [..]}}",
+        documents.len()
+    ));
+    prompt
+}
+
+/// Read `{"scores": [..]}`, tolerating surrounding prose; None unless there's one score per candidate
+fn parse_scores(text: &str, expected: usize) -> Option<Vec<f64>> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let parsed: Value = serde_json::from_str(text.get(start..=end)?).ok()?;
+    let scores: Vec<f64> = parsed.get("scores")?.as_array()?.iter().map(Value::as_f64).collect::<Option<_>>()?;
+    (scores.len() == expected).then_some(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scores_and_fallback_order() {
+        assert_eq!(parse_scores("Sure: {\"scores\": [2, 9.5, 0]}", 3), Some(vec![2.0, 9.5, 0.0]));
+        assert_eq!(parse_scores("{\"scores\": [2, 9]}", 3), None);
+        assert_eq!(parse_scores("no idea", 1), None);
+
+        assert!(Reranker::from_config(&RerankConfig::default()).is_none());
+        // Nothing listens on the discard port: the similarity order is kept
+        let config = RerankConfig {
+            backend: RerankKind::CrossEncoder,
+            url: Some("http://127.0.0.1:9".into()),
+            ..Default::default()
+        };
+        let reranker = Reranker::from_config(&config).unwrap();
+        assert_eq!(reranker.candidates(), 20);
+        let items = vec!["cargo build", "npm test", "ls"];
+        assert_eq!(reranker.rerank("build", items.clone(), |s| s, Duration::from_secs(1)), items);
+    }
+}
@@ -9,9 +9,11 @@
 #![allow(dead_code)] // Many methods reserved for future embedding operations
 
 use anyhow::Result;
-use rusqlite::{Connection, params};
+use rusqlite::params;
 use std::path::{Path, PathBuf};
+use tracing::warn;
 
+use super::meta::{SearchFilter, Sidecar, VectorMeta};
 use super::{EmbeddingConfig, EmbeddingModel, EmbeddingStatus, VectorIndex};
 use super::model::cosine_similarity;
 
@@ -25,12 +27,14 @@ pub struct EmbeddingStore {
     index: VectorIndex,
     /// Configuration
     config: EmbeddingConfig,
+    /// Tool, category and score per pattern, for filtered searches
+    meta: Option<Sidecar>,
 }
 
 impl EmbeddingStore {
     /// Create a new embedding store
     pub fn new(mana_dir: &Path, config: &EmbeddingConfig) -> Result<Self> {
-        let model = EmbeddingModel::new(&config.model)?;
+        let model = Self::model_for(mana_dir, &config.model)?;
         let index = VectorIndex::new(config.dimensions);
 
         // Initialize SQLite schema
@@ -41,13 +45,14 @@ impl EmbeddingStore {
             model,
             index,
             config: config.clone(),
+            meta: None,
         })
     }
 
     /// Open an existing embedding store
     pub fn open(mana_dir: &Path) -> Result<Self> {
         let config = Self::load_config(mana_dir)?;
-        let model = EmbeddingModel::new(&config.model)?;
+        let model = Self::model_for(mana_dir, &config.model)?;
 
         // Load existing index if available
         let index_path = mana_dir.join("vectors.usearch");
@@ -62,13 +67,23 @@ impl EmbeddingStore {
             model,
             index,
             config,
+            meta: Sidecar::load(mana_dir),
         })
     }
 
+    /// The named model, versioned by its installed assets if any
+    fn model_for(mana_dir: &Path, name: &str) -> Result<EmbeddingModel> {
+        let mut model = EmbeddingModel::new(name)?;
+        if let Some(version) = super::assets::installed_version(mana_dir, name) {
+            model.set_version(version);
+        }
+        Ok(model)
+    }
+
     /// Initialize the database schema for embeddings
     fn init_schema(mana_dir: &Path) -> Result<()> {
         let db_path = mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
 
         // Add embedding columns if they don't exist
         // Note: SQLite doesn't have IF NOT EXISTS for columns, so we check first
@@ -111,7 +126,7 @@ impl EmbeddingStore {
             return Ok(EmbeddingConfig::default());
         }
 
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
 
         // Try to load from embedding_meta table
         let result: Result<(String, usize), _> = conn.query_row(
@@ -120,20 +135,37 @@ impl EmbeddingStore {
             |row| Ok((row.get(0)?, row.get(1)?)),
         );
 
-        match result {
-            Ok((model, dimensions)) => Ok(EmbeddingConfig {
+        let mut config = match result {
+            Ok((model, dimensions)) => EmbeddingConfig {
                 model,
                 dimensions,
                 ..Default::default()
-            }),
-            Err(_) => Ok(EmbeddingConfig::default()),
+            },
+            Err(_) => EmbeddingConfig::default(),
+        };
+
+        // Index tuning lives in config.toml rather than the database
+        let user = crate::config::load_config(mana_dir).embeddings;
+        config.hnsw = user.hnsw;
+        config.ann_min_vectors = user.ann_min_vectors;
+        Ok(config)
+    }
+
+    /// Build the HNSW graph if the index is large enough to benefit
+    ///
+    /// Returns true if searches will now be approximate.
+    pub fn enable_ann(&mut self) -> bool {
+        if self.index.len() < self.config.ann_min_vectors {
+            return false;
         }
+        self.index.build_ann(self.config.hnsw);
+        true
     }
 
     /// Get embedding status
     pub fn status(&self) -> Result<EmbeddingStatus> {
         let db_path = self.mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
 
         // Count patterns without embeddings
         let unembedded: i64 = conn.query_row(
@@ -161,55 +193,68 @@ impl EmbeddingStore {
     }
 
     /// Generate embeddings for patterns that don't have them
+    ///
+    /// Holds the index lock throughout and starts from the index on disk, so
+    /// vectors saved by other processes since this store was opened are kept.
     pub fn embed_missing(&mut self) -> Result<usize> {
-        let db_path = self.mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let _lock = super::lock::acquire(&self.mana_dir)?;
+        self.load_index()?;
+        self.embed_all_and_save()
+    }
 
-        // Get patterns without embeddings
-        let mut stmt = conn.prepare(
-            "SELECT id, context_query FROM patterns WHERE embedding IS NULL LIMIT 1000"
-        )?;
+    fn embed_all_and_save(&mut self) -> Result<usize> {
+        let count = self.embed_next(1000)?;
+        if count > 0 {
+            self.save_index()?;
+        }
+        Ok(count)
+    }
 
-        let patterns: Vec<(i64, String)> = stmt
-            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+    /// Embed up to `limit` patterns that have no embedding, in one transaction
+    ///
+    /// The index is updated in memory only; call [`save_index`](Self::save_index)
+    /// to write it out.
+    pub fn embed_next(&mut self, limit: usize) -> Result<usize> {
+        let db_path = self.mana_dir.join("metadata.sqlite");
+        let mut conn = crate::storage::db::open(&db_path)?;
+
+        let patterns: Vec<(i64, String)> = conn
+            .prepare("SELECT id, context_query FROM patterns WHERE embedding IS NULL ORDER BY id LIMIT ?1")?
+            .query_map(params![limit as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
             .filter_map(|r| r.ok())
             .collect();
-
         if patterns.is_empty() {
             return Ok(0);
         }
 
-        let mut count = 0;
-
-        for (id, context_query) in &patterns {
-            let embedding = self.model.embed(context_query)?;
-
-            // Store in SQLite
-            let embedding_bytes: Vec<u8> = embedding
-                .iter()
-                .flat_map(|f| f.to_le_bytes())
-                .collect();
-
-            conn.execute(
-                "UPDATE patterns SET embedding = ?, embedding_version = 1 WHERE id = ?",
-                params![embedding_bytes, id],
-            )?;
-
-            // Add to index
-            self.index.add(*id, &embedding)?;
-            count += 1;
+        let embeddings = patterns
+            .iter()
+            .map(|(id, context_query)| Ok((*id, self.model.embed(context_query)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let tx = crate::storage::db::write_transaction(&mut conn)?;
+        {
+            let mut update = tx.prepare_cached("UPDATE patterns SET embedding = ?1, embedding_version = 1 WHERE id = ?2")?;
+            for (id, embedding) in &embeddings {
+                let embedding_bytes: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
+                update.execute(params![embedding_bytes, id])?;
+            }
         }
-
-        // Save index
-        self.save_index()?;
-
-        Ok(count)
+        tx.commit()?;
+        // Only once the database has them, so a failed batch is simply redone
+        for (id, embedding) in &embeddings {
+            // A pattern whose embedding was cleared may still have its old vector
+            self.index.remove(*id);
+            self.index.add(*id, embedding)?;
+        }
+        Ok(embeddings.len())
     }
 
     /// Rebuild all embeddings
     pub fn rebuild(&mut self) -> Result<usize> {
+        let _lock = super::lock::acquire(&self.mana_dir)?;
         let db_path = self.mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
 
         // Clear existing embeddings
         conn.execute("UPDATE patterns SET embedding = NULL, embedding_version = 0", [])?;
@@ -218,7 +263,7 @@ impl EmbeddingStore {
         self.index = VectorIndex::new(self.config.dimensions);
 
         // Re-embed all
-        self.embed_missing()
+        self.embed_all_and_save()
     }
 
     /// Search for similar patterns using vector similarity
@@ -229,35 +274,65 @@ impl EmbeddingStore {
         Ok(matches.into_iter().map(|m| (m.id, m.similarity)).collect())
     }
 
+    /// Search among patterns matching `filter`
+    ///
+    /// Patterns the metadata sidecar rules out are skipped before scoring;
+    /// without a sidecar every vector is a candidate.
+    pub fn search_filtered(&self, query: &str, k: usize, filter: &SearchFilter) -> Result<Vec<(i64, f32)>> {
+        if filter.is_empty() {
+            return self.search(query, k);
+        }
+        let query_embedding = self.model.embed(query)?;
+        let matches = self
+            .index
+            .search_filtered(&query_embedding, k, |id| self.meta.as_ref().is_none_or(|meta| meta.admits(id, filter)));
+        Ok(matches.into_iter().map(|m| (m.id, m.similarity)).collect())
+    }
+
     /// Search with combined vector and pattern info
+    ///
+    /// Matches are checked against the database, so a stale sidecar never
+    /// lets a pattern through that no longer fits `filter`.
     pub fn search_with_context(
         &self,
         query: &str,
         k: usize,
+        filter: &SearchFilter,
     ) -> Result<Vec<PatternMatch>> {
         let db_path = self.mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
+        let category = if crate::storage::has_column(&conn, "patterns", "command_category") {
+            "command_category"
+        } else {
+            "NULL"
+        };
 
-        let query_embedding = self.model.embed(query)?;
-        let matches = self.index.search(&query_embedding, k * 2); // Get more for filtering
+        let matches = self.search_filtered(query, k * 2, filter)?; // Get more for filtering
 
         let mut results = Vec::new();
 
-        for m in matches {
-            let pattern: Option<(String, String, i64, i64)> = conn
+        for (id, similarity) in matches {
+            let pattern: Option<(String, Option<String>, String, i64, i64)> = conn
                 .query_row(
-                    "SELECT tool_type, context_query, success_count, failure_count
-                     FROM patterns WHERE id = ?",
-                    params![m.id],
-                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                    &format!(
+                        "SELECT tool_type, {}, context_query, success_count, failure_count
+                         FROM patterns WHERE id = ?",
+                        category
+                    ),
+                    params![id],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
                 )
                 .ok();
 
-            if let Some((tool_type, context_query, success, failure)) = pattern {
+            if let Some((tool_type, category, context_query, success, failure)) = pattern {
+                let meta = VectorMeta { tool_type, category, score: success - failure };
+                if !filter.matches(&meta) {
+                    continue;
+                }
                 results.push(PatternMatch {
-                    id: m.id,
-                    similarity: m.similarity,
-                    tool_type,
+                    id,
+                    similarity,
+                    tool_type: meta.tool_type,
                     context_query,
                     success_count: success,
                     failure_count: failure,
@@ -277,7 +352,7 @@ impl EmbeddingStore {
         let embedding = self.model.embed(context_query)?;
 
         let db_path = self.mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
 
         let embedding_bytes: Vec<u8> = embedding
             .iter()
@@ -299,13 +374,18 @@ impl EmbeddingStore {
     }
 
     /// Save the index to disk
+    ///
+    /// The caller should hold the [index lock](super::lock) from
+    /// before the store was opened, or it may overwrite another process's
+    /// changes.
     pub fn save_index(&self) -> Result<()> {
+        crate::storage::read_only::ensure_writable("Saving the embedding index")?;
         let index_path = self.mana_dir.join("vectors.usearch");
         self.index.save(&index_path)?;
 
         // Update metadata
         let db_path = self.mana_dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = crate::storage::db::open(&db_path)?;
 
         conn.execute(
             "INSERT OR REPLACE INTO embedding_meta (id, model_name, model_version, dimensions)
@@ -316,6 +396,9 @@ impl EmbeddingStore {
                 self.config.dimensions as i64
             ],
         )?;
+        if let Err(e) = super::meta::refresh(&self.mana_dir, &conn) {
+            warn!("Failed to write {}: {}", super::meta::META_FILE, e);
+        }
 
         Ok(())
     }
@@ -388,7 +471,7 @@ mod tests {
 
     fn setup_test_db(dir: &Path) -> Result<()> {
         let db_path = dir.join("metadata.sqlite");
-        let conn = Connection::open(&db_path)?;
+        let conn = rusqlite::Connection::open(&db_path)?;
 
         conn.execute(
             "CREATE TABLE IF NOT EXISTS patterns (
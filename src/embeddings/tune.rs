@@ -0,0 +1,184 @@
+//! HNSW parameter tuning
+//!
+//! Benchmarks recall and latency of the HNSW graph against exact search on
+//! the current index, so `mana embed tune` can recommend `[embeddings]`
+//! values sized for this machine's pattern count.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+use std::time::Instant;
+
+use super::hnsw::HnswParams;
+use super::index::VectorIndex;
+
+/// Neighbors compared per query when measuring recall
+const RECALL_AT: usize = 10;
+
+/// Candidate values for each parameter
+const M_VALUES: &[usize] = &[8, 16, 32];
+const EF_CONSTRUCTION_VALUES: &[usize] = &[100, 200];
+const EF_SEARCH_VALUES: &[usize] = &[16, 32, 64, 128];
+
+/// Measured result for one parameter combination
+#[derive(Debug, Clone)]
+pub struct TuneResult {
+    pub params: HnswParams,
+    /// Mean recall@10 against exact search
+    pub recall: f64,
+    /// Mean query latency in microseconds
+    pub latency_us: f64,
+    /// Graph build time in milliseconds
+    pub build_ms: f64,
+}
+
+/// Full tuning report
+#[derive(Debug, Clone)]
+pub struct TuneReport {
+    pub vector_count: usize,
+    pub query_count: usize,
+    /// Mean exact-search latency in microseconds (the baseline to beat)
+    pub exact_latency_us: f64,
+    pub results: Vec<TuneResult>,
+}
+
+impl TuneReport {
+    /// Fastest configuration meeting the recall target, or the most accurate one
+    pub fn recommend(&self, target_recall: f64) -> Option<&TuneResult> {
+        self.results
+            .iter()
+            .filter(|r| r.recall >= target_recall)
+            .min_by(|a, b| a.latency_us.total_cmp(&b.latency_us))
+            .or_else(|| {
+                self.results
+                    .iter()
+                    .max_by(|a, b| a.recall.total_cmp(&b.recall))
+            })
+    }
+}
+
+/// Benchmark the parameter grid on `index` using `query_count` sampled queries
+///
+/// Queries are stored vectors with a small amount of noise, which mirrors
+/// real lookups (similar but never identical to a stored context).
+pub fn run_tune(index: &VectorIndex, query_count: usize) -> TuneReport {
+    let queries = sample_queries(index, query_count);
+
+    let start = Instant::now();
+    let truth: Vec<HashSet<i64>> = queries
+        .iter()
+        .map(|q| index.search_exact(q, RECALL_AT).into_iter().map(|m| m.id).collect())
+        .collect();
+    let exact_latency_us = per_query_us(start, queries.len());
+
+    let mut results = Vec::new();
+    for &m in M_VALUES {
+        for &ef_construction in EF_CONSTRUCTION_VALUES {
+            let build_start = Instant::now();
+            let graph = index.build_graph(HnswParams { m, ef_construction, ef_search: 0 });
+            let build_ms = build_start.elapsed().as_secs_f64() * 1000.0;
+
+            for &ef_search in EF_SEARCH_VALUES {
+                let start = Instant::now();
+                let found: Vec<Vec<i64>> = queries
+                    .iter()
+                    .map(|q| index.search_with_ef(&graph, q, RECALL_AT, ef_search).into_iter().map(|m| m.id).collect())
+                    .collect();
+                let latency_us = per_query_us(start, queries.len());
+
+                results.push(TuneResult {
+                    params: HnswParams { m, ef_construction, ef_search },
+                    recall: mean_recall(&truth, &found),
+                    latency_us,
+                    build_ms,
+                });
+            }
+        }
+    }
+
+    TuneReport {
+        vector_count: index.len(),
+        query_count: queries.len(),
+        exact_latency_us,
+        results,
+    }
+}
+
+fn sample_queries(index: &VectorIndex, count: usize) -> Vec<Vec<f32>> {
+    let mut rng = StdRng::seed_from_u64(42);
+    let total = index.len();
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut queries = Vec::with_capacity(count.min(total));
+    for _ in 0..count.min(total) {
+        if let Some(v) = index.vector_at(rng.gen_range(0..total)) {
+            queries.push(v.iter().map(|x| x + rng.gen_range(-0.02..0.02)).collect());
+        }
+    }
+    queries
+}
+
+fn mean_recall(truth: &[HashSet<i64>], found: &[Vec<i64>]) -> f64 {
+    if truth.is_empty() {
+        return 1.0;
+    }
+    let total: f64 = truth
+        .iter()
+        .zip(found)
+        .map(|(t, f)| {
+            if t.is_empty() {
+                1.0
+            } else {
+                f.iter().filter(|id| t.contains(id)).count() as f64 / t.len() as f64
+            }
+        })
+        .sum();
+    total / truth.len() as f64
+}
+
+fn per_query_us(start: Instant, queries: usize) -> f64 {
+    start.elapsed().as_secs_f64() * 1_000_000.0 / queries.max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommend_prefers_fastest_meeting_target() {
+        let result = |ef_search, recall, latency_us| TuneResult {
+            params: HnswParams { m: 16, ef_construction: 200, ef_search },
+            recall,
+            latency_us,
+            build_ms: 1.0,
+        };
+        let report = TuneReport {
+            vector_count: 100,
+            query_count: 10,
+            exact_latency_us: 50.0,
+            results: vec![result(16, 0.80, 5.0), result(32, 0.96, 8.0), result(64, 0.99, 12.0)],
+        };
+
+        assert_eq!(report.recommend(0.95).unwrap().params.ef_search, 32);
+        // Unreachable target falls back to the most accurate configuration
+        assert_eq!(report.recommend(1.0).unwrap().params.ef_search, 64);
+    }
+
+    #[test]
+    fn test_run_tune_small_index() {
+        let mut index = VectorIndex::new(8);
+        let mut rng = StdRng::seed_from_u64(1);
+        for id in 0..200 {
+            let v: Vec<f32> = (0..8).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            index.add(id, &v).unwrap();
+        }
+
+        let report = run_tune(&index, 20);
+        assert_eq!(report.query_count, 20);
+        assert_eq!(report.results.len(), M_VALUES.len() * EF_CONSTRUCTION_VALUES.len() * EF_SEARCH_VALUES.len());
+        let best = report.recommend(0.9).unwrap();
+        assert!(best.recall >= 0.9);
+    }
+}
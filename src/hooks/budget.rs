@@ -0,0 +1,216 @@
+//! Token and latency budgets for injected context
+//!
+//! Token counts use a cheap chars/4 estimate rather than a real tokenizer;
+//! the hook path has a <10ms latency budget and only needs a rough upper
+//! bound. That latency budget (`performance.injection_timeout_ms`) is
+//! enforced with [`LatencyBudget`]: once it runs out, the search stops and
+//! the tool input passes through without context.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Below this many remaining tokens, stop adding pattern entries
+const MIN_ENTRY_TOKENS: usize = 16;
+
+/// Don't bother keeping a partial line shorter than this
+const MIN_LINE_CHARS: usize = 12;
+
+/// Estimate the token count of a string (~4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Lines that carry the actionable part of a pattern and are kept first
+fn is_key_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("Pitfall:") || trimmed.starts_with("Advice:") || trimmed.starts_with("⚠️")
+}
+
+/// Truncate a string to `max_chars` characters, appending "..." when cut
+fn truncate_chars(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let kept: String = s.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", kept)
+}
+
+/// Truncate multi-line text to fit within `max_tokens`
+///
+/// Pitfall/Advice lines are kept ahead of other lines; the surviving lines
+/// keep their original order.
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+
+    let max_chars = max_tokens * 4;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut kept: Vec<Option<String>> = vec![None; lines.len()];
+    let mut used = 0;
+
+    let key = (0..lines.len()).filter(|&i| is_key_line(lines[i]));
+    let rest = (0..lines.len()).filter(|&i| !is_key_line(lines[i]));
+
+    for i in key.chain(rest) {
+        let cost = lines[i].chars().count() + 1;
+        if used + cost <= max_chars {
+            kept[i] = Some(lines[i].to_string());
+            used += cost;
+        } else {
+            let room = max_chars.saturating_sub(used + 1);
+            if room >= MIN_LINE_CHARS {
+                kept[i] = Some(truncate_chars(lines[i], room));
+                used = max_chars;
+            }
+        }
+    }
+
+    kept.into_iter().flatten().collect::<Vec<_>>().join("\n")
+}
+
+/// Tracks the remaining token budget while a context block is assembled
+pub struct TokenBudget {
+    remaining: usize,
+}
+
+impl TokenBudget {
+    pub fn new(max_tokens: usize) -> Self {
+        Self { remaining: max_tokens }
+    }
+
+    /// Charge text that is always emitted (headers, wrapper lines)
+    pub fn consume(&mut self, text: &str) {
+        self.remaining = self.remaining.saturating_sub(estimate_tokens(text));
+    }
+
+    /// Fit a pattern entry into the remaining budget
+    ///
+    /// The entry's first line (its heading) is kept as-is and the body is
+    /// truncated if needed. Returns None once the budget is exhausted.
+    pub fn fit(&mut self, entry: &str) -> Option<String> {
+        let cost = estimate_tokens(entry);
+        if cost <= self.remaining {
+            self.remaining -= cost;
+            return Some(entry.to_string());
+        }
+        if self.remaining < MIN_ENTRY_TOKENS {
+            return None;
+        }
+
+        let (head, body) = entry.split_once('\n').unwrap_or((entry, ""));
+        let head_cost = estimate_tokens(head);
+        if head_cost >= self.remaining {
+            return None;
+        }
+
+        let body = truncate_to_tokens(body, self.remaining - head_cost);
+        self.remaining = 0;
+        if body.is_empty() {
+            Some(head.to_string())
+        } else {
+            Some(format!("{}\n{}", head, body))
+        }
+    }
+}
+
+/// Deadline for one injection, checked between search stages
+pub struct LatencyBudget {
+    start: Instant,
+    limit_ms: u64,
+}
+
+impl LatencyBudget {
+    /// Budget of `limit_ms` counted from `start`
+    pub fn new(start: Instant, limit_ms: u64) -> Self {
+        Self { start, limit_ms }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Time left before the deadline
+    pub fn remaining(&self) -> Duration {
+        Duration::from_millis(self.limit_ms).saturating_sub(self.elapsed())
+    }
+
+    /// Fail once the deadline has passed, naming the stage that ran over
+    pub fn check(&self, stage: &'static str) -> Result<(), BudgetExceeded> {
+        let elapsed = self.elapsed();
+        if elapsed > Duration::from_millis(self.limit_ms) {
+            return Err(BudgetExceeded { stage, elapsed, limit_ms: self.limit_ms });
+        }
+        Ok(())
+    }
+}
+
+/// An injection ran past its latency budget
+#[derive(Debug, Clone)]
+pub struct BudgetExceeded {
+    /// Last stage that finished before the check failed
+    pub stage: &'static str,
+    pub elapsed: Duration,
+    pub limit_ms: u64,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "injection exceeded its {}ms budget after {} ({}µs)",
+            self.limit_ms,
+            self.stage,
+            self.elapsed.as_micros()
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_truncate_preserves_pitfall_and_advice() {
+        let text = format!(
+            "Task: {}\nPitfall: cargo build failed with E0382\nAdvice: Verify ownership before moving",
+            "x".repeat(400)
+        );
+
+        let truncated = truncate_to_tokens(&text, 30);
+        assert!(estimate_tokens(&truncated) <= 30);
+        assert!(truncated.contains("Pitfall: cargo build failed with E0382"));
+        assert!(truncated.contains("Advice: Verify ownership before moving"));
+    }
+
+    #[test]
+    fn test_budget_stops_when_exhausted() {
+        let mut budget = TokenBudget::new(40);
+        let entry = "- **Bash** (score: 3)\n  Ran `cargo`: build the project in release mode";
+
+        assert!(budget.fit(entry).is_some());
+        let second = budget.fit(entry);
+        assert!(second.is_some_and(|e| e.starts_with("- **Bash**")));
+        assert!(budget.fit(entry).is_none());
+    }
+
+    #[test]
+    fn test_latency_budget_names_the_late_stage() {
+        let start = Instant::now();
+        assert!(LatencyBudget::new(start, 60_000).check("fetch").is_ok());
+
+        let late = LatencyBudget::new(start - Duration::from_millis(20), 10).check("similarity").unwrap_err();
+        assert_eq!(late.stage, "similarity");
+        assert!(late.elapsed >= Duration::from_millis(20));
+        assert!(late.to_string().contains("10ms budget after similarity"));
+    }
+}
@@ -1,59 +1,44 @@
 //! Context injection for pre-hooks
 //!
 //! Reads tool input from stdin, queries ReasoningBank for relevant patterns,
-//! and outputs context to stdout. Latency budget: <10ms.
-
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
+//! and outputs context to stdout. Latency budget: <10ms by default
+//! (`performance.injection_timeout_ms`); past it, the input passes through
+//! without context and the overrun is recorded for `mana stats`.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Read as IoRead, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::PathBuf;
 use std::time::Instant;
-use tracing::{debug, warn};
-
-use crate::storage::{PatternStore, Pattern, calculate_similarity, CausalStore};
-
-/// Top-level hook input structure from Claude Code
-#[derive(Debug, Deserialize)]
-struct HookInput {
-    #[allow(dead_code)]
-    tool_name: Option<String>,
-    /// Claude Code uses "input" as the nested key
-    input: Option<ToolInputFields>,
-    /// Legacy: support "tool_input" for backwards compatibility
-    tool_input: Option<ToolInputFields>,
-    // Also support flat structure for backwards compatibility
-    #[serde(flatten)]
-    flat: ToolInputFields,
-}
-
-/// Fields that can appear in tool_input or at top level
-#[derive(Debug, Default, Deserialize)]
-struct ToolInputFields {
-    file_path: Option<String>,
-    command: Option<String>,
-    subagent_type: Option<String>,
-    description: Option<String>,
-    #[allow(dead_code)]
-    content: Option<String>,
-    #[allow(dead_code)]
-    prompt: Option<String>,
-}
+use tracing::{debug, debug_span, instrument, warn};
+
+use super::budget::{estimate_tokens, BudgetExceeded, LatencyBudget, TokenBudget};
+use super::explain::{Explanation, Outcome};
+use super::pitfalls;
+use super::protocol::{self, HookInput, ToolInputFields};
+use super::repo_signals::RepoSignals;
+use super::session_memory;
+use super::template::{render_pattern, wrap_context, PatternFields};
+use crate::config::{load_config, InjectionConfig, ManaConfig, OutputAdapter};
+use crate::storage::{PatternStore, Pattern, Scorer};
+use crate::storage::ranking;
+use crate::storage::snapshot::{CandidateSource, Snapshot};
+use crate::storage::injection_log::{append_overrun, append_spool, BudgetOverrun, InjectionRecord};
 
 #[derive(Debug, Serialize)]
 struct ContextInjection {
     context_block: String,
-    patterns_used: Vec<i64>,
+    /// Injected patterns as (pattern id, ranking score)
+    patterns_used: Vec<(i64, f64)>,
 }
 
-/// Maximum patterns to inject per context (to avoid overwhelming Claude)
-const MAX_PATTERNS: usize = 3;
-
 /// Number of patterns to retrieve for similarity scoring (before filtering)
 /// Balanced at 8 - enough for quality matches without excess overhead
 const PATTERNS_TO_SCORE: usize = 8;
 
-/// Maximum time budget for injection in milliseconds
-const INJECTION_TIMEOUT_MS: u128 = 10;
 
 /// Minimum relevance score to include a pattern (currently unused but reserved for future)
 #[allow(dead_code)]
@@ -72,15 +57,20 @@ const MIN_TECH_STACK_SIMILARITY: f64 = 0.35;
 ///
 /// If the MANA daemon is running, uses it for faster response (keeps state in memory).
 /// Falls back to direct database access if daemon is not available.
-pub fn inject_context(tool: &str) -> Result<()> {
+///
+/// Once stdin is read, the input always reaches stdout unchanged: invalid
+/// input, errors and panics only cost the context, and are logged to
+/// [`protocol::ERRORS_FILE`].
+#[instrument(level = "debug", name = "inject", skip_all, fields(tool = tool))]
+pub fn inject_context(tool: &str, adapter: Option<OutputAdapter>) -> Result<()> {
     let start = Instant::now();
     debug!("Injecting context for tool: {}", tool);
 
     // Read input from stdin - read all bytes at once for speed
     // Typical input is <1KB, so reading everything is fast
     let stdin = io::stdin();
-    let mut input = String::with_capacity(1024);
-    stdin.lock().read_to_string(&mut input)?;
+    let mut input = Vec::with_capacity(1024);
+    debug_span!("read_stdin").in_scope(|| stdin.lock().read_to_end(&mut input))?;
     let stdin_time = start.elapsed().as_micros();
 
     if input.is_empty() {
@@ -88,16 +78,86 @@ pub fn inject_context(tool: &str) -> Result<()> {
         return Ok(());
     }
 
+    let prepared = panic::catch_unwind(AssertUnwindSafe(|| prepare_injection(tool, adapter, &input, start, stdin_time)));
+    let failure = match &prepared {
+        Ok(Ok(_)) => None,
+        Ok(Err(e)) => Some(format!("{:#}", e)),
+        Err(payload) => Some(format!("panic: {}", panic_message(payload.as_ref()))),
+    };
+    if let Some(error) = failure {
+        warn!("Context injection failed: {}, passing through", error);
+        if !crate::storage::read_only::enabled() {
+            if let Ok(dir) = get_mana_dir() {
+                protocol::log_failure(&dir, &protocol::HookFailure::new(tool, &input, error));
+            }
+        }
+    }
+    let prepared = prepared.ok().and_then(Result::ok);
+
+    let output = debug_span!("output").entered();
+    let mut stdout = io::stdout().lock();
+    if let Some(context) = prepared.as_ref().and_then(|p| p.context.as_deref()) {
+        stdout.write_all(context.as_bytes())?;
+    }
+    // Pass through original input
+    stdout.write_all(&input)?;
+    stdout.flush()?;
+    drop(output);
+
+    // Record the injection after output is flushed so it never delays the tool
+    if let Some(followup) = prepared.and_then(|p| p.followup) {
+        let _spool = debug_span!("spool").entered();
+        if panic::catch_unwind(AssertUnwindSafe(|| followup.record(tool, start))).is_err() {
+            warn!("Recording the injection panicked");
+        }
+    }
+
+    debug!("Context injection complete in {}ms", start.elapsed().as_millis());
+    Ok(())
+}
+
+/// What [`inject_context`] prints ahead of the input, and records afterwards
+struct Prepared {
+    context: Option<String>,
+    followup: Option<Followup>,
+}
+
+/// Bookkeeping done once the tool has its input
+struct Followup {
+    hook_input: HookInput,
+    config: InjectionConfig,
+    mana_dir: Option<PathBuf>,
+    overrun: Option<BudgetExceeded>,
+    control: bool,
+    patterns_used: Vec<(i64, f64)>,
+}
+
+/// Validate the input and build the context block to print before it
+fn prepare_injection(
+    tool: &str,
+    adapter: Option<OutputAdapter>,
+    input: &[u8],
+    start: Instant,
+    stdin_time: u128,
+) -> Result<Prepared> {
+    let input = std::str::from_utf8(input).context("Hook input is not UTF-8")?;
+    let hook_input = debug_span!("parse")
+        .in_scope(|| HookInput::parse(input))
+        .context("Invalid hook input")?;
+    let parse_time = start.elapsed().as_micros() - stdin_time;
+    debug!("Hook input has the {} shape", hook_input.version.name());
+
     // Try daemon first (faster path - keeps state in memory)
     if crate::daemon::is_running() {
         debug!("Daemon is running, using daemon path");
-        match crate::daemon::inject_via_daemon(tool, &input) {
+        match debug_span!("daemon").in_scope(|| crate::daemon::inject_via_daemon(tool, input, adapter)) {
             Ok(result) => {
-                // Daemon returns the full output (context + input)
-                print!("{}", result);
-                io::stdout().flush()?;
+                // Daemon returns the full output (context + input); anything else would corrupt the tool call
+                let context = result
+                    .strip_suffix(input)
+                    .context("Daemon output does not end with the hook input")?;
                 debug!("Daemon injection complete in {}ms", start.elapsed().as_millis());
-                return Ok(());
+                return Ok(Prepared { context: Some(context.to_string()).filter(|c| !c.is_empty()), followup: None });
             }
             Err(e) => {
                 warn!("Daemon injection failed: {}, falling back to direct", e);
@@ -106,38 +166,49 @@ pub fn inject_context(tool: &str) -> Result<()> {
         }
     }
 
-    // Parse hook input
-    let hook_input: HookInput = match serde_json::from_str(&input) {
-        Ok(hi) => hi,
-        Err(e) => {
-            debug!("Failed to parse hook input: {}, passing through", e);
-            // Pass through original input
-            print!("{}", input);
-            io::stdout().flush()?;
-            return Ok(());
-        }
-    };
-    let parse_time = start.elapsed().as_micros() - stdin_time;
-
-    // Extract fields from nested input (Claude Code), tool_input (legacy), or flat structure
-    let fields = if let Some(ref inp) = hook_input.input {
-        inp
-    } else if let Some(ref ti) = hook_input.tool_input {
-        ti
-    } else {
-        &hook_input.flat
-    };
+    let fields = &hook_input.fields;
 
-    // Build query based on tool type
+    // Build query based on tool type, plus what the working directory says
+    let ManaConfig { injection: config, performance, .. } =
+        get_mana_dir().map(|dir| load_config(&dir)).unwrap_or_default();
+    let repo = repo_signals(hook_input.cwd.as_deref(), &config);
     let query = build_query(tool, fields);
+    let query = match &repo {
+        Some(repo) => repo.enrich(query),
+        None => query,
+    };
+    let category = command_category(tool, fields);
     debug!("Query: {}", query);
 
-    // Query ReasoningBank for patterns
+    // Session state goes first, so patterns get what's left of the token budget
+    let mana_dir = get_mana_dir().ok();
+    let state = mana_dir.as_deref().and_then(|dir| session_memory::state_block(dir, hook_input.session_id.as_deref(), &config));
+    let pattern_config = InjectionConfig {
+        max_tokens: config.max_tokens.saturating_sub(state.as_deref().map_or(0, estimate_tokens)),
+        ..config.clone()
+    };
+
+    // Query ReasoningBank for patterns, giving up once the latency budget is spent
+    let budget = LatencyBudget::new(start, performance.injection_timeout_ms);
     let query_start = Instant::now();
-    let context = match query_patterns(tool, &query) {
+    let mut overrun: Option<BudgetExceeded> = None;
+    let repeated = recently_injected(hook_input.session_id.as_deref(), &config);
+    let context = match query_patterns(tool, &query, category.as_deref(), &pattern_config, &budget, &repeated, None)
+        .and_then(|ctx| {
+            budget.check("format")?;
+            Ok(ctx)
+        })
+    {
         Ok(ctx) => ctx,
         Err(e) => {
-            warn!("Failed to query patterns: {}, passing through", e);
+            match e.downcast::<BudgetExceeded>() {
+                Ok(exceeded) => {
+                    warn!("Context injection {}, passing through (stdin: {}µs, parse: {}µs)",
+                          exceeded, stdin_time, parse_time);
+                    overrun = Some(exceeded);
+                }
+                Err(e) => warn!("Failed to query patterns: {}, passing through", e),
+            }
             ContextInjection {
                 context_block: String::new(),
                 patterns_used: vec![],
@@ -145,36 +216,204 @@ pub fn inject_context(tool: &str) -> Result<()> {
         }
     };
     let query_time = query_start.elapsed().as_micros();
-
-    // Check time budget
     let elapsed = start.elapsed().as_millis();
-    if elapsed > INJECTION_TIMEOUT_MS {
-        warn!("Context injection exceeded time budget: {}ms > {}ms (stdin: {}µs, parse: {}µs, query: {}µs)",
-              elapsed, INJECTION_TIMEOUT_MS, stdin_time, parse_time, query_time);
-    }
+
+    // Control-group sessions get nothing; the selection is only logged
+    let control = hook_input
+        .session_id
+        .as_deref()
+        .is_some_and(|id| crate::reflection::in_control_group(id, config.control_fraction));
 
     // If we have context, inject it as a system-reminder style block
-    if !context.context_block.is_empty() {
+    let mut output = None;
+    if control {
+        debug!("Session in experiment control group, withholding {} patterns", context.patterns_used.len());
+    } else if !context.context_block.is_empty() || state.is_some() {
         debug!("Injecting {} patterns in {}ms (stdin: {}µs, parse: {}µs, query: {}µs)",
                context.patterns_used.len(), elapsed, stdin_time, parse_time, query_time);
-        println!("<mana-context>");
-        println!("{}", context.context_block);
-        println!("</mana-context>");
-        println!();
+        let block = match &state {
+            Some(state) if context.context_block.is_empty() => format!("{}\n", state),
+            Some(state) => format!("{}\n\n{}", state, context.context_block),
+            None => context.context_block.clone(),
+        };
+        let adapter = adapter.unwrap_or(config.adapter);
+        output = Some(wrap_context(&config, adapter, &block, &context.patterns_used));
     }
 
-    // Pass through original input
-    print!("{}", input);
-    io::stdout().flush()?;
+    let followup = Followup { hook_input, config, mana_dir, overrun, control, patterns_used: context.patterns_used };
+    Ok(Prepared { context: output, followup: Some(followup) })
+}
 
-    debug!("Context injection complete in {}ms", start.elapsed().as_millis());
+impl Followup {
+    /// Spool the injection record and budget overrun, and note the touched file
+    fn record(self, tool: &str, start: Instant) {
+        let Followup { hook_input, config, mana_dir, overrun, control, patterns_used } = self;
+        if crate::storage::read_only::enabled() {
+            debug!("Read-only mode, not recording the injection");
+            return;
+        }
+        let logged_tool = hook_input.tool_name.as_deref().unwrap_or(tool);
+        if let Some(exceeded) = overrun {
+            let record = BudgetOverrun::new(logged_tool, exceeded.stage, exceeded.elapsed, exceeded.limit_ms);
+            if let Err(e) = get_mana_dir().and_then(|dir| append_overrun(&dir, &record)) {
+                debug!("Failed to spool budget overrun: {}", e);
+            }
+        }
+        if let (Some(dir), Some(session_id), Some(file)) = (&mana_dir, &hook_input.session_id, &hook_input.fields.file_path) {
+            if config.session_state {
+                if let Err(e) = session_memory::note_file(dir, session_id, file) {
+                    debug!("Failed to update session memory: {}", e);
+                }
+            }
+        }
+        if let Some(ref session_id) = hook_input.session_id {
+            if !patterns_used.is_empty() {
+                let record = InjectionRecord::new(session_id, logged_tool, &patterns_used)
+                    .with_latency(start.elapsed())
+                    .with_control(control);
+                if let Err(e) = get_mana_dir().and_then(|dir| append_spool(&dir, &record)) {
+                    debug!("Failed to spool injection record: {}", e);
+                }
+                if let (false, Some(dir)) = (control, &mana_dir) {
+                    let ids: Vec<i64> = patterns_used.iter().map(|(id, _)| *id).collect();
+                    if let Err(e) = session_memory::note_injected(dir, session_id, &ids, &config) {
+                        debug!("Failed to note injected patterns: {}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown")
+}
+
+/// Dry-run an injection and report how each candidate pattern was ranked
+///
+/// Reads the same hook input as [`inject_context`] and prints a report (JSON
+/// on stdout with `json`, text on stderr otherwise) instead of the context.
+pub fn explain_injection(tool: &str, adapter: Option<OutputAdapter>, json: bool) -> Result<()> {
+    let start = Instant::now();
+    let mut input = String::new();
+    io::stdin().lock().read_to_string(&mut input)?;
+    let hook_input = HookInput::parse(&input).context("Invalid hook input: expected the JSON Claude Code sends to a pre-hook")?;
+
+    let fields = &hook_input.fields;
+    let ManaConfig { injection: config, performance, .. } =
+        get_mana_dir().map(|dir| load_config(&dir)).unwrap_or_default();
+    let repo = repo_signals(hook_input.cwd.as_deref(), &config);
+    let query = build_query(tool, fields);
+    let query = match &repo {
+        Some(repo) => repo.enrich(query),
+        None => query,
+    };
+    let category = command_category(tool, fields);
+    let mut explanation = Explanation::new(tool, &query, category.clone(), &config, performance.injection_timeout_ms);
+    explanation.repo = repo;
+
+    // Report the latency budget instead of enforcing it
+    let budget = LatencyBudget::new(start, u64::MAX);
+    let repeated = recently_injected(hook_input.session_id.as_deref(), &config);
+    let context =
+        query_patterns(tool, &query, category.as_deref(), &config, &budget, &repeated, Some(&mut explanation))?;
+    explanation.elapsed_us = start.elapsed().as_micros();
+    explanation.control = hook_input
+        .session_id
+        .as_deref()
+        .is_some_and(|id| crate::reflection::in_control_group(id, config.control_fraction));
+    if !context.context_block.is_empty() {
+        let adapter = adapter.unwrap_or(config.adapter);
+        explanation.block = wrap_context(&config, adapter, &context.context_block, &context.patterns_used);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&explanation)?);
+    } else {
+        eprintln!("{}", explanation.render().trim_end());
+    }
     Ok(())
 }
 
-/// Query patterns from the ReasoningBank
-fn query_patterns(tool: &str, query: &str) -> Result<ContextInjection> {
-    let _query_start = Instant::now();
+/// The `--tool` whose pre-hook fires for a Claude Code tool, if any
+fn hook_tool(tool_name: &str) -> Option<&'static str> {
+    match tool_name {
+        "Write" | "Edit" | "MultiEdit" => Some("edit"),
+        "Bash" => Some("bash"),
+        "Task" => Some("task"),
+        _ => None,
+    }
+}
+
+/// Rank patterns for a recorded tool call as the pre-hook would now
+///
+/// Used by `mana simulate`: the call's input and working directory stand
+/// in for the hook input, and the explanation says what would have been
+/// injected and why. None for tools no pre-hook fires on.
+pub fn replay_call(
+    tool_name: &str,
+    tool_input: &serde_json::Value,
+    cwd: Option<&str>,
+    config: &InjectionConfig,
+) -> Result<Option<Explanation>> {
+    let Some(tool) = hook_tool(tool_name) else {
+        return Ok(None);
+    };
+    let fields: ToolInputFields = serde_json::from_value(tool_input.clone()).unwrap_or_default();
+    // A session without a recorded directory gets no signals, not ours
+    let repo = cwd.and_then(|cwd| repo_signals(Some(cwd), config));
+    let query = build_query(tool, &fields);
+    let query = match &repo {
+        Some(repo) => repo.enrich(query),
+        None => query,
+    };
+    let category = command_category(tool, &fields);
+    let mut explanation = Explanation::new(tool, &query, category.clone(), config, 0);
+    explanation.repo = repo;
+
+    let budget = LatencyBudget::new(Instant::now(), u64::MAX);
+    let context =
+        query_patterns(tool, &query, category.as_deref(), config, &budget, &HashSet::new(), Some(&mut explanation))?;
+    if !context.context_block.is_empty() {
+        explanation.block = wrap_context(config, config.adapter, &context.context_block, &context.patterns_used);
+    }
+    Ok(Some(explanation))
+}
+
+/// Patterns already injected into the session within the repeat window
+///
+/// Read from the session memory file; empty without a session id or with
+/// `repeat_window_mins = 0`.
+fn recently_injected(session_id: Option<&str>, config: &InjectionConfig) -> HashSet<i64> {
+    let (Some(session_id), Some(since)) = (session_id, config.repeat_since(Utc::now())) else {
+        return HashSet::new();
+    };
+    let Ok(mana_dir) = get_mana_dir() else {
+        return HashSet::new();
+    };
+    session_memory::recently_injected(&mana_dir, session_id, since)
+}
 
+/// Query patterns from the ReasoningBank
+///
+/// Reads the memory-mapped snapshot when there is one and only opens the
+/// database without it. Patterns in `repeated` are skipped in favour of the
+/// next-best ones. Fails with [`BudgetExceeded`] if the latency budget runs
+/// out between stages.
+#[instrument(level = "debug", name = "query", skip_all)]
+fn query_patterns(
+    tool: &str,
+    query: &str,
+    category: Option<&str>,
+    config: &InjectionConfig,
+    budget: &LatencyBudget,
+    repeated: &HashSet<i64>,
+    mut explain: Option<&mut Explanation>,
+) -> Result<ContextInjection> {
     // Get MANA data directory
     let mana_dir = get_mana_dir()?;
     let db_path = mana_dir.join("metadata.sqlite");
@@ -187,12 +426,98 @@ fn query_patterns(tool: &str, query: &str) -> Result<ContextInjection> {
         });
     }
 
-    // Open pattern store in read-only mode for faster access
-    let db_open_start = Instant::now();
-    let store = PatternStore::open_readonly(&db_path)?;
-    let db_open_time = db_open_start.elapsed().as_micros();
-    debug!("DB open: {}µs", db_open_time);
+    let open_start = Instant::now();
+    let open = debug_span!("open").entered();
+    let snapshot = Snapshot::open(&mana_dir).unwrap_or_else(|e| {
+        debug!("Ignoring injection snapshot: {}", e);
+        None
+    });
+    let source: Box<dyn CandidateSource> = match snapshot {
+        Some(snapshot) if !snapshot.is_empty() => {
+            debug!("Snapshot open: {}µs ({} entries)", open_start.elapsed().as_micros(), snapshot.len());
+            if let Some(explain) = explain.as_deref_mut() {
+                explain.source = "snapshot".to_string();
+            }
+            Box::new(snapshot)
+        }
+        _ => {
+            // Open pattern store in read-only mode for faster access
+            let store = PatternStore::open_readonly(&db_path)?;
+            debug!("DB open: {}µs", open_start.elapsed().as_micros());
+            if let Some(explain) = explain.as_deref_mut() {
+                explain.source = "database".to_string();
+            }
+            Box::new(DatabaseSource { store })
+        }
+    };
+    drop(open);
+
+    // Known pitfalls are ranked on their own and charged to the token budget first
+    let mut tokens = TokenBudget::new(config.max_tokens);
+    let scorer = source.scorer(query);
+    let mut pitfall_candidates: Vec<Pattern> = if config.max_pitfalls > 0 && !query.is_empty() {
+        source.top(pitfalls::TOOL_TYPE, None, pitfalls::TO_SCORE)?.into_iter().map(|(p, _)| p).collect()
+    } else {
+        Vec::new()
+    };
+    if let Some(explain) = explain.as_deref_mut() {
+        for p in pitfall_candidates.iter().filter(|p| repeated.contains(&p.id)) {
+            explain.consider(p);
+            explain.mark(p.id, Outcome::Repeated);
+        }
+    }
+    pitfall_candidates.retain(|p| !repeated.contains(&p.id));
+    let scored = explain.is_some().then(|| {
+        pitfall_candidates.iter().map(|p| (p.clone(), pitfalls::score(&scorer, p))).collect::<Vec<_>>()
+    });
+    let ranked_pitfalls = pitfalls::rank(&scorer, pitfall_candidates, config);
+    let pitfall_section = pitfalls::section(&ranked_pitfalls, &mut tokens);
+    budget.check("pitfalls")?;
+    if let (Some(explain), Some(scored)) = (explain.as_deref_mut(), scored) {
+        let used: &[(i64, f64)] = pitfall_section.as_ref().map_or(&[], |(_, used)| used);
+        for (p, (similarity, hits, score)) in scored {
+            explain.consider(&p);
+            explain.score(p.id, similarity, hits, score);
+            let outcome = if used.iter().any(|(id, _)| *id == p.id) {
+                Outcome::Injected
+            } else if ranked_pitfalls.iter().any(|(r, _)| r.id == p.id) {
+                Outcome::TokenBudget
+            } else if similarity < config.pitfall_min_similarity {
+                Outcome::BelowThreshold
+            } else {
+                Outcome::OverLimit
+            };
+            explain.mark(p.id, outcome);
+        }
+    }
+
+    let mut context =
+        rank_patterns(source.as_ref(), tool, query, category, config, budget, tokens, repeated, explain)?;
+    if let Some((section, used)) = pitfall_section {
+        context.context_block = if context.context_block.is_empty() {
+            section
+        } else {
+            format!("{}\n{}", context.context_block, section)
+        };
+        context.patterns_used.extend(used);
+    }
+    Ok(context)
+}
 
+/// Rank candidate patterns for a query and format the best ones
+#[instrument(level = "debug", name = "rank", skip_all)]
+#[allow(clippy::too_many_arguments)]
+fn rank_patterns(
+    source: &dyn CandidateSource,
+    tool: &str,
+    query: &str,
+    category: Option<&str>,
+    config: &InjectionConfig,
+    budget: &LatencyBudget,
+    tokens: TokenBudget,
+    repeated: &HashSet<i64>,
+    mut explain: Option<&mut Explanation>,
+) -> Result<ContextInjection> {
     // Map tool argument to database tool_types - prioritize exact matches
     let primary_types: Vec<&str> = match tool {
         "edit" => vec!["Edit", "Write", "MultiEdit"],
@@ -204,38 +529,69 @@ fn query_patterns(tool: &str, query: &str) -> Result<ContextInjection> {
         _ => vec![tool],
     };
 
-    // Get relevant patterns for primary tool types only
+    // Get relevant patterns for primary tool types only, same command category first
     // Retrieve more patterns than we need so similarity scoring can find the best matches
+    // Repeats are dropped below, so fetch enough to replace them
+    let max_patterns = config.max_patterns;
+    let to_score = PATTERNS_TO_SCORE.max(max_patterns);
     let mut patterns: Vec<Pattern> = Vec::new();
+    let mut quality: HashMap<i64, f64> = HashMap::new();
     for tool_type in &primary_types {
-        let mut type_patterns = store.get_by_tool(tool_type, PATTERNS_TO_SCORE)?;
-        patterns.append(&mut type_patterns);
+        for (pattern, q) in source.top(tool_type, category, to_score + repeated.len())? {
+            quality.insert(pattern.id, q);
+            patterns.push(pattern);
+        }
     }
+    budget.check("fetch")?;
+    if let Some(explain) = explain.as_deref_mut() {
+        explain.threshold = MIN_TECH_STACK_SIMILARITY;
+        for p in &patterns {
+            explain.consider(p);
+            if p.risky {
+                explain.mark(p.id, Outcome::Risky);
+            } else if repeated.contains(&p.id) {
+                explain.mark(p.id, Outcome::Repeated);
+            }
+        }
+    }
+
+    // Risky (destructive) patterns are never injected until approved, and
+    // what the session already has in context isn't injected again
+    patterns.retain(|p| !p.risky && !repeated.contains(&p.id));
 
-    // Patterns are already sorted by score from DB query
+    // Patterns are already sorted by quality from the ranking cache
     // Skip heavy deduplication - similarity scoring handles relevance
     // Just do a quick truncate to limit work
-    patterns.truncate(PATTERNS_TO_SCORE * 2);
+    if let Some(explain) = explain.as_deref_mut() {
+        patterns.iter().skip(to_score * 2).for_each(|p| explain.mark(p.id, Outcome::NotScored));
+    }
+    patterns.truncate(to_score * 2);
 
     // Score patterns by semantic similarity if query is not empty
-    if !query.is_empty() {
+    let ranked: Vec<(Pattern, f64)> = if !query.is_empty() {
         debug!("Scoring {} patterns for query: {}", patterns.len(), query);
 
-        // Use TF-IDF style similarity scoring for better relevance
-        // Process patterns in batches for better cache locality
+        // TF-IDF similarity, weighed with the source's corpus statistics
+        let scorer = source.scorer(query);
         let mut scored_patterns: Vec<(Pattern, f64)> = patterns
             .into_iter()
             .filter_map(|p| {
-                let similarity = calculate_similarity(query, &p.context_query);
+                let similarity = scorer.score(&p.context_query);
+
+                // Combine similarity with the pattern's quality for final ranking
+                let success_score = quality.get(&p.id).copied().unwrap_or_default().max(0.0);
+                let combined_score = similarity * 0.6 + success_score * 0.4;
+                if let Some(explain) = explain.as_deref_mut() {
+                    explain.score(p.id, similarity, success_score, combined_score);
+                    if similarity < MIN_TECH_STACK_SIMILARITY {
+                        explain.mark(p.id, Outcome::BelowThreshold);
+                    }
+                }
 
                 // Early filter: skip patterns below threshold
                 if similarity < MIN_TECH_STACK_SIMILARITY {
                     return None;
                 }
-
-                // Combine similarity with success score for final ranking
-                let success_score = (p.success_count - p.failure_count) as f64;
-                let combined_score = similarity * 0.6 + (success_score.max(0.0) / 10.0) * 0.4;
                 debug!("  Pattern [{}]: sim={:.3}, combined={:.3}, context: {}",
                     p.tool_type, similarity, combined_score,
                     p.context_query.chars().take(60).collect::<String>());
@@ -245,42 +601,65 @@ fn query_patterns(tool: &str, query: &str) -> Result<ContextInjection> {
 
         // Sort by combined score (descending)
         scored_patterns.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Only filter causal conflicts if we have more candidates than needed
-        // This avoids extra DB I/O in the common case
-        if scored_patterns.len() > MAX_PATTERNS {
-            scored_patterns = filter_causal_conflicts(&db_path, scored_patterns);
+        budget.check("similarity")?;
+
+        // Drop conflicting pairs and pull synergistic companions forward
+        if scored_patterns.len() > 1 {
+            let before: Vec<i64> = scored_patterns.iter().map(|(p, _)| p.id).collect();
+            scored_patterns = filter_causal_conflicts(source, scored_patterns, max_patterns);
+            budget.check("causal")?;
+            if let Some(explain) = explain.as_deref_mut() {
+                // Selection also stops at max_patterns; only incompatible ones are conflicts
+                let compatible = source.select_compatible(&before, before.len());
+                for id in before.into_iter().filter(|id| !scored_patterns.iter().any(|(p, _)| p.id == *id)) {
+                    let outcome = if compatible.contains(&id) { Outcome::OverLimit } else { Outcome::Conflict };
+                    explain.mark(id, outcome);
+                }
+            }
         }
 
-        scored_patterns.truncate(MAX_PATTERNS);
+        if let Some(explain) = explain.as_deref_mut() {
+            scored_patterns.iter().skip(max_patterns).for_each(|(p, _)| explain.mark(p.id, Outcome::OverLimit));
+        }
+        scored_patterns.truncate(max_patterns);
 
         debug!("Ranked {} patterns by similarity (filtered by tech stack + causal)", scored_patterns.len());
-        patterns = scored_patterns.into_iter().map(|(p, _)| p).collect();
+        scored_patterns
     } else {
-        patterns.truncate(MAX_PATTERNS);
-    }
+        if let Some(explain) = explain.as_deref_mut() {
+            patterns.iter().skip(max_patterns).for_each(|p| explain.mark(p.id, Outcome::OverLimit));
+        }
+        patterns.truncate(max_patterns);
+        patterns.into_iter().map(|p| (p, 0.0)).collect()
+    };
 
     // If similarity filtering returned empty due to tech stack mismatch,
     // try to provide generic helpful patterns with a caveat
-    if patterns.is_empty() && !query.is_empty() {
+    if ranked.is_empty() && !query.is_empty() {
         debug!("Similarity filtering returned 0 patterns - trying generic fallback");
 
         // Get top patterns without tech stack filtering for generic guidance
         // These are high-quality patterns that might still be helpful
-        let fallback_patterns: Vec<Pattern> = store.get_top_patterns(PATTERNS_TO_SCORE)?
+        let fallback_patterns: Vec<(Pattern, f64)> = source
+            .fallback(&primary_types, query, max_patterns + repeated.len())?
             .into_iter()
-            .filter(|p| primary_types.iter().any(|t| p.tool_type.eq_ignore_ascii_case(t)))
-            .take(MAX_PATTERNS)
+            .filter(|p| !repeated.contains(&p.id))
+            .take(max_patterns)
+            .map(|p| (p, 0.0))
             .collect();
 
         if !fallback_patterns.is_empty() {
             debug!("Using {} generic fallback patterns", fallback_patterns.len());
-            return format_generic_patterns(&fallback_patterns);
+            if let Some(explain) = explain.as_deref_mut() {
+                explain.fallback = true;
+                fallback_patterns.iter().for_each(|(p, _)| explain.consider(p));
+            }
+            return format_generic_patterns(&fallback_patterns, config, tokens, explain);
         }
     }
 
-    if !patterns.is_empty() {
-        return format_success_patterns(&patterns);
+    if !ranked.is_empty() {
+        return format_success_patterns(&ranked, config, tokens, explain);
     }
 
     // No patterns found at all
@@ -291,146 +670,140 @@ fn query_patterns(tool: &str, query: &str) -> Result<ContextInjection> {
     })
 }
 
+/// Candidates read straight from the database, when there is no snapshot
+struct DatabaseSource {
+    store: PatternStore,
+}
 
-/// Filter out patterns that conflict with top-ranked patterns
-/// This uses causal edges to prevent recommending incompatible patterns together
-/// OPTIMIZATION: Skip causal filtering for small result sets to reduce latency
-fn filter_causal_conflicts(db_path: &std::path::Path, mut patterns: Vec<(Pattern, f64)>) -> Vec<(Pattern, f64)> {
-    // Skip causal filtering entirely if we have few patterns
-    // The overhead of opening another DB connection isn't worth it for small sets
-    if patterns.len() <= MAX_PATTERNS + 1 {
-        return patterns;
+impl CandidateSource for DatabaseSource {
+    fn top(&self, tool_type: &str, category: Option<&str>, limit: usize) -> Result<Vec<(Pattern, f64)>> {
+        self.store.get_top_by_tool(tool_type, category, limit)
     }
 
-    // Try to open causal store, skip filtering if it fails
-    let causal_store = match CausalStore::open_readonly(db_path) {
-        Ok(store) => store,
-        Err(_) => return patterns, // No causal data available
-    };
+    /// Falls back to the input order if there is no causal data
+    fn select_compatible(&self, ranked: &[i64], limit: usize) -> Vec<i64> {
+        self.store
+            .select_compatible(ranked, limit)
+            .unwrap_or_else(|_| ranked.to_vec())
+    }
 
-    // Get conflicts for the top pattern
-    let top_pattern_id = patterns[0].0.id;
-    let conflicts = match causal_store.get_conflicts(top_pattern_id) {
-        Ok(c) => c,
-        Err(_) => return patterns,
-    };
+    fn fallback(&self, tool_types: &[&str], _query: &str, limit: usize) -> Result<Vec<Pattern>> {
+        let mut candidates: Vec<(Pattern, f64)> = Vec::new();
+        for tool_type in tool_types {
+            candidates.extend(self.store.get_top_by_tool(tool_type, None, PATTERNS_TO_SCORE.max(limit))?);
+        }
+        candidates.retain(|(p, _)| !p.risky);
+        candidates.sort_by(|(a, qa), (b, qb)| ranking::best_first((*qa, a.success_count), (*qb, b.success_count)));
+        Ok(candidates.into_iter().take(limit).map(|(p, _)| p).collect())
+    }
 
-    if conflicts.is_empty() {
-        return patterns;
+    fn scorer(&self, query: &str) -> Scorer {
+        Scorer::new(query, &self.store.corpus())
     }
+}
 
-    // Filter out conflicting patterns
-    let original_len = patterns.len();
-    patterns.retain(|(p, _)| !conflicts.contains(&p.id));
+/// Select a conflict-free set of patterns using causal edges
+/// Keeps the higher-ranked side of each conflict and prefers synergistic
+/// companions of the top pattern.
+fn filter_causal_conflicts(
+    source: &dyn CandidateSource,
+    patterns: Vec<(Pattern, f64)>,
+    max_patterns: usize,
+) -> Vec<(Pattern, f64)> {
+    let ranked: Vec<i64> = patterns.iter().map(|(p, _)| p.id).collect();
+    let selected = source.select_compatible(&ranked, max_patterns);
 
-    let filtered = original_len - patterns.len();
-    if filtered > 0 {
-        debug!("Filtered {} conflicting patterns based on causal edges", filtered);
-    }
+    let original_len = patterns.len();
+    let mut by_id: std::collections::HashMap<i64, (Pattern, f64)> =
+        patterns.into_iter().map(|entry| (entry.0.id, entry)).collect();
+    let filtered: Vec<(Pattern, f64)> = selected.iter().filter_map(|id| by_id.remove(id)).collect();
 
-    patterns
+    debug!("Causal selection kept {} of {} candidates", filtered.len(), original_len);
+    filtered
 }
 
 /// Format success patterns into context block
-fn format_success_patterns(patterns: &[Pattern]) -> Result<ContextInjection> {
+fn format_success_patterns(
+    patterns: &[(Pattern, f64)],
+    config: &InjectionConfig,
+    tokens: TokenBudget,
+    explain: Option<&mut Explanation>,
+) -> Result<ContextInjection> {
+    format_patterns("**Relevant patterns from previous successful operations:**", patterns, config, tokens, explain)
+}
+
+/// Format generic patterns as fallback (when no tech-specific match)
+fn format_generic_patterns(
+    patterns: &[(Pattern, f64)],
+    config: &InjectionConfig,
+    tokens: TokenBudget,
+    explain: Option<&mut Explanation>,
+) -> Result<ContextInjection> {
+    format_patterns("**General patterns (no tech-specific matches found):**", patterns, config, tokens, explain)
+}
+
+/// Format patterns under a heading, stopping once the token budget is spent
+fn format_patterns(
+    heading: &str,
+    patterns: &[(Pattern, f64)],
+    config: &InjectionConfig,
+    mut budget: TokenBudget,
+    mut explain: Option<&mut Explanation>,
+) -> Result<ContextInjection> {
     let mut context_lines = Vec::new();
-    let mut pattern_ids = Vec::new();
+    let mut patterns_used = Vec::new();
     let mut seen_insights: std::collections::HashSet<String> = std::collections::HashSet::new();
 
-    context_lines.push("**Relevant patterns from previous successful operations:**".to_string());
+    budget.consume(heading);
+    context_lines.push(heading.to_string());
     context_lines.push(String::new());
 
-    for pattern in patterns {
+    for (i, (pattern, score)) in patterns.iter().enumerate() {
         // Extract key insight from context_query
         let insight = extract_insight(&pattern.context_query);
 
         // Skip duplicates in output (compare full insight, lowercased)
         let normalized = insight.to_lowercase();
         if seen_insights.contains(&normalized) {
+            if let Some(explain) = explain.as_deref_mut() {
+                explain.mark(pattern.id, Outcome::Duplicate);
+            }
             continue;
         }
         seen_insights.insert(normalized);
 
-        let score = pattern.success_count - pattern.failure_count;
-        let confidence = if pattern.success_count + pattern.failure_count > 0 {
-            (pattern.success_count as f64 / (pattern.success_count + pattern.failure_count) as f64) * 100.0
-        } else {
-            50.0
-        };
-
-        context_lines.push(format!("- **{}** (score: {}, {:.0}% success rate)",
-            pattern.tool_type, score, confidence));
-        context_lines.push(format!("  {}", insight));
-        context_lines.push(String::new());
-
-        pattern_ids.push(pattern.id);
-    }
-
-    Ok(ContextInjection {
-        context_block: context_lines.join("\n"),
-        patterns_used: pattern_ids,
-    })
-}
-
-/// Format generic patterns as fallback (when no tech-specific match)
-fn format_generic_patterns(patterns: &[Pattern]) -> Result<ContextInjection> {
-    let mut context_lines = Vec::new();
-    let mut pattern_ids = Vec::new();
-    let mut seen_insights: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-    context_lines.push("**General patterns (no tech-specific matches found):**".to_string());
-    context_lines.push(String::new());
-
-    for pattern in patterns {
-        let insight = extract_insight(&pattern.context_query);
-
-        // Skip duplicates
-        let normalized = insight.to_lowercase();
-        if seen_insights.contains(&normalized) {
-            continue;
+        let mut entry = render_pattern(config, &PatternFields {
+            id: pattern.id,
+            tool: &pattern.tool_type,
+            success_count: pattern.success_count,
+            failure_count: pattern.failure_count,
+            insight: &insight,
+        });
+        if insight.starts_with("⚠️") {
+            if let Some(advice) = extract_advice(&pattern.context_query) {
+                entry.push_str(&format!("\n  Advice: {}", advice));
+            }
         }
-        seen_insights.insert(normalized);
 
-        let score = pattern.success_count - pattern.failure_count;
-        let confidence = if pattern.success_count + pattern.failure_count > 0 {
-            (pattern.success_count as f64 / (pattern.success_count + pattern.failure_count) as f64) * 100.0
-        } else {
-            50.0
+        let Some(entry) = budget.fit(&entry) else {
+            debug!("Token budget of {} exhausted, dropping remaining patterns", config.max_tokens);
+            if let Some(explain) = explain.as_deref_mut() {
+                patterns[i..].iter().for_each(|(p, _)| explain.mark(p.id, Outcome::TokenBudget));
+            }
+            break;
         };
-
-        context_lines.push(format!("- **{}** (score: {}, {:.0}% success rate)",
-            pattern.tool_type, score, confidence));
-        context_lines.push(format!("  {}", insight));
+        context_lines.push(entry);
         context_lines.push(String::new());
+        if let Some(explain) = explain.as_deref_mut() {
+            explain.mark(pattern.id, Outcome::Injected);
+        }
 
-        pattern_ids.push(pattern.id);
-    }
-
-    Ok(ContextInjection {
-        context_block: context_lines.join("\n"),
-        patterns_used: pattern_ids,
-    })
-}
-
-/// Format failure patterns into warnings (reserved for future use)
-#[allow(dead_code)]
-fn format_failure_patterns(patterns: &[Pattern]) -> Result<ContextInjection> {
-    let mut context_lines = Vec::new();
-    let mut pattern_ids = Vec::new();
-
-    context_lines.push("**Common pitfalls to avoid:**".to_string());
-    context_lines.push(String::new());
-
-    for pattern in patterns {
-        // Extract the error insight
-        let insight = extract_insight(&pattern.context_query);
-        context_lines.push(format!("- ⚠️ {}", insight));
-        pattern_ids.push(pattern.id);
+        patterns_used.push((pattern.id, *score));
     }
 
     Ok(ContextInjection {
         context_block: context_lines.join("\n"),
-        patterns_used: pattern_ids,
+        patterns_used,
     })
 }
 
@@ -490,6 +863,15 @@ fn extract_insight(context_query: &str) -> String {
     truncate_str(context_query.lines().next().unwrap_or(context_query), 80).to_string()
 }
 
+/// Extract the "Advice:" line that accompanies a pitfall, if any
+fn extract_advice(context_query: &str) -> Option<&str> {
+    context_query
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Advice:"))
+        .map(str::trim)
+        .filter(|advice| !advice.is_empty())
+}
+
 /// Format approach string into an actionable hint
 /// Input: "Bash - npm - Initialize project with package.json"
 /// Output: "Ran `npm`: Initialize project with package.json"
@@ -533,26 +915,42 @@ fn truncate_str(s: &str, max_len: usize) -> &str {
     }
 }
 
+/// Command category of the tool input, as learning assigns it to patterns
+fn command_category(tool: &str, fields: &ToolInputFields) -> Option<String> {
+    let tool_name = match tool {
+        "edit" => "Edit",
+        "bash" => "Bash",
+        "task" => "Task",
+        "read" | "grep" => "Read",
+        _ => tool,
+    };
+    let input = serde_json::json!({
+        "command": fields.command,
+        "file_path": fields.file_path,
+        "subagent_type": fields.subagent_type,
+    });
+    crate::learning::extract_command_category(tool_name, &input)
+}
+
+/// Repository signals for the session's working directory (ours if the hook input has none)
+fn repo_signals(cwd: Option<&str>, config: &InjectionConfig) -> Option<RepoSignals> {
+    if !config.repo_signals {
+        return None;
+    }
+    let cwd = cwd.map(PathBuf::from).or_else(|| std::env::current_dir().ok())?;
+    Some(RepoSignals::for_dir(&get_mana_dir().ok()?, &cwd))
+}
+
 /// Get MANA data directory with caching for performance
 /// Uses a static cache to avoid repeated filesystem checks
 fn get_mana_dir() -> Result<PathBuf> {
     use std::sync::OnceLock;
     static MANA_DIR: OnceLock<PathBuf> = OnceLock::new();
 
-    Ok(MANA_DIR.get_or_init(|| {
-        // Check project-local .mana first
-        if let Ok(cwd) = std::env::current_dir() {
-            let project_mana = cwd.join(".mana");
-            if project_mana.exists() {
-                return project_mana;
-            }
-        }
-
-        // Fall back to home directory
-        dirs::home_dir()
-            .map(|h| h.join(".mana"))
-            .unwrap_or_else(|| PathBuf::from(".mana"))
-    }).clone())
+    // Profile resolution reads the environment and at most two paths
+    Ok(MANA_DIR
+        .get_or_init(|| crate::profile::resolve_mana_dir().unwrap_or_else(|_| PathBuf::from(".mana")))
+        .clone())
 }
 
 fn build_query(tool: &str, input: &ToolInputFields) -> String {
@@ -0,0 +1,186 @@
+//! Claude Code hook events beyond pre-tool injection (`mana hook <event>`)
+//!
+//! Every event gets its JSON on stdin; `mana hook` reads it and dispatches:
+//!
+//! - `stop` / `session-end`: the session-end handler (session memory, learning)
+//! - `subagent-stop`: a finished subagent's tool calls count towards the
+//!   learning threshold like a finished turn does
+//! - `pre-compact`: the session's working memory is refreshed from the
+//!   transcript before it is compacted, so the goal and recent failures
+//!   survive in the injected session state
+//! - `notification`: messages Claude Code showed the user (permission
+//!   prompts, idle waits) are appended to `notifications.jsonl`
+//!
+//! Handlers never fail the hook: problems are logged and the event is
+//! dropped. In read-only mode nothing is written.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::session_memory;
+use crate::get_mana_dir;
+
+/// Notification log inside the MANA data directory
+pub const NOTIFICATIONS_FILE: &str = "notifications.jsonl";
+
+/// Notifications kept; older ones are dropped when the log is trimmed
+const MAX_NOTIFICATIONS: usize = 500;
+
+/// Events `mana hook` handles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HookEvent {
+    #[value(alias = "Stop")]
+    Stop,
+    #[value(alias = "SessionEnd")]
+    SessionEnd,
+    #[value(alias = "SubagentStop")]
+    SubagentStop,
+    #[value(alias = "PreCompact")]
+    PreCompact,
+    #[value(alias = "Notification")]
+    Notification,
+}
+
+impl HookEvent {
+    /// Name of the event in Claude Code settings
+    pub fn settings_name(self) -> &'static str {
+        match self {
+            HookEvent::Stop => "Stop",
+            HookEvent::SessionEnd => "SessionEnd",
+            HookEvent::SubagentStop => "SubagentStop",
+            HookEvent::PreCompact => "PreCompact",
+            HookEvent::Notification => "Notification",
+        }
+    }
+}
+
+/// Fields Claude Code sends with Stop, SessionEnd, SubagentStop, PreCompact and Notification
+#[derive(Debug, Default, Deserialize)]
+pub struct EventInput {
+    pub session_id: Option<String>,
+    pub transcript_path: Option<PathBuf>,
+    pub hook_event_name: Option<String>,
+    /// Notification text
+    pub message: Option<String>,
+    /// PreCompact: "manual" or "auto"
+    pub trigger: Option<String>,
+}
+
+/// Hook input from stdin; empty when run by hand from a terminal
+pub fn read_input() -> EventInput {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return EventInput::default();
+    }
+    let mut input = String::new();
+    if stdin.lock().read_to_string(&mut input).is_err() {
+        return EventInput::default();
+    }
+    serde_json::from_str(&input).unwrap_or_default()
+}
+
+/// A recorded notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: Option<String>,
+    pub message: String,
+}
+
+/// Handle `mana hook <event>`
+pub async fn dispatch(event: HookEvent) -> Result<()> {
+    if matches!(event, HookEvent::Stop | HookEvent::SessionEnd) {
+        return super::session_end().await;
+    }
+
+    let input = read_input();
+    let mana_dir = get_mana_dir()?;
+    if crate::storage::read_only::enabled() {
+        debug!("Read-only mode, ignoring {} hook", event.settings_name());
+        return Ok(());
+    }
+    std::fs::create_dir_all(&mana_dir)?;
+
+    let result = match event {
+        HookEvent::SubagentStop => super::session_end_handler::learn_if_due(&mana_dir).await,
+        HookEvent::PreCompact => pre_compact(&mana_dir, &input),
+        HookEvent::Notification => record_notification(&mana_dir, &input),
+        HookEvent::Stop | HookEvent::SessionEnd => unreachable!("handled above"),
+    };
+    if let Err(e) = result {
+        warn!("{} hook failed: {}", event.settings_name(), e);
+    }
+    Ok(())
+}
+
+/// Save the session's working memory before the transcript is compacted
+fn pre_compact(mana_dir: &Path, input: &EventInput) -> Result<()> {
+    let (Some(session_id), Some(transcript)) = (&input.session_id, &input.transcript_path) else {
+        return Ok(());
+    };
+    session_memory::refresh(mana_dir, session_id, transcript)?;
+    info!("Saved session state before {} compaction", input.trigger.as_deref().unwrap_or("a"));
+    Ok(())
+}
+
+/// Append a notification to the log, trimming it to the newest [`MAX_NOTIFICATIONS`]
+pub fn record_notification(mana_dir: &Path, input: &EventInput) -> Result<()> {
+    let Some(message) = input.message.as_deref().map(str::trim).filter(|m| !m.is_empty()) else {
+        return Ok(());
+    };
+    let notification =
+        Notification { timestamp: Utc::now(), session_id: input.session_id.clone(), message: message.to_string() };
+    let path = mana_dir.join(NOTIFICATIONS_FILE);
+    let mut line = serde_json::to_string(&notification)?;
+    line.push('\n');
+    std::fs::OpenOptions::new().create(true).append(true).open(&path)?.write_all(line.as_bytes())?;
+
+    // Trim rarely: only once the log holds twice what's kept
+    let content = std::fs::read_to_string(&path)?;
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() > MAX_NOTIFICATIONS * 2 {
+        let tmp = path.with_extension(format!("jsonl.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, lines[lines.len() - MAX_NOTIFICATIONS..].join("\n") + "\n")?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notifications(mana_dir: &Path) -> Vec<Notification> {
+        std::fs::read_to_string(mana_dir.join(NOTIFICATIONS_FILE))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_notifications_are_recorded_and_trimmed() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = |message: &str| EventInput {
+            session_id: Some("s1".into()),
+            message: Some(message.into()),
+            ..Default::default()
+        };
+        record_notification(temp.path(), &input("Claude needs your permission to use Bash")).unwrap();
+        record_notification(temp.path(), &input("   ")).unwrap();
+        let recorded = notifications(temp.path());
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].session_id.as_deref(), Some("s1"));
+
+        for i in 0..MAX_NOTIFICATIONS * 2 {
+            record_notification(temp.path(), &input(&format!("waiting {}", i))).unwrap();
+        }
+        let recorded = notifications(temp.path());
+        assert_eq!(recorded.len(), MAX_NOTIFICATIONS);
+        assert_eq!(recorded.last().unwrap().message, format!("waiting {}", MAX_NOTIFICATIONS * 2 - 1));
+    }
+}
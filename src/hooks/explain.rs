@@ -0,0 +1,241 @@
+//! Dry-run explanation of a context injection
+//!
+//! `mana inject --explain` runs the same ranking as the pre-hook but records
+//! what happened to every candidate pattern: its similarity and score
+//! components, and the stage that kept or dropped it. Nothing is printed for
+//! Claude Code and nothing is spooled. The latency budget is reported rather
+//! than enforced, so a cold cache doesn't hide the ranking.
+
+use serde::Serialize;
+use std::fmt::Write;
+
+use super::repo_signals::RepoSignals;
+use crate::config::InjectionConfig;
+use crate::storage::Pattern;
+
+/// What happened to a candidate pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Rendered into the context block
+    Injected,
+    /// Destructive pattern that isn't approved yet
+    Risky,
+    /// Past the number of candidates that get scored
+    NotScored,
+    /// Similarity below the tech-stack threshold
+    BelowThreshold,
+    /// Dropped by causal conflict selection
+    Conflict,
+    /// Ranked below `max_patterns`
+    OverLimit,
+    /// Same insight as a higher-ranked pattern
+    Duplicate,
+    /// Didn't fit in the token budget
+    TokenBudget,
+    /// Already injected into the session within the repeat window
+    Repeated,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Injected => "injected",
+            Outcome::Risky => "risky",
+            Outcome::NotScored => "not scored",
+            Outcome::BelowThreshold => "below threshold",
+            Outcome::Conflict => "causal conflict",
+            Outcome::OverLimit => "over limit",
+            Outcome::Duplicate => "duplicate",
+            Outcome::TokenBudget => "token budget",
+            Outcome::Repeated => "already injected",
+        }
+    }
+}
+
+/// A pattern the ranking looked at
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub id: i64,
+    pub tool_type: String,
+    pub success_count: i64,
+    pub failure_count: i64,
+    /// Query similarity, with the tech-stack modifier applied
+    pub similarity: Option<f64>,
+    /// Success component of the score, (successes - failures) / 10; for
+    /// pitfalls, how often they were hit (failures / 10)
+    pub success_score: Option<f64>,
+    /// Final ranking score: 0.6 × similarity + 0.4 × success
+    pub score: Option<f64>,
+    pub outcome: Option<Outcome>,
+    /// First line of the pattern's context
+    pub context: String,
+}
+
+/// Everything `inject --explain` reports
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Explanation {
+    pub tool: String,
+    pub query: String,
+    pub category: Option<String>,
+    /// Working directory signals added to the query
+    pub repo: Option<RepoSignals>,
+    /// Where candidates came from: "snapshot", "database" or "none"
+    pub source: String,
+    pub threshold: f64,
+    pub max_patterns: usize,
+    pub max_tokens: usize,
+    pub candidates: Vec<Candidate>,
+    /// Nothing passed the similarity filter, so top patterns were used instead
+    pub fallback: bool,
+    /// The session is in the experiment control group and would get nothing
+    pub control: bool,
+    pub elapsed_us: u128,
+    pub budget_ms: u64,
+    /// The block as it would be injected, empty if nothing would be
+    pub block: String,
+}
+
+impl Explanation {
+    pub fn new(tool: &str, query: &str, category: Option<String>, config: &InjectionConfig, budget_ms: u64) -> Self {
+        Self {
+            tool: tool.to_string(),
+            query: query.to_string(),
+            category,
+            source: "none".to_string(),
+            max_patterns: config.max_patterns,
+            max_tokens: config.max_tokens,
+            budget_ms,
+            ..Default::default()
+        }
+    }
+
+    /// Add a candidate, or reconsider one seen earlier
+    pub fn consider(&mut self, pattern: &Pattern) {
+        if let Some(candidate) = self.candidates.iter_mut().find(|c| c.id == pattern.id) {
+            candidate.outcome = None;
+            return;
+        }
+        self.candidates.push(Candidate {
+            id: pattern.id,
+            tool_type: pattern.tool_type.clone(),
+            success_count: pattern.success_count,
+            failure_count: pattern.failure_count,
+            similarity: None,
+            success_score: None,
+            score: None,
+            outcome: None,
+            context: pattern.context_query.lines().next().unwrap_or("").to_string(),
+        });
+    }
+
+    pub fn score(&mut self, id: i64, similarity: f64, success_score: f64, score: f64) {
+        if let Some(candidate) = self.candidates.iter_mut().find(|c| c.id == id) {
+            candidate.similarity = Some(similarity);
+            candidate.success_score = Some(success_score);
+            candidate.score = Some(score);
+        }
+    }
+
+    pub fn mark(&mut self, id: i64, outcome: Outcome) {
+        if let Some(candidate) = self.candidates.iter_mut().find(|c| c.id == id) {
+            candidate.outcome = Some(outcome);
+        }
+    }
+
+    /// Human-readable report
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let category = self.category.as_deref().map(|c| format!(" (category {})", c)).unwrap_or_default();
+        let _ = writeln!(out, "Injection for {}: {:?}{}", self.tool, self.query, category);
+        if let Some(repo) = &self.repo {
+            let markers = if repo.markers.is_empty() { "no markers".to_string() } else { repo.markers.join(", ") };
+            let branch = repo.branch.as_deref().map(|b| format!(", branch {}", b)).unwrap_or_default();
+            let _ = writeln!(out, "Repo: {}{}", markers, branch);
+        }
+        let _ = writeln!(
+            out,
+            "Source: {}, similarity threshold {:.2}, up to {} patterns in {} tokens",
+            self.source, self.threshold, self.max_patterns, self.max_tokens
+        );
+        let over = if self.elapsed_us > u128::from(self.budget_ms) * 1000 { " (over budget, the hook would pass through)" } else { "" };
+        let _ = writeln!(out, "Took {:.1}ms of a {}ms budget{}", self.elapsed_us as f64 / 1000.0, self.budget_ms, over);
+        if self.fallback {
+            let _ = writeln!(out, "No pattern passed the similarity threshold, used generic fallback patterns");
+        }
+        if self.control {
+            let _ = writeln!(out, "Session is in the experiment control group: the hook would inject nothing");
+        }
+        let _ = writeln!(out);
+
+        if self.candidates.is_empty() {
+            let _ = writeln!(out, "No candidate patterns");
+        } else {
+            let _ = writeln!(out, "{:>6}  {:<10} {:>6} {:>7} {:>6}  {:<16} Context", "ID", "Tool", "Sim", "Success", "Score", "Outcome");
+            let num = |v: Option<f64>| v.map(|v| format!("{:.3}", v)).unwrap_or_else(|| "-".to_string());
+            // Best-scored first; unscored candidates keep their fetch order at the end
+            let mut rows: Vec<&Candidate> = self.candidates.iter().collect();
+            rows.sort_by(|a, b| b.score.unwrap_or(f64::MIN).total_cmp(&a.score.unwrap_or(f64::MIN)));
+            for c in rows {
+                let context: String = c.context.chars().take(60).collect();
+                let _ = writeln!(
+                    out,
+                    "{:>6}  {:<10} {:>6} {:>7} {:>6}  {:<16} {}",
+                    c.id,
+                    c.tool_type,
+                    num(c.similarity),
+                    num(c.success_score),
+                    num(c.score),
+                    c.outcome.map_or("-", Outcome::label),
+                    context
+                );
+            }
+        }
+        let _ = writeln!(out);
+
+        if self.block.is_empty() {
+            let _ = writeln!(out, "Nothing would be injected");
+        } else {
+            let _ = writeln!(out, "Rendered block:");
+            out.push_str(&self.block);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(id: i64) -> Pattern {
+        Pattern {
+            id,
+            pattern_hash: format!("h{}", id),
+            tool_type: "Bash".to_string(),
+            command_category: Some("cargo".to_string()),
+            context_query: "Task: build\nApproach: Bash - cargo build".to_string(),
+            success_count: 3,
+            failure_count: 0,
+            embedding_id: None,
+            risky: false,
+        }
+    }
+
+    #[test]
+    fn test_explanation_tracks_outcomes() {
+        let mut explanation = Explanation::new("bash", "cargo build", None, &InjectionConfig::default(), 10);
+        explanation.consider(&pattern(1));
+        explanation.consider(&pattern(2));
+        explanation.score(1, 0.8, 0.3, 0.6);
+        explanation.mark(1, Outcome::Injected);
+        explanation.mark(2, Outcome::BelowThreshold);
+        explanation.consider(&pattern(2));
+        assert_eq!(explanation.candidates.len(), 2);
+        assert_eq!(explanation.candidates[1].outcome, None);
+
+        let report = explanation.render();
+        assert!(report.contains("injected"));
+        assert!(report.contains("0.800"));
+        assert!(report.contains("Nothing would be injected"));
+    }
+}
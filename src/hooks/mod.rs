@@ -3,9 +3,18 @@
 //! Pre-hooks inject context from ReasoningBank before tool execution.
 //! Session-end hooks trigger learning when threshold is met.
 
+pub mod budget;
 mod context_injection;
+pub mod events;
+pub mod explain;
+pub mod pitfalls;
+pub mod protocol;
+pub mod repo_signals;
 pub mod session_end_handler;
+pub mod session_memory;
+pub mod settings;
+pub mod template;
 
-pub use context_injection::inject_context;
+pub use context_injection::{explain_injection, inject_context, replay_call};
 pub use session_end_handler::session_end;
 // AccumulatorState is used directly via crate::hooks::session_end_handler::AccumulatorState
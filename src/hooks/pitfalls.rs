@@ -0,0 +1,124 @@
+//! Known pitfalls: failure patterns injected as their own section
+//!
+//! Failure patterns (`tool_type = "failure"`) are retrieved and ranked apart
+//! from success patterns and rendered under their own heading, so a warning
+//! still surfaces when plenty of success patterns match. The section is
+//! charged to the token budget first; success patterns get what's left.
+//! `injection.max_pitfalls` and `injection.pitfall_min_similarity` control it.
+
+use crate::config::InjectionConfig;
+use crate::storage::{Pattern, Scorer};
+
+use super::budget::TokenBudget;
+
+/// Tool type learning gives failure patterns
+pub const TOOL_TYPE: &str = "failure";
+
+/// Heading of the pitfalls section
+pub const HEADING: &str = "**Known pitfalls:**";
+
+/// Failure patterns retrieved for scoring
+pub const TO_SCORE: usize = 16;
+
+/// Similarity, hit component and ranking score of a failure pattern for the query
+///
+/// Ranks by similarity first, then by how often the pitfall was hit.
+pub fn score(query: &Scorer, pattern: &Pattern) -> (f64, f64, f64) {
+    let similarity = query.score(&pattern.context_query);
+    let hits = pattern.failure_count.clamp(0, 10) as f64 / 10.0;
+    (similarity, hits, similarity * 0.6 + hits * 0.4)
+}
+
+/// Failure patterns relevant to the query, best first, at most `max_pitfalls`
+pub fn rank(query: &Scorer, candidates: Vec<Pattern>, config: &InjectionConfig) -> Vec<(Pattern, f64)> {
+    if config.max_pitfalls == 0 || query.is_empty() {
+        return Vec::new();
+    }
+    let mut ranked: Vec<(Pattern, f64)> = candidates
+        .into_iter()
+        .filter(|p| p.tool_type == TOOL_TYPE && !p.risky)
+        .filter_map(|p| {
+            let (similarity, _, score) = score(query, &p);
+            (similarity >= config.pitfall_min_similarity).then_some((p, score))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(config.max_pitfalls);
+    ranked
+}
+
+/// One pitfall as a list entry, with its advice
+pub fn entry(pattern: &Pattern) -> String {
+    let field = |name: &str| {
+        pattern
+            .context_query
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    };
+    let pitfall = field("Pitfall:").unwrap_or_else(|| pattern.context_query.lines().next().unwrap_or(""));
+    match field("Advice:") {
+        Some(advice) => format!("- ⚠️ {}\n  Advice: {}", pitfall, advice),
+        None => format!("- ⚠️ {}", pitfall),
+    }
+}
+
+/// The pitfalls section charged to `budget`, with the (id, score) of each pitfall that fit
+pub fn section(pitfalls: &[(Pattern, f64)], budget: &mut TokenBudget) -> Option<(String, Vec<(i64, f64)>)> {
+    if pitfalls.is_empty() {
+        return None;
+    }
+    budget.consume(HEADING);
+    let mut lines = vec![HEADING.to_string(), String::new()];
+    let mut used = Vec::new();
+    for (pattern, score) in pitfalls {
+        let Some(entry) = budget.fit(&entry(pattern)) else { break };
+        lines.push(entry);
+        lines.push(String::new());
+        used.push((pattern.id, *score));
+    }
+    (!used.is_empty()).then(|| (lines.join("\n"), used))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::similarity::Uniform;
+
+    fn failure(id: i64, pitfall: &str, failures: i64) -> Pattern {
+        Pattern {
+            id,
+            pattern_hash: format!("f{}", id),
+            tool_type: TOOL_TYPE.to_string(),
+            command_category: None,
+            context_query: format!("Task: Build/compile project\nPitfall: {}\nAdvice: Check the lockfile", pitfall),
+            success_count: 0,
+            failure_count: failures,
+            embedding_id: None,
+            risky: false,
+        }
+    }
+
+    #[test]
+    fn test_pitfalls_rank_and_render() {
+        let config = InjectionConfig { max_pitfalls: 1, ..Default::default() };
+        let candidates = vec![
+            failure(1, "cargo build failed: linker cc not found for the rust crate", 2),
+            failure(2, "cargo build failed: could not compile the rust crate", 6),
+            failure(3, "npm ERR! missing script: start", 9),
+        ];
+        let ranked = rank(&Scorer::new("Bash cargo rust cargo build", &Uniform), candidates, &config);
+        assert_eq!(ranked.iter().map(|(p, _)| p.id).collect::<Vec<_>>(), vec![2]);
+
+        let mut budget = TokenBudget::new(400);
+        let (text, used) = section(&ranked, &mut budget).unwrap();
+        assert!(text.starts_with(HEADING));
+        assert!(text.contains("- ⚠️ cargo build failed: could not compile"));
+        assert!(text.contains("Advice: Check the lockfile"));
+        assert_eq!(used.len(), 1);
+
+        let off = InjectionConfig { max_pitfalls: 0, ..Default::default() };
+        assert!(rank(&Scorer::new("Bash cargo", &Uniform), vec![failure(1, "cargo build failed badly", 1)], &off).is_empty());
+    }
+}
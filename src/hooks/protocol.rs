@@ -0,0 +1,243 @@
+//! Pre-hook input schemas and the pass-through failure mode
+//!
+//! Claude Code writes the pending tool call to the hook's stdin as JSON and
+//! runs the tool with whatever the hook prints. Three shapes have been sent
+//! over time, detected by their keys:
+//!
+//! - [`HookVersion::Event`]: `hook_event_name`, `tool_name` and a
+//!   `tool_input` object (current releases)
+//! - [`HookVersion::Nested`]: tool fields under `input`
+//! - [`HookVersion::Flat`]: tool fields at the top level
+//!
+//! Each is parsed into a typed schema: known fields must have the right
+//! types, required ones must be present, and unknown ones are ignored so
+//! newer releases that add fields keep working. Input that doesn't fit is
+//! passed through byte for byte without context, and the reason goes to
+//! `hook-errors.jsonl` (never stdout, which Claude Code would read).
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+
+/// Failure log inside the MANA data directory
+pub const ERRORS_FILE: &str = "hook-errors.jsonl";
+
+/// The failure log is cut back to half once it grows past this
+const MAX_ERRORS_BYTES: u64 = 256 * 1024;
+
+/// Event name of the hooks MANA injects from
+const PRE_TOOL_EVENT: &str = "PreToolUse";
+
+/// Shapes of pre-hook input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookVersion {
+    /// `hook_event_name`, `tool_name` and `tool_input`
+    Event,
+    /// Tool fields nested under `input`
+    Nested,
+    /// Tool fields at the top level
+    Flat,
+}
+
+impl HookVersion {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookVersion::Event => "event",
+            HookVersion::Nested => "nested",
+            HookVersion::Flat => "flat",
+        }
+    }
+
+    /// Which shape a payload has; None if it isn't a JSON object
+    pub fn detect(value: &Value) -> Option<Self> {
+        let object = value.as_object()?;
+        Some(if object.contains_key("hook_event_name") || object.contains_key("tool_input") {
+            HookVersion::Event
+        } else if object.contains_key("input") {
+            HookVersion::Nested
+        } else {
+            HookVersion::Flat
+        })
+    }
+}
+
+/// Tool fields MANA builds queries from
+#[derive(Debug, Default, Deserialize)]
+pub struct ToolInputFields {
+    pub file_path: Option<String>,
+    pub command: Option<String>,
+    pub subagent_type: Option<String>,
+    pub description: Option<String>,
+    #[allow(dead_code)]
+    pub content: Option<String>,
+    #[allow(dead_code)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EventPayload {
+    hook_event_name: Option<String>,
+    tool_name: String,
+    tool_input: ToolInputFields,
+    session_id: Option<String>,
+    cwd: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NestedPayload {
+    tool_name: Option<String>,
+    session_id: Option<String>,
+    cwd: Option<String>,
+    input: ToolInputFields,
+}
+
+#[derive(Deserialize)]
+struct FlatPayload {
+    tool_name: Option<String>,
+    session_id: Option<String>,
+    cwd: Option<String>,
+    #[serde(flatten)]
+    fields: ToolInputFields,
+}
+
+/// A validated pre-hook payload, whatever its shape
+#[derive(Debug)]
+pub struct HookInput {
+    pub version: HookVersion,
+    pub tool_name: Option<String>,
+    /// Claude Code session id, used to attribute injections in the log
+    pub session_id: Option<String>,
+    /// Working directory of the session, for repository signals
+    pub cwd: Option<String>,
+    pub fields: ToolInputFields,
+}
+
+impl HookInput {
+    /// Parse and validate hook input against the schema of its detected shape
+    pub fn parse(input: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(input).context("not valid JSON")?;
+        let version = HookVersion::detect(&value).context("expected a JSON object")?;
+        let parsed = match version {
+            HookVersion::Event => {
+                let p: EventPayload = from_value(value, version)?;
+                if let Some(event) = p.hook_event_name.as_deref().filter(|e| *e != PRE_TOOL_EVENT) {
+                    bail!("{} input sent to a {} hook", event, PRE_TOOL_EVENT);
+                }
+                HookInput { version, tool_name: Some(p.tool_name), session_id: p.session_id, cwd: p.cwd, fields: p.tool_input }
+            }
+            HookVersion::Nested => {
+                let p: NestedPayload = from_value(value, version)?;
+                HookInput { version, tool_name: p.tool_name, session_id: p.session_id, cwd: p.cwd, fields: p.input }
+            }
+            HookVersion::Flat => {
+                let p: FlatPayload = from_value(value, version)?;
+                HookInput { version, tool_name: p.tool_name, session_id: p.session_id, cwd: p.cwd, fields: p.fields }
+            }
+        };
+        if parsed.session_id.as_deref().is_some_and(str::is_empty) {
+            bail!("empty session_id");
+        }
+        Ok(parsed)
+    }
+}
+
+fn from_value<T: DeserializeOwned>(value: Value, version: HookVersion) -> Result<T> {
+    serde_json::from_value(value).with_context(|| format!("does not match the {} hook schema", version.name()))
+}
+
+/// Why a hook passed its input through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookFailure {
+    pub timestamp: DateTime<Utc>,
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<HookVersion>,
+    pub error: String,
+    /// Size of the input; the input itself is never logged
+    pub input_bytes: usize,
+}
+
+impl HookFailure {
+    pub fn new(tool: &str, input: &[u8], error: impl std::fmt::Display) -> Self {
+        let version = std::str::from_utf8(input)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Value>(s).ok())
+            .and_then(|v| HookVersion::detect(&v));
+        Self { timestamp: Utc::now(), tool: tool.to_string(), version, error: error.to_string(), input_bytes: input.len() }
+    }
+}
+
+/// Append a failure to the log; errors here are swallowed, the hook must still pass through
+pub fn log_failure(mana_dir: &Path, failure: &HookFailure) {
+    let _ = append_failure(mana_dir, failure);
+}
+
+fn append_failure(mana_dir: &Path, failure: &HookFailure) -> Result<()> {
+    std::fs::create_dir_all(mana_dir)?;
+    let path = mana_dir.join(ERRORS_FILE);
+    let mut line = serde_json::to_string(failure)?;
+    line.push('\n');
+    std::fs::OpenOptions::new().create(true).append(true).open(&path)?.write_all(line.as_bytes())?;
+
+    if std::fs::metadata(&path)?.len() > MAX_ERRORS_BYTES {
+        let content = std::fs::read_to_string(&path)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let tmp = path.with_extension(format!("jsonl.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, lines[lines.len() / 2..].join("\n") + "\n")?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    Ok(())
+}
+
+/// Logged failures from the last `hours`, oldest first
+pub fn recent_failures(mana_dir: &Path, hours: i64) -> Vec<HookFailure> {
+    let since = Utc::now() - Duration::hours(hours);
+    std::fs::read_to_string(mana_dir.join(ERRORS_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HookFailure>(line).ok())
+        .filter(|failure| failure.timestamp >= since)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_each_version_and_reject_bad_input() {
+        let event = HookInput::parse(
+            r#"{"hook_event_name":"PreToolUse","session_id":"s1","tool_name":"Bash","tool_input":{"command":"cargo test"},"permission_mode":"default"}"#,
+        )
+        .unwrap();
+        assert_eq!(event.version, HookVersion::Event);
+        assert_eq!(event.fields.command.as_deref(), Some("cargo test"));
+        let nested = HookInput::parse(r#"{"tool_name":"Edit","input":{"file_path":"src/lib.rs"}}"#).unwrap();
+        assert_eq!((nested.version, nested.fields.file_path.as_deref()), (HookVersion::Nested, Some("src/lib.rs")));
+        assert_eq!(HookInput::parse(r#"{"command":"ls"}"#).unwrap().version, HookVersion::Flat);
+
+        for bad in [
+            "not json",
+            "[1, 2]",
+            r#"{"hook_event_name":"PreToolUse","tool_input":{"command":"ls"}}"#,
+            r#"{"hook_event_name":"PostToolUse","tool_name":"Bash","tool_input":{}}"#,
+            r#"{"tool_name":"Bash","tool_input":"ls"}"#,
+            r#"{"input":{"command":42}}"#,
+            r#"{"session_id":"","command":"ls"}"#,
+        ] {
+            assert!(HookInput::parse(bad).is_err(), "{}", bad);
+        }
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let input = br#"{"tool_name":"Bash","tool_input":"ls"}"#;
+        log_failure(temp.path(), &HookFailure::new("bash", input, "does not match"));
+        let failures = recent_failures(temp.path(), 1);
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].version, failures[0].input_bytes), (Some(HookVersion::Event), input.len()));
+    }
+}
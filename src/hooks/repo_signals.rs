@@ -0,0 +1,226 @@
+//! Repository signals for injection queries
+//!
+//! Tool input often says nothing about the tech stack: editing a README or
+//! running `make test` looks the same in a Rust and a Node repo. The
+//! working directory does, through marker files such as `Cargo.toml` or
+//! `package.json`. Those are added to the query when the tool input has no
+//! stack of its own, so a cargo pattern scores low in a Node repo. Words from
+//! the git branch name (`fix/auth-timeout` → `auth timeout`) are added too.
+//!
+//! Walking up the tree for markers costs a few stat calls, so the result is
+//! cached per working directory in `.mana/repo-signals.json`. The branch is
+//! read from `.git/HEAD` every time; it changes far more often.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage::similarity::has_tech_stack;
+
+/// Cache of detected markers, keyed by working directory
+const CACHE_FILE: &str = "repo-signals.json";
+
+/// Cached detections are redone after this long (markers appear with `cargo init`)
+const CACHE_TTL_SECS: i64 = 600;
+
+/// Working directories kept in the cache
+const CACHE_ENTRIES: usize = 64;
+
+/// Directories walked up from the working directory when looking for markers
+const MAX_DEPTH: usize = 8;
+
+/// Marker files and the query words they stand for
+const MARKERS: &[(&str, &str)] = &[
+    ("Cargo.toml", "rust cargo"),
+    ("package.json", "javascript npm node"),
+    ("tsconfig.json", "typescript npm node"),
+    ("pyproject.toml", "python pip"),
+    ("requirements.txt", "python pip"),
+    ("setup.py", "python pip"),
+    ("go.mod", "golang go.mod"),
+    ("Gemfile", "ruby gem"),
+    ("pom.xml", "java maven"),
+    ("build.gradle", "java gradle"),
+    ("CMakeLists.txt", "cpp cmake"),
+];
+
+/// Branch name words that say nothing about the task
+const BRANCH_NOISE: &[&str] = &[
+    "main", "master", "develop", "dev", "trunk", "head", "feature", "feat", "fix", "bugfix", "hotfix", "release",
+    "chore", "wip",
+];
+
+/// What the working directory says about the project
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RepoSignals {
+    /// Marker files found in the nearest directory that has any
+    pub markers: Vec<String>,
+    /// Git directory of the enclosing repository
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_dir: Option<PathBuf>,
+    /// Current branch (not cached)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+impl RepoSignals {
+    /// Signals for `cwd`, from the cache in `mana_dir` when it's fresh
+    pub fn for_dir(mana_dir: &Path, cwd: &Path) -> Self {
+        let key = cwd.to_string_lossy().into_owned();
+        let now = chrono::Utc::now().timestamp();
+        let cache_path = mana_dir.join(CACHE_FILE);
+        let mut cache: HashMap<String, CachedSignals> =
+            fs::read(&cache_path).ok().and_then(|bytes| serde_json::from_slice(&bytes).ok()).unwrap_or_default();
+
+        let mut signals = match cache.get(&key) {
+            Some(cached) if now - cached.checked_at < CACHE_TTL_SECS => cached.signals.clone(),
+            _ => {
+                let signals = detect(cwd);
+                cache.insert(key, CachedSignals { signals: signals.clone(), checked_at: now });
+                if cache.len() > CACHE_ENTRIES {
+                    let mut ages: Vec<i64> = cache.values().map(|c| c.checked_at).collect();
+                    ages.sort_unstable();
+                    let cutoff = ages[cache.len() - CACHE_ENTRIES];
+                    cache.retain(|_, c| c.checked_at >= cutoff);
+                }
+                // Losing a racing write only costs a re-detection
+                let tmp = cache_path.with_extension("json.tmp");
+                if let Ok(bytes) = serde_json::to_vec(&cache) {
+                    if !crate::storage::read_only::enabled() && fs::write(&tmp, bytes).is_ok() {
+                        let _ = fs::rename(&tmp, &cache_path);
+                    }
+                }
+                signals
+            }
+        };
+        signals.branch = signals.git_dir.as_deref().and_then(current_branch);
+        signals
+    }
+
+    /// Query words for the markers, without repeats
+    pub fn stack_hint(&self) -> String {
+        let mut words: Vec<&str> = Vec::new();
+        for marker in &self.markers {
+            let Some((_, hint)) = MARKERS.iter().find(|(name, _)| name == marker) else { continue };
+            for word in hint.split_whitespace() {
+                if !words.contains(&word) {
+                    words.push(word);
+                }
+            }
+        }
+        words.join(" ")
+    }
+
+    /// Words from the branch name that describe the task
+    pub fn branch_words(&self) -> Vec<String> {
+        let Some(branch) = &self.branch else { return Vec::new() };
+        branch
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .filter(|w| w.len() > 2 && !w.chars().all(|c| c.is_ascii_digit()) && !BRANCH_NOISE.contains(&w.as_str()))
+            .take(4)
+            .collect()
+    }
+
+    /// `query` with the stack hint (if the query has no stack) and branch words
+    pub fn enrich(&self, query: String) -> String {
+        let mut query = query;
+        let hint = self.stack_hint();
+        // Every Bash query names the shell; only the command says anything about the stack
+        let own = query.strip_prefix("Bash ").unwrap_or(&query);
+        if !hint.is_empty() && !has_tech_stack(own) {
+            query.push(' ');
+            query.push_str(&hint);
+        }
+        for word in self.branch_words() {
+            query.push(' ');
+            query.push_str(&word);
+        }
+        query
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedSignals {
+    #[serde(flatten)]
+    signals: RepoSignals,
+    checked_at: i64,
+}
+
+/// Walk up from `cwd` to the repository root looking for markers
+pub fn detect(cwd: &Path) -> RepoSignals {
+    let mut signals = RepoSignals::default();
+    for dir in cwd.ancestors().take(MAX_DEPTH) {
+        if signals.markers.is_empty() {
+            signals.markers = MARKERS
+                .iter()
+                .filter(|(name, _)| dir.join(name).is_file())
+                .map(|(name, _)| name.to_string())
+                .collect();
+        }
+        let dot_git = dir.join(".git");
+        if dot_git.exists() {
+            signals.git_dir = git_dir(&dot_git);
+            break;
+        }
+    }
+    signals
+}
+
+/// The git directory behind a `.git` entry (a directory, or a worktree's `gitdir:` file)
+fn git_dir(dot_git: &Path) -> Option<PathBuf> {
+    if dot_git.is_dir() {
+        return Some(dot_git.to_path_buf());
+    }
+    let text = fs::read_to_string(dot_git).ok()?;
+    let target = Path::new(text.strip_prefix("gitdir:")?.trim());
+    Some(match dot_git.parent() {
+        Some(parent) if target.is_relative() => parent.join(target),
+        _ => target.to_path_buf(),
+    })
+}
+
+/// Branch checked out in `git_dir`, None when detached
+fn current_branch(git_dir: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim().strip_prefix("ref: refs/heads/").map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detects_markers_and_branch() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().join("web");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src/components")).unwrap();
+        fs::write(repo.join("package.json"), "{}").unwrap();
+        fs::write(repo.join(".git/HEAD"), "ref: refs/heads/fix/login-timeout-42\n").unwrap();
+        let mana_dir = temp.path().join(".mana");
+        fs::create_dir_all(&mana_dir).unwrap();
+
+        let signals = RepoSignals::for_dir(&mana_dir, &repo.join("src/components"));
+        assert_eq!(signals.markers, vec!["package.json"]);
+        assert_eq!(signals.branch.as_deref(), Some("fix/login-timeout-42"));
+        assert_eq!(signals.branch_words(), vec!["login", "timeout"]);
+        assert!(mana_dir.join(CACHE_FILE).is_file());
+
+        // Stack-less queries get the repo's stack; queries with their own keep it
+        let query = signals.enrich("Editing md markdown file README.md".to_string());
+        assert!(query.contains("javascript npm node"));
+        assert!(query.ends_with("login timeout"));
+        assert!(signals.enrich("Bash make build make".to_string()).contains("npm"));
+        assert!(!signals.enrich("Bash cargo rust cargo".to_string()).contains("npm"));
+
+        // A cached detection is reused, the branch is always re-read
+        fs::write(repo.join("Cargo.toml"), "").unwrap();
+        fs::write(repo.join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        let cached = RepoSignals::for_dir(&mana_dir, &repo.join("src/components"));
+        assert_eq!(cached.markers, vec!["package.json"]);
+        assert!(cached.branch_words().is_empty());
+    }
+}
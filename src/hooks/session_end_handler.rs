@@ -1,18 +1,23 @@
 //! Session end handler
 //!
 //! Parses recent JSONL logs, updates accumulator state, and triggers
-//! learning when trajectory count reaches threshold.
+//! learning when trajectory count reaches threshold. Also keeps the
+//! session's working memory (`session_memory`) current after each turn
+//! and drops it when the session ends.
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
+use super::events::{self, EventInput};
+use super::session_memory;
+use crate::get_mana_dir;
 use crate::learning;
 
-const DEFAULT_THRESHOLD: u32 = 15;
-
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct AccumulatorState {
     pub trajectory_count: u32,
@@ -24,22 +29,85 @@ pub struct AccumulatorState {
 }
 
 impl AccumulatorState {
-    pub fn load(path: &std::path::Path) -> Result<Self> {
-        match std::fs::read(path) {
-            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
-            Err(_) => Ok(Self::default()),
+    /// Load state, falling back to the last good copy if the file is damaged
+    ///
+    /// A truncated or corrupt state file (e.g. from a crash mid-write on a
+    /// filesystem without atomic rename) is moved aside rather than failing
+    /// every later session end.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(_) => return Ok(Self::default()),
+        };
+        let err = match serde_json::from_slice(&bytes) {
+            Ok(state) => return Ok(state),
+            Err(e) => e,
+        };
+
+        warn!("Learning state {:?} is unreadable ({}), recovering", path, err);
+        let _ = std::fs::rename(path, path.with_extension("json.corrupt"));
+
+        let backup = std::fs::read(backup_path(path))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok());
+        match backup {
+            Some(state) => {
+                info!("Recovered learning state from backup");
+                Ok(state)
+            }
+            None => {
+                warn!("No usable backup; starting with empty learning state");
+                Ok(Self::default())
+            }
         }
     }
 
-    pub fn save(&self, path: &std::path::Path) -> Result<()> {
-        let tmp = path.with_extension("tmp");
+    /// Write state atomically, keeping the previous version as a backup
+    pub fn save(&self, path: &Path) -> Result<()> {
+        // Per-process temp name so concurrent writers never share a file
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
         let json = serde_json::to_string_pretty(self)?;
-        std::fs::write(&tmp, json)?;
+        {
+            let mut file = std::fs::File::create(&tmp)?;
+            file.write_all(json.as_bytes())?;
+            file.sync_all()?;
+        }
+        if path.exists() {
+            let _ = std::fs::copy(path, backup_path(path));
+        }
         std::fs::rename(tmp, path)?;
         Ok(())
     }
 }
 
+fn backup_path(path: &Path) -> PathBuf {
+    path.with_extension("json.bak")
+}
+
+/// Refresh the session's working memory after a turn, or forget it at the end
+fn update_session_memory(mana_dir: &Path, input: &EventInput) {
+    let pruned = session_memory::prune_stale(mana_dir, Utc::now());
+    if pruned > 0 {
+        debug!("Pruned {} stale session memories", pruned);
+    }
+    let Some(session_id) = input.session_id.as_deref() else {
+        return;
+    };
+    if input.hook_event_name.as_deref() == Some("SessionEnd") {
+        session_memory::clear(mana_dir, session_id);
+    } else if let Some(transcript) = &input.transcript_path {
+        if let Err(e) = session_memory::refresh(mana_dir, session_id, transcript) {
+            debug!("Failed to update session memory: {}", e);
+        }
+    }
+}
+
+/// How long a session end waits for another learner before deferring
+///
+/// Deferring loses nothing: file offsets are only advanced under the lock,
+/// so the next session end counts the same trajectories.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Process session end event
 ///
 /// 1. Find JSONL log files
@@ -51,20 +119,37 @@ pub async fn session_end() -> Result<()> {
 
     // Get MANA data directory
     let mana_dir = get_mana_dir()?;
+    if crate::storage::read_only::enabled() {
+        info!("Read-only mode, not learning from this session");
+        return Ok(());
+    }
     std::fs::create_dir_all(&mana_dir)?;
+    update_session_memory(&mana_dir, &events::read_input());
+    learn_if_due(&mana_dir).await
+}
+
+/// Count new trajectories in the Claude Code logs and learn once enough have accumulated
+///
+/// Shared by session ends and subagent completions (`mana hook subagent-stop`).
+pub async fn learn_if_due(mana_dir: &Path) -> Result<()> {
+    // Serialize with other session ends and `mana watch`
+    let Some(_lock) = learning::lock::acquire(mana_dir, LOCK_TIMEOUT)? else {
+        info!("Another learning run is in progress, deferring to the next session end");
+        return Ok(());
+    };
 
     let state_path = mana_dir.join("learning-state.json");
     let mut state = AccumulatorState::load(&state_path)?;
 
-    // Find Claude Code log directory
-    let claude_logs = get_claude_logs_dir();
-    if !claude_logs.exists() {
-        debug!("Claude logs directory not found: {:?}", claude_logs);
+    // Find Claude Code logs under every configured root
+    let jsonl_files = learning::collect_log_files(mana_dir)?;
+    if jsonl_files.is_empty() {
+        debug!("No Claude logs found");
         return Ok(());
     }
 
     // Count new trajectories from JSONL files
-    let (new_trajectories, updated_positions) = count_new_trajectories(&claude_logs, &state)?;
+    let (new_trajectories, updated_positions) = count_new_trajectories(jsonl_files, &state)?;
 
     state.trajectory_count += new_trajectories;
     state.last_file_positions.extend(updated_positions);
@@ -75,9 +160,10 @@ pub async fn session_end() -> Result<()> {
     );
 
     // Check threshold
-    if state.trajectory_count >= DEFAULT_THRESHOLD {
+    let threshold = crate::config::load_config(mana_dir).learning.threshold;
+    if state.trajectory_count >= threshold {
         info!("Threshold reached ({} >= {}), triggering learning",
-              state.trajectory_count, DEFAULT_THRESHOLD);
+              state.trajectory_count, threshold);
 
         // Run foreground learning
         match learning::foreground_learn(&state.pending_files).await {
@@ -113,28 +199,8 @@ pub async fn session_end() -> Result<()> {
     Ok(())
 }
 
-fn get_mana_dir() -> Result<PathBuf> {
-    // Check for .mana directory in current project first
-    let cwd = std::env::current_dir()?;
-    let project_mana = cwd.join(".mana");
-    if project_mana.exists() {
-        return Ok(project_mana);
-    }
-
-    // Fall back to home directory
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".mana"))
-}
-
-fn get_claude_logs_dir() -> PathBuf {
-    dirs::home_dir()
-        .map(|h| h.join(".claude/projects"))
-        .unwrap_or_else(|| PathBuf::from(".claude/projects"))
-}
-
 fn count_new_trajectories(
-    logs_dir: &std::path::Path,
+    jsonl_files: Vec<PathBuf>,
     state: &AccumulatorState,
 ) -> Result<(u32, std::collections::HashMap<PathBuf, u64>)> {
     use std::fs::File;
@@ -143,34 +209,6 @@ fn count_new_trajectories(
     let mut total_new = 0u32;
     let mut updated_positions = std::collections::HashMap::new();
 
-    // Collect all JSONL files from logs_dir and its subdirectories
-    let mut jsonl_files = Vec::new();
-
-    let entries = match std::fs::read_dir(logs_dir) {
-        Ok(e) => e,
-        Err(e) => {
-            debug!("Could not read logs dir {:?}: {}", logs_dir, e);
-            return Ok((0, updated_positions));
-        }
-    };
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if path.is_dir() {
-            // Check subdirectory for JSONL files
-            if let Ok(subentries) = std::fs::read_dir(&path) {
-                for subentry in subentries.flatten() {
-                    let subpath = subentry.path();
-                    if subpath.extension().map(|e| e == "jsonl").unwrap_or(false) {
-                        jsonl_files.push(subpath);
-                    }
-                }
-            }
-        } else if path.extension().map(|e| e == "jsonl").unwrap_or(false) {
-            jsonl_files.push(path);
-        }
-    }
-
     debug!("Found {} JSONL files to process", jsonl_files.len());
 
     for path in jsonl_files {
@@ -238,3 +276,27 @@ fn count_new_trajectories(
 
     Ok((total_new, updated_positions))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncated_state_recovers_from_backup() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("learning-state.json");
+
+        let mut state = AccumulatorState { trajectory_count: 4, ..Default::default() };
+        state.save(&path).unwrap();
+        state.trajectory_count = 7;
+        state.save(&path).unwrap();
+
+        // Simulate a crash that left a half-written file
+        std::fs::write(&path, "{\"trajectory_count\": 9, \"pend").unwrap();
+
+        let recovered = AccumulatorState::load(&path).unwrap();
+        assert_eq!(recovered.trajectory_count, 4);
+        assert!(path.with_extension("json.corrupt").exists());
+        assert_eq!(AccumulatorState::load(&temp.path().join("missing.json")).unwrap().trajectory_count, 0);
+    }
+}
@@ -0,0 +1,352 @@
+//! Short-term working memory for one session
+//!
+//! Patterns are long-term memory: what worked across many sessions. Within
+//! a session Claude also benefits from a reminder of where it is: what the
+//! user last asked for, which files it has been editing and which commands
+//! just failed. The inject hook records touched files, the Stop hook reads
+//! the goal and failed commands from the session transcript, and both keep
+//! them in `sessions/<session_id>.json`. Injection prepends them as a
+//! compact "session state" block. `SessionEnd` deletes the file; files of
+//! sessions that never reported their end are pruned after a day.
+//!
+//! The same file remembers which patterns were injected recently, so the
+//! repeat window can be honoured without opening the database or reading
+//! the injection spool on every cold inject.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::budget::truncate_to_tokens;
+use crate::config::InjectionConfig;
+
+/// Directory of session files inside the MANA data directory
+pub const DIR: &str = "sessions";
+
+/// Most recently touched files kept
+const MAX_FILES: usize = 5;
+
+/// Most recent failed commands kept
+const MAX_FAILED: usize = 3;
+
+/// Most recently injected patterns kept for the repeat window
+const MAX_INJECTED: usize = 64;
+
+const GOAL_CHARS: usize = 160;
+const COMMAND_CHARS: usize = 100;
+
+/// Only the end of a transcript is read for the goal and failures
+const TRANSCRIPT_TAIL_BYTES: u64 = 256 * 1024;
+
+/// Session files untouched for this long are removed
+const STALE_HOURS: i64 = 24;
+
+/// What MANA remembers about a running session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMemory {
+    /// Latest user request
+    pub goal: Option<String>,
+    /// Files edited or written, most recent last
+    pub files: Vec<String>,
+    /// Commands that failed, most recent last
+    pub failed_commands: Vec<String>,
+    /// Injected pattern ids and when they were shown, most recent last
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub injected: Vec<(i64, DateTime<Utc>)>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Session file path; None for ids that aren't safe as file names
+fn path(mana_dir: &Path, session_id: &str) -> Option<PathBuf> {
+    let safe = !session_id.is_empty()
+        && session_id.len() <= 128
+        && session_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    safe.then(|| mana_dir.join(DIR).join(format!("{}.json", session_id)))
+}
+
+impl SessionMemory {
+    /// The session's memory, empty if there is none yet
+    pub fn load(mana_dir: &Path, session_id: &str) -> Self {
+        path(mana_dir, session_id)
+            .and_then(|path| std::fs::read(path).ok())
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write atomically, so a concurrent hook never reads half a file
+    pub fn save(&mut self, mana_dir: &Path, session_id: &str) -> Result<()> {
+        let Some(path) = path(mana_dir, session_id) else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(mana_dir.join(DIR))?;
+        self.updated_at = Some(Utc::now());
+        let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.goal.is_none() && self.files.is_empty() && self.failed_commands.is_empty()
+    }
+
+    /// Remember a touched file; false if it already was the latest
+    pub fn touch_file(&mut self, file: &str) -> bool {
+        if self.files.last().is_some_and(|last| last == file) {
+            return false;
+        }
+        push_recent(&mut self.files, file.to_string(), MAX_FILES);
+        true
+    }
+
+    /// Remember injected patterns, dropping ones shown before `since`
+    pub fn note_injected(&mut self, ids: &[i64], at: DateTime<Utc>, since: DateTime<Utc>) {
+        self.injected.retain(|(id, shown)| *shown >= since && !ids.contains(id));
+        self.injected.extend(ids.iter().map(|id| (*id, at)));
+        if self.injected.len() > MAX_INJECTED {
+            self.injected.drain(..self.injected.len() - MAX_INJECTED);
+        }
+    }
+
+    /// Patterns injected at or after `since`
+    pub fn injected_since(&self, since: DateTime<Utc>) -> HashSet<i64> {
+        self.injected.iter().filter(|(_, shown)| *shown >= since).map(|(id, _)| *id).collect()
+    }
+
+    /// Take the goal and failed commands from the session transcript
+    pub fn update_from_transcript(&mut self, transcript: &Path) -> Result<()> {
+        let (goal, failed) = scan_transcript(transcript)?;
+        if goal.is_some() {
+            self.goal = goal;
+        }
+        for command in failed {
+            push_recent(&mut self.failed_commands, command, MAX_FAILED);
+        }
+        Ok(())
+    }
+
+    /// The compact block injected ahead of patterns, None when empty
+    pub fn render(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut block = String::from("**Session state:**\n");
+        if let Some(goal) = &self.goal {
+            block.push_str(&format!("- Goal: {}\n", goal));
+        }
+        if !self.files.is_empty() {
+            let names: Vec<&str> = self.files.iter().rev().map(String::as_str).collect();
+            block.push_str(&format!("- Recently touched: {}\n", names.join(", ")));
+        }
+        for command in self.failed_commands.iter().rev() {
+            block.push_str(&format!("- Failed: `{}`\n", command));
+        }
+        Some(block)
+    }
+}
+
+fn push_recent(list: &mut Vec<String>, item: String, max: usize) {
+    list.retain(|existing| *existing != item);
+    list.push(item);
+    if list.len() > max {
+        list.drain(..list.len() - max);
+    }
+}
+
+fn clip(text: &str, max: usize) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= max {
+        return line;
+    }
+    format!("{}...", line.chars().take(max.saturating_sub(3)).collect::<String>())
+}
+
+/// Latest user request and failed Bash commands in the transcript's tail
+fn scan_transcript(transcript: &Path) -> Result<(Option<String>, Vec<String>)> {
+    let mut file = std::fs::File::open(transcript)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TRANSCRIPT_TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+
+    let mut goal = None;
+    let mut commands: HashMap<String, String> = HashMap::new();
+    let mut failed = Vec::new();
+    // The first line may be cut off; it just doesn't parse
+    for line in tail.lines() {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else { continue };
+        let content = entry.pointer("/message/content");
+        match entry.get("type").and_then(Value::as_str) {
+            Some("user") => {
+                if let Some(text) = content.and_then(Value::as_str).filter(|t| is_request(t)) {
+                    goal = Some(clip(text, GOAL_CHARS));
+                }
+                for item in content.and_then(Value::as_array).into_iter().flatten() {
+                    match item.get("type").and_then(Value::as_str) {
+                        Some("text") => {
+                            if let Some(text) = item.get("text").and_then(Value::as_str).filter(|t| is_request(t)) {
+                                goal = Some(clip(text, GOAL_CHARS));
+                            }
+                        }
+                        Some("tool_result") if item.get("is_error").and_then(Value::as_bool) == Some(true) => {
+                            let id = item.get("tool_use_id").and_then(Value::as_str).unwrap_or_default();
+                            if let Some(command) = commands.get(id) {
+                                failed.push(command.clone());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some("assistant") => {
+                for item in content.and_then(Value::as_array).into_iter().flatten() {
+                    if item.get("type").and_then(Value::as_str) != Some("tool_use")
+                        || item.get("name").and_then(Value::as_str) != Some("Bash")
+                    {
+                        continue;
+                    }
+                    let id = item.get("id").and_then(Value::as_str).unwrap_or_default();
+                    if let Some(command) = item.pointer("/input/command").and_then(Value::as_str) {
+                        commands.insert(id.to_string(), clip(command, COMMAND_CHARS));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((goal, failed))
+}
+
+/// Typed text from the user, as opposed to command output and reminders
+fn is_request(text: &str) -> bool {
+    let text = text.trim();
+    text.len() > 5 && !text.starts_with('<') && !text.contains("<command-") && !text.starts_with("Caveat:")
+}
+
+/// The session state block to inject, within a quarter of the token budget
+pub fn state_block(mana_dir: &Path, session_id: Option<&str>, config: &InjectionConfig) -> Option<String> {
+    let session_id = session_id.filter(|_| config.session_state)?;
+    let block = SessionMemory::load(mana_dir, session_id).render()?;
+    Some(truncate_to_tokens(block.trim_end(), config.max_tokens / 4))
+}
+
+/// Update the session's goal and failed commands from its transcript
+///
+/// Run after each turn (Stop) and before compaction (PreCompact).
+pub fn refresh(mana_dir: &Path, session_id: &str, transcript: &Path) -> Result<()> {
+    if !crate::config::load_config(mana_dir).injection.session_state {
+        return Ok(());
+    }
+    let mut memory = SessionMemory::load(mana_dir, session_id);
+    memory.update_from_transcript(transcript)?;
+    memory.save(mana_dir, session_id)
+}
+
+/// Record a touched file for the session (inject hook)
+pub fn note_file(mana_dir: &Path, session_id: &str, file: &str) -> Result<()> {
+    let mut memory = SessionMemory::load(mana_dir, session_id);
+    if memory.touch_file(file) {
+        memory.save(mana_dir, session_id)?;
+    }
+    Ok(())
+}
+
+/// Record patterns shown to the session, if the repeat window is enabled
+pub fn note_injected(mana_dir: &Path, session_id: &str, ids: &[i64], config: &InjectionConfig) -> Result<()> {
+    let now = Utc::now();
+    let Some(since) = config.repeat_since(now).filter(|_| !ids.is_empty()) else {
+        return Ok(());
+    };
+    let mut memory = SessionMemory::load(mana_dir, session_id);
+    memory.note_injected(ids, now, since);
+    memory.save(mana_dir, session_id)
+}
+
+/// Patterns shown to the session since `since`
+pub fn recently_injected(mana_dir: &Path, session_id: &str, since: DateTime<Utc>) -> HashSet<i64> {
+    SessionMemory::load(mana_dir, session_id).injected_since(since)
+}
+
+/// Forget a finished session
+pub fn clear(mana_dir: &Path, session_id: &str) {
+    if let Some(path) = path(mana_dir, session_id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Remove session files not updated for a day; returns how many
+pub fn prune_stale(mana_dir: &Path, now: DateTime<Utc>) -> usize {
+    let Ok(entries) = std::fs::read_dir(mana_dir.join(DIR)) else {
+        return 0;
+    };
+    let cutoff = now - Duration::hours(STALE_HOURS);
+    entries
+        .flatten()
+        .filter(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            modified.is_some_and(|m| DateTime::<Utc>::from(m) < cutoff)
+        })
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_tracks_files_goal_and_failures() {
+        let temp = tempfile::TempDir::new().unwrap();
+        for file in ["a.rs", "b.rs", "a.rs", "c.rs", "d.rs", "e.rs", "f.rs", "f.rs"] {
+            note_file(temp.path(), "s-1", file).unwrap();
+        }
+        let transcript = temp.path().join("s-1.jsonl");
+        std::fs::write(
+            &transcript,
+            [
+                r#"{"type":"user","message":{"content":"fix the failing parser tests"}}"#,
+                r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test parser"}}]}}"#,
+                r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":"failed","is_error":true}]}}"#,
+                r#"{"type":"user","message":{"content":[{"type":"text","text":"<command-name>/clear</command-name>"}]}}"#,
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let mut memory = SessionMemory::load(temp.path(), "s-1");
+        assert_eq!(memory.files, vec!["a.rs", "c.rs", "d.rs", "e.rs", "f.rs"]);
+        memory.update_from_transcript(&transcript).unwrap();
+        memory.save(temp.path(), "s-1").unwrap();
+        let block = SessionMemory::load(temp.path(), "s-1").render().unwrap();
+        assert!(block.contains("- Goal: fix the failing parser tests"), "{}", block);
+        assert!(block.contains("- Recently touched: f.rs, e.rs"), "{}", block);
+        assert!(block.contains("- Failed: `cargo test parser`"), "{}", block);
+
+        // Unsafe ids are never turned into paths
+        note_file(temp.path(), "../escape", "x.rs").unwrap();
+        assert!(SessionMemory::load(temp.path(), "../escape").is_empty());
+
+        // Injected patterns are remembered within the window, without showing up in the block
+        let config = InjectionConfig { repeat_window_mins: 30, ..InjectionConfig::default() };
+        note_injected(temp.path(), "s-1", &[3, 7], &config).unwrap();
+        let mut memory = SessionMemory::load(temp.path(), "s-1");
+        assert_eq!(memory.render().unwrap(), block);
+        let old = Utc::now() - Duration::hours(2);
+        memory.note_injected(&[9], old, old);
+        memory.note_injected(&[7, 8], Utc::now(), Utc::now() - Duration::minutes(30));
+        assert_eq!(memory.injected_since(Utc::now() - Duration::minutes(30)), HashSet::from([3, 7, 8]));
+        memory.save(temp.path(), "s-1").unwrap();
+        assert_eq!(recently_injected(temp.path(), "s-1", Utc::now() - Duration::minutes(30)).len(), 3);
+
+        clear(temp.path(), "s-1");
+        assert!(SessionMemory::load(temp.path(), "s-1").is_empty());
+        note_file(temp.path(), "s-2", "a.rs").unwrap();
+        assert_eq!(prune_stale(temp.path(), Utc::now()), 0);
+        assert_eq!(prune_stale(temp.path(), Utc::now() + Duration::hours(STALE_HOURS + 1)), 1);
+    }
+}
@@ -0,0 +1,367 @@
+//! Claude Code settings discovery and hook installation
+//!
+//! Finds the settings.json files Claude Code reads, reports which MANA
+//! hooks they register, and installs or removes those hooks.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Overrides the user-level Claude Code config directory (`~/.claude`)
+pub const CLAUDE_CONFIG_ENV: &str = "CLAUDE_CONFIG_DIR";
+
+/// Events whose hooks run when a session finishes
+const SESSION_END_EVENTS: &[&str] = &["Stop", "SessionEnd"];
+
+/// Other events and the `mana hook` argument each one dispatches as
+const EVENT_HOOKS: &[(&str, &str)] =
+    &[("PreCompact", "pre-compact"), ("SubagentStop", "subagent-stop"), ("Notification", "notification")];
+
+/// PreToolUse matchers and the `--tool` each one injects for
+const INJECT_HOOKS: &[(&str, &str)] = &[("Write|Edit|MultiEdit", "edit"), ("Bash", "bash"), ("Task", "task")];
+
+/// Outcome of installing or removing hooks
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookChange {
+    pub settings_path: PathBuf,
+    /// Copy of the original file, if it existed and was modified
+    pub backup: Option<PathBuf>,
+    /// Whether the file was rewritten
+    pub changed: bool,
+}
+
+/// MANA hooks found in a settings file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HookRegistration {
+    /// Tool matchers with a `mana inject` PreToolUse hook
+    pub inject_matchers: Vec<String>,
+    /// Whether a `mana session-end` hook is registered
+    pub session_end: bool,
+    /// Events with a `mana hook` hook
+    pub events: Vec<String>,
+}
+
+impl HookRegistration {
+    pub fn is_complete(&self) -> bool {
+        !self.inject_matchers.is_empty() && self.session_end
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inject_matchers.is_empty() && !self.session_end && self.events.is_empty()
+    }
+}
+
+/// User-level Claude Code config directory
+pub fn claude_config_dir() -> Option<PathBuf> {
+    match std::env::var_os(CLAUDE_CONFIG_ENV).filter(|v| !v.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => dirs::home_dir().map(|h| h.join(".claude")),
+    }
+}
+
+/// Settings files Claude Code reads, user-level first
+pub fn settings_paths(project_dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = claude_config_dir().map(|d| d.join("settings.json")).into_iter().collect();
+    paths.push(project_dir.join(".claude/settings.json"));
+    paths.push(project_dir.join(".claude/settings.local.json"));
+    paths
+}
+
+/// Read a settings file, treating a missing file as empty
+pub fn read_settings(path: &Path) -> Result<Value> {
+    match std::fs::read_to_string(path) {
+        Ok(content) if content.trim().is_empty() => Ok(Value::Object(Default::default())),
+        Ok(content) => serde_json::from_str(&content).with_context(|| format!("Invalid JSON in {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Value::Object(Default::default())),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Commands registered for one hook event, as (matcher, command) pairs
+fn event_commands<'a>(settings: &'a Value, event: &str) -> Vec<(&'a str, &'a str)> {
+    let groups = settings.pointer(&format!("/hooks/{}", event)).and_then(Value::as_array);
+    groups
+        .into_iter()
+        .flatten()
+        .flat_map(|group| {
+            let matcher = group.get("matcher").and_then(Value::as_str).unwrap_or("*");
+            let hooks = group.get("hooks").and_then(Value::as_array).into_iter().flatten();
+            hooks.filter_map(move |hook| hook.get("command").and_then(Value::as_str).map(|cmd| (matcher, cmd)))
+        })
+        .collect()
+}
+
+/// Global options that take a value, skipped when looking for the subcommand
+const VALUE_OPTIONS: &[&str] = &["--profile", "--trace-file"];
+
+/// Whether a hook command runs the `mana` executable with `subcommand`
+///
+/// Each stage of a pipeline or command list is checked: its program must be
+/// `mana` (or a path ending in `/mana`) and its first argument after the
+/// global options must be `subcommand`. Commands that merely mention
+/// "mana", such as `mana-lint inject` or `echo mana hook`, don't count.
+fn is_mana_command(command: &str, subcommand: &str) -> bool {
+    command.split(['|', '&', ';']).any(|stage| {
+        let mut words = shell_words(stage).into_iter().skip_while(|w| w.contains('=') && !w.starts_with('-'));
+        let Some(program) = words.next() else {
+            return false;
+        };
+        let name = program.rsplit(['/', '\\']).next().unwrap_or(&program);
+        if name != "mana" && name != "mana.exe" {
+            return false;
+        }
+        let mut words = words.peekable();
+        while let Some(word) = words.next() {
+            if VALUE_OPTIONS.contains(&word.as_str()) {
+                words.next();
+            } else if !word.starts_with('-') {
+                return word == subcommand;
+            }
+        }
+        false
+    })
+}
+
+/// Split a command on whitespace, keeping double-quoted words (paths with spaces) whole
+fn shell_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// MANA hooks registered in parsed settings
+pub fn registered_hooks(settings: &Value) -> HookRegistration {
+    let mut registration = HookRegistration::default();
+    for (matcher, command) in event_commands(settings, "PreToolUse") {
+        if is_mana_command(command, "inject") && !registration.inject_matchers.iter().any(|m| m == matcher) {
+            registration.inject_matchers.push(matcher.to_string());
+        }
+    }
+    registration.session_end = SESSION_END_EVENTS
+        .iter()
+        .any(|event| event_commands(settings, event).iter().any(|(_, cmd)| is_mana_command(cmd, "session-end")));
+    for (event, _) in EVENT_HOOKS {
+        if event_commands(settings, event).iter().any(|(_, cmd)| is_mana_command(cmd, "hook")) {
+            registration.events.push(event.to_string());
+        }
+    }
+    registration
+}
+
+/// Command line hooks should use to run this binary
+///
+/// Uses the absolute path of the running executable so hooks don't depend
+/// on PATH, and pins the active `--profile` if one was selected.
+pub fn mana_command() -> Result<String> {
+    let exe = std::env::current_exe().context("Cannot locate the mana executable")?;
+    let exe = exe.to_string_lossy();
+    let mut command = if exe.contains(char::is_whitespace) { format!("\"{}\"", exe) } else { exe.into_owned() };
+
+    if let Ok(profile) = std::env::var(crate::profile::PROFILE_ENV) {
+        if !profile.is_empty() {
+            command.push_str(&format!(" --profile {}", profile));
+        }
+    }
+    Ok(command)
+}
+
+/// Install MANA hooks into a settings file, replacing any earlier ones
+///
+/// `mana_command` is how Claude Code should invoke MANA (usually the
+/// absolute path of this binary). Other hooks are left untouched.
+pub fn install_hooks(path: &Path, mana_command: &str) -> Result<HookChange> {
+    let original = read_settings(path)?;
+    let mut settings = original.clone();
+    remove_mana_hooks(&mut settings);
+
+    let root = settings.as_object_mut().context("Claude Code settings must be a JSON object")?;
+    let hooks = root.entry("hooks").or_insert_with(|| json!({}));
+    let hooks = hooks.as_object_mut().context("\"hooks\" in Claude Code settings must be an object")?;
+
+    let pre_tool = hooks.entry("PreToolUse").or_insert_with(|| json!([]));
+    let pre_tool = pre_tool.as_array_mut().context("\"PreToolUse\" hooks must be an array")?;
+    for (matcher, tool) in INJECT_HOOKS {
+        pre_tool.push(json!({
+            "matcher": matcher,
+            "hooks": [{"type": "command", "command": format!("{} inject --tool {}", mana_command, tool)}],
+        }));
+    }
+
+    // Stop refreshes session memory and may learn; SessionEnd also clears the memory
+    for event in SESSION_END_EVENTS {
+        let groups = hooks.entry(*event).or_insert_with(|| json!([]));
+        let groups = groups.as_array_mut().with_context(|| format!("\"{}\" hooks must be an array", event))?;
+        groups.push(json!({"hooks": [{"type": "command", "command": format!("{} session-end", mana_command)}]}));
+    }
+
+    for (event, name) in EVENT_HOOKS {
+        let groups = hooks.entry(*event).or_insert_with(|| json!([]));
+        let groups = groups.as_array_mut().with_context(|| format!("\"{}\" hooks must be an array", event))?;
+        groups.push(json!({"hooks": [{"type": "command", "command": format!("{} hook {}", mana_command, name)}]}));
+    }
+
+    write_if_changed(path, &original, &settings)
+}
+
+/// Remove every MANA hook from a settings file
+pub fn uninstall_hooks(path: &Path) -> Result<HookChange> {
+    let original = read_settings(path)?;
+    let mut settings = original.clone();
+    remove_mana_hooks(&mut settings);
+    write_if_changed(path, &original, &settings)
+}
+
+/// Strip MANA hook commands, dropping groups and events left empty
+fn remove_mana_hooks(settings: &mut Value) {
+    let Some(hooks) = settings.get_mut("hooks").and_then(Value::as_object_mut) else {
+        return;
+    };
+
+    for (event, groups) in hooks.iter_mut() {
+        let subcommand = if SESSION_END_EVENTS.contains(&event.as_str()) {
+            "session-end"
+        } else if EVENT_HOOKS.iter().any(|(e, _)| e == event) {
+            "hook"
+        } else {
+            "inject"
+        };
+        let Some(groups) = groups.as_array_mut() else {
+            continue;
+        };
+        for group in groups.iter_mut() {
+            if let Some(commands) = group.get_mut("hooks").and_then(Value::as_array_mut) {
+                commands.retain(|hook| {
+                    !hook.get("command").and_then(Value::as_str).is_some_and(|cmd| is_mana_command(cmd, subcommand))
+                });
+            }
+        }
+        groups.retain(|group| group.get("hooks").and_then(Value::as_array).is_none_or(|h| !h.is_empty()));
+    }
+    hooks.retain(|_, groups| groups.as_array().is_none_or(|g| !g.is_empty()));
+
+    if hooks.is_empty() {
+        if let Some(root) = settings.as_object_mut() {
+            root.remove("hooks");
+        }
+    }
+}
+
+/// Back up the original and write `updated` if it differs
+fn write_if_changed(path: &Path, original: &Value, updated: &Value) -> Result<HookChange> {
+    let mut change = HookChange { settings_path: path.to_path_buf(), backup: None, changed: false };
+    if original == updated {
+        return Ok(change);
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if path.exists() {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut backup = path.with_file_name(format!("{}.mana-{}.bak", name, stamp));
+        // Never overwrite an earlier backup (it may be the only copy of the original)
+        let mut n = 1;
+        while backup.exists() {
+            backup = path.with_file_name(format!("{}.mana-{}-{}.bak", name, stamp, n));
+            n += 1;
+        }
+        std::fs::copy(path, &backup).with_context(|| format!("Failed to back up {}", path.display()))?;
+        change.backup = Some(backup);
+    }
+
+    // Write beside the original and rename so Claude Code never reads a partial file
+    let tmp = path.with_extension("json.mana-tmp");
+    std::fs::write(&tmp, format!("{}\n", serde_json::to_string_pretty(updated)?))?;
+    std::fs::rename(&tmp, path)?;
+    change.changed = true;
+    Ok(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_hooks() {
+        let settings = serde_json::json!({
+            "hooks": {
+                "PreToolUse": [
+                    {"matcher": "Write|Edit|MultiEdit", "hooks": [{"type": "command", "command": "cat | .mana/mana inject --tool edit"}]},
+                    {"matcher": "Bash", "hooks": [{"type": "command", "command": "other-tool inject"}]}
+                ],
+                "Stop": [{"hooks": [{"type": "command", "command": "~/.local/bin/mana session-end"}]}]
+            }
+        });
+
+        let registration = registered_hooks(&settings);
+        assert_eq!(registration.inject_matchers, vec!["Write|Edit|MultiEdit"]);
+        assert!(registration.session_end);
+        assert!(registration.is_complete());
+        assert!(registered_hooks(&serde_json::json!({})).is_empty());
+
+        // Only the mana executable counts, with the subcommand as its first argument
+        assert!(is_mana_command("\"/Users/a b/bin/mana\" --profile work --ci inject --tool bash", "inject"));
+        assert!(is_mana_command("MANA_CI=1 mana hook pre-compact", "hook"));
+        assert!(!is_mana_command("mana-lint inject", "inject"));
+        assert!(!is_mana_command("/opt/mana/bin/audit inject", "inject"));
+        assert!(!is_mana_command("echo mana hook", "hook"));
+        assert!(!is_mana_command("mana export --tag inject", "inject"));
+    }
+
+    #[test]
+    fn test_install_and_uninstall_hooks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("settings.json");
+        std::fs::write(
+            &path,
+            r#"{"model": "opus", "hooks": {"PreToolUse": [
+                {"matcher": "Bash", "hooks": [{"type": "command", "command": "audit-log"}]},
+                {"matcher": "Bash", "hooks": [{"type": "command", "command": "/old/mana inject --tool bash"}]}
+            ]}}"#,
+        )
+        .unwrap();
+
+        let change = install_hooks(&path, "/usr/local/bin/mana").unwrap();
+        assert!(change.changed);
+        assert!(change.backup.as_ref().is_some_and(|b| b.exists()));
+
+        let settings = read_settings(&path).unwrap();
+        let registration = registered_hooks(&settings);
+        assert_eq!(registration.inject_matchers, vec!["Write|Edit|MultiEdit", "Bash", "Task"]);
+        assert!(registration.session_end);
+        assert_eq!(event_commands(&settings, "SessionEnd").len(), 1);
+        assert_eq!(registration.events, vec!["PreCompact", "SubagentStop", "Notification"]);
+        assert_eq!(event_commands(&settings, "PreCompact"), vec![("*", "/usr/local/bin/mana hook pre-compact")]);
+        let commands: Vec<&str> = event_commands(&settings, "PreToolUse").into_iter().map(|(_, c)| c).collect();
+        assert!(commands.contains(&"audit-log"));
+        assert!(!commands.iter().any(|c| c.starts_with("/old/mana")));
+
+        // Reinstalling the same command leaves the file alone
+        assert!(!install_hooks(&path, "/usr/local/bin/mana").unwrap().changed);
+
+        assert!(uninstall_hooks(&path).unwrap().changed);
+        let settings = read_settings(&path).unwrap();
+        assert!(registered_hooks(&settings).is_empty());
+        assert_eq!(settings["model"], "opus");
+        assert_eq!(event_commands(&settings, "PreToolUse"), vec![("Bash", "audit-log")]);
+        assert!(settings["hooks"].get("Stop").is_none());
+        assert!(settings["hooks"].get("SessionEnd").is_none());
+        assert!(settings["hooks"].get("Notification").is_none());
+    }
+}
@@ -0,0 +1,139 @@
+//! Output templates for injected context
+//!
+//! A small placeholder system shared by the hook and daemon paths so the
+//! `<mana-context>` block is formatted in exactly one place. Supported
+//! placeholders in `[injection] pattern_format`:
+//! `{id}`, `{tool}`, `{score}`, `{rate}`, `{insight}`.
+//!
+//! The finished block goes through an [`OutputAdapter`]: the Claude tag,
+//! plain markdown, or a JSON line. Retrieval and ranking are the same for
+//! every adapter.
+
+use serde_json::json;
+
+use crate::config::{InjectionConfig, OutputAdapter};
+
+/// Per-pattern format used when scores are shown
+const FORMAT_WITH_SCORES: &str = "- **{tool}** (score: {score}, {rate}% success rate)\n  {insight}";
+
+/// Per-pattern format used when `show_scores = false`
+const FORMAT_WITHOUT_SCORES: &str = "- **{tool}**\n  {insight}";
+
+/// Values substituted into a pattern entry
+pub struct PatternFields<'a> {
+    pub id: i64,
+    pub tool: &'a str,
+    pub success_count: i64,
+    pub failure_count: i64,
+    pub insight: &'a str,
+}
+
+impl PatternFields<'_> {
+    fn score(&self) -> i64 {
+        self.success_count - self.failure_count
+    }
+
+    fn rate(&self) -> f64 {
+        let total = self.success_count + self.failure_count;
+        if total > 0 {
+            (self.success_count as f64 / total as f64) * 100.0
+        } else {
+            50.0
+        }
+    }
+}
+
+/// Render one pattern entry using the configured format
+///
+/// A custom `pattern_format` is used verbatim; otherwise `show_scores`
+/// picks between the built-in formats.
+pub fn render_pattern(config: &InjectionConfig, fields: &PatternFields) -> String {
+    let format = match config.pattern_format.as_deref() {
+        Some(custom) => custom,
+        None if config.show_scores => FORMAT_WITH_SCORES,
+        None => FORMAT_WITHOUT_SCORES,
+    };
+
+    format
+        .replace("{id}", &fields.id.to_string())
+        .replace("{tool}", fields.tool)
+        .replace("{score}", &fields.score().to_string())
+        .replace("{rate}", &format!("{:.0}", fields.rate()))
+        .replace("{insight}", fields.insight)
+}
+
+/// Format a context block for `adapter`, ready to print ahead of the hook input
+///
+/// `patterns` are the injected (pattern id, score) pairs, listed by the
+/// JSON adapter.
+pub fn wrap_context(config: &InjectionConfig, adapter: OutputAdapter, context_block: &str, patterns: &[(i64, f64)]) -> String {
+    match adapter {
+        OutputAdapter::Claude => format!(
+            "<{tag}>\n{block}\n</{tag}>\n\n",
+            tag = config.wrapper_tag,
+            block = context_block
+        ),
+        OutputAdapter::PlainMarkdown => format!("{}\n\n", context_block),
+        OutputAdapter::Json => {
+            let patterns: Vec<_> = patterns.iter().map(|(id, score)| json!({"id": id, "score": score})).collect();
+            format!("{}\n", json!({"mana_context": {"text": context_block, "patterns": patterns}}))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> PatternFields<'static> {
+        PatternFields {
+            id: 7,
+            tool: "Bash",
+            success_count: 3,
+            failure_count: 1,
+            insight: "Ran `cargo`: build",
+        }
+    }
+
+    #[test]
+    fn test_default_format_matches_legacy_output() {
+        let config = InjectionConfig::default();
+        assert_eq!(
+            render_pattern(&config, &fields()),
+            "- **Bash** (score: 2, 75% success rate)\n  Ran `cargo`: build"
+        );
+    }
+
+    #[test]
+    fn test_hide_scores() {
+        let config = InjectionConfig { show_scores: false, ..Default::default() };
+        assert_eq!(render_pattern(&config, &fields()), "- **Bash**\n  Ran `cargo`: build");
+    }
+
+    #[test]
+    fn test_custom_format_and_tag() {
+        let config = InjectionConfig {
+            pattern_format: Some("* [{id}] {insight}".to_string()),
+            wrapper_tag: "memory".to_string(),
+            ..Default::default()
+        };
+        let entry = render_pattern(&config, &fields());
+        assert_eq!(entry, "* [7] Ran `cargo`: build");
+        assert_eq!(
+            wrap_context(&config, OutputAdapter::Claude, &entry, &[]),
+            "<memory>\n* [7] Ran `cargo`: build\n</memory>\n\n"
+        );
+    }
+
+    #[test]
+    fn test_adapters() {
+        let config = InjectionConfig::default();
+        let entry = render_pattern(&config, &fields());
+        assert_eq!(wrap_context(&config, OutputAdapter::PlainMarkdown, &entry, &[]), format!("{}\n\n", entry));
+        let line = wrap_context(&config, OutputAdapter::Json, &entry, &[(7, 0.5)]);
+        assert!(line.ends_with('\n') && !line.trim_end().contains('\n'));
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["mana_context"]["text"], entry);
+        assert_eq!(parsed["mana_context"]["patterns"][0]["id"], 7);
+    }
+}
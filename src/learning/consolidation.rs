@@ -1,50 +1,163 @@
 //! Background consolidation - pattern optimization
 //!
-//! Runs asynchronously after foreground learning to:
-//! - Merge similar patterns
-//! - Decay unused patterns
-//! - Build skill summaries
+//! Runs after foreground learning, on the daemon's schedule or manually, in
+//! stages:
+//! - dedupe: clean up causal edges and merge near-identical patterns
+//! - skills: build skill summaries
+//! - decay: decay unused patterns and prune low-quality ones
+//! - retention: apply the retention policy (see `storage::retention`)
+//! - embed: embed new patterns, within limits (see `embeddings::backfill`)
+//! - vacuum: reclaim free pages
+//!
+//! `mana consolidate --only/--skip` picks stages; all run by default.
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::process::Command;
-use rusqlite::{Connection, params};
+use std::time::Instant;
+use rusqlite::params;
 use tracing::{debug, info, warn};
 
-use crate::storage::calculate_similarity;
+use crate::storage::{terms, Scorer};
+use crate::embeddings::backfill::{self, BackfillReport};
+use crate::storage::retention::{self, RetentionReport};
+
+/// Stage names, in the order they run
+pub const STAGES: &[&str] = &["dedupe", "skills", "decay", "retention", "embed", "vacuum"];
+
+/// A consolidation stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Dedupe,
+    Skills,
+    Decay,
+    Retention,
+    Embed,
+    Vacuum,
+}
 
-/// Run consolidation tasks manually
-pub async fn consolidate() -> Result<()> {
-    info!("Starting consolidation");
+impl Stage {
+    pub const ALL: [Stage; 6] =
+        [Stage::Dedupe, Stage::Skills, Stage::Decay, Stage::Retention, Stage::Embed, Stage::Vacuum];
+
+    pub fn name(self) -> &'static str {
+        STAGES[self as usize]
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match STAGES.iter().position(|s| *s == name) {
+            Some(i) => Ok(Self::ALL[i]),
+            None => bail!("Unknown consolidation stage {:?} (expected one of: {})", name, STAGES.join(", ")),
+        }
+    }
+
+    /// Stages to run: `only` if given, otherwise all but `skip`, in run order
+    pub fn select(only: &[String], skip: &[String]) -> Result<Vec<Stage>> {
+        let only = only.iter().map(|s| Self::parse(s)).collect::<Result<Vec<_>>>()?;
+        let skip = skip.iter().map(|s| Self::parse(s)).collect::<Result<Vec<_>>>()?;
+        Ok(Self::ALL
+            .into_iter()
+            .filter(|stage| (only.is_empty() || only.contains(stage)) && !skip.contains(stage))
+            .collect())
+    }
+}
 
-    let mana_dir = get_mana_dir()?;
+/// A stage that ran and how long it took
+#[derive(Debug, Clone, Serialize)]
+pub struct StageRun {
+    pub stage: Stage,
+    pub elapsed_ms: u64,
+}
+
+/// What a consolidation run changed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsolidationSummary {
+    pub stages: Vec<StageRun>,
+    pub edges_cleaned: usize,
+    pub merged: usize,
+    pub skills: usize,
+    pub decayed: usize,
+    pub pruned: usize,
+    /// Present when the retention stage ran
+    pub retention: Option<RetentionReport>,
+    /// Present when the embed stage found an index to add to
+    pub embedded: Option<BackfillReport>,
+    /// Database size before and after the vacuum stage
+    pub vacuum: Option<(u64, u64)>,
+}
+
+/// Run the given consolidation stages on the database in `mana_dir`
+///
+/// `progress` is called after each stage with the summary so far.
+pub fn consolidate(
+    mana_dir: &Path,
+    stages: &[Stage],
+    progress: &mut dyn FnMut(&StageRun, &ConsolidationSummary),
+) -> Result<ConsolidationSummary> {
+    info!("Starting consolidation");
     let db_path = mana_dir.join("metadata.sqlite");
+    let mut summary = ConsolidationSummary::default();
 
     if !db_path.exists() {
         info!("No database found, skipping consolidation");
-        return Ok(());
+        return Ok(summary);
     }
-
-    // Clean up invalid causal edges first (self-referential, orphaned)
-    let cleaned = cleanup_causal_edges(&db_path)?;
-    if cleaned > 0 {
-        info!("Cleaned up {} invalid causal edges", cleaned);
+    crate::storage::ensure_schema(&db_path)?;
+    let config = crate::config::load_config(mana_dir);
+
+    for &stage in stages {
+        let started = Instant::now();
+        match stage {
+            Stage::Dedupe => {
+                // Clean up invalid causal edges first (self-referential, orphaned)
+                summary.edges_cleaned = cleanup_causal_edges(&db_path)?;
+                if summary.edges_cleaned > 0 {
+                    info!("Cleaned up {} invalid causal edges", summary.edges_cleaned);
+                }
+                summary.merged = merge_similar_patterns(&db_path)?;
+            }
+            Stage::Skills => summary.skills = consolidate_to_skills(&db_path)?,
+            Stage::Decay => {
+                summary.decayed = decay_unused_patterns(&db_path)?;
+                summary.pruned = prune_low_quality_patterns(&db_path)?;
+            }
+            Stage::Retention => {
+                let report = retention::apply(&db_path, &config.retention)?;
+                if !report.evicted.is_empty() && crate::embeddings::is_available(mana_dir) {
+                    for id in &report.evicted {
+                        let _ = crate::embeddings::delete_from_index(mana_dir, *id);
+                    }
+                }
+                summary.retention = Some(report);
+            }
+            Stage::Embed => {
+                if backfill::enabled(mana_dir) {
+                    summary.embedded = Some(backfill::run(mana_dir, backfill::Limits::from_config(&config.embeddings))?);
+                }
+            }
+            Stage::Vacuum => summary.vacuum = Some(crate::storage::maintenance::vacuum(&db_path)?),
+        }
+        let run = StageRun { stage, elapsed_ms: started.elapsed().as_millis() as u64 };
+        debug!("Consolidation stage {} took {}ms", stage.name(), run.elapsed_ms);
+        progress(&run, &summary);
+        summary.stages.push(run);
     }
 
-    // Run consolidation tasks
-    let merged = merge_similar_patterns(&db_path)?;
-    let decayed = decay_unused_patterns(&db_path)?;
-    let pruned = prune_low_quality_patterns(&db_path)?;
-
-    // Consolidate patterns into skills
-    let skills = consolidate_to_skills(&db_path)?;
+    // Merged and evicted patterns must not be offered from the snapshot
+    crate::storage::snapshot::rebuild(mana_dir, &crate::storage::db::open(&db_path)?)?;
 
     info!(
-        "Consolidation complete: merged {} patterns, decayed {}, pruned {}, created {} skills",
-        merged, decayed, pruned, skills
+        "Consolidation complete: merged {} patterns, created {} skills, decayed {}, pruned {}, evicted {}",
+        summary.merged,
+        summary.skills,
+        summary.decayed,
+        summary.pruned,
+        summary.retention.as_ref().map_or(0, |r| r.evicted.len())
     );
-    Ok(())
+    Ok(summary)
 }
 
 /// Clean up invalid causal edges (self-referential, orphaned)
@@ -66,7 +179,7 @@ fn consolidate_to_skills(db_path: &Path) -> Result<usize> {
 
 /// Merge patterns with very high similarity (>90%)
 fn merge_similar_patterns(db_path: &Path) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = crate::storage::db::open(db_path)?;
 
     // Get all patterns grouped by tool type
     let mut stmt = conn.prepare(
@@ -89,6 +202,11 @@ fn merge_similar_patterns(db_path: &Path) -> Result<usize> {
         by_type.entry(tool_type).or_default().push((id, context, success, failure));
     }
 
+    // Weigh terms by rarity: the Task/Approach/Outcome scaffolding every pattern
+    // shares must not make two patterns look alike
+    terms::update(&conn)?;
+    let corpus = terms::Corpus::new(&conn);
+
     let mut merged_count = 0;
     let mut to_delete: Vec<i64> = Vec::new();
 
@@ -103,6 +221,7 @@ fn merge_similar_patterns(db_path: &Path) -> Result<usize> {
             if merged_into.contains_key(&id_i) {
                 continue;
             }
+            let scorer = Scorer::new(ctx_i, &corpus);
 
             for (id_j, ctx_j, success_j, failure_j) in type_patterns.iter().skip(i + 1) {
                 let (id_j, ctx_j, success_j, failure_j) = (*id_j, ctx_j, *success_j, *failure_j);
@@ -112,7 +231,7 @@ fn merge_similar_patterns(db_path: &Path) -> Result<usize> {
                     continue;
                 }
 
-                let similarity = calculate_similarity(ctx_i, ctx_j);
+                let similarity = scorer.score(ctx_j);
 
                 // Very high similarity = merge (90% threshold for consolidation)
                 if similarity > 0.90 {
@@ -134,16 +253,16 @@ fn merge_similar_patterns(db_path: &Path) -> Result<usize> {
     }
 
     // Delete merged patterns
-    for id in &to_delete {
-        conn.execute("DELETE FROM patterns WHERE id = ?", params![id])?;
-    }
+    let tx = conn.unchecked_transaction()?;
+    crate::storage::patterns::delete_with_dependents(&tx, &to_delete)?;
+    tx.commit()?;
 
     Ok(merged_count)
 }
 
 /// Decay patterns that haven't been used recently
 fn decay_unused_patterns(db_path: &Path) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = crate::storage::db::open(db_path)?;
 
     // Decay patterns not used in 7+ days
     let changes = conn.execute(
@@ -161,27 +280,19 @@ fn decay_unused_patterns(db_path: &Path) -> Result<usize> {
 
 /// Prune patterns with very low scores
 fn prune_low_quality_patterns(db_path: &Path) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = crate::storage::db::open(db_path)?;
 
     // Delete patterns with very negative scores (failures > successes + 3)
-    let changes = conn.execute(
-        "DELETE FROM patterns WHERE (success_count - failure_count) < -3",
-        [],
-    )?;
-
-    Ok(changes)
-}
-
-fn get_mana_dir() -> Result<PathBuf> {
-    let cwd = std::env::current_dir()?;
-    let project_mana = cwd.join(".mana");
-    if project_mana.exists() {
-        return Ok(project_mana);
-    }
-
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".mana"))
+    let ids: Vec<i64> = {
+        let mut stmt = conn.prepare("SELECT id FROM patterns WHERE (success_count - failure_count) < -3")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<Result<_, _>>()?
+    };
+    let tx = conn.unchecked_transaction()?;
+    crate::storage::patterns::delete_with_dependents(&tx, &ids)?;
+    tx.commit()?;
+
+    Ok(ids.len())
 }
 
 /// Spawn background consolidation process
@@ -214,3 +325,19 @@ pub fn spawn_consolidation() -> Result<()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_selection() {
+        let names = |stages: Vec<Stage>| stages.into_iter().map(Stage::name).collect::<Vec<_>>();
+        assert_eq!(names(Stage::select(&[], &[]).unwrap()), STAGES);
+        let only = vec!["vacuum".to_string(), "dedupe".to_string()];
+        assert_eq!(names(Stage::select(&only, &[]).unwrap()), vec!["dedupe", "vacuum"]);
+        let skip = vec!["skills".to_string()];
+        assert_eq!(names(Stage::select(&[], &skip).unwrap()), vec!["dedupe", "decay", "retention", "embed", "vacuum"]);
+        assert!(Stage::select(&["compact".to_string()], &[]).is_err());
+    }
+}
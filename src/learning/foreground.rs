@@ -1,29 +1,46 @@
 //! Foreground learning - quick pattern extraction
 //!
-//! Runs synchronously after session-end when threshold is reached.
-//! Latency budget: <1 second.
+//! Runs synchronously after session-end when threshold is reached, or on
+//! demand with `mana learn`.
+//! Log parsing and pattern extraction run in parallel across cores; the
+//! database is written from one thread.
 
 use anyhow::Result;
-use rusqlite::Connection;
+use chrono::{DateTime, Utc};
+use rusqlite::OptionalExtension;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
-use tracing::{debug, info};
-
-use super::trajectory::{parse_trajectories, Trajectory};
-use super::LearningResult;
-use crate::storage::{PatternStore, Pattern, CausalStore};
+use rayon::prelude::*;
+use tracing::{debug, debug_span, info, instrument};
+
+use super::trajectory::{parse_files, Trajectory};
+use super::privacy::ProjectFilter;
+use super::{LearningResult, PlannedPattern};
+use crate::get_mana_dir;
+use crate::storage::{self, PatternStore, Pattern, CausalStore};
 use crate::hooks::session_end_handler::AccumulatorState;
 
 /// Maximum patterns to extract per trajectory (ReasoningBank constraint)
 const MAX_PATTERNS_PER_TRAJECTORY: usize = 3;
 
+/// What a learning pass reads and whether it stores anything
+#[derive(Debug, Clone, Default)]
+pub struct LearnOptions {
+    /// Log files to read instead of the configured log roots
+    pub files: Vec<PathBuf>,
+    /// Only learn from sessions active since this time; older new data is passed over
+    pub since: Option<DateTime<Utc>>,
+    /// Report what would be created and updated without storing anything
+    pub dry_run: bool,
+}
+
 /// Run foreground learning on accumulated trajectories
 ///
 /// Extracts patterns from JSONL logs and stores them in the ReasoningBank.
-/// This runs synchronously and should complete in <1 second.
+/// Every new trajectory contributes; files are parsed in parallel.
 ///
 /// OPTIMIZATION: Uses batch deduplication to reduce DB queries from O(n) to O(1)
 /// where n is the number of patterns extracted. Previously each pattern required
@@ -31,143 +48,216 @@ const MAX_PATTERNS_PER_TRAJECTORY: usize = 3;
 ///
 /// IMPORTANT: Uses last_file_positions to only process NEW trajectories,
 /// preventing score inflation from repeatedly processing the same data.
+#[instrument(level = "debug", name = "learn", skip_all, fields(pending = pending_files.len()))]
 pub async fn foreground_learn(pending_files: &[PathBuf]) -> Result<LearningResult> {
-    let start = Instant::now();
-
     info!("Starting foreground learning with {} pending files", pending_files.len());
+    learn(&get_mana_dir()?, &LearnOptions::default())
+}
 
+/// Learn from new log data now (`mana learn`)
+///
+/// Reads each file from where the last pass stopped, so the same sessions
+/// are never counted twice, whichever command learned them.
+pub fn learn(mana_dir: &Path, options: &LearnOptions) -> Result<LearningResult> {
+    let start = Instant::now();
     let mut result = LearningResult::default();
 
-    // Get MANA data directory
-    let mana_dir = get_mana_dir()?;
-    let db_path = mana_dir.join("metadata.sqlite");
-    let mut store = PatternStore::open(&db_path)?;
-
     // Load learning state to get file positions
     let state_path = mana_dir.join("learning-state.json");
     let state = AccumulatorState::load(&state_path)?;
 
-    // Parse trajectories from all JSONL files in Claude logs
-    let claude_logs = get_claude_logs_dir();
-    if !claude_logs.exists() {
-        info!("Claude logs directory not found, skipping learning");
+    // Collect all JSONL files from the configured Claude log roots
+    let jsonl_files = if options.files.is_empty() {
+        collect_log_files(mana_dir)?
+    } else {
+        options.files.iter().map(std::path::absolute).collect::<std::io::Result<_>>()?
+    };
+    if jsonl_files.is_empty() {
+        info!("No Claude logs found, skipping learning");
         return Ok(result);
     }
-
-    // Collect all JSONL files
-    let jsonl_files = collect_jsonl_files(&claude_logs)?;
     info!("Found {} JSONL files to process", jsonl_files.len());
 
-    // Track which files we actually processed (for updating positions)
-    let mut new_positions: HashMap<PathBuf, u64> = HashMap::new();
-
     // Parse trajectories - USING STORED POSITIONS to only get new data
-    let mut all_trajectories = Vec::new();
-    for file in &jsonl_files {
-        // Get the last processed position for this file (0 if never processed)
-        let start_offset = state.last_file_positions
-            .get(file)
-            .copied()
-            .unwrap_or(0);
-
-        // Get current file size to track new position
-        let file_len = std::fs::metadata(file)
-            .map(|m| m.len())
-            .unwrap_or(0);
-
-        // Skip if we've already processed to the end
-        if start_offset >= file_len {
-            continue;
+    // Files are parsed in parallel (one rayon worker per core); results keep file order
+    let files: Vec<(PathBuf, u64)> = jsonl_files
+        .iter()
+        .filter_map(|file| {
+            // Get the last processed position for this file (0 if never processed)
+            let start_offset = state.last_file_positions.get(file).copied().unwrap_or(0);
+            // Skip if we've already processed to the end
+            let file_len = std::fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+            (start_offset < file_len).then(|| (file.clone(), start_offset))
+        })
+        .collect();
+    let (mut all_trajectories, files) = debug_span!("parse", files = files.len()).in_scope(|| parse_files(&files));
+    if let Some(since) = options.since {
+        all_trajectories.retain(|t| t.ended_at.or(t.started_at).is_some_and(|t| t >= since));
+    }
+
+    // Track where each file was read to (for updating positions); an
+    // unterminated last line is left for next time
+    let new_positions: HashMap<PathBuf, u64> = files.iter().map(|f| (f.path.clone(), f.end_offset)).collect();
+    result.lines_skipped = files.iter().map(|f| f.skipped as u64).sum();
+    if result.lines_skipped > 0 {
+        info!("Skipped {} malformed log lines", result.lines_skipped);
+    }
+    result.files = files;
+
+    info!("Parsed {} trajectories total", all_trajectories.len());
+
+    if options.dry_run {
+        preview(mana_dir, &all_trajectories, &mut result)?;
+        result.duration_ms = start.elapsed().as_millis() as u64;
+        return Ok(result);
+    }
+
+    let mut store = PatternStore::open(&mana_dir.join("metadata.sqlite"))?;
+    let learned = learn_from_trajectories(mana_dir, &mut store, &all_trajectories)?;
+    result.patterns_created = learned.patterns_created;
+    result.trajectories_processed = learned.trajectories_processed;
+
+    // Update file positions in the learning state
+    // This ensures we don't reprocess the same trajectories
+    if !new_positions.is_empty() {
+        let mut updated_state = state;
+        updated_state.last_file_positions.extend(new_positions);
+        if let Err(e) = updated_state.save(&state_path) {
+            debug!("Failed to save updated file positions: {}", e);
+        } else {
+            debug!("Updated file positions for {} files", updated_state.last_file_positions.len());
         }
+    }
 
-        match parse_trajectories(file, start_offset) {
-            Ok(trajectories) => {
-                if !trajectories.is_empty() {
-                    debug!("Parsed {} new trajectories from {:?} (offset {} -> {})",
-                           trajectories.len(), file, start_offset, file_len);
-                    all_trajectories.extend(trajectories);
-                }
-                // Record new position
-                new_positions.insert(file.clone(), file_len);
-            }
-            Err(e) => {
-                debug!("Failed to parse {:?}: {}", file, e);
-            }
+    result.duration_ms = start.elapsed().as_millis() as u64;
+
+    info!(
+        "Foreground learning complete: {} patterns created from {} trajectories in {}ms",
+        result.patterns_created, result.trajectories_processed, result.duration_ms
+    );
+
+    Ok(result)
+}
+
+/// Fill `result` with the patterns learning `trajectories` would create and update
+fn preview(mana_dir: &Path, trajectories: &[Trajectory], result: &mut LearningResult) -> Result<()> {
+    let filter = ProjectFilter::configured(mana_dir);
+    let admitted: Vec<Trajectory> = trajectories.iter().filter(|t| filter.admits(t)).cloned().collect();
+    let patterns = deduplicate_patterns_fast(extract_patterns(&admitted).into_iter().flatten().collect());
+
+    let conn = storage::db::open_readonly(&mana_dir.join("metadata.sqlite"))?;
+    let mut stmt = conn.prepare("SELECT id FROM patterns WHERE pattern_hash = ?1")?;
+    for pattern in patterns {
+        let id: Option<i64> = stmt.query_row([&pattern.pattern_hash], |row| row.get(0)).optional()?;
+        if id.is_some() {
+            result.patterns_updated += 1;
+        } else {
+            result.patterns_created += 1;
         }
+        result.planned.push(PlannedPattern {
+            id,
+            tool_type: pattern.tool_type,
+            context_query: pattern.context_query,
+            success_count: pattern.success_count,
+            failure_count: pattern.failure_count,
+        });
     }
+    result.trajectories_processed = admitted.len() as u32;
+    Ok(())
+}
 
-    info!("Parsed {} trajectories total", all_trajectories.len());
+/// Extract patterns from parsed trajectories and store them
+///
+/// Shared by log learning and `mana import-logs`. Patterns are extracted
+/// from every trajectory in parallel, then inserted from this thread. Logs a
+/// learning event to the database.
+pub(crate) fn learn_from_trajectories(
+    mana_dir: &Path,
+    store: &mut PatternStore,
+    trajectories: &[Trajectory],
+) -> Result<LearningResult> {
+    let db_path = mana_dir.join("metadata.sqlite");
+    let mut result = LearningResult::default();
+
+    let filter = ProjectFilter::configured(mana_dir);
+    let admitted: Vec<Trajectory>;
+    let trajectories = if trajectories.iter().all(|t| filter.admits(t)) {
+        trajectories
+    } else {
+        admitted = trajectories.iter().filter(|t| filter.admits(t)).cloned().collect();
+        debug!("Skipped {} trajectories from excluded projects", trajectories.len() - admitted.len());
+        &admitted
+    };
 
     // OPTIMIZATION: Collect all patterns first, then batch-deduplicate in memory
     // This reduces DB queries from O(n) to O(1) and avoids repeated similarity calculations
+    // Extraction is independent per trajectory, so it runs in parallel; results keep input order
+    let extracted = debug_span!("extract", trajectories = trajectories.len()).in_scope(|| extract_patterns(trajectories));
+
     let mut all_patterns: Vec<Pattern> = Vec::new();
-    let mut edit_count = 0;
-    let mut bash_count = 0;
-
-    for trajectory in all_trajectories.iter().take(100) {
-        // Extract patterns from individual successful tool calls
-        let patterns = extract_per_tool_patterns(trajectory);
-        for pattern in patterns {
-            match pattern.tool_type.as_str() {
-                "Edit" => edit_count += 1,
-                "Bash" => bash_count += 1,
-                _ => {}
-            }
-            all_patterns.push(pattern);
+    // Pattern hashes seen per project, linked once the patterns exist
+    let mut project_hashes: HashMap<String, Vec<String>> = HashMap::new();
+    for (trajectory, patterns) in trajectories.iter().zip(extracted) {
+        if let Some(cwd) = &trajectory.cwd {
+            let project = storage::projects::project_hash(Path::new(cwd));
+            let hashes = project_hashes.entry(project).or_default();
+            hashes.extend(patterns.iter().map(|p| p.pattern_hash.clone()));
         }
-
-        // Also extract failure patterns from error results
-        let failure_patterns = extract_failure_patterns(trajectory);
-        all_patterns.extend(failure_patterns);
-
+        all_patterns.extend(patterns);
         result.trajectories_processed += 1;
     }
+    let edit_count = all_patterns.iter().filter(|p| p.tool_type == "Edit").count();
+    let bash_count = all_patterns.iter().filter(|p| p.tool_type == "Bash").count();
 
     info!("Extracted {} Edit patterns, {} Bash patterns", edit_count, bash_count);
 
     // OPTIMIZATION: In-memory deduplication before DB insertion
     // Uses hash-based deduplication for O(1) lookup instead of O(n) similarity checks
     let dedupe_start = Instant::now();
-    let deduplicated = deduplicate_patterns_fast(all_patterns);
+    let deduplicated = debug_span!("dedupe").in_scope(|| deduplicate_patterns_fast(all_patterns));
     debug!("Deduplicated {} patterns to {} unique in {}ms",
            edit_count + bash_count, deduplicated.len(), dedupe_start.elapsed().as_millis());
 
-    // OPTIMIZATION: Batch insert in single transaction for 10-100x speedup
+    // OPTIMIZATION: Batch insert in chunked transactions for 10-100x speedup
     let insert_start = Instant::now();
-    result.patterns_created = store.insert_batch(&deduplicated)? as u32;
+    let chunk_size = crate::config::load_config(mana_dir).learning.batch_chunk_size;
+    result.patterns_created = debug_span!("insert", patterns = deduplicated.len())
+        .in_scope(|| store.insert_batch(&deduplicated, chunk_size))? as u32;
     debug!("Batch inserted {} patterns in {}ms", result.patterns_created, insert_start.elapsed().as_millis());
 
+    if !project_hashes.is_empty() {
+        let conn = storage::db::open(&db_path)?;
+        for (project, hashes) in &project_hashes {
+            storage::projects::link(&conn, project, hashes.iter().map(String::as_str))?;
+        }
+    }
+
     // Discover causal edges from pattern co-occurrences
-    let causal_edges = discover_causal_edges(&db_path, &all_trajectories)?;
+    let causal_edges = debug_span!("causal").in_scope(|| discover_causal_edges(&db_path, trajectories))?;
     if causal_edges > 0 {
         info!("Discovered {} causal edges from co-occurrences", causal_edges);
     }
 
-    // Log learning event to database
+    debug_span!("snapshot").in_scope(|| storage::snapshot::rebuild(mana_dir, &storage::db::open(&db_path)?))?;
     log_learning_event(&db_path, &result)?;
-
-    // Update file positions in the learning state
-    // This ensures we don't reprocess the same trajectories
-    if !new_positions.is_empty() {
-        let mut updated_state = state;
-        updated_state.last_file_positions.extend(new_positions);
-        if let Err(e) = updated_state.save(&state_path) {
-            debug!("Failed to save updated file positions: {}", e);
-        } else {
-            debug!("Updated file positions for {} files", updated_state.last_file_positions.len());
-        }
-    }
-
-    result.duration_ms = start.elapsed().as_millis() as u64;
-
-    info!(
-        "Foreground learning complete: {} patterns created from {} trajectories in {}ms",
-        result.patterns_created, result.trajectories_processed, result.duration_ms
-    );
-
     Ok(result)
 }
 
+/// Patterns from each trajectory, in input order
+///
+/// Patterns from individual successful tool calls come first, then failure
+/// patterns from error results.
+pub(crate) fn extract_patterns(trajectories: &[Trajectory]) -> Vec<Vec<Pattern>> {
+    trajectories
+        .par_iter()
+        .map(|trajectory| {
+            let mut patterns = extract_per_tool_patterns(trajectory);
+            patterns.extend(extract_failure_patterns(trajectory));
+            patterns
+        })
+        .collect()
+}
+
 /// Fast in-memory pattern deduplication using hash-based grouping
 ///
 /// Groups patterns by (tool_type, command_category) and keeps only unique ones.
@@ -233,6 +323,14 @@ fn extract_per_tool_patterns(trajectory: &Trajectory) -> Vec<Pattern> {
 
                 let pattern_hash = hash_string(&context_query);
 
+                // Destructive commands are stored but held back from injection until approved
+                let risky = tool_call.tool_name == "Bash"
+                    && tool_call.tool_input.get("command")
+                        .and_then(|v| v.as_str())
+                        .and_then(super::risk::classify_command_risk)
+                        .inspect(|reason| debug!("Flagging risky Bash pattern: {}", reason))
+                        .is_some();
+
                 patterns.push(Pattern {
                     id: 0,  // Will be set by database
                     pattern_hash,
@@ -242,6 +340,7 @@ fn extract_per_tool_patterns(trajectory: &Trajectory) -> Vec<Pattern> {
                     success_count: 1,
                     failure_count: 0,
                     embedding_id: None,
+                    risky,
                 });
             }
             _ => continue,
@@ -254,7 +353,7 @@ fn extract_per_tool_patterns(trajectory: &Trajectory) -> Vec<Pattern> {
 /// Extract command category for grouping similar patterns
 /// For Bash: returns the primary command (cargo, npm, git, etc.)
 /// For Edit/Write: returns the file extension (rs, ts, py, etc.)
-fn extract_command_category(tool_name: &str, input: &serde_json::Value) -> Option<String> {
+pub fn extract_command_category(tool_name: &str, input: &serde_json::Value) -> Option<String> {
     match tool_name {
         "Bash" => {
             let cmd = input.get("command")
@@ -361,6 +460,7 @@ fn extract_success_patterns(trajectory: &Trajectory) -> Vec<Pattern> {
             success_count: 1,
             failure_count: 0,
             embedding_id: None,
+            risky: false,
         });
     }
 
@@ -472,74 +572,96 @@ fn extract_extension(path: &str) -> &str {
     path.rsplit('.').next().unwrap_or("")
 }
 
+/// Candidate patterns per tool with their context terms, loaded once
+///
+/// Causal discovery matches every tool call against the best patterns of
+/// its tool; this holds them, already split into terms, for the whole run.
+struct ContextIndex {
+    by_tool: HashMap<String, Vec<(i64, std::collections::HashSet<String>)>>,
+}
+
+impl ContextIndex {
+    /// Load up to `per_tool` patterns for each tool
+    fn load<'a>(store: &PatternStore, tools: impl IntoIterator<Item = &'a str>, per_tool: usize) -> Self {
+        let mut by_tool = HashMap::new();
+        for tool in tools {
+            if by_tool.contains_key(tool) {
+                continue;
+            }
+            let candidates = store
+                .get_by_tool(tool, per_tool)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|pattern| (pattern.id, context_terms(&pattern.context_query)))
+                .collect();
+            by_tool.insert(tool.to_string(), candidates);
+        }
+        Self { by_tool }
+    }
+
+    /// First candidate of the tool sharing at least two terms with the context
+    fn best_match(&self, tool: &str, tool_context: &str) -> Option<i64> {
+        let terms = context_terms(tool_context);
+        self.by_tool
+            .get(tool)?
+            .iter()
+            .find(|(_, pattern_terms)| terms.intersection(pattern_terms).count() >= 2)
+            .map(|(id, _)| *id)
+    }
+}
+
 /// Discover causal edges from pattern co-occurrences in trajectories
 ///
 /// This analyzes which patterns tend to appear together and whether
-/// they lead to success or failure, building a causal graph.
+/// they lead to success or failure, building a causal graph. Candidates
+/// are loaded once per tool and all edges are written in one transaction.
 fn discover_causal_edges(db_path: &Path, trajectories: &[Trajectory]) -> Result<usize> {
     let store = PatternStore::open(db_path)?;
-    let causal_store = CausalStore::open(db_path)?;
-
-    let mut edges_created = 0;
+    let recent = &trajectories[..trajectories.len().min(50)];
+    let index = ContextIndex::load(
+        &store,
+        recent
+            .iter()
+            .flat_map(|t| t.tool_calls.iter().take(MAX_PATTERNS_PER_TRAJECTORY))
+            .map(|call| call.tool_name.as_str()),
+        10,
+    );
 
-    for trajectory in trajectories.iter().take(50) {  // Process recent trajectories
-        // Determine trajectory success
+    let mut pairs: Vec<(i64, i64, bool)> = Vec::new();
+    for trajectory in recent {
         let is_success = trajectory.verdict.map(|v| v.success).unwrap_or(false);
 
-        // Get pattern IDs for tool calls in this trajectory
-        let mut pattern_ids: Vec<i64> = Vec::new();
-
-        for tool_call in trajectory.tool_calls.iter().take(MAX_PATTERNS_PER_TRAJECTORY) {
-            // Try to find matching pattern by tool type
-            let tool_type = &tool_call.tool_name;
-            if let Ok(patterns) = store.get_by_tool(tool_type, 10) {
-                // Find the best matching pattern for this tool call
-                let tool_context = extract_tool_context(tool_type, &tool_call.tool_input);
-                for pattern in patterns {
-                    // Simple context match - if there's overlap, consider it related
-                    if context_matches(&tool_context, &pattern.context_query) {
-                        pattern_ids.push(pattern.id);
-                        break;
-                    }
-                }
-            }
-        }
-
-        // Record co-occurrences between all pairs of patterns
+        // Pattern IDs for tool calls in this trajectory
+        let pattern_ids: Vec<i64> = trajectory
+            .tool_calls
+            .iter()
+            .take(MAX_PATTERNS_PER_TRAJECTORY)
+            .filter_map(|call| {
+                index.best_match(&call.tool_name, &extract_tool_context(&call.tool_name, &call.tool_input))
+            })
+            .collect();
+
+        // Co-occurrences between all pairs of patterns
         for i in 0..pattern_ids.len() {
             for j in (i + 1)..pattern_ids.len() {
-                if let Err(e) = causal_store.record_cooccurrence(
-                    pattern_ids[i],
-                    pattern_ids[j],
-                    is_success,
-                ) {
-                    debug!("Failed to record causal edge: {}", e);
-                } else {
-                    edges_created += 1;
-                }
+                pairs.push((pattern_ids[i], pattern_ids[j], is_success));
             }
         }
     }
 
-    Ok(edges_created)
+    if pairs.is_empty() {
+        return Ok(0);
+    }
+    CausalStore::open(db_path)?.record_cooccurrences(&pairs)
 }
 
-/// Check if tool context matches a pattern's context
-fn context_matches(tool_context: &str, pattern_context: &str) -> bool {
-    // Extract key terms from both contexts
-    let tool_words: std::collections::HashSet<&str> = tool_context
-        .split_whitespace()
-        .filter(|w| w.len() > 2)
-        .collect();
-
-    let pattern_words: std::collections::HashSet<&str> = pattern_context
+/// Key terms of a context, for overlap matching
+fn context_terms(context: &str) -> std::collections::HashSet<String> {
+    context
         .split_whitespace()
         .filter(|w| w.len() > 2)
-        .collect();
-
-    // Check for significant overlap (at least 2 matching words)
-    let overlap = tool_words.intersection(&pattern_words).count();
-    overlap >= 2
+        .map(str::to_string)
+        .collect()
 }
 
 /// Extract patterns from failed trajectories (what to avoid)
@@ -575,6 +697,7 @@ fn extract_failure_patterns(trajectory: &Trajectory) -> Vec<Pattern> {
                 success_count: 0,
                 failure_count: 1,
                 embedding_id: None,
+                risky: false,
             });
 
             if patterns.len() >= MAX_PATTERNS_PER_TRAJECTORY {
@@ -778,7 +901,21 @@ fn clean_error_line(line: &str) -> String {
     result
 }
 
-fn collect_jsonl_files(dir: &Path) -> Result<Vec<PathBuf>> {
+/// JSONL logs under every configured Claude log root
+pub fn collect_log_files(mana_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for dir in crate::config::load_config(mana_dir).learning.resolved_log_dirs() {
+        if !dir.exists() {
+            debug!("Claude logs directory not found: {:?}", dir);
+            continue;
+        }
+        files.extend(collect_jsonl_files(&dir)?);
+    }
+    Ok(files)
+}
+
+/// JSONL logs directly in `dir` or one level below (one directory per project)
+pub fn collect_jsonl_files(dir: &Path) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     let entries = match std::fs::read_dir(dir) {
@@ -809,24 +946,6 @@ fn collect_jsonl_files(dir: &Path) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-fn get_mana_dir() -> Result<PathBuf> {
-    let cwd = std::env::current_dir()?;
-    let project_mana = cwd.join(".mana");
-    if project_mana.exists() {
-        return Ok(project_mana);
-    }
-
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".mana"))
-}
-
-fn get_claude_logs_dir() -> PathBuf {
-    dirs::home_dir()
-        .map(|h| h.join(".claude/projects"))
-        .unwrap_or_else(|| PathBuf::from(".claude/projects"))
-}
-
 fn hash_string(s: &str) -> String {
     let mut hasher = DefaultHasher::new();
     s.hash(&mut hasher);
@@ -847,7 +966,7 @@ fn truncate(s: &str, max_len: usize) -> &str {
 }
 
 fn log_learning_event(db_path: &Path, result: &LearningResult) -> Result<()> {
-    let conn = Connection::open(db_path)?;
+    let conn = crate::storage::db::open(db_path)?;
 
     conn.execute(
         r#"
@@ -865,6 +984,34 @@ mod tests {
     use super::*;
     use crate::learning::trajectory::{ToolCall, ToolResult, Verdict};
 
+    #[test]
+    fn test_learn_dry_run_then_store_once() {
+        let temp = tempfile::TempDir::new().unwrap();
+        storage::create_schema(&storage::db::open(&temp.path().join("metadata.sqlite")).unwrap()).unwrap();
+        let log = temp.path().join("s1.jsonl");
+        std::fs::write(
+            &log,
+            concat!(
+                r#"{"type":"user","sessionId":"s1","timestamp":"2026-01-01T10:00:00Z","message":{"content":"Build the release binary"}}"#,
+                "\n",
+                r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-01-01T10:00:05Z","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo build --release"}}]}}"#,
+                "\n"
+            ),
+        )
+        .unwrap();
+        let options = |dry_run, since| LearnOptions { files: vec![log.clone()], since, dry_run };
+
+        let recent = Some(Utc::now() - chrono::Duration::days(2));
+        assert_eq!(learn(temp.path(), &options(true, recent)).unwrap().trajectories_processed, 0);
+        let preview = learn(temp.path(), &options(true, None)).unwrap();
+        assert_eq!((preview.patterns_created, preview.patterns_updated), (1, 0));
+        assert!(preview.planned[0].id.is_none());
+
+        assert_eq!(learn(temp.path(), &options(false, None)).unwrap().patterns_created, 1);
+        // Read to the end: nothing is learned twice
+        assert_eq!(learn(temp.path(), &options(true, None)).unwrap().trajectories_processed, 0);
+    }
+
     #[test]
     fn test_extract_success_patterns() {
         let trajectory = Trajectory {
@@ -880,6 +1027,9 @@ mod tests {
             }],
             tool_results: vec![],
             verdict: Some(Verdict { success: true, confidence: 0.9 }),
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         };
 
         let patterns = extract_success_patterns(&trajectory);
@@ -905,6 +1055,9 @@ mod tests {
                 is_error: true,
             }],
             verdict: Some(Verdict { success: false, confidence: 0.8 }),
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         };
 
         let patterns = extract_failure_patterns(&trajectory);
@@ -928,6 +1081,9 @@ mod tests {
                 is_error: true,
             }],
             verdict: Some(Verdict { success: false, confidence: 0.8 }),
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         };
 
         let patterns = extract_failure_patterns(&trajectory);
@@ -1062,6 +1218,9 @@ mod tests {
             }],
             tool_results: vec![],
             verdict: Some(Verdict { success: true, confidence: 0.9 }),
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         };
 
         let patterns = extract_success_patterns(&trajectory);
@@ -1069,4 +1228,59 @@ mod tests {
         assert_eq!(patterns[0].tool_type, "Bash");
         assert_eq!(patterns[0].command_category, Some("cargo".to_string()));
     }
+
+    #[test]
+    fn test_learning_links_patterns_to_project() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut store = PatternStore::open(&temp.path().join("metadata.sqlite")).unwrap();
+        let trajectory = Trajectory {
+            session_id: "test".into(),
+            user_query: "Run the test suite".into(),
+            assistant_content: String::new(),
+            tool_calls: vec![ToolCall {
+                tool_name: "Bash".into(),
+                tool_input: serde_json::json!({"command": "cargo test --workspace"}),
+            }],
+            tool_results: vec![],
+            verdict: Some(Verdict { success: true, confidence: 0.9 }),
+            started_at: None,
+            ended_at: None,
+            cwd: Some("/work/app".into()),
+        };
+
+        learn_from_trajectories(temp.path(), &mut store, &[trajectory]).unwrap();
+
+        let conn = storage::db::open(&temp.path().join("metadata.sqlite")).unwrap();
+        let filter = storage::PatternFilter {
+            project: Some(storage::projects::project_hash(Path::new("/work/app"))),
+            ..Default::default()
+        };
+        assert_eq!(filter.matching_ids(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_learning_uses_every_trajectory() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let mut store = PatternStore::open(&temp.path().join("metadata.sqlite")).unwrap();
+        let trajectories: Vec<Trajectory> = (0..150)
+            .map(|i| Trajectory {
+                session_id: format!("s{}", i),
+                user_query: format!("Build target number {}", i),
+                assistant_content: String::new(),
+                tool_calls: vec![ToolCall {
+                    tool_name: "Bash".into(),
+                    tool_input: serde_json::json!({"command": format!("make target{}", i)}),
+                }],
+                tool_results: vec![],
+                verdict: Some(Verdict { success: true, confidence: 0.9 }),
+                started_at: None,
+                ended_at: None,
+                cwd: None,
+            })
+            .collect();
+
+        let result = learn_from_trajectories(temp.path(), &mut store, &trajectories).unwrap();
+        assert_eq!(result.trajectories_processed, 150);
+        assert!(result.patterns_created > 100);
+    }
 }
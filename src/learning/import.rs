@@ -0,0 +1,637 @@
+//! Importers for other assistants' session logs
+//!
+//! Each [`TrajectoryImporter`] turns one tool's transcript format into
+//! [`Trajectory`] values that go through the same pattern extraction as
+//! Claude Code logs. Tool activity is mapped onto Claude Code tool names
+//! (`Bash`, `Edit`, `Write`, `Read`) so imported patterns are injected for
+//! the same hooks.
+//!
+//! Fingerprints of imported sessions are kept in `import-state.json`, so
+//! re-importing a log that has grown only learns from the new sessions.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use super::trajectory::{judge_trajectory, ToolCall, ToolResult, Trajectory};
+use super::LearningResult;
+use crate::storage::PatternStore;
+
+/// Formats accepted by `mana import-logs --format`
+pub const FORMATS: &[&str] = &["aider", "cursor", "openai"];
+
+/// Imported session fingerprints, relative to the MANA data directory
+const STATE_FILE: &str = "import-state.json";
+
+/// Cursor's chat panel key in a workspace `state.vscdb`
+const CURSOR_CHAT_KEY: &str = "workbench.panel.aichat.view.aichat.chatdata";
+
+/// Directories never descended into when collecting log files
+const SKIP_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// Parses one assistant's transcript format into trajectories
+pub trait TrajectoryImporter {
+    /// Name used with `--format`
+    fn format(&self) -> &'static str;
+
+    /// Whether a file found while walking a directory is in this format
+    fn accepts(&self, path: &Path) -> bool;
+
+    /// Parse one file
+    fn parse(&self, path: &Path) -> Result<Vec<Trajectory>>;
+}
+
+/// Importer for a `--format` name
+pub fn importer_for(format: &str) -> Result<Box<dyn TrajectoryImporter>> {
+    match format {
+        "aider" => Ok(Box::new(AiderImporter)),
+        "cursor" => Ok(Box::new(CursorImporter)),
+        "openai" => Ok(Box::new(OpenAiImporter)),
+        other => bail!("Unknown log format '{}'. Supported: {}", other, FORMATS.join(", ")),
+    }
+}
+
+/// Accumulates one session's messages and tool activity
+#[derive(Debug, Default)]
+struct TrajectoryBuilder {
+    session_id: String,
+    user_query: String,
+    assistant: Vec<String>,
+    tool_calls: Vec<ToolCall>,
+    tool_results: Vec<ToolResult>,
+    started_at: Option<DateTime<Utc>>,
+}
+
+impl TrajectoryBuilder {
+    fn new(session_id: String) -> Self {
+        Self { session_id, ..Default::default() }
+    }
+
+    /// The first substantial user message becomes the query
+    fn user(&mut self, text: &str) {
+        let text = text.trim();
+        if self.user_query.is_empty() && text.len() > 5 {
+            self.user_query = text.to_string();
+        }
+    }
+
+    fn assistant(&mut self, text: &str) {
+        if !text.trim().is_empty() {
+            self.assistant.push(text.trim().to_string());
+        }
+    }
+
+    /// Record a tool call, returning the id to attach its result to
+    fn tool(&mut self, name: &str, input: Value) -> String {
+        self.tool_calls.push(ToolCall { tool_name: name.to_string(), tool_input: input });
+        format!("{}:{}", self.session_id, self.tool_calls.len())
+    }
+
+    fn result(&mut self, tool_use_id: &str, content: &str, is_error: bool) {
+        self.tool_results.push(ToolResult {
+            tool_use_id: tool_use_id.to_string(),
+            content: content.to_string(),
+            is_error,
+        });
+    }
+
+    /// Finish the session; sessions without tool calls teach nothing
+    fn build(self) -> Option<Trajectory> {
+        if self.tool_calls.is_empty() {
+            return None;
+        }
+        let mut trajectory = Trajectory {
+            session_id: self.session_id,
+            user_query: self.user_query,
+            assistant_content: self.assistant.join("\n"),
+            tool_calls: self.tool_calls,
+            tool_results: self.tool_results,
+            verdict: None,
+            started_at: self.started_at,
+            ended_at: None,
+            cwd: None,
+        };
+        trajectory.verdict = Some(judge_trajectory(&trajectory));
+        Some(trajectory)
+    }
+}
+
+/// Aider's `.aider.chat.history.md`
+///
+/// Sessions start at `# aider chat started at ...`, user input is prefixed
+/// with `#### `, and tool output is quoted with `> `. Edits come from
+/// SEARCH/REPLACE blocks and `Applied edit to` lines, shell commands from
+/// `Running ...` lines and `/run` or `/test` input.
+pub struct AiderImporter;
+
+impl TrajectoryImporter for AiderImporter {
+    fn format(&self) -> &'static str {
+        "aider"
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|n| n.to_string_lossy().ends_with(".chat.history.md"))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<Trajectory>> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(parse_aider(&content, &path.display().to_string()))
+    }
+}
+
+fn parse_aider(content: &str, source: &str) -> Vec<Trajectory> {
+    let mut trajectories = Vec::new();
+    let mut sessions = 0;
+    let mut current = TrajectoryBuilder::new(format!("aider:{}:0", source));
+    let mut edited: HashSet<String> = HashSet::new();
+    // Shell command awaiting its quoted output
+    let mut pending: Option<(String, Vec<String>)> = None;
+    // Last non-fence line, which names the file of a following SEARCH block
+    let mut path_line = "";
+
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let quoted = line.strip_prefix("> ").or_else(|| (line == ">").then_some(""));
+        if quoted.is_none() {
+            if let Some((id, output)) = pending.take() {
+                current.result(&id, &output.join("\n"), false);
+            }
+        }
+
+        if let Some(started) = line.strip_prefix("# aider chat started at ") {
+            trajectories.extend(std::mem::take(&mut current).build());
+            sessions += 1;
+            current = TrajectoryBuilder::new(format!("aider:{}:{}", source, sessions));
+            current.started_at = NaiveDateTime::parse_from_str(started.trim(), "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .and_then(|t| Local.from_local_datetime(&t).single())
+                .map(|t| t.with_timezone(&Utc));
+            edited.clear();
+        } else if let Some(input) = line.strip_prefix("#### ") {
+            match input.strip_prefix("/run ").or_else(|| input.strip_prefix("/test ")) {
+                Some(command) => pending = Some((current.tool("Bash", json!({"command": command.trim()})), Vec::new())),
+                None => current.user(input),
+            }
+        } else if let Some(text) = quoted {
+            if let Some(file) = text.strip_prefix("Applied edit to ") {
+                if edited.insert(file.trim().to_string()) {
+                    current.tool("Edit", json!({"file_path": file.trim()}));
+                }
+            } else if let Some(command) = text.strip_prefix("Running ") {
+                pending = Some((current.tool("Bash", json!({"command": command.trim()})), Vec::new()));
+            } else if let Some((_, output)) = pending.as_mut() {
+                output.push(text.to_string());
+            }
+        } else if line.trim() == "<<<<<<< SEARCH" {
+            let file = path_line.trim().to_string();
+            let (mut old, mut new, mut in_new) = (Vec::new(), Vec::new(), false);
+            for block_line in lines.by_ref() {
+                match block_line.trim() {
+                    "=======" => in_new = true,
+                    ">>>>>>> REPLACE" => break,
+                    _ if in_new => new.push(block_line),
+                    _ => old.push(block_line),
+                }
+            }
+            edited.insert(file.clone());
+            current.tool("Edit", json!({"file_path": file, "old_string": old.join("\n"), "new_string": new.join("\n")}));
+        } else {
+            current.assistant(line);
+        }
+
+        if !line.trim().is_empty() && !line.trim_start().starts_with("```") {
+            path_line = line;
+        }
+    }
+
+    if let Some((id, output)) = pending.take() {
+        current.result(&id, &output.join("\n"), false);
+    }
+    trajectories.extend(current.build());
+    trajectories
+}
+
+/// Cursor chat history
+///
+/// Reads the chat panel data from a workspace `state.vscdb`, or the same
+/// JSON exported to a file (`{"tabs": [{"bubbles": [...]}]}`). Fenced code
+/// blocks tagged with a path (```rust:src/main.rs) become edits; shell
+/// blocks become commands.
+pub struct CursorImporter;
+
+impl TrajectoryImporter for CursorImporter {
+    fn format(&self) -> &'static str {
+        "cursor"
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        matches!(path.extension().and_then(|e| e.to_str()), Some("vscdb" | "json"))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<Trajectory>> {
+        let data: Value = if path.extension().is_some_and(|e| e == "vscdb") {
+            let conn = crate::storage::db::open_readonly(path)?;
+            let raw: Option<String> = conn
+                .query_row("SELECT value FROM ItemTable WHERE key = ?1", [CURSOR_CHAT_KEY], |row| row.get(0))
+                .ok();
+            match raw {
+                Some(raw) => serde_json::from_str(&raw)?,
+                None => return Ok(Vec::new()),
+            }
+        } else {
+            serde_json::from_str(&std::fs::read_to_string(path)?)
+                .with_context(|| format!("Invalid JSON in {}", path.display()))?
+        };
+        Ok(parse_cursor(&data, &path.display().to_string()))
+    }
+}
+
+fn parse_cursor(data: &Value, source: &str) -> Vec<Trajectory> {
+    let tabs = data.get("tabs").or_else(|| data.get("conversations")).unwrap_or(data);
+    let tabs: Vec<&Value> = match tabs.as_array() {
+        Some(tabs) => tabs.iter().collect(),
+        None => vec![tabs],
+    };
+
+    let mut trajectories = Vec::new();
+    for (i, tab) in tabs.into_iter().enumerate() {
+        let id = tab.get("tabId").or_else(|| tab.get("id")).and_then(Value::as_str).map(str::to_string);
+        let mut builder = TrajectoryBuilder::new(format!("cursor:{}:{}", source, id.unwrap_or_else(|| i.to_string())));
+        let bubbles = tab.get("bubbles").or_else(|| tab.get("messages")).and_then(Value::as_array);
+
+        for bubble in bubbles.into_iter().flatten() {
+            let text = ["text", "rawText", "content"].iter().find_map(|k| bubble.get(*k).and_then(Value::as_str));
+            let Some(text) = text else { continue };
+            let role = bubble.get("type").or_else(|| bubble.get("role")).and_then(Value::as_str).unwrap_or("");
+
+            if role == "user" {
+                builder.user(text);
+                continue;
+            }
+            builder.assistant(text);
+            for (tag, code) in code_blocks(text) {
+                let (lang, path) = tag.split_once(':').unwrap_or((tag, ""));
+                if matches!(lang, "bash" | "sh" | "shell" | "zsh" | "console") {
+                    for command in code.lines().map(|l| l.trim().trim_start_matches("$ ")).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+                        builder.tool("Bash", json!({"command": command}));
+                    }
+                } else if !path.is_empty() {
+                    builder.tool("Edit", json!({"file_path": path, "new_string": code}));
+                }
+            }
+        }
+        trajectories.extend(builder.build());
+    }
+    trajectories
+}
+
+/// Fenced code blocks as (info string, body)
+fn code_blocks(text: &str) -> Vec<(&str, String)> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(&str, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match (open.as_mut(), trimmed.strip_prefix("```")) {
+            (None, Some(tag)) => open = Some((tag.trim(), Vec::new())),
+            (Some(_), Some(rest)) if rest.trim().is_empty() => {
+                let (tag, body) = open.take().unwrap_or_default();
+                blocks.push((tag, body.join("\n")));
+            }
+            (Some((_, body)), _) => body.push(line),
+            (None, None) => {}
+        }
+    }
+    blocks
+}
+
+/// OpenAI-style chat transcripts
+///
+/// Accepts a JSON array of messages, an object with `messages`, a list of
+/// such objects, or JSONL with one conversation (or one message) per line.
+/// Function/tool calls are mapped to Claude Code tools by name.
+pub struct OpenAiImporter;
+
+impl TrajectoryImporter for OpenAiImporter {
+    fn format(&self) -> &'static str {
+        "openai"
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        matches!(path.extension().and_then(|e| e.to_str()), Some("json" | "jsonl"))
+    }
+
+    fn parse(&self, path: &Path) -> Result<Vec<Trajectory>> {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let values: Vec<Value> = if path.extension().is_some_and(|e| e == "jsonl") {
+            content.lines().filter(|l| !l.trim().is_empty()).filter_map(|l| serde_json::from_str(l).ok()).collect()
+        } else {
+            vec![serde_json::from_str(&content).with_context(|| format!("Invalid JSON in {}", path.display()))?]
+        };
+        Ok(parse_openai(values, &path.display().to_string()))
+    }
+}
+
+fn parse_openai(values: Vec<Value>, source: &str) -> Vec<Trajectory> {
+    let is_message = |v: &Value| v.get("role").is_some();
+
+    // Normalize every accepted shape to a list of conversations (message lists)
+    let mut conversations: Vec<(Option<String>, Vec<Value>)> = Vec::new();
+    let mut loose_messages = Vec::new();
+    for value in values {
+        let items = match value {
+            Value::Array(items) => items,
+            Value::Object(_) if value.get("conversations").is_some() => {
+                value["conversations"].as_array().cloned().unwrap_or_default()
+            }
+            other => vec![other],
+        };
+        for item in items {
+            if is_message(&item) {
+                loose_messages.push(item);
+            } else if let Some(messages) = item.get("messages").and_then(Value::as_array) {
+                let id = item.get("id").and_then(Value::as_str).map(str::to_string);
+                conversations.push((id, messages.clone()));
+            }
+        }
+    }
+    if !loose_messages.is_empty() {
+        conversations.push((None, loose_messages));
+    }
+
+    let mut trajectories = Vec::new();
+    for (i, (id, messages)) in conversations.into_iter().enumerate() {
+        let mut builder = TrajectoryBuilder::new(format!("openai:{}:{}", source, id.unwrap_or_else(|| i.to_string())));
+        for message in &messages {
+            let text = message_text(message.get("content"));
+            match message.get("role").and_then(Value::as_str).unwrap_or("") {
+                "user" => builder.user(&text),
+                "assistant" => {
+                    builder.assistant(&text);
+                    let calls = message.get("tool_calls").and_then(Value::as_array).cloned().unwrap_or_default();
+                    let legacy = message.get("function_call").map(|f| json!({"function": f}));
+                    for call in calls.iter().chain(legacy.iter()) {
+                        let function = call.get("function").unwrap_or(call);
+                        let name = function.get("name").and_then(Value::as_str).unwrap_or("");
+                        let arguments = match function.get("arguments") {
+                            Some(Value::String(raw)) => serde_json::from_str(raw).unwrap_or_else(|_| json!({"input": raw})),
+                            Some(args) => args.clone(),
+                            None => Value::Null,
+                        };
+                        let (tool, input) = map_tool(name, &arguments);
+                        builder.tool(&tool, input);
+                    }
+                }
+                "tool" | "function" => {
+                    let call_id = message.get("tool_call_id").or_else(|| message.get("name")).and_then(Value::as_str);
+                    let is_error = message.get("is_error").and_then(Value::as_bool).unwrap_or(false);
+                    builder.result(call_id.unwrap_or_default(), &text, is_error);
+                }
+                _ => {}
+            }
+        }
+        trajectories.extend(builder.build());
+    }
+    trajectories
+}
+
+/// Text of a message's content (a string or a list of text parts)
+fn message_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Map a function-call name and arguments onto a Claude Code tool
+fn map_tool(name: &str, args: &Value) -> (String, Value) {
+    let lower = name.to_lowercase();
+    let field = |keys: &[&str]| keys.iter().find_map(|k| args.get(*k).and_then(Value::as_str)).unwrap_or_default();
+    let path = field(&["file_path", "path", "filename", "file"]);
+
+    if ["bash", "shell", "terminal", "exec", "command"].iter().any(|k| lower.contains(k)) {
+        let command = match args {
+            Value::String(command) => command.as_str(),
+            _ => field(&["command", "cmd", "script", "input"]),
+        };
+        ("Bash".into(), json!({"command": command}))
+    } else if lower.contains("write") || lower.contains("create_file") {
+        ("Write".into(), json!({"file_path": path, "content": field(&["content", "text"])}))
+    } else if ["edit", "patch", "replace"].iter().any(|k| lower.contains(k)) {
+        let old = field(&["old_string", "old_str", "search"]);
+        let new = field(&["new_string", "new_str", "replace"]);
+        ("Edit".into(), json!({"file_path": path, "old_string": old, "new_string": new}))
+    } else if lower.contains("read") || lower.contains("open_file") {
+        ("Read".into(), json!({"file_path": path}))
+    } else {
+        (name.to_string(), args.clone())
+    }
+}
+
+/// Files under `path` an importer accepts (or `path` itself if it is a file)
+pub fn collect_files(importer: &dyn TrajectoryImporter, path: &Path) -> Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        bail!("{} does not exist", path.display());
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)?.flatten() {
+            let entry_path = entry.path();
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_dir() {
+                if !SKIP_DIRS.iter().any(|skip| entry.file_name() == *skip) {
+                    pending.push(entry_path);
+                }
+            } else if file_type.is_file() && importer.accepts(&entry_path) {
+                files.push(entry_path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Sessions already imported, by fingerprint
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ImportState {
+    sessions: HashSet<String>,
+}
+
+impl ImportState {
+    fn load(mana_dir: &Path) -> Self {
+        std::fs::read(mana_dir.join(STATE_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, mana_dir: &Path) -> Result<()> {
+        let path = mana_dir.join(STATE_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Identifies a session's content, so a session that grew is imported again
+fn fingerprint(trajectory: &Trajectory) -> String {
+    let mut hasher = DefaultHasher::new();
+    trajectory.session_id.hash(&mut hasher);
+    trajectory.user_query.hash(&mut hasher);
+    trajectory.tool_calls.len().hash(&mut hasher);
+    trajectory.tool_results.len().hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+/// Outcome of `mana import-logs`
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub format: String,
+    pub files: usize,
+    /// Sessions with tool activity found in the files
+    pub sessions: usize,
+    /// Sessions skipped because an identical copy was imported before
+    pub already_imported: usize,
+    pub tool_calls: usize,
+    /// Learning outcome (absent on dry runs)
+    pub learning: Option<LearningResult>,
+}
+
+/// Parse logs with an importer and learn from sessions not seen before
+///
+/// The caller should hold the learning lock.
+pub fn import_logs(mana_dir: &Path, importer: &dyn TrajectoryImporter, paths: &[PathBuf], dry_run: bool) -> Result<ImportSummary> {
+    let mut summary = ImportSummary { format: importer.format().to_string(), ..Default::default() };
+    let mut state = ImportState::load(mana_dir);
+    let mut fresh = Vec::new();
+
+    for path in paths {
+        for file in collect_files(importer, path)? {
+            summary.files += 1;
+            let trajectories = importer.parse(&file).with_context(|| format!("Failed to import {}", file.display()))?;
+            debug!("Parsed {} {} sessions from {:?}", trajectories.len(), importer.format(), file);
+
+            for trajectory in trajectories {
+                summary.sessions += 1;
+                if state.sessions.contains(&fingerprint(&trajectory)) {
+                    summary.already_imported += 1;
+                    continue;
+                }
+                summary.tool_calls += trajectory.tool_calls.len();
+                fresh.push(trajectory);
+            }
+        }
+    }
+
+    if dry_run || fresh.is_empty() {
+        return Ok(summary);
+    }
+
+    let mut store = PatternStore::open(&mana_dir.join("metadata.sqlite"))?;
+    let learning = super::foreground::learn_from_trajectories(mana_dir, &mut store, &fresh)?;
+    info!(
+        "Imported {} {} sessions: {} patterns created",
+        learning.trajectories_processed,
+        importer.format(),
+        learning.patterns_created
+    );
+
+    state.sessions.extend(fresh.iter().map(fingerprint));
+    state.save(mana_dir)?;
+    summary.learning = Some(learning);
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aider_history() {
+        let history = r#"
+# aider chat started at 2024-05-01 10:00:00
+
+#### fix the failing parser test in src/parser.rs
+
+The test fails because of an off-by-one.
+
+src/parser.rs
+```rust
+<<<<<<< SEARCH
+    let end = start + len + 1;
+=======
+    let end = start + len;
+>>>>>>> REPLACE
+```
+
+> Applied edit to src/parser.rs
+> Running cargo test --lib parser
+> test result: ok. 4 passed
+
+# aider chat started at 2024-05-02 09:00:00
+
+#### what does this crate do?
+
+It parses config files.
+"#;
+        let trajectories = parse_aider(history, "h.md");
+        assert_eq!(trajectories.len(), 1, "the question-only session has no tool calls");
+
+        let t = &trajectories[0];
+        assert_eq!(t.user_query, "fix the failing parser test in src/parser.rs");
+        assert!(t.started_at.is_some());
+        let tools: Vec<&str> = t.tool_calls.iter().map(|c| c.tool_name.as_str()).collect();
+        assert_eq!(tools, vec!["Edit", "Bash"]);
+        assert_eq!(t.tool_calls[0].tool_input["file_path"], "src/parser.rs");
+        assert_eq!(t.tool_calls[0].tool_input["old_string"], "    let end = start + len + 1;");
+        assert_eq!(t.tool_calls[1].tool_input["command"], "cargo test --lib parser");
+        assert_eq!(t.tool_results[0].content, "test result: ok. 4 passed");
+    }
+
+    #[test]
+    fn test_parse_openai_and_cursor() {
+        let conversation = json!({"id": "c1", "messages": [
+            {"role": "user", "content": "run the unit tests please"},
+            {"role": "assistant", "content": null, "tool_calls": [
+                {"id": "call_1", "type": "function", "function": {"name": "run_shell", "arguments": "{\"command\": \"npm test\"}"}}
+            ]},
+            {"role": "tool", "tool_call_id": "call_1", "content": "Error: 2 tests failed"},
+            {"role": "assistant", "content": [{"type": "text", "text": "Two tests fail."}]}
+        ]});
+        let trajectories = parse_openai(vec![json!([conversation])], "t.json");
+        assert_eq!(trajectories.len(), 1);
+        let t = &trajectories[0];
+        assert_eq!(t.session_id, "openai:t.json:c1");
+        assert_eq!(t.tool_calls[0].tool_name, "Bash");
+        assert_eq!(t.tool_calls[0].tool_input, json!({"command": "npm test"}));
+        assert_eq!(t.tool_results[0].tool_use_id, "call_1");
+        assert!(!t.verdict.unwrap().success);
+
+        let cursor = json!({"tabs": [{"tabId": "t1", "bubbles": [
+            {"type": "user", "text": "add a health endpoint"},
+            {"type": "ai", "text": "Add this:\n```ts:src/server.ts\napp.get('/health', ok);\n```\nThen run:\n```bash\nnpm run build\n```"}
+        ]}]});
+        let trajectories = parse_cursor(&cursor, "state.vscdb");
+        let tools: Vec<(&str, &Value)> =
+            trajectories[0].tool_calls.iter().map(|c| (c.tool_name.as_str(), &c.tool_input)).collect();
+        assert_eq!(tools[0].0, "Edit");
+        assert_eq!(tools[0].1["file_path"], "src/server.ts");
+        assert_eq!(tools[1], ("Bash", &json!({"command": "npm run build"})));
+    }
+}
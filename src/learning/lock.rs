@@ -0,0 +1,26 @@
+//! Advisory lock around learning
+//!
+//! Session-end hooks from concurrent sessions, `mana watch` and manual runs
+//! all read and write the same database and learning-state.json. Holding
+//! this lock for the whole load-learn-save sequence keeps them from double
+//! counting trajectories or interleaving state writes.
+//!
+//! It is a [`FileLock`] on `<mana_dir>/learning.lock`.
+
+use anyhow::Result;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::storage::lock::FileLock;
+
+/// Lock file name inside the MANA data directory
+pub const LOCK_FILE: &str = "learning.lock";
+
+/// Acquire the learning lock, waiting up to `timeout`
+///
+/// Returns `None` if another process still holds it after the timeout.
+/// Learning writes, so this fails in read-only mode.
+pub fn acquire(mana_dir: &Path, timeout: Duration) -> Result<Option<FileLock>> {
+    crate::storage::read_only::ensure_writable("Learning")?;
+    FileLock::acquire(mana_dir, LOCK_FILE, timeout)
+}
@@ -7,10 +7,20 @@ use serde::{Deserialize, Serialize};
 
 mod foreground;
 mod consolidation;
+mod watch;
+mod import;
+mod schedule;
+pub mod lock;
+pub mod risk;
+pub mod privacy;
+pub mod relearn;
 pub mod trajectory;
 
-pub use foreground::foreground_learn;
-pub use consolidation::{consolidate, spawn_consolidation};
+pub use foreground::{collect_log_files, extract_command_category, foreground_learn, learn, LearnOptions};
+pub use consolidation::{consolidate, spawn_consolidation, ConsolidationSummary, Stage, StageRun, STAGES as CONSOLIDATION_STAGES};
+pub use schedule::Schedule;
+pub use watch::{watch_logs, WatchOptions};
+pub use import::{import_logs, importer_for, FORMATS as IMPORT_FORMATS};
 // Trajectory types are internal to foreground learning - only expose what's needed
 #[allow(unused_imports)]
 pub(crate) use trajectory::parse_trajectories;
@@ -22,4 +32,24 @@ pub struct LearningResult {
     pub patterns_updated: u32,
     pub trajectories_processed: u32,
     pub duration_ms: u64,
+    /// Malformed log lines skipped while parsing
+    #[serde(default)]
+    pub lines_skipped: u64,
+    /// What parsing found in each log file read
+    #[serde(skip)]
+    pub files: Vec<trajectory::ParseStats>,
+    /// Patterns a dry run would create (no id) or update
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub planned: Vec<PlannedPattern>,
+}
+
+/// A pattern a dry run would store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedPattern {
+    /// The stored pattern it would add to
+    pub id: Option<i64>,
+    pub tool_type: String,
+    pub context_query: String,
+    pub success_count: i64,
+    pub failure_count: i64,
 }
@@ -0,0 +1,133 @@
+//! Projects kept out of learning
+//!
+//! `[learning] exclude_projects` lists globs over session working
+//! directories. Trajectories recorded in a matching directory are dropped
+//! before foreground learning, `mana import-logs`, relearn and reflection see
+//! them, so nothing from those projects reaches the store. Patterns learned
+//! before a project was excluded are removed with `mana patterns purge`.
+//!
+//! Globs use `*` and `?` within a path segment and `**` across segments;
+//! `~/` is the home directory. A glob without a `/` is matched against each
+//! segment, so `"*secret*"` excludes any directory with "secret" in its
+//! path, and `dir/**` covers `dir` itself as well as everything below it.
+
+use regex::Regex;
+use std::path::Path;
+
+use super::trajectory::Trajectory;
+
+/// Compiled `exclude_projects` globs
+#[derive(Debug, Default)]
+pub struct ProjectFilter {
+    /// Patterns matched against the whole path
+    paths: Vec<Regex>,
+    /// Patterns matched against each path segment
+    segments: Vec<Regex>,
+}
+
+impl ProjectFilter {
+    pub fn new(globs: &[String]) -> Self {
+        let home = dirs::home_dir();
+        let mut filter = Self::default();
+        for glob in globs {
+            let expanded = match (glob.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => format!("{}/{}", home.to_string_lossy().trim_end_matches('/'), rest),
+                _ => glob.clone(),
+            };
+            let regex = glob_regex(&expanded);
+            if expanded.contains('/') {
+                filter.paths.push(regex);
+            } else {
+                filter.segments.push(regex);
+            }
+        }
+        filter
+    }
+
+    /// The `[learning] exclude_projects` filter for `mana_dir`
+    pub fn configured(mana_dir: &Path) -> Self {
+        Self::new(&crate::config::load_config(mana_dir).learning.exclude_projects)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.segments.is_empty()
+    }
+
+    /// Whether the project at `cwd` is excluded
+    pub fn excludes(&self, cwd: &str) -> bool {
+        let path = cwd.trim_end_matches('/');
+        self.paths.iter().any(|re| re.is_match(path))
+            || path.split('/').any(|segment| self.segments.iter().any(|re| re.is_match(segment)))
+    }
+
+    /// Whether a trajectory may be learned from
+    ///
+    /// Trajectories without a recorded working directory are.
+    pub fn admits(&self, trajectory: &Trajectory) -> bool {
+        !trajectory.cwd.as_deref().is_some_and(|cwd| self.excludes(cwd))
+    }
+
+    /// Drop trajectories from excluded projects, returning how many went
+    pub fn retain(&self, trajectories: &mut Vec<Trajectory>) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        let before = trajectories.len();
+        trajectories.retain(|t| self.admits(t));
+        before - trajectories.len()
+    }
+}
+
+/// Anchored regex for a glob
+fn glob_regex(glob: &str) -> Regex {
+    let mut pattern = String::from("^");
+    let mut rest = glob.trim_end_matches('/');
+    while let Some(c) = rest.chars().next() {
+        if let Some(tail) = rest.strip_prefix("**/") {
+            pattern.push_str("(?:.*/)?");
+            rest = tail;
+        } else if rest == "/**" {
+            pattern.push_str("(?:/.*)?");
+            rest = "";
+        } else if let Some(tail) = rest.strip_prefix("**") {
+            pattern.push_str(".*");
+            rest = tail;
+        } else {
+            match c {
+                '*' => pattern.push_str("[^/]*"),
+                '?' => pattern.push_str("[^/]"),
+                c => pattern.push_str(&regex::escape(&c.to_string())),
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    pattern.push('$');
+    // Everything but the wildcards is escaped, so this always compiles
+    Regex::new(&pattern).expect("glob regex")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclude_globs() {
+        let filter = ProjectFilter::new(&["*secret*".into(), "/work/client-x/**".into(), "/src/**/vendor".into()]);
+        assert!(filter.excludes("/home/u/top-secret-app"));
+        assert!(filter.excludes("/home/u/secrets/app/"));
+        assert!(filter.excludes("/work/client-x"));
+        assert!(filter.excludes("/work/client-x/api/src"));
+        assert!(filter.excludes("/src/vendor"));
+        assert!(filter.excludes("/src/a/b/vendor"));
+        assert!(!filter.excludes("/work/client-xy"));
+        assert!(!filter.excludes("/home/u/app"));
+        assert!(!filter.excludes("/src/a/vendor/lib"));
+
+        let mut trajectories = vec![Trajectory::default(), Trajectory::default(), Trajectory::default()];
+        trajectories[0].cwd = Some("/work/client-x/api".into());
+        trajectories[1].cwd = Some("/work/other".into());
+        assert_eq!(filter.retain(&mut trajectories), 1);
+        assert_eq!(trajectories.len(), 2);
+        assert_eq!(ProjectFilter::default().retain(&mut trajectories), 0);
+    }
+}
@@ -0,0 +1,487 @@
+//! Relearning patterns from the session logs (`mana relearn`)
+//!
+//! Relearning extracts patterns from every log again and recounts them, for
+//! when extraction has changed. Rather than wiping the bank it reconciles
+//! with it: a relearned pattern whose hash is already stored keeps its id,
+//! content, tags, history and verdicts and only gets the new counts; new
+//! ones are added; and stored patterns that no log produces any more are
+//! deleted, unless they were synced from another device, installed from a
+//! pack, edited since they were learned, approved, quarantined or judged by
+//! reflection.
+//!
+//! `--project`, `--tool` and `--since` narrow which patterns are touched to
+//! those from sessions in a project, for a tool, or from recent sessions.
+//! Counts still come from every session, so a pattern shared with other
+//! projects isn't undercounted. `--stage` writes the result to the
+//! `relearn_staging` table and reports how it differs from the bank without
+//! changing it; `--apply` commits the staged result, and refuses to if the
+//! bank changed since it was staged (a learning run in between would
+//! otherwise lose its patterns and counts).
+//!
+//! Causal edges are left alone: preserved ids keep theirs, and deleted
+//! patterns take theirs with them.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use super::foreground::{collect_log_files, extract_patterns};
+use super::privacy::ProjectFilter;
+use super::trajectory::{parse_files, Trajectory};
+use crate::hooks::session_end_handler::AccumulatorState;
+use crate::storage::{self, db, projects};
+
+/// What a relearn may touch; everything when empty
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Scope {
+    /// Project hash
+    pub project: Option<String>,
+    pub tool: Option<String>,
+    /// Only sessions (and stored patterns) from after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl Scope {
+    pub fn is_full(&self) -> bool {
+        self.project.is_none() && self.tool.is_none() && self.since.is_none()
+    }
+
+    fn covers_session(&self, trajectory: &Trajectory) -> bool {
+        let in_project = self.project.as_ref().is_none_or(|project| {
+            trajectory.cwd.as_deref().is_some_and(|cwd| &projects::project_hash(Path::new(cwd)) == project)
+        });
+        let recent = self.since.is_none_or(|since| trajectory.ended_at.or(trajectory.started_at).is_some_and(|t| t >= since));
+        in_project && recent
+    }
+
+    fn covers_tool(&self, tool_type: &str) -> bool {
+        self.tool.as_ref().is_none_or(|tool| tool.eq_ignore_ascii_case(tool_type))
+    }
+}
+
+/// Success and failure counts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Counts {
+    pub success: i64,
+    pub failure: i64,
+}
+
+/// A pattern a relearn adds, recounts or deletes
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    /// Stored pattern id, None for a new pattern
+    pub id: Option<i64>,
+    pub tool_type: String,
+    pub context: String,
+    pub before: Option<Counts>,
+    pub after: Option<Counts>,
+}
+
+/// How a relearn differs from the bank
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelearnReport {
+    pub scope: Scope,
+    pub trajectories: usize,
+    pub added: Vec<Change>,
+    pub updated: Vec<Change>,
+    pub unchanged: usize,
+    pub removed: Vec<Change>,
+    /// Patterns no log produces that are kept: synced, from a pack, edited,
+    /// approved, quarantined or with verdicts
+    pub kept: usize,
+    /// Whether the bank was changed, or the result only staged
+    pub applied: bool,
+}
+
+/// A relearned pattern
+struct Relearned {
+    hash: String,
+    tool_type: String,
+    category: Option<String>,
+    context: String,
+    counts: Counts,
+    risky: bool,
+    projects: BTreeSet<String>,
+    /// Whether it came from a session in scope, for a tool in scope
+    in_scope: bool,
+}
+
+/// Create the staging tables
+pub fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- Patterns from the last staged relearn, in scope or not
+        CREATE TABLE IF NOT EXISTS relearn_staging (
+            pattern_hash TEXT PRIMARY KEY,
+            tool_type TEXT NOT NULL,
+            command_category TEXT,
+            context_query TEXT NOT NULL,
+            success_count INTEGER NOT NULL,
+            failure_count INTEGER NOT NULL,
+            risky INTEGER NOT NULL DEFAULT 0,
+            projects TEXT NOT NULL DEFAULT '',
+            in_scope INTEGER NOT NULL
+        );
+
+        -- Scope of the staged relearn (one row)
+        CREATE TABLE IF NOT EXISTS relearn_staging_scope (
+            project TEXT,
+            tool TEXT,
+            since TEXT,
+            trajectories INTEGER NOT NULL,
+            file_positions TEXT NOT NULL,
+            bank_fingerprint TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )?;
+    storage::add_column_if_missing(conn, "relearn_staging_scope", "bank_fingerprint", "TEXT")?;
+    Ok(())
+}
+
+/// Summary of the bank that changes whenever a pattern is added, removed or recounted
+fn bank_fingerprint(conn: &Connection) -> Result<String> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) || ':' || COALESCE(MAX(id), 0) || ':' || TOTAL(success_count) || ':' || TOTAL(failure_count)
+         FROM patterns",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Relearn within `scope` and apply the result
+///
+/// Fails without applying anything when no session logs are found, which
+/// would otherwise delete every learned pattern in scope.
+pub fn relearn(mana_dir: &Path, scope: &Scope) -> Result<RelearnReport> {
+    if stage(mana_dir, scope)?.trajectories == 0 {
+        bail!("No sessions found in the Claude logs; nothing was relearned (--stage shows what would change)");
+    }
+    apply(mana_dir)
+}
+
+/// Relearn within `scope` into the staging table, leaving the bank alone
+pub fn stage(mana_dir: &Path, scope: &Scope) -> Result<RelearnReport> {
+    let (trajectories, positions) = parse_logs(mana_dir)?;
+    let conn = db::open(&mana_dir.join("metadata.sqlite"))?;
+    stage_trajectories(&conn, &trajectories, scope, &positions)?;
+    diff(&conn)
+}
+
+/// Apply the staged relearn to the bank and clear it
+pub fn apply(mana_dir: &Path) -> Result<RelearnReport> {
+    let conn = db::open(&mana_dir.join("metadata.sqlite"))?;
+    let mut report = diff(&conn)?;
+    let positions = apply_staged(&conn, &report)?;
+    report.applied = true;
+
+    if crate::embeddings::is_available(mana_dir) {
+        for id in report.removed.iter().filter_map(|c| c.id) {
+            let _ = crate::embeddings::delete_from_index(mana_dir, id);
+        }
+    }
+    storage::snapshot::rebuild(mana_dir, &conn)?;
+
+    // A full relearn counted the logs to their end; learning carries on from there
+    if report.scope.is_full() {
+        let state_path = mana_dir.join("learning-state.json");
+        let mut state = AccumulatorState::load(&state_path)?;
+        state.last_file_positions.extend(positions);
+        state.save(&state_path)?;
+    }
+    Ok(report)
+}
+
+/// Every trajectory in the logs, with where each file was read to
+fn parse_logs(mana_dir: &Path) -> Result<(Vec<Trajectory>, HashMap<PathBuf, u64>)> {
+    let files: Vec<(PathBuf, u64)> = collect_log_files(mana_dir)?.into_iter().map(|file| (file, 0)).collect();
+    let (mut trajectories, files) = parse_files(&files);
+    let positions = files.into_iter().map(|f| (f.path, f.end_offset)).collect();
+    ProjectFilter::configured(mana_dir).retain(&mut trajectories);
+    Ok((trajectories, positions))
+}
+
+/// Extract and count patterns from `trajectories` into the staging table
+fn stage_trajectories(
+    conn: &Connection,
+    trajectories: &[Trajectory],
+    scope: &Scope,
+    positions: &HashMap<PathBuf, u64>,
+) -> Result<()> {
+    let mut relearned: HashMap<String, Relearned> = HashMap::new();
+    for (trajectory, patterns) in trajectories.iter().zip(extract_patterns(trajectories)) {
+        let session_in_scope = scope.covers_session(trajectory);
+        let project = trajectory.cwd.as_deref().map(|cwd| projects::project_hash(Path::new(cwd)));
+        for pattern in patterns {
+            let entry = relearned.entry(pattern.pattern_hash.clone()).or_insert_with(|| Relearned {
+                hash: pattern.pattern_hash.clone(),
+                tool_type: pattern.tool_type.clone(),
+                category: pattern.command_category.clone(),
+                context: pattern.context_query.clone(),
+                counts: Counts { success: 0, failure: 0 },
+                risky: pattern.risky,
+                projects: BTreeSet::new(),
+                in_scope: false,
+            });
+            entry.counts.success += pattern.success_count;
+            entry.counts.failure += pattern.failure_count;
+            entry.projects.extend(project.clone());
+            entry.in_scope |= session_in_scope && scope.covers_tool(&pattern.tool_type);
+        }
+    }
+
+    create_tables(conn)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch("DELETE FROM relearn_staging; DELETE FROM relearn_staging_scope;")?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO relearn_staging
+             (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, risky, projects, in_scope)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+        for r in relearned.values() {
+            let projects = r.projects.iter().cloned().collect::<Vec<_>>().join(",");
+            stmt.execute(params![
+                r.hash, r.tool_type, r.category, r.context, r.counts.success, r.counts.failure, r.risky, projects, r.in_scope
+            ])?;
+        }
+    }
+    let positions: HashMap<String, u64> = positions.iter().map(|(p, len)| (p.to_string_lossy().into_owned(), *len)).collect();
+    tx.execute(
+        "INSERT INTO relearn_staging_scope (project, tool, since, trajectories, file_positions, bank_fingerprint)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            scope.project,
+            scope.tool,
+            scope.since.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            trajectories.len() as i64,
+            serde_json::to_string(&positions)?,
+            bank_fingerprint(&tx)?
+        ],
+    )?;
+    tx.commit()?;
+    debug!("Staged {} relearned patterns", relearned.len());
+    Ok(())
+}
+
+/// Compare the staged relearn with the bank
+fn diff(conn: &Connection) -> Result<RelearnReport> {
+    create_tables(conn)?;
+    let staged = conn
+        .query_row("SELECT project, tool, since, trajectories FROM relearn_staging_scope", [], |row| {
+            Ok((
+                row.get::<_, Option<String>>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .optional()?;
+    let Some((project, tool, since, trajectories)) = staged else {
+        bail!("Nothing is staged; run 'mana relearn --stage' first");
+    };
+    let since = since.map(|s| DateTime::parse_from_rfc3339(&s).map(|t| t.with_timezone(&Utc))).transpose()?;
+    let mut report = RelearnReport {
+        scope: Scope { project, tool, since },
+        trajectories: trajectories as usize,
+        ..Default::default()
+    };
+
+    // Patterns from sessions in scope: new or recounted
+    let mut stmt = conn.prepare(
+        "SELECT s.tool_type, s.context_query, s.success_count, s.failure_count, p.id, p.context_query, p.success_count, p.failure_count
+         FROM relearn_staging s LEFT JOIN patterns p ON p.pattern_hash = s.pattern_hash
+         WHERE s.in_scope = 1
+         ORDER BY s.tool_type, s.context_query",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let after = Counts { success: row.get(2)?, failure: row.get(3)? };
+        let stored = match row.get::<_, Option<i64>>(4)? {
+            Some(id) => Some((id, row.get::<_, String>(5)?, Counts { success: row.get(6)?, failure: row.get(7)? })),
+            None => None,
+        };
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, after, stored))
+    })?;
+    for row in rows {
+        let (tool_type, context, after, stored) = row?;
+        match stored {
+            None => report.added.push(Change { id: None, tool_type, context, before: None, after: Some(after) }),
+            Some((_, _, before)) if before == after => report.unchanged += 1,
+            Some((id, context, before)) => {
+                report.updated.push(Change { id: Some(id), tool_type, context, before: Some(before), after: Some(after) })
+            }
+        }
+    }
+
+    // Stored patterns in scope that no log produces any more
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.tool_type, p.context_query, p.success_count, p.failure_count,
+                EXISTS (SELECT 1 FROM pattern_devices d WHERE d.pattern_id = p.id)
+                OR EXISTS (SELECT 1 FROM pattern_tags t WHERE t.pattern_id = p.id AND t.tag LIKE 'pack:%')
+                OR EXISTS (SELECT 1 FROM pattern_history h WHERE h.pattern_id = p.id AND h.context_query != p.context_query)
+                OR EXISTS (SELECT 1 FROM reflection_verdicts v WHERE v.pattern_id = p.id)
+                OR p.approved_at IS NOT NULL
+                OR p.status = 'quarantined'
+         FROM patterns p
+         WHERE p.pattern_hash NOT IN (SELECT pattern_hash FROM relearn_staging)
+           AND (?1 IS NULL OR p.id IN (SELECT pattern_id FROM pattern_projects WHERE project_hash = ?1))
+           AND (?2 IS NULL OR p.tool_type = ?2 COLLATE NOCASE)
+           AND (?3 IS NULL OR julianday(p.created_at) >= julianday(?3))
+         ORDER BY p.id",
+    )?;
+    let since = report.scope.since.map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let rows = stmt.query_map(params![report.scope.project, report.scope.tool, since], |row| {
+        Ok((
+            Change {
+                id: Some(row.get(0)?),
+                tool_type: row.get(1)?,
+                context: row.get(2)?,
+                before: Some(Counts { success: row.get(3)?, failure: row.get(4)? }),
+                after: None,
+            },
+            row.get::<_, bool>(5)?,
+        ))
+    })?;
+    for row in rows {
+        let (change, protected) = row?;
+        if protected {
+            report.kept += 1;
+        } else {
+            report.removed.push(change);
+        }
+    }
+    Ok(report)
+}
+
+/// Write the staged relearn into the bank, returning the log positions it read to
+///
+/// Fails if the bank changed since the relearn was staged.
+fn apply_staged(conn: &Connection, report: &RelearnReport) -> Result<HashMap<PathBuf, u64>> {
+    let tx = conn.unchecked_transaction()?;
+    let (positions, fingerprint): (String, Option<String>) =
+        tx.query_row("SELECT file_positions, bank_fingerprint FROM relearn_staging_scope", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    if fingerprint.as_deref() != Some(bank_fingerprint(&tx)?.as_str()) {
+        bail!("Patterns changed since the relearn was staged; run 'mana relearn --stage' again before applying");
+    }
+    let positions: HashMap<PathBuf, u64> = serde_json::from_str(&positions)?;
+
+    tx.execute(
+        "INSERT INTO patterns (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, risky)
+         SELECT pattern_hash, tool_type, command_category, context_query, success_count, failure_count, risky
+         FROM relearn_staging s
+         WHERE in_scope = 1 AND NOT EXISTS (SELECT 1 FROM patterns p WHERE p.pattern_hash = s.pattern_hash)",
+        [],
+    )?;
+    {
+        let mut stmt = tx.prepare("UPDATE patterns SET success_count = ?2, failure_count = ?3 WHERE id = ?1")?;
+        for change in &report.updated {
+            if let (Some(id), Some(after)) = (change.id, change.after) {
+                stmt.execute(params![id, after.success, after.failure])?;
+            }
+        }
+    }
+
+    let links: Vec<(String, String)> = {
+        let mut stmt = tx.prepare("SELECT pattern_hash, projects FROM relearn_staging WHERE in_scope = 1 AND projects != ''")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    for (hash, projects) in &links {
+        for project in projects.split(',') {
+            projects::link(&tx, project, [hash.as_str()])?;
+        }
+    }
+
+    let removed: Vec<i64> = report.removed.iter().filter_map(|c| c.id).collect();
+    storage::patterns::delete_with_dependents(&tx, &removed)?;
+    tx.execute_batch("DELETE FROM relearn_staging; DELETE FROM relearn_staging_scope;")?;
+    tx.commit()?;
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learning::trajectory::ToolCall;
+
+    fn session(cwd: &str, command: &str) -> Trajectory {
+        Trajectory {
+            user_query: "Build the project".into(),
+            tool_calls: vec![ToolCall { tool_name: "Bash".into(), tool_input: serde_json::json!({ "command": command }) }],
+            cwd: Some(cwd.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_relearn_preserves_ids_and_respects_scope() {
+        let conn = Connection::open_in_memory().unwrap();
+        storage::create_schema(&conn).unwrap();
+        let sessions = vec![
+            session("/work/app", "cargo build --release"),
+            session("/work/app", "cargo build --release"),
+            session("/work/lib", "cargo test --workspace"),
+        ];
+        let extracted = extract_patterns(&sessions);
+        let (build, test) = (&extracted[0][0], &extracted[2][0]);
+        conn.execute(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, success_count) VALUES (7, ?1, 'Bash', 'edited build', 1)",
+            params![build.pattern_hash],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (8, 'gone', 'Bash', 'npm run old'), (9, 'pulled', 'Bash', 'make all');
+             INSERT INTO pattern_devices (pattern_id, device) VALUES (9, 'laptop');",
+        )
+        .unwrap();
+        let app = projects::project_hash(Path::new("/work/app"));
+        projects::link(&conn, &app, ["gone"]).unwrap();
+
+        // Only the app project: the lib pattern is neither added nor removed
+        let scope = Scope { project: Some(app.clone()), ..Default::default() };
+        stage_trajectories(&conn, &sessions, &scope, &HashMap::new()).unwrap();
+        let report = diff(&conn).unwrap();
+        assert!(report.added.is_empty());
+        assert_eq!(report.updated.len(), 1);
+        assert_eq!((report.updated[0].id, report.updated[0].after.unwrap().success), (Some(7), 2));
+        assert_eq!(report.removed.iter().map(|c| c.id).collect::<Vec<_>>(), vec![Some(8)]);
+
+        apply_staged(&conn, &report).unwrap();
+        let (context, success): (String, i64) =
+            conn.query_row("SELECT context_query, success_count FROM patterns WHERE id = 7", [], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+        assert_eq!((context.as_str(), success), ("edited build", 2));
+        let exists = |hash: &str| conn.query_row("SELECT COUNT(*) FROM patterns WHERE pattern_hash = ?1", [hash], |r| r.get::<_, i64>(0)).unwrap();
+        assert_eq!((exists("gone"), exists("pulled"), exists(&test.pattern_hash)), (0, 1, 0));
+        assert_eq!(projects::patterns_in(&conn, &app).unwrap(), vec![7]);
+
+        // Approved, quarantined and judged patterns are kept like the synced one
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, approved_at, status)
+             VALUES (10, 'approved', 'Bash', 'rm -rf dist', CURRENT_TIMESTAMP, 'active'),
+                    (11, 'quarantined', 'Bash', 'git push -f', NULL, 'quarantined'),
+                    (12, 'judged', 'Bash', 'npm ci', NULL, 'active');
+             INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence) VALUES ('t', 12, 'EFFECTIVE', 0.9);",
+        )
+        .unwrap();
+
+        // A full relearn adds the rest and keeps the synced pattern
+        stage_trajectories(&conn, &sessions, &Scope::default(), &HashMap::new()).unwrap();
+        let report = diff(&conn).unwrap();
+        assert_eq!((report.added.len(), report.unchanged, report.removed.len(), report.kept), (1, 1, 0, 4));
+
+        // Learning in between makes the staged result stale
+        conn.execute("UPDATE patterns SET success_count = success_count + 1 WHERE id = 7", []).unwrap();
+        assert!(apply_staged(&conn, &report).is_err());
+        stage_trajectories(&conn, &sessions, &Scope::default(), &HashMap::new()).unwrap();
+        let report = diff(&conn).unwrap();
+        assert!(diff(&conn).is_ok());
+        apply_staged(&conn, &report).unwrap();
+        assert!(diff(&conn).is_err());
+    }
+}
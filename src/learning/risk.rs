@@ -0,0 +1,86 @@
+//! Risk classification for learned commands
+//!
+//! Destructive shell commands (recursive deletes, force pushes, hard resets)
+//! are tagged as risky at extraction time so they are never injected as
+//! advice until a human approves them with `mana patterns approve-risky`.
+
+use anyhow::Result;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use std::sync::OnceLock;
+
+/// Destructive command signatures and the reason each is considered risky
+const RISKY_COMMANDS: &[(&str, &str)] = &[
+    (r"\brm\s+(-[a-zA-Z]*r[a-zA-Z]*f|-[a-zA-Z]*f[a-zA-Z]*r|-r\s+-f|-f\s+-r|--recursive\s+--force|--force\s+--recursive)\b", "recursive forced delete"),
+    (r"\bgit\s+push\b.*(\s-f\b|--force)", "force push"),
+    (r"\bgit\s+reset\s+--hard\b", "hard reset discards local changes"),
+    (r"\bgit\s+clean\s+-[a-zA-Z]*f", "git clean deletes untracked files"),
+    (r"\bgit\s+branch\s+-D\b", "force branch delete"),
+    (r"\bdd\s+.*\bof=/dev/", "raw device write"),
+    (r"\bmkfs(\.\w+)?\b", "filesystem format"),
+    (r"\bchmod\s+-R\s+0?777\b", "recursive world-writable permissions"),
+    (r"(?i)\bdrop\s+(table|database|schema)\b", "drops database objects"),
+    (r"(?i)\btruncate\s+table\b", "truncates database table"),
+    (r":\(\)\s*\{\s*:\|:&\s*\};:", "fork bomb"),
+];
+
+fn risky_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        RISKY_COMMANDS
+            .iter()
+            .filter_map(|(re, reason)| Regex::new(re).ok().map(|r| (r, *reason)))
+            .collect()
+    })
+}
+
+/// Classify a shell command, returning the reason if it looks destructive
+pub fn classify_command_risk(command: &str) -> Option<&'static str> {
+    risky_patterns()
+        .iter()
+        .find(|(re, _)| re.is_match(command))
+        .map(|(_, reason)| *reason)
+}
+
+/// Flag stored Bash patterns whose context holds a destructive command
+///
+/// For patterns learned before classification existed, or by a classifier
+/// with fewer signatures. Never clears a flag or an approval; returns how
+/// many patterns were newly flagged.
+pub fn flag_risky_patterns(conn: &Connection) -> Result<usize> {
+    let candidates: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, context_query FROM patterns WHERE tool_type = 'Bash' AND risky = 0")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    let mut flagged = 0;
+    for (id, context) in candidates {
+        if classify_command_risk(&context).is_some() {
+            flagged += conn.execute("UPDATE patterns SET risky = 1 WHERE id = ?1", params![id])?;
+        }
+    }
+    Ok(flagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_destructive_commands_flagged() {
+        assert!(classify_command_risk("rm -rf target/").is_some());
+        assert!(classify_command_risk("rm -fr /tmp/build").is_some());
+        assert!(classify_command_risk("git push --force origin main").is_some());
+        assert!(classify_command_risk("git push -f").is_some());
+        assert!(classify_command_risk("git reset --hard HEAD~1").is_some());
+        assert!(classify_command_risk("sqlite3 db 'DROP TABLE patterns'").is_some());
+    }
+
+    #[test]
+    fn test_safe_commands_not_flagged() {
+        assert!(classify_command_risk("cargo build --release").is_none());
+        assert!(classify_command_risk("rm src/old.rs").is_none());
+        assert!(classify_command_risk("git push origin feature-branch").is_none());
+        assert!(classify_command_risk("git reset HEAD file.rs").is_none());
+    }
+}
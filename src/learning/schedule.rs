@@ -0,0 +1,64 @@
+//! Daily schedule for consolidation in the daemon
+//!
+//! `[consolidation] schedule` lists local times of day, `"03:00"` or
+//! `"03:00, 15:30"`. While the daemon runs it consolidates at each of them;
+//! runs missed while it was stopped are not made up.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveTime, TimeZone};
+
+/// Times of day to run at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    times: Vec<NaiveTime>,
+}
+
+impl Schedule {
+    /// Parse a comma-separated list of `HH:MM` times
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut times = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(|t| NaiveTime::parse_from_str(t, "%H:%M").with_context(|| format!("invalid time {:?}, expected HH:MM", t)))
+            .collect::<Result<Vec<_>>>()?;
+        if times.is_empty() {
+            bail!("schedule lists no times");
+        }
+        times.sort();
+        times.dedup();
+        Ok(Self { times })
+    }
+
+    /// First scheduled time strictly after `now`
+    ///
+    /// A time skipped by a daylight saving change is skipped that day.
+    pub fn next_after<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = now.timezone();
+        let today = now.date_naive();
+        (0..=2)
+            .flat_map(|days| self.times.iter().map(move |t| (today + Duration::days(days)).and_time(*t)))
+            .filter_map(|local| tz.from_local_datetime(&local).earliest())
+            .find(|at| at > now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_next_run() {
+        let schedule = Schedule::parse("15:30, 03:00").unwrap();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+
+        assert_eq!(schedule.next_after(&at("2026-10-16T01:00:00Z")), Some(at("2026-10-16T03:00:00Z")));
+        assert_eq!(schedule.next_after(&at("2026-10-16T03:00:00Z")), Some(at("2026-10-16T15:30:00Z")));
+        assert_eq!(schedule.next_after(&at("2026-10-16T20:00:00Z")), Some(at("2026-10-17T03:00:00Z")));
+
+        assert!(Schedule::parse("nightly").is_err());
+        assert!(Schedule::parse("25:00").is_err());
+        assert!(Schedule::parse(" , ").is_err());
+    }
+}
@@ -2,17 +2,28 @@
 //!
 //! Parses Claude Code JSONL format to reconstruct trajectories
 //! for pattern extraction.
+//!
+//! Files are read a line at a time. A line that isn't valid JSON (or UTF-8)
+//! is skipped and counted in the file's [`ParseStats`] rather than failing
+//! the file, and an unterminated last line is left for the next read, since
+//! Claude Code may still be writing it. [`parse_files`] parses many files in
+//! parallel and reports on each.
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tracing::debug;
 
+/// Skipped lines described per file
+const MAX_DIAGNOSTICS: usize = 5;
+
 /// A reconstructed trajectory from JSONL logs
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Trajectory {
     pub session_id: String,
     pub user_query: String,
@@ -20,6 +31,15 @@ pub struct Trajectory {
     pub tool_calls: Vec<ToolCall>,
     pub tool_results: Vec<ToolResult>,
     pub verdict: Option<Verdict>,
+    /// Timestamp of the first message in the session (if the log has one)
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Timestamp of the last message in the session
+    #[serde(default)]
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Working directory the session ran in (if the log records it)
+    #[serde(default)]
+    pub cwd: Option<String>,
 }
 
 /// A tool call from the assistant
@@ -51,6 +71,8 @@ struct JsonlMessage {
     msg_type: Option<String>,
     #[serde(rename = "sessionId")]
     session_id: Option<String>,
+    timestamp: Option<String>,
+    cwd: Option<String>,
     message: Option<MessageContent>,
 }
 
@@ -61,13 +83,78 @@ struct MessageContent {
     content: Option<serde_json::Value>,
 }
 
+/// What parsing one log file found
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ParseStats {
+    pub path: PathBuf,
+    pub start_offset: u64,
+    /// End of the last complete line read; where the next read starts
+    pub end_offset: u64,
+    pub lines: usize,
+    pub trajectories: usize,
+    /// Lines that aren't valid JSON or UTF-8
+    pub skipped: usize,
+    /// The first few problems, by byte offset
+    pub errors: Vec<String>,
+}
+
+impl ParseStats {
+    fn note(&mut self, offset: u64, error: impl std::fmt::Display) {
+        if self.errors.len() < MAX_DIAGNOSTICS {
+            self.errors.push(format!("byte {}: {}", offset, error));
+        }
+    }
+}
+
 /// Parse trajectories from a JSONL file
 pub fn parse_trajectories(path: &Path, start_offset: u64) -> Result<Vec<Trajectory>> {
+    Ok(parse_file(path, start_offset)?.0)
+}
+
+/// Parse many JSONL files in parallel, each from its offset
+///
+/// Trajectories come back in file order. A file that can't be read is
+/// reported with its error and no progress.
+pub fn parse_files(files: &[(PathBuf, u64)]) -> (Vec<Trajectory>, Vec<ParseStats>) {
+    let parsed: Vec<(Vec<Trajectory>, ParseStats)> = files
+        .par_iter()
+        .map(|(path, start_offset)| {
+            parse_file(path, *start_offset).unwrap_or_else(|e| {
+                debug!("Failed to parse {:?}: {}", path, e);
+                let stats = ParseStats {
+                    path: path.clone(),
+                    start_offset: *start_offset,
+                    end_offset: *start_offset,
+                    errors: vec![format!("unreadable: {}", e)],
+                    ..Default::default()
+                };
+                (Vec::new(), stats)
+            })
+        })
+        .collect();
+
+    let mut trajectories = Vec::new();
+    let mut stats = Vec::with_capacity(parsed.len());
+    for (file_trajectories, file_stats) in parsed {
+        trajectories.extend(file_trajectories);
+        stats.push(file_stats);
+    }
+    (trajectories, stats)
+}
+
+/// Parse trajectories from a JSONL file, with what was read and skipped
+pub fn parse_file(path: &Path, start_offset: u64) -> Result<(Vec<Trajectory>, ParseStats)> {
     let file = File::open(path)?;
     let file_len = file.metadata()?.len();
+    let mut stats = ParseStats {
+        path: path.to_path_buf(),
+        start_offset,
+        end_offset: start_offset,
+        ..Default::default()
+    };
 
     if start_offset >= file_len {
-        return Ok(vec![]);
+        return Ok((vec![], stats));
     }
 
     let mut reader = BufReader::new(file);
@@ -79,20 +166,36 @@ pub fn parse_trajectories(path: &Path, start_offset: u64) -> Result<Vec<Trajecto
     let mut sessions: HashMap<String, SessionData> = HashMap::new();
     let default_session = "default".to_string();
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let offset = stats.end_offset;
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        let complete = buf.last() == Some(&b'\n');
+        let line = buf.trim_ascii();
 
         if line.is_empty() {
+            stats.end_offset += read as u64;
             continue;
         }
 
-        let msg: JsonlMessage = match serde_json::from_str(&line) {
+        let msg: JsonlMessage = match serde_json::from_slice(line) {
             Ok(m) => m,
-            Err(_) => continue,
+            // Still being written; the next read picks it up
+            Err(_) if !complete => break,
+            Err(e) => {
+                stats.end_offset += read as u64;
+                stats.lines += 1;
+                stats.skipped += 1;
+                stats.note(offset, e);
+                continue;
+            }
         };
+        stats.end_offset += read as u64;
+        stats.lines += 1;
 
         let msg_type = match &msg.msg_type {
             Some(t) => t.as_str(),
@@ -102,6 +205,15 @@ pub fn parse_trajectories(path: &Path, start_offset: u64) -> Result<Vec<Trajecto
         let session_id = msg.session_id.clone().unwrap_or_else(|| default_session.clone());
         let session = sessions.entry(session_id).or_default();
 
+        if let Some(ts) = msg.timestamp.as_deref().and_then(|t| DateTime::parse_from_rfc3339(t).ok()) {
+            let ts = ts.with_timezone(&Utc);
+            session.started_at.get_or_insert(ts);
+            session.ended_at = Some(ts);
+        }
+        if let Some(cwd) = msg.cwd.filter(|c| !c.is_empty()) {
+            session.cwd.get_or_insert(cwd);
+        }
+
         match msg_type {
             "user" => {
                 if let Some(ref message) = msg.message {
@@ -199,6 +311,9 @@ pub fn parse_trajectories(path: &Path, start_offset: u64) -> Result<Vec<Trajecto
                 tool_calls: data.tool_calls,
                 tool_results: data.tool_results,
                 verdict: None,
+                started_at: data.started_at,
+                ended_at: data.ended_at,
+                cwd: data.cwd,
             };
 
             // Judge the trajectory
@@ -207,8 +322,12 @@ pub fn parse_trajectories(path: &Path, start_offset: u64) -> Result<Vec<Trajecto
         }
     }
 
+    stats.trajectories = trajectories.len();
+    if stats.skipped > 0 {
+        debug!("Skipped {} malformed lines in {:?}", stats.skipped, path);
+    }
     debug!("Parsed {} trajectories from {:?}", trajectories.len(), path);
-    Ok(trajectories)
+    Ok((trajectories, stats))
 }
 
 #[derive(Debug, Default)]
@@ -217,6 +336,9 @@ struct SessionData {
     assistant_content: String,
     tool_calls: Vec<ToolCall>,
     tool_results: Vec<ToolResult>,
+    started_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+    cwd: Option<String>,
 }
 
 fn extract_text_content(content: &serde_json::Value) -> Option<String> {
@@ -248,7 +370,7 @@ fn extract_text_content(content: &serde_json::Value) -> Option<String> {
 /// Heuristic-based verdict judgment
 ///
 /// Based on ReasoningBank paper: use simple heuristics first, LLM judge optional
-fn judge_trajectory(trajectory: &Trajectory) -> Verdict {
+pub(crate) fn judge_trajectory(trajectory: &Trajectory) -> Verdict {
     // Check for errors in tool results
     let has_errors = trajectory.tool_results.iter().any(|r| {
         r.is_error ||
@@ -302,6 +424,9 @@ mod tests {
                 is_error: false,
             }],
             verdict: None,
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         };
 
         let verdict = judge_trajectory(&trajectory);
@@ -322,9 +447,39 @@ mod tests {
                 is_error: true,
             }],
             verdict: None,
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         };
 
         let verdict = judge_trajectory(&trajectory);
         assert!(!verdict.success);
     }
+
+    #[test]
+    fn test_parse_skips_malformed_lines_and_waits_for_partial_ones() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("s1.jsonl");
+        let tool_use = r#"{"type":"assistant","sessionId":"s1","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo build"}}]}}"#;
+        let partial = r#"{"type":"assistant","sessionId":"s2","mess"#;
+        let mut content = format!("{}\n{{not json\n\n", tool_use).into_bytes();
+        content.extend_from_slice(b"\xff\xfe\n");
+        let complete_len = content.len() as u64;
+        content.extend_from_slice(partial.as_bytes());
+        std::fs::write(&path, &content).unwrap();
+
+        let (trajectories, stats) = parse_files(&[(path.clone(), 0), (temp.path().join("missing.jsonl"), 0)]);
+        assert_eq!(trajectories.len(), 1);
+        let file = &stats[0];
+        assert_eq!((file.lines, file.trajectories, file.skipped), (3, 1, 2));
+        assert_eq!(file.end_offset, complete_len);
+        assert!(file.errors[0].starts_with(&format!("byte {}:", tool_use.len() + 1)));
+        assert!(stats[1].errors[0].starts_with("unreadable"));
+
+        // Once the line is finished it's read from where the last read stopped
+        content.extend_from_slice(b"sage\":{}}\n");
+        std::fs::write(&path, &content).unwrap();
+        let (_, stats) = parse_file(&path, complete_len).unwrap();
+        assert_eq!((stats.lines, stats.skipped, stats.end_offset), (1, 0, content.len() as u64));
+    }
 }
@@ -0,0 +1,150 @@
+//! Watch mode - learn from Claude Code logs as they are written
+//!
+//! Watches every configured log root for JSONL appends and runs an
+//! incremental learning pass once writes have been quiet for the debounce
+//! interval, instead of waiting for the session-end threshold. Learning
+//! resumes from the stored file offsets, so passes are cheap and nothing is
+//! learned twice.
+
+use anyhow::{bail, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::foreground_learn;
+use crate::reflection::{self, ScanMode};
+
+/// How long a pass waits for another learner to finish
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+
+/// Watch mode settings
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Quiet period after the last write before learning runs
+    pub debounce: Duration,
+    /// Also run incremental reflection after each learning pass
+    pub reflect: bool,
+}
+
+/// Watch the Claude log roots until interrupted
+pub async fn watch_logs(mana_dir: &Path, options: WatchOptions) -> Result<()> {
+    let roots: Vec<PathBuf> = crate::config::load_config(mana_dir)
+        .learning
+        .resolved_log_dirs()
+        .into_iter()
+        .filter(|dir| dir.exists())
+        .collect();
+    if roots.is_empty() {
+        bail!("No Claude log directories found (set [learning] log_dirs or MANA_CLAUDE_LOGS)");
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) if is_log_write(&event) => {
+            let _ = tx.send(());
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Watch error: {}", e),
+    })?;
+    for root in &roots {
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        println!("Watching {}", root.display());
+    }
+    println!("Press Ctrl-C to stop.");
+
+    // Catch up on anything written while nothing was watching
+    run_pass(mana_dir, &options).await;
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                if event.is_none() {
+                    break;
+                }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+
+        // Sessions write in bursts; wait for a quiet period before parsing
+        loop {
+            match tokio::time::timeout(options.debounce, rx.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => return Ok(()),
+                Err(_) => break,
+            }
+        }
+
+        run_pass(mana_dir, &options).await;
+    }
+
+    println!("Stopped watching.");
+    Ok(())
+}
+
+/// One learning pass (plus reflection if enabled); failures are reported, not fatal
+async fn run_pass(mana_dir: &Path, options: &WatchOptions) {
+    let now = chrono::Local::now().format("%H:%M:%S");
+
+    // Wait out a concurrent session end; if it is still going, the next write retries
+    let _lock = match super::lock::acquire(mana_dir, LOCK_WAIT) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            debug!("Learning already in progress, skipping pass");
+            return;
+        }
+        Err(e) => {
+            warn!("Could not take learning lock: {}", e);
+            return;
+        }
+    };
+
+    let learned = match foreground_learn(&[]).await {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("Learning pass failed: {}", e);
+            return;
+        }
+    };
+    if learned.trajectories_processed == 0 {
+        debug!("No new trajectories");
+        return;
+    }
+    println!(
+        "[{}] Learned from {} trajectories: {} patterns ({}ms)",
+        now, learned.trajectories_processed, learned.patterns_created, learned.duration_ms
+    );
+
+    if options.reflect {
+        match reflection::run_cycle(mana_dir, "watch", ScanMode::Incremental) {
+            Ok(summary) if summary.verdicts > 0 => println!(
+                "[{}] Reflected on {} trajectories: {} verdicts, {} patterns updated",
+                now, summary.trajectories, summary.verdicts, summary.patterns_updated
+            ),
+            Ok(_) => {}
+            Err(e) => warn!("Reflection pass failed: {}", e),
+        }
+    }
+}
+
+/// Whether an event is a write to a JSONL log
+fn is_log_write(event: &Event) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event.paths.iter().any(|p| p.extension().is_some_and(|e| e == "jsonl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, DataChange, ModifyKind};
+
+    #[test]
+    fn test_is_log_write() {
+        let event = |kind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+
+        assert!(is_log_write(&event(EventKind::Modify(ModifyKind::Data(DataChange::Any)), "/p/s.jsonl")));
+        assert!(is_log_write(&event(EventKind::Create(CreateKind::File), "/p/new.jsonl")));
+        assert!(!is_log_write(&event(EventKind::Modify(ModifyKind::Data(DataChange::Any)), "/p/notes.md")));
+        assert!(!is_log_write(&event(EventKind::Access(AccessKind::Any), "/p/s.jsonl")));
+    }
+}
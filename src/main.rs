@@ -1,20 +1,48 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use tracing::info;
+use tracing::{info, Instrument};
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 // Type aliases for complex types (clippy::type_complexity)
-type VerdictRow = (String, Option<i64>, String, f64, Option<String>, String);
+type VerdictRow = (i64, String, Option<i64>, String, f64, Option<String>, Option<String>, String);
 type PatternRow = (String, String, i64, i64, Option<Vec<u8>>);
 
+// Printing goes through `ci` so CI mode can drop emoji in one place. Defined
+// before the modules so these shadow the std macros crate-wide.
+macro_rules! println {
+    () => { ::std::println!() };
+    ($($arg:tt)*) => { $crate::ci::print_line(::std::format_args!($($arg)*)) };
+}
+macro_rules! print {
+    ($($arg:tt)*) => { $crate::ci::print(::std::format_args!($($arg)*)) };
+}
+macro_rules! eprintln {
+    () => { ::std::eprintln!() };
+    ($($arg:tt)*) => { $crate::ci::eprint_line(::std::format_args!($($arg)*)) };
+}
+
 mod bench;
+mod browse;
+mod ci;
+mod config;
+mod dashboard;
 mod daemon;
+mod doctor;
 mod embeddings;
 mod hooks;
 mod learning;
+mod net;
+mod profile;
 mod reflection;
+mod report;
+mod simulate;
 mod storage;
+mod suggest;
 mod sync;
+mod table;
+mod telemetry;
+mod trace;
 mod update;
 
 /// MANA - Memory-Augmented Neural Assistant
@@ -24,11 +52,33 @@ mod update;
 #[command(author = "MANA Autonomous Agent")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "Memory-Augmented Neural Assistant for Claude Code", long_about = None)]
+#[command(after_help = "Exit codes: 0 success, 1 error, 2 invalid arguments, 3 not initialized, \
+4 busy (locked), 5 verification failed, 6 check failed, 7 input required, 8 read-only")]
 struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
 
+    /// Print machine-readable JSON instead of formatted text (read commands)
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Use the named profile (~/.mana/profiles/<name>); overrides MANA_PROFILE
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Non-interactive output for pipelines: no prompts, emoji or colors (also MANA_CI=1)
+    #[arg(long, global = true)]
+    ci: bool,
+
+    /// Never write the database, index or state files; commands that would fail (also [storage] read_only)
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    /// Record tracing spans to a file: Chrome trace JSON, or folded stacks if it ends in .folded (also MANA_TRACE_FILE)
+    #[arg(long, global = true, value_name = "PATH")]
+    trace_file: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -40,28 +90,111 @@ enum Commands {
         /// Tool type: edit, bash, task
         #[arg(long)]
         tool: String,
+
+        /// Dry run: report how each candidate pattern was ranked and what would be injected
+        #[arg(long)]
+        explain: bool,
+
+        /// Output format of the injected context (default: injection.adapter)
+        #[arg(long, value_enum)]
+        adapter: Option<config::OutputAdapter>,
     },
 
     /// Process session end and trigger learning if threshold met
     SessionEnd,
 
+    /// Handle a Claude Code hook event (PreCompact, SubagentStop, Notification) read from stdin
+    Hook {
+        /// Event name, e.g. pre-compact or PreCompact
+        event: hooks::events::HookEvent,
+    },
+
+    /// Learn from new Claude Code log data now
+    Learn {
+        /// Learn from these JSONL files instead of the Claude Code log directories (repeatable)
+        #[arg(long)]
+        file: Vec<std::path::PathBuf>,
+        /// Only learn from sessions active within this window, e.g. 2d
+        #[arg(long)]
+        since: Option<String>,
+        /// Show which patterns would be created or updated without storing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// List each log file read, with trajectories found and lines skipped
+        #[arg(long)]
+        report: bool,
+    },
+
     /// Run consolidation tasks manually
-    Consolidate,
+    Consolidate {
+        /// Run only these stages (repeatable)
+        #[arg(long, value_name = "STAGE", value_parser = clap::builder::PossibleValuesParser::new(learning::CONSOLIDATION_STAGES))]
+        only: Vec<String>,
+        /// Skip these stages (repeatable)
+        #[arg(long, value_name = "STAGE", conflicts_with = "only", value_parser = clap::builder::PossibleValuesParser::new(learning::CONSOLIDATION_STAGES))]
+        skip: Vec<String>,
+    },
+
+    /// Learn continuously as Claude Code writes session logs
+    Watch {
+        /// Also run incremental reflection after each learning pass
+        #[arg(long)]
+        reflect: bool,
+        /// Quiet period after the last write before learning (milliseconds)
+        #[arg(long, default_value = "2000")]
+        debounce_ms: u64,
+    },
+
+    /// Learn from other assistants' logs (Aider, Cursor, OpenAI-style transcripts)
+    ImportLogs {
+        /// Log format
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(learning::IMPORT_FORMATS))]
+        format: String,
+        /// Log files or directories to scan
+        #[arg(required = true)]
+        paths: Vec<std::path::PathBuf>,
+        /// Parse and report without storing patterns
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Show current status and statistics
     Status,
 
     /// Show detailed statistics
-    Stats,
+    Stats {
+        /// Open a live dashboard that refreshes until you press q
+        #[arg(long, conflicts_with = "json")]
+        watch: bool,
+        /// Dashboard refresh interval (seconds)
+        #[arg(long, default_value = "2", requires = "watch")]
+        interval: u64,
+    },
 
     /// Initialize MANA configuration
-    Init,
+    Init {
+        /// Also register MANA's hooks in Claude Code's settings.json
+        #[arg(long, conflicts_with = "uninstall_hooks")]
+        install_hooks: bool,
+        /// Remove MANA's hooks from Claude Code's settings.json (skips initialization)
+        #[arg(long)]
+        uninstall_hooks: bool,
+    },
 
     /// Check for updates and self-update if available
     Update {
         /// Actually install the update (otherwise just checks)
         #[arg(long)]
         force: bool,
+        /// Release channel: stable or beta (defaults to [update] channel)
+        #[arg(long)]
+        channel: Option<String>,
+        /// Restore the binary replaced by the last update
+        #[arg(long, conflicts_with_all = ["force", "channel", "assets"])]
+        rollback: bool,
+        /// Install or upgrade the embedding model files instead of the binary
+        #[arg(long, conflicts_with = "force")]
+        assets: bool,
     },
 
     /// Debug: show sample patterns for inspection
@@ -81,11 +214,40 @@ enum Commands {
         dry_run: bool,
     },
 
-    /// Reset patterns and re-learn from logs
-    Relearn,
+    /// Re-learn patterns from the logs, keeping ids, edits and synced patterns
+    Relearn {
+        /// Only patterns from sessions in this project (directory or hash)
+        #[arg(long)]
+        project: Option<String>,
+        /// Only patterns for this tool (e.g. Bash, Edit)
+        #[arg(long)]
+        tool: Option<String>,
+        /// Only patterns from recent sessions, e.g. 7d
+        #[arg(long)]
+        since: Option<String>,
+        /// Stage the result and show how it differs, without changing patterns
+        #[arg(long, conflicts_with = "apply")]
+        stage: bool,
+        /// Apply the staged result
+        #[arg(long, conflicts_with_all = ["project", "tool", "since"])]
+        apply: bool,
+    },
 
     /// Run performance benchmarks
-    Bench,
+    Bench {
+        /// Measure batch insert throughput at 10k/100k patterns instead
+        #[arg(long)]
+        insert: bool,
+        /// Compare against a saved run (a JSON file from .mana/bench/)
+        #[arg(long, conflicts_with = "insert")]
+        compare: Option<std::path::PathBuf>,
+        /// Also benchmark embedding search at 100k vectors (building the graph takes minutes)
+        #[arg(long, conflicts_with = "insert")]
+        full: bool,
+        /// Print the results as a markdown table as well (for issue updates)
+        #[arg(long, conflicts_with = "insert")]
+        markdown: bool,
+    },
 
     /// Manage vector embeddings for semantic search
     Embed {
@@ -101,30 +263,120 @@ enum Commands {
 
     /// Export patterns to a file (for sync/sharing)
     Export {
-        /// Output file path
-        #[arg(long, default_value = "mana-patterns.json")]
-        output: String,
+        /// Output format
+        #[arg(long, default_value = "json", value_parser = ["json", "markdown", "sqlite"])]
+        format: String,
+        /// Output path (defaults to mana-patterns.json, mana-patterns.md, mana-patterns/ with --split, or mana-snapshot.sqlite)
+        #[arg(long)]
+        output: Option<String>,
+        /// SQLite: include embedding vectors in the snapshot
+        #[arg(long)]
+        with_vectors: bool,
+        /// JSON: include reflection verdict stats so patterns arrive with their reputation
+        #[arg(long)]
+        with_verdicts: bool,
+        /// Markdown: write one file per tool/category plus an index into the output directory
+        #[arg(long)]
+        split: bool,
+        /// Markdown: patterns listed per tool/category
+        #[arg(long, default_value = "10")]
+        limit: usize,
         /// Encrypt the export with a passphrase
         #[arg(long)]
         encrypted: bool,
         /// Passphrase for encryption (reads from MANA_SYNC_KEY env var if not provided)
         #[arg(long)]
         passphrase: Option<String>,
+        /// Encrypt to an age public key (repeatable; requires the age CLI)
+        #[arg(long = "encrypt-to", value_name = "RECIPIENT", conflicts_with = "encrypted")]
+        encrypt_to: Vec<String>,
+        /// Sign the export with an Ed25519 SSH key (writes <output>.sig)
+        #[arg(long)]
+        sign: bool,
+        /// Key for --sign (defaults to [signing] key in sync.toml, then ~/.ssh/id_ed25519)
+        #[arg(long, requires = "sign")]
+        sign_key: Option<String>,
         /// Skip path sanitization (not recommended for sharing)
         #[arg(long)]
         no_sanitize: bool,
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Write an annotated copy of a session log showing injections and verdicts
+    Annotate {
+        /// Claude Code session log (JSONL)
+        input: std::path::PathBuf,
+        /// Output path (defaults to <input>.annotated.jsonl)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// List recent sessions with the patterns injected and how they were judged
+    Sessions {
+        /// Number of sessions to show
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Only sessions active since a duration ago or a date (e.g. 1d, 2w, 2024-05-01)
+        #[arg(long)]
+        since: Option<String>,
+    },
+
+    /// Write a static learning report (HTML or Markdown) for CI artifacts or GitHub Pages
+    Report {
+        /// Output format: html or markdown
+        #[arg(long, default_value = "html")]
+        format: String,
+        /// Directory to write the report into
+        #[arg(long, default_value = "report")]
+        out: std::path::PathBuf,
+        /// Days covered by the trend, leaderboard and regressions
+        #[arg(long, default_value = "7")]
+        days: u32,
+    },
+
+    /// Suggest a ranked plan of patterns and skills for a task
+    Suggest {
+        /// What you're about to do, in free text
+        #[arg(long)]
+        context: String,
+        /// Maximum number of steps
+        #[arg(short, long, default_value = "5")]
+        limit: usize,
+    },
+
+    /// Replay past sessions against the current patterns and config, without calling Claude
+    Simulate {
+        /// Sessions since this long ago (12h, 30d, 2w) or a date (2024-05-01)
+        #[arg(long, default_value = "30d")]
+        since: String,
+        /// Patterns and gaps listed
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
     },
 
     /// Import patterns from a file
     Import {
-        /// Input file path
+        /// Input file path (JSON export or SQLite snapshot)
         input: String,
         /// Passphrase for decryption (reads from MANA_SYNC_KEY env var if not provided)
         #[arg(long)]
         passphrase: Option<String>,
-        /// Merge strategy: add (default), replace, keep-best
-        #[arg(long, default_value = "add")]
-        merge: String,
+        /// Merge strategy for patterns already here
+        #[arg(long, value_enum, default_value_t = sync::export::MergeStrategy::Add)]
+        merge: sync::export::MergeStrategy,
+        /// Pick the winner of each conflicting pattern yourself (a keep-best merge you decide)
+        #[arg(long, conflicts_with = "merge")]
+        interactive: bool,
+        /// Refuse the file unless it carries a signature from a trusted key
+        #[arg(long)]
+        require_signed: bool,
+    },
+
+    /// Manage age keys for recipient-encrypted exports
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
     },
 
     /// Sync patterns with a remote repository
@@ -139,6 +391,22 @@ enum Commands {
         action: TeamAction,
     },
 
+    /// Sign in to the Supabase backend
+    Login {
+        /// Email address to send a one-time sign-in code to
+        #[arg(long, conflicts_with = "token")]
+        email: Option<String>,
+        /// Code from the email (prompted for when omitted)
+        #[arg(long, requires = "email")]
+        code: Option<String>,
+        /// Existing access token to use instead (reads MANA_SUPABASE_TOKEN if not provided)
+        #[arg(long)]
+        token: Option<String>,
+    },
+
+    /// Sign out of the Supabase backend
+    Logout,
+
     /// Pattern management and inspection
     Patterns {
         #[command(subcommand)]
@@ -150,15 +418,122 @@ enum Commands {
         #[command(subcommand)]
         action: DaemonAction,
     },
+
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
+    /// Inspect and edit config.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Check database, embeddings, hooks, daemon, sync, log access and version
+    Doctor {
+        /// Skip checks that need the network (sync connectivity, latest version)
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Manage named profiles (separate config, database and sync settings)
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+
+    /// Search, install and publish community pattern packs
+    Registry {
+        #[command(subcommand)]
+        action: RegistryAction,
+    },
+
+    /// Opt-in anonymous usage metrics for the maintainers
+    Telemetry {
+        #[command(subcommand)]
+        action: TelemetryAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum TelemetryAction {
+    /// Send anonymous aggregate metrics periodically (see `preview` for what)
+    Enable {
+        /// Where reports are POSTed (default: the configured telemetry.endpoint)
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+
+    /// Stop sending metrics
+    Disable,
+
+    /// Whether telemetry is on, where it goes and when it last went
+    Status,
+
+    /// Print exactly what the next report would contain
+    Preview,
+
+    /// Send a report now instead of waiting for the daemon
+    Send,
+}
+
+#[derive(Subcommand)]
+enum RegistryAction {
+    /// List packs in the registry, newest version of each
+    Search {
+        /// Only packs whose name or description contains this
+        query: Option<String>,
+        /// Registry index URL or path (defaults to [registry] index_url in sync.toml)
+        #[arg(long)]
+        index: Option<String>,
+    },
+    /// Download, verify and install a pack
+    Install {
+        /// Pack name, optionally with a version: name@1.2.0, name@^1.2.0, name@1
+        pack: String,
+        /// Registry index URL or path (defaults to [registry] index_url in sync.toml)
+        #[arg(long)]
+        index: Option<String>,
+        /// Install even if the pack has no signature
+        #[arg(long)]
+        allow_unsigned: bool,
+    },
+    /// Add an export bundle to a local registry checkout
+    Publish {
+        /// Export bundle to publish (from 'mana export', unencrypted)
+        file: String,
+        /// Pack name (lowercase letters, digits and '-')
+        #[arg(long)]
+        name: String,
+        /// Pack version (major.minor.patch)
+        #[arg(long)]
+        version: String,
+        /// One-line description shown by search
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Directory holding the registry's index.json
+        #[arg(long)]
+        registry_dir: String,
+        /// Key to sign with (defaults to [signing] key in sync.toml, then ~/.ssh/id_ed25519)
+        #[arg(long)]
+        sign_key: Option<String>,
+        /// Publish without a signature
+        #[arg(long, conflicts_with = "sign_key")]
+        no_sign: bool,
+    },
 }
 
 #[derive(Subcommand)]
+// Parsed once per run; boxing Init's backend options buys nothing
+#[allow(clippy::large_enum_variant)]
 enum SyncAction {
     /// Initialize sync with a git repository
     Init {
-        /// Backend type: git (default), s3, supabase, or p2p
-        #[arg(long, default_value = "git")]
-        backend: String,
+        /// Backend type
+        #[arg(long, value_enum, ignore_case = true, default_value_t = sync::BackendKind::Git)]
+        backend: sync::BackendKind,
         /// Git remote URL (for git backend, leave empty for local-only init)
         #[arg(long, default_value = "")]
         remote: String,
@@ -174,12 +549,27 @@ enum SyncAction {
         /// AWS region (for s3 backend)
         #[arg(long, default_value = "us-east-1")]
         region: String,
+        /// Endpoint of an S3-compatible service such as MinIO, R2 or B2 (for s3 backend)
+        #[arg(long)]
+        endpoint_url: Option<String>,
+        /// Use path-style bucket addressing, as MinIO expects (for s3 backend)
+        #[arg(long)]
+        path_style: bool,
+        /// PEM bundle to trust instead of the system roots, for self-signed endpoints (for s3 backend)
+        #[arg(long)]
+        ca_bundle: Option<String>,
+        /// Allow an unencrypted http:// endpoint (for s3 backend)
+        #[arg(long)]
+        allow_http: bool,
         /// Supabase project URL (for supabase backend)
         #[arg(long, default_value = "")]
         url: String,
-        /// Discovery method for P2P: static (default), mdns, dht
-        #[arg(long, default_value = "static")]
-        discover: String,
+        /// SSH target as user@host:/path (for ssh backend)
+        #[arg(long, default_value = "")]
+        host: String,
+        /// Discovery method for P2P
+        #[arg(long, value_enum, ignore_case = true, default_value_t = sync::DiscoveryMethod::Static)]
+        discover: sync::DiscoveryMethod,
         /// Listen port for P2P sync
         #[arg(long, default_value = "4222")]
         port: u16,
@@ -196,6 +586,12 @@ enum SyncAction {
         /// Passphrase for encryption (reads from MANA_SYNC_KEY env var if not provided)
         #[arg(long)]
         passphrase: Option<String>,
+        /// Sign the pushed export with an Ed25519 SSH key (git only)
+        #[arg(long)]
+        sign: bool,
+        /// Key for --sign (defaults to [signing] key in sync.toml, then ~/.ssh/id_ed25519)
+        #[arg(long, requires = "sign")]
+        sign_key: Option<String>,
     },
 
     /// Pull patterns from the remote repository
@@ -203,14 +599,49 @@ enum SyncAction {
         /// Passphrase for decryption (reads from MANA_SYNC_KEY env var if not provided)
         #[arg(long)]
         passphrase: Option<String>,
-        /// Merge strategy: add (default), replace, keep-best
-        #[arg(long, default_value = "add")]
-        merge: String,
+        /// Merge strategy for patterns already here
+        #[arg(long, value_enum, default_value_t = sync::export::MergeStrategy::Add)]
+        merge: sync::export::MergeStrategy,
+        /// Pick the winner of each conflicting pattern yourself (a keep-best merge you decide)
+        #[arg(long, conflicts_with = "merge")]
+        interactive: bool,
+        /// Refuse the pull unless the export is signed by a trusted key (git only)
+        #[arg(long)]
+        require_signed: bool,
+    },
+
+    /// Pull, merge and push in one step; stops before pushing if conflicts need review
+    Sync {
+        /// Commit message for the push
+        #[arg(short, long)]
+        message: Option<String>,
+        /// Passphrase for encryption (reads from MANA_SYNC_KEY env var if not provided)
+        #[arg(long)]
+        passphrase: Option<String>,
+        /// Merge strategy for patterns already here
+        #[arg(long, value_enum, default_value_t = sync::export::MergeStrategy::Add)]
+        merge: sync::export::MergeStrategy,
+        /// Refuse the pull unless the export is signed by a trusted key (git only)
+        #[arg(long)]
+        require_signed: bool,
+    },
+
+    /// Merge teammates' patterns as they change, until interrupted
+    ///
+    /// With supabase this follows the team's live updates; with p2p it
+    /// answers syncs from peers on the listen port.
+    Listen {
+        /// Merge strategy for patterns already here
+        #[arg(long, value_enum, default_value_t = sync::export::MergeStrategy::Add)]
+        merge: sync::export::MergeStrategy,
     },
 
     /// Show sync status
     Status,
 
+    /// Check that the git remote accepts the configured credentials
+    TestAuth,
+
     /// Set the encryption passphrase
     SetKey,
 
@@ -260,10 +691,32 @@ enum EmbedAction {
         /// Number of results
         #[arg(short, long, default_value = "5")]
         limit: usize,
+        /// Only patterns for this tool (e.g. Bash, Edit)
+        #[arg(long)]
+        tool: Option<String>,
+        /// Only patterns in this command category (e.g. cargo, npm)
+        #[arg(long)]
+        category: Option<String>,
+        /// Only patterns scoring at least this (successes minus failures)
+        #[arg(long, allow_hyphen_values = true)]
+        min_score: Option<i64>,
     },
 
     /// Generate embeddings for patterns that don't have them
     Generate,
+
+    /// Benchmark HNSW parameters on the current index and recommend values
+    Tune {
+        /// Minimum acceptable recall@10 against exact search
+        #[arg(long, default_value = "0.95")]
+        target_recall: f64,
+        /// Number of sampled queries
+        #[arg(long, default_value = "200")]
+        queries: usize,
+        /// Write the recommended values to config.toml
+        #[arg(long)]
+        apply: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -276,13 +729,23 @@ enum ReflectAction {
         /// Trigger type label (manual by default)
         #[arg(long, default_value = "manual")]
         trigger: String,
+
+        /// Rescan logs for trajectories that ended within this window (e.g. 12h, 7d)
+        #[arg(long, conflicts_with = "all")]
+        since: Option<String>,
+
+        /// Rescan all logs from the start
+        #[arg(long)]
+        all: bool,
     },
 
     /// Show recent verdicts
     Verdicts {
-        /// Number of verdicts to show
+        /// Number of verdicts to show (0 for all)
         #[arg(short, long, default_value = "10")]
         limit: usize,
+        #[command(flatten)]
+        layout: LayoutArgs,
     },
 
     /// Analyze a specific pattern's reflection history
@@ -291,10 +754,57 @@ enum ReflectAction {
         pattern_id: i64,
     },
 
+    /// Apply a verdict's suggested improvement to its pattern
+    Apply {
+        /// Verdict ID (shown by 'mana reflect verdicts')
+        verdict_id: i64,
+    },
+
+    /// Show recurring root causes across verdicts
+    Causes {
+        /// Number of failure modes to show
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+
+        /// Only consider verdicts from the last N days
+        #[arg(long)]
+        days: Option<u32>,
+
+        /// Print a markdown table (for retros)
+        #[arg(long)]
+        markdown: bool,
+    },
+
+    /// Compare sessions with and without injection (needs [injection] control_fraction)
+    Experiment {
+        /// Only sessions logged since a duration ago or a date (e.g. 7d, 2024-05-01)
+        #[arg(long, default_value = "30d")]
+        since: String,
+    },
+
     /// Initialize reflection tables (run once)
     Init,
 }
 
+#[derive(Subcommand)]
+enum KeysAction {
+    /// Generate a local age identity
+    Generate,
+
+    /// Print the public key to share with teammates
+    Show,
+
+    /// Retire the current identity and generate a new one
+    Rotate,
+
+    /// List the current and retired identities
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum TeamAction {
     /// Create a new team
@@ -313,6 +823,18 @@ enum TeamAction {
         team: String,
         /// Email of the user to invite
         email: String,
+        /// Role to grant: member or admin
+        #[arg(long, default_value = "member")]
+        role: String,
+        /// Days until the invite expires
+        #[arg(long, default_value = "7")]
+        expires_days: i64,
+    },
+
+    /// Manage pending invites
+    Invites {
+        #[command(subcommand)]
+        action: InvitesAction,
     },
 
     /// Join a team using an invite code
@@ -336,126 +858,701 @@ enum TeamAction {
 }
 
 #[derive(Subcommand)]
-enum DaemonAction {
-    /// Start the daemon in background
-    Start {
-        /// Run in foreground (for debugging)
+enum InvitesAction {
+    /// List invites for the teams you administer
+    List {
+        /// Only show invites for this team
         #[arg(long)]
-        foreground: bool,
+        team: Option<String>,
     },
 
-    /// Stop the running daemon
-    Stop,
-
-    /// Show daemon status
-    Status,
+    /// Revoke an invite so it can't be redeemed
+    Revoke {
+        /// Invite code
+        code: String,
+    },
 }
 
 #[derive(Subcommand)]
-enum PatternsAction {
-    /// List all patterns with filtering options
-    List {
-        /// Filter by tool type (e.g., Edit, Bash, Write)
-        #[arg(long)]
-        tool: Option<String>,
-        /// Maximum number of patterns to show
-        #[arg(short, long, default_value = "20")]
-        limit: usize,
-        /// Sort by: score (default), recent, uses
-        #[arg(long, default_value = "score")]
-        sort: String,
-        /// Show only patterns with score above this threshold
+enum DbAction {
+    /// Apply pending schema migrations (backing up the database first)
+    Migrate {
+        /// List pending migrations without applying them
         #[arg(long)]
-        min_score: Option<i64>,
+        dry_run: bool,
     },
 
-    /// Show detailed information about a specific pattern
-    Show {
-        /// Pattern ID to show
-        pattern_id: i64,
+    /// Show the database schema version
+    Version,
+
+    /// Rebuild the database file to reclaim unused space
+    Vacuum,
+
+    /// Check the database for corruption
+    IntegrityCheck,
+
+    /// Find rows and vectors left behind by deleted patterns and remove them
+    Fsck {
+        /// Report what would be removed without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
 
-    /// Search patterns by content
-    Search {
-        /// Search query (semantic search if embeddings available)
-        query: String,
-        /// Number of results to show
-        #[arg(short, long, default_value = "10")]
-        limit: usize,
+    /// Flush the write-ahead log into the main database file
+    Checkpoint,
+
+    /// Write a timestamped backup to .mana/backups/
+    Backup {
+        /// Also include the embedding index, learning state and CRDT state, as one tarball
+        #[arg(long)]
+        full: bool,
     },
 
-    /// Show pattern statistics summary
-    Summary,
+    /// List available backups
+    Backups,
 
-    /// Delete a specific pattern by ID
-    Delete {
-        /// Pattern ID to delete
-        pattern_id: i64,
+    /// Replace the database with a backup (the current one is backed up first)
+    Restore {
+        /// Backup file name or path (default: newest backup)
+        backup: Option<String>,
         /// Skip confirmation prompt
         #[arg(long)]
         force: bool,
     },
+
+    /// Encrypt the database with SQLCipher (key from MANA_DB_KEY or the OS keychain)
+    Encrypt {
+        /// Generate a random key and store it in the OS keychain
+        #[arg(long)]
+        generate_key: bool,
+    },
+
+    /// Decrypt the database back to plain SQLite
+    Decrypt,
 }
 
-/// Main entry point - uses sync main for inject command to avoid tokio overhead
-fn main() -> Result<()> {
-    // OPTIMIZATION: Parse args without initializing tokio runtime
-    // The inject command needs <10ms latency, but tokio::main adds ~50ms overhead
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective value of a key (e.g. injection.max_patterns)
+    Get {
+        key: String,
+    },
 
-    // For inject command, run without tokio for maximum speed
-    if let Commands::Inject { tool } = &cli.command {
-        // Skip logging setup for inject - it adds overhead and we don't need it
-        // Just run the context injection synchronously
-        return hooks::inject_context(tool);
-    }
+    /// Set a key in config.toml after validating it
+    Set {
+        key: String,
+        /// TOML literal; bare words are stored as strings
+        value: String,
+    },
 
-    // For all other commands, use the async runtime
-    run_async_main(cli)
+    /// Check config.toml for syntax errors, unknown keys and invalid values
+    Validate,
+
+    /// Print the effective configuration (defaults, file and environment merged)
+    Show,
 }
 
-/// Async main for commands that need tokio runtime
-#[tokio::main(flavor = "current_thread")]
-async fn run_async_main(cli: Cli) -> Result<()> {
-    // Initialize logging - for most commands
-    let filter = if cli.verbose {
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"))
-    } else {
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
-    };
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// List profiles and mark the active one
+    List,
 
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .with_target(false)
-        .with_writer(std::io::stderr)  // Always write logs to stderr, not stdout
-        .init();
+    /// Create a profile with a fresh database and default config
+    Create {
+        name: String,
+    },
 
-    match cli.command {
-        Commands::Inject { tool } => {
-            // Should never reach here due to early return in main()
-            // But keep for completeness
-            hooks::inject_context(&tool)?;
-        }
-        Commands::SessionEnd => {
+    /// Use a profile by default ("default" returns to ~/.mana)
+    Switch {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DaemonAction {
+    /// Start the daemon in background
+    Start {
+        /// Run in foreground (for debugging)
+        #[arg(long)]
+        foreground: bool,
+    },
+
+    /// Stop the running daemon
+    Stop,
+
+    /// Show daemon status
+    Status,
+}
+
+/// Order of `mana patterns list`
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum PatternSort {
+    /// Quality per the [ranking] formula
+    Score,
+    /// Newest first
+    Recent,
+    /// Most used first
+    Uses,
+}
+
+/// Table layout flags for listings (see `table`)
+#[derive(clap::Args, Debug, Clone, Copy)]
+struct LayoutArgs {
+    /// Don't cut columns to the terminal width
+    #[arg(long)]
+    wide: bool,
+    /// Wrap long text onto extra lines instead of cutting it
+    #[arg(long)]
+    full_context: bool,
+}
+
+impl LayoutArgs {
+    /// Render `table` and print it, paging if it's long
+    fn print(self, table: &table::Table) -> Result<()> {
+        table::page(&table.render(table::width(), table::Fit::from_flags(self.wide, self.full_context)))
+    }
+}
+
+/// Pattern selection flags shared by `patterns list` and `export`
+#[derive(clap::Args, Debug, Clone)]
+struct FilterArgs {
+    /// Filter by tool type (e.g., Edit, Bash, Write)
+    #[arg(long)]
+    tool: Option<String>,
+    /// Filter by command category (e.g., cargo, npm, rs)
+    #[arg(long)]
+    category: Option<String>,
+    /// Only patterns with at least this score (successes minus failures)
+    #[arg(long, allow_hyphen_values = true)]
+    min_score: Option<i64>,
+    /// Only patterns with this tag (repeat to require several)
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Only patterns learned in this project (directory or project hash)
+    #[arg(long)]
+    project: Option<String>,
+    /// Only patterns created or used since a duration ago or a date (e.g. 7d, 2w, 2024-05-01)
+    #[arg(long)]
+    since: Option<String>,
+    /// Only patterns created since a duration ago or a date (e.g. 7d, 2024-05-01)
+    #[arg(long, value_name = "WHEN")]
+    created_after: Option<String>,
+    /// Only patterns with an embedding (`--has-embedding false` for those without)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    has_embedding: Option<bool>,
+}
+
+impl FilterArgs {
+    fn into_filter(self) -> Result<storage::PatternFilter> {
+        Ok(storage::PatternFilter {
+            tool: self.tool,
+            category: self.category,
+            min_score: self.min_score,
+            tags: self.tags.iter().map(|t| storage::tags::normalize(t)).collect::<Result<_>>()?,
+            project: self.project.as_deref().map(storage::projects::resolve).transpose()?,
+            since: self.since.as_deref().map(storage::filter::parse_since).transpose()?,
+            created_after: self.created_after.as_deref().map(storage::filter::parse_since).transpose()?,
+            has_embedding: self.has_embedding,
+        })
+    }
+}
+
+/// Patterns shown before a bulk change
+const BULK_PREVIEW_ROWS: usize = 20;
+
+/// Pattern selection and confirmation for bulk delete, archive and tag
+#[derive(clap::Args, Debug, Clone)]
+struct BulkArgs {
+    /// Comma-separated pattern IDs
+    #[arg(long, value_delimiter = ',')]
+    ids: Vec<i64>,
+    #[command(flatten)]
+    filter: FilterArgs,
+    /// Don't ask to confirm the count (the preview is still printed)
+    #[arg(long, short = 'y', visible_alias = "yes")]
+    force: bool,
+}
+
+impl BulkArgs {
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.filter.clone().into_filter().is_ok_and(|f| f.is_empty())
+    }
+
+    /// Patterns picked by `pattern_id`, `--ids` and the filter flags
+    fn select(&self, conn: &rusqlite::Connection, pattern_id: Option<i64>) -> Result<Vec<storage::bulk::Target>> {
+        let mut ids = self.ids.clone();
+        ids.extend(pattern_id);
+        storage::bulk::select(conn, &ids, &self.filter.clone().into_filter()?)
+    }
+
+    /// Preview `targets` and have their count confirmed; false leaves them alone
+    ///
+    /// Without a terminal to ask on, only `--yes` confirms.
+    fn confirm(&self, verb: &str, targets: &[storage::bulk::Target], json: bool) -> Result<bool> {
+        if json {
+            if !self.force {
+                let ids: Vec<i64> = targets.iter().map(|t| t.id).collect();
+                print_json(&serde_json::json!({ "action": verb, "matched": ids, "confirmed": false }))?;
+            }
+            return Ok(self.force);
+        }
+
+        println!("About to {} {} pattern(s):", verb, targets.len());
+        let mut table = table::Table::new(&[">ID", "TOOL", ">SCORE", "STATUS", "~CONTEXT"]);
+        for t in targets.iter().take(BULK_PREVIEW_ROWS) {
+            table.row(vec![t.id.to_string(), t.tool_type.clone(), t.score.to_string(), t.status.clone(), t.context.clone()]);
+        }
+        print!("{}", table.render(table::width(), table::Fit::Truncate));
+        if targets.len() > BULK_PREVIEW_ROWS {
+            println!("... and {} more", targets.len() - BULK_PREVIEW_ROWS);
+        }
+        println!();
+        if self.force {
+            return Ok(true);
+        }
+        if ci::enabled() || !std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+            println!("Re-run with --yes to {} them.", verb);
+            return Ok(false);
+        }
+        print!("Type {} to confirm: ", targets.len());
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if answer.trim() != targets.len().to_string() {
+            println!("Cancelled.");
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Subcommand)]
+enum PatternsAction {
+    /// List all patterns with filtering options
+    List {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Maximum number of patterns to show (0 for all)
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+        /// Sort order
+        #[arg(long, value_enum, default_value_t = PatternSort::Score)]
+        sort: PatternSort,
+        #[command(flatten)]
+        layout: LayoutArgs,
+    },
+
+    /// Browse patterns interactively: search, filter, delete, archive, tag and share
+    Browse {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Team to share patterns with (`s` in the browser)
+        #[arg(long)]
+        team: Option<String>,
+    },
+
+    /// Show detailed information about a specific pattern
+    Show {
+        /// Pattern ID to show
+        pattern_id: i64,
+    },
+
+    /// Search patterns by content
+    Search {
+        /// Search query (semantic search if embeddings available)
+        query: String,
+        /// Number of results to show (0 for all)
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        #[command(flatten)]
+        layout: LayoutArgs,
+    },
+
+    /// Show pattern statistics summary
+    Summary,
+
+    /// Delete a pattern, or every pattern picked by --ids and filter flags
+    Delete {
+        /// Pattern ID to delete
+        pattern_id: Option<i64>,
+        #[command(flatten)]
+        targets: BulkArgs,
+    },
+
+    /// Archive patterns so they're kept but no longer injected
+    Archive {
+        /// Pattern ID to archive
+        pattern_id: Option<i64>,
+        #[command(flatten)]
+        targets: BulkArgs,
+    },
+
+    /// Delete every pattern learned in a project (see [learning] exclude_projects)
+    Purge {
+        /// Project directory or hash
+        #[arg(long)]
+        project: String,
+        /// Skip confirmation prompt
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List risky (destructive) patterns awaiting approval
+    Risky,
+
+    /// Approve a risky pattern so it can be injected
+    ApproveRisky {
+        /// Pattern ID to approve
+        pattern_id: i64,
+    },
+
+    /// List patterns quarantined after repeated harmful verdicts
+    Quarantined,
+
+    /// Return a quarantined pattern to injection
+    Reinstate {
+        /// Pattern ID to reinstate
+        pattern_id: i64,
+    },
+
+    /// Add or remove tags on a pattern, or on every pattern picked by --ids and filter flags
+    Tag {
+        /// Pattern ID then the tags to add (or remove with --remove); just the tags with --ids or filters
+        #[arg(required = true, value_name = "[ID] TAGS")]
+        args: Vec<String>,
+        /// Remove the tags instead of adding them
+        #[arg(long)]
+        remove: bool,
+        #[command(flatten)]
+        targets: BulkArgs,
+    },
+
+    /// List the starter packs that ship with MANA
+    Packs,
+
+    /// Install a starter pack, tagging its patterns pack:<name>
+    Install {
+        /// Bundled pack name (see 'mana patterns packs') or an export file
+        pack: String,
+    },
+
+    /// Remove every pattern a pack installed
+    Uninstall {
+        /// Pack name
+        pack: String,
+    },
+
+    /// Rank patterns by how effective their recent verdicts were
+    Top {
+        /// Number of patterns to show
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+        /// Recent window in days
+        #[arg(long, default_value = "14")]
+        days: u32,
+        /// Recent verdicts a pattern needs to be ranked
+        #[arg(long, default_value = "3")]
+        min_verdicts: usize,
+    },
+
+    /// Show previously good patterns whose recent verdicts turned negative
+    Regressions {
+        /// Recent window in days
+        #[arg(long, default_value = "14")]
+        days: u32,
+        /// Recent verdicts a pattern needs before it can be flagged
+        #[arg(long, default_value = "3")]
+        min_verdicts: usize,
+        /// Exit with status 1 when any regression is found (for CI alerts)
+        #[arg(long)]
+        exit_code: bool,
+    },
+
+    /// Show prior versions of a pattern
+    History {
+        /// Pattern ID
+        pattern_id: i64,
+        /// Number of versions to show
+        #[arg(short, long, default_value = "10")]
+        limit: usize,
+    },
+
+    /// Restore a pattern to a prior version
+    Rollback {
+        /// Pattern ID
+        pattern_id: i64,
+        /// Version to restore (see 'mana patterns history')
+        #[arg(long)]
+        to: i64,
+    },
+}
+
+/// Main entry point - uses sync main for inject command to avoid tokio overhead
+fn main() {
+    // OPTIMIZATION: Parse args without initializing tokio runtime
+    // The inject command needs <10ms latency, but tokio::main adds ~50ms overhead
+    let cli = Cli::parse();
+    if cli.ci {
+        std::env::set_var(ci::ENV, "1");
+    }
+
+    if let Err(e) = run(cli) {
+        let e = storage::read_only::explain(e);
+        eprintln!("Error: {:?}", e);
+        ci::exit(ci::exit_code(&e));
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    // Export the profile so hooks, spawned workers and every data-dir lookup see it
+    if let Some(name) = &cli.profile {
+        profile::validate_name(name)?;
+        std::env::set_var(profile::PROFILE_ENV, name);
+    }
+    // Exported for the same reason: a spawned worker must not write either
+    if cli.read_only {
+        std::env::set_var(storage::read_only::ENV, "true");
+    }
+
+    // For inject command, run without tokio for maximum speed
+    if let Commands::Inject { tool, explain, adapter } = &cli.command {
+        // Skip logging setup for inject - it adds overhead and we don't need it
+        // Just run the context injection synchronously; spans are recorded only with a trace file
+        let _trace = trace::path(cli.trace_file.clone()).map(|path| trace::init(&path)).transpose()?;
+        if *explain {
+            return hooks::explain_injection(tool, *adapter, cli.json);
+        }
+        return hooks::inject_context(tool, *adapter);
+    }
+
+    // For all other commands, use the async runtime
+    run_async_main(cli)
+}
+
+/// Async main for commands that need tokio runtime
+#[tokio::main(flavor = "current_thread")]
+async fn run_async_main(cli: Cli) -> Result<()> {
+    // Initialize logging - for most commands
+    let filter = if cli.verbose {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"))
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+    };
+
+    // The trace file gets every MANA span regardless of the log level
+    let (trace_layer, _trace) = match trace::path(cli.trace_file.clone()) {
+        Some(path) => {
+            let (layer, guard) = trace::layer(&path)?;
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let log_layer = tracing_subscriber::fmt::layer()
+        .with_target(false)
+        .with_ansi(!ci::enabled())
+        .with_writer(std::io::stderr)  // Always write logs to stderr, not stdout
+        .with_filter(filter);
+    tracing_subscriber::registry().with(trace_layer).with(log_layer).init();
+
+    let json = cli.json;
+
+    if let Some(command) = writing_command(&cli.command) {
+        storage::read_only::ensure_writable(&format!("'mana {}'", command))?;
+    }
+
+    match cli.command {
+        Commands::Inject { tool, adapter, .. } => {
+            // Should never reach here due to early return in main()
+            // But keep for completeness
+            hooks::inject_context(&tool, adapter)?;
+        }
+        Commands::SessionEnd => {
             info!("Processing session end");
             hooks::session_end().await?;
         }
-        Commands::Consolidate => {
+        Commands::Hook { event } => {
+            hooks::events::dispatch(event).await?;
+        }
+        Commands::Learn { file, since, dry_run, report } => {
+            let mana_dir = get_mana_dir()?;
+            if !mana_dir.join("metadata.sqlite").exists() {
+                return Err(ci::not_initialized());
+            }
+            let options = learning::LearnOptions {
+                files: file,
+                since: since.as_deref().map(reflection::parse_since).transpose()?.map(|d| chrono::Utc::now() - d),
+                dry_run,
+            };
+            // A dry run writes nothing, so it needn't wait for other learning
+            let _lock = if dry_run {
+                None
+            } else {
+                Some(
+                    learning::lock::acquire(&mana_dir, std::time::Duration::from_secs(10))?
+                        .ok_or_else(|| ci::fail(ci::ExitCode::Busy, "Learning is in progress; try again shortly"))?,
+                )
+            };
+
+            let result = learning::learn(&mana_dir, &options)?;
+            if json {
+                return print_json(&serde_json::json!({ "result": result, "files": result.files }));
+            }
+            if report {
+                print_parse_report(&result.files);
+            }
+            if dry_run {
+                println!(
+                    "Dry run: {} trajectories would create {} patterns and update {}",
+                    result.trajectories_processed, result.patterns_created, result.patterns_updated
+                );
+                const SHOWN: usize = 20;
+                for planned in result.planned.iter().take(SHOWN) {
+                    let (sign, id) = planned.id.map_or(("+", "new".to_string()), |id| ("~", format!("#{}", id)));
+                    let approach = planned.context_query.lines().find(|l| l.starts_with("Approach:")).unwrap_or(&planned.context_query);
+                    println!("  {} {:<6} {}", sign, id, approach.chars().take(80).collect::<String>());
+                }
+                if result.planned.len() > SHOWN {
+                    println!("  ... and {} more (--json lists all)", result.planned.len() - SHOWN);
+                }
+            } else {
+                println!(
+                    "Learned from {} trajectories: {} patterns created ({} malformed lines skipped)",
+                    result.trajectories_processed, result.patterns_created, result.lines_skipped
+                );
+            }
+            ci::summary("learn", &[
+                ("dry_run", &dry_run),
+                ("trajectories", &result.trajectories_processed),
+                ("created", &result.patterns_created),
+                ("updated", &result.patterns_updated),
+                ("lines_skipped", &result.lines_skipped),
+            ]);
+        }
+        Commands::Consolidate { only, skip } => {
             info!("Running consolidation");
-            learning::consolidate().await?;
+            let mana_dir = get_mana_dir()?;
+            let stages = learning::Stage::select(&only, &skip)?;
+            let caps = config::load_config(&mana_dir).retention;
+            let total = stages.len();
+            let mut done = 0;
+            let summary = learning::consolidate(&mana_dir, &stages, &mut |run, summary| {
+                done += 1;
+                if !json {
+                    print_consolidation_stage(done, total, run, summary, &caps);
+                }
+            })?;
+            if json {
+                return print_json(&summary);
+            }
+            let elapsed: u64 = summary.stages.iter().map(|s| s.elapsed_ms).sum();
+            println!("Consolidation complete: {} of {} stages in {}ms", total, learning::Stage::ALL.len(), elapsed);
+        }
+        Commands::Watch { reflect, debounce_ms } => {
+            let mana_dir = get_mana_dir()?;
+            let options = learning::WatchOptions {
+                debounce: std::time::Duration::from_millis(debounce_ms),
+                reflect,
+            };
+            learning::watch_logs(&mana_dir, options).await?;
+        }
+        Commands::ImportLogs { format, paths, dry_run } => {
+            let importer = learning::importer_for(&format)?;
+            let mana_dir = get_mana_dir()?;
+            if !mana_dir.join("metadata.sqlite").exists() {
+                return Err(ci::not_initialized());
+            }
+            let _lock = learning::lock::acquire(&mana_dir, std::time::Duration::from_secs(10))?
+                .ok_or_else(|| ci::fail(ci::ExitCode::Busy, "Learning is in progress; try again shortly"))?;
+
+            let summary = learning::import_logs(&mana_dir, importer.as_ref(), &paths, dry_run)?;
+            if json {
+                return print_json(&summary);
+            }
+            println!("Scanned {} {} file(s): {} session(s) with tool activity", summary.files, summary.format, summary.sessions);
+            if summary.already_imported > 0 {
+                println!("Skipped {} session(s) imported earlier", summary.already_imported);
+            }
+            match &summary.learning {
+                Some(result) => println!(
+                    "Learned from {} session(s), {} tool call(s): {} pattern(s) created",
+                    result.trajectories_processed, summary.tool_calls, result.patterns_created
+                ),
+                None if dry_run => println!("Dry run: {} tool call(s) would be learned from", summary.tool_calls),
+                None => println!("Nothing new to learn"),
+            }
+            let created = summary.learning.as_ref().map_or(0, |r| r.patterns_created);
+            ci::summary("import-logs", &[
+                ("files", &summary.files),
+                ("sessions", &summary.sessions),
+                ("skipped", &summary.already_imported),
+                ("tool_calls", &summary.tool_calls),
+                ("patterns_created", &created),
+                ("dry_run", &dry_run),
+            ]);
         }
         Commands::Status => {
-            storage::show_status().await?;
+            storage::show_status(json).await?;
         }
-        Commands::Stats => {
-            storage::show_stats().await?;
+        Commands::Stats { watch, interval } => {
+            if watch {
+                let mana_dir = get_mana_dir()?;
+                if !mana_dir.join("metadata.sqlite").exists() {
+                    return Err(ci::not_initialized());
+                }
+                ci::ensure_interactive("stats --watch")?;
+                dashboard::run(&mana_dir, std::time::Duration::from_secs(interval.max(1)))?;
+            } else {
+                storage::show_stats(json).await?;
+            }
         }
-        Commands::Init => {
+        Commands::Init { install_hooks, uninstall_hooks } => {
+            let settings_path = hooks::settings::claude_config_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not find the Claude Code config directory"))?
+                .join("settings.json");
+
+            if uninstall_hooks {
+                let change = hooks::settings::uninstall_hooks(&settings_path)?;
+                if change.changed {
+                    println!("Removed MANA hooks from {}", settings_path.display());
+                } else {
+                    println!("No MANA hooks found in {}", settings_path.display());
+                }
+                if let Some(backup) = change.backup {
+                    println!("Backup of the original: {}", backup.display());
+                }
+                return Ok(());
+            }
+
             info!("Initializing MANA");
             storage::init().await?;
+
+            if install_hooks {
+                let command = hooks::settings::mana_command()?;
+                let change = hooks::settings::install_hooks(&settings_path, &command)?;
+                if change.changed {
+                    println!("Installed MANA hooks in {}", settings_path.display());
+                    println!("  PreToolUse: {} inject --tool <edit|bash|task>", command);
+                    println!("  Stop, SessionEnd: {} session-end", command);
+                    println!("  PreCompact, SubagentStop, Notification: {} hook <event>", command);
+                } else {
+                    println!("MANA hooks already installed in {}", settings_path.display());
+                }
+                if let Some(backup) = change.backup {
+                    println!("Backup of the original: {}", backup.display());
+                }
+            }
         }
-        Commands::Update { force } => {
-            update::update_command(force).await?;
+        Commands::Update { force, channel, rollback, assets } => {
+            if rollback {
+                update::rollback_command()?;
+            } else if assets {
+                let mana_dir = get_mana_dir()?;
+                let settings = config::load_config(&mana_dir).update;
+                update::assets_command(&mana_dir, channel.as_deref(), &settings)?;
+            } else {
+                let settings = config::load_config(&get_mana_dir()?).update;
+                update::update_command(force, channel.as_deref(), &settings).await?;
+            }
         }
         Commands::Debug { limit } => {
             storage::debug_patterns(limit).await?;
@@ -463,17 +1560,59 @@ async fn run_async_main(cli: Cli) -> Result<()> {
         Commands::Prune { min_score, dry_run } => {
             storage::prune_patterns(min_score, dry_run).await?;
         }
-        Commands::Relearn => {
-            storage::relearn().await?;
+        Commands::Relearn { project, tool, since, stage, apply } => {
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            if !db_path.exists() {
+                return Err(ci::not_initialized());
+            }
+            storage::ensure_schema(&db_path)?;
+            let report = if apply {
+                learning::relearn::apply(&mana_dir)?
+            } else {
+                let scope = learning::relearn::Scope {
+                    project: project.as_deref().map(storage::projects::resolve).transpose()?,
+                    tool,
+                    since: since.as_deref().map(reflection::parse_since).transpose()?.map(|d| chrono::Utc::now() - d),
+                };
+                if stage {
+                    learning::relearn::stage(&mana_dir, &scope)?
+                } else {
+                    learning::relearn::relearn(&mana_dir, &scope)?
+                }
+            };
+            if json {
+                return print_json(&report);
+            }
+            print_relearn_report(&report);
         }
-        Commands::Bench => {
-            bench::run_benchmarks().await?;
+        Commands::Bench { insert, compare, full, markdown } => {
+            if insert {
+                bench::run_insert_benchmarks()?;
+            } else {
+                let report = bench::run_benchmarks(compare.as_deref(), full).await?;
+                if markdown {
+                    println!();
+                    println!("{}", report.to_markdown());
+                }
+                if !report.all_pass() {
+                    anyhow::bail!("Critical benchmarks missed their targets");
+                }
+            }
         }
         Commands::Embed { action } => {
             let mana_dir = get_mana_dir()?;
 
             match action {
                 EmbedAction::Status => {
+                    if json {
+                        if embeddings::is_available(&mana_dir) {
+                            print_json(&embeddings::status(&mana_dir)?)?;
+                        } else {
+                            print_json(&serde_json::json!({ "initialized": false }))?;
+                        }
+                        return Ok(());
+                    }
                     if embeddings::is_available(&mana_dir) {
                         let status = embeddings::status(&mana_dir)?;
                         println!("Embedding Status");
@@ -512,7 +1651,7 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                         println!("All patterns already have embeddings.");
                     }
                 }
-                EmbedAction::Search { query, limit } => {
+                EmbedAction::Search { query, limit, tool, category, min_score } => {
                     // Use open to load existing embeddings
                     let store = embeddings::EmbeddingStore::open(&mana_dir)?;
 
@@ -526,20 +1665,66 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                     println!("Searching for: \"{}\"", query);
                     println!();
 
-                    let results = store.search_with_context(&query, limit)?;
+                    let filter = embeddings::SearchFilter { tool, category, min_score };
+                    let results = store.search_with_context(&query, limit, &filter)?;
 
                     if results.is_empty() {
                         println!("No matching patterns found.");
                     } else {
                         for (i, m) in results.iter().enumerate() {
                             let success_rate = m.success_rate() * 100.0;
-                            println!("{}. [{}] (sim: {:.3}, success: {:.0}%)",
-                                i + 1, m.tool_type, m.similarity, success_rate);
+                            println!("{}. [{}] (sim: {:.3}, score: {}, success: {:.0}%)",
+                                i + 1, m.tool_type, m.similarity, m.success_count - m.failure_count, success_rate);
                             println!("   {}", m.context_query);
                             println!();
                         }
                     }
                 }
+                EmbedAction::Tune { target_recall, queries, apply } => {
+                    let store = embeddings::EmbeddingStore::open(&mana_dir)?;
+                    if store.index().is_empty() {
+                        println!("No embeddings found. Run 'mana embed generate' first.");
+                        return Ok(());
+                    }
+
+                    println!("Tuning HNSW on {} vectors...", store.index().len());
+                    println!();
+                    let report = embeddings::tune::run_tune(store.index(), queries);
+
+                    println!("{:>4} {:>8} {:>6} {:>8} {:>12} {:>10}",
+                        "m", "ef_cons", "ef", "recall", "latency(us)", "build(ms)");
+                    for r in &report.results {
+                        println!("{:>4} {:>8} {:>6} {:>8.3} {:>12.1} {:>10.1}",
+                            r.params.m, r.params.ef_construction, r.params.ef_search,
+                            r.recall, r.latency_us, r.build_ms);
+                    }
+                    println!();
+                    println!("Exact search: {:.1}us/query ({} queries, {} vectors)",
+                        report.exact_latency_us, report.query_count, report.vector_count);
+
+                    let Some(best) = report.recommend(target_recall) else {
+                        return Ok(());
+                    };
+                    if best.recall < target_recall {
+                        println!("No configuration reached recall {:.2}; showing the most accurate", target_recall);
+                    }
+                    println!();
+                    println!("Recommended [embeddings]:");
+                    println!("  m = {}", best.params.m);
+                    println!("  ef_construction = {}", best.params.ef_construction);
+                    println!("  ef_search = {}", best.params.ef_search);
+
+                    if apply {
+                        config::set_value(&mana_dir, "embeddings", "m", &best.params.m.to_string())?;
+                        config::set_value(&mana_dir, "embeddings", "ef_construction", &best.params.ef_construction.to_string())?;
+                        config::set_value(&mana_dir, "embeddings", "ef_search", &best.params.ef_search.to_string())?;
+                        println!();
+                        println!("Wrote recommended values to {}", mana_dir.join("config.toml").display());
+                    } else {
+                        println!();
+                        println!("Run with --apply to write these to config.toml");
+                    }
+                }
             }
         }
         Commands::Reflect { action } => {
@@ -549,6 +1734,9 @@ async fn run_async_main(cli: Cli) -> Result<()> {
             match action {
                 ReflectAction::Status => {
                     let status = reflection::get_reflection_status(&db_path)?;
+                    if json {
+                        return print_json(&status);
+                    }
 
                     println!("Reflection Status");
                     println!("=================");
@@ -573,100 +1761,63 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                     println!();
                     println!("Reflection cycles: {}", status.total_cycles);
 
-                    if status.last_trigger.is_some() {
+                    if let Some(ref trigger) = status.last_trigger {
                         println!();
                         println!("Last cycle:");
-                        println!("  Trigger: {}", status.last_trigger.as_ref().unwrap());
+                        println!("  Trigger: {}", trigger);
                         println!("  Trajectories: {}", status.last_trajectories);
                         println!("  Verdicts: {}", status.last_verdicts);
                         println!("  Duration: {}ms", status.last_duration_ms);
                     }
                 }
-                ReflectAction::Run { trigger } => {
-                    use std::time::Instant;
+                ReflectAction::Run { trigger, since, all } => {
+                    let mode = match (since, all) {
+                        (Some(since), _) => reflection::ScanMode::Since(reflection::parse_since(&since)?),
+                        (None, true) => reflection::ScanMode::All,
+                        (None, false) => reflection::ScanMode::Incremental,
+                    };
 
                     println!("Running reflection cycle ({})...", trigger);
 
-                    // Initialize tables if needed
-                    let conn = rusqlite::Connection::open(&db_path)?;
-                    reflection::init_reflection_tables(&conn)?;
+                    let summary = reflection::run_cycle(&mana_dir, &trigger, mode)?;
 
-                    // Parse recent trajectories from all JSONL files
-                    let start = Instant::now();
-                    let log_dir = dirs::home_dir()
-                        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-                        .join(".claude")
-                        .join("projects");
-
-                    let mut all_trajectories = Vec::new();
-                    if log_dir.exists() {
-                        // Collect all JSONL files in subdirectories (same approach as foreground learning)
-                        for entry in std::fs::read_dir(&log_dir)? {
-                            let entry = entry?;
-                            let path = entry.path();
-                            if path.is_dir() {
-                                // Scan all JSONL files in project directory
-                                if let Ok(subentries) = std::fs::read_dir(&path) {
-                                    for subentry in subentries.flatten() {
-                                        let subpath = subentry.path();
-                                        if subpath.extension().map(|e| e == "jsonl").unwrap_or(false) {
-                                            if let Ok(trajectories) = learning::trajectory::parse_trajectories(&subpath, 0) {
-                                                all_trajectories.extend(trajectories);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                    if summary.already_judged > 0 {
+                        println!("Skipping {} trajectories that already have verdicts", summary.already_judged);
                     }
-
-                    if all_trajectories.is_empty() {
-                        println!("No trajectories found for reflection.");
+                    if summary.trajectories == 0 {
+                        println!("No new trajectories found for reflection.");
                         return Ok(());
                     }
+                    if let Some(judge) = summary.judge {
+                        println!("Used {:?} judge (heuristics as fallback)", judge);
+                    }
 
-                    println!("Found {} trajectories to analyze", all_trajectories.len());
-
-                    // Run reflection with database path for pattern linking
-                    let config = reflection::ReflectionConfig::default();
-                    let engine = reflection::ReflectionEngine::with_db_path(config, &db_path);
-
-                    let verdicts = engine.reflect(&all_trajectories)?;
-                    let updated = engine.apply_verdicts(&conn, &verdicts)?;
-
-                    let duration = start.elapsed();
-
-                    // Log the cycle
-                    reflection::log_reflection_cycle(
-                        &conn,
-                        &trigger,
-                        all_trajectories.len(),
-                        verdicts.len(),
-                        updated,
-                        0, // new patterns
-                        0, // demoted
-                        duration.as_millis() as u64,
-                    )?;
-
-                    println!();
-                    println!("Reflection complete:");
-                    println!("  Trajectories analyzed: {}", all_trajectories.len());
-                    println!("  Verdicts produced: {}", verdicts.len());
-                    println!("  Patterns updated: {}", updated);
-                    println!("  Duration: {:?}", duration);
-                }
-                ReflectAction::Verdicts { limit } => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    println!();
+                    println!("Reflection complete:");
+                    println!("  Trajectories analyzed: {}", summary.trajectories);
+                    println!("  Verdicts produced: {}", summary.verdicts);
+                    println!("  Patterns updated: {}", summary.patterns_updated);
+                    if summary.patterns_quarantined > 0 {
+                        println!(
+                            "  Patterns quarantined: {} (see 'mana patterns quarantined')",
+                            summary.patterns_quarantined
+                        );
+                    }
+                    println!("  Duration: {:?}", summary.duration);
+                }
+                ReflectAction::Verdicts { limit, layout } => {
+                    let conn = storage::db::open(&db_path)?;
 
                     let mut stmt = conn.prepare(
-                        "SELECT trajectory_hash, pattern_id, verdict, confidence, root_cause, created_at
+                        "SELECT id, trajectory_hash, pattern_id, verdict, confidence, root_cause,
+                                suggested_improvement, created_at
                          FROM reflection_verdicts
                          ORDER BY created_at DESC
                          LIMIT ?1"
                     )?;
 
                     let verdicts: Vec<VerdictRow> = stmt
-                        .query_map([limit as i64], |row| {
+                        .query_map([if limit == 0 { -1 } else { limit as i64 }], |row| {
                             Ok((
                                 row.get(0)?,
                                 row.get(1)?,
@@ -674,11 +1825,32 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                                 row.get(3)?,
                                 row.get(4)?,
                                 row.get(5)?,
+                                row.get(6)?,
+                                row.get(7)?,
                             ))
                         })?
                         .filter_map(|r| r.ok())
                         .collect();
 
+                    if json {
+                        let rows: Vec<_> = verdicts
+                            .iter()
+                            .map(|(id, hash, pattern_id, verdict, confidence, root_cause, suggestion, created_at)| {
+                                serde_json::json!({
+                                    "id": id,
+                                    "trajectory_hash": hash,
+                                    "pattern_id": pattern_id,
+                                    "verdict": verdict,
+                                    "confidence": confidence,
+                                    "root_cause": root_cause,
+                                    "suggested_improvement": suggestion,
+                                    "created_at": created_at,
+                                })
+                            })
+                            .collect();
+                        return print_json(&rows);
+                    }
+
                     if verdicts.is_empty() {
                         println!("No verdicts found.");
                         println!();
@@ -687,32 +1859,26 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                     }
 
                     println!("Recent Verdicts");
-                    println!("===============");
                     println!();
-
-                    for (hash, pattern_id, verdict, confidence, root_cause, created_at) in verdicts {
-                        let emoji = match verdict.as_str() {
-                            "EFFECTIVE" => "",
-                            "HARMFUL" => "",
-                            "INEFFECTIVE" => "",
-                            _ => "",
-                        };
-                        let pattern_str = pattern_id
-                            .map(|id| format!("pattern #{}", id))
-                            .unwrap_or_else(|| "no pattern".into());
-
-                        println!("{} {} ({:.0}% confidence)", emoji, verdict, confidence * 100.0);
-                        println!("   Trajectory: {}...", &hash[..8]);
-                        println!("   {}", pattern_str);
-                        if let Some(cause) = root_cause {
-                            println!("   Root cause: {}", cause);
-                        }
-                        println!("   {}", created_at);
-                        println!();
+                    let mut table = table::Table::new(&[
+                        ">ID", "VERDICT", ">CONF", "PATTERN", "TRAJECTORY", "CREATED", "~ROOT CAUSE", "~SUGGESTED ADVICE",
+                    ]);
+                    for (id, hash, pattern_id, verdict, confidence, root_cause, suggestion, created_at) in verdicts {
+                        table.row(vec![
+                            format!("#{}", id),
+                            verdict,
+                            format!("{:.0}%", confidence * 100.0),
+                            pattern_id.map(|id| format!("#{}", id)).unwrap_or_else(|| "-".into()),
+                            hash.chars().take(8).collect(),
+                            created_at,
+                            root_cause.unwrap_or_default(),
+                            suggestion.unwrap_or_default(),
+                        ]);
                     }
+                    layout.print(&table)?;
                 }
                 ReflectAction::Analyze { pattern_id } => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
 
                     // Get pattern info
                     let pattern: Option<(String, String, i64, i64)> = conn.query_row(
@@ -770,16 +1936,160 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                         }
                     }
                 }
+                ReflectAction::Apply { verdict_id } => {
+                    let mut conn = storage::db::open(&db_path)?;
+                    reflection::init_reflection_tables(&conn)?;
+                    let applied = reflection::apply_improvement(&mut conn, verdict_id)?;
+
+                    println!("Applied verdict #{} to pattern #{}", verdict_id, applied.pattern_id);
+                    println!();
+                    println!("Before:");
+                    for line in applied.old_content.lines() {
+                        println!("  {}", line);
+                    }
+                    println!("After:");
+                    for line in applied.new_content.lines() {
+                        println!("  {}", line);
+                    }
+
+                    if embeddings::is_available(&mana_dir) {
+                        embeddings::reembed_pattern(&mana_dir, applied.pattern_id, &applied.new_content)?;
+                        println!();
+                        println!("Re-embedded pattern #{}", applied.pattern_id);
+                    }
+                }
+                ReflectAction::Causes { limit, days, markdown } => {
+                    let conn = storage::db::open(&db_path)?;
+                    reflection::init_reflection_tables(&conn)?;
+                    let clusters = reflection::cluster_root_causes(&conn, days)?;
+
+                    if markdown {
+                        print!("{}", reflection::render_causes_markdown(&clusters, limit));
+                    } else if clusters.is_empty() {
+                        println!("No root causes recorded yet.");
+                    } else {
+                        println!("Recurring Failure Modes");
+                        println!("=======================");
+                        for (i, cluster) in clusters.iter().take(limit).enumerate() {
+                            println!();
+                            println!("{:>2}. {} ({}x, {} harmful)", i + 1, cluster.label, cluster.count, cluster.harmful);
+                            if !cluster.pattern_ids.is_empty() {
+                                let ids: Vec<String> = cluster.pattern_ids.iter().map(|id| format!("#{}", id)).collect();
+                                println!("    Patterns: {}", ids.join(", "));
+                            }
+                            for example in &cluster.examples {
+                                println!("    ~ {}", example);
+                            }
+                            println!("    Last seen: {}", cluster.last_seen);
+                        }
+                        if clusters.len() > limit {
+                            println!();
+                            println!("({} more, use --limit to show)", clusters.len() - limit);
+                        }
+                    }
+                }
+                ReflectAction::Experiment { since } => {
+                    let since = storage::filter::parse_since(&since)?;
+                    {
+                        let mut conn = storage::db::open(&db_path)?;
+                        storage::create_schema(&conn)?;
+                        storage::injection_log::drain_spool(&mana_dir, &mut conn)?;
+                    }
+                    let report = reflection::experiment_report(&mana_dir, &db_path, since)?;
+                    if json {
+                        return print_json(&report);
+                    }
+                    print_experiment(&report, config::load_config(&mana_dir).injection.control_fraction);
+                }
                 ReflectAction::Init => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
                     reflection::init_reflection_tables(&conn)?;
                     println!("Reflection tables initialized.");
                 }
             }
         }
-        Commands::Export { output, encrypted, passphrase, no_sanitize } => {
+        Commands::Export { format, output, with_vectors, with_verdicts, split, limit, encrypted, passphrase, encrypt_to, sign, sign_key, no_sanitize, filter } => {
             let mana_dir = get_mana_dir()?;
             let db_path = mana_dir.join("metadata.sqlite");
+            let filter = filter.into_filter()?;
+            for recipient in &encrypt_to {
+                sync::age::validate_recipient(recipient)?;
+            }
+            let sign_key = if sign {
+                let signing = sync::load_sync_config(&mana_dir.join("sync.toml"))?.signing;
+                Some(signing.signing_key(sign_key.as_deref()))
+            } else {
+                None
+            };
+            storage::ensure_schema(&db_path)?;
+
+            if format == "sqlite" {
+                if encrypted || split {
+                    anyhow::bail!("--encrypted and --split are not supported for SQLite snapshots");
+                }
+                let output = output.unwrap_or_else(|| "mana-snapshot.sqlite".to_string());
+                let security = sync::SecurityConfig {
+                    sanitize_paths: !no_sanitize,
+                    redact_secrets: !no_sanitize,
+                    encrypt: false,
+                    visibility: sync::Visibility::Private,
+                };
+                let manifest = sync::export_snapshot(&mana_dir, std::path::Path::new(&output), &security, &filter, with_vectors)?;
+                println!("✅ Exported snapshot to {}", output);
+                println!("   Patterns: {}", manifest.pattern_count);
+                println!("   Causal edges: {}", manifest.edge_count);
+                if with_vectors {
+                    println!("   Vectors: {}", manifest.vector_count);
+                }
+                println!("🔏 Manifest hash (patterns): {}", &manifest.hashes.patterns[..16]);
+                if !encrypt_to.is_empty() {
+                    sync::age::encrypt_file(std::path::Path::new(&output), &encrypt_to)?;
+                    println!("📦 Snapshot is encrypted to {} age recipient(s)", encrypt_to.len());
+                }
+                if let Some(key) = &sign_key {
+                    let sig = sync::signing::sign_file(std::path::Path::new(&output), key)?;
+                    println!("🔏 Signed: {}", sig.display());
+                }
+                if !no_sanitize {
+                    println!("🔒 Paths sanitized, secrets redacted");
+                }
+                return Ok(());
+            }
+            if with_vectors {
+                anyhow::bail!("--with-vectors is only supported for SQLite snapshots");
+            }
+            if with_verdicts && format != "json" {
+                anyhow::bail!("--with-verdicts is only supported for JSON exports");
+            }
+
+            if format == "markdown" {
+                if encrypted || !encrypt_to.is_empty() || sign {
+                    anyhow::bail!("--encrypted, --encrypt-to and --sign are not supported for Markdown exports");
+                }
+                let output = output.unwrap_or_else(|| {
+                    if split { "mana-patterns".to_string() } else { "mana-patterns.md".to_string() }
+                });
+                let security = sync::SecurityConfig {
+                    sanitize_paths: !no_sanitize,
+                    redact_secrets: !no_sanitize,
+                    encrypt: false,
+                    visibility: sync::Visibility::Private,
+                };
+                let options = sync::MarkdownOptions { split, per_group: limit, ..Default::default() };
+                let result = sync::export_markdown(&db_path, std::path::Path::new(&output), &security, &filter, &options)?;
+                println!("✅ Exported {} patterns in {} groups to {}", result.patterns, result.groups, output);
+                if split {
+                    println!("📄 {} files written (index: {})", result.files.len(), result.files[0].display());
+                }
+                if !no_sanitize {
+                    println!("🔒 Paths sanitized, secrets redacted");
+                }
+                return Ok(());
+            }
+            if split {
+                anyhow::bail!("--split is only supported for Markdown exports");
+            }
+            let output = output.unwrap_or_else(|| "mana-patterns.json".to_string());
 
             // Get passphrase from arg or env
             let passphrase = passphrase.or_else(|| std::env::var("MANA_SYNC_KEY").ok());
@@ -797,29 +2107,256 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                 None
             };
 
-            let count = sync::export_patterns(&db_path, std::path::Path::new(&output), &security, pass_ref)?;
+            let count = sync::export_patterns(&db_path, std::path::Path::new(&output), &security, pass_ref, &filter, with_verdicts)?;
             println!("✅ Exported {} patterns to {}", count, output);
             if encrypted {
                 println!("📦 Export is encrypted with AES-256-GCM");
             }
+            if !encrypt_to.is_empty() {
+                sync::age::encrypt_file(std::path::Path::new(&output), &encrypt_to)?;
+                println!("📦 Export is encrypted to {} age recipient(s)", encrypt_to.len());
+            }
+            if let Some(key) = &sign_key {
+                let sig = sync::signing::sign_file(std::path::Path::new(&output), key)?;
+                println!("🔏 Signed: {}", sig.display());
+            }
             if !no_sanitize {
                 println!("🔒 Paths sanitized, secrets redacted");
             }
+            ci::summary("export", &[("patterns", &count), ("output", &output), ("signed", &sign_key.is_some())]);
+        }
+        Commands::Annotate { input, output } => {
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            let output = output.unwrap_or_else(|| input.with_extension("annotated.jsonl"));
+
+            // Make sure injections still sitting in the hook spool are included
+            {
+                let mut conn = storage::db::open(&db_path)?;
+                storage::create_schema(&conn)?;
+                reflection::init_reflection_tables(&conn)?;
+                storage::injection_log::drain_spool(&mana_dir, &mut conn)?;
+            }
+
+            let summary = reflection::annotate_session(&input, &output, &db_path)?;
+            println!("Annotated {} ({} lines, {} sessions)", output.display(), summary.lines, summary.sessions);
+            println!("  Tool calls: {}", summary.tool_calls);
+            println!("  Injections matched: {}", summary.injections_matched);
+            if summary.injections_unmatched > 0 {
+                println!("  Injections without a matching tool call: {}", summary.injections_unmatched);
+            }
+            println!("  Reflection verdicts: {}", summary.verdicts);
+        }
+        Commands::Report { format, out, days } => {
+            let format = report::ReportFormat::parse(&format)?;
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            if !db_path.exists() {
+                return Err(ci::not_initialized());
+            }
+            // The report reads the database read-only, so upgrade older schemas first
+            storage::ensure_schema(&db_path)?;
+
+            let report = report::collect(&mana_dir, days.max(1))?;
+            let page = report::write(&report, format, &out)?;
+            println!("✅ Wrote {} report to {}", if format == report::ReportFormat::Html { "HTML" } else { "Markdown" }, page.display());
+            println!("   {} patterns, {} regressions over the last {} days", report.stats.patterns.total, report.regressions.len(), report.days);
+            ci::summary("report", &[
+                ("page", &page.display()),
+                ("patterns", &report.stats.patterns.total),
+                ("regressions", &report.regressions.len()),
+            ]);
+        }
+        Commands::Simulate { since, limit } => {
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            if !db_path.exists() {
+                return Err(ci::not_initialized());
+            }
+            storage::ensure_schema(&db_path)?;
+
+            let conn = storage::db::open_readonly(&db_path)?;
+            let since = storage::filter::parse_since(&since)?;
+            let report = simulate::run(&mana_dir, &conn, &config::load_config(&mana_dir).injection, since)?;
+            if json {
+                return print_json(&report);
+            }
+
+            let heading = format!("Simulation of sessions since {}", since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"));
+            println!("{}", heading);
+            println!("{}", "=".repeat(heading.chars().count()));
+            println!();
+            if report.calls == 0 {
+                println!("No hooked tool calls in {} session(s); nothing to replay.", report.sessions);
+                return Ok(());
+            }
+            let share = |n: usize| n as f64 / report.calls as f64 * 100.0;
+            println!("Sessions:        {}", report.sessions);
+            println!("Hooked calls:    {}", report.calls);
+            println!("Matched:         {} ({:.0}%)", report.matched_calls, share(report.matched_calls));
+            println!("Fallback only:   {} ({:.0}%)", report.fallback_calls, share(report.fallback_calls));
+            let unmatched = report.calls - report.matched_calls - report.fallback_calls;
+            println!("Nothing:         {} ({:.0}%)", unmatched, share(unmatched));
+
+            if !report.patterns.is_empty() {
+                println!();
+                println!("Would inject:");
+                let mut table = table::Table::new(&[">ID", "TOOL", ">CALLS", ">SESSIONS", ">RELEVANCE", "~CONTEXT"]);
+                for p in report.patterns.iter().take(limit) {
+                    table.row(vec![
+                        p.id.to_string(),
+                        p.tool_type.clone(),
+                        p.injections.to_string(),
+                        p.sessions.to_string(),
+                        format!("{:.2}", p.mean_similarity),
+                        p.context.clone(),
+                    ]);
+                }
+                print!("{}", table.render(table::width(), table::Fit::Truncate));
+            }
+
+            if !report.gap_categories.is_empty() {
+                println!();
+                println!("Unmatched calls by tool and category:");
+                for gap in report.gap_categories.iter().take(limit) {
+                    println!("  {:>5}  {} {}", gap.calls, gap.tool, gap.category.as_deref().unwrap_or("(none)"));
+                }
+            }
+            if !report.gaps.is_empty() {
+                println!();
+                println!("Sessions with no matching pattern ({}):", report.gaps.len());
+                for gap in report.gaps.iter().take(limit) {
+                    let when = gap.started_at.map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                    println!("  {}  {:>3} calls  {}", when.unwrap_or_else(|| "-".repeat(16)), gap.calls, gap.query);
+                }
+            }
+
+            let c = &report.changes;
+            if c.logged_sessions > 0 {
+                println!();
+                println!(
+                    "Against the injection log: {}/{} sessions unchanged, {} pattern(s) added, {} dropped",
+                    c.unchanged_sessions, c.logged_sessions, c.added, c.dropped
+                );
+            }
+        }
+        Commands::Suggest { context, limit } => {
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            if !db_path.exists() {
+                return Err(ci::not_initialized());
+            }
+            storage::ensure_schema(&db_path)?;
+
+            let conn = storage::db::open_readonly(&db_path)?;
+            let embedding_store = if embeddings::is_available(&mana_dir) {
+                embeddings::EmbeddingStore::open(&mana_dir).ok()
+            } else {
+                None
+            };
+            let causal = storage::CausalStore::open_readonly(&db_path)?;
+            let plan = suggest::plan(&conn, embedding_store.as_ref(), Some(&causal), &context, limit)?;
+
+            if json {
+                return print_json(&plan);
+            }
+
+            println!("Plan for: \"{}\"", context);
+            println!("{}", "=".repeat(50));
+            println!();
+            if plan.steps.is_empty() {
+                println!("No matching patterns found.");
+            }
+            for (i, step) in plan.steps.iter().enumerate() {
+                let via = step.via.map(|id| format!(", goes with #{}", id)).unwrap_or_default();
+                println!(
+                    "{}. #{} [{}] relevance:{:.2} success:{:.0}%{}",
+                    i + 1,
+                    step.id,
+                    step.tool_type,
+                    step.relevance,
+                    step.success_rate * 100.0,
+                    via
+                );
+                println!("   {}", step.context.lines().next().unwrap_or_default().chars().take(80).collect::<String>());
+                for skill in &step.skills {
+                    println!("   skill: {} (#{}, {:.0}% success)", skill.name, skill.id, skill.success_rate * 100.0);
+                }
+                println!();
+            }
+            if plan.mode == "text" {
+                println!("(Text matching; run 'mana embed generate' for semantic search)");
+            }
+        }
+        Commands::Sessions { limit, since } => {
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            if !db_path.exists() {
+                return Err(ci::not_initialized());
+            }
+            let since = since.as_deref().map(storage::filter::parse_since).transpose()?;
+
+            // Include injections still sitting in the hook spool
+            {
+                let mut conn = storage::db::open(&db_path)?;
+                storage::create_schema(&conn)?;
+                reflection::init_reflection_tables(&conn)?;
+                storage::injection_log::drain_spool(&mana_dir, &mut conn)?;
+            }
+
+            let sessions = reflection::recent_sessions(&mana_dir, &db_path, since, limit)?;
+            if json {
+                return print_json(&sessions);
+            }
+            print_sessions(&sessions);
         }
-        Commands::Import { input, passphrase, merge } => {
+        Commands::Import { input, passphrase, merge, interactive, require_signed } => {
             let mana_dir = get_mana_dir()?;
             let db_path = mana_dir.join("metadata.sqlite");
 
             // Get passphrase from arg or env
             let passphrase = passphrase.or_else(|| std::env::var("MANA_SYNC_KEY").ok());
 
-            let merge_strategy = match merge.as_str() {
-                "replace" => sync::export::MergeStrategy::Replace,
-                "keep-best" => sync::export::MergeStrategy::KeepBest,
-                _ => sync::export::MergeStrategy::Add,
-            };
+            let merge_strategy = if interactive { sync::export::MergeStrategy::Interactive } else { merge };
+
+            // The signature covers the file as shipped, so check it before decrypting
+            let signing = sync::load_sync_config(&mana_dir.join("sync.toml"))?.signing;
+            if let sync::signing::SignatureStatus::Verified { signer } =
+                sync::signing::check(std::path::Path::new(&input), &signing, require_signed)?
+            {
+                println!("🔏 Signature verified ({})", signer);
+            }
 
-            let result = sync::import_patterns(&db_path, std::path::Path::new(&input), passphrase.as_deref(), merge_strategy)?;
+            // age-encrypted files are decrypted with the local identities first
+            let decrypted = sync::age::decrypt_if_encrypted(&mana_dir, std::path::Path::new(&input))?;
+            let input_path = decrypted.as_ref().map_or(std::path::Path::new(&input), |d| d.path());
+            if sync::is_snapshot(input_path) {
+                if interactive {
+                    anyhow::bail!("--interactive is not supported for snapshots; use --merge keep-best");
+                }
+                let result = sync::import_snapshot(&mana_dir, input_path, merge_strategy)?;
+                println!("✅ Snapshot verified and imported from {}", result.manifest.source_workspace);
+                println!("   Total patterns: {}", result.patterns.total);
+                println!("   New patterns: {}", result.patterns.imported);
+                println!("   Merged: {}", result.patterns.merged);
+                if result.patterns.skipped > 0 {
+                    println!("   Skipped: {}", result.patterns.skipped);
+                }
+                println!("   Causal edges: {} new, {} merged", result.edges_imported, result.edges_merged);
+                if result.manifest.vector_count > 0 {
+                    println!("   Vectors: {} imported, {} skipped", result.vectors_imported, result.vectors_skipped);
+                }
+                ci::summary("import", &[
+                    ("total", &result.patterns.total),
+                    ("imported", &result.patterns.imported),
+                    ("merged", &result.patterns.merged),
+                    ("skipped", &result.patterns.skipped),
+                    ("edges", &result.edges_imported),
+                ]);
+                return Ok(());
+            }
+
+            let result = sync::import_patterns(&db_path, input_path, passphrase.as_deref(), merge_strategy)?;
 
             println!("✅ Import complete from {}", result.source_workspace);
             println!("   Total patterns: {}", result.total);
@@ -828,15 +2365,81 @@ async fn run_async_main(cli: Cli) -> Result<()> {
             if result.skipped > 0 {
                 println!("   Skipped: {}", result.skipped);
             }
+            if result.verdicts > 0 {
+                println!("   Verdict stats: {} patterns", result.verdicts);
+                // Shared verdicts feed into ranking
+                storage::snapshot::rebuild(&mana_dir, &storage::db::open(&db_path)?)?;
+            }
+            ci::summary("import", &[
+                ("total", &result.total),
+                ("imported", &result.imported),
+                ("merged", &result.merged),
+                ("skipped", &result.skipped),
+                ("verdicts", &result.verdicts),
+            ]);
+        }
+        Commands::Keys { action } => {
+            let mana_dir = get_mana_dir()?;
+
+            match action {
+                KeysAction::Generate => {
+                    let key = sync::age::generate(&mana_dir)?;
+                    println!("✅ Generated age identity at {}", key.path.display());
+                    println!("   Public key: {}", key.public_key);
+                    println!();
+                    println!("   Share the public key; teammates export with:");
+                    println!("   mana export --encrypt-to {}", key.public_key);
+                }
+                KeysAction::Show => match sync::age::current(&mana_dir)? {
+                    Some(key) => println!("{}", key.public_key),
+                    None => anyhow::bail!("No age identity yet. Run 'mana keys generate'"),
+                },
+                KeysAction::Rotate => {
+                    let (key, retired) = sync::age::rotate(&mana_dir)?;
+                    println!("✅ Rotated age identity");
+                    println!("   New public key: {}", key.public_key);
+                    println!("   Old identity kept at {} to decrypt older exports", retired.display());
+                }
+                KeysAction::List { json } => {
+                    let keys = sync::age::list(&mana_dir)?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&keys)?);
+                    } else if keys.is_empty() {
+                        println!("No age identities. Run 'mana keys generate' to create one.");
+                    } else {
+                        for key in keys {
+                            let status = if key.current { "current" } else { "retired" };
+                            let created = key.created.as_deref().unwrap_or("unknown");
+                            println!("{} ({}, created {})", key.public_key, status, created);
+                        }
+                    }
+                }
+            }
         }
         Commands::Sync { action } => {
             let mana_dir = get_mana_dir()?;
             let db_path = mana_dir.join("metadata.sqlite");
 
             match action {
-                SyncAction::Init { backend, remote, branch, bucket, prefix, region, url, discover, port, peers } => {
-                    match backend.to_lowercase().as_str() {
-                        "s3" => {
+                SyncAction::Init {
+                    backend,
+                    remote,
+                    branch,
+                    bucket,
+                    prefix,
+                    region,
+                    endpoint_url,
+                    path_style,
+                    ca_bundle,
+                    allow_http,
+                    url,
+                    host,
+                    discover,
+                    port,
+                    peers,
+                } => {
+                    match backend {
+                        sync::BackendKind::S3 => {
                             if bucket.is_empty() {
                                 return Err(anyhow::anyhow!("S3 bucket is required. Use --bucket <name>"));
                             }
@@ -845,10 +2448,12 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                                     "S3 sync not available. Rebuild MANA with: cargo build --release --features s3"
                                 ));
                             }
-                            sync::save_s3_config(&mana_dir, &bucket, &prefix, &region)?;
-                            sync::init_s3_sync(&mana_dir, &bucket, &prefix, &region).await?;
+                            let endpoint = sync::S3Endpoint { url: endpoint_url, path_style, ca_bundle, allow_http };
+                            endpoint.validate()?;
+                            sync::save_s3_config(&mana_dir, &bucket, &prefix, &region, &endpoint)?;
+                            sync::init_s3_sync(&mana_dir, &bucket, &prefix, &region, &endpoint).await?;
                         }
-                        "supabase" => {
+                        sync::BackendKind::Supabase => {
                             if url.is_empty() {
                                 return Err(anyhow::anyhow!("Supabase URL is required. Use --url <project-url>"));
                             }
@@ -859,14 +2464,13 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                             }
                             sync::init_supabase_sync(&mana_dir, &url).await?;
                         }
-                        "p2p" => {
-                            // Parse discovery method
-                            let discovery = match discover.to_lowercase().as_str() {
-                                "mdns" => sync::DiscoveryMethod::Mdns,
-                                "dht" => sync::DiscoveryMethod::Dht,
-                                _ => sync::DiscoveryMethod::Static,
-                            };
-
+                        sync::BackendKind::Ssh => {
+                            if host.is_empty() {
+                                return Err(anyhow::anyhow!("SSH target is required. Use --host <user@host:/path>"));
+                            }
+                            sync::init_ssh_sync(&mana_dir, &sync::SshTarget::parse(&host)?)?;
+                        }
+                        sync::BackendKind::P2p => {
                             // Parse static peers
                             let static_peers: Vec<String> = if peers.is_empty() {
                                 Vec::new()
@@ -874,9 +2478,9 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                                 peers.split(',').map(|s| s.trim().to_string()).collect()
                             };
 
-                            sync::init_p2p_sync(&mana_dir, discovery, port, static_peers)?;
+                            sync::init_p2p_sync(&mana_dir, discover, port, static_peers)?;
                         }
-                        _ => {
+                        sync::BackendKind::Git => {
                             // Save config first
                             sync::save_git_config(&mana_dir, &remote, &branch)?;
                             // Then initialize the repository
@@ -889,197 +2493,158 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                         }
                     }
                 }
-                SyncAction::Push { message, passphrase } => {
+                SyncAction::Push { message, passphrase, sign, sign_key } => {
+                    let ctx = sync::SyncContext::load(&mana_dir, &db_path)?;
+                    let backend = sync::backend_for(&ctx.config.backend);
+                    if sign && !backend.supports_signing() {
+                        anyhow::bail!("--sign is not supported for {} sync", backend.name());
+                    }
+                    let options = sync::PushOptions {
+                        passphrase: passphrase.or_else(|| std::env::var("MANA_SYNC_KEY").ok()),
+                        message,
+                        sign_key: sign.then(|| ctx.config.signing.signing_key(sign_key.as_deref())),
+                    };
+                    let span = tracing::debug_span!("sync_push", backend = backend.name());
+                    backend.push(&ctx, &options).instrument(span).await?;
+                }
+                SyncAction::Pull { passphrase, merge, interactive, require_signed } => {
+                    let merge_strategy = if interactive { sync::export::MergeStrategy::Interactive } else { merge };
+
+                    let ctx = sync::SyncContext::load(&mana_dir, &db_path)?;
+                    let backend = sync::backend_for(&ctx.config.backend);
+                    if (require_signed || ctx.config.signing.require_signed) && !backend.supports_signing() {
+                        anyhow::bail!("Signed pulls are not supported for {} sync", backend.name());
+                    }
+                    let options = sync::PullOptions {
+                        passphrase: passphrase.or_else(|| std::env::var("MANA_SYNC_KEY").ok()),
+                        merge_strategy,
+                        require_signed,
+                    };
+                    let span = tracing::debug_span!("sync_pull", backend = backend.name());
+                    backend.pull(&ctx, &options).instrument(span).await?;
+                }
+                SyncAction::Sync { message, passphrase, merge, require_signed } => {
+                    let ctx = sync::SyncContext::load(&mana_dir, &db_path)?;
+                    let backend = sync::backend_for(&ctx.config.backend);
+                    if (require_signed || ctx.config.signing.require_signed) && !backend.supports_signing() {
+                        anyhow::bail!("Signed pulls are not supported for {} sync", backend.name());
+                    }
                     let passphrase = passphrase.or_else(|| std::env::var("MANA_SYNC_KEY").ok());
-                    let security = sync::SecurityConfig::default();
-
-                    // Auto-detect backend from config
-                    let config_path = mana_dir.join("sync.toml");
-                    let config = sync::load_sync_config(&config_path)?;
-                    match &config.backend {
-                        sync::SyncBackend::S3 { .. } => {
-                            sync::push_patterns_s3(
-                                &mana_dir,
-                                &db_path,
-                                &security,
-                                passphrase.as_deref(),
-                            ).await?;
-                        }
-                        sync::SyncBackend::Git { .. } => {
-                            sync::push_patterns(
-                                &mana_dir,
-                                &db_path,
-                                &security,
-                                passphrase.as_deref(),
-                                message.as_deref(),
-                            )?;
+                    let pull = sync::PullOptions { passphrase: passphrase.clone(), merge_strategy: merge, require_signed };
+                    let push = sync::PushOptions { passphrase, message, sign_key: None };
+                    let span = tracing::debug_span!("sync", backend = backend.name());
+                    let report = backend.sync(&ctx, &pull, &push).instrument(span).await?;
+
+                    if json {
+                        print_json(&report)?;
+                    } else {
+                        let pulled = &report.pulled;
+                        println!();
+                        println!("🔄 Sync with {} {}", report.backend, if report.pushed { "complete" } else { "stopped before push" });
+                        println!("   Pulled: {} changed, {} new, {} merged, {} skipped",
+                            pulled.changed, pulled.imported, pulled.merged, pulled.skipped);
+                        if !pulled.conflicts.is_empty() {
+                            println!("   Changed on both sides: {}", pulled.conflicts.len());
                         }
-                        sync::SyncBackend::Supabase { .. } => {
-                            if !sync::is_supabase_available() {
-                                return Err(anyhow::anyhow!(
-                                    "Supabase sync not available. Rebuild MANA with: cargo build --release --features supabase"
-                                ));
+                        if !report.pushed {
+                            for conflict in pulled.conflicts.iter().take(10) {
+                                println!("   - {} {} local {:?}, remote {:?}",
+                                    conflict.tool_type, &conflict.pattern_hash[..conflict.pattern_hash.len().min(12)],
+                                    conflict.local, conflict.remote);
                             }
-                            let count = sync::push_patterns_supabase(
-                                &mana_dir,
-                                &db_path,
-                                &security,
-                                &config.security.visibility.to_string(),
-                            ).await?;
-                            println!("✅ Pushed {} patterns to Supabase", count);
-                        }
-                        sync::SyncBackend::P2P { .. } => {
-                            // P2P push = sync with all peers
-                            let results = sync::sync_with_all_peers(&mana_dir, &db_path, &security)?;
-                            let total_new: usize = results.iter().map(|r| r.new_patterns).sum();
-                            let successful = results.iter().filter(|r| r.success).count();
-                            println!("✅ P2P sync complete: {} peers, +{} patterns", successful, total_new);
+                            println!("   '--merge {}' picked one side for these; check them, then run 'mana sync push'", merge);
                         }
                     }
+                    if !report.pushed {
+                        anyhow::bail!("{} conflicts need review before pushing", report.pulled.conflicts.len());
+                    }
                 }
-                SyncAction::Pull { passphrase, merge } => {
-                    let passphrase = passphrase.or_else(|| std::env::var("MANA_SYNC_KEY").ok());
-                    let merge_strategy = match merge.as_str() {
-                        "replace" => sync::export::MergeStrategy::Replace,
-                        "keep-best" => sync::export::MergeStrategy::KeepBest,
-                        _ => sync::export::MergeStrategy::Add,
-                    };
-
-                    // Auto-detect backend from config
-                    let config_path = mana_dir.join("sync.toml");
-                    let config = sync::load_sync_config(&config_path)?;
-                    match &config.backend {
-                        sync::SyncBackend::S3 { .. } => {
-                            sync::pull_patterns_s3(
-                                &mana_dir,
-                                &db_path,
-                                passphrase.as_deref(),
-                                merge_strategy,
-                            ).await?;
+                SyncAction::Listen { merge } => {
+                    let config = sync::load_sync_config(&mana_dir.join("sync.toml"))?;
+                    if let sync::SyncBackend::P2P { listen_port, .. } = &config.backend {
+                        if !json {
+                            println!("👂 Answering peer syncs on port {} (Ctrl+C to stop)", listen_port);
                         }
-                        sync::SyncBackend::Git { .. } => {
-                            sync::pull_patterns(
-                                &mana_dir,
-                                &db_path,
-                                passphrase.as_deref(),
-                                merge_strategy,
-                            )?;
+                        sync::serve_p2p(&mana_dir, &db_path, &config.security, merge, *listen_port, |peer, result| {
+                            match result {
+                                Ok(merged) if json => println!("{}", serde_json::json!({ "peer": peer, "merged": merged })),
+                                Ok(merged) => println!("   Synced with {}: {} patterns updated", peer, merged),
+                                Err(e) if json => println!("{}", serde_json::json!({ "peer": peer, "error": e.to_string() })),
+                                Err(e) => eprintln!("⚠️  Sync from {} failed: {}", peer, e),
+                            }
+                        })?;
+                    } else {
+                        if !matches!(config.backend, sync::SyncBackend::Supabase { .. }) {
+                            anyhow::bail!("Live updates need the Supabase or P2P backend. Run 'mana sync init --backend supabase'");
                         }
-                        sync::SyncBackend::Supabase { .. } => {
-                            if !sync::is_supabase_available() {
-                                return Err(anyhow::anyhow!(
-                                    "Supabase sync not available. Rebuild MANA with: cargo build --release --features supabase"
-                                ));
+                        sync::listen(&mana_dir, &db_path, merge, |event| {
+                            if json {
+                                if let Ok(line) = serde_json::to_string(event) {
+                                    println!("{}", line);
+                                }
+                                return;
                             }
-                            let result = sync::pull_patterns_supabase(
-                                &mana_dir,
-                                &db_path,
-                                merge_strategy,
-                                true,   // include team patterns
-                                false,  // don't include public by default
-                            ).await?;
-                            println!("✅ Pulled patterns from Supabase");
-                            println!("   Total: {}, New: {}, Merged: {}",
-                                result.total, result.imported, result.merged);
-                            if result.skipped > 0 {
-                                println!("   Skipped: {}", result.skipped);
+                            match event {
+                                sync::ListenEvent::Subscribed { teams } => {
+                                    println!("👂 Listening for pattern changes in {} team{} (Ctrl+C to stop)",
+                                        teams.len(), if teams.len() == 1 { "" } else { "s" });
+                                }
+                                sync::ListenEvent::Merged { pattern_hash, tool_type, outcome, .. } => {
+                                    println!("   {} {} pattern {}", outcome, tool_type, &pattern_hash[..pattern_hash.len().min(12)]);
+                                }
+                                sync::ListenEvent::Reconnecting { error, delay_secs } => {
+                                    eprintln!("⚠️  Connection lost ({}), reconnecting in {}s", error, delay_secs);
+                                }
                             }
-                        }
-                        sync::SyncBackend::P2P { .. } => {
-                            // P2P pull = sync with all peers
-                            let security = sync::SecurityConfig::default();
-                            let results = sync::sync_with_all_peers(&mana_dir, &db_path, &security)?;
-                            let total_new: usize = results.iter().map(|r| r.new_patterns).sum();
-                            let successful = results.iter().filter(|r| r.success).count();
-                            println!("✅ P2P sync complete: {} peers, +{} patterns", successful, total_new);
+                        }).await?;
+                    }
+                }
+                SyncAction::TestAuth => {
+                    let check = sync::test_auth(&mana_dir)?;
+                    if json {
+                        print_json(&check)?;
+                    } else {
+                        println!("Remote: {}", check.remote);
+                        println!("Credentials: {}", check.method);
+                        match &check.error {
+                            None => println!("✅ Authenticated ({} branch{} visible)", check.branches.len(), if check.branches.len() == 1 { "" } else { "es" }),
+                            Some(e) => println!("❌ {}", e),
                         }
                     }
+                    if !check.ok {
+                        anyhow::bail!("Authentication check failed");
+                    }
                 }
                 SyncAction::Status => {
-                    // Auto-detect backend from config
-                    let config_path = mana_dir.join("sync.toml");
-                    let config = sync::load_sync_config(&config_path)?;
+                    let ctx = sync::SyncContext::load(&mana_dir, &db_path)?;
+                    let backend = sync::backend_for(&ctx.config.backend);
+
+                    let device = sync::device::name(&mana_dir);
+                    let devices = storage::db::open_readonly(&db_path)
+                        .and_then(|conn| storage::provenance::counts(&conn, &device))
+                        .unwrap_or_default();
+                    if json {
+                        let status = backend.status(&ctx).await?;
+                        let devices: Vec<_> = devices.iter().map(|(name, patterns)| serde_json::json!({ "device": name, "patterns": patterns })).collect();
+                        return print_json(&serde_json::json!({
+                            "backend": ctx.config.backend,
+                            "status": status,
+                            "device": device,
+                            "devices": devices,
+                        }));
+                    }
 
                     println!("MANA Sync Status");
                     println!("================");
                     println!();
-
-                    match &config.backend {
-                        sync::SyncBackend::S3 { bucket, prefix, region } => {
-                            let s3_status = sync::s3_status(&mana_dir).await?;
-                            println!("Backend: s3");
-                            println!("Bucket: {}", bucket);
-                            println!("Prefix: {}", prefix);
-                            println!("Region: {}", region);
-                            println!("Patterns file: {}", if s3_status.object_exists { "✅ Exists" } else { "❌ Not found" });
-                            if let Some(modified) = &s3_status.last_modified {
-                                println!("Last modified: {}", modified);
-                            }
-                            if let Some(size) = s3_status.size_bytes {
-                                println!("Size: {} bytes", size);
-                            }
-                        }
-                        sync::SyncBackend::Git { .. } => {
-                            let status = sync::sync_status(&mana_dir)?;
-                            if !status.configured {
-                                println!("⚠️  Sync not configured");
-                                println!("   Run 'mana sync init' to set up synchronization");
-                            } else {
-                                println!("Backend: {}", status.backend);
-                                println!("Initialized: {}", if status.repo_initialized { "✅" } else { "❌" });
-
-                                if let Some(remote) = &status.remote {
-                                    println!("Remote: {}", remote);
-                                }
-                                if let Some(branch) = &status.branch {
-                                    println!("Branch: {}", branch);
-                                }
-                                if status.local_changes {
-                                    println!("Local changes: ⚠️  Uncommitted changes");
-                                } else {
-                                    println!("Local changes: ✅ None");
-                                }
-                                if let Some(last_sync) = &status.last_sync {
-                                    println!("Last sync: {}", last_sync);
-                                }
-                            }
-                        }
-                        sync::SyncBackend::Supabase { url } => {
-                            println!("Backend: supabase");
-                            println!("URL: {}", url);
-                            if sync::is_supabase_available() {
-                                let status = sync::supabase_status(&mana_dir).await?;
-                                if status.connected {
-                                    println!("Connected: ✅");
-                                    if let Some(count) = status.pattern_count {
-                                        println!("Remote patterns: {}", count);
-                                    }
-                                } else {
-                                    println!("Connected: ❌");
-                                    println!("Check MANA_SUPABASE_KEY environment variable");
-                                }
-                            } else {
-                                println!("Status: ⚠️  Feature not compiled");
-                                println!("Rebuild with: cargo build --release --features supabase");
-                            }
-                        }
-                        sync::SyncBackend::P2P { discovery, listen_port, peers } => {
-                            let status = sync::p2p_status(&mana_dir)?;
-                            println!("Backend: p2p");
-                            println!("Discovery: {}", discovery);
-                            println!("Listen port: {}", listen_port);
-                            println!("Node ID: {}", status.node_id);
-                            println!("CRDT entries: {}", status.entry_count);
-                            println!();
-                            println!("Configured peers: {}", peers.len());
-                            for peer in peers {
-                                println!("  - {}", peer);
-                            }
-                            if peers.is_empty() {
-                                println!("   (none configured)");
-                                println!();
-                                println!("   Add peers with: mana sync peer add <address>");
-                            }
+                    backend.print_status(&ctx).await?;
+                    println!();
+                    println!("This device: {}", device);
+                    if devices.iter().any(|(name, _)| *name != device) {
+                        println!("Patterns by device:");
+                        for (name, patterns) in &devices {
+                            let here = if *name == device { " (this device)" } else { "" };
+                            println!("  {:<24} {}{}", name, patterns, here);
                         }
                     }
                 }
@@ -1116,13 +2681,16 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                             } else {
                                 for peer in peers {
                                     let status = if peer.online { "🟢" } else { "⚪" };
-                                    println!("{} {} ({})", status, peer.address, peer.node_id);
+                                    match &peer.device {
+                                        Some(device) => println!("{} {} ({}, {})", status, peer.address, device, peer.node_id),
+                                        None => println!("{} {} ({})", status, peer.address, peer.node_id),
+                                    }
                                 }
                             }
                         }
                         PeerAction::SyncWith { address } => {
                             let security = sync::SecurityConfig::default();
-                            let result = sync::sync_with_peer(&mana_dir, &db_path, &address, &security, 30)?;
+                            let result = sync::sync_with_peer(&mana_dir, &db_path, &address, &security, sync::export::MergeStrategy::Replace, 30)?;
                             if result.success {
                                 println!("✅ Synced with {}", address);
                                 println!("   Received: {}, Merged: {}, New: {}",
@@ -1135,6 +2703,55 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                 }
             }
         }
+        Commands::Login { email, code, token } => {
+            let mana_dir = get_mana_dir()?;
+            if !sync::is_supabase_available() {
+                return Err(anyhow::anyhow!(
+                    "Login requires Supabase. Rebuild MANA with: cargo build --release --features supabase"
+                ));
+            }
+
+            let session = match (email, token.or_else(|| std::env::var("MANA_SUPABASE_TOKEN").ok())) {
+                (Some(email), _) => {
+                    let code = match code {
+                        Some(code) => code,
+                        None => {
+                            ci::ensure_interactive("Signing in without --code")?;
+                            sync::request_login_code(&mana_dir, &email).await?;
+                            println!("📧 Sent a sign-in code to {}", email);
+                            print!("Code: ");
+                            std::io::Write::flush(&mut std::io::stdout())?;
+                            let mut code = String::new();
+                            std::io::stdin().read_line(&mut code)?;
+                            code
+                        }
+                    };
+                    sync::login_with_code(&mana_dir, &email, code.trim()).await?
+                }
+                (None, Some(token)) => sync::login_with_token(&mana_dir, token.trim()).await?,
+                (None, None) => {
+                    return Err(anyhow::anyhow!(
+                        "Pass --email <address> to sign in with a one-time code, or --token <access-token>"
+                    ));
+                }
+            };
+
+            println!("✅ Logged in as {}", session.display_name());
+            if session.refresh_token.is_none() {
+                let expires = chrono::DateTime::from_timestamp(session.expires_at, 0)
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default();
+                println!("   This token can't be refreshed; log in again after it expires ({})", expires);
+            }
+        }
+        Commands::Logout => {
+            let mana_dir = get_mana_dir()?;
+            if sync::logout(&mana_dir).await? {
+                println!("✅ Logged out");
+            } else {
+                println!("Not logged in");
+            }
+        }
         Commands::Team { action } => {
             let mana_dir = get_mana_dir()?;
 
@@ -1173,13 +2790,53 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                         }
                     }
                 }
-                TeamAction::Invite { team, email } => {
+                TeamAction::Invite { team, email, role, expires_days } => {
+                    if !sync::is_supabase_available() {
+                        return Err(anyhow::anyhow!(
+                            "Team features require Supabase. Rebuild MANA with: cargo build --release --features supabase"
+                        ));
+                    }
+                    if expires_days < 1 {
+                        return Err(anyhow::anyhow!("--expires-days must be at least 1"));
+                    }
+                    sync::invite_to_team(&mana_dir, &team, &email, &role, chrono::Duration::days(expires_days)).await?;
+                }
+                TeamAction::Invites { action } => {
                     if !sync::is_supabase_available() {
                         return Err(anyhow::anyhow!(
                             "Team features require Supabase. Rebuild MANA with: cargo build --release --features supabase"
                         ));
                     }
-                    sync::invite_to_team(&mana_dir, &team, &email).await?;
+                    match action {
+                        InvitesAction::List { team } => {
+                            let invites = sync::list_invites(&mana_dir, team.as_deref()).await?;
+                            if json {
+                                return print_json(&invites);
+                            }
+                            if invites.is_empty() {
+                                println!("No invites found.");
+                            } else {
+                                let now = chrono::Utc::now();
+                                println!("Team Invites");
+                                println!("============");
+                                for invite in &invites {
+                                    println!(
+                                        "  {}  {:<9} {:<7} {}  team {}  expires {}",
+                                        invite.code,
+                                        invite.status(now),
+                                        invite.role,
+                                        invite.email.as_deref().unwrap_or("-"),
+                                        invite.team_id,
+                                        invite.expires_at
+                                    );
+                                }
+                            }
+                        }
+                        InvitesAction::Revoke { code } => {
+                            sync::revoke_invite(&mana_dir, &code).await?;
+                            println!("✅ Invite revoked");
+                        }
+                    }
                 }
                 TeamAction::Join { code } => {
                     if !sync::is_supabase_available() {
@@ -1212,67 +2869,121 @@ async fn run_async_main(cli: Cli) -> Result<()> {
             let db_path = mana_dir.join("metadata.sqlite");
 
             match action {
-                PatternsAction::List { tool, limit, sort, min_score } => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
-
-                    // Build query based on filters
-                    let order_by = match sort.as_str() {
-                        "recent" => "p.id DESC",
-                        "uses" => "(p.success_count + p.failure_count) DESC",
-                        _ => "(p.success_count - p.failure_count) DESC", // score
+                PatternsAction::List { filter, limit, sort, layout } => {
+                    let filter = filter.into_filter()?;
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+
+                    // Build query based on filters; score order is by quality, ranked below
+                    let by_quality = sort == PatternSort::Score;
+                    let order_by = match sort {
+                        PatternSort::Recent => "p.id DESC",
+                        PatternSort::Uses => "(p.success_count + p.failure_count) DESC",
+                        PatternSort::Score => "(p.success_count - p.failure_count) DESC",
                     };
 
-                    let tool_filter = tool.as_ref().map(|t| format!("AND p.tool_type = '{}'", t)).unwrap_or_default();
-                    let score_filter = min_score.map(|s| format!("AND (p.success_count - p.failure_count) >= {}", s)).unwrap_or_default();
+                    let (conditions, mut values) = filter.where_clause();
+                    if !by_quality {
+                        // SQLite treats a negative limit as none
+                        values.push(rusqlite::types::Value::Integer(if limit == 0 { -1 } else { limit as i64 }));
+                    }
 
                     let query = format!(
                         "SELECT p.id, p.tool_type, p.context_query,
                                 p.success_count, p.failure_count,
-                                (p.success_count - p.failure_count) as score
+                                (p.success_count - p.failure_count) as score,
+                                p.command_category, {}
                          FROM patterns p
-                         WHERE 1=1 {} {}
-                         ORDER BY {}
-                         LIMIT ?",
-                        tool_filter, score_filter, order_by
+                         WHERE 1=1{}
+                         ORDER BY {}{}",
+                        storage::ranking::AGE_DAYS,
+                        conditions,
+                        order_by,
+                        if by_quality { "" } else { " LIMIT ?" }
                     );
 
+                    let ranker = storage::ranking::Ranker::configured(&conn)?;
                     let mut stmt = conn.prepare(&query)?;
-                    let patterns: Vec<(i64, String, String, i64, i64, i64)> = stmt
-                        .query_map([limit as i64], |row| {
-                            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+                    let mut patterns: Vec<(i64, String, String, i64, i64, i64, f64)> = stmt
+                        .query_map(rusqlite::params_from_iter(values), |row| {
+                            let (tool_type, success, failure): (String, i64, i64) = (row.get(1)?, row.get(3)?, row.get(4)?);
+                            let category: Option<String> = row.get(6)?;
+                            let id: i64 = row.get(0)?;
+                            let quality = ranker.quality(id, &tool_type, category.as_deref(), success, failure, row.get(7)?);
+                            Ok((id, tool_type, row.get(2)?, success, failure, row.get(5)?, quality))
                         })?
                         .filter_map(|r| r.ok())
                         .collect();
+                    if by_quality {
+                        patterns.sort_by(|a, b| storage::ranking::best_first((a.6, a.3), (b.6, b.3)));
+                        if limit > 0 {
+                            patterns.truncate(limit);
+                        }
+                    }
 
-                    println!("Patterns ({})", patterns.len());
-                    println!("{}", "=".repeat(50));
-                    println!();
+                    if json {
+                        let rows: Vec<_> = patterns
+                            .iter()
+                            .map(|(id, tool_type, context, success, failure, score, quality)| {
+                                serde_json::json!({
+                                    "id": id,
+                                    "tool_type": tool_type,
+                                    "context": context,
+                                    "success_count": success,
+                                    "failure_count": failure,
+                                    "score": score,
+                                    "quality": quality,
+                                })
+                            })
+                            .collect();
+                        return print_json(&rows);
+                    }
 
                     if patterns.is_empty() {
                         println!("No patterns found matching filters.");
-                    } else {
-                        for (id, tool_type, context, success, failure, score) in patterns {
-                            let rate = if success + failure > 0 {
-                                (success as f64 / (success + failure) as f64) * 100.0
-                            } else {
-                                0.0
-                            };
-
-                            // Truncate context for display
-                            let context_display = if context.len() > 60 {
-                                format!("{}...", &context[..57])
-                            } else {
-                                context
-                            };
-
-                            println!("#{} [{}] score:{} ({:.0}%)", id, tool_type, score, rate);
-                            println!("   {}", context_display);
-                            println!();
-                        }
+                        return Ok(());
+                    }
+                    println!("Patterns ({})", patterns.len());
+                    println!();
+                    let mut table = table::Table::new(&[">ID", "TOOL", ">SCORE", ">RATE", ">QUALITY", "~CONTEXT"]);
+                    for (id, tool_type, context, success, failure, score, quality) in patterns {
+                        let rate = if success + failure > 0 {
+                            (success as f64 / (success + failure) as f64) * 100.0
+                        } else {
+                            0.0
+                        };
+                        table.row(vec![
+                            format!("#{}", id),
+                            tool_type,
+                            score.to_string(),
+                            format!("{:.0}%", rate),
+                            format!("{:.2}", quality),
+                            context,
+                        ]);
                     }
+                    layout.print(&table)?;
                 }
-                PatternsAction::Show { pattern_id } => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                PatternsAction::Browse { filter, team } => {
+                    let filter = filter.into_filter()?;
+                    ci::ensure_interactive("patterns browse")?;
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+
+                    // Tool, category and score stay adjustable in the browser
+                    let loaded = storage::PatternFilter { tool: None, category: None, min_score: None, ..filter.clone() };
+                    let entries = browse::load(&conn, &loaded)?;
+                    let browser = browse::run(&conn, &mana_dir, browse::Browser::new(entries, &filter, team.clone()))?;
+                    if browser.changed {
+                        storage::snapshot::rebuild(&mana_dir, &conn)?;
+                    }
+                    if let Some(team) = team {
+                        for hash in &browser.shares {
+                            sync::share_pattern(&mana_dir, hash, &team).await?;
+                        }
+                    }
+                }
+                PatternsAction::Show { pattern_id } => {
+                    let conn = storage::db::open(&db_path)?;
 
                     let result: Option<PatternRow> = conn
                         .query_row(
@@ -1283,6 +2994,37 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                         )
                         .ok();
 
+                    if json {
+                        let Some((tool_type, context, success, failure, embedding)) = result else {
+                            anyhow::bail!("Pattern #{} not found", pattern_id);
+                        };
+                        let (risky, approved_at): (bool, Option<String>) = conn.query_row(
+                            "SELECT risky = 1, approved_at FROM patterns WHERE id = ?1",
+                            [pattern_id],
+                            |row| Ok((row.get(0)?, row.get(1)?)),
+                        )?;
+                        let reflection = reflection::MemoryDistiller::get_pattern_stats(&conn, pattern_id).ok();
+                        let origin = storage::provenance::device_of(&conn, pattern_id).ok().flatten();
+                        let ranker = storage::ranking::Ranker::configured(&conn)?;
+                        return print_json(&serde_json::json!({
+                            "id": pattern_id,
+                            "tool_type": tool_type,
+                            "context": context,
+                            "success_count": success,
+                            "failure_count": failure,
+                            "score": success - failure,
+                            "has_embedding": embedding.is_some(),
+                            "risky": risky,
+                            "approved_at": approved_at,
+                            "tags": storage::tags::for_pattern(&conn, pattern_id).unwrap_or_default(),
+                            "device": origin.clone().unwrap_or_else(|| sync::device::name(&mana_dir)),
+                            "synced_from": origin,
+                            "reflection": reflection,
+                            "quality": ranker.quality_of(&conn, pattern_id)?,
+                            "reputation": ranker.reputation(pattern_id),
+                        }));
+                    }
+
                     match result {
                         Some((tool_type, context, success, failure, embedding)) => {
                             let score = success - failure;
@@ -1299,6 +3041,26 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                             println!("Score: {} ({:.0}% success rate)", score, rate);
                             println!("Uses: {} success, {} failure", success, failure);
                             println!("Has embedding: {}", if embedding.is_some() { "✅" } else { "❌" });
+                            let risk: Option<(bool, Option<String>)> = conn
+                                .query_row(
+                                    "SELECT risky = 1, approved_at FROM patterns WHERE id = ?1",
+                                    [pattern_id],
+                                    |row| Ok((row.get(0)?, row.get(1)?)),
+                                )
+                                .ok();
+                            match risk {
+                                Some((true, None)) => println!("Risk: ⚠️  destructive command, awaiting approval"),
+                                Some((true, Some(at))) => println!("Risk: destructive command, approved {}", at),
+                                _ => {}
+                            }
+                            let tags = storage::tags::for_pattern(&conn, pattern_id).unwrap_or_default();
+                            if !tags.is_empty() {
+                                println!("Tags: {}", tags.join(", "));
+                            }
+                            match storage::provenance::device_of(&conn, pattern_id).ok().flatten() {
+                                Some(device) => println!("Device: {} (synced)", device),
+                                None => println!("Device: {} (learned here)", sync::device::name(&mana_dir)),
+                            }
                             println!();
                             println!("Context:");
                             println!("{}", context);
@@ -1314,93 +3076,136 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                                     println!("  Harmful: {} ({:.0}%)", stats.harmful, stats.harm_ratio() * 100.0);
                                 }
                             }
+
+                            // Local and shared verdicts as weighed for ranking
+                            let ranker = storage::ranking::Ranker::configured(&conn)?;
+                            if let (Some(quality), Some(reputation)) = (ranker.quality_of(&conn, pattern_id)?, ranker.reputation(pattern_id)) {
+                                println!();
+                                println!("Reputation: {:.2}", quality);
+                                for (label, tally) in [("Own", reputation.own), ("Team", reputation.team), ("Public", reputation.public)] {
+                                    if tally.total() > 0 {
+                                        println!(
+                                            "  {:<7} {} effective, {} neutral, {} ineffective, {} harmful",
+                                            format!("{}:", label),
+                                            tally.effective,
+                                            tally.neutral,
+                                            tally.ineffective,
+                                            tally.harmful
+                                        );
+                                    }
+                                }
+                            }
                         }
                         None => {
                             println!("Pattern #{} not found.", pattern_id);
                         }
                     }
                 }
-                PatternsAction::Search { query, limit } => {
+                PatternsAction::Search { query, limit, layout } => {
+                    let conn = storage::db::open(&db_path)?;
+                    let limit = if limit == 0 {
+                        conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get::<_, i64>(0))?.max(1) as usize
+                    } else {
+                        limit
+                    };
+
                     // Try semantic search first, fall back to text search
-                    if embeddings::is_available(&mana_dir) {
+                    let semantic = if embeddings::is_available(&mana_dir) {
                         match embeddings::search(&mana_dir, &query, limit) {
-                            Ok(results) => {
-                                println!("Semantic Search Results for: \"{}\"", query);
-                                println!("{}", "=".repeat(50));
-                                println!();
-
-                                if results.is_empty() {
-                                    println!("No matching patterns found.");
-                                } else {
-                                    let conn = rusqlite::Connection::open(&db_path)?;
-
-                                    for (pattern_id, similarity) in results {
-                                        if let Ok((tool_type, context, success, failure)) = conn.query_row::<(String, String, i64, i64), _, _>(
-                                            "SELECT tool_type, context_query, success_count, failure_count FROM patterns WHERE id = ?1",
-                                            [pattern_id],
-                                            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
-                                        ) {
-                                            let score = success - failure;
-                                            let context_display = if context.len() > 50 {
-                                                format!("{}...", &context[..47])
-                                            } else {
-                                                context
-                                            };
-                                            println!("#{} [{}] similarity:{:.2} score:{}", pattern_id, tool_type, similarity, score);
-                                            println!("   {}", context_display);
-                                            println!();
-                                        }
-                                    }
-                                }
-                            }
+                            Ok(results) => Some(results),
                             Err(e) => {
-                                println!("Semantic search failed: {}", e);
-                                println!("Falling back to text search...");
+                                if !json {
+                                    println!("Semantic search failed: {}", e);
+                                    println!("Falling back to text search...");
+                                }
+                                None
                             }
                         }
                     } else {
-                        // Text-based search fallback
-                        let conn = rusqlite::Connection::open(&db_path)?;
-                        let search_pattern = format!("%{}%", query);
-
-                        let mut stmt = conn.prepare(
-                            "SELECT id, tool_type, context_query, success_count, failure_count
-                             FROM patterns
-                             WHERE context_query LIKE ?1
-                             ORDER BY (success_count - failure_count) DESC
-                             LIMIT ?2"
-                        )?;
+                        None
+                    };
+
+                    // (id, tool_type, context, score, similarity)
+                    let hits: Vec<(i64, String, String, i64, Option<f32>)> = match &semantic {
+                        Some(results) => results
+                            .iter()
+                            .filter_map(|&(pattern_id, similarity)| {
+                                conn.query_row(
+                                    "SELECT tool_type, context_query, success_count - failure_count FROM patterns WHERE id = ?1",
+                                    [pattern_id],
+                                    |row| Ok((pattern_id, row.get(0)?, row.get(1)?, row.get(2)?, Some(similarity))),
+                                )
+                                .ok()
+                            })
+                            .collect(),
+                        None => {
+                            // Matches ranked by quality (see `storage::ranking`)
+                            let search_pattern = format!("%{}%", query);
+                            let ranker = storage::ranking::Ranker::configured(&conn)?;
+                            let mut stmt = conn.prepare(&format!(
+                                "SELECT id, tool_type, context_query, success_count, failure_count, command_category, {}
+                                 FROM patterns
+                                 WHERE context_query LIKE ?1",
+                                storage::ranking::AGE_DAYS
+                            ))?;
+                            let mut rows: Vec<_> = stmt
+                                .query_map(rusqlite::params![search_pattern], |row| {
+                                    let (tool_type, success, failure): (String, i64, i64) = (row.get(1)?, row.get(3)?, row.get(4)?);
+                                    let category: Option<String> = row.get(5)?;
+                                    let id: i64 = row.get(0)?;
+                                    let quality = ranker.quality(id, &tool_type, category.as_deref(), success, failure, row.get(6)?);
+                                    Ok(((id, tool_type, row.get::<_, String>(2)?, success - failure, None), quality, success))
+                                })?
+                                .filter_map(|r| r.ok())
+                                .collect();
+                            rows.sort_by(|a, b| storage::ranking::best_first((a.1, a.2), (b.1, b.2)));
+                            rows.into_iter().take(limit).map(|(hit, ..)| hit).collect()
+                        }
+                    };
 
-                        let results: Vec<(i64, String, String, i64, i64)> = stmt
-                            .query_map(rusqlite::params![search_pattern, limit as i64], |row| {
-                                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
-                            })?
-                            .filter_map(|r| r.ok())
+                    if json {
+                        let rows: Vec<_> = hits
+                            .iter()
+                            .map(|(id, tool_type, context, score, similarity)| {
+                                serde_json::json!({
+                                    "id": id,
+                                    "tool_type": tool_type,
+                                    "context": context,
+                                    "score": score,
+                                    "similarity": similarity,
+                                })
+                            })
                             .collect();
+                        return print_json(&serde_json::json!({
+                            "query": query,
+                            "mode": if semantic.is_some() { "semantic" } else { "text" },
+                            "results": rows,
+                        }));
+                    }
 
-                        println!("Text Search Results for: \"{}\"", query);
-                        println!("{}", "=".repeat(50));
-                        println!();
+                    let kind = if semantic.is_some() { "Semantic" } else { "Text" };
+                    println!("{} Search Results for: \"{}\"", kind, query);
+                    println!();
 
-                        if results.is_empty() {
-                            println!("No matching patterns found.");
-                        } else {
-                            for (id, tool_type, context, success, failure) in results {
-                                let score = success - failure;
-                                let context_display = if context.len() > 50 {
-                                    format!("{}...", &context[..47])
-                                } else {
-                                    context
-                                };
-                                println!("#{} [{}] score:{}", id, tool_type, score);
-                                println!("   {}", context_display);
-                                println!();
-                            }
-                        }
+                    if hits.is_empty() {
+                        println!("No matching patterns found.");
+                        return Ok(());
                     }
+                    let mut table = if semantic.is_some() {
+                        table::Table::new(&[">ID", "TOOL", ">SIMILARITY", ">SCORE", "~CONTEXT"])
+                    } else {
+                        table::Table::new(&[">ID", "TOOL", ">SCORE", "~CONTEXT"])
+                    };
+                    for (id, tool_type, context, score, similarity) in hits {
+                        let mut cells = vec![format!("#{}", id), tool_type];
+                        cells.extend(similarity.map(|s| format!("{:.2}", s)));
+                        cells.extend([score.to_string(), context]);
+                        table.row(cells);
+                    }
+                    layout.print(&table)?;
                 }
                 PatternsAction::Summary => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
 
                     // Get overall stats
                     let total: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0))?;
@@ -1439,51 +3244,842 @@ async fn run_async_main(cli: Cli) -> Result<()> {
                         };
                         println!("  {}: {} patterns ({:.0}% success)", tool_type, count, rate);
                     }
-                }
-                PatternsAction::Delete { pattern_id, force } => {
-                    let conn = rusqlite::Connection::open(&db_path)?;
 
-                    // Check if pattern exists
-                    let exists: bool = conn
-                        .query_row("SELECT 1 FROM patterns WHERE id = ?1", [pattern_id], |_| Ok(true))
-                        .unwrap_or(false);
+                    let (_, by_language, by_project) = storage::stats::breakdowns(&conn)?;
+                    println!();
+                    println!("By Language:");
+                    storage::print_breakdowns(&by_language, usize::MAX);
+                    if !by_project.is_empty() {
+                        println!();
+                        println!("By Project:");
+                        storage::print_breakdowns(&by_project, storage::PROJECTS_SHOWN);
+                    }
+                }
+                PatternsAction::Delete { pattern_id, targets } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let selected = targets.select(&conn, pattern_id)?;
+                    if selected.is_empty() {
+                        match pattern_id {
+                            Some(id) if targets.is_empty() => println!("Pattern #{} not found.", id),
+                            _ => println!("No patterns match."),
+                        }
+                        return Ok(());
+                    }
+                    if !targets.confirm("delete", &selected, json)? {
+                        return Ok(());
+                    }
 
-                    if !exists {
-                        println!("Pattern #{} not found.", pattern_id);
+                    let ids: Vec<i64> = selected.iter().map(|t| t.id).collect();
+                    storage::bulk::delete(&conn, &ids)?;
+                    if embeddings::is_available(&mana_dir) {
+                        for id in &ids {
+                            let _ = embeddings::delete_from_index(&mana_dir, *id);
+                        }
+                    }
+                    storage::snapshot::rebuild(&mana_dir, &conn)?;
+                    if json {
+                        return print_json(&serde_json::json!({ "deleted": ids }));
+                    }
+                    println!("✅ Deleted {} pattern(s).", ids.len());
+                }
+                PatternsAction::Archive { pattern_id, targets } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let selected: Vec<_> = targets.select(&conn, pattern_id)?.into_iter().filter(|t| t.status == "active").collect();
+                    if selected.is_empty() {
+                        println!("No active patterns match.");
+                        return Ok(());
+                    }
+                    if !targets.confirm("archive", &selected, json)? {
                         return Ok(());
                     }
 
+                    let ids: Vec<i64> = selected.iter().map(|t| t.id).collect();
+                    let archived = storage::bulk::archive(&conn, &ids)?;
+                    storage::snapshot::rebuild(&mana_dir, &conn)?;
+                    if json {
+                        return print_json(&serde_json::json!({ "archived": ids }));
+                    }
+                    println!("✅ Archived {} pattern(s). Bring one back with: mana patterns reinstate <id>", archived);
+                }
+                PatternsAction::Purge { project, force } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let project_hash = storage::projects::resolve(&project)?;
+
                     if !force {
-                        // Show pattern info and ask for confirmation
-                        let (tool_type, context): (String, String) = conn.query_row(
-                            "SELECT tool_type, context_query FROM patterns WHERE id = ?1",
-                            [pattern_id],
-                            |row| Ok((row.get(0)?, row.get(1)?)),
+                        let ids = storage::projects::patterns_in(&conn, &project_hash)?;
+                        if ids.is_empty() {
+                            println!("No patterns were learned in project {}.", project_hash);
+                            return Ok(());
+                        }
+                        let shared: i64 = conn.query_row(
+                            "SELECT COUNT(DISTINCT pattern_id) FROM pattern_projects
+                             WHERE project_hash != ?1
+                               AND pattern_id IN (SELECT pattern_id FROM pattern_projects WHERE project_hash = ?1)",
+                            [&project_hash],
+                            |row| row.get(0),
                         )?;
+                        println!("About to delete {} patterns learned in project {}", ids.len(), project_hash);
+                        if shared > 0 {
+                            println!("  {} of them were also learned in other projects", shared);
+                        }
+                        println!();
+                        println!("Use --force to confirm deletion.");
+                        return Ok(());
+                    }
 
-                        let context_display = if context.len() > 50 {
-                            format!("{}...", &context[..47])
+                    let removed = storage::projects::purge(&conn, &project_hash)?;
+                    if !removed.is_empty() {
+                        if embeddings::is_available(&mana_dir) {
+                            for id in &removed {
+                                let _ = embeddings::delete_from_index(&mana_dir, *id);
+                            }
+                        }
+                        storage::snapshot::rebuild(&mana_dir, &conn)?;
+                    }
+                    if json {
+                        return print_json(&serde_json::json!({ "project": project_hash, "deleted": removed }));
+                    }
+                    println!("✅ Deleted {} patterns learned in project {}.", removed.len(), project_hash);
+                }
+                PatternsAction::Packs => {
+                    storage::ensure_schema(&db_path)?;
+                    let packs = sync::packs::bundled(&storage::db::open(&db_path)?)?;
+                    if json {
+                        return print_json(&packs);
+                    }
+
+                    println!("Starter Packs");
+                    println!("{}", "=".repeat(40));
+                    println!();
+                    for pack in &packs {
+                        let installed = if pack.installed > 0 {
+                            format!(" ({} installed)", pack.installed)
                         } else {
-                            context
+                            String::new()
                         };
+                        println!("{:<14} {:>2} patterns{}", pack.name, pack.patterns, installed);
+                        println!("               {}", pack.description);
+                    }
+                    println!();
+                    println!("Install with: mana patterns install <pack>");
+                }
+                PatternsAction::Install { pack } => {
+                    storage::ensure_schema(&db_path)?;
+                    let result = sync::packs::install(&db_path, &pack)?;
+                    storage::snapshot::rebuild(&mana_dir, &storage::db::open(&db_path)?)?;
+                    if json {
+                        return print_json(&result);
+                    }
+                    println!("✅ Installed pack {}: {} new patterns, {} already present", result.name, result.added, result.merged);
+                    if result.risky > 0 {
+                        println!("⚠️  {} destructive patterns held back; review with: mana patterns risky", result.risky);
+                    }
+                    println!("   List with: mana patterns list --tag {}", result.tag);
+                    println!("   Remove with: mana patterns uninstall {}", result.name);
+                }
+                PatternsAction::Uninstall { pack } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let removed = sync::packs::uninstall(&conn, &pack)?;
+                    if removed.is_empty() {
+                        println!("No patterns from pack {} are installed.", pack);
+                        return Ok(());
+                    }
+                    if embeddings::is_available(&mana_dir) {
+                        for id in &removed {
+                            let _ = embeddings::delete_from_index(&mana_dir, *id);
+                        }
+                    }
+                    storage::snapshot::rebuild(&mana_dir, &conn)?;
+                    println!("✅ Removed {} patterns from pack {}.", removed.len(), pack);
+                }
+                PatternsAction::Top { limit, days, min_verdicts } => {
+                    let conn = storage::db::open(&db_path)?;
+                    reflection::init_reflection_tables(&conn)?;
+                    let top = reflection::leaderboard(&conn, days, min_verdicts, limit)?;
+                    if json {
+                        return print_json(&top);
+                    }
 
-                        println!("About to delete pattern #{}:", pattern_id);
-                        println!("  Type: {}", tool_type);
-                        println!("  Context: {}", context_display);
+                    println!("Most Effective Patterns (last {} days)", days);
+                    println!("{}", "=".repeat(40));
+                    println!();
+                    if top.is_empty() {
+                        println!("No pattern has {} or more verdicts in the window.", min_verdicts);
+                    }
+                    for (rank, trend) in top.iter().enumerate() {
+                        println!(
+                            "{:>2}. #{:<6} {:<6} {:>3.0}% effective ({}/{} verdicts), score {}",
+                            rank + 1,
+                            trend.id,
+                            trend.tool_type,
+                            trend.recent.effectiveness_ratio() * 100.0,
+                            trend.recent.effective,
+                            trend.recent.total,
+                            trend.score
+                        );
+                        println!("    {}", trend.context_query.lines().next().unwrap_or_default().chars().take(80).collect::<String>());
+                    }
+                }
+                PatternsAction::Regressions { days, min_verdicts, exit_code } => {
+                    let conn = storage::db::open(&db_path)?;
+                    reflection::init_reflection_tables(&conn)?;
+                    let found = reflection::regressions(&conn, days, min_verdicts)?;
+                    if json {
+                        print_json(&found)?;
+                    } else if found.is_empty() {
+                        println!("No regressions in the last {} days.", days);
+                    } else {
+                        println!("Pattern Regressions (last {} days)", days);
+                        println!("{}", "=".repeat(40));
+                        for trend in &found {
+                            let now = if trend.recent.harmful >= trend.recent.ineffective { "harmful" } else { "ineffective" };
+                            println!();
+                            println!("#{} [{}] score {}, now {}", trend.id, trend.tool_type, trend.score, now);
+                            println!(
+                                "   before: {} effective / {} verdicts   recent: {} harmful, {} ineffective / {} verdicts",
+                                trend.earlier.effective,
+                                trend.earlier.total,
+                                trend.recent.harmful,
+                                trend.recent.ineffective,
+                                trend.recent.total
+                            );
+                            println!("   {}", trend.context_query.lines().next().unwrap_or_default().chars().take(80).collect::<String>());
+                        }
                         println!();
-                        println!("Use --force to confirm deletion.");
+                        println!("Inspect with: mana reflect analyze <id>");
+                    }
+                    if exit_code && !found.is_empty() {
+                        ci::exit(ci::ExitCode::CheckFailed);
+                    }
+                }
+                PatternsAction::Risky => {
+                    let store = storage::PatternStore::open(&db_path)?;
+                    let pending = store.get_pending_risky()?;
+
+                    println!("Risky Patterns Awaiting Approval");
+                    println!("{}", "=".repeat(40));
+                    println!();
+
+                    if pending.is_empty() {
+                        println!("No risky patterns awaiting approval.");
+                    } else {
+                        for pattern in &pending {
+                            let approach = pattern.context_query
+                                .lines()
+                                .find(|l| l.starts_with("Approach:"))
+                                .unwrap_or(&pattern.context_query);
+                            let approach: String = approach.chars().take(80).collect();
+                            println!("#{:<6} {:<6} {}", pattern.id, pattern.tool_type, approach);
+                        }
+                        println!();
+                        println!("Approve with: mana patterns approve-risky <id>");
+                    }
+                }
+                PatternsAction::ApproveRisky { pattern_id } => {
+                    let store = storage::PatternStore::open(&db_path)?;
+                    if store.approve_risky(pattern_id)? {
+                        println!("✅ Pattern #{} approved for injection.", pattern_id);
+                    } else {
+                        println!("Pattern #{} not found or not awaiting approval.", pattern_id);
+                    }
+                }
+                PatternsAction::Quarantined => {
+                    let store = storage::PatternStore::open(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    reflection::init_reflection_tables(&conn)?;
+                    let quarantined = store.get_quarantined()?;
+
+                    println!("Quarantined Patterns");
+                    println!("{}", "=".repeat(40));
+                    println!();
+
+                    if quarantined.is_empty() {
+                        println!("No quarantined patterns.");
+                    } else {
+                        for (pattern, since) in &quarantined {
+                            let stats = reflection::MemoryDistiller::get_pattern_stats(&conn, pattern.id)?;
+                            let approach = pattern.context_query
+                                .lines()
+                                .find(|l| l.starts_with("Approach:"))
+                                .unwrap_or(&pattern.context_query);
+                            let approach: String = approach.chars().take(80).collect();
+                            println!("#{:<6} {:<6} {}", pattern.id, pattern.tool_type, approach);
+                            println!(
+                                "        {} harmful / {} verdicts, since {}",
+                                stats.harmful,
+                                stats.total,
+                                since.as_deref().unwrap_or("unknown")
+                            );
+                        }
+                        println!();
+                        println!("Review with: mana reflect analyze <id>");
+                        println!("Reinstate with: mana patterns reinstate <id>");
+                    }
+                }
+                PatternsAction::Reinstate { pattern_id } => {
+                    let store = storage::PatternStore::open(&db_path)?;
+                    if store.reinstate(pattern_id)? {
+                        storage::snapshot::rebuild(&mana_dir, &storage::db::open(&db_path)?)?;
+                        println!("✅ Pattern #{} reinstated for injection.", pattern_id);
+                    } else {
+                        println!("Pattern #{} not found or already active.", pattern_id);
+                    }
+                }
+                PatternsAction::Tag { args, remove, targets } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+
+                    if !targets.is_empty() {
+                        let tags: Vec<String> = args.iter().map(|t| storage::tags::normalize(t)).collect::<Result<_>>()?;
+                        let selected = targets.select(&conn, None)?;
+                        if selected.is_empty() {
+                            println!("No patterns match.");
+                            return Ok(());
+                        }
+                        if !json {
+                            println!("Tags: {}", tags.join(", "));
+                        }
+                        if !targets.confirm(if remove { "untag" } else { "tag" }, &selected, json)? {
+                            return Ok(());
+                        }
+                        let ids: Vec<i64> = selected.iter().map(|t| t.id).collect();
+                        let changed = storage::bulk::tag(&conn, &ids, &tags, remove)?;
+                        if json {
+                            return print_json(&serde_json::json!({ "patterns": ids, "tags": tags, "removed": remove, "changed": changed }));
+                        }
+                        let verb = if remove { "Removed" } else { "Added" };
+                        println!("✅ {} {} tag(s) across {} pattern(s).", verb, changed, ids.len());
                         return Ok(());
                     }
 
-                    // Delete the pattern
-                    conn.execute("DELETE FROM patterns WHERE id = ?1", [pattern_id])?;
+                    let (pattern_id, tags) = match args.split_first() {
+                        Some((id, tags)) if !tags.is_empty() => (
+                            id.parse::<i64>().map_err(|_| anyhow::anyhow!("Expected a pattern ID before the tags, got {:?}", id))?,
+                            tags,
+                        ),
+                        _ => anyhow::bail!("Give a pattern ID and at least one tag (or pick patterns with --ids or filter flags)"),
+                    };
+                    if remove {
+                        let removed = storage::tags::remove(&conn, pattern_id, tags)?;
+                        println!("Removed {} tag(s) from pattern #{}", removed, pattern_id);
+                    } else {
+                        let added = storage::tags::add(&conn, pattern_id, tags)?;
+                        println!("Added {} tag(s) to pattern #{}", added, pattern_id);
+                    }
+                    let current = storage::tags::for_pattern(&conn, pattern_id)?;
+                    if current.is_empty() {
+                        println!("   Tags: (none)");
+                    } else {
+                        println!("   Tags: {}", current.join(", "));
+                    }
+                }
+                PatternsAction::History { pattern_id, limit } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let versions = storage::history::list_versions(&conn, pattern_id, limit)?;
+
+                    println!("History for pattern #{}", pattern_id);
+                    println!("{}", "=".repeat(40));
+
+                    if versions.is_empty() {
+                        println!();
+                        println!("No recorded changes.");
+                        return Ok(());
+                    }
 
-                    // Also delete from vector index if available
+                    for version in &versions {
+                        println!();
+                        println!(
+                            "v{:<4} score {:>4} ({}/{})  {} {}",
+                            version.version,
+                            version.score(),
+                            version.success_count,
+                            version.failure_count,
+                            if version.counts_only { "counts changed" } else { "replaced" },
+                            version.created_at
+                        );
+                        for line in version.context_query.lines().take(4) {
+                            let line: String = line.chars().take(100).collect();
+                            println!("      {}", line);
+                        }
+                    }
+                    println!();
+                    println!("Restore with: mana patterns rollback {} --to <version>", pattern_id);
+                }
+                PatternsAction::Rollback { pattern_id, to } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let current: Option<String> = conn
+                        .query_row("SELECT context_query FROM patterns WHERE id = ?1", [pattern_id], |row| row.get(0))
+                        .ok();
+                    let restored = storage::history::rollback(&conn, pattern_id, to)?;
+
+                    // Content changes need a fresh embedding
+                    if current.as_deref() != Some(restored.context_query.as_str()) && embeddings::is_available(&mana_dir) {
+                        embeddings::reembed_pattern(&mana_dir, pattern_id, &restored.context_query)?;
+                    }
+
+                    println!("✅ Pattern #{} restored to v{} (score {})", pattern_id, to, restored.score());
+                }
+            }
+        }
+        Commands::Db { action } => {
+            let mana_dir = get_mana_dir()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            if !db_path.exists() && !matches!(action, DbAction::Restore { .. } | DbAction::Backups) {
+                anyhow::bail!("No database at {:?}. Run `mana init` first.", db_path);
+            }
+
+            match action {
+                DbAction::Migrate { dry_run } => {
+                    use storage::migrations;
+
+                    if dry_run {
+                        let conn = storage::db::open(&db_path)?;
+                        let pending = migrations::pending(&conn);
+                        if pending.is_empty() {
+                            println!("Schema is up to date (v{})", migrations::current_version(&conn));
+                        } else {
+                            println!("Pending migrations:");
+                            for migration in pending {
+                                println!("  {:>3}  {}", migration.version, migration.name);
+                            }
+                        }
+                        return Ok(());
+                    }
+
+                    let report = migrations::upgrade(&db_path)?;
+                    if report.applied.is_empty() {
+                        println!("Schema is up to date (v{})", report.to);
+                    } else {
+                        for migration in &report.applied {
+                            println!("  Applied {:>3}  {}", migration.version, migration.name);
+                        }
+                        println!("Migrated schema v{} -> v{}", report.from, report.to);
+                        if let Some(backup) = &report.backup {
+                            println!("Backup: {}", backup.display());
+                        }
+                    }
+                }
+                DbAction::Version => {
+                    use storage::migrations;
+
+                    let conn = storage::db::open(&db_path)?;
+                    let current = migrations::current_version(&conn);
+                    let latest = migrations::latest_version();
+                    println!("Schema version: {}", current);
+                    println!("Latest known:   {}", latest);
+                    if current < latest {
+                        println!("\n{} migration(s) pending; run `mana db migrate`", latest - current);
+                    } else if current > latest {
+                        println!("\nDatabase was written by a newer mana; consider upgrading");
+                    }
+                }
+                DbAction::Vacuum => {
+                    let (before, after) = storage::maintenance::vacuum(&db_path)?;
+                    println!(
+                        "Vacuumed {}: {} -> {} bytes ({} reclaimed)",
+                        db_path.display(),
+                        before,
+                        after,
+                        before.saturating_sub(after)
+                    );
+                }
+                DbAction::IntegrityCheck => {
+                    let conn = storage::db::open(&db_path)?;
+                    let problems = storage::maintenance::integrity_check(&conn)?;
+                    if problems.is_empty() {
+                        println!("Integrity check passed");
+                    } else {
+                        println!("Integrity check found {} problem(s):", problems.len());
+                        for problem in &problems {
+                            println!("  {}", problem);
+                        }
+                        println!("\nRestore a backup with `mana db restore`.");
+                        ci::exit(ci::ExitCode::Verification);
+                    }
+                }
+                DbAction::Fsck { dry_run } => {
+                    storage::ensure_schema(&db_path)?;
+                    let conn = storage::db::open(&db_path)?;
+                    let mut report = storage::fsck::check(&conn, dry_run)?;
                     if embeddings::is_available(&mana_dir) {
-                        let _ = embeddings::delete_from_index(&mana_dir, pattern_id);
+                        report.vectors = embeddings::remove_orphans(&mana_dir, &storage::fsck::pattern_ids(&conn)?, dry_run)?;
+                    }
+                    if !dry_run && !report.is_clean() {
+                        storage::snapshot::rebuild(&mana_dir, &conn)?;
+                    }
+                    if json {
+                        return print_json(&serde_json::json!({ "dry_run": dry_run, "report": report }));
+                    }
+
+                    if report.is_clean() {
+                        println!("No orphaned rows found");
+                        return Ok(());
+                    }
+                    let verb = if dry_run { "Found" } else { "Removed" };
+                    for orphans in &report.orphans {
+                        println!("{} {} orphaned row(s) in {} ({})", verb, orphans.rows, orphans.table, orphans.column);
+                    }
+                    if report.skills > 0 {
+                        if dry_run {
+                            println!("Found {} skill(s) listing missing patterns", report.skills);
+                        } else {
+                            println!("Dropped missing patterns from {} skill(s)", report.skills);
+                        }
+                    }
+                    if report.vectors > 0 {
+                        println!("{} {} orphaned vector(s) in the embedding index", verb, report.vectors);
+                    }
+                    if dry_run {
+                        println!("\nRun `mana db fsck` to repair.");
+                        ci::exit(ci::ExitCode::Verification);
+                    }
+                }
+                DbAction::Checkpoint => {
+                    let conn = storage::db::open(&db_path)?;
+                    let result = storage::maintenance::checkpoint(&conn)?;
+                    if result.log_frames < 0 {
+                        println!("Database is not in WAL mode; nothing to checkpoint");
+                    } else if result.busy {
+                        println!(
+                            "Checkpoint incomplete: {}/{} frames written (database busy)",
+                            result.checkpointed_frames, result.log_frames
+                        );
+                    } else {
+                        println!("Checkpointed {} WAL frames", result.checkpointed_frames);
+                    }
+                }
+                DbAction::Backup { full: false } => {
+                    let path = storage::maintenance::backup(&mana_dir)?;
+                    println!("Backup written to {}", path.display());
+                }
+                DbAction::Backup { full: true } => {
+                    // Learning state has to match the database it's copied with
+                    let _lock = learning::lock::acquire(&mana_dir, std::time::Duration::from_secs(10))?
+                        .ok_or_else(|| ci::fail(ci::ExitCode::Busy, "Learning is in progress; try again shortly"))?;
+                    let path = storage::full_backup::create(&mana_dir)?;
+                    println!("Backup written to {}", path.display());
+                }
+                DbAction::Backups => {
+                    let backups = storage::maintenance::list_backups(&mana_dir)?;
+                    if backups.is_empty() {
+                        println!("No backups yet. Create one with `mana db backup`.");
+                    }
+                    for backup in backups {
+                        let name = backup.path.file_name().unwrap_or_default().to_string_lossy();
+                        println!("{}  {:>10} bytes  {}", backup.modified.format("%Y-%m-%d %H:%M:%S"), backup.size, name);
+                    }
+                }
+                DbAction::Restore { backup, force } => {
+                    let source = storage::maintenance::resolve_backup(&mana_dir, backup.as_deref())?;
+                    let full = storage::full_backup::is_full(&source);
+                    if !force {
+                        if full {
+                            let manifest = storage::full_backup::verify(&mana_dir, &source)?;
+                            let names: Vec<&str> = manifest.files.iter().map(|f| f.name.as_str()).collect();
+                            println!("About to replace {} in {} with {}", names.join(", "), mana_dir.display(), source.display());
+                            println!(
+                                "The backup holds {} patterns and {} vectors from {}; the current state will be backed up first.",
+                                manifest.patterns, manifest.vectors, manifest.created_at
+                            );
+                        } else {
+                            println!("About to replace {} with {}", db_path.display(), source.display());
+                            println!("The current database will be backed up first.");
+                        }
+                        println!();
+                        println!("Use --force to confirm restore.");
+                        return Ok(());
+                    }
+
+                    // The daemon keeps its own connection open, which the lock doesn't cover
+                    if daemon::is_running() {
+                        return Err(ci::fail(
+                            ci::ExitCode::Busy,
+                            "The daemon has the database open; run 'mana daemon stop', restore, then 'mana daemon start'",
+                        ));
+                    }
+                    // Keep learners from writing while the file is swapped
+                    let _lock = learning::lock::acquire(&mana_dir, std::time::Duration::from_secs(10))?
+                        .ok_or_else(|| ci::fail(ci::ExitCode::Busy, "Learning is in progress; try again shortly"))?;
+                    let safety = if full {
+                        let safety = storage::full_backup::restore(&mana_dir, &source)?;
+                        let _ = std::fs::remove_file(mana_dir.join(storage::snapshot::SNAPSHOT_FILE));
+                        let _ = std::fs::remove_file(mana_dir.join(embeddings::meta::META_FILE));
+                        println!("Restored database, embedding index and state from {}", source.display());
+                        safety
+                    } else {
+                        let safety = storage::maintenance::restore(&mana_dir, &source)?;
+                        println!("Restored database from {}", source.display());
+                        safety
+                    };
+                    if let Some(safety) = safety {
+                        println!("Previous {} saved to {}", if full { "state" } else { "database" }, safety.display());
+                    }
+                }
+                DbAction::Encrypt { generate_key } => {
+                    use storage::encryption;
+
+                    encryption::require_sqlcipher()?;
+                    let key = if generate_key {
+                        if std::env::var_os(encryption::KEY_ENV).is_some() {
+                            anyhow::bail!("{} is set and would override a generated key; unset it first", encryption::KEY_ENV);
+                        }
+                        let key = encryption::generate_key();
+                        encryption::store_key(&mana_dir, &key)?;
+                        println!("Generated a key and stored it in the OS keychain");
+                        key
+                    } else {
+                        encryption::key(&mana_dir)?
+                    };
+
+                    let _lock = learning::lock::acquire(&mana_dir, std::time::Duration::from_secs(10))?
+                        .ok_or_else(|| ci::fail(ci::ExitCode::Busy, "Learning is in progress; try again shortly"))?;
+                    encryption::encrypt(&db_path, &key)?;
+                    config::set_key(&mana_dir, "storage.encrypt", "true")?;
+                    let _ = std::fs::remove_file(mana_dir.join(storage::snapshot::SNAPSHOT_FILE));
+                    println!("Encrypted {}", db_path.display());
+                    let backups = storage::maintenance::list_backups(&mana_dir)?;
+                    if !backups.is_empty() {
+                        println!(
+                            "⚠️  {} backup(s) in {} were written before and are not encrypted",
+                            backups.len(),
+                            mana_dir.join(storage::maintenance::BACKUP_DIR).display()
+                        );
+                    }
+                }
+                DbAction::Decrypt => {
+                    use storage::encryption;
+
+                    encryption::require_sqlcipher()?;
+                    let key = encryption::key(&mana_dir)?;
+                    let _lock = learning::lock::acquire(&mana_dir, std::time::Duration::from_secs(10))?
+                        .ok_or_else(|| ci::fail(ci::ExitCode::Busy, "Learning is in progress; try again shortly"))?;
+                    encryption::decrypt(&db_path, &key)?;
+                    config::set_key(&mana_dir, "storage.encrypt", "false")?;
+                    println!("Decrypted {}", db_path.display());
+                }
+            }
+        }
+        Commands::Config { action } => {
+            let mana_dir = get_mana_dir()?;
+
+            match action {
+                ConfigAction::Get { key } => {
+                    let config = config::load_config_strict(&mana_dir)?;
+                    match config::get_value(&config, &key)? {
+                        Some(value) if json => print_json(&value)?,
+                        Some(toml::Value::String(text)) => println!("{}", text),
+                        Some(value) => println!("{}", value),
+                        None => println!("(unset)"),
+                    }
+                }
+                ConfigAction::Set { key, value } => {
+                    config::set_key(&mana_dir, &key, &value)?;
+                    println!("Set {} = {}", key, value);
+                }
+                ConfigAction::Validate => {
+                    let config_path = mana_dir.join("config.toml");
+                    let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+
+                    let config = match config::load_config_strict(&mana_dir) {
+                        Ok(config) => config,
+                        Err(e) => {
+                            println!("❌ {}: {}", config_path.display(), e);
+                            ci::exit(ci::ExitCode::CheckFailed);
+                        }
+                    };
+                    for key in config::unknown_keys(&content)? {
+                        println!("⚠️  Unknown key {} (ignored)", key);
+                    }
+                    let problems = config.validate();
+                    for problem in &problems {
+                        println!("❌ {}", problem);
+                    }
+                    if !problems.is_empty() {
+                        ci::exit(ci::ExitCode::CheckFailed);
+                    }
+                    println!("✅ {} is valid", config_path.display());
+                }
+                ConfigAction::Show => {
+                    let config = config::load_config_strict(&mana_dir)?;
+                    if json {
+                        return print_json(&config);
+                    }
+                    print!("{}", toml::to_string_pretty(&config)?);
+                }
+            }
+        }
+        Commands::Doctor { offline } => {
+            let mana_dir = get_mana_dir()?;
+            let checks = doctor::run_checks(&mana_dir, offline).await;
+            if json {
+                print_json(&checks)?;
+            } else {
+                doctor::print_report(&checks);
+            }
+            if checks.iter().any(|c| c.status == doctor::CheckStatus::Fail) {
+                ci::exit(ci::ExitCode::CheckFailed);
+            }
+        }
+        Commands::Profile { action } => {
+            let root = profile::root_dir()?;
+
+            match action {
+                ProfileAction::List => {
+                    let profiles = profile::list(&root)?;
+                    if json {
+                        return print_json(&profiles);
+                    }
+                    for p in &profiles {
+                        let marker = if p.active { "*" } else { " " };
+                        println!("{} {:<16} {}", marker, p.name, p.path.display());
+                    }
+                    let project_mana = std::env::current_dir()?.join(".mana");
+                    if cli.profile.is_none() && std::env::var_os(profile::PROFILE_ENV).is_none() && project_mana.exists() {
+                        println!();
+                        println!("Note: project-local {} is in use in this directory", project_mana.display());
                     }
+                }
+                ProfileAction::Create { name } => {
+                    let dir = profile::create(&root, &name)?;
+                    storage::init_dir(&dir)?;
+                    println!("Created profile '{}' at {}", name, dir.display());
+                    println!("Use it with 'mana --profile {}' or 'mana profile switch {}'", name, name);
+                }
+                ProfileAction::Switch { name } => {
+                    let dir = profile::switch(&root, &name)?;
+                    println!("Switched to profile '{}' ({})", name, dir.display());
+                }
+            }
+        }
+        Commands::Telemetry { action } => {
+            let mana_dir = get_mana_dir()?;
+            let settings = config::load_config(&mana_dir).telemetry;
+            let report = || -> anyhow::Result<telemetry::Report> {
+                let conn = storage::db::open_readonly(&mana_dir.join("metadata.sqlite"))?;
+                telemetry::collect(&conn, &mana_dir)
+            };
 
-                    println!("✅ Pattern #{} deleted.", pattern_id);
+            match action {
+                TelemetryAction::Enable { endpoint } => {
+                    let endpoint = endpoint.or(settings.endpoint).ok_or_else(|| {
+                        anyhow::anyhow!("No endpoint configured; pass --endpoint URL")
+                    })?;
+                    config::set_key(&mana_dir, "telemetry.endpoint", &format!("{:?}", endpoint))?;
+                    config::set_key(&mana_dir, "telemetry.enabled", "true")?;
+                    println!("Telemetry enabled: a report every {}h to {}", settings.interval_hours, endpoint);
+                    println!("Reports are sent by the daemon and look like this:");
+                    println!();
+                    print_json(&report()?)?;
+                    println!();
+                    println!("Turn it off again with `mana telemetry disable`.");
+                }
+                TelemetryAction::Disable => {
+                    config::set_key(&mana_dir, "telemetry.enabled", "false")?;
+                    println!("Telemetry disabled");
+                }
+                TelemetryAction::Status => {
+                    let last = telemetry::last_sent(&mana_dir);
+                    if json {
+                        print_json(&serde_json::json!({
+                            "enabled": settings.enabled,
+                            "endpoint": settings.endpoint,
+                            "interval_hours": settings.interval_hours,
+                            "last_sent": last,
+                        }))?;
+                        return Ok(());
+                    }
+                    println!("Telemetry: {}", if settings.enabled { "enabled" } else { "disabled" });
+                    if let Some(endpoint) = &settings.endpoint {
+                        println!("Endpoint:  {} (every {}h)", endpoint, settings.interval_hours);
+                    }
+                    match last {
+                        Some(at) => println!("Last sent: {}", at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")),
+                        None => println!("Last sent: never"),
+                    }
+                }
+                TelemetryAction::Preview => print_json(&report()?)?,
+                TelemetryAction::Send => {
+                    let endpoint = settings
+                        .endpoint
+                        .filter(|_| settings.enabled)
+                        .ok_or_else(|| anyhow::anyhow!("Telemetry is disabled; enable it with `mana telemetry enable`"))?;
+                    telemetry::send(&mana_dir, &endpoint, &report()?)?;
+                    println!("Sent telemetry to {}", endpoint);
+                }
+            }
+        }
+        Commands::Registry { action } => {
+            let mana_dir = get_mana_dir()?;
+            let config = sync::load_sync_config(&mana_dir.join("sync.toml"))?;
+
+            match action {
+                RegistryAction::Search { query, index } => {
+                    let index = config.registry.index(index.as_deref())?;
+                    let registry = sync::registry::RegistryIndex::fetch(index)?;
+                    let found = registry.search(query.as_deref());
+                    if json {
+                        return print_json(&found);
+                    }
+                    if found.is_empty() {
+                        println!("No packs found.");
+                        return Ok(());
+                    }
+                    for entry in &found {
+                        let signed = if entry.signature.is_some() { "" } else { " (unsigned)" };
+                        println!("{:<20} {:<10} {:>3} patterns{}", entry.name, entry.version, entry.patterns, signed);
+                        if !entry.description.is_empty() {
+                            println!("{:21}{}", "", entry.description);
+                        }
+                    }
+                    println!();
+                    println!("Install with: mana registry install <name>[@version]");
+                }
+                RegistryAction::Install { pack, index, allow_unsigned } => {
+                    storage::ensure_schema(&mana_dir.join("metadata.sqlite"))?;
+                    let index = config.registry.index(index.as_deref())?;
+                    let require_signed = config.registry.require_signed && !allow_unsigned;
+                    let result = sync::registry::install(&mana_dir, index, &pack, &config.signing, require_signed)?;
+                    storage::snapshot::rebuild(&mana_dir, &storage::db::open(&mana_dir.join("metadata.sqlite"))?)?;
+                    if json {
+                        return print_json(&result);
+                    }
+                    if let Some(signer) = &result.signer {
+                        println!("🔏 Signature verified ({})", signer);
+                    }
+                    println!(
+                        "✅ Installed {} {}: {} new patterns, {} already present",
+                        result.pack.name, result.version, result.pack.added, result.pack.merged
+                    );
+                    if result.pack.risky > 0 {
+                        println!("⚠️  {} destructive patterns held back; review with: mana patterns risky", result.pack.risky);
+                    }
+                    println!("   List with: mana patterns list --tag {}", result.pack.tag);
+                    println!("   Remove with: mana patterns uninstall {}", result.pack.name);
+                }
+                RegistryAction::Publish { file, name, version, description, registry_dir, sign_key, no_sign } => {
+                    let key = (!no_sign).then(|| config.signing.signing_key(sign_key.as_deref()));
+                    let entry = sync::registry::publish(
+                        std::path::Path::new(&registry_dir),
+                        std::path::Path::new(&file),
+                        &name,
+                        &version,
+                        &description,
+                        key.as_deref(),
+                    )?;
+                    if json {
+                        return print_json(&entry);
+                    }
+                    println!("✅ Published {} {} ({} patterns) to {}", entry.name, entry.version, entry.patterns, registry_dir);
+                    if entry.signature.is_none() {
+                        println!("   Unsigned: installs will need --allow-unsigned");
+                    }
+                    println!("   Commit and push the registry to make it available.");
                 }
             }
         }
@@ -1540,18 +4136,336 @@ async fn run_async_main(cli: Cli) -> Result<()> {
     Ok(())
 }
 
+/// Resolve the MANA data directory (profile, project-local `.mana`, or `~/.mana`)
 pub fn get_mana_dir() -> Result<std::path::PathBuf> {
-    // Check for .mana directory in current project first
-    let cwd = std::env::current_dir()?;
-    let project_mana = cwd.join(".mana");
-    if project_mana.exists() {
-        return Ok(project_mana);
+    profile::resolve_mana_dir()
+}
+
+/// Name of a command that writes the database, index or state files
+///
+/// These fail up front in read-only mode. The session-end hook isn't one:
+/// it quietly does nothing instead.
+fn writing_command(command: &Commands) -> Option<&'static str> {
+    Some(match command {
+        Commands::Consolidate { .. } => "consolidate",
+        Commands::Watch { .. } => "watch",
+        Commands::ImportLogs { .. } => "import-logs",
+        Commands::Learn { dry_run: false, .. } => "learn",
+        Commands::Init { uninstall_hooks: false, .. } => "init",
+        Commands::Prune { dry_run: false, .. } => "prune",
+        Commands::Relearn { .. } => "relearn",
+        Commands::Bench { .. } => "bench",
+        Commands::Import { .. } => "import",
+        Commands::Embed { action: EmbedAction::Rebuild } => "embed rebuild",
+        Commands::Embed { action: EmbedAction::Generate } => "embed generate",
+        Commands::Reflect { action: ReflectAction::Run { .. } } => "reflect run",
+        Commands::Reflect { action: ReflectAction::Apply { .. } } => "reflect apply",
+        Commands::Reflect { action: ReflectAction::Init } => "reflect init",
+        Commands::Sync { action } => match action {
+            SyncAction::Init { .. } => "sync init",
+            SyncAction::Push { .. } => "sync push",
+            SyncAction::Pull { .. } => "sync pull",
+            SyncAction::Sync { .. } => "sync sync",
+            SyncAction::Listen { .. } => "sync listen",
+            SyncAction::SetKey => "sync set-key",
+            SyncAction::Peer { action: PeerAction::List } => return None,
+            SyncAction::Peer { .. } => "sync peer",
+            SyncAction::Status | SyncAction::TestAuth => return None,
+        },
+        Commands::Patterns { action } => match action {
+            PatternsAction::Browse { .. } => "patterns browse",
+            PatternsAction::Delete { .. } => "patterns delete",
+            PatternsAction::Archive { .. } => "patterns archive",
+            PatternsAction::Purge { force: true, .. } => "patterns purge",
+            PatternsAction::ApproveRisky { .. } => "patterns approve-risky",
+            PatternsAction::Reinstate { .. } => "patterns reinstate",
+            PatternsAction::Tag { .. } => "patterns tag",
+            PatternsAction::Install { .. } => "patterns install",
+            PatternsAction::Uninstall { .. } => "patterns uninstall",
+            PatternsAction::Rollback { .. } => "patterns rollback",
+            _ => return None,
+        },
+        Commands::Registry { action: RegistryAction::Install { .. } } => "registry install",
+        Commands::Telemetry { action: TelemetryAction::Send } => "telemetry send",
+        Commands::Daemon { action: DaemonAction::Start { .. } } => "daemon start",
+        Commands::Db { action } => match action {
+            DbAction::Migrate { dry_run: false } => "db migrate",
+            DbAction::Vacuum => "db vacuum",
+            DbAction::Checkpoint => "db checkpoint",
+            DbAction::Backup { .. } => "db backup",
+            DbAction::Restore { .. } => "db restore",
+            DbAction::Encrypt { .. } => "db encrypt",
+            DbAction::Decrypt => "db decrypt",
+            DbAction::Fsck { dry_run: false } => "db fsck",
+            DbAction::Migrate { dry_run: true }
+            | DbAction::Version
+            | DbAction::IntegrityCheck
+            | DbAction::Fsck { dry_run: true }
+            | DbAction::Backups => return None,
+        },
+        _ => return None,
+    })
+}
+
+/// Print a value as pretty JSON (for `--json`)
+/// Print `mana reflect experiment` output
+fn print_experiment(report: &reflection::ExperimentReport, control_fraction: f64) {
+    let heading = format!("Injection Experiment (since {})", report.since.format("%Y-%m-%d"));
+    println!("{}", heading);
+    println!("{}", "=".repeat(heading.chars().count()));
+    if control_fraction <= 0.0 {
+        println!();
+        println!("No control group configured. Set [injection] control_fraction (e.g. 0.1)");
+        println!("to hold out a share of sessions, then check back here.");
+    }
+    println!();
+    println!("{:<22} {:>10} {:>10}", "", "injected", "control");
+    let (i, c) = (&report.injected, &report.control);
+    println!("{:<22} {:>10} {:>10}", "Sessions", i.sessions, c.sessions);
+    println!("{:<22} {:>9.0}% {:>9.0}%", "Success rate", i.success_rate() * 100.0, c.success_rate() * 100.0);
+    println!("{:<22} {:>10.2} {:>10.2}", "Retries per session", i.retries_per_session(), c.retries_per_session());
+    println!("{:<22} {:>10.2} {:>10.2}", "Errors per session", i.errors_per_session(), c.errors_per_session());
+
+    let mut error_types: Vec<&String> = i.error_types.keys().chain(c.error_types.keys()).collect();
+    error_types.sort();
+    error_types.dedup();
+    if !error_types.is_empty() {
+        println!();
+        println!("Error types:");
+        for error in error_types {
+            println!(
+                "  {:<20} {:>10} {:>10}",
+                error,
+                i.error_types.get(error).copied().unwrap_or(0),
+                c.error_types.get(error).copied().unwrap_or(0)
+            );
+        }
+    }
+
+    println!();
+    let delta = report.success_rate_delta * 100.0;
+    match report.conclusion {
+        reflection::Conclusion::NotEnoughData => println!(
+            "Not enough sessions yet: each group needs at least {}.",
+            reflection::MIN_SESSIONS_PER_ARM
+        ),
+        reflection::Conclusion::Helps => println!(
+            "✅ Injection helps: {:+.0} points success rate (z = {:.2})",
+            delta,
+            report.z_score.unwrap_or_default()
+        ),
+        reflection::Conclusion::Hurts => println!(
+            "⚠️  Injection hurts: {:+.0} points success rate (z = {:.2})",
+            delta,
+            report.z_score.unwrap_or_default()
+        ),
+        reflection::Conclusion::NoDifference => println!(
+            "No measurable difference: {:+.0} points success rate (z = {:.2})",
+            delta,
+            report.z_score.unwrap_or_default()
+        ),
+    }
+}
+
+/// Print `mana sessions` output
+fn print_sessions(sessions: &[reflection::SessionSummary]) {
+    if sessions.is_empty() {
+        println!("No sessions found in the configured log directories.");
+        return;
+    }
+
+    println!("Recent Sessions");
+    println!("===============");
+    for session in sessions {
+        println!();
+        let ended = session
+            .ended_at
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "unknown time".to_string());
+        let duration = session
+            .duration_secs
+            .map(|secs| format!("  {}m{:02}s", secs / 60, secs % 60))
+            .unwrap_or_default();
+        let short_id: String = session.session_id.chars().take(8).collect();
+        println!("{}  {}{}", short_id, ended, duration);
+        if let Some(cwd) = &session.cwd {
+            println!("  Project: {}", cwd);
+        }
+        if !session.query.is_empty() {
+            println!("  Prompt:  {}", session.query);
+        }
+
+        let tools: Vec<String> = session.tools.iter().map(|(tool, n)| format!("{} ×{}", tool, n)).collect();
+        println!("  Tools:   {}", tools.join(", "));
+        let outcome = match session.success {
+            Some(true) => "success",
+            Some(false) => "failure",
+            None => "unjudged",
+        };
+        match session.success_ratio() {
+            Some(ratio) => println!(
+                "  Results: {}/{} ok ({:.0}%), outcome {}",
+                session.tool_results - session.tool_errors,
+                session.tool_results,
+                ratio * 100.0,
+                outcome
+            ),
+            None => println!("  Results: none recorded, outcome {}", outcome),
+        }
+
+        if session.injected.is_empty() {
+            println!("  Injected: nothing");
+        } else {
+            println!("  Injected: {} pattern(s)", session.injected.len());
+            for pattern in &session.injected {
+                println!(
+                    "    #{:<5} {:<6} {:.2}  {:<11} {}",
+                    pattern.id,
+                    pattern.tool_type.as_deref().unwrap_or("-"),
+                    pattern.score,
+                    pattern.verdict.as_deref().unwrap_or("unreflected"),
+                    pattern.context.as_deref().unwrap_or("(pattern since removed)")
+                );
+            }
+        }
+        if !session.verdicts.is_empty() {
+            let verdicts: Vec<String> = session.verdicts.iter().map(|(v, n)| format!("{} {}", n, v)).collect();
+            println!("  Verdicts: {}", verdicts.join(", "));
+        }
     }
+}
 
-    // Fall back to home directory
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".mana"))
+fn print_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Each log file a learning pass read
+fn print_parse_report(files: &[learning::trajectory::ParseStats]) {
+    if files.is_empty() {
+        println!("No new log data.");
+        return;
+    }
+    println!("{:>8} {:>6} {:>7}  File", "Lines", "Trajs", "Skipped");
+    for file in files {
+        println!("{:>8} {:>6} {:>7}  {}", file.lines, file.trajectories, file.skipped, file.path.display());
+        for error in &file.errors {
+            println!("{:>24}{}", "", error);
+        }
+    }
+    let total = |f: fn(&learning::trajectory::ParseStats) -> usize| files.iter().map(f).sum::<usize>();
+    println!(
+        "{:>8} {:>6} {:>7}  {} files",
+        total(|f| f.lines),
+        total(|f| f.trajectories),
+        total(|f| f.skipped),
+        files.len()
+    );
+    println!();
+}
+
+/// Differences between a relearn and the current patterns
+fn print_relearn_report(report: &learning::relearn::RelearnReport) {
+    let scope = &report.scope;
+    let mut scoped = Vec::new();
+    if let Some(project) = &scope.project {
+        scoped.push(format!("project {}", project));
+    }
+    if let Some(tool) = &scope.tool {
+        scoped.push(format!("tool {}", tool));
+    }
+    if let Some(since) = scope.since {
+        scoped.push(format!("since {}", since.format("%Y-%m-%d %H:%M")));
+    }
+    let scoped = if scoped.is_empty() { "all patterns".to_string() } else { scoped.join(", ") };
+    let verb = if report.applied { "Relearned" } else { "Staged relearn" };
+    println!("{} from {} trajectories ({})", verb, report.trajectories, scoped);
+    println!(
+        "  {} new, {} recounted, {} unchanged, {} removed, {} kept (synced, from a pack or edited)",
+        report.added.len(),
+        report.updated.len(),
+        report.unchanged,
+        report.removed.len(),
+        report.kept
+    );
+    if report.applied {
+        return;
+    }
+
+    const SHOWN: usize = 10;
+    let counts = |c: Option<learning::relearn::Counts>| c.map_or("-".to_string(), |c| format!("+{}/-{}", c.success, c.failure));
+    let summary = |c: &learning::relearn::Change| {
+        let line = c.context.lines().find(|l| l.starts_with("Approach:")).unwrap_or(&c.context);
+        line.chars().take(70).collect::<String>()
+    };
+    for (sign, changes) in [("+", &report.added), ("~", &report.updated), ("-", &report.removed)] {
+        for change in changes.iter().take(SHOWN) {
+            let id = change.id.map_or("new".to_string(), |id| format!("#{}", id));
+            println!("  {} {:<6} {} -> {}  {}", sign, id, counts(change.before), counts(change.after), summary(change));
+        }
+        if changes.len() > SHOWN {
+            println!("  {} ... and {} more", sign, changes.len() - SHOWN);
+        }
+    }
+    println!();
+    println!("Apply with: mana relearn --apply (--json lists every change)");
+}
+
+/// One finished consolidation stage, with what it changed
+fn print_consolidation_stage(
+    index: usize,
+    total: usize,
+    run: &learning::StageRun,
+    summary: &learning::ConsolidationSummary,
+    caps: &config::RetentionConfig,
+) {
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let cap = |value: u64, unit: &str| if value == 0 { "no cap".to_string() } else { format!("cap {}{}", value, unit) };
+    let detail = match run.stage {
+        learning::Stage::Dedupe => {
+            format!("merged {} patterns, cleaned {} causal edges", summary.merged, summary.edges_cleaned)
+        }
+        learning::Stage::Skills => format!("built {} skills", summary.skills),
+        learning::Stage::Decay => format!("decayed {}, pruned {} patterns", summary.decayed, summary.pruned),
+        learning::Stage::Retention => {
+            let evicted = summary.retention.as_ref().map_or(0, |r| r.evicted.len());
+            format!("evicted {} patterns ({})", evicted, cap(caps.max_patterns as u64, ""))
+        }
+        learning::Stage::Embed => match &summary.embedded {
+            Some(report) => format!("embedded {} new patterns, {} left", report.embedded, report.remaining),
+            None => "skipped (no embedding index, or auto_embed is off)".to_string(),
+        },
+        learning::Stage::Vacuum => {
+            let (before, after) = summary.vacuum.unwrap_or_default();
+            format!("{:.1} MB -> {:.1} MB", mb(before), mb(after))
+        }
+    };
+    println!("[{}/{}] {}: {} ({}ms)", index, total, run.stage.name(), detail, run.elapsed_ms);
+
+    let Some(retention) = summary.retention.as_ref().filter(|_| run.stage == learning::Stage::Retention) else {
+        return;
+    };
+    println!(
+        "    Deleted verdicts: {} ({})",
+        retention.verdicts_deleted,
+        cap(caps.max_verdict_age_days.into(), " days")
+    );
+    println!(
+        "    Deleted trajectory records: {} ({})",
+        retention.trajectories_deleted,
+        cap(caps.max_trajectory_age_days.into(), " days")
+    );
+    let vacuumed = if retention.vacuumed { ", vacuumed" } else { "" };
+    println!(
+        "    Database: {:.1} MB -> {:.1} MB ({}{})",
+        mb(retention.db_bytes_before),
+        mb(retention.db_bytes_after),
+        cap(caps.max_db_mb, " MB"),
+        vacuumed
+    );
+    if retention.over_size {
+        println!("    Still over the size cap: raise [retention] max_db_mb or run `mana prune`");
+    }
 }
 
 /// Format count with appropriate emoji for status display
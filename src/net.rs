@@ -0,0 +1,108 @@
+//! HTTP requests through the system curl
+//!
+//! MANA doesn't link an HTTP client; the judge, reranker, telemetry, pack
+//! registry and model asset downloads all shell out to `curl`. Every request
+//! is passed as a curl config on stdin so headers such as API keys never
+//! appear in the process list.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Attempts curl makes on transient failures of a GET
+const GET_RETRIES: u32 = 2;
+
+/// POST a JSON body and parse the JSON response
+pub fn post_json(url: &str, headers: &[(&str, &str)], body: &impl Serialize, timeout: Duration) -> Result<Value> {
+    let response = post(url, headers, body, timeout)?;
+    serde_json::from_slice(&response).with_context(|| format!("Unexpected response from {}", url))
+}
+
+/// POST a JSON body and return the raw response
+pub fn post(url: &str, headers: &[(&str, &str)], body: &impl Serialize, timeout: Duration) -> Result<Vec<u8>> {
+    let mut config = request_config(url, headers, Some(timeout));
+    config.push_str("header = \"content-type: application/json\"\n");
+    config.push_str(&format!("data-binary = \"{}\"\n", escape_config(&serde_json::to_string(body)?)));
+    run(&config)
+}
+
+/// GET a URL, following redirects
+pub fn get(url: &str, timeout: Duration) -> Result<Vec<u8>> {
+    let mut config = request_config(url, &[], Some(timeout));
+    config.push_str(&format!("location\nretry = {}\n", GET_RETRIES));
+    run(&config)
+}
+
+/// GET a URL into `dest`, following redirects, with no overall time limit
+pub fn download(url: &str, dest: &Path) -> Result<()> {
+    let mut config = request_config(url, &[], None);
+    config.push_str(&format!("location\nretry = {}\n", GET_RETRIES));
+    config.push_str(&format!("output = \"{}\"\n", escape_config(&dest.to_string_lossy())));
+    run(&config).map(|_| ())
+}
+
+fn request_config(url: &str, headers: &[(&str, &str)], timeout: Option<Duration>) -> String {
+    let mut config = format!("url = \"{}\"\nsilent\nshow-error\nfail\n", escape_config(url));
+    if let Some(timeout) = timeout {
+        config.push_str(&format!("max-time = {:.3}\n", timeout.as_secs_f64().max(0.001)));
+    }
+    for (name, value) in headers {
+        config.push_str(&format!("header = \"{}: {}\"\n", escape_config(name), escape_config(value)));
+    }
+    config
+}
+
+fn run(config: &str) -> Result<Vec<u8>> {
+    let mut child = Command::new("curl")
+        .args(["-K", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run curl ({}); install curl to reach remote endpoints", e))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("curl stdin unavailable"))?
+        .write_all(config.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("curl failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// Escape a value for a double-quoted curl config string
+fn escape_config(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_config_quotes_values_and_keeps_keys_off_the_command_line() {
+        let config = request_config(
+            "https://api.example.com/v1?q=\"x\"",
+            &[("x-api-key", "secret\nheader = \"evil: 1\"")],
+            Some(Duration::from_millis(1500)),
+        );
+        assert_eq!(
+            config,
+            "url = \"https://api.example.com/v1?q=\\\"x\\\"\"\nsilent\nshow-error\nfail\nmax-time = 1.500\n\
+             header = \"x-api-key: secret\\nheader = \\\"evil: 1\\\"\"\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Named profiles
+//!
+//! A profile is a separate MANA data directory with its own config.toml,
+//! database and sync.toml, stored under `~/.mana/profiles/<name>/`. The
+//! `default` profile is `~/.mana` itself.
+//!
+//! The data directory is resolved in this order:
+//! 1. `--profile <name>` or `MANA_PROFILE` (the flag sets the variable, so
+//!    hooks and spawned workers inherit it)
+//! 2. A project-local `.mana` directory in the working directory
+//! 3. The profile selected with `mana profile switch`
+//! 4. `~/.mana`
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Environment variable selecting a profile
+pub const PROFILE_ENV: &str = "MANA_PROFILE";
+
+/// Profile that maps to the root data directory
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Directory holding named profiles, relative to `~/.mana`
+const PROFILES_DIR: &str = "profiles";
+
+/// File in `~/.mana` recording the profile chosen with `mana profile switch`
+const ACTIVE_FILE: &str = "active-profile";
+
+/// One entry in `mana profile list`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub active: bool,
+}
+
+/// The root data directory (`~/.mana`) that holds all profiles
+pub fn root_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(home.join(".mana"))
+}
+
+/// Resolve the MANA data directory for this process
+pub fn resolve_mana_dir() -> Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    let explicit = std::env::var(PROFILE_ENV).ok().filter(|name| !name.is_empty());
+    resolve(&cwd, &root_dir()?, explicit.as_deref())
+}
+
+fn resolve(cwd: &Path, root: &Path, explicit: Option<&str>) -> Result<PathBuf> {
+    if let Some(name) = explicit {
+        validate_name(name)?;
+        return Ok(profile_dir(root, name));
+    }
+
+    let project_mana = cwd.join(".mana");
+    if project_mana.exists() {
+        return Ok(project_mana);
+    }
+
+    match switched_profile(root) {
+        Some(name) if validate_name(&name).is_ok() => Ok(profile_dir(root, &name)),
+        _ => Ok(root.to_path_buf()),
+    }
+}
+
+/// Data directory for a profile name
+pub fn profile_dir(root: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        root.to_path_buf()
+    } else {
+        root.join(PROFILES_DIR).join(name)
+    }
+}
+
+/// Profile names may only use letters, digits, '-' and '_'
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("Invalid profile name {:?}: use letters, digits, '-' or '_'", name);
+    }
+    Ok(())
+}
+
+/// Profile recorded by `mana profile switch`, if any
+fn switched_profile(root: &Path) -> Option<String> {
+    let name = std::fs::read_to_string(root.join(ACTIVE_FILE)).ok()?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Name of the profile in effect (flag/env first, then the switched profile)
+pub fn active_profile(root: &Path) -> String {
+    std::env::var(PROFILE_ENV)
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| switched_profile(root))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// All profiles, `default` first
+pub fn list(root: &Path) -> Result<Vec<ProfileInfo>> {
+    let active = active_profile(root);
+    let mut names = Vec::new();
+
+    let profiles = root.join(PROFILES_DIR);
+    if profiles.is_dir() {
+        for entry in std::fs::read_dir(&profiles)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+
+    Ok(names
+        .into_iter()
+        .map(|name| ProfileInfo { path: profile_dir(root, &name), active: name == active, name })
+        .collect())
+}
+
+/// Create an empty profile directory, failing if it already exists
+pub fn create(root: &Path, name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    if name == DEFAULT_PROFILE {
+        bail!("The '{}' profile always exists ({})", DEFAULT_PROFILE, root.display());
+    }
+
+    let dir = profile_dir(root, name);
+    if dir.exists() {
+        bail!("Profile '{}' already exists at {}", name, dir.display());
+    }
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Make `name` the profile used when no flag, env or project `.mana` applies
+pub fn switch(root: &Path, name: &str) -> Result<PathBuf> {
+    validate_name(name)?;
+    let dir = profile_dir(root, name);
+    let active_file = root.join(ACTIVE_FILE);
+
+    if name == DEFAULT_PROFILE {
+        if active_file.exists() {
+            std::fs::remove_file(&active_file)?;
+        }
+        return Ok(dir);
+    }
+
+    if !dir.is_dir() {
+        bail!("Profile '{}' does not exist. Create it with 'mana profile create {}'", name, name);
+    }
+    std::fs::create_dir_all(root)?;
+    std::fs::write(&active_file, format!("{}\n", name))?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_precedence() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("home/.mana");
+        let project = temp.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+
+        assert_eq!(resolve(&project, &root, None).unwrap(), root);
+
+        create(&root, "work").unwrap();
+        switch(&root, "work").unwrap();
+        assert_eq!(resolve(&project, &root, None).unwrap(), root.join("profiles/work"));
+
+        // A project-local .mana beats the switched profile, an explicit profile beats both
+        std::fs::create_dir_all(project.join(".mana")).unwrap();
+        assert_eq!(resolve(&project, &root, None).unwrap(), project.join(".mana"));
+        assert_eq!(resolve(&project, &root, Some("home")).unwrap(), root.join("profiles/home"));
+        assert_eq!(resolve(&project, &root, Some("default")).unwrap(), root);
+        assert!(resolve(&project, &root, Some("../etc")).is_err());
+    }
+
+    #[test]
+    fn test_create_list_switch() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join(".mana");
+
+        create(&root, "personal").unwrap();
+        assert!(create(&root, "personal").is_err());
+        assert!(create(&root, DEFAULT_PROFILE).is_err());
+        assert!(switch(&root, "missing").is_err());
+
+        switch(&root, "personal").unwrap();
+        assert_eq!(switched_profile(&root).as_deref(), Some("personal"));
+        let names: Vec<String> = list(&root).unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["default", "personal"]);
+
+        switch(&root, DEFAULT_PROFILE).unwrap();
+        assert_eq!(switched_profile(&root), None);
+    }
+}
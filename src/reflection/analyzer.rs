@@ -5,14 +5,20 @@
 
 use crate::learning::trajectory::Trajectory;
 use crate::storage::{PatternStore, calculate_similarity};
+use crate::storage::injection_log::{injected_in_window, is_control_session};
 #[allow(unused_imports)]
 use crate::storage::Pattern; // Used in find_matching_pattern return type inference
-use super::verdict::{ReflectionVerdict, compute_trajectory_hash};
+use super::improve::suggest_advice;
+use super::judge::LlmJudge;
+use super::verdict::{ReflectionVerdict, VerdictCategory, compute_trajectory_hash};
 #[allow(unused_imports)]
-use super::verdict::Verdict; // Used in judge() internal logic
+use super::verdict::Verdict; // Used in verdict_for() internal logic
 use std::path::Path;
 use tracing::debug;
 
+/// Slack around a trajectory's first/last message when joining injection records
+const INJECTION_WINDOW_SLACK_SECS: i64 = 60;
+
 /// Trajectory outcome analysis result
 #[derive(Debug, Clone)]
 pub struct TrajectoryOutcome {
@@ -200,6 +206,20 @@ impl ErrorType {
         None
     }
 
+    /// Short name for reports
+    pub fn label(&self) -> &str {
+        match self {
+            ErrorType::CompileError => "compile error",
+            ErrorType::RuntimeError => "runtime error",
+            ErrorType::FileNotFound => "file not found",
+            ErrorType::PermissionDenied => "permission denied",
+            ErrorType::Timeout => "timeout",
+            ErrorType::SyntaxError => "syntax error",
+            ErrorType::TestFailure => "test failure",
+            ErrorType::Other(description) => description,
+        }
+    }
+
     /// Get severity score (higher = more severe)
     pub fn severity(&self) -> i32 {
         match self {
@@ -223,6 +243,8 @@ pub struct TrajectoryAnalyzer {
     max_penalty: i32,
     /// Database path for pattern lookups
     db_path: Option<std::path::PathBuf>,
+    /// Optional LLM judge consulted before the heuristics
+    llm_judge: Option<LlmJudge>,
 }
 
 impl TrajectoryAnalyzer {
@@ -232,13 +254,14 @@ impl TrajectoryAnalyzer {
             max_boost: 5,
             max_penalty: -5,
             db_path: None,
+            llm_judge: None,
         }
     }
 
     /// Create with custom boost/penalty limits
     #[allow(dead_code)] // Exposed for external configuration
     pub fn with_limits(max_boost: i32, max_penalty: i32) -> Self {
-        Self { max_boost, max_penalty, db_path: None }
+        Self { max_boost, max_penalty, db_path: None, llm_judge: None }
     }
 
     /// Set the database path for pattern lookups
@@ -247,6 +270,12 @@ impl TrajectoryAnalyzer {
         self
     }
 
+    /// Consult an LLM judge, falling back to heuristics when it declines
+    pub fn with_llm_judge(mut self, judge: LlmJudge) -> Self {
+        self.llm_judge = Some(judge);
+        self
+    }
+
     /// Analyze a trajectory to determine its outcome
     pub fn analyze(&self, trajectory: &Trajectory) -> TrajectoryOutcome {
         // Count explicit errors (is_error=true) - these are real failures
@@ -422,10 +451,60 @@ impl TrajectoryAnalyzer {
             .any(|phrase| content.contains(phrase))
     }
 
-    /// Find the most relevant pattern for a trajectory
+    /// Patterns a trajectory's verdict should be attributed to
+    ///
+    /// Uses the patterns actually injected into the trajectory's session
+    /// (from `injection_log`, within the trajectory's time window) and only
+    /// falls back to similarity re-matching when nothing was logged.
+    /// Control-group sessions were shown nothing, so they get no patterns.
+    pub fn attribute_patterns(&self, trajectory: &Trajectory) -> Vec<i64> {
+        if self.is_control_session(trajectory) {
+            debug!("Session {} is in the control group; nothing to attribute", trajectory.session_id);
+            return Vec::new();
+        }
+        let injected = self.find_injected_patterns(trajectory);
+        if !injected.is_empty() {
+            debug!("Attributed {} injected patterns (session {})", injected.len(), trajectory.session_id);
+            return injected;
+        }
+        self.find_matching_pattern(trajectory).into_iter().collect()
+    }
+
+    /// Whether the session was held out of injection for the experiment
+    fn is_control_session(&self, trajectory: &Trajectory) -> bool {
+        let Some(db_path) = self.db_path.as_ref().filter(|p| p.exists()) else {
+            return false;
+        };
+        crate::storage::db::open_readonly(db_path)
+            .ok()
+            .and_then(|conn| is_control_session(&conn, &trajectory.session_id).ok())
+            .unwrap_or(false)
+    }
+
+    /// Pattern ids injected into this trajectory's session, best score first
+    fn find_injected_patterns(&self, trajectory: &Trajectory) -> Vec<i64> {
+        let Some(db_path) = self.db_path.as_ref().filter(|p| p.exists()) else {
+            return Vec::new();
+        };
+        let Ok(conn) = crate::storage::db::open_readonly(db_path) else {
+            return Vec::new();
+        };
+
+        let slack = chrono::Duration::seconds(INJECTION_WINDOW_SLACK_SECS);
+        let from = trajectory.started_at.map(|t| t - slack);
+        let to = trajectory.ended_at.map(|t| t + slack);
+
+        // A missing table (older database) just means nothing was logged
+        injected_in_window(&conn, &trajectory.session_id, from, to)
+            .map(|injected| injected.into_iter().map(|(id, _)| id).collect())
+            .unwrap_or_default()
+    }
+
+    /// Find the most relevant pattern for a trajectory by similarity
     ///
     /// This links trajectories to patterns that would have been injected
     /// by looking at the tool calls and matching against stored patterns.
+    /// Only used when the injection log has no record for the session.
     ///
     /// Strategy:
     /// 1. Try to find a strong semantic match (similarity > 0.30)
@@ -602,20 +681,57 @@ impl TrajectoryAnalyzer {
         tool_context
     }
 
-    /// Judge a trajectory and produce a verdict
-    pub fn judge(&self, outcome: &TrajectoryOutcome, trajectory: &Trajectory) -> Option<ReflectionVerdict> {
+    /// Judge a trajectory, producing one verdict per attributed pattern
+    ///
+    /// Every pattern injected during the trajectory shares its outcome. When
+    /// no pattern can be attributed a single unlinked verdict is returned.
+    pub fn judge(&self, outcome: &TrajectoryOutcome, trajectory: &Trajectory) -> Vec<ReflectionVerdict> {
         let trajectory_hash = compute_trajectory_hash(
             &trajectory.session_id,
             &trajectory.user_query,
             &trajectory.tool_calls,
         );
 
-        // Find matching pattern for this trajectory
-        let pattern_id = self.find_matching_pattern(trajectory);
+        let verdict = self
+            .llm_judge
+            .as_ref()
+            .and_then(|judge| judge.judge(&trajectory_hash, trajectory, outcome))
+            .unwrap_or_else(|| self.verdict_for(outcome));
+        let verdict = match verdict.category {
+            VerdictCategory::Harmful | VerdictCategory::Ineffective
+                if verdict.suggested_improvement.is_none() =>
+            {
+                match suggest_advice(outcome, trajectory) {
+                    Some(advice) => verdict.with_suggestion(advice),
+                    None => verdict,
+                }
+            }
+            _ => verdict,
+        };
+        let pattern_ids = self.attribute_patterns(trajectory);
 
+        debug!(
+            "Verdict for trajectory {}: {:?} (confidence: {:.2}, patterns: {:?})",
+            trajectory_hash,
+            verdict.category,
+            verdict.confidence,
+            pattern_ids
+        );
+
+        if pattern_ids.is_empty() {
+            return vec![ReflectionVerdict::new(trajectory_hash, None, verdict)];
+        }
+        pattern_ids
+            .into_iter()
+            .map(|id| ReflectionVerdict::new(trajectory_hash.clone(), Some(id), verdict.clone()))
+            .collect()
+    }
+
+    /// Determine the verdict for a trajectory outcome
+    fn verdict_for(&self, outcome: &TrajectoryOutcome) -> Verdict {
         // Determine verdict based on outcome
         // Be conservative with HARMFUL - only mark HARMFUL for clear failures with explicit errors
-        let verdict = if outcome.success && outcome.retry_count == 0 {
+        if outcome.success && outcome.retry_count == 0 {
             // Clean success - effective
             Verdict::effective(outcome.confidence, self.max_boost)
         } else if outcome.success && outcome.retry_count > 0 {
@@ -644,17 +760,7 @@ impl TrajectoryAnalyzer {
         } else {
             // Ambiguous - neutral
             Verdict::neutral()
-        };
-
-        debug!(
-            "Verdict for trajectory {}: {:?} (confidence: {:.2}, pattern: {:?})",
-            trajectory_hash,
-            verdict.category,
-            verdict.confidence,
-            pattern_id
-        );
-
-        Some(ReflectionVerdict::new(trajectory_hash, pattern_id, verdict))
+        }
     }
 
     /// Analyze root cause from error types
@@ -692,6 +798,7 @@ mod tests {
     use super::*;
     use crate::learning::trajectory::{ToolCall, ToolResult};
     use crate::reflection::VerdictCategory;
+    use rusqlite::Connection;
 
     fn make_trajectory(
         tool_calls: Vec<ToolCall>,
@@ -705,9 +812,56 @@ mod tests {
             tool_calls,
             tool_results,
             verdict: None,
+            started_at: None,
+            ended_at: None,
+            cwd: None,
         }
     }
 
+    #[test]
+    fn test_attribute_patterns_uses_injection_log_window() {
+        use crate::storage::injection_log::{insert_records, InjectionRecord};
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let mut conn = Connection::open(&db_path).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+
+        let now = chrono::Utc::now();
+        let mut stale = InjectionRecord::new("test", "Bash", &[(10, 1.0)]);
+        stale.timestamp = now - chrono::Duration::hours(2);
+        insert_records(&mut conn, &[
+            stale,
+            InjectionRecord::new("test", "Bash", &[(11, 0.4), (12, 0.9)]),
+            InjectionRecord::new("test", "Edit", &[(11, 0.7)]),
+            InjectionRecord::new("other", "Bash", &[(13, 1.0)]),
+        ]).unwrap();
+
+        let analyzer = TrajectoryAnalyzer::new().with_db_path(&db_path);
+        let mut trajectory = make_trajectory(
+            vec![ToolCall {
+                tool_name: "Bash".into(),
+                tool_input: serde_json::json!({"command": "cargo test"}),
+            }],
+            vec![],
+            "",
+        );
+        trajectory.started_at = Some(now - chrono::Duration::minutes(5));
+        trajectory.ended_at = Some(now);
+
+        // Stale injection from an earlier window and other sessions are excluded
+        assert_eq!(analyzer.attribute_patterns(&trajectory), vec![12, 11]);
+
+        let outcome = analyzer.analyze(&trajectory);
+        let verdicts = analyzer.judge(&outcome, &trajectory);
+        let ids: Vec<Option<i64>> = verdicts.iter().map(|v| v.pattern_id).collect();
+        assert_eq!(ids, vec![Some(12), Some(11)]);
+
+        // No log entries: falls back to similarity matching (no patterns stored here)
+        trajectory.session_id = "unlogged".into();
+        assert!(analyzer.attribute_patterns(&trajectory).is_empty());
+    }
+
     #[test]
     fn test_analyze_success() {
         let analyzer = TrajectoryAnalyzer::new();
@@ -787,7 +941,7 @@ mod tests {
         );
 
         let outcome = analyzer.analyze(&trajectory);
-        let verdict = analyzer.judge(&outcome, &trajectory).unwrap();
+        let verdict = analyzer.judge(&outcome, &trajectory).remove(0);
 
         assert_eq!(verdict.verdict.category, VerdictCategory::Effective);
         assert!(verdict.verdict.score_impact > 0);
@@ -816,10 +970,13 @@ mod tests {
         assert!(!outcome.success);
 
         // Verdict should be HARMFUL only if there are severe errors
-        let verdict = analyzer.judge(&outcome, &trajectory).unwrap();
+        let verdict = analyzer.judge(&outcome, &trajectory).remove(0);
         // Since is_error is true, it creates "explicit error" which has severity 1
         // So it may be INEFFECTIVE not HARMFUL
         assert!(verdict.verdict.score_impact <= 0);
+        // Failures carry replacement advice quoting the observed error
+        let suggestion = verdict.verdict.suggested_improvement.unwrap();
+        assert!(suggestion.contains("error[E0308]: mismatched types"));
     }
 
     #[test]
@@ -841,7 +998,7 @@ mod tests {
         );
 
         let outcome = analyzer.analyze(&trajectory);
-        let verdict = analyzer.judge(&outcome, &trajectory).unwrap();
+        let verdict = analyzer.judge(&outcome, &trajectory).remove(0);
 
         // Minor errors should be INEFFECTIVE, not HARMFUL
         assert!(verdict.verdict.score_impact <= 0);
@@ -0,0 +1,224 @@
+//! Session transcript annotation
+//!
+//! Produces an annotated copy of a Claude Code JSONL session log for review:
+//! every original line is kept verbatim, with extra lines inserted after each
+//! tool call that MANA injected context for, plus one verdict line per
+//! reflected trajectory at the end.
+//!
+//! Inserted lines use their own `type` values (`mana_injection`,
+//! `mana_verdict`) so tools that read Claude logs skip them.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::Path;
+
+use super::verdict::compute_trajectory_hash;
+use crate::learning::trajectory::parse_trajectories;
+use crate::storage::injection_log::{session_records, InjectionRecord};
+use crate::storage::{Pattern, PatternStore};
+
+/// Maximum gap between a logged tool call and its injection record
+const MATCH_WINDOW_SECS: i64 = 120;
+
+/// Characters of pattern context included in each annotation
+const CONTEXT_PREVIEW_CHARS: usize = 160;
+
+/// Counts reported after annotating a log
+#[derive(Debug, Default)]
+pub struct AnnotationSummary {
+    pub lines: usize,
+    pub sessions: usize,
+    pub tool_calls: usize,
+    pub injections_matched: usize,
+    pub injections_unmatched: usize,
+    pub verdicts: usize,
+}
+
+/// Annotate `input` and write the result to `output`
+pub fn annotate_session(input: &Path, output: &Path, db_path: &Path) -> Result<AnnotationSummary> {
+    let content = std::fs::read_to_string(input)
+        .with_context(|| format!("Failed to read {}", input.display()))?;
+    let conn = crate::storage::db::open_readonly(db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let store = PatternStore::open_readonly(db_path)?;
+
+    let mut summary = AnnotationSummary::default();
+    let mut injections: HashMap<String, VecDeque<InjectionRecord>> = HashMap::new();
+    let mut patterns: HashMap<i64, Option<Pattern>> = HashMap::new();
+    let mut out = std::io::BufWriter::new(std::fs::File::create(output)?);
+
+    for line in content.lines() {
+        writeln!(out, "{}", line)?;
+        summary.lines += 1;
+
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if msg.get("type").and_then(Value::as_str) != Some("assistant") {
+            continue;
+        }
+        let Some(session_id) = msg.get("sessionId").and_then(Value::as_str) else {
+            continue;
+        };
+        let timestamp = msg
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc));
+
+        let pending = injections.entry(session_id.to_string()).or_insert_with(|| {
+            summary.sessions += 1;
+            session_records(&conn, session_id).unwrap_or_default().into()
+        });
+
+        for (tool_use_id, tool_name) in tool_uses(&msg) {
+            summary.tool_calls += 1;
+            let Some(record) = take_injection(pending, &tool_name, timestamp) else {
+                continue;
+            };
+            summary.injections_matched += 1;
+
+            let entries: Vec<Value> = record
+                .injected()
+                .into_iter()
+                .map(|(id, score)| {
+                    let pattern = patterns
+                        .entry(id)
+                        .or_insert_with(|| store.get_by_id(id).ok().flatten());
+                    pattern_annotation(id, score, pattern.as_ref())
+                })
+                .collect();
+
+            writeln!(out, "{}", json!({
+                "type": "mana_injection",
+                "sessionId": session_id,
+                "toolUseId": tool_use_id,
+                "tool": tool_name,
+                "timestamp": record.timestamp.to_rfc3339(),
+                "patterns": entries,
+            }))?;
+        }
+    }
+
+    summary.injections_unmatched = injections.values().map(VecDeque::len).sum();
+
+    // Verdicts are keyed by trajectory hash, so rebuild trajectories the same way reflection does
+    for trajectory in parse_trajectories(input, 0)? {
+        let hash = compute_trajectory_hash(&trajectory.session_id, &trajectory.user_query, &trajectory.tool_calls);
+        let verdicts = verdicts_for_hash(&conn, &hash).unwrap_or_default();
+        if verdicts.is_empty() {
+            continue;
+        }
+        summary.verdicts += verdicts.len();
+
+        writeln!(out, "{}", json!({
+            "type": "mana_verdict",
+            "sessionId": trajectory.session_id,
+            "trajectoryHash": hash,
+            "verdicts": verdicts,
+        }))?;
+    }
+
+    out.flush()?;
+    Ok(summary)
+}
+
+/// (tool_use id, tool name) for each tool call in an assistant message
+fn tool_uses(msg: &Value) -> Vec<(String, String)> {
+    msg.pointer("/message/content")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter(|item| item.get("type").and_then(Value::as_str) == Some("tool_use"))
+                .filter_map(|item| {
+                    let name = item.get("name").and_then(Value::as_str)?;
+                    let id = item.get("id").and_then(Value::as_str).unwrap_or_default();
+                    Some((id.to_string(), name.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Take the first pending injection for this tool close enough in time
+///
+/// Records that are already too old to match any later call are dropped.
+fn take_injection(
+    pending: &mut VecDeque<InjectionRecord>,
+    tool: &str,
+    timestamp: Option<DateTime<Utc>>,
+) -> Option<InjectionRecord> {
+    let window = Duration::seconds(MATCH_WINDOW_SECS);
+    if let Some(ts) = timestamp {
+        while pending.front().is_some_and(|r| r.timestamp < ts - window) {
+            pending.pop_front();
+        }
+    }
+
+    let pos = pending.iter().position(|r| {
+        r.tool.eq_ignore_ascii_case(tool)
+            && timestamp.is_none_or(|ts| (r.timestamp - ts).abs() <= window)
+    })?;
+    pending.remove(pos)
+}
+
+fn pattern_annotation(id: i64, score: f64, pattern: Option<&Pattern>) -> Value {
+    match pattern {
+        Some(p) => json!({
+            "id": id,
+            "score": score,
+            "toolType": p.tool_type,
+            "context": p.context_query.chars().take(CONTEXT_PREVIEW_CHARS).collect::<String>(),
+        }),
+        // Pattern was pruned or merged since the injection
+        None => json!({ "id": id, "score": score, "missing": true }),
+    }
+}
+
+fn verdicts_for_hash(conn: &Connection, hash: &str) -> Result<Vec<Value>> {
+    let mut stmt = conn.prepare(
+        "SELECT pattern_id, verdict, confidence, root_cause, created_at
+         FROM reflection_verdicts WHERE trajectory_hash = ?1
+         ORDER BY created_at",
+    )?;
+    let rows = stmt.query_map(params![hash], |row| {
+        Ok(json!({
+            "patternId": row.get::<_, Option<i64>>(0)?,
+            "verdict": row.get::<_, String>(1)?,
+            "confidence": row.get::<_, f64>(2)?,
+            "rootCause": row.get::<_, Option<String>>(3)?,
+            "createdAt": row.get::<_, String>(4)?,
+        }))
+    })?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(tool: &str, secs: i64) -> InjectionRecord {
+        let mut record = InjectionRecord::new("s1", tool, &[(1, 0.5)]);
+        record.timestamp = DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        record
+    }
+
+    #[test]
+    fn test_take_injection_matches_tool_within_window() {
+        let mut pending: VecDeque<InjectionRecord> =
+            vec![record("Bash", 0), record("Edit", 5), record("Bash", 400)].into();
+        let at = |secs: i64| DateTime::from_timestamp(1_700_000_000 + secs, 0);
+
+        assert_eq!(take_injection(&mut pending, "Edit", at(3)).unwrap().tool, "Edit");
+        // Nothing for Bash near t=200; the stale t=0 record is discarded
+        assert!(take_injection(&mut pending, "Bash", at(200)).is_none());
+        assert_eq!(pending.len(), 1);
+        assert!(take_injection(&mut pending, "bash", at(390)).is_some());
+        assert!(pending.is_empty());
+    }
+}
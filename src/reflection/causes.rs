@@ -0,0 +1,268 @@
+//! Root-cause clustering
+//!
+//! Groups the free-text `root_cause` strings stored with reflection verdicts
+//! into recurring failure modes. Causes are normalized (paths, numbers and
+//! quoted identifiers replaced by placeholders) and then merged greedily when
+//! their token sets overlap enough, so "cannot find `Foo` in src/a.rs" and
+//! "cannot find `Bar` in src/b.rs" land in the same cluster.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::collections::{BTreeSet, HashMap};
+
+/// Minimum token-set overlap (Jaccard) for two normalized causes to merge
+const MERGE_SIMILARITY: f64 = 0.6;
+
+/// Example variants kept per cluster
+const MAX_EXAMPLES: usize = 3;
+
+/// A recurring failure mode
+#[derive(Debug, Clone)]
+pub struct RootCauseCluster {
+    /// Most frequent raw cause in the cluster
+    pub label: String,
+    /// Number of verdicts in the cluster
+    pub count: usize,
+    /// Of which HARMFUL
+    pub harmful: usize,
+    /// Distinct patterns the verdicts were attributed to
+    pub pattern_ids: Vec<i64>,
+    /// Other raw phrasings, most frequent first
+    pub examples: Vec<String>,
+    /// Most recent verdict timestamp
+    pub last_seen: String,
+}
+
+/// Verdict row with a root cause
+struct CauseRow {
+    cause: String,
+    verdict: String,
+    pattern_id: Option<i64>,
+    created_at: String,
+}
+
+/// Normalized cause with its member rows
+struct Group {
+    tokens: BTreeSet<String>,
+    rows: Vec<CauseRow>,
+}
+
+/// Cluster root causes, largest cluster first
+///
+/// `days` limits the analysis to recent verdicts.
+pub fn cluster_root_causes(conn: &Connection, days: Option<u32>) -> Result<Vec<RootCauseCluster>> {
+    let since = days
+        .map(|d| format!("-{} days", d))
+        .unwrap_or_else(|| "-100 years".to_string());
+    let mut stmt = conn.prepare(
+        "SELECT root_cause, verdict, pattern_id, created_at FROM reflection_verdicts
+         WHERE root_cause IS NOT NULL AND TRIM(root_cause) != ''
+           AND created_at >= datetime('now', ?1)",
+    )?;
+    let rows: Vec<CauseRow> = stmt
+        .query_map(params![since], |row| {
+            Ok(CauseRow {
+                cause: row.get(0)?,
+                verdict: row.get(1)?,
+                pattern_id: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(cluster_rows(rows))
+}
+
+fn cluster_rows(rows: Vec<CauseRow>) -> Vec<RootCauseCluster> {
+    // Exact normalized matches first
+    let mut by_key: HashMap<String, Vec<CauseRow>> = HashMap::new();
+    for row in rows {
+        by_key.entry(normalize_cause(&row.cause)).or_default().push(row);
+    }
+
+    let mut keyed: Vec<(String, Vec<CauseRow>)> = by_key.into_iter().collect();
+    keyed.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+
+    // Greedily fold smaller groups into the most similar larger one
+    let mut groups: Vec<Group> = Vec::new();
+    for (key, rows) in keyed {
+        let tokens: BTreeSet<String> = key.split_whitespace().map(String::from).collect();
+        let best = groups
+            .iter_mut()
+            .map(|g| (jaccard(&g.tokens, &tokens), g))
+            .filter(|(sim, _)| *sim >= MERGE_SIMILARITY)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match best {
+            Some((_, group)) => group.rows.extend(rows),
+            None => groups.push(Group { tokens, rows }),
+        }
+    }
+
+    let mut clusters: Vec<RootCauseCluster> = groups.into_iter().map(summarize).collect();
+    clusters.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| b.last_seen.cmp(&a.last_seen)));
+    clusters
+}
+
+fn summarize(group: Group) -> RootCauseCluster {
+    let mut phrasing: HashMap<&str, usize> = HashMap::new();
+    for row in &group.rows {
+        *phrasing.entry(row.cause.trim()).or_default() += 1;
+    }
+    let mut phrasings: Vec<(&str, usize)> = phrasing.into_iter().collect();
+    phrasings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let pattern_ids: BTreeSet<i64> = group.rows.iter().filter_map(|r| r.pattern_id).collect();
+
+    RootCauseCluster {
+        label: phrasings[0].0.to_string(),
+        count: group.rows.len(),
+        harmful: group.rows.iter().filter(|r| r.verdict == "HARMFUL").count(),
+        pattern_ids: pattern_ids.into_iter().collect(),
+        examples: phrasings
+            .iter()
+            .skip(1)
+            .take(MAX_EXAMPLES)
+            .map(|(p, _)| p.to_string())
+            .collect(),
+        last_seen: group.rows.iter().map(|r| r.created_at.clone()).max().unwrap_or_default(),
+    }
+}
+
+/// Normalize a cause so incidental details don't split clusters
+pub fn normalize_cause(cause: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_quote: Option<char> = None;
+
+    for raw in cause.split_whitespace() {
+        // Quoted spans (`x`, 'x', "x") collapse to a single placeholder
+        if let Some(q) = in_quote {
+            if raw.ends_with(q) {
+                in_quote = None;
+            }
+            continue;
+        }
+        if let Some(q) = raw.chars().next().filter(|c| matches!(c, '`' | '\'' | '"')) {
+            let closes = raw.len() > 1 && raw.ends_with(q);
+            if !closes {
+                in_quote = Some(q);
+            }
+            out.push("<q>".to_string());
+            continue;
+        }
+
+        let word = raw.trim_matches(|c: char| !c.is_alphanumeric() && c != '/' && c != '.' && c != '_');
+        if word.is_empty() {
+            continue;
+        }
+        let token = if word.contains('/') || (word.contains('.') && word.chars().any(|c| c.is_alphabetic())) {
+            "<path>".to_string()
+        } else if word.chars().any(|c| c.is_ascii_digit()) {
+            "<n>".to_string()
+        } else {
+            word.to_lowercase()
+        };
+        out.push(token);
+    }
+
+    out.join(" ")
+}
+
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Render clusters as a markdown section for a retro
+pub fn render_markdown(clusters: &[RootCauseCluster], limit: usize) -> String {
+    let total: usize = clusters.iter().map(|c| c.count).sum();
+    let mut md = String::from("## Recurring failure modes\n\n");
+    md.push_str(&format!(
+        "{} verdicts with a root cause, grouped into {} failure modes.\n\n",
+        total,
+        clusters.len()
+    ));
+    md.push_str("| # | Failure mode | Count | Harmful | Patterns |\n");
+    md.push_str("|---|---|---|---|---|\n");
+
+    for (i, cluster) in clusters.iter().take(limit).enumerate() {
+        let patterns = if cluster.pattern_ids.is_empty() {
+            "-".to_string()
+        } else {
+            cluster.pattern_ids.iter().map(|id| format!("#{}", id)).collect::<Vec<_>>().join(", ")
+        };
+        md.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            i + 1,
+            cluster.label.replace('|', "\\|"),
+            cluster.count,
+            cluster.harmful,
+            patterns
+        ));
+    }
+
+    let with_examples: Vec<&RootCauseCluster> =
+        clusters.iter().take(limit).filter(|c| !c.examples.is_empty()).collect();
+    if !with_examples.is_empty() {
+        md.push_str("\n### Variants\n\n");
+        for cluster in with_examples {
+            md.push_str(&format!("- **{}**\n", cluster.label));
+            for example in &cluster.examples {
+                md.push_str(&format!("  - {}\n", example));
+            }
+        }
+    }
+
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cause: &str, verdict: &str, pattern_id: Option<i64>) -> CauseRow {
+        CauseRow {
+            cause: cause.to_string(),
+            verdict: verdict.to_string(),
+            pattern_id,
+            created_at: "2026-01-01 00:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_replaces_incidental_details() {
+        assert_eq!(
+            normalize_cause("Cannot find `Foo` in src/main.rs line 12"),
+            "cannot find <q> in <path> line <n>"
+        );
+        assert_eq!(
+            normalize_cause("Cannot find `Bar baz` in lib.rs line 7"),
+            "cannot find <q> in <path> line <n>"
+        );
+    }
+
+    #[test]
+    fn test_cluster_groups_similar_causes() {
+        let clusters = cluster_rows(vec![
+            row("Compilation failed - check syntax and types", "HARMFUL", Some(1)),
+            row("Compilation failed - check syntax and types", "HARMFUL", Some(2)),
+            row("Compilation failed: check syntax and the types", "INEFFECTIVE", Some(2)),
+            row("Permission denied - check file permissions", "HARMFUL", Some(3)),
+        ]);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].label, "Compilation failed - check syntax and types");
+        assert_eq!(clusters[0].count, 3);
+        assert_eq!(clusters[0].harmful, 2);
+        assert_eq!(clusters[0].pattern_ids, vec![1, 2]);
+        assert_eq!(clusters[0].examples.len(), 1);
+
+        let md = render_markdown(&clusters, 10);
+        assert!(md.contains("| 1 | Compilation failed - check syntax and types | 3 | 2 | #1, #2 |"));
+        assert!(md.contains("| 2 | Permission denied - check file permissions | 1 | 1 | #3 |"));
+    }
+}
@@ -5,9 +5,12 @@
 
 use anyhow::Result;
 use rusqlite::{Connection, params};
+use serde::Serialize;
 use tracing::{debug, info};
 
+use super::verdict::VerdictCategory;
 use super::{ReflectionConfig, ReflectionVerdict};
+use crate::storage::patterns::quarantine_patterns;
 
 /// Memory distiller for applying verdicts to patterns
 pub struct MemoryDistiller {
@@ -64,6 +67,45 @@ impl MemoryDistiller {
         Ok(updated)
     }
 
+    /// Quarantine patterns that have collected enough confident HARMFUL verdicts
+    ///
+    /// Only patterns judged HARMFUL in `verdicts` are checked, and verdicts
+    /// from before a pattern was last reinstated don't count against it.
+    /// Call after [`distill`](Self::distill) has stored the verdicts.
+    /// Returns the ids of newly quarantined patterns.
+    pub fn quarantine(&self, conn: &Connection, verdicts: &[ReflectionVerdict]) -> Result<Vec<i64>> {
+        if self.config.quarantine_after == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut harmed: Vec<i64> = verdicts
+            .iter()
+            .filter(|v| v.verdict.category == VerdictCategory::Harmful)
+            .filter_map(|v| v.pattern_id)
+            .collect();
+        harmed.sort_unstable();
+        harmed.dedup();
+
+        let mut stmt = conn.prepare(
+            "SELECT COUNT(*) FROM reflection_verdicts v JOIN patterns p ON p.id = v.pattern_id
+             WHERE v.pattern_id = ?1 AND v.verdict = 'HARMFUL' AND v.confidence >= ?2
+               AND (p.status_changed_at IS NULL OR v.created_at > p.status_changed_at)",
+        )?;
+        let mut over_limit = Vec::new();
+        for id in harmed {
+            let count: i64 = stmt.query_row(params![id, self.config.quarantine_min_confidence], |row| row.get(0))?;
+            if count >= self.config.quarantine_after as i64 {
+                over_limit.push(id);
+            }
+        }
+
+        let quarantined = quarantine_patterns(conn, &over_limit)?;
+        for id in &quarantined {
+            info!("Quarantined pattern {} after repeated harmful verdicts", id);
+        }
+        Ok(quarantined)
+    }
+
     /// Calculate the score change for a verdict
     fn calculate_score_change(&self, verdict: &ReflectionVerdict) -> i32 {
         let base = verdict.score_impact();
@@ -181,14 +223,12 @@ pub struct VerdictSummary {
 }
 
 /// Aggregated verdict statistics
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct VerdictStats {
     pub total: i64,
     pub effective: i64,
-    #[allow(dead_code)] // Reserved for future use in expanded statistics display
     pub ineffective: i64,
     pub harmful: i64,
-    #[allow(dead_code)] // Reserved for future use in expanded statistics display
     pub neutral: i64,
     pub avg_confidence: f64,
 }
@@ -302,6 +342,36 @@ mod tests {
         assert!(failure > 2);
     }
 
+    #[test]
+    fn test_quarantine_after_confident_harmful_verdicts() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (1, 'h', 'Bash', 'rm -rf target')",
+            [],
+        )
+        .unwrap();
+        let distiller = MemoryDistiller::new(ReflectionConfig::default());
+        let harmful = |confidence| {
+            ReflectionVerdict::new("traj_hash".into(), Some(1), Verdict::harmful(confidence, -5, "broke it".into()))
+        };
+        let status = || -> String {
+            conn.query_row("SELECT status FROM patterns WHERE id = 1", [], |row| row.get(0)).unwrap()
+        };
+
+        // Low-confidence verdicts don't count
+        let weak = vec![harmful(0.9), harmful(0.9), harmful(0.5)];
+        distiller.distill(&conn, &weak).unwrap();
+        assert!(distiller.quarantine(&conn, &weak).unwrap().is_empty());
+        assert_eq!(status(), "active");
+
+        let strong = vec![harmful(0.9)];
+        distiller.distill(&conn, &strong).unwrap();
+        assert_eq!(distiller.quarantine(&conn, &strong).unwrap(), vec![1]);
+        assert_eq!(status(), "quarantined");
+        assert!(distiller.quarantine(&conn, &strong).unwrap().is_empty());
+    }
+
     #[test]
     fn test_verdict_stored() {
         let conn = setup_test_db();
@@ -0,0 +1,193 @@
+//! A/B evaluation of context injection
+//!
+//! With `[injection] control_fraction` set, that share of sessions is held
+//! out: patterns are still selected and logged (flagged `control`), but
+//! nothing is shown. Assignment is by session id, so a session stays in one
+//! arm for its whole length.
+//!
+//! `mana reflect experiment` then rebuilds the logged sessions of both arms
+//! from the Claude logs and compares success rates, retries and error types.
+//! Only sessions where injection had something to show count, in both arms,
+//! so the comparison isn't skewed by sessions MANA had nothing for.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use super::analyzer::TrajectoryAnalyzer;
+use crate::learning::collect_log_files;
+use crate::learning::trajectory::parse_trajectories;
+use crate::storage::injection_log::session_arms;
+
+/// Sessions each arm needs before a difference is reported
+pub const MIN_SESSIONS_PER_ARM: usize = 10;
+
+/// |z| above which the success-rate difference counts (95%, two-sided)
+const SIGNIFICANT_Z: f64 = 1.96;
+
+/// Whether a session belongs to the control group
+///
+/// Stable for a session id, so every injection in the session agrees.
+pub fn in_control_group(session_id: &str, fraction: f64) -> bool {
+    if fraction <= 0.0 {
+        return false;
+    }
+    let mut hasher = DefaultHasher::new();
+    "mana-experiment".hash(&mut hasher);
+    session_id.hash(&mut hasher);
+    let bucket = (hasher.finish() % 10_000) as f64 / 10_000.0;
+    bucket < fraction
+}
+
+/// Outcomes of one experiment arm
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArmStats {
+    pub sessions: usize,
+    pub successes: usize,
+    pub retries: usize,
+    pub tool_calls: usize,
+    pub errors: usize,
+    /// Error type -> occurrences
+    pub error_types: BTreeMap<String, usize>,
+}
+
+impl ArmStats {
+    pub fn success_rate(&self) -> f64 {
+        ratio(self.successes, self.sessions)
+    }
+
+    pub fn retries_per_session(&self) -> f64 {
+        ratio(self.retries, self.sessions)
+    }
+
+    pub fn errors_per_session(&self) -> f64 {
+        ratio(self.errors, self.sessions)
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// What the comparison shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Conclusion {
+    /// Fewer than [`MIN_SESSIONS_PER_ARM`] sessions in an arm
+    NotEnoughData,
+    /// Injected sessions succeed significantly more often
+    Helps,
+    /// Injected sessions succeed significantly less often
+    Hurts,
+    /// No significant difference in success rate
+    NoDifference,
+}
+
+/// Result of `mana reflect experiment`
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentReport {
+    pub since: DateTime<Utc>,
+    pub injected: ArmStats,
+    pub control: ArmStats,
+    /// Injected minus control success rate
+    pub success_rate_delta: f64,
+    /// Two-proportion z statistic of the success rates
+    pub z_score: Option<f64>,
+    pub conclusion: Conclusion,
+}
+
+/// Compare the injected and control arms over sessions logged since `since`
+pub fn experiment_report(mana_dir: &Path, db_path: &Path, since: DateTime<Utc>) -> Result<ExperimentReport> {
+    let conn = crate::storage::db::open_readonly(db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let arms = session_arms(&conn, since)?;
+
+    let analyzer = TrajectoryAnalyzer::new();
+    let (mut injected, mut control) = (ArmStats::default(), ArmStats::default());
+    if !arms.is_empty() {
+        for path in collect_log_files(mana_dir)? {
+            for trajectory in parse_trajectories(&path, 0).unwrap_or_default() {
+                let Some(&is_control) = arms.get(&trajectory.session_id) else {
+                    continue;
+                };
+                let outcome = analyzer.analyze(&trajectory);
+                let arm = if is_control { &mut control } else { &mut injected };
+                arm.sessions += 1;
+                arm.successes += usize::from(outcome.success);
+                arm.retries += outcome.retry_count;
+                arm.tool_calls += trajectory.tool_calls.len();
+                arm.errors += outcome.error_types.len();
+                for error in &outcome.error_types {
+                    *arm.error_types.entry(error.label().to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    let (z_score, conclusion) = compare(&injected, &control);
+    Ok(ExperimentReport {
+        since,
+        success_rate_delta: injected.success_rate() - control.success_rate(),
+        injected,
+        control,
+        z_score,
+        conclusion,
+    })
+}
+
+/// Two-proportion z-test on the arms' success rates
+fn compare(injected: &ArmStats, control: &ArmStats) -> (Option<f64>, Conclusion) {
+    if injected.sessions < MIN_SESSIONS_PER_ARM || control.sessions < MIN_SESSIONS_PER_ARM {
+        return (None, Conclusion::NotEnoughData);
+    }
+
+    let pooled = ratio(injected.successes + control.successes, injected.sessions + control.sessions);
+    let se = (pooled * (1.0 - pooled) * (1.0 / injected.sessions as f64 + 1.0 / control.sessions as f64)).sqrt();
+    if se == 0.0 {
+        // Every session in both arms had the same outcome
+        return (Some(0.0), Conclusion::NoDifference);
+    }
+
+    let z = (injected.success_rate() - control.success_rate()) / se;
+    let conclusion = if z >= SIGNIFICANT_Z {
+        Conclusion::Helps
+    } else if z <= -SIGNIFICANT_Z {
+        Conclusion::Hurts
+    } else {
+        Conclusion::NoDifference
+    };
+    (Some(z), conclusion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_assignment_is_stable_and_proportional() {
+        assert!(!in_control_group("s1", 0.0));
+        assert_eq!(in_control_group("s1", 0.3), in_control_group("s1", 0.3));
+
+        let held_out = (0..2000).filter(|i| in_control_group(&format!("session-{}", i), 0.25)).count();
+        assert!((400..600).contains(&held_out), "expected about 500 control sessions, got {}", held_out);
+    }
+
+    #[test]
+    fn test_compare_requires_data_and_significance() {
+        let arm = |sessions, successes| ArmStats { sessions, successes, ..Default::default() };
+
+        assert_eq!(compare(&arm(9, 9), &arm(50, 10)).1, Conclusion::NotEnoughData);
+        assert_eq!(compare(&arm(100, 80), &arm(100, 50)).1, Conclusion::Helps);
+        assert_eq!(compare(&arm(100, 50), &arm(100, 80)).1, Conclusion::Hurts);
+        assert_eq!(compare(&arm(40, 22), &arm(40, 20)).1, Conclusion::NoDifference);
+        assert_eq!(compare(&arm(20, 20), &arm(20, 20)), (Some(0.0), Conclusion::NoDifference));
+    }
+}
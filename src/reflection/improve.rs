@@ -0,0 +1,231 @@
+//! Suggested improvements
+//!
+//! Failed trajectories produce a replacement `Advice:` line for the patterns
+//! they were attributed to. Suggestions are stored with the verdict and only
+//! change pattern content when applied explicitly with `mana reflect apply`;
+//! every applied edit is recorded in `improvement_history`.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::analyzer::{ErrorType, TrajectoryOutcome};
+use crate::learning::trajectory::Trajectory;
+
+/// Maximum characters of the observed error quoted in a suggestion
+const ERROR_SNIPPET_CHARS: usize = 80;
+
+/// Create the improvement_history table
+pub fn create_history_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- Pattern edits applied from verdict suggestions
+        CREATE TABLE IF NOT EXISTS improvement_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            verdict_id INTEGER NOT NULL UNIQUE,
+            pattern_id INTEGER NOT NULL,
+            old_content TEXT NOT NULL,
+            new_content TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_improvement_pattern ON improvement_history(pattern_id);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Suggest replacement advice for a failed trajectory
+///
+/// Returns `None` for successful or ambiguous outcomes.
+pub fn suggest_advice(outcome: &TrajectoryOutcome, trajectory: &Trajectory) -> Option<String> {
+    if outcome.success {
+        return None;
+    }
+
+    let advice = match outcome.error_types.iter().max_by_key(|e| e.severity()) {
+        Some(ErrorType::CompileError) => "Type-check right after applying this; it has led to compile errors",
+        Some(ErrorType::SyntaxError) => "Re-read the edited region for balanced delimiters before saving",
+        Some(ErrorType::RuntimeError) => "Guard edge cases before running; this has hit runtime errors",
+        Some(ErrorType::TestFailure) => "Run the affected tests immediately after this change",
+        Some(ErrorType::FileNotFound) => "Confirm the path exists before relying on it",
+        Some(ErrorType::PermissionDenied) => "Check permissions and ownership before writing",
+        Some(ErrorType::Timeout) => "Narrow the scope or set a timeout; this has timed out",
+        Some(ErrorType::Other(_)) => "Verify the result of this step before continuing",
+        None if outcome.abandoned => "This approach was abandoned before; confirm it fits the task first",
+        None => return None,
+    };
+
+    Some(match first_error_line(trajectory) {
+        Some(error) => format!("{} (seen: {})", advice, error),
+        None => advice.to_string(),
+    })
+}
+
+/// First line of the first failing tool result
+fn first_error_line(trajectory: &Trajectory) -> Option<String> {
+    let result = trajectory
+        .tool_results
+        .iter()
+        .find(|r| r.is_error)
+        .or_else(|| trajectory.tool_results.iter().find(|r| ErrorType::from_content(&r.content).is_some()))?;
+
+    let line = result
+        .content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())?;
+    Some(line.chars().take(ERROR_SNIPPET_CHARS).collect())
+}
+
+/// Replace the pattern's Advice line, or append one
+pub fn rewrite_advice(content: &str, advice: &str) -> String {
+    let new_line = format!("Advice: {}", advice);
+    let mut replaced = false;
+
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| {
+            if !replaced && line.trim_start().starts_with("Advice:") {
+                replaced = true;
+                new_line.clone()
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+
+    if !replaced {
+        lines.push(new_line);
+    }
+    lines.join("\n")
+}
+
+/// Result of applying a suggestion
+#[derive(Debug)]
+pub struct AppliedImprovement {
+    pub pattern_id: i64,
+    pub old_content: String,
+    pub new_content: String,
+}
+
+/// Apply a verdict's suggestion to its pattern and record the edit
+///
+/// The caller is responsible for re-embedding the updated content.
+pub fn apply_improvement(conn: &mut Connection, verdict_id: i64) -> Result<AppliedImprovement> {
+    create_history_table(conn)?;
+
+    let row: Option<(Option<i64>, Option<String>)> = conn
+        .query_row(
+            "SELECT pattern_id, suggested_improvement FROM reflection_verdicts WHERE id = ?1",
+            params![verdict_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let Some((pattern_id, suggestion)) = row else {
+        bail!("Verdict #{} not found", verdict_id);
+    };
+    let Some(pattern_id) = pattern_id else {
+        bail!("Verdict #{} is not linked to a pattern", verdict_id);
+    };
+    let Some(suggestion) = suggestion.filter(|s| !s.trim().is_empty()) else {
+        bail!("Verdict #{} has no suggested improvement", verdict_id);
+    };
+
+    let applied: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM improvement_history WHERE verdict_id = ?1",
+        params![verdict_id],
+        |row| row.get(0),
+    )?;
+    if applied {
+        bail!("Verdict #{} has already been applied", verdict_id);
+    }
+
+    let old_content: String = conn
+        .query_row(
+            "SELECT context_query FROM patterns WHERE id = ?1",
+            params![pattern_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .with_context(|| format!("Pattern #{} no longer exists", pattern_id))?;
+    let new_content = rewrite_advice(&old_content, &suggestion);
+
+    let tx = crate::storage::db::write_transaction(conn)?;
+    tx.execute(
+        "UPDATE patterns SET context_query = ?1 WHERE id = ?2",
+        params![new_content, pattern_id],
+    )?;
+    tx.execute(
+        "INSERT INTO improvement_history (verdict_id, pattern_id, old_content, new_content)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![verdict_id, pattern_id, old_content, new_content],
+    )?;
+    tx.commit()?;
+
+    Ok(AppliedImprovement { pattern_id, old_content, new_content })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE patterns (
+                id INTEGER PRIMARY KEY,
+                context_query TEXT
+            );
+            INSERT INTO patterns (id, context_query)
+            VALUES (1, 'Task: build
+Pitfall: cargo build failed
+Advice: Verify this approach won''t hit the same error');
+            "#,
+        )
+        .unwrap();
+        super::super::init_reflection_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO reflection_verdicts (id, trajectory_hash, pattern_id, verdict, confidence, suggested_improvement)
+             VALUES (7, 'h', 1, 'HARMFUL', 0.9, 'Type-check right after applying this')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_rewrite_advice_replaces_or_appends() {
+        assert_eq!(
+            rewrite_advice("Task: x\nAdvice: old\nmore", "new"),
+            "Task: x\nAdvice: new\nmore"
+        );
+        assert_eq!(rewrite_advice("Task: x", "new"), "Task: x\nAdvice: new");
+    }
+
+    #[test]
+    fn test_apply_improvement_updates_pattern_once() {
+        let mut conn = setup_db();
+
+        let applied = apply_improvement(&mut conn, 7).unwrap();
+        assert_eq!(applied.pattern_id, 1);
+        assert!(applied.old_content.contains("won't hit the same error"));
+
+        let content: String = conn
+            .query_row("SELECT context_query FROM patterns WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(
+            content,
+            "Task: build\nPitfall: cargo build failed\nAdvice: Type-check right after applying this"
+        );
+
+        let history: i64 = conn
+            .query_row("SELECT COUNT(*) FROM improvement_history WHERE pattern_id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(history, 1);
+
+        assert!(apply_improvement(&mut conn, 7).is_err());
+        assert!(apply_improvement(&mut conn, 99).is_err());
+    }
+}
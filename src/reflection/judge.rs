@@ -0,0 +1,398 @@
+//! Optional LLM judge for reflection verdicts
+//!
+//! When `[reflection] judge` is set, trajectories are summarized and sent to
+//! Claude (Messages API) or a local Ollama server for a verdict with root
+//! cause text. Results are cached by trajectory hash in `judge_cache`, calls
+//! are rate limited per cycle, and any failure (no key, server down, bad
+//! response, limit reached) falls back to the heuristic analyzer.
+//!
+//! HTTP goes through `curl` so no TLS stack is linked into the binary.
+
+use anyhow::{anyhow, bail, Result};
+use rusqlite::params;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use super::analyzer::TrajectoryOutcome;
+use super::verdict::{Verdict, VerdictCategory};
+use crate::config::{JudgeKind, ReflectionSettings};
+use crate::learning::trajectory::Trajectory;
+
+const CLAUDE_API_URL: &str = "https://api.anthropic.com/v1/messages";
+const DEFAULT_CLAUDE_MODEL: &str = "claude-3-5-haiku-latest";
+const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+
+/// Characters kept from free-text fields in the trajectory summary
+const SUMMARY_FIELD_CHARS: usize = 400;
+
+/// Tool calls included in the trajectory summary
+const SUMMARY_MAX_TOOL_CALLS: usize = 12;
+
+/// Verdict as returned by the model
+#[derive(Debug, Deserialize)]
+struct JudgeResponse {
+    verdict: String,
+    confidence: Option<f32>,
+    root_cause: Option<String>,
+}
+
+/// LLM-backed trajectory judge
+pub struct LlmJudge {
+    kind: JudgeKind,
+    model: String,
+    endpoint: String,
+    api_key: Option<String>,
+    db_path: PathBuf,
+    max_calls: usize,
+    interval: Duration,
+    timeout: Duration,
+    max_boost: i32,
+    max_penalty: i32,
+    calls: Cell<usize>,
+    last_call: Cell<Option<Instant>>,
+}
+
+impl LlmJudge {
+    /// Build a judge from config, or None if disabled or unusable
+    pub fn from_settings(settings: &ReflectionSettings, db_path: &Path) -> Option<Self> {
+        let (model, endpoint, api_key) = match settings.judge {
+            JudgeKind::None => return None,
+            JudgeKind::Claude => {
+                let Ok(key) = std::env::var("ANTHROPIC_API_KEY") else {
+                    warn!("judge = \"claude\" but ANTHROPIC_API_KEY is not set; using heuristics");
+                    return None;
+                };
+                (DEFAULT_CLAUDE_MODEL, CLAUDE_API_URL.to_string(), Some(key))
+            }
+            JudgeKind::Ollama => (
+                DEFAULT_OLLAMA_MODEL,
+                format!("{}/api/generate", settings.ollama_url.trim_end_matches('/')),
+                None,
+            ),
+        };
+
+        Some(Self {
+            kind: settings.judge,
+            model: settings.judge_model.clone().unwrap_or_else(|| model.to_string()),
+            endpoint,
+            api_key,
+            db_path: db_path.to_path_buf(),
+            max_calls: settings.judge_max_calls,
+            interval: Duration::from_millis(settings.judge_interval_ms),
+            timeout: Duration::from_secs(settings.judge_timeout_secs),
+            max_boost: 5,
+            max_penalty: -5,
+            calls: Cell::new(0),
+            last_call: Cell::new(None),
+        })
+    }
+
+    /// Use the same boost/penalty limits as the heuristic analyzer
+    pub fn with_limits(mut self, max_boost: i32, max_penalty: i32) -> Self {
+        self.max_boost = max_boost;
+        self.max_penalty = max_penalty;
+        self
+    }
+
+    /// Judge a trajectory, returning None to fall back to heuristics
+    pub fn judge(&self, trajectory_hash: &str, trajectory: &Trajectory, outcome: &TrajectoryOutcome) -> Option<Verdict> {
+        if let Some(verdict) = self.cached(trajectory_hash) {
+            debug!("Judge cache hit for {}", trajectory_hash);
+            return Some(verdict);
+        }
+
+        if self.calls.get() >= self.max_calls {
+            debug!("Judge call limit ({}) reached, using heuristics", self.max_calls);
+            return None;
+        }
+        self.wait_for_slot();
+        self.calls.set(self.calls.get() + 1);
+
+        let prompt = build_prompt(trajectory, outcome);
+        let text = match self.complete(&prompt) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("LLM judge failed: {}, using heuristics", e);
+                return None;
+            }
+        };
+
+        let verdict = match parse_response(&text, self.max_boost, self.max_penalty) {
+            Some(v) => v,
+            None => {
+                warn!("LLM judge returned an unparseable verdict, using heuristics");
+                return None;
+            }
+        };
+
+        if let Err(e) = self.store(trajectory_hash, &verdict) {
+            debug!("Failed to cache judge verdict: {}", e);
+        }
+        Some(verdict)
+    }
+
+    /// Sleep until the minimum interval since the previous call has passed
+    fn wait_for_slot(&self) {
+        if let Some(last) = self.last_call.get() {
+            let elapsed = last.elapsed();
+            if elapsed < self.interval {
+                std::thread::sleep(self.interval - elapsed);
+            }
+        }
+        self.last_call.set(Some(Instant::now()));
+    }
+
+    fn complete(&self, prompt: &str) -> Result<String> {
+        match self.kind {
+            JudgeKind::Claude => {
+                let key = self.api_key.as_deref().ok_or_else(|| anyhow!("missing API key"))?;
+                let body = json!({
+                    "model": self.model,
+                    "max_tokens": 300,
+                    "messages": [{ "role": "user", "content": prompt }],
+                });
+                let response = crate::net::post_json(&self.endpoint, &[
+                    ("x-api-key", key),
+                    ("anthropic-version", "2023-06-01"),
+                ], &body, self.timeout)?;
+                response
+                    .pointer("/content/0/text")
+                    .and_then(Value::as_str)
+                    .map(String::from)
+                    .ok_or_else(|| anyhow!("unexpected Claude response shape"))
+            }
+            JudgeKind::Ollama => {
+                let body = json!({
+                    "model": self.model,
+                    "prompt": prompt,
+                    "stream": false,
+                    "format": "json",
+                });
+                let response = crate::net::post_json(&self.endpoint, &[], &body, self.timeout)?;
+                response
+                    .get("response")
+                    .and_then(Value::as_str)
+                    .map(String::from)
+                    .ok_or_else(|| anyhow!("unexpected Ollama response shape"))
+            }
+            JudgeKind::None => bail!("judge disabled"),
+        }
+    }
+
+    fn cached(&self, trajectory_hash: &str) -> Option<Verdict> {
+        let conn = crate::storage::db::open(&self.db_path).ok()?;
+        let (verdict, confidence, root_cause): (String, f32, Option<String>) = conn
+            .query_row(
+                "SELECT verdict, confidence, root_cause FROM judge_cache
+                 WHERE trajectory_hash = ?1 AND judge = ?2",
+                params![trajectory_hash, self.cache_key()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .ok()?;
+        let category = VerdictCategory::from_str(&verdict)?;
+        Some(make_verdict(category, confidence, root_cause, self.max_boost, self.max_penalty))
+    }
+
+    fn store(&self, trajectory_hash: &str, verdict: &Verdict) -> Result<()> {
+        let conn = crate::storage::db::open(&self.db_path)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO judge_cache (trajectory_hash, judge, verdict, confidence, root_cause)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                trajectory_hash,
+                self.cache_key(),
+                verdict.category.as_str(),
+                verdict.confidence,
+                verdict.root_cause,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Cache entries are per judge and model so switching models re-judges
+    fn cache_key(&self) -> String {
+        let kind = match self.kind {
+            JudgeKind::Claude => "claude",
+            JudgeKind::Ollama => "ollama",
+            JudgeKind::None => "none",
+        };
+        format!("{}:{}", kind, self.model)
+    }
+}
+
+/// Summarize a trajectory into a judging prompt
+fn build_prompt(trajectory: &Trajectory, outcome: &TrajectoryOutcome) -> String {
+    let mut summary = String::new();
+    summary.push_str(&format!("User request: {}\n\n", clip(&trajectory.user_query)));
+
+    summary.push_str("Tool calls:\n");
+    for call in trajectory.tool_calls.iter().take(SUMMARY_MAX_TOOL_CALLS) {
+        summary.push_str(&format!("- {} {}\n", call.tool_name, clip(&call.tool_input.to_string())));
+    }
+    if trajectory.tool_calls.len() > SUMMARY_MAX_TOOL_CALLS {
+        summary.push_str(&format!("- ... {} more\n", trajectory.tool_calls.len() - SUMMARY_MAX_TOOL_CALLS));
+    }
+
+    let errors: Vec<&str> = trajectory
+        .tool_results
+        .iter()
+        .filter(|r| r.is_error)
+        .map(|r| r.content.as_str())
+        .collect();
+    if !errors.is_empty() {
+        summary.push_str("\nTool errors:\n");
+        for error in errors.iter().take(5) {
+            summary.push_str(&format!("- {}\n", clip(error)));
+        }
+    }
+
+    summary.push_str(&format!(
+        "\nHeuristic signals: retries={}, abandoned={}\n",
+        outcome.retry_count, outcome.abandoned
+    ));
+    summary.push_str(&format!("\nFinal assistant text: {}\n", clip(last_chars(&trajectory.assistant_content))));
+
+    format!(
+        "You are reviewing a coding-assistant session to judge whether the guidance it \
+         followed helped. Classify the session as EFFECTIVE (task accomplished cleanly), \
+         NEUTRAL (unclear), INEFFECTIVE (did not help), or HARMFUL (caused errors or wasted effort).\n\n\
+         {}\n\
+         Respond with only a JSON object: \
+         {{\"verdict\": \"EFFECTIVE|NEUTRAL|INEFFECTIVE|HARMFUL\", \"confidence\": 0.0-1.0, \
+         \"root_cause\": \"one sentence on why, especially for failures\"}}",
+        summary
+    )
+}
+
+/// Parse the model's JSON verdict, tolerating surrounding prose
+fn parse_response(text: &str, max_boost: i32, max_penalty: i32) -> Option<Verdict> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    let parsed: JudgeResponse = serde_json::from_str(text.get(start..=end)?).ok()?;
+
+    let category = VerdictCategory::from_str(parsed.verdict.trim())?;
+    let confidence = parsed.confidence.unwrap_or(0.7).clamp(0.0, 1.0);
+    let root_cause = parsed.root_cause.filter(|r| !r.trim().is_empty());
+    Some(make_verdict(category, confidence, root_cause, max_boost, max_penalty))
+}
+
+fn make_verdict(
+    category: VerdictCategory,
+    confidence: f32,
+    root_cause: Option<String>,
+    max_boost: i32,
+    max_penalty: i32,
+) -> Verdict {
+    let mut verdict = match category {
+        VerdictCategory::Effective => Verdict::effective(confidence, max_boost),
+        VerdictCategory::Neutral => Verdict::neutral(),
+        VerdictCategory::Ineffective => Verdict::ineffective(confidence),
+        VerdictCategory::Harmful => {
+            Verdict::harmful(confidence, max_penalty, root_cause.clone().unwrap_or_default())
+        }
+    };
+    verdict.confidence = confidence;
+    verdict.root_cause = root_cause;
+    verdict
+}
+
+fn clip(text: &str) -> String {
+    let clipped: String = text.chars().take(SUMMARY_FIELD_CHARS).collect();
+    if clipped.len() < text.len() {
+        format!("{}...", clipped)
+    } else {
+        clipped
+    }
+}
+
+/// The tail of a string, where the assistant's conclusion usually is
+fn last_chars(text: &str) -> &str {
+    let count = text.chars().count();
+    if count <= SUMMARY_FIELD_CHARS {
+        return text;
+    }
+    let skip = text
+        .char_indices()
+        .nth(count - SUMMARY_FIELD_CHARS)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    &text[skip..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn settings(judge: JudgeKind) -> ReflectionSettings {
+        ReflectionSettings {
+            judge,
+            // Nothing listens here, so any real call fails fast
+            ollama_url: "http://127.0.0.1:9".to_string(),
+            judge_interval_ms: 0,
+            judge_timeout_secs: 1,
+            ..Default::default()
+        }
+    }
+
+    fn trajectory() -> Trajectory {
+        Trajectory {
+            session_id: "s".into(),
+            user_query: "fix the build".into(),
+            assistant_content: "done".into(),
+            tool_calls: vec![],
+            tool_results: vec![],
+            verdict: None,
+            started_at: None,
+            ended_at: None,
+            cwd: None,
+        }
+    }
+
+    fn outcome() -> TrajectoryOutcome {
+        TrajectoryOutcome {
+            success: true,
+            retry_count: 0,
+            error_types: vec![],
+            duration_ms: 0,
+            abandoned: false,
+            confidence: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_parse_response_tolerates_prose() {
+        let text = "Sure! {\"verdict\": \"harmful\", \"confidence\": 0.9, \"root_cause\": \"ran the wrong migration\"} hope that helps";
+        let verdict = parse_response(text, 5, -5).unwrap();
+        assert_eq!(verdict.category, VerdictCategory::Harmful);
+        assert_eq!(verdict.root_cause.as_deref(), Some("ran the wrong migration"));
+        assert!(verdict.score_impact < 0);
+
+        assert!(parse_response("no json here", 5, -5).is_none());
+        assert!(parse_response("{\"verdict\": \"GREAT\"}", 5, -5).is_none());
+    }
+
+    #[test]
+    fn test_cache_hit_skips_call_and_limit_falls_back() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        super::super::init_reflection_tables(&rusqlite::Connection::open(&db_path).unwrap()).unwrap();
+
+        assert!(LlmJudge::from_settings(&settings(JudgeKind::None), &db_path).is_none());
+
+        let judge = LlmJudge::from_settings(&settings(JudgeKind::Ollama), &db_path).unwrap();
+        judge.store("abc", &Verdict::effective(0.9, 5)).unwrap();
+
+        let cached = judge.judge("abc", &trajectory(), &outcome()).unwrap();
+        assert_eq!(cached.category, VerdictCategory::Effective);
+        assert_eq!(judge.calls.get(), 0);
+
+        // Uncached with no calls left falls back without touching the network
+        let limited = LlmJudge { max_calls: 0, ..judge };
+        assert!(limited.judge("def", &trajectory(), &outcome()).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Pattern effectiveness leaderboard and regression detection
+//!
+//! Splits each pattern's reflection verdicts into a recent window and
+//! everything before it. The leaderboard ranks patterns by how effective
+//! their recent verdicts were; regressions are patterns with a good lifetime
+//! record (positive score, or mostly effective earlier verdicts) whose recent
+//! verdicts turned mostly ineffective or harmful.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::distillation::VerdictStats;
+
+/// Share of recent negative verdicts that makes a pattern a regression
+const REGRESSION_NEGATIVE_RATIO: f64 = 0.5;
+
+/// A pattern's verdicts, split at the start of the recent window
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternTrend {
+    pub id: i64,
+    pub tool_type: String,
+    pub context_query: String,
+    /// Lifetime score (successes minus failures)
+    pub score: i64,
+    /// Verdicts before the window
+    pub earlier: VerdictStats,
+    /// Verdicts within the window
+    pub recent: VerdictStats,
+}
+
+impl PatternTrend {
+    /// Share of recent verdicts that were ineffective or harmful
+    pub fn recent_negative_ratio(&self) -> f64 {
+        if self.recent.total == 0 {
+            return 0.0;
+        }
+        (self.recent.ineffective + self.recent.harmful) as f64 / self.recent.total as f64
+    }
+
+    /// Whether the pattern looked good before the window
+    fn had_good_record(&self) -> bool {
+        self.score > 0 || (self.earlier.total > 0 && self.earlier.effectiveness_ratio() >= 0.5)
+    }
+
+    /// Whether recent verdicts contradict a good record
+    pub fn is_regression(&self, min_verdicts: usize) -> bool {
+        self.recent.total >= min_verdicts as i64
+            && self.had_good_record()
+            && self.recent_negative_ratio() >= REGRESSION_NEGATIVE_RATIO
+    }
+}
+
+/// Verdict trends for every pattern with verdicts, split `days` back
+pub fn pattern_trends(conn: &Connection, days: u32) -> Result<Vec<PatternTrend>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.tool_type, p.context_query, p.success_count - p.failure_count,
+                v.verdict, v.confidence, v.created_at >= datetime('now', ?1)
+         FROM reflection_verdicts v JOIN patterns p ON p.id = v.pattern_id
+         ORDER BY p.id",
+    )?;
+    let rows = stmt.query_map(params![format!("-{} days", days)], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, f64>(5)?,
+            row.get::<_, bool>(6)?,
+        ))
+    })?;
+
+    let mut trends: BTreeMap<i64, PatternTrend> = BTreeMap::new();
+    for row in rows {
+        let (id, tool_type, context_query, score, verdict, confidence, recent) = row?;
+        let trend = trends.entry(id).or_insert_with(|| PatternTrend {
+            id,
+            tool_type,
+            context_query,
+            score,
+            earlier: VerdictStats::default(),
+            recent: VerdictStats::default(),
+        });
+        let stats = if recent { &mut trend.recent } else { &mut trend.earlier };
+        stats.avg_confidence = (stats.avg_confidence * stats.total as f64 + confidence) / (stats.total + 1) as f64;
+        stats.total += 1;
+        match verdict.as_str() {
+            "EFFECTIVE" => stats.effective += 1,
+            "INEFFECTIVE" => stats.ineffective += 1,
+            "HARMFUL" => stats.harmful += 1,
+            _ => stats.neutral += 1,
+        }
+    }
+    Ok(trends.into_values().collect())
+}
+
+/// Patterns with at least `min_verdicts` recent verdicts, most effective first
+pub fn leaderboard(conn: &Connection, days: u32, min_verdicts: usize, limit: usize) -> Result<Vec<PatternTrend>> {
+    let mut ranked: Vec<PatternTrend> = pattern_trends(conn, days)?
+        .into_iter()
+        .filter(|t| t.recent.total >= min_verdicts as i64)
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.recent
+            .effectiveness_ratio()
+            .total_cmp(&a.recent.effectiveness_ratio())
+            .then(b.recent.effective.cmp(&a.recent.effective))
+            .then(b.score.cmp(&a.score))
+    });
+    ranked.truncate(limit);
+    Ok(ranked)
+}
+
+/// Patterns whose recent verdicts contradict their record, worst first
+pub fn regressions(conn: &Connection, days: u32, min_verdicts: usize) -> Result<Vec<PatternTrend>> {
+    let mut found: Vec<PatternTrend> = pattern_trends(conn, days)?
+        .into_iter()
+        .filter(|t| t.is_regression(min_verdicts))
+        .collect();
+    found.sort_by(|a, b| {
+        b.recent
+            .harm_ratio()
+            .total_cmp(&a.recent.harm_ratio())
+            .then(b.recent_negative_ratio().total_cmp(&a.recent_negative_ratio()))
+            .then(b.score.cmp(&a.score))
+    });
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regressions_and_leaderboard() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'was good', 8, 1),
+                    (2, 'b', 'Bash', 'still good', 5, 0),
+                    (3, 'c', 'Edit', 'always bad', 0, 4);",
+        )
+        .unwrap();
+        let verdict = |pattern: i64, verdict: &str, age_days: u32| {
+            conn.execute(
+                "INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence, created_at)
+                 VALUES ('t', ?1, ?2, 0.8, datetime('now', ?3))",
+                params![pattern, verdict, format!("-{} days", age_days)],
+            )
+            .unwrap();
+        };
+        for _ in 0..3 {
+            verdict(1, "EFFECTIVE", 30);
+            verdict(1, "HARMFUL", 1);
+            verdict(2, "EFFECTIVE", 1);
+            verdict(3, "INEFFECTIVE", 1);
+        }
+        verdict(2, "NEUTRAL", 2);
+
+        let found = regressions(&conn, 7, 3).unwrap();
+        assert_eq!(found.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!((found[0].earlier.effective, found[0].recent.harmful), (3, 3));
+        assert!(regressions(&conn, 7, 4).unwrap().is_empty());
+
+        let top = leaderboard(&conn, 7, 3, 10).unwrap();
+        assert_eq!(top.iter().map(|t| t.id).collect::<Vec<_>>(), vec![2, 1, 3]);
+        assert_eq!(top[0].recent.total, 4);
+    }
+}
@@ -18,6 +18,14 @@
 mod verdict;
 mod analyzer;
 mod distillation;
+mod annotate;
+mod sessions;
+mod experiment;
+mod leaderboard;
+mod judge;
+mod causes;
+mod improve;
+mod offsets;
 
 pub use verdict::ReflectionVerdict;
 // VerdictCategory and Verdict are used internally; public for future extensions
@@ -28,6 +36,14 @@ pub use analyzer::TrajectoryAnalyzer;
 #[allow(unused_imports)]
 pub use analyzer::TrajectoryOutcome;
 pub use distillation::MemoryDistiller;
+pub use annotate::annotate_session;
+pub use sessions::{recent_sessions, SessionSummary};
+pub use leaderboard::{leaderboard, regressions, PatternTrend};
+pub use experiment::{experiment_report, in_control_group, Conclusion, ExperimentReport, MIN_SESSIONS_PER_ARM};
+pub use judge::LlmJudge;
+pub use improve::apply_improvement;
+pub use offsets::{collect_pending, parse_since, ScanMode};
+pub use causes::{cluster_root_causes, render_markdown as render_causes_markdown};
 // VerdictSummary and VerdictStats are used in main.rs analyze command
 #[allow(unused_imports)]
 pub use distillation::{VerdictSummary, VerdictStats};
@@ -36,7 +52,7 @@ use anyhow::Result;
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use tracing::{debug, info};
+use tracing::{debug, debug_span, info, instrument};
 
 /// Reflection engine state
 #[allow(dead_code)] // Reserved for daemon mode state tracking
@@ -67,11 +83,29 @@ pub struct ReflectionConfig {
     pub max_penalty: i32,
     /// Maximum boost for EFFECTIVE verdicts
     pub max_boost: i32,
+    /// Confident HARMFUL verdicts before a pattern is quarantined (0 disables)
+    pub quarantine_after: u32,
+    /// Minimum confidence for a HARMFUL verdict to count toward quarantine
+    pub quarantine_min_confidence: f32,
     /// Enable failure root cause analysis
     #[allow(dead_code)] // Reserved for future root cause toggle
     pub analyze_failures: bool,
 }
 
+impl ReflectionConfig {
+    /// Scoring limits from `[reflection]` in config.toml
+    pub fn from_settings(settings: &crate::config::ReflectionSettings) -> Self {
+        Self {
+            min_confidence: settings.min_confidence as f32,
+            max_penalty: settings.max_penalty,
+            max_boost: settings.max_boost,
+            quarantine_after: settings.quarantine_after,
+            quarantine_min_confidence: settings.quarantine_min_confidence as f32,
+            ..Self::default()
+        }
+    }
+}
+
 impl Default for ReflectionConfig {
     fn default() -> Self {
         Self {
@@ -80,6 +114,8 @@ impl Default for ReflectionConfig {
             min_confidence: 0.6,
             max_penalty: -5,
             max_boost: 5,
+            quarantine_after: 3,
+            quarantine_min_confidence: 0.8,
             analyze_failures: true,
         }
     }
@@ -116,6 +152,13 @@ impl ReflectionEngine {
         }
     }
 
+    /// Consult an LLM judge before falling back to heuristic verdicts
+    pub fn with_llm_judge(mut self, judge: LlmJudge) -> Self {
+        let judge = judge.with_limits(self.config.max_boost, self.config.max_penalty);
+        self.analyzer = self.analyzer.with_llm_judge(judge);
+        self
+    }
+
     /// Check if reflection should be triggered based on current state
     #[allow(dead_code)] // Reserved for daemon mode automatic triggering
     pub fn should_reflect(&self, state: &ReflectionState) -> bool {
@@ -154,12 +197,13 @@ impl ReflectionEngine {
             // Analyze the trajectory outcome
             let outcome = self.analyzer.analyze(trajectory);
 
-            // Generate verdict based on outcome
-            if let Some(verdict) = self.analyzer.judge(&outcome, trajectory) {
-                if verdict.confidence >= self.config.min_confidence {
-                    verdicts.push(verdict);
-                }
-            }
+            // Generate verdicts based on outcome, one per attributed pattern
+            verdicts.extend(
+                self.analyzer
+                    .judge(&outcome, trajectory)
+                    .into_iter()
+                    .filter(|verdict| verdict.confidence >= self.config.min_confidence),
+            );
         }
 
         info!("Reflection produced {} verdicts", verdicts.len());
@@ -170,6 +214,11 @@ impl ReflectionEngine {
     pub fn apply_verdicts(&self, conn: &Connection, verdicts: &[ReflectionVerdict]) -> Result<usize> {
         self.distiller.distill(conn, verdicts)
     }
+
+    /// Quarantine patterns the applied verdicts found repeatedly harmful
+    pub fn quarantine_harmful(&self, conn: &Connection, verdicts: &[ReflectionVerdict]) -> Result<Vec<i64>> {
+        self.distiller.quarantine(conn, verdicts)
+    }
 }
 
 /// Initialize reflection tables in the database
@@ -203,12 +252,25 @@ pub fn init_reflection_tables(conn: &Connection) -> Result<()> {
             created_at DATETIME DEFAULT CURRENT_TIMESTAMP
         );
 
+        -- LLM judge verdicts, cached per trajectory and judge:model
+        CREATE TABLE IF NOT EXISTS judge_cache (
+            trajectory_hash TEXT NOT NULL,
+            judge TEXT NOT NULL,
+            verdict TEXT NOT NULL,
+            confidence REAL NOT NULL,
+            root_cause TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (trajectory_hash, judge)
+        );
+
         -- Indices for efficient queries
         CREATE INDEX IF NOT EXISTS idx_verdicts_pattern ON reflection_verdicts(pattern_id);
         CREATE INDEX IF NOT EXISTS idx_verdicts_verdict ON reflection_verdicts(verdict);
         CREATE INDEX IF NOT EXISTS idx_verdicts_created ON reflection_verdicts(created_at);
         "#,
     )?;
+    improve::create_history_table(conn)?;
+    offsets::create_table(conn)?;
 
     debug!("Initialized reflection tables");
     Ok(())
@@ -216,7 +278,7 @@ pub fn init_reflection_tables(conn: &Connection) -> Result<()> {
 
 /// Get reflection status from the database
 pub fn get_reflection_status(db_path: &Path) -> Result<ReflectionStatus> {
-    let conn = Connection::open(db_path)?;
+    let conn = crate::storage::db::open(db_path)?;
 
     // Check if tables exist
     let tables_exist: bool = conn.query_row(
@@ -268,8 +330,49 @@ pub fn get_reflection_status(db_path: &Path) -> Result<ReflectionStatus> {
     })
 }
 
+/// Verdict counts for one day
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DailyVerdicts {
+    /// Date as YYYY-MM-DD (UTC)
+    pub day: String,
+    pub effective: i64,
+    pub neutral: i64,
+    pub ineffective: i64,
+    pub harmful: i64,
+}
+
+/// Verdict counts per day for the last `days` days, oldest first
+///
+/// Days without verdicts are omitted.
+pub fn verdict_trend(conn: &Connection, days: u32) -> Result<Vec<DailyVerdicts>> {
+    let mut stmt = conn.prepare(
+        "SELECT date(created_at), verdict, COUNT(*) FROM reflection_verdicts
+         WHERE created_at >= date('now', ?1)
+         GROUP BY 1, 2 ORDER BY 1",
+    )?;
+    let rows = stmt.query_map([format!("-{} days", days.saturating_sub(1))], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+    })?;
+
+    let mut trend: Vec<DailyVerdicts> = Vec::new();
+    for row in rows {
+        let (day, verdict, count) = row?;
+        if trend.last().is_none_or(|d| d.day != day) {
+            trend.push(DailyVerdicts { day, ..Default::default() });
+        }
+        let entry = trend.last_mut().expect("pushed above");
+        match verdict.as_str() {
+            "EFFECTIVE" => entry.effective = count,
+            "INEFFECTIVE" => entry.ineffective = count,
+            "HARMFUL" => entry.harmful = count,
+            _ => entry.neutral += count,
+        }
+    }
+    Ok(trend)
+}
+
 /// Reflection status for display
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct ReflectionStatus {
     pub tables_exist: bool,
     pub total_verdicts: i64,
@@ -284,6 +387,83 @@ pub struct ReflectionStatus {
     pub last_duration_ms: i64,
 }
 
+/// Outcome of [`run_cycle`]
+#[derive(Debug, Default)]
+pub struct CycleSummary {
+    pub trajectories: usize,
+    /// Trajectories skipped because they already had verdicts
+    pub already_judged: usize,
+    pub verdicts: usize,
+    pub patterns_updated: usize,
+    /// Patterns quarantined after repeated harmful verdicts
+    pub patterns_quarantined: usize,
+    /// LLM judge in use, if one is configured and available
+    pub judge: Option<crate::config::JudgeKind>,
+    pub duration: std::time::Duration,
+}
+
+/// Reflect on trajectories not yet judged and apply the verdicts
+///
+/// Drains the injection spool first so verdicts can be attributed, and
+/// advances the per-file offsets only after verdicts are stored.
+#[instrument(level = "debug", name = "reflect", skip(mana_dir, mode))]
+pub fn run_cycle(mana_dir: &Path, trigger: &str, mode: ScanMode) -> Result<CycleSummary> {
+    let start = std::time::Instant::now();
+    let db_path = mana_dir.join("metadata.sqlite");
+
+    let mut conn = crate::storage::db::open(&db_path)?;
+    init_reflection_tables(&conn)?;
+    debug_span!("drain_spool").in_scope(|| crate::storage::injection_log::drain_spool(mana_dir, &mut conn))?;
+
+    let files = crate::learning::collect_log_files(mana_dir)?;
+    let mut pending = debug_span!("collect_pending", files = files.len()).in_scope(|| collect_pending(&conn, &files, mode))?;
+    // Offsets still advance past excluded sessions, so they're never judged
+    let excluded = crate::learning::privacy::ProjectFilter::configured(mana_dir).retain(&mut pending.trajectories);
+    if excluded > 0 {
+        debug!("Skipped {} trajectories from excluded projects", excluded);
+    }
+    let mut summary = CycleSummary {
+        trajectories: pending.trajectories.len(),
+        already_judged: pending.already_judged,
+        ..Default::default()
+    };
+    if pending.trajectories.is_empty() {
+        pending.commit(&conn)?;
+        return Ok(summary);
+    }
+
+    let settings = crate::config::load_config(mana_dir).reflection;
+    let mut engine = ReflectionEngine::with_db_path(ReflectionConfig::from_settings(&settings), &db_path);
+    if let Some(judge) = LlmJudge::from_settings(&settings, &db_path) {
+        summary.judge = Some(settings.judge);
+        engine = engine.with_llm_judge(judge);
+    }
+
+    let verdicts = debug_span!("judge", trajectories = pending.trajectories.len())
+        .in_scope(|| engine.reflect(&pending.trajectories))?;
+    summary.verdicts = verdicts.len();
+    summary.patterns_updated = debug_span!("apply").in_scope(|| engine.apply_verdicts(&conn, &verdicts))?;
+    summary.patterns_quarantined = debug_span!("quarantine").in_scope(|| engine.quarantine_harmful(&conn, &verdicts))?.len();
+    if summary.patterns_updated > 0 || summary.patterns_quarantined > 0 {
+        debug_span!("snapshot").in_scope(|| crate::storage::snapshot::rebuild(mana_dir, &conn))?;
+    }
+    pending.commit(&conn)?;
+    summary.duration = start.elapsed();
+
+    log_reflection_cycle(
+        &conn,
+        trigger,
+        summary.trajectories,
+        summary.verdicts,
+        summary.patterns_updated,
+        0, // new patterns
+        summary.patterns_quarantined,
+        summary.duration.as_millis() as u64,
+    )?;
+
+    Ok(summary)
+}
+
 /// Log a reflection cycle to the database
 #[allow(clippy::too_many_arguments)]
 pub fn log_reflection_cycle(
@@ -333,7 +513,7 @@ mod tests {
             |row| row.get(0),
         ).unwrap();
 
-        assert_eq!(count, 2); // reflection_verdicts and reflection_log
+        assert_eq!(count, 3); // reflection_verdicts, reflection_log and reflection_offsets
     }
 
     #[test]
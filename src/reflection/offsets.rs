@@ -0,0 +1,232 @@
+//! Incremental reflection
+//!
+//! Tracks how far each JSONL log has been reflected on so `mana reflect run`
+//! only judges new trajectories. Trajectories whose hash already has a
+//! verdict are skipped as well, which keeps `--all` and `--since` rescans
+//! from producing duplicate verdicts.
+
+use anyhow::{bail, Result};
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::verdict::compute_trajectory_hash;
+use crate::learning::trajectory::{parse_files, Trajectory};
+
+/// Which part of each log to read
+#[derive(Debug, Clone, Copy)]
+pub enum ScanMode {
+    /// From the last reflected offset
+    Incremental,
+    /// Whole files, keeping trajectories that ended within the duration
+    Since(Duration),
+    /// Whole files
+    All,
+}
+
+/// Create the reflection_offsets table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- Byte offset up to which each log has been reflected on
+        CREATE TABLE IF NOT EXISTS reflection_offsets (
+            path TEXT PRIMARY KEY,
+            offset INTEGER NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Trajectories that still need a verdict, plus the offsets to save afterwards
+pub struct PendingReflection {
+    pub trajectories: Vec<Trajectory>,
+    /// Trajectories skipped because they already have verdicts
+    pub already_judged: usize,
+    positions: Vec<(PathBuf, u64)>,
+}
+
+impl PendingReflection {
+    /// Record that the scanned files have been reflected on
+    ///
+    /// Call after the verdicts are stored so a failed run is retried.
+    pub fn commit(&self, conn: &Connection) -> Result<()> {
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO reflection_offsets (path, offset, updated_at)
+             VALUES (?1, ?2, CURRENT_TIMESTAMP)
+             ON CONFLICT(path) DO UPDATE SET offset = excluded.offset, updated_at = excluded.updated_at",
+        )?;
+        for (path, offset) in &self.positions {
+            stmt.execute(params![path.to_string_lossy(), *offset as i64])?;
+        }
+        Ok(())
+    }
+}
+
+/// Collect trajectories from `files` that haven't been reflected on yet
+pub fn collect_pending(conn: &Connection, files: &[PathBuf], mode: ScanMode) -> Result<PendingReflection> {
+    create_table(conn)?;
+    let offsets = load_offsets(conn)?;
+    let cutoff = match mode {
+        ScanMode::Since(duration) => Some(Utc::now() - duration),
+        _ => None,
+    };
+
+    let mut pending = PendingReflection { trajectories: Vec::new(), already_judged: 0, positions: Vec::new() };
+    let mut judged_stmt = conn.prepare_cached("SELECT 1 FROM reflection_verdicts WHERE trajectory_hash = ?1 LIMIT 1")?;
+
+    // Pick the files and offsets to read, then parse them all in parallel
+    let mut to_parse = Vec::new();
+    for file in files {
+        let Ok(meta) = std::fs::metadata(file) else {
+            continue;
+        };
+        let file_len = meta.len();
+
+        // Files untouched since the cutoff can't contain recent trajectories
+        if let (Some(cutoff), Ok(modified)) = (cutoff, meta.modified()) {
+            if chrono::DateTime::<Utc>::from(modified) < cutoff {
+                continue;
+            }
+        }
+
+        let start = match mode {
+            ScanMode::Incremental => offsets
+                .get(file)
+                .copied()
+                // A shorter file was truncated or replaced; start over
+                .filter(|offset| *offset <= file_len)
+                .unwrap_or(0),
+            ScanMode::Since(_) | ScanMode::All => 0,
+        };
+        if start < file_len {
+            to_parse.push((file.clone(), start));
+        }
+    }
+    let (trajectories, parsed) = parse_files(&to_parse);
+    // An unterminated last line is read next time
+    pending.positions.extend(parsed.into_iter().map(|f| (f.path, f.end_offset)));
+
+    for trajectory in trajectories {
+        let recent = cutoff.is_none_or(|c| {
+            trajectory.ended_at.or(trajectory.started_at).is_none_or(|t| t >= c)
+        });
+        if !recent {
+            continue;
+        }
+
+        let hash = compute_trajectory_hash(&trajectory.session_id, &trajectory.user_query, &trajectory.tool_calls);
+        if judged_stmt.exists(params![hash])? {
+            pending.already_judged += 1;
+            continue;
+        }
+        pending.trajectories.push(trajectory);
+    }
+
+    Ok(pending)
+}
+
+fn load_offsets(conn: &Connection) -> Result<HashMap<PathBuf, u64>> {
+    let mut stmt = conn.prepare("SELECT path, offset FROM reflection_offsets")?;
+    let offsets = stmt
+        .query_map([], |row| Ok((PathBuf::from(row.get::<_, String>(0)?), row.get::<_, i64>(1)? as u64)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(offsets)
+}
+
+/// Parse a duration like `30m`, `12h`, `7d` or `2w`
+pub fn parse_since(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let Some(unit) = value.chars().last() else {
+        bail!("Empty duration");
+    };
+    let amount: i64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid duration '{}' (expected e.g. 12h, 7d, 2w)", value))?;
+
+    Ok(match unit {
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        'w' => Duration::weeks(amount),
+        _ => bail!("Invalid duration unit in '{}' (use m, h, d or w)", value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learning::trajectory::ToolCall;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    /// A user prompt followed by one Bash call
+    fn exchange(session: &str, text: &str) -> String {
+        let user = serde_json::json!({
+            "type": "user",
+            "sessionId": session,
+            "timestamp": Utc::now().to_rfc3339(),
+            "message": {"role": "user", "content": text},
+        });
+        let assistant = serde_json::json!({
+            "type": "assistant",
+            "sessionId": session,
+            "timestamp": Utc::now().to_rfc3339(),
+            "message": {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": text}}
+            ]},
+        });
+        format!("{}\n{}", user, assistant)
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_since("7d").unwrap(), Duration::days(7));
+        assert!(parse_since("7").is_err());
+        assert!(parse_since("d").is_err());
+    }
+
+    #[test]
+    fn test_incremental_scan_judges_each_trajectory_once() {
+        let temp = TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        super::super::init_reflection_tables(&conn).unwrap();
+
+        let log = temp.path().join("session.jsonl");
+        std::fs::write(&log, format!("{}\n", exchange("s1", "first task"))).unwrap();
+        let files = vec![log.clone()];
+
+        let first = collect_pending(&conn, &files, ScanMode::Incremental).unwrap();
+        assert_eq!(first.trajectories.len(), 1);
+        first.commit(&conn).unwrap();
+        assert!(load_offsets(&conn).unwrap().contains_key(&log));
+
+        // Nothing new since the last run
+        let again = collect_pending(&conn, &files, ScanMode::Incremental).unwrap();
+        assert!(again.trajectories.is_empty());
+
+        // Appended trajectories are picked up on their own
+        let mut file = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+        writeln!(file, "{}", exchange("s2", "second task")).unwrap();
+        let next = collect_pending(&conn, &files, ScanMode::Incremental).unwrap();
+        assert_eq!(next.trajectories.len(), 1);
+        assert_eq!(next.trajectories[0].user_query, "second task");
+
+        // A full rescan skips trajectories that already have verdicts
+        let call = ToolCall { tool_name: "Bash".into(), tool_input: serde_json::json!({"command": "first task"}) };
+        let hash = compute_trajectory_hash("s1", "first task", &[call]);
+        conn.execute(
+            "INSERT INTO reflection_verdicts (trajectory_hash, verdict, confidence) VALUES (?1, 'NEUTRAL', 1.0)",
+            params![hash],
+        )
+        .unwrap();
+        let all = collect_pending(&conn, &files, ScanMode::All).unwrap();
+        assert_eq!(all.already_judged, 1);
+        assert_eq!(all.trajectories.len(), 1);
+    }
+}
@@ -0,0 +1,241 @@
+//! Per-session analytics for `mana sessions`
+//!
+//! Rebuilds recent sessions from the Claude Code logs and joins each one with
+//! what MANA did during it: the patterns injected (from the injection log)
+//! and the reflection verdicts on those patterns. Answers "did MANA help in
+//! yesterday's session?" without reading the raw logs.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::verdict::compute_trajectory_hash;
+use crate::learning::trajectory::{parse_trajectories, Trajectory};
+use crate::learning::collect_log_files;
+use crate::storage::injection_log::injected_in_window;
+use crate::storage::PatternStore;
+
+/// Characters of the opening prompt and pattern contexts kept per session
+const PREVIEW_CHARS: usize = 80;
+
+/// One session and what MANA contributed to it
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_secs: Option<i64>,
+    pub cwd: Option<String>,
+    /// Start of the first user prompt
+    pub query: String,
+    /// Tool name -> number of calls
+    pub tools: BTreeMap<String, usize>,
+    pub tool_results: usize,
+    pub tool_errors: usize,
+    /// Heuristic verdict on the whole session
+    pub success: Option<bool>,
+    /// Reflection verdict category -> count
+    pub verdicts: BTreeMap<String, usize>,
+    /// Patterns injected during the session, highest score first
+    pub injected: Vec<InjectedPattern>,
+}
+
+impl SessionSummary {
+    /// Share of tool results that weren't errors, if any were recorded
+    pub fn success_ratio(&self) -> Option<f64> {
+        (self.tool_results > 0).then(|| (self.tool_results - self.tool_errors) as f64 / self.tool_results as f64)
+    }
+}
+
+/// A pattern injected into a session
+#[derive(Debug, Clone, Serialize)]
+pub struct InjectedPattern {
+    pub id: i64,
+    pub score: f64,
+    /// None when the pattern was pruned or merged since
+    pub tool_type: Option<String>,
+    pub context: Option<String>,
+    /// Reflection's verdict on this pattern in this session
+    pub verdict: Option<String>,
+}
+
+/// The most recent sessions in the configured log directories, newest first
+///
+/// Only logs modified since `since` are read. Sessions that appear in more
+/// than one log are reported from the newest.
+pub fn recent_sessions(
+    mana_dir: &Path,
+    db_path: &Path,
+    since: Option<DateTime<Utc>>,
+    limit: usize,
+) -> Result<Vec<SessionSummary>> {
+    let conn = crate::storage::db::open_readonly(db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    let store = PatternStore::open_readonly(db_path)?;
+
+    let mut logs: Vec<(PathBuf, SystemTime)> = collect_log_files(mana_dir)?
+        .into_iter()
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .filter(|(_, modified)| since.is_none_or(|s| DateTime::<Utc>::from(*modified) >= s))
+        .collect();
+    logs.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+
+    let mut seen = HashSet::new();
+    let mut trajectories: Vec<Trajectory> = Vec::new();
+    for (path, _) in logs {
+        // Logs are newest first, so a full page means the rest is older
+        if trajectories.len() >= limit {
+            break;
+        }
+        for trajectory in parse_trajectories(&path, 0).unwrap_or_default() {
+            if since.is_some_and(|s| trajectory.ended_at.is_some_and(|end| end < s)) {
+                continue;
+            }
+            if seen.insert(trajectory.session_id.clone()) {
+                trajectories.push(trajectory);
+            }
+        }
+    }
+    trajectories.sort_by_key(|t| std::cmp::Reverse(t.ended_at));
+    trajectories.truncate(limit);
+
+    trajectories.iter().map(|t| summarize(&conn, &store, t)).collect()
+}
+
+/// Join one trajectory with its injections and verdicts
+fn summarize(conn: &Connection, store: &PatternStore, trajectory: &Trajectory) -> Result<SessionSummary> {
+    let mut tools = BTreeMap::new();
+    for call in &trajectory.tool_calls {
+        *tools.entry(call.tool_name.clone()).or_insert(0) += 1;
+    }
+
+    let hash = compute_trajectory_hash(&trajectory.session_id, &trajectory.user_query, &trajectory.tool_calls);
+    let pattern_verdicts = verdicts_for_hash(conn, &hash).unwrap_or_default();
+    let mut verdicts = BTreeMap::new();
+    for (_, verdict) in &pattern_verdicts {
+        *verdicts.entry(verdict.clone()).or_insert(0) += 1;
+    }
+
+    let injected = injected_in_window(conn, &trajectory.session_id, None, None)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, score)| {
+            let pattern = store.get_by_id(id).ok().flatten();
+            InjectedPattern {
+                id,
+                score,
+                tool_type: pattern.as_ref().map(|p| p.tool_type.clone()),
+                context: pattern.map(|p| preview(&p.context_query)),
+                verdict: pattern_verdicts
+                    .iter()
+                    .rev()
+                    .find(|(pattern_id, _)| *pattern_id == Some(id))
+                    .map(|(_, verdict)| verdict.clone()),
+            }
+        })
+        .collect();
+
+    Ok(SessionSummary {
+        session_id: trajectory.session_id.clone(),
+        started_at: trajectory.started_at,
+        ended_at: trajectory.ended_at,
+        duration_secs: trajectory
+            .started_at
+            .zip(trajectory.ended_at)
+            .map(|(start, end)| (end - start).num_seconds()),
+        cwd: trajectory.cwd.clone(),
+        query: preview(&trajectory.user_query),
+        tools,
+        tool_results: trajectory.tool_results.len(),
+        tool_errors: trajectory.tool_results.iter().filter(|r| r.is_error).count(),
+        success: trajectory.verdict.map(|v| v.success),
+        verdicts,
+        injected,
+    })
+}
+
+/// (pattern id, verdict) for a trajectory, oldest first
+fn verdicts_for_hash(conn: &Connection, hash: &str) -> Result<Vec<(Option<i64>, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT pattern_id, verdict FROM reflection_verdicts
+         WHERE trajectory_hash = ?1 ORDER BY created_at",
+    )?;
+    let rows = stmt.query_map(params![hash], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// First line of `text`, cut to [`PREVIEW_CHARS`]
+fn preview(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > PREVIEW_CHARS {
+        format!("{}…", line.chars().take(PREVIEW_CHARS - 1).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::injection_log::{insert_records, InjectionRecord};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sessions_join_injections_and_verdicts() {
+        let temp = TempDir::new().unwrap();
+        let logs = temp.path().join("logs");
+        std::fs::create_dir_all(logs.join("project")).unwrap();
+        std::fs::write(
+            temp.path().join("config.toml"),
+            format!("[learning]\nlog_dirs = [{:?}]\n", logs.display().to_string()),
+        )
+        .unwrap();
+        let lines = [
+            r#"{"type":"user","sessionId":"s1","timestamp":"2026-10-15T10:00:00Z","message":{"role":"user","content":"Fix the failing build"}}"#,
+            r#"{"type":"assistant","sessionId":"s1","timestamp":"2026-10-15T10:01:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo build"}},{"type":"tool_use","id":"t2","name":"Bash","input":{"command":"cargo test"}}]}}"#,
+            r#"{"type":"user","sessionId":"s1","timestamp":"2026-10-15T10:02:00Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"},{"type":"tool_result","tool_use_id":"t2","content":"boom","is_error":true}]}}"#,
+        ];
+        std::fs::write(logs.join("project").join("s1.jsonl"), lines.join("\n")).unwrap();
+
+        let db_path = temp.path().join("metadata.sqlite");
+        let mut conn = crate::storage::db::open(&db_path).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        super::super::init_reflection_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (7, 'h', 'Bash', 'cargo build first')",
+            [],
+        )
+        .unwrap();
+        insert_records(&mut conn, &[InjectionRecord::new("s1", "Bash", &[(7, 0.8)])]).unwrap();
+
+        let trajectory = &parse_trajectories(&logs.join("project").join("s1.jsonl"), 0).unwrap()[0];
+        let hash = compute_trajectory_hash("s1", &trajectory.user_query, &trajectory.tool_calls);
+        conn.execute(
+            "INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence) VALUES (?1, 7, 'EFFECTIVE', 0.9)",
+            params![hash],
+        )
+        .unwrap();
+        drop(conn);
+
+        let sessions = recent_sessions(temp.path(), &db_path, None, 10).unwrap();
+        assert_eq!(sessions.len(), 1);
+        let session = &sessions[0];
+        assert_eq!(session.duration_secs, Some(120));
+        assert_eq!(session.tools.get("Bash"), Some(&2));
+        assert_eq!(session.success_ratio(), Some(0.5));
+        assert_eq!(session.verdicts.get("EFFECTIVE"), Some(&1));
+        assert_eq!(session.injected.len(), 1);
+        assert_eq!(session.injected[0].verdict.as_deref(), Some("EFFECTIVE"));
+        assert_eq!(session.injected[0].context.as_deref(), Some("cargo build first"));
+
+        let future = "2030-01-01T00:00:00Z".parse().unwrap();
+        assert!(recent_sessions(temp.path(), &db_path, Some(future), 10).unwrap().is_empty());
+    }
+}
@@ -119,7 +119,6 @@ impl Verdict {
     }
 
     /// Add a suggested improvement to the verdict
-    #[allow(dead_code)] // Reserved for future improvement suggestions
     pub fn with_suggestion(mut self, suggestion: String) -> Self {
         self.suggested_improvement = Some(suggestion);
         self
@@ -0,0 +1,359 @@
+//! Static learning digest (`mana report`)
+//!
+//! Collects pattern statistics, the reflection verdict trend, the pattern
+//! leaderboard, regressions and sync state into a [`Report`], then renders it
+//! as a self-contained HTML page or a Markdown file. The output directory
+//! also gets `report.json`, so a CI job can upload the directory as an
+//! artifact or publish it to GitHub Pages as is.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::dashboard::sync_lines;
+use crate::reflection::{self, DailyVerdicts, PatternTrend};
+use crate::storage::stats::{self, StatsReport};
+
+/// Patterns listed in the leaderboard section
+const TOP_PATTERNS: usize = 10;
+
+/// Recent verdicts a pattern needs to be ranked or flagged
+const MIN_VERDICTS: usize = 3;
+
+/// Characters of pattern context shown per row
+const CONTEXT_CHARS: usize = 100;
+
+/// Output format of `mana report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+}
+
+impl ReportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "html" => Ok(Self::Html),
+            "markdown" | "md" => Ok(Self::Markdown),
+            other => bail!("Unknown report format {:?}: use html or markdown", other),
+        }
+    }
+
+    /// Name of the page written to the output directory
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Html => "index.html",
+            Self::Markdown => "README.md",
+        }
+    }
+}
+
+/// Everything in a report, read in one pass
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub generated_at: DateTime<Utc>,
+    /// Days covered by the trend, leaderboard and regressions
+    pub days: u32,
+    pub stats: StatsReport,
+    pub trend: Vec<DailyVerdicts>,
+    pub leaderboard: Vec<PatternTrend>,
+    pub regressions: Vec<PatternTrend>,
+    pub sync: Vec<String>,
+}
+
+/// Read the report for the last `days` days
+pub fn collect(mana_dir: &Path, days: u32) -> Result<Report> {
+    let conn = crate::storage::db::open_readonly(&mana_dir.join("metadata.sqlite"))?;
+    Ok(Report {
+        generated_at: Utc::now(),
+        days,
+        stats: stats::collect_stats(&conn, 10)?,
+        // Reflection tables only exist once reflection has run
+        trend: reflection::verdict_trend(&conn, days).unwrap_or_default(),
+        leaderboard: reflection::leaderboard(&conn, days, MIN_VERDICTS, TOP_PATTERNS).unwrap_or_default(),
+        regressions: reflection::regressions(&conn, days, MIN_VERDICTS).unwrap_or_default(),
+        sync: sync_lines(mana_dir),
+    })
+}
+
+/// Write the report page and `report.json` into `out_dir`, returning the page
+pub fn write(report: &Report, format: ReportFormat, out_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(out_dir)?;
+    let page = out_dir.join(format.file_name());
+    let content = match format {
+        ReportFormat::Html => render_html(report),
+        ReportFormat::Markdown => render_markdown(report),
+    };
+    std::fs::write(&page, content)?;
+    std::fs::write(out_dir.join("report.json"), serde_json::to_string_pretty(report)?)?;
+    Ok(page)
+}
+
+/// Headline numbers shared by both formats
+fn summary_rows(report: &Report) -> Vec<(&'static str, String)> {
+    let s = &report.stats;
+    let verdicts: i64 = report.trend.iter().map(|d| d.effective + d.neutral + d.ineffective + d.harmful).sum();
+    vec![
+        ("Patterns", s.patterns.total.to_string()),
+        ("Success rate", s.patterns.success_rate.map_or("n/a".to_string(), |r| format!("{:.1}%", r))),
+        ("Successes / failures", format!("{} / {}", s.patterns.successes, s.patterns.failures)),
+        ("Skills", s.skills.total.to_string()),
+        ("Causal edges", s.causal.total_edges.to_string()),
+        (
+            "Reflection verdicts",
+            format!("{} in the last {} days", verdicts, report.days),
+        ),
+        ("Regressions", report.regressions.len().to_string()),
+        ("Budget overruns (7 days)", s.budget.overruns.to_string()),
+    ]
+}
+
+fn context_preview(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > CONTEXT_CHARS {
+        format!("{}…", line.chars().take(CONTEXT_CHARS - 1).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn percent(ratio: f64) -> String {
+    format!("{:.0}%", ratio * 100.0)
+}
+
+/// Render the report as GitHub-flavored Markdown
+pub fn render_markdown(report: &Report) -> String {
+    let cell = |text: &str| text.replace('|', "\\|");
+    let mut doc = String::new();
+    let _ = writeln!(doc, "# MANA learning report\n");
+    let _ = writeln!(doc, "_Generated {} · last {} days_\n", report.generated_at.format("%Y-%m-%d %H:%M UTC"), report.days);
+
+    let _ = writeln!(doc, "## Summary\n\n| Metric | Value |\n|---|---|");
+    for (label, value) in summary_rows(report) {
+        let _ = writeln!(doc, "| {} | {} |", label, value);
+    }
+
+    let _ = writeln!(doc, "\n## Patterns by tool\n");
+    if report.stats.patterns.by_tool.is_empty() {
+        let _ = writeln!(doc, "No patterns yet.");
+    } else {
+        let _ = writeln!(doc, "| Tool | Patterns |\n|---|---:|");
+        for tool in &report.stats.patterns.by_tool {
+            let _ = writeln!(doc, "| {} | {} |", cell(&tool.tool), tool.count);
+        }
+    }
+
+    let _ = writeln!(doc, "\n## Reflection trend\n");
+    if report.trend.is_empty() {
+        let _ = writeln!(doc, "No reflection verdicts in this period.");
+    } else {
+        let _ = writeln!(doc, "| Day | Effective | Neutral | Ineffective | Harmful |\n|---|---:|---:|---:|---:|");
+        for day in &report.trend {
+            let _ = writeln!(
+                doc,
+                "| {} | {} | {} | {} | {} |",
+                day.day, day.effective, day.neutral, day.ineffective, day.harmful
+            );
+        }
+    }
+
+    let _ = writeln!(doc, "\n## Top patterns\n");
+    if report.leaderboard.is_empty() {
+        let _ = writeln!(doc, "No pattern has {} or more recent verdicts.", MIN_VERDICTS);
+    } else {
+        let _ = writeln!(doc, "| # | Tool | Effective | Verdicts | Score | Context |\n|---|---|---:|---:|---:|---|");
+        for t in &report.leaderboard {
+            let _ = writeln!(
+                doc,
+                "| {} | {} | {} | {} | {} | {} |",
+                t.id,
+                cell(&t.tool_type),
+                percent(t.recent.effectiveness_ratio()),
+                t.recent.total,
+                t.score,
+                cell(&context_preview(&t.context_query))
+            );
+        }
+    }
+
+    let _ = writeln!(doc, "\n## Regressions\n");
+    if report.regressions.is_empty() {
+        let _ = writeln!(doc, "No regressions.");
+    } else {
+        let _ = writeln!(doc, "| # | Tool | Recent negative | Harmful | Score | Context |\n|---|---|---:|---:|---:|---|");
+        for t in &report.regressions {
+            let _ = writeln!(
+                doc,
+                "| {} | {} | {} | {} | {} | {} |",
+                t.id,
+                cell(&t.tool_type),
+                percent(t.recent_negative_ratio()),
+                t.recent.harmful,
+                t.score,
+                cell(&context_preview(&t.context_query))
+            );
+        }
+    }
+
+    let _ = writeln!(doc, "\n## Sync\n");
+    for line in &report.sync {
+        let _ = writeln!(doc, "- {}", line);
+    }
+    doc
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:960px;margin:2em auto;padding:0 1em;color:#222}\
+table{border-collapse:collapse;width:100%;margin-bottom:1em}th,td{border-bottom:1px solid #ddd;padding:4px 8px;text-align:left}\
+td.n{text-align:right}.bar{display:flex;height:12px;min-width:120px}.bar span{display:block}\
+.effective{background:#2da44e}.neutral{background:#8c959f}.ineffective{background:#d4a72c}.harmful{background:#cf222e}\
+.muted{color:#666}code{font-size:90%}";
+
+/// Render the report as a single HTML page with inline styles
+pub fn render_html(report: &Report) -> String {
+    let mut doc = String::new();
+    let _ = write!(
+        doc,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>MANA learning report</title>\n<style>{}</style>\n</head>\n<body>\n",
+        STYLE
+    );
+    let _ = writeln!(doc, "<h1>MANA learning report</h1>");
+    let _ = writeln!(
+        doc,
+        "<p class=\"muted\">Generated {} · last {} days</p>",
+        report.generated_at.format("%Y-%m-%d %H:%M UTC"),
+        report.days
+    );
+
+    let _ = writeln!(doc, "<h2>Summary</h2>\n<table>");
+    for (label, value) in summary_rows(report) {
+        let _ = writeln!(doc, "<tr><th>{}</th><td>{}</td></tr>", label, escape(&value));
+    }
+    let _ = writeln!(doc, "</table>");
+
+    let _ = writeln!(doc, "<h2>Patterns by tool</h2>");
+    if report.stats.patterns.by_tool.is_empty() {
+        let _ = writeln!(doc, "<p>No patterns yet.</p>");
+    } else {
+        let _ = writeln!(doc, "<table>\n<tr><th>Tool</th><th>Patterns</th></tr>");
+        for tool in &report.stats.patterns.by_tool {
+            let _ = writeln!(doc, "<tr><td>{}</td><td class=\"n\">{}</td></tr>", escape(&tool.tool), tool.count);
+        }
+        let _ = writeln!(doc, "</table>");
+    }
+
+    let _ = writeln!(doc, "<h2>Reflection trend</h2>");
+    if report.trend.is_empty() {
+        let _ = writeln!(doc, "<p>No reflection verdicts in this period.</p>");
+    } else {
+        let most = report.trend.iter().map(|d| d.effective + d.neutral + d.ineffective + d.harmful).max().unwrap_or(1).max(1);
+        let _ = writeln!(
+            doc,
+            "<table>\n<tr><th>Day</th><th>Effective</th><th>Neutral</th><th>Ineffective</th><th>Harmful</th><th></th></tr>"
+        );
+        for day in &report.trend {
+            let mut bar = String::new();
+            for (class, count) in [
+                ("effective", day.effective),
+                ("neutral", day.neutral),
+                ("ineffective", day.ineffective),
+                ("harmful", day.harmful),
+            ] {
+                if count > 0 {
+                    let _ = write!(bar, "<span class=\"{}\" style=\"width:{:.1}%\"></span>", class, count as f64 / most as f64 * 100.0);
+                }
+            }
+            let _ = writeln!(
+                doc,
+                "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td><div class=\"bar\">{}</div></td></tr>",
+                day.day, day.effective, day.neutral, day.ineffective, day.harmful, bar
+            );
+        }
+        let _ = writeln!(doc, "</table>");
+    }
+
+    let pattern_table = |doc: &mut String, rows: &[PatternTrend], ratio_label: &str, ratio: fn(&PatternTrend) -> f64| {
+        let _ = writeln!(
+            doc,
+            "<table>\n<tr><th>#</th><th>Tool</th><th>{}</th><th>Verdicts</th><th>Score</th><th>Context</th></tr>",
+            ratio_label
+        );
+        for t in rows {
+            let _ = writeln!(
+                doc,
+                "<tr><td>{}</td><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td><code>{}</code></td></tr>",
+                t.id,
+                escape(&t.tool_type),
+                percent(ratio(t)),
+                t.recent.total,
+                t.score,
+                escape(&context_preview(&t.context_query))
+            );
+        }
+        let _ = writeln!(doc, "</table>");
+    };
+
+    let _ = writeln!(doc, "<h2>Top patterns</h2>");
+    if report.leaderboard.is_empty() {
+        let _ = writeln!(doc, "<p>No pattern has {} or more recent verdicts.</p>", MIN_VERDICTS);
+    } else {
+        pattern_table(&mut doc, &report.leaderboard, "Effective", |t| t.recent.effectiveness_ratio());
+    }
+
+    let _ = writeln!(doc, "<h2>Regressions</h2>");
+    if report.regressions.is_empty() {
+        let _ = writeln!(doc, "<p>No regressions.</p>");
+    } else {
+        pattern_table(&mut doc, &report.regressions, "Recent negative", PatternTrend::recent_negative_ratio);
+    }
+
+    let _ = writeln!(doc, "<h2>Sync</h2>\n<ul>");
+    for line in &report.sync {
+        let _ = writeln!(doc, "<li>{}</li>", escape(line));
+    }
+    let _ = writeln!(doc, "</ul>\n</body>\n</html>");
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_renders_both_formats() {
+        let temp = TempDir::new().unwrap();
+        let conn = crate::storage::db::open(&temp.path().join("metadata.sqlite")).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        reflection::init_reflection_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'run <tests> | tee log', 5, 1);
+             INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence)
+             VALUES ('t1', 1, 'EFFECTIVE', 0.9), ('t2', 1, 'EFFECTIVE', 0.9), ('t3', 1, 'NEUTRAL', 0.5);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let report = collect(temp.path(), 7).unwrap();
+        assert_eq!(report.leaderboard.len(), 1);
+        assert_eq!(report.trend.len(), 1);
+
+        let html = render_html(&report);
+        assert!(html.contains("run &lt;tests&gt; | tee log"));
+        assert!(html.contains("<span class=\"effective\""));
+        let markdown = render_markdown(&report);
+        assert!(markdown.contains("| 1 | Bash | 67% | 3 | 4 | run <tests> \\| tee log |"));
+
+        let out = temp.path().join("report");
+        let page = write(&report, ReportFormat::parse("markdown").unwrap(), &out).unwrap();
+        assert_eq!(page, out.join("README.md"));
+        assert!(out.join("report.json").is_file());
+        assert!(ReportFormat::parse("pdf").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Replay past sessions against the current pattern bank (`mana simulate`)
+//!
+//! Each tool call in the Claude Code logs that a pre-hook fires on (Edit,
+//! Write, MultiEdit, Bash, Task) is ranked again with today's patterns and
+//! config, the same way `mana inject --explain` does it: nothing is sent to
+//! Claude and nothing is recorded. The report says which patterns would
+//! have been injected and how relevant they were, which calls and sessions
+//! nothing matched (coverage gaps), and how the result differs from what
+//! the injection log says was actually injected. Run it before and after a
+//! ranking or config change to see the effect without waiting for new
+//! sessions.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::config::InjectionConfig;
+use crate::hooks::explain::{Explanation, Outcome};
+use crate::learning::collect_log_files;
+use crate::learning::trajectory::{parse_trajectories, Trajectory};
+use crate::storage::injection_log::injected_in_window;
+
+/// Characters of a session's opening prompt kept for gaps
+const PREVIEW_CHARS: usize = 80;
+
+/// Everything `mana simulate` reports
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationReport {
+    pub since: DateTime<Utc>,
+    pub sessions: usize,
+    /// Tool calls a pre-hook would have fired on
+    pub calls: usize,
+    /// Calls that got at least one pattern matching their query
+    pub matched_calls: usize,
+    /// Calls that only got the generic fallback
+    pub fallback_calls: usize,
+    /// Patterns that would have been injected, most often first
+    pub patterns: Vec<PatternUse>,
+    /// Sessions in which no call matched a pattern
+    pub gaps: Vec<Gap>,
+    /// Unmatched calls by (tool, command category), most first
+    pub gap_categories: Vec<GapCategory>,
+    pub changes: Changes,
+}
+
+/// How often a pattern would have been injected
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternUse {
+    pub id: i64,
+    pub tool_type: String,
+    pub context: String,
+    pub injections: usize,
+    pub sessions: usize,
+    /// Mean query similarity where injected: the hypothetical relevance
+    pub mean_similarity: f64,
+    pub mean_score: f64,
+}
+
+/// A session nothing matched
+#[derive(Debug, Clone, Serialize)]
+pub struct Gap {
+    pub session_id: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub query: String,
+    pub calls: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GapCategory {
+    pub tool: String,
+    pub category: Option<String>,
+    pub calls: usize,
+}
+
+/// Replayed injections compared with the injection log
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Changes {
+    /// Sessions the injection log has anything for
+    pub logged_sessions: usize,
+    /// Of those, sessions that would get exactly the same patterns
+    pub unchanged_sessions: usize,
+    /// Patterns that would now be injected into a logged session but weren't
+    pub added: usize,
+    /// Patterns logged for a session that would no longer be injected
+    pub dropped: usize,
+}
+
+#[derive(Default)]
+struct Usage {
+    tool_type: String,
+    context: String,
+    injections: usize,
+    sessions: HashSet<String>,
+    similarity: f64,
+    score: f64,
+}
+
+/// Accumulates replayed sessions into a report
+#[derive(Default)]
+pub struct Simulation {
+    sessions: usize,
+    calls: usize,
+    matched_calls: usize,
+    fallback_calls: usize,
+    usage: HashMap<i64, Usage>,
+    gaps: Vec<Gap>,
+    gap_categories: BTreeMap<(String, Option<String>), usize>,
+    changes: Changes,
+}
+
+impl Simulation {
+    /// Add one session: its replayed calls and what the log says it got
+    pub fn add(&mut self, trajectory: &Trajectory, replayed: &[Explanation], logged: &HashSet<i64>) {
+        self.sessions += 1;
+        let mut injected = HashSet::new();
+        let mut matched = false;
+        for call in replayed {
+            self.calls += 1;
+            let used: Vec<_> = call.candidates.iter().filter(|c| c.outcome == Some(Outcome::Injected)).collect();
+            if used.is_empty() || call.fallback {
+                *self.gap_categories.entry((call.tool.clone(), call.category.clone())).or_insert(0) += 1;
+                self.fallback_calls += usize::from(call.fallback && !used.is_empty());
+            } else {
+                self.matched_calls += 1;
+                matched = true;
+            }
+            for candidate in used {
+                injected.insert(candidate.id);
+                let usage = self.usage.entry(candidate.id).or_insert_with(|| Usage {
+                    tool_type: candidate.tool_type.clone(),
+                    context: candidate.context.clone(),
+                    ..Default::default()
+                });
+                usage.injections += 1;
+                usage.sessions.insert(trajectory.session_id.clone());
+                usage.similarity += candidate.similarity.unwrap_or(0.0);
+                usage.score += candidate.score.unwrap_or(0.0);
+            }
+        }
+
+        if !replayed.is_empty() && !matched {
+            self.gaps.push(Gap {
+                session_id: trajectory.session_id.clone(),
+                started_at: trajectory.started_at,
+                query: trajectory.user_query.chars().take(PREVIEW_CHARS).collect(),
+                calls: replayed.len(),
+            });
+        }
+        if !logged.is_empty() {
+            self.changes.logged_sessions += 1;
+            self.changes.unchanged_sessions += usize::from(*logged == injected);
+            self.changes.added += injected.difference(logged).count();
+            self.changes.dropped += logged.difference(&injected).count();
+        }
+    }
+
+    pub fn finish(self, since: DateTime<Utc>) -> SimulationReport {
+        let mut patterns: Vec<PatternUse> = self
+            .usage
+            .into_iter()
+            .map(|(id, u)| PatternUse {
+                id,
+                tool_type: u.tool_type,
+                context: u.context,
+                injections: u.injections,
+                sessions: u.sessions.len(),
+                mean_similarity: u.similarity / u.injections as f64,
+                mean_score: u.score / u.injections as f64,
+            })
+            .collect();
+        patterns.sort_by(|a, b| b.injections.cmp(&a.injections).then(a.id.cmp(&b.id)));
+        let mut gap_categories: Vec<GapCategory> = self
+            .gap_categories
+            .into_iter()
+            .map(|((tool, category), calls)| GapCategory { tool, category, calls })
+            .collect();
+        gap_categories.sort_by_key(|g| std::cmp::Reverse(g.calls));
+        let mut gaps = self.gaps;
+        gaps.sort_by_key(|g| std::cmp::Reverse(g.started_at));
+
+        SimulationReport {
+            since,
+            sessions: self.sessions,
+            calls: self.calls,
+            matched_calls: self.matched_calls,
+            fallback_calls: self.fallback_calls,
+            patterns,
+            gaps,
+            gap_categories,
+            changes: self.changes,
+        }
+    }
+}
+
+/// Replay every session in the configured log directories since `since`
+pub fn run(mana_dir: &Path, conn: &Connection, config: &InjectionConfig, since: DateTime<Utc>) -> Result<SimulationReport> {
+    let mut seen = HashSet::new();
+    let mut simulation = Simulation::default();
+    for path in collect_log_files(mana_dir)? {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified.is_some_and(|m| DateTime::<Utc>::from(m) < since) {
+            continue;
+        }
+        for trajectory in parse_trajectories(&path, 0).unwrap_or_default() {
+            if trajectory.ended_at.is_some_and(|end| end < since) || !seen.insert(trajectory.session_id.clone()) {
+                continue;
+            }
+            let mut replayed = Vec::new();
+            for call in &trajectory.tool_calls {
+                if let Some(explanation) =
+                    crate::hooks::replay_call(&call.tool_name, &call.tool_input, trajectory.cwd.as_deref(), config)?
+                {
+                    replayed.push(explanation);
+                }
+            }
+            let logged: HashSet<i64> = injected_in_window(conn, &trajectory.session_id, None, None)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            simulation.add(&trajectory, &replayed, &logged);
+        }
+    }
+    Ok(simulation.finish(since))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Pattern;
+
+    fn call(tool: &str, category: Option<&str>, injected: &[(i64, f64)], fallback: bool) -> Explanation {
+        let mut explanation =
+            Explanation::new(tool, "query", category.map(String::from), &InjectionConfig::default(), 0);
+        for &(id, similarity) in injected {
+            let pattern = Pattern {
+                id,
+                pattern_hash: id.to_string(),
+                tool_type: "Bash".into(),
+                command_category: None,
+                context_query: format!("pattern {}", id),
+                success_count: 1,
+                failure_count: 0,
+                embedding_id: None,
+                risky: false,
+            };
+            explanation.consider(&pattern);
+            explanation.score(id, similarity, 0.5, similarity * 0.6 + 0.2);
+            explanation.mark(id, Outcome::Injected);
+        }
+        explanation.fallback = fallback;
+        explanation
+    }
+
+    fn session(id: &str) -> Trajectory {
+        Trajectory { session_id: id.into(), user_query: "fix the build".into(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_simulation_reports_usage_gaps_and_changes() {
+        let mut simulation = Simulation::default();
+        simulation.add(
+            &session("s1"),
+            &[call("bash", Some("cargo"), &[(1, 0.8), (2, 0.4)], false), call("bash", Some("cargo"), &[(1, 0.6)], false)],
+            &HashSet::from([1, 3]),
+        );
+        simulation.add(&session("s2"), &[call("edit", Some("rs"), &[], false), call("bash", None, &[(4, 0.0)], true)], &HashSet::new());
+        // No hooked calls: neither covered nor a gap
+        simulation.add(&session("s3"), &[], &HashSet::from([5]));
+
+        let report = simulation.finish(Utc::now());
+        assert_eq!((report.sessions, report.calls, report.matched_calls, report.fallback_calls), (3, 4, 2, 1));
+        assert_eq!(report.patterns[0].id, 1);
+        assert_eq!(report.patterns[0].injections, 2);
+        assert!((report.patterns[0].mean_similarity - 0.7).abs() < 1e-9);
+        assert_eq!(report.gaps.iter().map(|g| g.session_id.as_str()).collect::<Vec<_>>(), vec!["s2"]);
+        assert_eq!(report.gap_categories.len(), 2);
+        let c = &report.changes;
+        assert_eq!((c.logged_sessions, c.unchanged_sessions, c.added, c.dropped), (2, 0, 1, 2));
+    }
+}
@@ -0,0 +1,108 @@
+//! Bulk pattern operations
+//!
+//! `mana patterns delete`, `archive` and `tag` act on a set of patterns
+//! picked by `--ids`, the usual filter flags, or both (patterns must then be
+//! listed and match). The CLI previews the set and asks for its size to be
+//! confirmed before anything changes; these functions do the changing.
+
+use anyhow::{bail, Result};
+use rusqlite::Connection;
+
+use super::PatternFilter;
+
+/// A pattern as previewed before a bulk change
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub id: i64,
+    pub tool_type: String,
+    pub context: String,
+    pub score: i64,
+    pub status: String,
+}
+
+/// Patterns picked by `ids` and `filter`, lowest id first
+///
+/// Refuses an empty selection so a bare command can't touch every pattern.
+pub fn select(conn: &Connection, ids: &[i64], filter: &PatternFilter) -> Result<Vec<Target>> {
+    if ids.is_empty() && filter.is_empty() {
+        bail!("Pick patterns with --ids or filter flags (--tool, --category, --min-score, ...)");
+    }
+    let (mut conditions, mut values) = filter.where_clause();
+    if !ids.is_empty() {
+        conditions.push_str(&format!(" AND p.id IN ({})", vec!["?"; ids.len()].join(", ")));
+        values.extend(ids.iter().map(|&id| rusqlite::types::Value::Integer(id)));
+    }
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.id, p.tool_type, p.context_query, p.success_count - p.failure_count, p.status
+         FROM patterns p WHERE 1=1{} ORDER BY p.id",
+        conditions
+    ))?;
+    let targets = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+        Ok(Target { id: row.get(0)?, tool_type: row.get(1)?, context: row.get(2)?, score: row.get(3)?, status: row.get(4)? })
+    })?;
+    Ok(targets.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Delete patterns with their history, verdicts, tags and causal edges in one transaction
+///
+/// The embedding index and snapshot are the caller's to update.
+pub fn delete(conn: &Connection, ids: &[i64]) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    super::patterns::delete_with_dependents(&tx, ids)?;
+    tx.commit()?;
+    Ok(ids.len())
+}
+
+/// Archive active patterns, returning how many changed
+pub fn archive(conn: &Connection, ids: &[i64]) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let archived = super::patterns::archive_patterns(&tx, ids)?;
+    tx.commit()?;
+    Ok(archived.len())
+}
+
+/// Add (or with `remove`, take off) tags on patterns, returning how many tags changed
+pub fn tag(conn: &Connection, ids: &[i64], tags: &[String], remove: bool) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut changed = 0;
+    for &id in ids {
+        changed += if remove { super::tags::remove(&tx, id, tags)? } else { super::tags::add(&tx, id, tags)? };
+    }
+    tx.commit()?;
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_and_apply() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'cargo build', 5, 0), (2, 'b', 'Bash', 'rm -rf target', 0, 4), (3, 'c', 'Edit', 'lib.rs', 0, 2);
+             INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift) VALUES (1, 2, 1.5);",
+        )
+        .unwrap();
+
+        assert!(select(&conn, &[], &PatternFilter::default()).is_err());
+        let bash = PatternFilter { tool: Some("bash".into()), ..Default::default() };
+        let ids: Vec<i64> = select(&conn, &[], &bash).unwrap().iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        let ids: Vec<i64> = select(&conn, &[2, 3], &bash).unwrap().iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2]);
+
+        assert_eq!(tag(&conn, &[1, 3], &["keep".into()], false).unwrap(), 2);
+        assert_eq!(archive(&conn, &[3]).unwrap(), 1);
+        assert_eq!(archive(&conn, &[3]).unwrap(), 0);
+        assert_eq!(delete(&conn, &[2]).unwrap(), 1);
+        let (patterns, edges): (i64, i64) = conn
+            .query_row("SELECT (SELECT COUNT(*) FROM patterns), (SELECT COUNT(*) FROM causal_edges)", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((patterns, edges), (2, 0));
+    }
+}
@@ -28,7 +28,7 @@ pub struct CausalStore {
 impl CausalStore {
     /// Open or create a causal store at the given database path
     pub fn open(db_path: &Path) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
+        let conn = super::db::open(db_path)?;
         Ok(Self { conn })
     }
 
@@ -38,100 +38,46 @@ impl CausalStore {
             db_path,
             rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        super::encryption::unlock(&conn)?;
         Ok(Self { conn })
     }
 
-    /// Record a co-occurrence of two patterns with an outcome
-    /// This updates the lift score based on whether they succeeded together
-    pub fn record_cooccurrence(
-        &self,
-        pattern_a: i64,
-        pattern_b: i64,
-        both_succeeded: bool,
-    ) -> Result<()> {
-        // Skip self-referential edges - a pattern cannot conflict with itself
-        if pattern_a == pattern_b {
-            debug!("Skipping self-referential causal edge for pattern {}", pattern_a);
-            return Ok(());
-        }
-
-        // Ensure consistent ordering (smaller ID first)
-        let (id_a, id_b) = if pattern_a < pattern_b {
-            (pattern_a, pattern_b)
-        } else {
-            (pattern_b, pattern_a)
-        };
-
-        // Check if edge exists
-        let existing: Option<(i64, f64, i64)> = self.conn.query_row(
-            "SELECT id, lift, co_occurrences FROM causal_edges WHERE pattern_a_id = ? AND pattern_b_id = ?",
-            params![id_a, id_b],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-        ).ok();
-
-        match existing {
-            Some((id, current_lift, co_count)) => {
-                // Update existing edge with exponential moving average
-                // Success pushes lift up toward 1.5, failure pushes it down toward 0.3
-                // This ensures repeated failures can drive lift below 0.5 threshold
-                let outcome_value = if both_succeeded { 1.5 } else { 0.3 };
-                let alpha = 0.3; // Learning rate
-                let new_lift = current_lift * (1.0 - alpha) + outcome_value * alpha;
-
-                self.conn.execute(
-                    "UPDATE causal_edges SET lift = ?, co_occurrences = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-                    params![new_lift, co_count + 1, id],
-                )?;
-                debug!("Updated causal edge {} -> {}: lift {:.2} -> {:.2}", id_a, id_b, current_lift, new_lift);
-            }
-            None => {
-                // Create new edge
-                let initial_lift = if both_succeeded { 1.2 } else { 0.8 };
-
-                self.conn.execute(
-                    "INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (?, ?, ?, 1)",
-                    params![id_a, id_b, initial_lift],
-                )?;
-                debug!("Created causal edge {} -> {}: lift {:.2}", id_a, id_b, initial_lift);
+    /// Record co-occurrences of pattern pairs in one write transaction
+    ///
+    /// Each pair updates the lift score based on whether the two patterns
+    /// succeeded together. Returns the number of co-occurrences recorded, not counting
+    /// self-referential pairs.
+    pub fn record_cooccurrences(&mut self, pairs: &[(i64, i64, bool)]) -> Result<usize> {
+        let tx = super::db::write_transaction(&mut self.conn)?;
+        let mut recorded = 0;
+        for &(pattern_a, pattern_b, both_succeeded) in pairs {
+            if pattern_a != pattern_b {
+                record_edge(&tx, pattern_a, pattern_b, both_succeeded)?;
+                recorded += 1;
             }
         }
-
-        Ok(())
+        tx.commit()?;
+        Ok(recorded)
     }
 
     /// Get all conflicting patterns for a given pattern ID
     /// Returns pattern IDs that have lift < 0.5 (conflict threshold)
+    #[allow(dead_code)]
     pub fn get_conflicts(&self, pattern_id: i64) -> Result<Vec<i64>> {
-        let mut stmt = self.conn.prepare(
-            r#"
-            SELECT pattern_b_id FROM causal_edges
-            WHERE pattern_a_id = ? AND lift < 0.5 AND co_occurrences >= 3
-            UNION
-            SELECT pattern_a_id FROM causal_edges
-            WHERE pattern_b_id = ? AND lift < 0.5 AND co_occurrences >= 3
-            "#,
-        )?;
+        conflicts(&self.conn, pattern_id)
+    }
 
-        let conflicts = stmt.query_map(params![pattern_id, pattern_id], |row| row.get(0))?;
-        conflicts.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    /// Select up to `limit` patterns from a ranked candidate list (best first)
+    ///
+    /// See [`select_compatible`].
+    pub fn select_compatible(&self, ranked: &[i64], limit: usize) -> Result<Vec<i64>> {
+        select_compatible(&self.conn, ranked, limit)
     }
 
     /// Get all synergistic patterns for a given pattern ID
     /// Returns pattern IDs that have lift > 1.5 (synergy threshold)
-    #[allow(dead_code)]
     pub fn get_synergies(&self, pattern_id: i64) -> Result<Vec<i64>> {
-        let mut stmt = self.conn.prepare(
-            r#"
-            SELECT pattern_b_id FROM causal_edges
-            WHERE pattern_a_id = ? AND lift > 1.5 AND co_occurrences >= 3
-            UNION
-            SELECT pattern_a_id FROM causal_edges
-            WHERE pattern_b_id = ? AND lift > 1.5 AND co_occurrences >= 3
-            "#,
-        )?;
-
-        let synergies = stmt.query_map(params![pattern_id, pattern_id], |row| row.get(0))?;
-        synergies.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        synergies(&self.conn, pattern_id)
     }
 
     /// Get all edges for a pattern (for debugging/stats)
@@ -192,6 +138,134 @@ impl CausalStore {
     }
 }
 
+/// Pattern IDs in a conflict (lift < 0.5) with `pattern_id`
+fn conflicts(conn: &Connection, pattern_id: i64) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare_cached(
+        r#"
+        SELECT pattern_b_id FROM causal_edges
+        WHERE pattern_a_id = ? AND lift < 0.5 AND co_occurrences >= 3
+        UNION
+        SELECT pattern_a_id FROM causal_edges
+        WHERE pattern_b_id = ? AND lift < 0.5 AND co_occurrences >= 3
+        "#,
+    )?;
+
+    let conflicts = stmt.query_map(params![pattern_id, pattern_id], |row| row.get(0))?;
+    conflicts.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+}
+
+/// Pattern IDs in a synergy (lift > 1.5) with `pattern_id`
+fn synergies(conn: &Connection, pattern_id: i64) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare_cached(
+        r#"
+        SELECT pattern_b_id FROM causal_edges
+        WHERE pattern_a_id = ? AND lift > 1.5 AND co_occurrences >= 3
+        UNION
+        SELECT pattern_a_id FROM causal_edges
+        WHERE pattern_b_id = ? AND lift > 1.5 AND co_occurrences >= 3
+        "#,
+    )?;
+
+    let synergies = stmt.query_map(params![pattern_id, pattern_id], |row| row.get(0))?;
+    synergies.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+}
+
+/// Select up to `limit` patterns from a ranked candidate list (best first)
+///
+/// The top candidate is always kept. Synergistic companions of the top
+/// pattern are considered next, then the remaining candidates in rank
+/// order. A candidate that conflicts with any already-selected pattern
+/// is dropped, so the lower-ranked side of a conflict never makes it in.
+///
+/// Takes any open connection, so callers that already hold one (the
+/// inject hot path) don't need to open a second.
+pub fn select_compatible(conn: &Connection, ranked: &[i64], limit: usize) -> Result<Vec<i64>> {
+    let Some(&top) = ranked.first() else {
+        return Ok(Vec::new());
+    };
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+
+    let synergies = synergies(conn, top)?;
+    let (companions, rest): (Vec<i64>, Vec<i64>) = ranked[1..]
+        .iter()
+        .partition(|id| synergies.contains(id));
+
+    let mut selected = vec![top];
+    let mut blocked = conflicts(conn, top)?;
+
+    for id in companions.into_iter().chain(rest) {
+        if selected.len() >= limit {
+            break;
+        }
+        if selected.contains(&id) {
+            debug!("Skipping pattern {} already selected", id);
+            continue;
+        }
+        if blocked.contains(&id) {
+            debug!("Skipping pattern {} due to causal conflict", id);
+            continue;
+        }
+        blocked.extend(conflicts(conn, id)?);
+        selected.push(id);
+    }
+
+    Ok(selected)
+}
+
+/// Insert or update the edge between two patterns
+fn record_edge(conn: &Connection, pattern_a: i64, pattern_b: i64, both_succeeded: bool) -> Result<()> {
+    // Skip self-referential edges - a pattern cannot conflict with itself
+    if pattern_a == pattern_b {
+        debug!("Skipping self-referential causal edge for pattern {}", pattern_a);
+        return Ok(());
+    }
+
+    // Ensure consistent ordering (smaller ID first)
+    let (id_a, id_b) = if pattern_a < pattern_b {
+        (pattern_a, pattern_b)
+    } else {
+        (pattern_b, pattern_a)
+    };
+
+    // Check if edge exists
+    let existing: Option<(i64, f64, i64)> = conn.query_row(
+        "SELECT id, lift, co_occurrences FROM causal_edges WHERE pattern_a_id = ? AND pattern_b_id = ?",
+        params![id_a, id_b],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    ).ok();
+
+    match existing {
+        Some((id, current_lift, co_count)) => {
+            // Update existing edge with exponential moving average
+            // Success pushes lift up toward 1.5, failure pushes it down toward 0.3
+            // This ensures repeated failures can drive lift below 0.5 threshold
+            let outcome_value = if both_succeeded { 1.5 } else { 0.3 };
+            let alpha = 0.3; // Learning rate
+            let new_lift = current_lift * (1.0 - alpha) + outcome_value * alpha;
+
+            conn.execute(
+                "UPDATE causal_edges SET lift = ?, co_occurrences = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+                params![new_lift, co_count + 1, id],
+            )?;
+            debug!("Updated causal edge {} -> {}: lift {:.2} -> {:.2}", id_a, id_b, current_lift, new_lift);
+        }
+        None => {
+            // Create new edge
+            let initial_lift = if both_succeeded { 1.2 } else { 0.8 };
+
+            conn.execute(
+                "INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (?, ?, ?, 1)",
+                params![id_a, id_b, initial_lift],
+            )?;
+            debug!("Created causal edge {} -> {}: lift {:.2}", id_a, id_b, initial_lift);
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,9 +310,9 @@ mod tests {
 
     #[test]
     fn test_record_cooccurrence_creates_edge() {
-        let (_tmp, store) = setup_test_db();
+        let (_tmp, mut store) = setup_test_db();
 
-        store.record_cooccurrence(1, 2, true).unwrap();
+        store.record_cooccurrences(&[(1, 2, true)]).unwrap();
 
         let count = store.count().unwrap();
         assert_eq!(count, 1);
@@ -246,12 +320,10 @@ mod tests {
 
     #[test]
     fn test_record_cooccurrence_updates_lift() {
-        let (_tmp, store) = setup_test_db();
+        let (_tmp, mut store) = setup_test_db();
 
         // Record several failures - lift should decrease
-        for _ in 0..5 {
-            store.record_cooccurrence(1, 2, false).unwrap();
-        }
+        store.record_cooccurrences(&[(1, 2, false); 5]).unwrap();
 
         let edges = store.get_edges(1).unwrap();
         assert_eq!(edges.len(), 1);
@@ -260,13 +332,11 @@ mod tests {
 
     #[test]
     fn test_get_conflicts() {
-        let (_tmp, store) = setup_test_db();
+        let (_tmp, mut store) = setup_test_db();
 
         // Record many failures to create a conflict
         // With EMA (alpha=0.3) starting at 0.8, need ~20 failures to get below 0.5
-        for _ in 0..20 {
-            store.record_cooccurrence(1, 2, false).unwrap();
-        }
+        store.record_cooccurrences(&[(1, 2, false); 20]).unwrap();
 
         // Verify lift dropped below threshold
         let edges = store.get_edges(1).unwrap();
@@ -280,13 +350,51 @@ mod tests {
 
     #[test]
     fn test_edge_ordering() {
-        let (_tmp, store) = setup_test_db();
+        let (_tmp, mut store) = setup_test_db();
 
         // Regardless of order passed, should create same edge
-        store.record_cooccurrence(2, 1, true).unwrap();
-        store.record_cooccurrence(1, 2, true).unwrap();
+        store.record_cooccurrences(&[(2, 1, true)]).unwrap();
+        store.record_cooccurrences(&[(1, 2, true)]).unwrap();
 
         let count = store.count().unwrap();
         assert_eq!(count, 1, "Should only create one edge regardless of order");
     }
+
+    #[test]
+    fn test_record_cooccurrences_in_one_batch() {
+        let (_tmp, mut store) = setup_test_db();
+
+        let recorded = store
+            .record_cooccurrences(&[(2, 1, true), (1, 2, true), (3, 3, false), (1, 3, false)])
+            .unwrap();
+        assert_eq!(recorded, 3, "Self-referential pair should be skipped");
+        assert_eq!(store.count().unwrap(), 2);
+
+        let edges = store.get_edges(1).unwrap();
+        let edge = edges.iter().find(|e| e.pattern_b_id == 2).unwrap();
+        assert_eq!(edge.co_occurrences, 2);
+    }
+
+    #[test]
+    fn test_select_compatible_drops_conflicts() {
+        let (_tmp, mut store) = setup_test_db();
+
+        store.record_cooccurrences(&[(1, 2, false); 20]).unwrap();
+
+        let selected = store.select_compatible(&[1, 2, 3], 3).unwrap();
+        assert_eq!(selected, vec![1, 3], "Lower-ranked conflicting pattern should be dropped");
+    }
+
+    #[test]
+    fn test_select_compatible_prefers_synergies() {
+        let (_tmp, store) = setup_test_db();
+
+        store.conn.execute(
+            "INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (1, 3, 1.8, 5)",
+            [],
+        ).unwrap();
+
+        let selected = store.select_compatible(&[1, 2, 3], 2).unwrap();
+        assert_eq!(selected, vec![1, 3], "Synergistic companion should be pulled forward");
+    }
 }
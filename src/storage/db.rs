@@ -0,0 +1,96 @@
+//! Shared database connections
+//!
+//! Hooks, the daemon, `mana watch` and CLI commands can all touch
+//! metadata.sqlite at once. Every connection that writes is opened here so
+//! they agree on WAL journaling (readers never block the writer) and wait
+//! out each other's locks instead of failing with SQLITE_BUSY.
+//!
+//! The latency-sensitive injection path keeps its own read-only open in
+//! [`PatternStore::open_readonly`](super::PatternStore::open_readonly).
+//! In read-only mode (see [`read_only`](super::read_only)) [`open`] gives out
+//! read-only connections for databases in the data directory. Both kinds of
+//! connection are keyed here when the database is encrypted.
+
+use anyhow::Result;
+use rusqlite::{Connection, OpenFlags, Transaction, TransactionBehavior};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long a connection waits for another writer before giving up
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Open a read-write connection in WAL mode
+pub fn open(db_path: &Path) -> Result<Connection> {
+    if super::read_only::protects(db_path) {
+        return open_readonly(db_path);
+    }
+    let conn = Connection::open(db_path)?;
+    super::encryption::unlock(&conn)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    // journal_mode is persistent; this is a no-op once the file is in WAL mode
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(conn)
+}
+
+/// Open a read-only connection that waits on locks like a writer would
+pub fn open_readonly(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    super::encryption::unlock(&conn)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// Start a transaction that takes the write lock up front
+///
+/// A deferred transaction that reads first and then writes can fail with
+/// SQLITE_BUSY when another writer got in between, without the busy
+/// handler ever running. Taking the lock immediately avoids that.
+pub fn write_transaction(conn: &mut Connection) -> Result<Transaction<'_>> {
+    Ok(conn.transaction_with_behavior(TransactionBehavior::Immediate)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_writers_wait_instead_of_failing() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let mut conn = open(&db_path).unwrap();
+        conn.execute_batch("CREATE TABLE t (n INTEGER)").unwrap();
+
+        let mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+
+        // Hold the write lock briefly from another thread
+        let path = db_path.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let holder = std::thread::spawn(move || {
+            let mut other = open(&path).unwrap();
+            let txn = write_transaction(&mut other).unwrap();
+            txn.execute("INSERT INTO t VALUES (1)", []).unwrap();
+            tx.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            txn.commit().unwrap();
+        });
+        rx.recv().unwrap();
+
+        // A reader is not blocked by the open write transaction
+        let reader = open_readonly(&db_path).unwrap();
+        let seen: i64 = reader.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(seen, 0);
+
+        let txn = write_transaction(&mut conn).unwrap();
+        txn.execute("INSERT INTO t VALUES (2)", []).unwrap();
+        txn.commit().unwrap();
+        holder.join().unwrap();
+
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,271 @@
+//! Encryption at rest for metadata.sqlite
+//!
+//! Builds with the `sqlcipher` feature can keep the database encrypted with
+//! SQLCipher. `[storage] encrypt = true` makes a new database encrypted;
+//! `mana db encrypt` and `mana db decrypt` convert an existing one. Whether
+//! a file is encrypted is read from its header, so plaintext files such as
+//! old backups and exports still open without a key.
+//!
+//! The key comes from `MANA_DB_KEY`, or else from the OS keychain (macOS
+//! `security`, or `secret-tool` on Linux), stored per data directory. A key
+//! of 64 hex digits is used as the raw key; anything else is a passphrase
+//! run through SQLCipher's key derivation, which adds a noticeable delay to
+//! every open, the injection hook's included.
+//!
+//! The injection snapshot would hold pattern text in the clear, so it is not
+//! written for an encrypted database; the hook reads the database instead.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, warn};
+
+/// Environment variable holding the database key
+pub const KEY_ENV: &str = "MANA_DB_KEY";
+
+/// Keychain service the key is stored under, with the data directory as account
+const KEYCHAIN_SERVICE: &str = "mana-db";
+
+/// First bytes of every unencrypted SQLite file
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Whether the file at `db_path` is an encrypted database
+///
+/// Missing and empty files are not.
+pub fn is_encrypted(db_path: &Path) -> bool {
+    use std::io::Read;
+    let mut header = [0u8; 16];
+    match std::fs::File::open(db_path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => &header != PLAINTEXT_HEADER,
+        Err(_) => false,
+    }
+}
+
+/// Key a freshly opened connection if its database is (or is to be) encrypted
+///
+/// An encrypted file gets the key; a new one gets it when `[storage] encrypt`
+/// is on. A plaintext file opens as it is, with a warning if it should have
+/// been encrypted.
+pub fn unlock(conn: &Connection) -> Result<()> {
+    let Some(db_path) = conn.path().filter(|p| !p.is_empty()).map(PathBuf::from) else {
+        return Ok(());
+    };
+    if !is_encrypted(&db_path) {
+        if !encrypted_dir().is_some_and(|dir| db_path.starts_with(dir)) {
+            return Ok(());
+        }
+        if std::fs::metadata(&db_path).is_ok_and(|m| m.len() > 0) {
+            warn_unencrypted(&db_path);
+            return Ok(());
+        }
+    }
+    apply_key(conn, &key(&crate::profile::resolve_mana_dir()?)?)
+}
+
+/// The data directory if `[storage] encrypt` is on, read once per process
+fn encrypted_dir() -> Option<&'static Path> {
+    static DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DIR.get_or_init(|| {
+        crate::profile::resolve_mana_dir()
+            .ok()
+            .filter(|dir| crate::config::load_config(dir).storage.encrypt)
+    })
+    .as_deref()
+}
+
+fn warn_unencrypted(db_path: &Path) {
+    static WARNED: OnceLock<()> = OnceLock::new();
+    if WARNED.set(()).is_ok() {
+        warn!("{} is not encrypted although [storage] encrypt is on; run 'mana db encrypt'", db_path.display());
+    }
+}
+
+/// The database key for `mana_dir`: `MANA_DB_KEY`, then the OS keychain
+pub fn key(mana_dir: &Path) -> Result<String> {
+    if let Some(key) = std::env::var(KEY_ENV).ok().filter(|k| !k.is_empty()) {
+        return Ok(key);
+    }
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(key) = cache.lock().ok().and_then(|c| c.get(mana_dir).cloned()) {
+        return Ok(key);
+    }
+    let Some(key) = keychain_lookup(mana_dir) else {
+        bail!("No database key found: set {} or store one in the OS keychain", KEY_ENV);
+    };
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(mana_dir.to_path_buf(), key.clone());
+    }
+    Ok(key)
+}
+
+/// A new random raw key (64 hex digits)
+pub fn generate_key() -> String {
+    use rand::RngCore;
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Value for `PRAGMA key` / `ATTACH ... KEY`: raw for 64 hex digits, else a passphrase
+fn key_spec(key: &str) -> String {
+    if key.len() == 64 && key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        format!("x'{}'", key)
+    } else {
+        key.to_string()
+    }
+}
+
+#[cfg(feature = "sqlcipher")]
+fn apply_key(conn: &Connection, key: &str) -> Result<()> {
+    conn.pragma_update(None, "key", key_spec(key))?;
+    // The key is only checked on first read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .map_err(|_| anyhow::anyhow!("Wrong database key (from {} or the OS keychain)", KEY_ENV))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+fn apply_key(_conn: &Connection, _key: &str) -> Result<()> {
+    require_sqlcipher()
+}
+
+/// Fail unless this build links SQLCipher
+pub fn require_sqlcipher() -> Result<()> {
+    if !cfg!(feature = "sqlcipher") {
+        bail!("Database encryption needs a build of mana with SQLCipher support (--features sqlcipher)");
+    }
+    Ok(())
+}
+
+/// Rewrite the plaintext database at `db_path` encrypted with `key`
+pub fn encrypt(db_path: &Path, key: &str) -> Result<()> {
+    if is_encrypted(db_path) {
+        bail!("{} is already encrypted", db_path.display());
+    }
+    rewrite(db_path, None, key)?;
+    info!("Encrypted {}", db_path.display());
+    Ok(())
+}
+
+/// Rewrite the encrypted database at `db_path` as plaintext
+pub fn decrypt(db_path: &Path, key: &str) -> Result<()> {
+    if !is_encrypted(db_path) {
+        bail!("{} is not encrypted", db_path.display());
+    }
+    rewrite(db_path, Some(key), "")?;
+    info!("Decrypted {}", db_path.display());
+    Ok(())
+}
+
+/// Export the database into a copy keyed with `to` ("" for plaintext), then swap it in
+fn rewrite(db_path: &Path, from: Option<&str>, to: &str) -> Result<()> {
+    require_sqlcipher()?;
+    let tmp = db_path.with_extension("sqlite.rekey");
+    let _ = std::fs::remove_file(&tmp);
+
+    let conn = Connection::open(db_path)?;
+    if let Some(key) = from {
+        apply_key(&conn, key)?;
+    }
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    let to = if to.is_empty() { String::new() } else { key_spec(to) };
+    conn.execute("ATTACH DATABASE ?1 AS rekeyed KEY ?2", rusqlite::params![tmp.to_string_lossy(), to])?;
+    conn.query_row("SELECT sqlcipher_export('rekeyed')", [], |_| Ok(()))
+        .context("Failed to copy the database")?;
+    conn.execute_batch("DETACH DATABASE rekeyed;")?;
+    drop(conn);
+
+    // Same swap as a restore: the WAL was checkpointed into the old file
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+    }
+    std::fs::rename(&tmp, db_path)?;
+    Ok(())
+}
+
+/// Store `key` in the OS keychain for `mana_dir`
+pub fn store_key(mana_dir: &Path, key: &str) -> Result<()> {
+    let account = mana_dir.to_string_lossy();
+    let status = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["add-generic-password", "-U", "-s", KEYCHAIN_SERVICE, "-a", &account, "-w", key])
+            .stdout(Stdio::null())
+            .status()
+    } else {
+        use std::io::Write;
+        Command::new("secret-tool")
+            .args(["store", "--label", "MANA database key", "service", KEYCHAIN_SERVICE, "account", &account])
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                child.stdin.take().map(|mut stdin| stdin.write_all(key.as_bytes())).transpose()?;
+                child.wait()
+            })
+    };
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => bail!("The keychain refused the key ({})", status),
+        Err(e) => bail!("No OS keychain available ({}); set {} instead", e, KEY_ENV),
+    }
+}
+
+fn keychain_lookup(mana_dir: &Path) -> Option<String> {
+    let account = mana_dir.to_string_lossy();
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security").args(["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a", &account, "-w"]).output()
+    } else {
+        Command::new("secret-tool").args(["lookup", "service", KEYCHAIN_SERVICE, "account", &account]).output()
+    };
+    let output = output.ok().filter(|o| o.status.success())?;
+    let key = String::from_utf8(output.stdout).ok()?.trim_end_matches('\n').to_string();
+    (!key.is_empty()).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plaintext_files_need_no_key() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        assert!(!is_encrypted(&db_path));
+        super::super::db::open(&db_path).unwrap().execute_batch("CREATE TABLE t (n INTEGER)").unwrap();
+        assert!(!is_encrypted(&db_path));
+
+        std::fs::write(&db_path, [7u8; 64]).unwrap();
+        assert!(is_encrypted(&db_path));
+
+        assert_eq!(key_spec(&"ab".repeat(32)), format!("x'{}'", "ab".repeat(32)));
+        assert_eq!(key_spec("correct horse"), "correct horse");
+        assert_eq!(generate_key().len(), 64);
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn test_encrypt_and_decrypt_round_trip() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let conn = super::super::db::open(&db_path).unwrap();
+        conn.execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (42);").unwrap();
+        drop(conn);
+
+        let key = generate_key();
+        encrypt(&db_path, &key).unwrap();
+        assert!(is_encrypted(&db_path));
+        let conn = Connection::open(&db_path).unwrap();
+        assert!(apply_key(&conn, "wrong").is_err());
+        let conn = Connection::open(&db_path).unwrap();
+        apply_key(&conn, &key).unwrap();
+        assert_eq!(conn.query_row("SELECT n FROM t", [], |row| row.get::<_, i64>(0)).unwrap(), 42);
+        drop(conn);
+
+        decrypt(&db_path, &key).unwrap();
+        assert!(!is_encrypted(&db_path));
+        assert_eq!(Connection::open(&db_path).unwrap().query_row("SELECT n FROM t", [], |row| row.get::<_, i64>(0)).unwrap(), 42);
+    }
+}
@@ -0,0 +1,135 @@
+//! Pattern selection filters
+//!
+//! Shared by `mana patterns list` and `mana export` so both accept the same
+//! `--tool`, `--category`, `--min-score`, `--tag`, `--project` and `--since`
+//! flags. Filters compile to a parameterized WHERE clause over `patterns p`.
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::collections::HashSet;
+
+/// Which patterns to select; empty fields match everything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatternFilter {
+    pub tool: Option<String>,
+    pub category: Option<String>,
+    /// Minimum score (successes minus failures)
+    pub min_score: Option<i64>,
+    /// Pattern must carry every one of these tags
+    pub tags: Vec<String>,
+    /// Project hash (see [`super::projects::resolve`])
+    pub project: Option<String>,
+    /// Only patterns created or used at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only patterns created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only patterns with (true) or without (false) an embedding
+    pub has_embedding: Option<bool>,
+}
+
+impl PatternFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// SQL conditions (each starting with `AND`) and their parameters
+    pub fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut sql = String::new();
+        let mut values = Vec::new();
+
+        if let Some(tool) = &self.tool {
+            sql.push_str(" AND p.tool_type = ? COLLATE NOCASE");
+            values.push(Value::Text(tool.clone()));
+        }
+        if let Some(category) = &self.category {
+            sql.push_str(" AND p.command_category = ? COLLATE NOCASE");
+            values.push(Value::Text(category.clone()));
+        }
+        if let Some(min_score) = self.min_score {
+            sql.push_str(" AND (p.success_count - p.failure_count) >= ?");
+            values.push(Value::Integer(min_score));
+        }
+        for tag in &self.tags {
+            sql.push_str(" AND EXISTS (SELECT 1 FROM pattern_tags t WHERE t.pattern_id = p.id AND t.tag = ?)");
+            values.push(Value::Text(tag.clone()));
+        }
+        if let Some(project) = &self.project {
+            sql.push_str(" AND EXISTS (SELECT 1 FROM pattern_projects pp WHERE pp.pattern_id = p.id AND pp.project_hash = ?)");
+            values.push(Value::Text(project.clone()));
+        }
+        if let Some(since) = self.since {
+            // CURRENT_TIMESTAMP format, so the comparison is lexicographic
+            sql.push_str(" AND COALESCE(p.last_used, p.created_at) >= ?");
+            values.push(Value::Text(since.format("%Y-%m-%d %H:%M:%S").to_string()));
+        }
+        if let Some(after) = self.created_after {
+            sql.push_str(" AND p.created_at >= ?");
+            values.push(Value::Text(after.format("%Y-%m-%d %H:%M:%S").to_string()));
+        }
+        match self.has_embedding {
+            Some(true) => sql.push_str(" AND p.embedding IS NOT NULL"),
+            Some(false) => sql.push_str(" AND p.embedding IS NULL"),
+            None => {}
+        }
+        (sql, values)
+    }
+
+    /// Ids of all patterns matching the filter
+    pub fn matching_ids(&self, conn: &Connection) -> Result<HashSet<i64>> {
+        let (conditions, values) = self.where_clause();
+        let mut stmt = conn.prepare(&format!("SELECT p.id FROM patterns p WHERE 1=1{}", conditions))?;
+        let ids = stmt.query_map(params_from_iter(values), |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+        Ok(ids)
+    }
+}
+
+/// Parse a `--since` value: a duration back from now (`12h`, `7d`, `2w`) or a date (`2024-05-01`)
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    Ok(Utc::now() - crate::reflection::parse_since(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, created_at)
+             VALUES (1, 'a', 'Bash', 'cargo', 'cargo build', 5, 0, '2024-01-01 00:00:00'),
+                    (2, 'b', 'Bash', 'npm', 'npm test', 1, 3, '2024-06-01 00:00:00'),
+                    (3, 'c', 'Edit', 'rs', 'edit lib.rs', 2, 0, '2024-06-01 00:00:00');
+             UPDATE patterns SET embedding = x'00' WHERE id = 1;
+             INSERT INTO pattern_tags (pattern_id, tag) VALUES (1, 'team'), (3, 'team');
+             INSERT INTO pattern_projects (pattern_id, project_hash) VALUES (3, 'abc');",
+        )
+        .unwrap();
+
+        let ids = |filter: PatternFilter| {
+            let mut ids: Vec<i64> = filter.matching_ids(&conn).unwrap().into_iter().collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(PatternFilter::default()), vec![1, 2, 3]);
+        assert_eq!(ids(PatternFilter { tool: Some("bash".into()), ..Default::default() }), vec![1, 2]);
+        assert_eq!(ids(PatternFilter { min_score: Some(1), category: Some("cargo".into()), ..Default::default() }), vec![1]);
+        assert_eq!(ids(PatternFilter { tags: vec!["team".into()], ..Default::default() }), vec![1, 3]);
+        assert_eq!(ids(PatternFilter { project: Some("abc".into()), ..Default::default() }), vec![3]);
+        assert_eq!(ids(PatternFilter { since: Some(parse_since("2024-03-01").unwrap()), ..Default::default() }), vec![2, 3]);
+        let june = parse_since("2024-06-01").unwrap();
+        assert_eq!(ids(PatternFilter { created_after: Some(june), ..Default::default() }), vec![2, 3]);
+        assert_eq!(ids(PatternFilter { has_embedding: Some(true), ..Default::default() }), vec![1]);
+        assert_eq!(
+            ids(PatternFilter { has_embedding: Some(false), tool: Some("it's".into()), ..Default::default() }),
+            Vec::<i64>::new()
+        );
+        assert!(parse_since("soon").is_err());
+    }
+}
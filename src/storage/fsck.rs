@@ -0,0 +1,121 @@
+//! Orphan checks (`mana db fsck`)
+//!
+//! Only some tables declare foreign keys on `patterns`: history, top
+//! patterns, improvement history and skill member lists don't, and
+//! databases created before a constraint was added never got it. Patterns
+//! deleted other than through `patterns::delete_with_dependents` (as older
+//! versions did) can leave those rows pointing at nothing. This finds them
+//! and, unless told not to, removes them. Vectors for missing patterns are
+//! the embedding index's to clean up (see `embeddings::remove_orphans`).
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::patterns::{detach_from_skills, DEPENDENT_TABLES};
+
+/// Rows in one table naming patterns that don't exist
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Orphans {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub rows: usize,
+}
+
+/// What a check found (and, when repairing, fixed)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FsckReport {
+    pub orphans: Vec<Orphans>,
+    /// Skills listing missing patterns
+    pub skills: usize,
+    /// Vectors for missing patterns
+    pub vectors: usize,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphans.is_empty() && self.skills == 0 && self.vectors == 0
+    }
+}
+
+/// Ids of every pattern in the database
+pub fn pattern_ids(conn: &Connection) -> Result<HashSet<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM patterns")?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(ids)
+}
+
+/// Find rows naming missing patterns, deleting them unless `dry_run`
+///
+/// Repairs run in one transaction.
+pub fn check(conn: &Connection, dry_run: bool) -> Result<FsckReport> {
+    let mut report = FsckReport::default();
+    let tx = conn.unchecked_transaction()?;
+    for &(table, column) in DEPENDENT_TABLES {
+        if !super::has_column(&tx, table, column) {
+            continue;
+        }
+        let orphaned = format!("{} IS NOT NULL AND {} NOT IN (SELECT id FROM patterns)", column, column);
+        let rows: i64 = tx.query_row(&format!("SELECT COUNT(*) FROM {} WHERE {}", table, orphaned), [], |row| row.get(0))?;
+        if rows == 0 {
+            continue;
+        }
+        if !dry_run {
+            tx.execute(&format!("DELETE FROM {} WHERE {}", table, orphaned), [])?;
+        }
+        report.orphans.push(Orphans { table, column, rows: rows as usize });
+    }
+
+    let live = pattern_ids(&tx)?;
+    if dry_run {
+        // Count without changing anything: the transaction is rolled back
+        report.skills = detach_from_skills(&tx, |id| live.contains(&id))?;
+        tx.rollback()?;
+    } else {
+        report.skills = detach_from_skills(&tx, |id| live.contains(&id))?;
+        tx.commit()?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fsck_finds_and_repairs_orphans() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        crate::reflection::init_reflection_tables(&conn).unwrap();
+        // As in a database from before the constraints
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (1, 'a', 'Bash', 'cargo build'), (2, 'b', 'Bash', 'cargo test');
+             INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift) VALUES (1, 2, 1.5), (1, 9, 1.2);
+             INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence) VALUES ('t1', 1, 'EFFECTIVE', 0.9), ('t2', 9, 'HARMFUL', 0.8), ('t3', NULL, 'NEUTRAL', 0.5);
+             INSERT INTO pattern_tags (pattern_id, tag) VALUES (9, 'ci');
+             INSERT INTO skills (name, pattern_ids) VALUES ('build', '1,9'), ('gone', '8,9');",
+        )
+        .unwrap();
+
+        let found = check(&conn, true).unwrap();
+        assert_eq!(found.orphans.iter().map(|o| (o.table, o.rows)).collect::<Vec<_>>(), vec![
+            ("pattern_tags", 1),
+            ("reflection_verdicts", 1),
+            ("causal_edges", 1)
+        ]);
+        assert_eq!(found.skills, 2);
+        // A dry run leaves everything in place
+        assert_eq!(check(&conn, true).unwrap().skills, 2);
+
+        assert!(!check(&conn, false).unwrap().is_clean());
+        assert!(check(&conn, true).unwrap().is_clean());
+        let (verdicts, skill): (i64, String) = conn
+            .query_row("SELECT (SELECT COUNT(*) FROM reflection_verdicts), (SELECT group_concat(pattern_ids, ';') FROM skills)", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((verdicts, skill.as_str()), (2, "1"));
+    }
+}
@@ -0,0 +1,381 @@
+//! Full backups (`mana db backup --full`)
+//!
+//! A plain backup only copies metadata.sqlite, but the embedding index,
+//! learning state and P2P CRDT state all describe the same patterns, and
+//! restoring the database alone leaves vectors for patterns that no longer
+//! exist (or none for ones that do). A full backup takes all of them at one
+//! moment, holding the learning and index locks, and packs them into
+//! `<mana_dir>/backups/mana-<stamp>.tar` with a manifest of sizes,
+//! checksums and pattern and vector counts.
+//!
+//! Restoring checks the manifest against every file, the database's
+//! integrity, and that every vector belongs to a pattern in the database,
+//! before anything in the data directory is replaced.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use super::maintenance::{self, BACKUP_DIR, DB_FILE};
+
+/// Extension of full backups
+pub const EXTENSION: &str = "tar";
+
+const MANIFEST: &str = "manifest.json";
+
+/// Files besides the database a full backup carries, when present
+const STATE_FILES: &[&str] = &["vectors.usearch", "learning-state.json", "p2p-crdt.json"];
+
+const VECTORS: &str = "vectors.usearch";
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// Archive members by name, manifest aside
+type Files = Vec<(String, Vec<u8>)>;
+
+/// One file in a full backup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// What a full backup holds, checked on restore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub created_at: String,
+    pub patterns: usize,
+    pub vectors: usize,
+    pub files: Vec<ManifestFile>,
+}
+
+/// Whether `path` is a full backup rather than a database copy
+pub fn is_full(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == EXTENSION)
+}
+
+/// Write a full backup to `<mana_dir>/backups/`
+///
+/// The caller holds the learning lock so learning state and the database
+/// agree; the index lock is taken here.
+pub fn create(mana_dir: &Path) -> Result<PathBuf> {
+    let db_path = mana_dir.join(DB_FILE);
+    if !db_path.exists() {
+        bail!("No database at {:?}", db_path);
+    }
+    let _index = crate::embeddings::lock::acquire(mana_dir)?;
+
+    let dir = mana_dir.join(BACKUP_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut target = dir.join(format!("mana-{}.{}", stamp, EXTENSION));
+    let mut n = 1;
+    while target.exists() {
+        target = dir.join(format!("mana-{}-{}.{}", stamp, n, EXTENSION));
+        n += 1;
+    }
+
+    let db_copy = dir.join(format!(".mana-{}.{}.sqlite", stamp, std::process::id()));
+    let conn = super::db::open(&db_path)?;
+    conn.execute("VACUUM INTO ?1", rusqlite::params![db_copy.to_string_lossy()])
+        .with_context(|| format!("Failed to copy the database to {:?}", db_copy))?;
+    drop(conn);
+    let db = std::fs::read(&db_copy);
+    let _ = std::fs::remove_file(&db_copy);
+    let mut files = vec![(DB_FILE.to_string(), db?)];
+    for name in STATE_FILES {
+        match std::fs::read(mana_dir.join(name)) {
+            Ok(data) => files.push((name.to_string(), data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", name)),
+        }
+    }
+
+    let (patterns, vectors) = check_consistency(mana_dir, &files)?;
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        patterns,
+        vectors,
+        files: files
+            .iter()
+            .map(|(name, data)| ManifestFile { name: name.clone(), size: data.len() as u64, sha256: sha256(data) })
+            .collect(),
+    };
+
+    let tmp = target.with_extension("tar.tmp");
+    let result = write_archive(&tmp, &manifest, &files).and_then(|()| Ok(std::fs::rename(&tmp, &target)?));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result?;
+    info!("Wrote full backup to {:?}", target);
+    Ok(target)
+}
+
+/// Read and check a full backup without restoring it
+pub fn verify(mana_dir: &Path, path: &Path) -> Result<Manifest> {
+    let (manifest, files) = read_archive(path)?;
+    verify_files(&manifest, &files)?;
+    let (patterns, vectors) = check_consistency(mana_dir, &files)?;
+    if (patterns, vectors) != (manifest.patterns, manifest.vectors) {
+        bail!(
+            "Backup holds {} patterns and {} vectors but its manifest says {} and {}",
+            patterns,
+            vectors,
+            manifest.patterns,
+            manifest.vectors
+        );
+    }
+    Ok(manifest)
+}
+
+/// Replace the database, embedding index, learning state and CRDT state with a full backup
+///
+/// Nothing changes unless the backup checks out (see [`verify`]). The
+/// current state is saved first (as a full backup if it's consistent), and
+/// that backup's path returned;
+/// files the backup doesn't carry are removed so they can't disagree with
+/// the restored database. The caller holds the learning lock.
+pub fn restore(mana_dir: &Path, path: &Path) -> Result<Option<PathBuf>> {
+    verify(mana_dir, path)?;
+    let (_, files) = read_archive(path)?;
+
+    let db_path = mana_dir.join(DB_FILE);
+    // State that's already inconsistent can't be saved as a full backup,
+    // and a corrupt database may not be copyable at all; restoring over it
+    // is the point
+    let safety = if db_path.exists() { create(mana_dir).or_else(|_| maintenance::backup(mana_dir)).ok() } else { None };
+
+    let _index = crate::embeddings::lock::acquire(mana_dir)?;
+    // Stage everything first so a failed write leaves the old state in place
+    let mut staged = Vec::new();
+    for name in std::iter::once(&DB_FILE).chain(STATE_FILES) {
+        let Some((_, data)) = files.iter().find(|(n, _)| n == name) else {
+            staged.push((name.to_string(), None));
+            continue;
+        };
+        let tmp = mana_dir.join(format!("{}.restore", name));
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        staged.push((name.to_string(), Some(tmp)));
+    }
+
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+    }
+    for (name, tmp) in staged {
+        match tmp {
+            Some(tmp) => std::fs::rename(&tmp, mana_dir.join(&name))?,
+            None => match std::fs::remove_file(mana_dir.join(&name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            },
+        }
+    }
+    info!("Restored full backup from {:?}", path);
+    Ok(safety)
+}
+
+/// Pattern and vector counts, failing if a vector names a missing pattern
+fn check_consistency(mana_dir: &Path, files: &Files) -> Result<(usize, usize)> {
+    let db = files.iter().find(|(n, _)| n == DB_FILE).map(|(_, d)| d).context("Backup has no database")?;
+    let dir = mana_dir.join(BACKUP_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let tmp = dir.join(format!(".check-{}.sqlite", std::process::id()));
+    std::fs::write(&tmp, db)?;
+    let live = (|| {
+        let conn = super::db::open_readonly(&tmp).context("Backup database is not readable")?;
+        let problems = maintenance::integrity_check(&conn)?;
+        if !problems.is_empty() {
+            bail!("Backup database failed integrity check: {}", problems.join("; "));
+        }
+        super::fsck::pattern_ids(&conn)
+    })();
+    let _ = std::fs::remove_file(&tmp);
+    let live: HashSet<i64> = live?;
+
+    let Some((_, index)) = files.iter().find(|(n, _)| n == VECTORS) else {
+        return Ok((live.len(), 0));
+    };
+    let tmp = dir.join(format!(".check-{}.usearch", std::process::id()));
+    std::fs::write(&tmp, index)?;
+    let index = crate::embeddings::VectorIndex::load(&tmp).context("Backup embedding index is not readable");
+    let _ = std::fs::remove_file(&tmp);
+    let index = index?;
+    let missing = index.ids().iter().filter(|id| !live.contains(id)).count();
+    if missing > 0 {
+        bail!("Backup is inconsistent: {} of {} vectors belong to patterns missing from its database", missing, index.len());
+    }
+    Ok((live.len(), index.len()))
+}
+
+fn verify_files(manifest: &Manifest, files: &Files) -> Result<()> {
+    if manifest.version > MANIFEST_VERSION {
+        bail!("Backup manifest version {} is newer than this mana supports", manifest.version);
+    }
+    for expected in &manifest.files {
+        let (_, data) = files
+            .iter()
+            .find(|(n, _)| *n == expected.name)
+            .with_context(|| format!("Backup is missing {}", expected.name))?;
+        if data.len() as u64 != expected.size || sha256(data) != expected.sha256 {
+            bail!("{} in the backup doesn't match its manifest checksum", expected.name);
+        }
+    }
+    if let Some((extra, _)) = files.iter().find(|(n, _)| !manifest.files.iter().any(|f| f.name == *n)) {
+        bail!("Backup contains {} which its manifest doesn't list", extra);
+    }
+    Ok(())
+}
+
+fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Archives are plain ustar: one 512-byte header per file, contents padded
+// to 512 bytes, two zero blocks at the end. Readable with `tar -xf`.
+
+const BLOCK: usize = 512;
+
+fn write_archive(path: &Path, manifest: &Manifest, files: &Files) -> Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    let manifest = serde_json::to_vec_pretty(manifest)?;
+    for (name, data) in std::iter::once((MANIFEST, &manifest)).chain(files.iter().map(|(n, d)| (n.as_str(), d))) {
+        out.write_all(&header(name, data.len() as u64, mtime)?)?;
+        out.write_all(data)?;
+        out.write_all(&vec![0u8; (BLOCK - data.len() % BLOCK) % BLOCK])?;
+    }
+    out.write_all(&[0u8; BLOCK * 2])?;
+    let file = out.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn header(name: &str, size: u64, mtime: u64) -> Result<[u8; BLOCK]> {
+    if name.len() >= 100 {
+        bail!("File name too long for a backup archive: {}", name);
+    }
+    let mut h = [0u8; BLOCK];
+    let mut field = |offset: usize, value: &[u8]| h[offset..offset + value.len()].copy_from_slice(value);
+    field(0, name.as_bytes());
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, format!("{:011o}\0", size).as_bytes());
+    field(136, format!("{:011o}\0", mtime).as_bytes());
+    field(148, b"        ");
+    field(156, b"0");
+    field(257, b"ustar\0");
+    field(263, b"00");
+    let sum: u32 = h.iter().map(|&b| b as u32).sum();
+    h[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+    Ok(h)
+}
+
+fn read_archive(path: &Path) -> Result<(Manifest, Files)> {
+    let mut input = std::io::BufReader::new(
+        std::fs::File::open(path).with_context(|| format!("Failed to open backup {:?}", path))?,
+    );
+    let mut manifest = None;
+    let mut files = Vec::new();
+    loop {
+        let mut h = [0u8; BLOCK];
+        input.read_exact(&mut h).with_context(|| format!("{:?} is not a full backup (truncated archive)", path))?;
+        if h.iter().all(|&b| b == 0) {
+            break;
+        }
+        if &h[257..262] != b"ustar" {
+            bail!("{:?} is not a full backup", path);
+        }
+        let name = String::from_utf8_lossy(&h[..100]).trim_end_matches('\0').to_string();
+        let size = octal(&h[124..136]).with_context(|| format!("Bad size for {} in {:?}", name, path))?;
+        let mut data = vec![0u8; size as usize];
+        input.read_exact(&mut data).with_context(|| format!("{:?} is truncated inside {}", path, name))?;
+        std::io::copy(&mut (&mut input).take(((BLOCK - data.len() % BLOCK) % BLOCK) as u64), &mut std::io::sink())?;
+        if name == MANIFEST {
+            manifest = Some(serde_json::from_slice(&data).context("Backup manifest is not valid")?);
+        } else {
+            files.push((name, data));
+        }
+    }
+    let manifest = manifest.with_context(|| format!("{:?} has no manifest", path))?;
+    Ok((manifest, files))
+}
+
+fn octal(field: &[u8]) -> Option<u64> {
+    let text = std::str::from_utf8(field).ok()?.trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(text, 8).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::VectorIndex;
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+
+    fn seed(mana_dir: &Path, patterns: &[i64], vectors: &[i64]) {
+        let conn = Connection::open(mana_dir.join(DB_FILE)).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute("DELETE FROM patterns", []).unwrap();
+        for id in patterns {
+            conn.execute(
+                "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (?1, ?1, 'Bash', 'cargo test')",
+                [id],
+            )
+            .unwrap();
+        }
+        let mut index = VectorIndex::new(2);
+        for &id in vectors {
+            index.add(id, &[id as f32, 1.0]).unwrap();
+        }
+        index.save(&mana_dir.join(VECTORS)).unwrap();
+    }
+
+    #[test]
+    fn test_full_backup_roundtrip_and_consistency() {
+        let temp = TempDir::new().unwrap();
+        let mana_dir = temp.path();
+        seed(mana_dir, &[1, 2], &[1, 2]);
+        std::fs::write(mana_dir.join("learning-state.json"), r#"{"last_offset": 42}"#).unwrap();
+
+        let saved = create(mana_dir).unwrap();
+        assert!(is_full(&saved));
+        let manifest = verify(mana_dir, &saved).unwrap();
+        assert_eq!((manifest.patterns, manifest.vectors, manifest.files.len()), (2, 2, 3));
+
+        // Diverge: one pattern more, new learning state, CRDT state the backup never had
+        seed(mana_dir, &[1, 2, 3], &[1, 2, 3]);
+        std::fs::write(mana_dir.join("learning-state.json"), "{}").unwrap();
+        std::fs::write(mana_dir.join("p2p-crdt.json"), "{}").unwrap();
+
+        let safety = restore(mana_dir, &saved).unwrap().unwrap();
+        assert!(is_full(&safety));
+        let conn = Connection::open(mana_dir.join(DB_FILE)).unwrap();
+        assert_eq!(crate::storage::fsck::pattern_ids(&conn).unwrap(), HashSet::from([1, 2]));
+        assert_eq!(VectorIndex::load(&mana_dir.join(VECTORS)).unwrap().ids(), &[1, 2]);
+        assert_eq!(std::fs::read_to_string(mana_dir.join("learning-state.json")).unwrap(), r#"{"last_offset": 42}"#);
+        assert!(!mana_dir.join("p2p-crdt.json").exists());
+
+        // Vectors for a pattern the database doesn't have are refused
+        seed(mana_dir, &[1], &[1, 7]);
+        assert!(create(mana_dir).unwrap_err().to_string().contains("inconsistent"));
+
+        // As is a tampered file
+        let mut bytes = std::fs::read(&saved).unwrap();
+        let at = bytes.windows(14).position(|w| w == b"last_offset\": ").unwrap();
+        bytes[at + 14] = b'9';
+        let tampered = mana_dir.join("tampered.tar");
+        std::fs::write(&tampered, bytes).unwrap();
+        assert!(verify(mana_dir, &tampered).unwrap_err().to_string().contains("checksum"));
+    }
+}
@@ -0,0 +1,279 @@
+//! Pattern version history
+//!
+//! Triggers on `patterns` snapshot the previous content and counts into
+//! `pattern_history` whenever either changes, so every write path (learning,
+//! reflection, `reflect apply`, manual edits) is covered without callers
+//! having to remember. Versions are numbered per pattern starting at 1.
+//!
+//! Content edits always get a version and the most recent [`MAX_VERSIONS`]
+//! are kept. Outcome counts change on nearly every learning run, so a
+//! count-only change is snapshotted at most once a day and those snapshots
+//! are capped separately at [`MAX_COUNT_SNAPSHOTS`]; busy patterns can't push
+//! their content versions out, and most count bumps write no history.
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::add_column_if_missing;
+
+/// Content versions kept per pattern
+pub const MAX_VERSIONS: i64 = 20;
+
+/// Count-only snapshots kept per pattern (one a day covers the 90-day stats trend)
+pub const MAX_COUNT_SNAPSHOTS: i64 = 90;
+
+/// A prior state of a pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternVersion {
+    pub version: i64,
+    pub context_query: String,
+    pub success_count: i64,
+    pub failure_count: i64,
+    /// When this state was replaced
+    pub created_at: String,
+    /// Only the counts changed after this state
+    pub counts_only: bool,
+}
+
+impl PatternVersion {
+    pub fn score(&self) -> i64 {
+        self.success_count - self.failure_count
+    }
+}
+
+/// Create the pattern_history table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS pattern_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pattern_id INTEGER NOT NULL,
+            version INTEGER NOT NULL,
+            context_query TEXT NOT NULL,
+            success_count INTEGER NOT NULL,
+            failure_count INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(pattern_id, version)
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Split count-only snapshots from content versions and (re)create the triggers
+///
+/// Existing rows are classified by comparing each snapshot's content with
+/// the state that replaced it.
+pub fn create_triggers(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "pattern_history", "counts_only", "INTEGER NOT NULL DEFAULT 0")?;
+    conn.execute_batch(&format!(
+        r#"
+        UPDATE pattern_history SET counts_only = 1
+        WHERE context_query IS COALESCE(
+            (SELECT n.context_query FROM pattern_history n
+             WHERE n.pattern_id = pattern_history.pattern_id AND n.version > pattern_history.version
+             ORDER BY n.version LIMIT 1),
+            (SELECT p.context_query FROM patterns p WHERE p.id = pattern_history.pattern_id)
+        );
+
+        DROP TRIGGER IF EXISTS trg_pattern_history;
+
+        CREATE TRIGGER IF NOT EXISTS trg_pattern_history_content
+        AFTER UPDATE OF context_query ON patterns
+        WHEN OLD.context_query IS NOT NEW.context_query
+        BEGIN
+            INSERT INTO pattern_history (pattern_id, version, context_query, success_count, failure_count)
+            VALUES (
+                OLD.id,
+                (SELECT COALESCE(MAX(version), 0) + 1 FROM pattern_history WHERE pattern_id = OLD.id),
+                OLD.context_query,
+                COALESCE(OLD.success_count, 0),
+                COALESCE(OLD.failure_count, 0)
+            );
+            DELETE FROM pattern_history
+            WHERE pattern_id = OLD.id AND counts_only = 0
+              AND version <= (SELECT version FROM pattern_history WHERE pattern_id = OLD.id AND counts_only = 0
+                              ORDER BY version DESC LIMIT 1 OFFSET {max});
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_pattern_history_counts
+        AFTER UPDATE OF success_count, failure_count ON patterns
+        WHEN OLD.context_query IS NEW.context_query
+          AND (OLD.success_count IS NOT NEW.success_count OR OLD.failure_count IS NOT NEW.failure_count)
+          AND NOT EXISTS (SELECT 1 FROM pattern_history WHERE pattern_id = OLD.id AND counts_only = 1
+                          AND created_at > datetime('now', '-1 day'))
+        BEGIN
+            INSERT INTO pattern_history (pattern_id, version, context_query, success_count, failure_count, counts_only)
+            VALUES (
+                OLD.id,
+                (SELECT COALESCE(MAX(version), 0) + 1 FROM pattern_history WHERE pattern_id = OLD.id),
+                OLD.context_query,
+                COALESCE(OLD.success_count, 0),
+                COALESCE(OLD.failure_count, 0),
+                1
+            );
+            DELETE FROM pattern_history
+            WHERE pattern_id = OLD.id AND counts_only = 1
+              AND version <= (SELECT version FROM pattern_history WHERE pattern_id = OLD.id AND counts_only = 1
+                              ORDER BY version DESC LIMIT 1 OFFSET {max_counts});
+        END;
+        "#,
+        max = MAX_VERSIONS,
+        max_counts = MAX_COUNT_SNAPSHOTS
+    ))?;
+    Ok(())
+}
+
+/// Stored versions of a pattern, newest first
+pub fn list_versions(conn: &Connection, pattern_id: i64, limit: usize) -> Result<Vec<PatternVersion>> {
+    let mut stmt = conn.prepare(
+        "SELECT version, context_query, success_count, failure_count, created_at, counts_only
+         FROM pattern_history WHERE pattern_id = ?1
+         ORDER BY version DESC LIMIT ?2",
+    )?;
+    let versions = stmt
+        .query_map(params![pattern_id, limit as i64], row_to_version)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(versions)
+}
+
+/// Restore a pattern to a stored version
+///
+/// The state being replaced is itself snapshotted by the triggers, so a
+/// rollback can be undone by rolling back to the newest version.
+pub fn rollback(conn: &Connection, pattern_id: i64, version: i64) -> Result<PatternVersion> {
+    let snapshot = conn
+        .query_row(
+            "SELECT version, context_query, success_count, failure_count, created_at, counts_only
+             FROM pattern_history WHERE pattern_id = ?1 AND version = ?2",
+            params![pattern_id, version],
+            row_to_version,
+        )
+        .optional()?;
+    let Some(snapshot) = snapshot else {
+        bail!("Pattern #{} has no version {}", pattern_id, version);
+    };
+
+    let updated = conn.execute(
+        "UPDATE patterns SET context_query = ?1, success_count = ?2, failure_count = ?3 WHERE id = ?4",
+        params![snapshot.context_query, snapshot.success_count, snapshot.failure_count, pattern_id],
+    )?;
+    if updated == 0 {
+        bail!("Pattern #{} no longer exists", pattern_id);
+    }
+    Ok(snapshot)
+}
+
+fn row_to_version(row: &rusqlite::Row) -> rusqlite::Result<PatternVersion> {
+    Ok(PatternVersion {
+        version: row.get(0)?,
+        context_query: row.get(1)?,
+        success_count: row.get(2)?,
+        failure_count: row.get(3)?,
+        created_at: row.get(4)?,
+        counts_only: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE patterns (
+                id INTEGER PRIMARY KEY,
+                context_query TEXT NOT NULL,
+                success_count INTEGER DEFAULT 0,
+                failure_count INTEGER DEFAULT 0,
+                last_used DATETIME
+            );
+            INSERT INTO patterns (id, context_query, success_count) VALUES (1, 'original', 2);",
+        )
+        .unwrap();
+        create_table(&conn).unwrap();
+        create_triggers(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_mutations_are_snapshotted_and_rolled_back() {
+        let conn = setup_db();
+
+        // Unrelated columns don't create versions
+        conn.execute("UPDATE patterns SET last_used = CURRENT_TIMESTAMP WHERE id = 1", []).unwrap();
+        assert!(list_versions(&conn, 1, 10).unwrap().is_empty());
+
+        conn.execute("UPDATE patterns SET context_query = 'edited' WHERE id = 1", []).unwrap();
+        conn.execute("UPDATE patterns SET failure_count = 5 WHERE id = 1", []).unwrap();
+
+        let versions = list_versions(&conn, 1, 10).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].version, 1);
+        assert_eq!(versions[1].context_query, "original");
+        assert_eq!(versions[0].score(), 2);
+
+        let restored = rollback(&conn, 1, 1).unwrap();
+        assert_eq!(restored.context_query, "original");
+        let (content, failures): (String, i64) = conn
+            .query_row("SELECT context_query, failure_count FROM patterns WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((content.as_str(), failures), ("original", 0));
+
+        // The rolled-back state is kept as version 3
+        let latest = &list_versions(&conn, 1, 1).unwrap()[0];
+        assert_eq!((latest.version, latest.failure_count), (3, 5));
+        assert!(rollback(&conn, 1, 42).is_err());
+    }
+
+    #[test]
+    fn test_count_changes_dont_push_out_content_versions() {
+        let conn = setup_db();
+        for i in 0..(MAX_VERSIONS + 5) {
+            conn.execute("UPDATE patterns SET context_query = ?1 WHERE id = 1", [format!("edit {}", i)]).unwrap();
+            for bump in 0..10 {
+                conn.execute("UPDATE patterns SET success_count = success_count + ?1 WHERE id = 1", [bump + 1]).unwrap();
+            }
+        }
+
+        // A day's count bumps share one snapshot
+        let versions = list_versions(&conn, 1, 100).unwrap();
+        let counts: Vec<_> = versions.iter().filter(|v| v.counts_only).collect();
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0].context_query, "edit 0");
+
+        let content: Vec<_> = versions.iter().filter(|v| !v.counts_only).collect();
+        assert_eq!(content.len() as i64, MAX_VERSIONS);
+        assert_eq!(content[0].context_query, format!("edit {}", MAX_VERSIONS + 3));
+
+        // The next day's bump is snapshotted again
+        conn.execute("UPDATE pattern_history SET created_at = datetime('now', '-2 days')", []).unwrap();
+        conn.execute("UPDATE patterns SET failure_count = 1 WHERE id = 1", []).unwrap();
+        assert!(list_versions(&conn, 1, 1).unwrap()[0].counts_only);
+    }
+
+    #[test]
+    fn test_existing_snapshots_are_classified() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE patterns (id INTEGER PRIMARY KEY, context_query TEXT NOT NULL,
+                success_count INTEGER DEFAULT 0, failure_count INTEGER DEFAULT 0);
+            INSERT INTO patterns (id, context_query, success_count) VALUES (1, 'second', 9);",
+        )
+        .unwrap();
+        create_table(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO pattern_history (pattern_id, version, context_query, success_count, failure_count)
+             VALUES (1, 1, 'first', 1, 0), (1, 2, 'first', 2, 0), (1, 3, 'second', 3, 0);",
+        )
+        .unwrap();
+        create_triggers(&conn).unwrap();
+
+        let kinds: Vec<bool> = list_versions(&conn, 1, 10).unwrap().iter().map(|v| v.counts_only).collect();
+        assert_eq!(kinds, vec![true, false, true]);
+    }
+}
@@ -0,0 +1,504 @@
+//! Injection telemetry
+//!
+//! Records which patterns were injected into which session so reflection can
+//! attribute verdicts to what the model actually saw instead of re-deriving
+//! it by similarity.
+//!
+//! Writes are kept off the injection hot path: the hook appends one JSON line
+//! to a spool file, the daemon buffers records in memory, and both are
+//! flushed into the `injection_log` table in batches.
+//!
+//! Sessions held out as the experiment control group (see
+//! `reflection::experiment`) are logged too, flagged `control`, with the
+//! patterns they would have been shown.
+//!
+//! Injections that run past their latency budget are recorded the same way,
+//! through a second spool, into the `budget_overruns` table.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Spool file the hook appends to, relative to the MANA data directory
+pub const SPOOL_FILE: &str = "injection-spool.jsonl";
+
+/// Spool for latency budget overruns, relative to the MANA data directory
+pub const OVERRUN_SPOOL_FILE: &str = "overrun-spool.jsonl";
+
+/// One injection event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InjectionRecord {
+    pub session_id: String,
+    /// Tool the context was injected for (e.g. "Bash", "Edit")
+    pub tool: String,
+    /// Injected pattern ids, best first
+    pub pattern_ids: Vec<i64>,
+    /// Ranking score of each injected pattern (parallel to pattern_ids)
+    pub scores: Vec<f64>,
+    pub timestamp: DateTime<Utc>,
+    /// Time from hook start to context output, if measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_us: Option<u64>,
+    /// The session is in the control group: the patterns were selected but not shown
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub control: bool,
+}
+
+impl InjectionRecord {
+    /// Build a record from (pattern id, score) pairs, timestamped now
+    pub fn new(session_id: &str, tool: &str, injected: &[(i64, f64)]) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            tool: tool.to_string(),
+            pattern_ids: injected.iter().map(|(id, _)| *id).collect(),
+            scores: injected.iter().map(|(_, score)| *score).collect(),
+            timestamp: Utc::now(),
+            latency_us: None,
+            control: false,
+        }
+    }
+
+    /// Attach the measured injection latency
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency_us = Some(latency.as_micros() as u64);
+        self
+    }
+
+    /// Mark the record as held back for the control group
+    pub fn with_control(mut self, control: bool) -> Self {
+        self.control = control;
+        self
+    }
+
+    /// Injected patterns as (pattern id, score) pairs
+    pub fn injected(&self) -> Vec<(i64, f64)> {
+        self.pattern_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, self.scores.get(i).copied().unwrap_or(0.0)))
+            .collect()
+    }
+}
+
+/// An injection that ran out of latency budget and passed the input through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetOverrun {
+    pub tool: String,
+    /// Search stage that was running when the budget ran out
+    pub stage: String,
+    pub elapsed_us: u64,
+    pub budget_ms: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl BudgetOverrun {
+    /// Record an overrun, timestamped now
+    pub fn new(tool: &str, stage: &str, elapsed: Duration, budget_ms: u64) -> Self {
+        Self {
+            tool: tool.to_string(),
+            stage: stage.to_string(),
+            elapsed_us: elapsed.as_micros() as u64,
+            budget_ms,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Create the injection_log table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- pattern_ids and scores are parallel JSON arrays
+        CREATE TABLE IF NOT EXISTS injection_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            tool TEXT NOT NULL,
+            pattern_ids TEXT NOT NULL,
+            scores TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            latency_us INTEGER,
+            control INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_injection_session ON injection_log(session_id, created_at);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Create the budget_overruns table
+pub fn create_overrun_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS budget_overruns (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tool TEXT NOT NULL,
+            stage TEXT NOT NULL,
+            elapsed_us INTEGER NOT NULL,
+            budget_ms INTEGER NOT NULL,
+            created_at DATETIME NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_budget_overruns_created ON budget_overruns(created_at);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Append a record to the spool file (hook path, no database access)
+pub fn append_spool(mana_dir: &Path, record: &InjectionRecord) -> Result<()> {
+    append_line(&mana_dir.join(SPOOL_FILE), record)
+}
+
+/// Append an overrun to its spool file (hook path, no database access)
+pub fn append_overrun(mana_dir: &Path, overrun: &BudgetOverrun) -> Result<()> {
+    append_line(&mana_dir.join(OVERRUN_SPOOL_FILE), overrun)
+}
+
+fn append_line<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+
+    // A single write on an O_APPEND file keeps concurrent hooks from interleaving
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Insert records in a single transaction
+pub fn insert_records(conn: &mut Connection, records: &[InjectionRecord]) -> Result<usize> {
+    if records.is_empty() {
+        return Ok(0);
+    }
+
+    let tx = super::db::write_transaction(conn)?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO injection_log (session_id, tool, pattern_ids, scores, created_at, latency_us, control)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for record in records {
+            stmt.execute(params![
+                record.session_id,
+                record.tool,
+                serde_json::to_string(&record.pattern_ids)?,
+                serde_json::to_string(&record.scores)?,
+                record.timestamp.to_rfc3339(),
+                record.latency_us.map(|us| us as i64),
+                record.control,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(records.len())
+}
+
+/// Insert overruns in a single transaction
+pub fn insert_overruns(conn: &mut Connection, overruns: &[BudgetOverrun]) -> Result<usize> {
+    if overruns.is_empty() {
+        return Ok(0);
+    }
+
+    let tx = super::db::write_transaction(conn)?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO budget_overruns (tool, stage, elapsed_us, budget_ms, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for overrun in overruns {
+            stmt.execute(params![
+                overrun.tool,
+                overrun.stage,
+                overrun.elapsed_us as i64,
+                overrun.budget_ms as i64,
+                overrun.timestamp.to_rfc3339(),
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(overruns.len())
+}
+
+/// Move spooled hook records and overruns into the database
+///
+/// Each spool is claimed by renaming it to a name unique to this drain
+/// before reading, so hooks that fire mid-drain start a fresh file and
+/// concurrent drainers never insert the same records twice. If the insert
+/// fails the records are appended back to the spool. Unparseable lines are
+/// skipped. Returns the number of injection records moved.
+pub fn drain_spool(mana_dir: &Path, conn: &mut Connection) -> Result<usize> {
+    create_table(conn)?;
+    let inserted = drain_file(&mana_dir.join(SPOOL_FILE), |records| insert_records(conn, records))?;
+    debug!("Drained {} injection records from spool", inserted);
+
+    create_overrun_table(conn)?;
+    let overruns = drain_file(&mana_dir.join(OVERRUN_SPOOL_FILE), |overruns| insert_overruns(conn, overruns))?;
+    if overruns > 0 {
+        debug!("Drained {} budget overruns from spool", overruns);
+    }
+    Ok(inserted)
+}
+
+fn drain_file<T: DeserializeOwned>(spool: &Path, insert: impl FnOnce(&[T]) -> Result<usize>) -> Result<usize> {
+    static CLAIMS: AtomicUsize = AtomicUsize::new(0);
+    let claimed = spool.with_extension(format!(
+        "jsonl.draining.{}-{}",
+        std::process::id(),
+        CLAIMS.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    // A `.draining` file is left by older versions whose drain failed; take it first
+    let leftover = spool.with_extension("jsonl.draining");
+    if std::fs::rename(&leftover, &claimed).is_err() && std::fs::rename(spool, &claimed).is_err() {
+        return Ok(0);
+    }
+
+    let content = std::fs::read_to_string(&claimed)?;
+    let entries: Vec<T> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("Skipping malformed spool line in {}: {}", spool.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    let inserted = match insert(&entries) {
+        Ok(inserted) => inserted,
+        Err(e) => {
+            // Hand the records back so the next drain retries them
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(spool)?;
+            file.write_all(content.as_bytes())?;
+            std::fs::remove_file(&claimed)?;
+            return Err(e);
+        }
+    };
+    std::fs::remove_file(&claimed)?;
+    Ok(inserted)
+}
+
+/// All injections shown in a session, oldest first
+///
+/// Control-group records are left out: nothing was shown for them.
+pub fn session_records(conn: &Connection, session_id: &str) -> Result<Vec<InjectionRecord>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT tool, pattern_ids, scores, created_at, latency_us FROM injection_log
+         WHERE session_id = ?1 AND control = 0
+         ORDER BY created_at",
+    )?;
+
+    let rows = stmt.query_map(params![session_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, Option<i64>>(4)?,
+        ))
+    })?;
+
+    let mut records = Vec::new();
+    for row in rows {
+        let (tool, ids, scores, created_at, latency_us) = row?;
+        let Ok(timestamp) = DateTime::parse_from_rfc3339(&created_at) else {
+            continue;
+        };
+        records.push(InjectionRecord {
+            session_id: session_id.to_string(),
+            tool,
+            pattern_ids: serde_json::from_str(&ids).unwrap_or_default(),
+            scores: serde_json::from_str(&scores).unwrap_or_default(),
+            timestamp: timestamp.with_timezone(&Utc),
+            latency_us: latency_us.map(|us| us as u64),
+            control: false,
+        });
+    }
+    Ok(records)
+}
+
+/// Whether any injection in the session was held back for the control group
+pub fn is_control_session(conn: &Connection, session_id: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM injection_log WHERE session_id = ?1 AND control = 1)",
+        params![session_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Experiment arm of each session with injections since `since`
+///
+/// Maps session id to `true` for the control group. Sessions with records in
+/// both arms (the control fraction changed mid-session) are left out.
+pub fn session_arms(conn: &Connection, since: DateTime<Utc>) -> Result<HashMap<String, bool>> {
+    let mut stmt = conn.prepare(
+        "SELECT session_id, MAX(control) FROM injection_log
+         WHERE created_at >= ?1
+         GROUP BY session_id
+         HAVING MIN(control) = MAX(control)",
+    )?;
+    let rows = stmt.query_map(params![since.to_rfc3339()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.filter_map(|r| r.ok()).collect())
+}
+
+/// Injection latency percentiles over a time window
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+    /// Most recent measurements, oldest first (for sparklines)
+    pub recent_us: Vec<u64>,
+}
+
+/// Summarize recorded injection latencies since `since`
+pub fn latency_summary(conn: &Connection, since: DateTime<Utc>, recent: usize) -> Result<LatencySummary> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT latency_us FROM injection_log
+         WHERE latency_us IS NOT NULL AND created_at >= ?1
+         ORDER BY created_at",
+    )?;
+    let chronological: Vec<u64> = stmt
+        .query_map(params![since.to_rfc3339()], |row| row.get::<_, i64>(0))?
+        .filter_map(|r| r.ok())
+        .map(|us| us.max(0) as u64)
+        .collect();
+    if chronological.is_empty() {
+        return Ok(LatencySummary::default());
+    }
+
+    let recent_us = chronological[chronological.len().saturating_sub(recent)..].to_vec();
+    let mut sorted = chronological;
+    sorted.sort_unstable();
+    let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+
+    Ok(LatencySummary {
+        samples: sorted.len(),
+        p50_us: percentile(0.50),
+        p95_us: percentile(0.95),
+        max_us: *sorted.last().unwrap_or(&0),
+        recent_us,
+    })
+}
+
+/// Distinct patterns injected into a session within a time window
+///
+/// Returns (pattern id, best score) pairs, highest score first. Open bounds
+/// include everything on that side.
+pub fn injected_in_window(
+    conn: &Connection,
+    session_id: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<(i64, f64)>> {
+    let mut best: Vec<(i64, f64)> = Vec::new();
+    let in_window = |r: &InjectionRecord| {
+        from.is_none_or(|f| r.timestamp >= f) && to.is_none_or(|t| r.timestamp <= t)
+    };
+
+    for record in session_records(conn, session_id)?.iter().filter(|r| in_window(r)) {
+        for (id, score) in record.injected() {
+            match best.iter_mut().find(|(seen, _)| *seen == id) {
+                Some(entry) => entry.1 = entry.1.max(score),
+                None => best.push((id, score)),
+            }
+        }
+    }
+
+    best.sort_by(|a, b| b.1.total_cmp(&a.1));
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_spool_drains_into_table() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = Connection::open(temp.path().join("test.db")).unwrap();
+
+        append_spool(temp.path(), &InjectionRecord::new("s1", "Bash", &[(3, 0.9), (7, 0.5)])).unwrap();
+        append_spool(temp.path(), &InjectionRecord::new("s1", "Edit", &[(4, 0.8)])).unwrap();
+        append_spool(temp.path(), &InjectionRecord::new("s2", "Bash", &[(9, 0.7)])).unwrap();
+
+        // A failed insert hands the records back to the spool
+        let spool = temp.path().join(SPOOL_FILE);
+        assert!(drain_file::<InjectionRecord>(&spool, |_| anyhow::bail!("database is locked")).is_err());
+        assert_eq!(std::fs::read_to_string(&spool).unwrap().lines().count(), 3);
+
+        // A second drainer running meanwhile finds nothing left to claim
+        create_table(&conn).unwrap();
+        let drained = drain_file::<InjectionRecord>(&spool, |records| {
+            assert_eq!(drain_file::<InjectionRecord>(&spool, |_| panic!("drained twice")).unwrap(), 0);
+            insert_records(&mut conn, records)
+        });
+        assert_eq!(drained.unwrap(), 3);
+        assert!(!spool.exists());
+        assert_eq!(drain_spool(temp.path(), &mut conn).unwrap(), 0);
+
+        let injected = injected_in_window(&conn, "s1", None, None).unwrap();
+        assert_eq!(injected, vec![(3, 0.9), (4, 0.8), (7, 0.5)]);
+        assert!(injected_in_window(&conn, "s3", None, None).unwrap().is_empty());
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        assert!(injected_in_window(&conn, "s1", Some(future), None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_overruns_drain_with_injections() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = Connection::open(temp.path().join("test.db")).unwrap();
+
+        let overrun = BudgetOverrun::new("Bash", "similarity", Duration::from_millis(14), 10);
+        append_overrun(temp.path(), &overrun).unwrap();
+        append_spool(temp.path(), &InjectionRecord::new("s1", "Bash", &[(3, 0.9)])).unwrap();
+
+        assert_eq!(drain_spool(temp.path(), &mut conn).unwrap(), 1);
+        assert!(!temp.path().join(OVERRUN_SPOOL_FILE).exists());
+        let stored: (String, String, i64, i64) = conn
+            .query_row("SELECT tool, stage, elapsed_us, budget_ms FROM budget_overruns", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap();
+        assert_eq!(stored, ("Bash".to_string(), "similarity".to_string(), 14_000, 10));
+    }
+
+    #[test]
+    fn test_latency_summary_percentiles() {
+        let temp = TempDir::new().unwrap();
+        let mut conn = Connection::open(temp.path().join("test.db")).unwrap();
+        create_table(&conn).unwrap();
+
+        let records: Vec<InjectionRecord> = (1..=20)
+            .map(|ms| InjectionRecord::new("s1", "Bash", &[(1, 0.5)]).with_latency(Duration::from_millis(ms)))
+            .chain([InjectionRecord::new("s1", "Bash", &[(1, 0.5)])])
+            .collect();
+        insert_records(&mut conn, &records).unwrap();
+
+        let since = Utc::now() - chrono::Duration::hours(1);
+        let summary = latency_summary(&conn, since, 5).unwrap();
+        assert_eq!(summary.samples, 20);
+        assert_eq!(summary.p50_us, 11_000);
+        assert_eq!(summary.p95_us, 19_000);
+        assert_eq!(summary.max_us, 20_000);
+        assert_eq!(summary.recent_us.len(), 5);
+        let measured = session_records(&conn, "s1").unwrap().iter().filter(|r| r.latency_us.is_some()).count();
+        assert_eq!(measured, 20);
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        assert_eq!(latency_summary(&conn, future, 5).unwrap().samples, 0);
+    }
+}
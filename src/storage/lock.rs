@@ -0,0 +1,78 @@
+//! Advisory file locks in the data directory
+//!
+//! Writers that load shared state, change it and save it back hold a lock
+//! for the whole sequence so overlapping runs can't drop each other's
+//! changes. Each lock is an OS file lock on a named file in the MANA data
+//! directory, released on drop or if the holder crashes. The learning lock
+//! (`learning::lock`) and the embedding index lock (`embeddings::lock`)
+//! are both this type on different files.
+
+use anyhow::Result;
+use std::fs::{File, TryLockError};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::ci::{self, ExitCode};
+
+/// How often a waiting process retries the lock
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Held lock; released on drop
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Lock `<mana_dir>/<name>`, waiting up to `timeout`
+    ///
+    /// Returns `None` if another process still holds it after the timeout.
+    pub fn acquire(mana_dir: &Path, name: &str, timeout: Duration) -> Result<Option<Self>> {
+        std::fs::create_dir_all(mana_dir)?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(mana_dir.join(name))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Some(Self { _file: file })),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => std::thread::sleep(POLL_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    debug!("{} still held after {:?}", name, timeout);
+                    return Ok(None);
+                }
+                Err(TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Like [`FileLock::acquire`], but a lock still held after `timeout`
+    /// fails with [`ExitCode::Busy`] and `busy` as the message
+    pub fn acquire_or_busy(mana_dir: &Path, name: &str, timeout: Duration, busy: &str) -> Result<Self> {
+        Self::acquire(mana_dir, name, timeout)?.ok_or_else(|| ci::fail(ExitCode::Busy, busy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_per_file_until_dropped() {
+        let temp = tempfile::TempDir::new().unwrap();
+
+        let held = FileLock::acquire(temp.path(), "a.lock", Duration::ZERO).unwrap();
+        assert!(held.is_some());
+        assert!(FileLock::acquire(temp.path(), "a.lock", Duration::from_millis(100)).unwrap().is_none());
+        let err = FileLock::acquire_or_busy(temp.path(), "a.lock", Duration::ZERO, "busy").unwrap_err();
+        assert_eq!(ci::exit_code(&err), ExitCode::Busy);
+        assert!(FileLock::acquire(temp.path(), "b.lock", Duration::ZERO).unwrap().is_some());
+
+        drop(held);
+        assert!(FileLock::acquire(temp.path(), "a.lock", Duration::ZERO).unwrap().is_some());
+    }
+}
@@ -0,0 +1,209 @@
+//! Database maintenance
+//!
+//! Vacuum, integrity checks, WAL checkpoints and timestamped backups of
+//! metadata.sqlite under `<mana_dir>/backups/`. Backups are written with
+//! `VACUUM INTO`, so they are consistent even while other processes hold
+//! the database open. Full backups, which also carry the embedding index
+//! and learning and CRDT state, are in `full_backup`.
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Directory inside the MANA data directory holding backups
+pub const BACKUP_DIR: &str = "backups";
+
+pub(crate) const DB_FILE: &str = "metadata.sqlite";
+
+/// A backup file on disk
+#[derive(Debug, Clone)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: chrono::DateTime<chrono::Local>,
+}
+
+/// Result of a WAL checkpoint
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointResult {
+    /// Whether a reader or writer prevented a full checkpoint
+    pub busy: bool,
+    /// Frames in the WAL (-1 if the database is not in WAL mode)
+    pub log_frames: i64,
+    pub checkpointed_frames: i64,
+}
+
+/// Rebuild the database file to reclaim free pages; returns (before, after) sizes in bytes
+pub fn vacuum(db_path: &Path) -> Result<(u64, u64)> {
+    let before = file_size(db_path);
+    let conn = super::db::open(db_path)?;
+    conn.execute_batch("VACUUM;").context("VACUUM failed (is another process writing?)")?;
+    drop(conn);
+    Ok((before, file_size(db_path)))
+}
+
+/// Run `PRAGMA integrity_check`; returns an empty list when the database is healthy
+pub fn integrity_check(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(problems.into_iter().filter(|line| line != "ok").collect())
+}
+
+/// Move WAL contents into the main file and truncate the WAL
+pub fn checkpoint(conn: &Connection) -> Result<CheckpointResult> {
+    let (busy, log_frames, checkpointed_frames) =
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    Ok(CheckpointResult { busy: busy != 0, log_frames, checkpointed_frames })
+}
+
+/// Write a timestamped backup of the database to `<mana_dir>/backups/`
+pub fn backup(mana_dir: &Path) -> Result<PathBuf> {
+    let db_path = mana_dir.join(DB_FILE);
+    if !db_path.exists() {
+        bail!("No database at {:?}", db_path);
+    }
+
+    let dir = mana_dir.join(BACKUP_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut target = dir.join(format!("metadata-{}.sqlite", stamp));
+    let mut n = 1;
+    while target.exists() {
+        target = dir.join(format!("metadata-{}-{}.sqlite", stamp, n));
+        n += 1;
+    }
+
+    let conn = super::db::open(&db_path)?;
+    conn.execute("VACUUM INTO ?1", params![target.to_string_lossy()])
+        .with_context(|| format!("Failed to write backup {:?}", target))?;
+    info!("Backed up database to {:?}", target);
+    Ok(target)
+}
+
+/// Backups (database copies and full backups) in `<mana_dir>/backups/`, newest first
+pub fn list_backups(mana_dir: &Path) -> Result<Vec<BackupInfo>> {
+    let dir = mana_dir.join(BACKUP_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        // Dot files are copies still being written or checked
+        let hidden = path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'));
+        if hidden || path.extension().is_none_or(|e| e != "sqlite" && e != super::full_backup::EXTENSION) {
+            continue;
+        }
+        let meta = std::fs::metadata(&path)?;
+        backups.push(BackupInfo { path, size: meta.len(), modified: meta.modified()?.into() });
+    }
+    backups.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| b.path.cmp(&a.path)));
+    Ok(backups)
+}
+
+/// Resolve a backup argument: an existing path, a file name in the backup
+/// directory, or the newest backup when `None`
+pub fn resolve_backup(mana_dir: &Path, name: Option<&str>) -> Result<PathBuf> {
+    match name {
+        Some(name) => {
+            let direct = PathBuf::from(name);
+            if direct.is_file() {
+                return Ok(direct);
+            }
+            let in_dir = mana_dir.join(BACKUP_DIR).join(name);
+            if in_dir.is_file() {
+                return Ok(in_dir);
+            }
+            bail!("Backup not found: {}", name)
+        }
+        None => list_backups(mana_dir)?
+            .into_iter()
+            .next()
+            .map(|b| b.path)
+            .ok_or_else(|| anyhow::anyhow!("No backups in {:?}", mana_dir.join(BACKUP_DIR))),
+    }
+}
+
+/// Replace the database with a backup
+///
+/// The backup must pass an integrity check. The current database, if
+/// readable, is backed up first so a restore can itself be undone; that
+/// safety backup's path is returned.
+pub fn restore(mana_dir: &Path, backup_path: &Path) -> Result<Option<PathBuf>> {
+    let problems = integrity_check(&super::db::open_readonly(backup_path)?)
+        .with_context(|| format!("{:?} is not a readable SQLite database", backup_path))?;
+    if !problems.is_empty() {
+        bail!("Backup {:?} failed integrity check: {}", backup_path, problems.join("; "));
+    }
+
+    let db_path = mana_dir.join(DB_FILE);
+    // A corrupt database may not be copyable; restoring over it is the point
+    let safety = if db_path.exists() { backup(mana_dir).ok() } else { None };
+
+    // Copy beside the target, then rename so a crash never leaves a partial file
+    let tmp = db_path.with_extension("sqlite.restore");
+    std::fs::copy(backup_path, &tmp)?;
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", db_path.display(), suffix));
+    }
+    std::fs::rename(&tmp, &db_path)?;
+    info!("Restored database from {:?}", backup_path);
+    Ok(safety)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn count(mana_dir: &Path) -> i64 {
+        let conn = Connection::open(mana_dir.join(DB_FILE)).unwrap();
+        conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_backup_and_restore_roundtrip() {
+        let temp = TempDir::new().unwrap();
+        let mana_dir = temp.path();
+        let conn = Connection::open(mana_dir.join(DB_FILE)).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO patterns (pattern_hash, tool_type, context_query) VALUES ('a', 'Bash', 'cargo test')",
+            [],
+        )
+        .unwrap();
+
+        let saved = backup(mana_dir).unwrap();
+        assert!(integrity_check(&Connection::open(&saved).unwrap()).unwrap().is_empty());
+
+        conn.execute("DELETE FROM patterns", []).unwrap();
+        drop(conn);
+        assert_eq!(count(mana_dir), 0);
+
+        let latest = resolve_backup(mana_dir, None).unwrap();
+        assert_eq!(latest, saved);
+        let safety = restore(mana_dir, &latest).unwrap();
+        assert_eq!(count(mana_dir), 1);
+        assert!(safety.is_some());
+        assert_eq!(list_backups(mana_dir).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restore_rejects_non_database() {
+        let temp = TempDir::new().unwrap();
+        let bogus = temp.path().join("bogus.sqlite");
+        std::fs::write(&bogus, b"definitely not sqlite, just some text padding it out").unwrap();
+        assert!(restore(temp.path(), &bogus).is_err());
+        assert!(!temp.path().join(DB_FILE).exists());
+    }
+}
@@ -0,0 +1,343 @@
+//! Schema migrations
+//!
+//! Numbered migrations applied in order and recorded in `schema_version`.
+//! Every migration is idempotent (`IF NOT EXISTS`, column checks) so
+//! databases created before versioning existed, which report version 0,
+//! can safely run the whole list.
+//!
+//! Writers upgrade automatically when they open the database; the old file
+//! is first copied next to it with `VACUUM INTO`.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use super::{add_column_if_missing, history, injection_log, projects, provenance, tags, terms, top_patterns};
+
+/// A single schema change
+#[derive(Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// All migrations, oldest first. Append only; never renumber.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "base_tables", up: base_tables },
+    Migration { version: 2, name: "pattern_command_category", up: pattern_command_category },
+    Migration { version: 3, name: "pattern_risk_flags", up: pattern_risk_flags },
+    Migration { version: 4, name: "injection_log", up: injection_log::create_table },
+    Migration { version: 5, name: "pattern_history", up: history::create_table },
+    Migration { version: 6, name: "reflection_tables", up: crate::reflection::init_reflection_tables },
+    Migration { version: 7, name: "pattern_embeddings", up: pattern_embeddings },
+    Migration { version: 8, name: "injection_latency", up: injection_latency },
+    Migration { version: 9, name: "pattern_tags", up: tags::create_table },
+    Migration { version: 10, name: "pattern_projects", up: projects::create_table },
+    Migration { version: 11, name: "budget_overruns", up: injection_log::create_overrun_table },
+    Migration { version: 12, name: "top_patterns", up: top_patterns::create_table },
+    Migration { version: 13, name: "injection_control", up: injection_control },
+    Migration { version: 14, name: "pattern_status", up: pattern_status },
+    Migration { version: 15, name: "term_stats", up: term_stats },
+    Migration { version: 16, name: "pattern_devices", up: provenance::create_table },
+    Migration { version: 17, name: "top_pattern_quality", up: top_pattern_quality },
+    Migration { version: 18, name: "pattern_risk_backfill", up: pattern_risk_backfill },
+    Migration { version: 19, name: "history_count_snapshots", up: history::create_triggers },
+];
+
+/// Newest schema version this binary knows about
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Schema version recorded in the database (0 if unversioned)
+pub fn current_version(conn: &Connection) -> u32 {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+        .unwrap_or(0)
+}
+
+/// Whether the database is behind this binary
+pub fn needs_upgrade(conn: &Connection) -> bool {
+    current_version(conn) < latest_version()
+}
+
+/// Migrations not yet applied to the database
+pub fn pending(conn: &Connection) -> Vec<&'static Migration> {
+    let current = current_version(conn);
+    MIGRATIONS.iter().filter(|m| m.version > current).collect()
+}
+
+/// Apply pending migrations, each in its own transaction
+pub fn migrate(conn: &Connection) -> Result<Vec<&'static Migration>> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )?;
+
+    let pending = pending(conn);
+    for migration in &pending {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx)
+            .with_context(|| format!("Migration {} ({}) failed", migration.version, migration.name))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )?;
+        tx.commit()?;
+        info!("Applied schema migration {} ({})", migration.version, migration.name);
+    }
+    Ok(pending)
+}
+
+/// Result of [`upgrade`]
+#[derive(Debug)]
+pub struct UpgradeReport {
+    pub from: u32,
+    pub to: u32,
+    pub applied: Vec<&'static Migration>,
+    /// Copy of the database taken before migrating
+    pub backup: Option<PathBuf>,
+}
+
+/// Bring the database at `db_path` up to date, backing it up first
+///
+/// New (empty) databases are not backed up.
+pub fn upgrade(db_path: &Path) -> Result<UpgradeReport> {
+    let conn = super::db::open(db_path)?;
+    let from = current_version(&conn);
+    if from >= latest_version() {
+        return Ok(UpgradeReport { from, to: from, applied: Vec::new(), backup: None });
+    }
+
+    let has_data: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+    let backup = if has_data { Some(backup_before_upgrade(&conn, db_path, from)?) } else { None };
+
+    let applied = migrate(&conn)?;
+    Ok(UpgradeReport { from, to: current_version(&conn), applied, backup })
+}
+
+fn backup_before_upgrade(conn: &Connection, db_path: &Path, from: u32) -> Result<PathBuf> {
+    let file_name = db_path.file_name().and_then(|n| n.to_str()).unwrap_or("metadata.sqlite");
+    let backup = db_path.with_file_name(format!(
+        "{}.v{}-{}.bak",
+        file_name,
+        from,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    conn.execute("VACUUM INTO ?1", params![backup.to_string_lossy()])
+        .with_context(|| format!("Failed to back up database to {}", backup.display()))?;
+    info!("Backed up schema v{} database to {:?}", from, backup);
+    Ok(backup)
+}
+
+fn base_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS patterns (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pattern_hash TEXT UNIQUE NOT NULL,
+            tool_type TEXT NOT NULL,
+            command_category TEXT,
+            context_query TEXT NOT NULL,
+            success_count INTEGER DEFAULT 0,
+            failure_count INTEGER DEFAULT 0,
+            last_used DATETIME,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            embedding_id INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS skills (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT UNIQUE NOT NULL,
+            description TEXT,
+            pattern_ids TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS learning_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
+            event_type TEXT NOT NULL,
+            details TEXT
+        );
+
+        -- Causal edges track pattern relationships discovered during learning
+        -- positive lift (>1.5) = synergy (patterns work well together)
+        -- negative lift (<0.5) = conflict (patterns interfere with each other)
+        CREATE TABLE IF NOT EXISTS causal_edges (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pattern_a_id INTEGER NOT NULL,
+            pattern_b_id INTEGER NOT NULL,
+            lift REAL NOT NULL,
+            co_occurrences INTEGER DEFAULT 1,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            UNIQUE(pattern_a_id, pattern_b_id),
+            FOREIGN KEY (pattern_a_id) REFERENCES patterns(id) ON DELETE CASCADE,
+            FOREIGN KEY (pattern_b_id) REFERENCES patterns(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_patterns_tool ON patterns(tool_type);
+        CREATE INDEX IF NOT EXISTS idx_patterns_hash ON patterns(pattern_hash);
+        CREATE INDEX IF NOT EXISTS idx_causal_pattern_a ON causal_edges(pattern_a_id);
+        CREATE INDEX IF NOT EXISTS idx_causal_pattern_b ON causal_edges(pattern_b_id);
+
+        -- Composite index for the hot query path (tool_type + score ordering)
+        CREATE INDEX IF NOT EXISTS idx_patterns_tool_score ON patterns(tool_type, (success_count - failure_count) DESC);
+        "#,
+    )?;
+    Ok(())
+}
+
+fn pattern_command_category(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "patterns", "command_category", "TEXT")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_patterns_category ON patterns(command_category)", [])?;
+    Ok(())
+}
+
+/// Risk flag for destructive commands, cleared by explicit approval
+fn pattern_risk_flags(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "patterns", "risky", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "patterns", "approved_at", "DATETIME")?;
+    Ok(())
+}
+
+/// Classify patterns stored before (or without) the risk check
+fn pattern_risk_backfill(conn: &Connection) -> Result<()> {
+    let flagged = crate::learning::risk::flag_risky_patterns(conn)?;
+    if flagged > 0 {
+        info!("Flagged {} existing destructive patterns as risky", flagged);
+    }
+    Ok(())
+}
+
+fn pattern_embeddings(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "patterns", "embedding", "BLOB")?;
+    add_column_if_missing(conn, "patterns", "embedding_version", "INTEGER DEFAULT 0")?;
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS embedding_meta (
+            id INTEGER PRIMARY KEY,
+            model_name TEXT NOT NULL,
+            model_version TEXT NOT NULL,
+            dimensions INTEGER NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+fn injection_latency(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "injection_log", "latency_us", "INTEGER")
+}
+
+fn injection_control(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "injection_log", "control", "INTEGER NOT NULL DEFAULT 0")
+}
+
+fn pattern_status(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "patterns", "status", "TEXT NOT NULL DEFAULT 'active'")?;
+    add_column_if_missing(conn, "patterns", "status_changed_at", "DATETIME")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_patterns_status ON patterns(status)", [])?;
+    Ok(())
+}
+
+/// Quality per cached ranking entry, filled by ranking with `[ranking] formula`
+fn top_pattern_quality(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "top_patterns", "quality", "REAL NOT NULL DEFAULT 0")?;
+    top_patterns::refresh(conn)?;
+    Ok(())
+}
+
+fn term_stats(conn: &Connection) -> Result<()> {
+    terms::create_tables(conn)?;
+    terms::update(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::has_column;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_migration_versions_are_sequential() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1, "{}", migration.name);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_legacy_database() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        {
+            // Pre-versioning layout without the later columns
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE patterns (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    pattern_hash TEXT UNIQUE NOT NULL,
+                    tool_type TEXT NOT NULL,
+                    context_query TEXT NOT NULL,
+                    success_count INTEGER DEFAULT 0,
+                    failure_count INTEGER DEFAULT 0,
+                    last_used DATETIME,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    embedding_id INTEGER
+                );
+                INSERT INTO patterns (pattern_hash, tool_type, context_query) VALUES ('h', 'Bash', 'cargo build');
+                INSERT INTO patterns (pattern_hash, tool_type, context_query) VALUES ('r', 'Bash', 'Approach: Bash - rm -rf target');",
+            )
+            .unwrap();
+        }
+
+        let report = upgrade(&db_path).unwrap();
+        assert_eq!((report.from, report.to), (0, latest_version()));
+        assert_eq!(report.applied.len(), MIGRATIONS.len());
+
+        let backup = report.backup.expect("existing data is backed up");
+        let old = Connection::open(&backup).unwrap();
+        assert!(!has_column(&old, "patterns", "risky"));
+
+        let conn = Connection::open(&db_path).unwrap();
+        assert!(has_column(&conn, "patterns", "risky"));
+        assert!(has_column(&conn, "patterns", "command_category"));
+        assert!(!needs_upgrade(&conn));
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        let risky: Vec<String> = conn
+            .prepare("SELECT pattern_hash FROM patterns WHERE risky = 1")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(risky, vec!["r"]);
+
+        // Up to date: nothing applied, no new backup
+        let again = upgrade(&db_path).unwrap();
+        assert!(again.applied.is_empty());
+        assert!(again.backup.is_none());
+    }
+
+    #[test]
+    fn test_new_database_is_not_backed_up() {
+        let temp = TempDir::new().unwrap();
+        let report = upgrade(&temp.path().join("metadata.sqlite")).unwrap();
+        assert!(report.backup.is_none());
+        assert_eq!(report.to, latest_version());
+    }
+}
@@ -6,15 +6,39 @@
 use anyhow::Result;
 use rusqlite::{Connection, params};
 use std::path::PathBuf;
-use tracing::info;
+use tracing::{debug, info};
+
+use crate::get_mana_dir;
 
 pub mod patterns;
 pub mod similarity;
 pub mod causal;
 pub mod skills;
+pub mod injection_log;
+pub mod history;
+pub mod migrations;
+pub mod maintenance;
+pub mod full_backup;
+pub mod db;
+pub mod stats;
+pub mod tags;
+pub mod projects;
+pub mod provenance;
+pub mod top_patterns;
+pub mod terms;
+pub mod snapshot;
+pub mod filter;
+pub mod bulk;
+pub mod fsck;
+pub mod retention;
+pub mod ranking;
+pub mod read_only;
+pub mod lock;
+pub mod encryption;
 
 pub use patterns::{PatternStore, Pattern};
-pub use similarity::calculate_similarity;
+pub use filter::PatternFilter;
+pub use similarity::{calculate_similarity, Scorer};
 pub use causal::CausalStore;
 #[allow(unused_imports)]
 pub use causal::CausalEdge;
@@ -23,84 +47,17 @@ pub use skills::{SkillStore, Skill, consolidate_patterns_to_skills};
 
 /// Initialize MANA storage and configuration
 pub async fn init() -> Result<()> {
-    let mana_dir = get_mana_dir()?;
-    std::fs::create_dir_all(&mana_dir)?;
+    init_dir(&get_mana_dir()?)
+}
+
+/// Create the database and default config in a specific data directory
+pub fn init_dir(mana_dir: &std::path::Path) -> Result<()> {
+    std::fs::create_dir_all(mana_dir)?;
 
     // Initialize SQLite database
     let db_path = mana_dir.join("metadata.sqlite");
-    let conn = Connection::open(&db_path)?;
-
-    // Create tables
-    conn.execute_batch(
-        r#"
-        CREATE TABLE IF NOT EXISTS patterns (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            pattern_hash TEXT UNIQUE NOT NULL,
-            tool_type TEXT NOT NULL,
-            command_category TEXT,
-            context_query TEXT NOT NULL,
-            success_count INTEGER DEFAULT 0,
-            failure_count INTEGER DEFAULT 0,
-            last_used DATETIME,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            embedding_id INTEGER
-        );
-
-        CREATE TABLE IF NOT EXISTS skills (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT UNIQUE NOT NULL,
-            description TEXT,
-            pattern_ids TEXT,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-
-        CREATE TABLE IF NOT EXISTS learning_log (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            timestamp DATETIME DEFAULT CURRENT_TIMESTAMP,
-            event_type TEXT NOT NULL,
-            details TEXT
-        );
-
-        -- Causal edges track pattern relationships discovered during learning
-        -- positive lift (>1.5) = synergy (patterns work well together)
-        -- negative lift (<0.5) = conflict (patterns interfere with each other)
-        CREATE TABLE IF NOT EXISTS causal_edges (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            pattern_a_id INTEGER NOT NULL,
-            pattern_b_id INTEGER NOT NULL,
-            lift REAL NOT NULL,
-            co_occurrences INTEGER DEFAULT 1,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            UNIQUE(pattern_a_id, pattern_b_id),
-            FOREIGN KEY (pattern_a_id) REFERENCES patterns(id) ON DELETE CASCADE,
-            FOREIGN KEY (pattern_b_id) REFERENCES patterns(id) ON DELETE CASCADE
-        );
-
-        CREATE INDEX IF NOT EXISTS idx_patterns_tool ON patterns(tool_type);
-        CREATE INDEX IF NOT EXISTS idx_patterns_hash ON patterns(pattern_hash);
-        CREATE INDEX IF NOT EXISTS idx_causal_pattern_a ON causal_edges(pattern_a_id);
-        CREATE INDEX IF NOT EXISTS idx_causal_pattern_b ON causal_edges(pattern_b_id);
-
-        -- Composite index for the hot query path (tool_type + score ordering)
-        CREATE INDEX IF NOT EXISTS idx_patterns_tool_score ON patterns(tool_type, (success_count - failure_count) DESC);
-        "#,
-    )?;
-
-    // Migration: add command_category column if it doesn't exist (for existing databases)
-    let has_category: bool = conn.query_row(
-        "SELECT COUNT(*) FROM pragma_table_info('patterns') WHERE name = 'command_category'",
-        [],
-        |row| Ok(row.get::<_, i64>(0)? > 0),
-    ).unwrap_or(false);
-
-    if !has_category {
-        conn.execute("ALTER TABLE patterns ADD COLUMN command_category TEXT", [])?;
-        info!("Migrated patterns table to add command_category column");
-    }
-
-    // Always ensure the category index exists
-    conn.execute("CREATE INDEX IF NOT EXISTS idx_patterns_category ON patterns(command_category)", [])?;
+    let conn = db::open(&db_path)?;
+    create_schema(&conn)?;
 
     info!("MANA initialized at {:?}", mana_dir);
 
@@ -112,20 +69,142 @@ pub async fn init() -> Result<()> {
 [learning]
 # Trajectory threshold before triggering learning
 threshold = 15
+# Rows per transaction for batch pattern inserts (0 = single transaction)
+# Run `mana bench --insert` for a recommendation on this machine
+batch_chunk_size = 10000
+# Claude Code log directories to learn from (MANA_CLAUDE_LOGS overrides)
+# log_dirs = ["~/.claude/projects"]
+# Projects never learned from, as globs over session working directories
+# (`mana patterns purge --project` removes what was learned before)
+# exclude_projects = ["*secret*", "~/work/client-x/**"]
+
+[injection]
 # Maximum patterns to inject per context
-max_patterns_per_context = 5
+max_patterns = 3
+# Approximate token budget for the injected context block
+max_tokens = 400
+# Tag wrapping the injected block
+wrapper_tag = "mana-context"
+# Output format: "claude", "plain-markdown" (e.g. Aider) or "json" (custom agents)
+adapter = "claude"
+# Include score and success rate in the default per-pattern format
+show_scores = true
+# Custom per-pattern format (placeholders: {id} {tool} {score} {rate} {insight})
+# pattern_format = "- {tool}: {insight}"
+# Hold out this share of sessions without injection; compare with `mana reflect experiment`
+# control_fraction = 0.1
+# Don't repeat a pattern the session was given in the last this many minutes (0 disables)
+repeat_window_mins = 30
+# Remind Claude of the session's goal, recently touched files and failed commands
+session_state = true
+
+[embeddings]
+# HNSW graph parameters; run `mana embed tune` for recommended values
+m = 16
+ef_construction = 200
+ef_search = 64
+# Use the HNSW graph only once the index has at least this many vectors
+ann_min_vectors = 10000
+# Embed new patterns after learning (consolidation and the daemon), in batches,
+# using at most this share of a core and this many seconds per run
+auto_embed = true
+auto_embed_batch = 32
+auto_embed_cpu = 0.5
+auto_embed_max_secs = 30
+
+[reflection]
+# Optional LLM judge for verdicts: "none", "claude" (needs ANTHROPIC_API_KEY), "ollama"
+judge = "none"
+# judge_model = "llama3.2"
+ollama_url = "http://localhost:11434"
+# Rate limits for judge calls; cached verdicts are free
+judge_max_calls = 50
+judge_interval_ms = 500
+# Verdicts below this confidence don't change pattern scores
+min_confidence = 0.6
+# Score change limits per verdict
+max_penalty = -5
+max_boost = 5
+# Stop injecting a pattern after this many HARMFUL verdicts at or above
+# quarantine_min_confidence (0 disables); see `mana patterns quarantined`
+quarantine_after = 3
+quarantine_min_confidence = 0.8
 
 [performance]
-# Maximum time for context injection in milliseconds
+# Injections slower than this (milliseconds) are logged as over budget
 injection_timeout_ms = 10
-# Maximum time for pattern search in milliseconds
-search_timeout_ms = 5
 
-[storage]
-# Maximum number of patterns to keep
+[rerank]
+# Daemon only: rescore the top `candidates` embedding matches with a
+# "cross-encoder" server (needs url, POST <url>/rerank) or an "ollama" model
+# before picking what to inject; raise injection_timeout_ms above timeout_ms
+backend = "none"
+# url = "http://localhost:8080"
+# model = "llama3.2"
+candidates = 20
+timeout_ms = 300
+
+[sync]
+# Quality gate applied before every push; withheld patterns stay local
+# min_score = 1
+exclude_tools = []
+# max_patterns = 500
+
+[retention]
+# Applied by consolidation after each learning run; 0 disables a limit
+# Evict the lowest-value patterns (by score, then least recently used) beyond this
 max_patterns = 10000
-# Decay factor for unused patterns (0-1)
-decay_factor = 0.95
+# Vacuum, then evict more patterns, while the database is larger than this
+max_db_mb = 500
+# Delete injection records and cached judge results older than this (off by default)
+max_trajectory_age_days = 0
+# Delete reflection verdicts older than this (off by default)
+max_verdict_age_days = 0
+
+[ranking]
+# How pattern quality is scored for injection, `patterns list` and search:
+# "wilson" (success rate, discounted for few uses), "bayesian" (success rate
+# smoothed toward its tool and category) or "raw" (successes minus failures)
+formula = "wilson"
+# Uses at the category's success rate that "bayesian" adds to each pattern
+prior_weight = 5.0
+# Share of the score that halves every half_life_days since last use (0 days disables)
+recency_weight = 0.5
+half_life_days = 30.0
+# Reputation: reflection verdicts blended into the score, weighted by where
+# they came from (this machine, teammates' exports, installed packs); the
+# pattern's own success rate counts as score_weight verdicts ("raw" ignores them)
+score_weight = 5.0
+own_weight = 1.0
+team_weight = 0.5
+public_weight = 0.2
+
+[consolidation]
+# Local times of day the daemon consolidates at (while it's running)
+# schedule = "03:00"
+
+[storage]
+# Never write the database, index or state files (also `mana --read-only`)
+read_only = false
+# Keep metadata.sqlite encrypted (needs a SQLCipher build; key from MANA_DB_KEY
+# or the OS keychain; 'mana db encrypt' converts an existing database)
+encrypt = false
+
+[device]
+# Name shown for this machine's patterns on other devices (default: hostname)
+# name = "laptop"
+
+[update]
+# "stable", or "beta" to include pre-releases
+channel = "stable"
+# Keys that must have signed a release's SHA256SUMS (ssh-ed25519 AAAA...)
+release_keys = []
+# Asset manifest for `mana update --assets` (URL or path); defaults to the
+# release's assets.json
+# assets_url = "https://example.com/mana/assets.json"
+
+# Any key can be overridden from the environment as MANA_<SECTION>_<KEY>,
+# e.g. MANA_INJECTION_MAX_PATTERNS=5. Check this file with `mana config validate`.
 "#;
         std::fs::write(&config_path, default_config)?;
         info!("Created default configuration at {:?}", config_path);
@@ -134,16 +213,93 @@ decay_factor = 0.95
     Ok(())
 }
 
-/// Show current MANA status
-pub async fn show_status() -> Result<()> {
+/// Create tables and indexes by applying any pending migrations
+///
+/// Does not back up the database; use [`migrations::upgrade`] when opening
+/// an existing file by path.
+pub fn create_schema(conn: &Connection) -> Result<()> {
+    migrations::migrate(conn)?;
+    Ok(())
+}
+
+/// Check whether a table has a given column
+pub(crate) fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| Ok(row.get::<_, i64>(0)? > 0),
+    ).unwrap_or(false)
+}
+
+/// Bring an existing database up to date if it predates this binary's schema
+///
+/// Read-only paths call this so an upgraded binary doesn't fail its queries
+/// against a database that no writer has migrated yet. The old file is
+/// backed up before any migration runs. In read-only mode the database is
+/// left as it is.
+pub fn ensure_schema(db_path: &std::path::Path) -> Result<()> {
+    if read_only::protects(db_path) {
+        debug!("Read-only mode: leaving the schema as it is");
+        return Ok(());
+    }
+    let report = migrations::upgrade(db_path)?;
+    if let Some(backup) = &report.backup {
+        info!("Upgraded schema v{} -> v{} (backup: {:?})", report.from, report.to, backup);
+    }
+    Ok(())
+}
+
+/// Add a column to a table if an older database doesn't have it yet
+pub(crate) fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    if !has_column(conn, table, column) {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+        info!("Migrated {} table to add {} column", table, column);
+    }
+    Ok(())
+}
+
+/// Snapshot behind `mana status`
+#[derive(Debug, Default, serde::Serialize)]
+pub struct StatusReport {
+    pub initialized: bool,
+    pub data_dir: PathBuf,
+    pub database_found: bool,
+    pub patterns: i64,
+    pub causal_edges: i64,
+    pub pending_trajectories: Option<u64>,
+}
+
+/// Show current MANA status (as JSON if `json` is set)
+pub async fn show_status(json: bool) -> Result<()> {
     let mana_dir = get_mana_dir()?;
+    let mut report = StatusReport { initialized: mana_dir.exists(), data_dir: mana_dir.clone(), ..Default::default() };
+
+    let db_path = mana_dir.join("metadata.sqlite");
+    if report.initialized && db_path.exists() {
+        let conn = db::open(&db_path)?;
+        report.database_found = true;
+        report.patterns = conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0)).unwrap_or(0);
+        report.causal_edges = conn.query_row("SELECT COUNT(*) FROM causal_edges", [], |row| row.get(0)).unwrap_or(0);
+    }
+
+    let state_path = mana_dir.join("learning-state.json");
+    if state_path.exists() {
+        let state: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(&state_path)?
+        )?;
+        report.pending_trajectories = state.get("trajectory_count").and_then(|v| v.as_u64());
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
 
     println!("MANA Status");
     println!("============");
     println!();
 
-    // Check if initialized
-    if !mana_dir.exists() {
+    if !report.initialized {
         println!("Status: NOT INITIALIZED");
         println!("Run 'mana init' to initialize MANA");
         return Ok(());
@@ -152,46 +308,33 @@ pub async fn show_status() -> Result<()> {
     println!("Status: INITIALIZED");
     println!("Data directory: {:?}", mana_dir);
 
-    // Check database
-    let db_path = mana_dir.join("metadata.sqlite");
-    if db_path.exists() {
-        let conn = Connection::open(&db_path)?;
-        let pattern_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM patterns",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0);
-
-        let causal_edge_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM causal_edges",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0);
-
-        println!("Patterns stored: {}", pattern_count);
-        println!("Causal edges: {}", causal_edge_count);
+    if report.database_found {
+        println!("Patterns stored: {}", report.patterns);
+        println!("Causal edges: {}", report.causal_edges);
     } else {
         println!("Database: NOT FOUND");
     }
 
-    // Check learning state
-    let state_path = mana_dir.join("learning-state.json");
-    if state_path.exists() {
-        let state: serde_json::Value = serde_json::from_str(
-            &std::fs::read_to_string(&state_path)?
-        )?;
-
-        if let Some(count) = state.get("trajectory_count").and_then(|v| v.as_u64()) {
-            println!("Pending trajectories: {}", count);
-        }
+    if let Some(count) = report.pending_trajectories {
+        println!("Pending trajectories: {}", count);
     }
 
     Ok(())
 }
 
-/// Show detailed MANA statistics
-pub async fn show_stats() -> Result<()> {
+/// Show detailed MANA statistics (as JSON if `json` is set)
+pub async fn show_stats(json: bool) -> Result<()> {
     let mana_dir = get_mana_dir()?;
+    let db_path = mana_dir.join("metadata.sqlite");
+
+    if json {
+        if !db_path.exists() {
+            return Err(crate::ci::fail(crate::ci::ExitCode::NotInitialized, format!("No database found at {:?}", db_path)));
+        }
+        let report = stats::collect_stats(&open_drained(&mana_dir, &db_path)?, 5)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
 
     println!("MANA Statistics");
     println!("================");
@@ -202,174 +345,125 @@ pub async fn show_stats() -> Result<()> {
         return Ok(());
     }
 
-    let db_path = mana_dir.join("metadata.sqlite");
     if !db_path.exists() {
         println!("No database found.");
         return Ok(());
     }
 
-    let conn = Connection::open(&db_path)?;
+    let report = stats::collect_stats(&open_drained(&mana_dir, &db_path)?, 5)?;
+    let budget_ms = crate::config::load_config(&mana_dir).performance.injection_timeout_ms;
 
-    // Pattern statistics
     println!("Pattern Statistics:");
     println!("-------------------");
-
-    let total_patterns: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM patterns",
-        [],
-        |row| row.get(0)
-    ).unwrap_or(0);
-    println!("  Total patterns: {}", total_patterns);
-
-    // Patterns by tool type
-    let mut stmt = conn.prepare(
-        "SELECT tool_type, COUNT(*) FROM patterns GROUP BY tool_type ORDER BY COUNT(*) DESC"
-    )?;
-    let tool_counts = stmt.query_map([], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
-    })?;
-
+    println!("  Total patterns: {}", report.patterns.total);
     println!("  By tool type:");
-    for (tool, count) in tool_counts.flatten() {
-        println!("    {}: {}", tool, count);
+    for entry in &report.patterns.by_tool {
+        println!("    {}: {}", entry.tool, entry.count);
+    }
+    match report.patterns.success_rate {
+        Some(rate) => println!(
+            "  Success rate: {:.1}% ({}/{} uses)",
+            rate,
+            report.patterns.successes,
+            report.patterns.successes + report.patterns.failures
+        ),
+        None => println!("  Success rate: N/A (no uses recorded)"),
     }
+    println!("  Trend: {}", trend_line(&report.patterns.trend));
 
-    // Success rate
-    let (total_success, total_failure): (i64, i64) = conn.query_row(
-        "SELECT COALESCE(SUM(success_count), 0), COALESCE(SUM(failure_count), 0) FROM patterns",
-        [],
-        |row| Ok((row.get(0)?, row.get(1)?))
-    ).unwrap_or((0, 0));
-
-    let total_uses = total_success + total_failure;
-    if total_uses > 0 {
-        let success_rate = (total_success as f64 / total_uses as f64) * 100.0;
-        println!("  Success rate: {:.1}% ({}/{} uses)", success_rate, total_success, total_uses);
-    } else {
-        println!("  Success rate: N/A (no uses recorded)");
+    println!();
+    println!("By Language:");
+    println!("------------");
+    print_breakdowns(&report.by_language, usize::MAX);
+
+    println!();
+    println!("By Project:");
+    println!("-----------");
+    if report.by_project.is_empty() {
+        println!("  No patterns linked to projects yet.");
     }
+    print_breakdowns(&report.by_project, PROJECTS_SHOWN);
 
-    // Learning log
     println!();
     println!("Learning History:");
     println!("-----------------");
-
-    let mut stmt = conn.prepare(
-        "SELECT timestamp, event_type FROM learning_log ORDER BY timestamp DESC LIMIT 5"
-    )?;
-    let events = stmt.query_map([], |row| {
-        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-    })?;
-
-    let mut has_events = false;
-    for result in events {
-        has_events = true;
-        if let Ok((timestamp, event_type)) = result {
-            println!("  {} - {}", timestamp, event_type);
-        }
+    for event in &report.recent_events {
+        println!("  {} - {}", event.timestamp, event.event_type);
     }
-
-    if !has_events {
+    if report.recent_events.is_empty() {
         println!("  No learning events recorded yet.");
     }
 
-    // Causal Graph Statistics
     println!();
     println!("Causal Graph:");
     println!("-------------");
-
-    let total_edges: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM causal_edges",
-        [],
-        |row| row.get(0)
-    ).unwrap_or(0);
-    println!("  Total edges: {}", total_edges);
-
-    if total_edges > 0 {
-        // Count synergies (lift > 1.5 with >= 3 co-occurrences)
-        let synergies: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM causal_edges WHERE lift > 1.5 AND co_occurrences >= 3",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0);
-
-        // Count conflicts (lift < 0.5 with >= 3 co-occurrences)
-        let conflicts: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM causal_edges WHERE lift < 0.5 AND co_occurrences >= 3",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0);
-
-        // Count uncertain (0.5 <= lift <= 1.5 or < 3 co-occurrences)
-        let uncertain = total_edges - synergies - conflicts;
-
-        println!("  Synergies (lift > 1.5): {}", synergies);
-        println!("  Conflicts (lift < 0.5): {}", conflicts);
-        println!("  Uncertain/learning: {}", uncertain);
-
-        // Average co-occurrences
-        let avg_cooccur: f64 = conn.query_row(
-            "SELECT AVG(co_occurrences) FROM causal_edges",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0.0);
-        println!("  Avg co-occurrences: {:.1}", avg_cooccur);
+    let causal = &report.causal;
+    println!("  Total edges: {}", causal.total_edges);
+    if causal.total_edges > 0 {
+        println!("  Synergies (lift > 1.5): {}", causal.synergies);
+        println!("  Conflicts (lift < 0.5): {}", causal.conflicts);
+        println!("  Uncertain/learning: {}", causal.uncertain);
+        println!("  Avg co-occurrences: {:.1}", causal.avg_co_occurrences);
     }
 
-    // Skill Statistics
     println!();
     println!("Skills:");
     println!("-------");
-
-    // Check if skills table exists and has data
-    let skill_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM skills",
-        [],
-        |row| row.get(0)
-    ).unwrap_or(0);
-
-    if skill_count > 0 {
-        println!("  Total skills: {}", skill_count);
-
-        // Skills by tool type
-        let mut stmt = conn.prepare(
-            "SELECT tool_type, COUNT(*), SUM(pattern_count) FROM skills GROUP BY tool_type ORDER BY COUNT(*) DESC"
-        )?;
-        let skill_stats = stmt.query_map([], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
-        })?;
-
+    let skills = &report.skills;
+    if skills.total > 0 {
+        println!("  Total skills: {}", skills.total);
         println!("  By tool type:");
-        for (tool, count, patterns) in skill_stats.flatten() {
-            println!("    {}: {} skills ({} patterns)", tool, count, patterns);
+        for entry in &skills.by_tool {
+            println!("    {}: {} skills ({} patterns)", entry.tool, entry.skills, entry.patterns);
         }
-
-        // Average success rate
-        let avg_success_rate: f64 = conn.query_row(
-            "SELECT AVG(CAST(total_success AS REAL) / NULLIF(total_success + total_failure, 0) * 100) FROM skills",
-            [],
-            |row| row.get(0)
-        ).unwrap_or(0.0);
-        println!("  Avg skill success rate: {:.1}%", avg_success_rate);
+        println!("  Avg skill success rate: {:.1}%", skills.avg_success_rate);
     } else {
         println!("  No skills created yet. Run 'mana consolidate' to create skills.");
     }
 
+    println!();
+    println!("Injection Budget:");
+    println!("-----------------");
+    let budget = &report.budget;
+    println!("  Budget: {}ms", budget_ms);
+    if budget.overruns > 0 {
+        let stages: Vec<String> = budget.by_stage.iter().map(|s| format!("{}: {}", s.stage, s.count)).collect();
+        println!("  Overruns (last 7 days): {} ({})", budget.overruns, stages.join(", "));
+        println!("  Slowest overrun: {:.1}ms", budget.max_elapsed_ms);
+    } else {
+        println!("  Overruns (last 7 days): 0");
+    }
+
     Ok(())
 }
 
-fn get_mana_dir() -> Result<PathBuf> {
-    // Check for .mana directory in current project first
-    let cwd = std::env::current_dir()?;
-    let project_mana = cwd.join(".mana");
-    if project_mana.exists() {
-        return Ok(project_mana);
+/// Projects listed by `mana stats`; JSON has them all
+pub(crate) const PROJECTS_SHOWN: usize = 10;
+
+/// "7d 82% of 41, 30d ..." for a trend
+fn trend_line(trend: &[stats::WindowRate]) -> String {
+    trend.iter().map(|w| format!("{}d {}", w.days, w.label())).collect::<Vec<_>>().join(", ")
+}
+
+/// One line per breakdown, flagging those with too little data
+pub(crate) fn print_breakdowns(breakdowns: &[stats::Breakdown], limit: usize) {
+    for b in breakdowns.iter().take(limit) {
+        let rate = b.success_rate.map_or("no uses".to_string(), |r| format!("{:.0}% success", r));
+        let thin = if b.thin { " (thin data)" } else { "" };
+        println!("  {}: {} patterns, {}{} | {}", b.key, b.patterns, rate, thin, trend_line(&b.trend));
     }
+    if breakdowns.len() > limit {
+        println!("  ... and {} more (see --json)", breakdowns.len() - limit);
+    }
+}
 
-    // Fall back to home directory
-    let home = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".mana"))
+/// Open the database with spooled hook telemetry moved in
+fn open_drained(mana_dir: &std::path::Path, db_path: &std::path::Path) -> Result<Connection> {
+    let mut conn = db::open(db_path)?;
+    if let Err(e) = injection_log::drain_spool(mana_dir, &mut conn) {
+        debug!("Failed to drain injection spool: {}", e);
+    }
+    Ok(conn)
 }
 
 /// Debug: show sample patterns for inspection
@@ -382,7 +476,7 @@ pub async fn debug_patterns(limit: usize) -> Result<()> {
         return Ok(());
     }
 
-    let conn = Connection::open(&db_path)?;
+    let conn = db::open(&db_path)?;
 
     println!("Sample Patterns (showing {} by type):", limit);
     println!("{}", "=".repeat(60));
@@ -443,8 +537,7 @@ pub async fn prune_patterns(min_score: i64, dry_run: bool) -> Result<()> {
     let db_path = mana_dir.join("metadata.sqlite");
 
     if !db_path.exists() {
-        println!("No database found.");
-        return Ok(());
+        return Err(crate::ci::not_initialized());
     }
 
     let store = PatternStore::open(&db_path)?;
@@ -476,48 +569,15 @@ pub async fn prune_patterns(min_score: i64, dry_run: bool) -> Result<()> {
             println!();
             println!("Run without --dry-run to actually delete these patterns.");
         }
+        crate::ci::summary("prune", &[("min_score", &min_score), ("would_prune", &to_prune.len()), ("dry_run", &true)]);
     } else {
         let pruned = store.prune_low_score(min_score)?;
         let after = store.count()?;
 
         println!("Pruned {} patterns (score < {})", pruned, min_score);
         println!("Patterns: {} -> {}", before, after);
+        crate::ci::summary("prune", &[("min_score", &min_score), ("pruned", &pruned), ("before", &before), ("after", &after)]);
     }
 
     Ok(())
 }
-
-/// Reset patterns and re-learn from logs
-pub async fn relearn() -> Result<()> {
-    use crate::learning::foreground_learn;
-
-    let mana_dir = get_mana_dir()?;
-    let db_path = mana_dir.join("metadata.sqlite");
-
-    if !db_path.exists() {
-        println!("No database found. Run 'mana init' first.");
-        return Ok(());
-    }
-
-    // Clear existing patterns
-    let conn = Connection::open(&db_path)?;
-    let deleted: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |r| r.get(0))?;
-    conn.execute("DELETE FROM patterns", [])?;
-    println!("Cleared {} existing patterns", deleted);
-
-    // Reset learning state
-    let state_path = mana_dir.join("learning-state.json");
-    if state_path.exists() {
-        std::fs::remove_file(&state_path)?;
-    }
-
-    // Re-learn from logs
-    println!("Re-learning from Claude logs...");
-    let result = foreground_learn(&[]).await?;
-    println!(
-        "Created {} patterns from {} trajectories in {}ms",
-        result.patterns_created, result.trajectories_processed, result.duration_ms
-    );
-
-    Ok(())
-}
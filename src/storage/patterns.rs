@@ -6,9 +6,13 @@
 use anyhow::Result;
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::Path;
 use tracing::debug;
 
+/// Default number of rows committed per transaction in batch inserts
+pub const DEFAULT_BATCH_CHUNK_SIZE: usize = 10_000;
+
 /// A stored pattern from the ReasoningBank
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pattern {
@@ -22,6 +26,9 @@ pub struct Pattern {
     pub success_count: i64,
     pub failure_count: i64,
     pub embedding_id: Option<i64>,
+    /// Destructive command awaiting approval; excluded from injection
+    #[serde(default)]
+    pub risky: bool,
 }
 
 /// Pattern store backed by SQLite
@@ -31,9 +38,13 @@ pub struct PatternStore {
 
 impl PatternStore {
     /// Open or create a pattern store at the given path
-    /// Uses default SQLite settings for maximum compatibility
+    /// Uses the shared WAL writer settings from [`super::db`]
     pub fn open(db_path: &Path) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
+        let conn = super::db::open(db_path)?;
+        // Writers bring older databases up to date (with a backup) before touching patterns
+        if super::migrations::needs_upgrade(&conn) {
+            super::ensure_schema(db_path)?;
+        }
         Ok(Self { conn })
     }
 
@@ -52,6 +63,7 @@ impl PatternStore {
                 | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX
                 | rusqlite::OpenFlags::SQLITE_OPEN_URI,
         )?;
+        super::encryption::unlock(&conn)?;
 
         // OPTIMIZATION: Skip execute_batch entirely - it adds parsing overhead.
         // SQLite's default cache (2000 pages = 8MB) is sufficient for read-only.
@@ -61,9 +73,29 @@ impl PatternStore {
         // Keep prepared statements cached (this is in-memory, fast)
         conn.set_prepared_statement_cache_capacity(4);
 
+        // One-time upgrade for databases older than this binary's schema
+        if super::migrations::needs_upgrade(&conn) {
+            super::ensure_schema(db_path)?;
+        }
+
         Ok(Self { conn })
     }
 
+    /// Corpus term statistics, for weighing query terms in similarity scoring
+    pub fn corpus(&self) -> super::terms::Corpus<'_> {
+        super::terms::Corpus::new(&self.conn)
+    }
+
+    /// Causally compatible subset of `ranked`, on this store's connection (see `causal`)
+    pub fn select_compatible(&self, ranked: &[i64], limit: usize) -> Result<Vec<i64>> {
+        super::causal::select_compatible(&self.conn, ranked, limit)
+    }
+
+    /// Record the device a pulled pattern came from (see `provenance`)
+    pub fn record_device(&self, pattern_hash: &str, device: &str) -> Result<bool> {
+        super::provenance::record(&self.conn, pattern_hash, device)
+    }
+
     /// Open pattern store with mmap enabled (for latency-sensitive hot paths)
     /// Use this when the connection will be reused many times
     #[allow(dead_code)]
@@ -73,6 +105,7 @@ impl PatternStore {
             rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY
                 | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        super::encryption::unlock(&conn)?;
 
         // Enable mmap for repeated queries (amortizes setup cost)
         conn.pragma_update(None, "mmap_size", 2_097_152)?; // 2MB
@@ -82,18 +115,6 @@ impl PatternStore {
         Ok(Self { conn })
     }
 
-    /// Open pattern store with write optimizations (for learning/consolidation)
-    #[allow(dead_code)]
-    pub fn open_write(db_path: &Path) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
-
-        // WAL mode for better concurrent access during writes
-        conn.pragma_update(None, "journal_mode", "WAL")?;
-        conn.pragma_update(None, "synchronous", "NORMAL")?;
-
-        Ok(Self { conn })
-    }
-
     /// Fast insert without similarity checks - uses hash-based deduplication
     ///
     /// For bulk loading during learning. Uses INSERT OR IGNORE with pattern_hash
@@ -105,11 +126,12 @@ impl PatternStore {
         let changes = self.conn.execute(
             r#"
             INSERT INTO patterns
-            (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id, risky)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
             ON CONFLICT(pattern_hash) DO UPDATE SET
                 success_count = success_count + excluded.success_count,
                 failure_count = failure_count + excluded.failure_count,
+                risky = MAX(risky, excluded.risky),
                 last_used = CURRENT_TIMESTAMP
             "#,
             params![
@@ -119,7 +141,8 @@ impl PatternStore {
                 pattern.context_query,
                 pattern.success_count,
                 pattern.failure_count,
-                pattern.embedding_id
+                pattern.embedding_id,
+                pattern.risky
             ],
         )?;
 
@@ -131,44 +154,65 @@ impl PatternStore {
         }
     }
 
-    /// Batch insert patterns in a single transaction
+    /// Overwrite a pattern's context and counts, matched by hash
     ///
-    /// Much faster than individual inserts for bulk loading.
-    /// Uses a single transaction to batch all inserts, reducing disk I/O.
-    pub fn insert_batch(&mut self, patterns: &[Pattern]) -> Result<usize> {
-        // Start a transaction for the batch
-        let tx = self.conn.transaction()?;
+    /// A risky flag is added but never cleared. Returns false if no pattern
+    /// has that hash.
+    pub fn overwrite(&self, pattern: &Pattern) -> Result<bool> {
+        let changes = self.conn.execute(
+            "UPDATE patterns SET context_query = ?2, success_count = ?3, failure_count = ?4, risky = MAX(risky, ?5)
+             WHERE pattern_hash = ?1",
+            params![pattern.pattern_hash, pattern.context_query, pattern.success_count, pattern.failure_count, pattern.risky],
+        )?;
+        Ok(changes > 0)
+    }
+
+    /// Batch insert patterns, committing every `chunk_size` rows
+    ///
+    /// Much faster than individual inserts for bulk loading. Each chunk runs
+    /// in its own transaction so a large relearn doesn't hold one giant write
+    /// transaction. The insert statement is prepared once and reused from the
+    /// connection's statement cache across chunks.
+    /// A `chunk_size` of 0 inserts everything in a single transaction.
+    pub fn insert_batch(&mut self, patterns: &[Pattern], chunk_size: usize) -> Result<usize> {
+        let chunk_size = if chunk_size == 0 { patterns.len().max(1) } else { chunk_size };
 
         let mut inserted = 0;
-        {
-            let mut stmt = tx.prepare_cached(
-                r#"
-                INSERT INTO patterns
-                (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-                ON CONFLICT(pattern_hash) DO UPDATE SET
-                    success_count = success_count + excluded.success_count,
-                    failure_count = failure_count + excluded.failure_count,
-                    last_used = CURRENT_TIMESTAMP
-                "#,
-            )?;
-
-            for pattern in patterns {
-                if stmt.execute(params![
-                    pattern.pattern_hash,
-                    pattern.tool_type,
-                    pattern.command_category,
-                    pattern.context_query,
-                    pattern.success_count,
-                    pattern.failure_count,
-                    pattern.embedding_id
-                ]).is_ok() {
-                    inserted += 1;
+        for chunk in patterns.chunks(chunk_size) {
+            let tx = super::db::write_transaction(&mut self.conn)?;
+            {
+                let mut stmt = tx.prepare_cached(
+                    r#"
+                    INSERT INTO patterns
+                    (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id, risky)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                    ON CONFLICT(pattern_hash) DO UPDATE SET
+                        success_count = success_count + excluded.success_count,
+                        failure_count = failure_count + excluded.failure_count,
+                        risky = MAX(risky, excluded.risky),
+                        last_used = CURRENT_TIMESTAMP
+                    "#,
+                )?;
+
+                for pattern in chunk {
+                    if stmt.execute(params![
+                        pattern.pattern_hash,
+                        pattern.tool_type,
+                        pattern.command_category,
+                        pattern.context_query,
+                        pattern.success_count,
+                        pattern.failure_count,
+                        pattern.embedding_id,
+                        pattern.risky
+                    ]).is_ok() {
+                        inserted += 1;
+                    }
                 }
             }
+            tx.commit()?;
         }
 
-        tx.commit()?;
+        debug!("Batch inserted {} patterns in chunks of {}", inserted, chunk_size);
         Ok(inserted)
     }
 
@@ -218,8 +262,8 @@ impl PatternStore {
         self.conn.execute(
             r#"
             INSERT OR REPLACE INTO patterns
-            (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id, risky)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
             "#,
             params![
                 pattern.pattern_hash,
@@ -228,7 +272,8 @@ impl PatternStore {
                 pattern.context_query,
                 pattern.success_count,
                 pattern.failure_count,
-                pattern.embedding_id
+                pattern.embedding_id,
+                pattern.risky
             ],
         )?;
 
@@ -240,7 +285,8 @@ impl PatternStore {
         // Use prepare_cached for faster repeated queries
         let mut stmt = self.conn.prepare_cached(
             r#"
-            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL)
             FROM patterns
             WHERE tool_type = ?1
             ORDER BY (success_count - failure_count) DESC, success_count DESC
@@ -258,12 +304,64 @@ impl PatternStore {
                 success_count: row.get(5)?,
                 failure_count: row.get(6)?,
                 embedding_id: row.get(7)?,
+                risky: row.get(8)?,
             })
         })?;
 
         patterns.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
+    /// Best patterns for a tool with their quality, those in `category` first
+    ///
+    /// Reads the precomputed ranking in `top_patterns`, falling back to
+    /// ranking the tool's patterns when the cache has nothing for it. Only
+    /// active patterns are returned.
+    pub fn get_top_by_tool(&self, tool_type: &str, category: Option<&str>, limit: usize) -> Result<Vec<(Pattern, f64)>> {
+        let cached = super::top_patterns::lookup(&self.conn, tool_type, category, limit).unwrap_or_default();
+        if !cached.is_empty() {
+            return Ok(cached);
+        }
+
+        let mut stmt = self.conn.prepare_cached(&format!(
+            r#"
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL), {}
+            FROM patterns
+            WHERE tool_type = ?1 AND status = 'active'
+            "#,
+            super::ranking::AGE_DAYS
+        ))?;
+
+        let ranker = super::ranking::Ranker::configured(&self.conn)?;
+        let patterns = stmt.query_map(params![tool_type], |row| {
+            let pattern = Pattern {
+                id: row.get(0)?,
+                pattern_hash: row.get(1)?,
+                tool_type: row.get(2)?,
+                command_category: row.get(3)?,
+                context_query: row.get(4)?,
+                success_count: row.get(5)?,
+                failure_count: row.get(6)?,
+                embedding_id: row.get(7)?,
+                risky: row.get(8)?,
+            };
+            let quality = ranker.quality(
+                pattern.id,
+                &pattern.tool_type,
+                pattern.command_category.as_deref(),
+                pattern.success_count,
+                pattern.failure_count,
+                row.get(9)?,
+            );
+            Ok((pattern, quality))
+        })?;
+
+        let mut ranked = patterns.collect::<Result<Vec<_>, _>>()?;
+        ranked.sort_by(|(a, qa), (b, qb)| super::ranking::best_first((*qa, a.success_count), (*qb, b.success_count)));
+        ranked.truncate(limit);
+        Ok(ranked)
+    }
+
     /// Get patterns by tool type and command category
     /// This is more efficient for Bash patterns where we want cargo vs npm vs git
     #[allow(dead_code)]
@@ -272,7 +370,8 @@ impl PatternStore {
             Some(cat) => {
                 let mut stmt = self.conn.prepare(
                     r#"
-                    SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id
+                    SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                           (risky = 1 AND approved_at IS NULL)
                     FROM patterns
                     WHERE tool_type = ?1 AND command_category = ?2
                     ORDER BY (success_count - failure_count) DESC, success_count DESC
@@ -290,6 +389,7 @@ impl PatternStore {
                         success_count: row.get(5)?,
                         failure_count: row.get(6)?,
                         embedding_id: row.get(7)?,
+                        risky: row.get(8)?,
                     })
                 })?;
 
@@ -318,12 +418,92 @@ impl PatternStore {
         Ok(())
     }
 
+    /// Approve a risky pattern so it becomes eligible for injection
+    ///
+    /// Returns false if the pattern doesn't exist or isn't flagged as risky.
+    pub fn approve_risky(&self, pattern_id: i64) -> Result<bool> {
+        let rows = self.conn.execute(
+            "UPDATE patterns SET approved_at = CURRENT_TIMESTAMP WHERE id = ?1 AND risky = 1 AND approved_at IS NULL",
+            params![pattern_id],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Return a quarantined or archived pattern to injection
+    ///
+    /// Returns false if the pattern doesn't exist or is already active.
+    pub fn reinstate(&self, pattern_id: i64) -> Result<bool> {
+        Ok(!reinstate_patterns(&self.conn, &[pattern_id])?.is_empty())
+    }
+
+    /// List quarantined patterns with when they were quarantined, most recent first
+    pub fn get_quarantined(&self) -> Result<Vec<(Pattern, Option<String>)>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL), status_changed_at
+            FROM patterns
+            WHERE status = 'quarantined'
+            ORDER BY status_changed_at DESC, id
+            "#,
+        )?;
+
+        let patterns = stmt.query_map([], |row| {
+            Ok((
+                Pattern {
+                    id: row.get(0)?,
+                    pattern_hash: row.get(1)?,
+                    tool_type: row.get(2)?,
+                    command_category: row.get(3)?,
+                    context_query: row.get(4)?,
+                    success_count: row.get(5)?,
+                    failure_count: row.get(6)?,
+                    embedding_id: row.get(7)?,
+                    risky: row.get(8)?,
+                },
+                row.get(9)?,
+            ))
+        })?;
+
+        patterns.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// List risky patterns that are still awaiting approval
+    pub fn get_pending_risky(&self) -> Result<Vec<Pattern>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL)
+            FROM patterns
+            WHERE risky = 1 AND approved_at IS NULL
+            ORDER BY id
+            "#,
+        )?;
+
+        let patterns = stmt.query_map([], |row| {
+            Ok(Pattern {
+                id: row.get(0)?,
+                pattern_hash: row.get(1)?,
+                tool_type: row.get(2)?,
+                command_category: row.get(3)?,
+                context_query: row.get(4)?,
+                success_count: row.get(5)?,
+                failure_count: row.get(6)?,
+                embedding_id: row.get(7)?,
+                risky: row.get(8)?,
+            })
+        })?;
+
+        patterns.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
     /// Get pattern by ID
     #[allow(dead_code)]
     pub fn get_by_id(&self, id: i64) -> Result<Option<Pattern>> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL)
             FROM patterns
             WHERE id = ?1
             "#,
@@ -341,6 +521,7 @@ impl PatternStore {
                 success_count: row.get(5)?,
                 failure_count: row.get(6)?,
                 embedding_id: row.get(7)?,
+                risky: row.get(8)?,
             }))
         } else {
             Ok(None)
@@ -368,21 +549,21 @@ impl PatternStore {
         Ok(changes as u64)
     }
 
-    /// Delete patterns with low scores
+    /// Delete patterns with low scores, with their dependent rows
     pub fn prune_low_score(&self, min_score: i64) -> Result<u64> {
-        let changes = self.conn.execute(
-            "DELETE FROM patterns WHERE (success_count - failure_count) < ?1",
-            params![min_score],
-        )?;
-
-        Ok(changes as u64)
+        let ids: Vec<i64> = self.get_patterns_below_score(min_score)?.iter().map(|p| p.id).collect();
+        let tx = self.conn.unchecked_transaction()?;
+        delete_with_dependents(&tx, &ids)?;
+        tx.commit()?;
+        Ok(ids.len() as u64)
     }
 
     /// Get patterns with score below threshold (for preview before pruning)
     pub fn get_patterns_below_score(&self, min_score: i64) -> Result<Vec<Pattern>> {
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL)
             FROM patterns
             WHERE (success_count - failure_count) < ?1
             ORDER BY (success_count - failure_count) ASC
@@ -399,19 +580,21 @@ impl PatternStore {
                 success_count: row.get(5)?,
                 failure_count: row.get(6)?,
                 embedding_id: row.get(7)?,
+                risky: row.get(8)?,
             })
         })?;
 
         patterns.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    /// Get top patterns across all tool types (for fallback)
+    /// Get top active patterns across all tool types (for fallback)
     pub fn get_top_patterns(&self, limit: usize) -> Result<Vec<Pattern>> {
         let mut stmt = self.conn.prepare_cached(
             r#"
-            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id
+            SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding_id,
+                   (risky = 1 AND approved_at IS NULL)
             FROM patterns
-            WHERE tool_type != 'failure'
+            WHERE tool_type != 'failure' AND status = 'active'
             ORDER BY (success_count - failure_count) DESC, success_count DESC
             LIMIT ?1
             "#,
@@ -427,9 +610,229 @@ impl PatternStore {
                 success_count: row.get(5)?,
                 failure_count: row.get(6)?,
                 embedding_id: row.get(7)?,
+                risky: row.get(8)?,
             })
         })?;
 
         patterns.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 }
+
+/// Set active patterns to quarantined, returning the ids that changed
+pub(crate) fn quarantine_patterns(conn: &Connection, pattern_ids: &[i64]) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare_cached(
+        "UPDATE patterns SET status = 'quarantined', status_changed_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status = 'active'",
+    )?;
+    let mut quarantined = Vec::new();
+    for &id in pattern_ids {
+        if stmt.execute(params![id])? > 0 {
+            quarantined.push(id);
+        }
+    }
+    Ok(quarantined)
+}
+
+/// Set active patterns to archived, returning the ids that changed
+///
+/// Archived patterns are kept but never injected, like quarantined ones.
+pub(crate) fn archive_patterns(conn: &Connection, pattern_ids: &[i64]) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare_cached(
+        "UPDATE patterns SET status = 'archived', status_changed_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status = 'active'",
+    )?;
+    let mut archived = Vec::new();
+    for &id in pattern_ids {
+        if stmt.execute(params![id])? > 0 {
+            archived.push(id);
+        }
+    }
+    Ok(archived)
+}
+
+/// Return quarantined or archived patterns to active, returning the ids that changed
+pub(crate) fn reinstate_patterns(conn: &Connection, pattern_ids: &[i64]) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare_cached(
+        "UPDATE patterns SET status = 'active', status_changed_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND status != 'active'",
+    )?;
+    let mut reinstated = Vec::new();
+    for &id in pattern_ids {
+        if stmt.execute(params![id])? > 0 {
+            reinstated.push(id);
+        }
+    }
+    Ok(reinstated)
+}
+
+/// Tables holding rows about a pattern, by the column naming it
+pub(crate) const DEPENDENT_TABLES: &[(&str, &str)] = &[
+    ("pattern_projects", "pattern_id"),
+    ("pattern_tags", "pattern_id"),
+    ("pattern_devices", "pattern_id"),
+    ("pattern_history", "pattern_id"),
+    ("top_patterns", "pattern_id"),
+    ("reflection_verdicts", "pattern_id"),
+    ("shared_verdicts", "pattern_id"),
+    ("improvement_history", "pattern_id"),
+    ("causal_edges", "pattern_a_id"),
+    ("causal_edges", "pattern_b_id"),
+];
+
+/// Delete patterns along with their history, verdicts and other rows that may quote them
+///
+/// Skills lose the deleted patterns from their member lists. Runs in the
+/// caller's transaction. The embedding index and snapshot are the caller's
+/// to update.
+pub(crate) fn delete_with_dependents(conn: &Connection, pattern_ids: &[i64]) -> Result<()> {
+    let tables: Vec<_> = DEPENDENT_TABLES
+        .iter()
+        .filter(|(table, column)| super::has_column(conn, table, column))
+        .collect();
+    for id in pattern_ids {
+        for (table, column) in &tables {
+            conn.execute(&format!("DELETE FROM {} WHERE {} = ?1", table, column), params![id])?;
+        }
+        conn.execute("DELETE FROM patterns WHERE id = ?1", params![id])?;
+    }
+    let deleted: HashSet<i64> = pattern_ids.iter().copied().collect();
+    detach_from_skills(conn, |id| !deleted.contains(&id))?;
+    Ok(())
+}
+
+/// Drop members `keep` rejects from skills, deleting skills left empty
+///
+/// Returns how many skills changed.
+pub(crate) fn detach_from_skills(conn: &Connection, keep: impl Fn(i64) -> bool) -> Result<usize> {
+    if !super::has_column(conn, "skills", "pattern_ids") {
+        return Ok(0);
+    }
+    let skills: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare("SELECT id, pattern_ids FROM skills WHERE pattern_ids IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_, _>>()?
+    };
+    let counted = super::has_column(conn, "skills", "pattern_count");
+
+    let mut changed = 0;
+    for (skill_id, members) in skills {
+        let ids: Vec<i64> = members.split(',').filter_map(|id| id.trim().parse().ok()).collect();
+        let kept: Vec<i64> = ids.iter().copied().filter(|&id| keep(id)).collect();
+        if kept.len() == ids.len() {
+            continue;
+        }
+        if kept.is_empty() {
+            conn.execute("DELETE FROM skills WHERE id = ?1", params![skill_id])?;
+        } else {
+            let members = kept.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",");
+            conn.execute("UPDATE skills SET pattern_ids = ?1 WHERE id = ?2", params![members, skill_id])?;
+            if counted {
+                conn.execute("UPDATE skills SET pattern_count = ?1 WHERE id = ?2", params![kept.len() as i64, skill_id])?;
+            }
+        }
+        changed += 1;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn setup_store() -> (NamedTempFile, PatternStore) {
+        let tmp = NamedTempFile::new().unwrap();
+        let conn = Connection::open(tmp.path()).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        drop(conn);
+
+        let store = PatternStore::open(tmp.path()).unwrap();
+        (tmp, store)
+    }
+
+    fn make_pattern(hash: &str) -> Pattern {
+        Pattern {
+            id: 0,
+            pattern_hash: hash.to_string(),
+            tool_type: "Bash".to_string(),
+            command_category: Some("cargo".to_string()),
+            context_query: format!("Task: {}\nApproach: Bash - cargo - build", hash),
+            success_count: 1,
+            failure_count: 0,
+            embedding_id: None,
+            risky: false,
+        }
+    }
+
+    #[test]
+    fn test_insert_batch_across_chunks() {
+        let (_tmp, mut store) = setup_store();
+        let patterns: Vec<Pattern> = (0..25).map(|i| make_pattern(&format!("h{}", i))).collect();
+
+        let inserted = store.insert_batch(&patterns, 10).unwrap();
+        assert_eq!(inserted, 25);
+        assert_eq!(store.count().unwrap(), 25);
+    }
+
+    #[test]
+    fn test_insert_batch_merges_duplicates() {
+        let (_tmp, mut store) = setup_store();
+        let patterns = vec![make_pattern("dup"), make_pattern("dup")];
+
+        store.insert_batch(&patterns, 0).unwrap();
+        assert_eq!(store.count().unwrap(), 1);
+
+        let stored = store.get_by_tool("Bash", 10).unwrap();
+        assert_eq!(stored[0].success_count, 2);
+    }
+
+    #[test]
+    fn test_risky_pattern_requires_approval() {
+        let (_tmp, mut store) = setup_store();
+        let mut pattern = make_pattern("risky");
+        pattern.risky = true;
+        store.insert_batch(&[pattern], 0).unwrap();
+
+        let pending = store.get_pending_risky().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(store.get_by_tool("Bash", 10).unwrap()[0].risky);
+
+        assert!(store.approve_risky(pending[0].id).unwrap());
+        assert!(store.get_pending_risky().unwrap().is_empty());
+        assert!(!store.get_by_tool("Bash", 10).unwrap()[0].risky);
+
+        // Approving twice is a no-op
+        assert!(!store.approve_risky(pending[0].id).unwrap());
+    }
+
+    #[test]
+    fn test_relearning_flags_existing_pattern_risky() {
+        let (_tmp, mut store) = setup_store();
+        store.insert_fast(&make_pattern("later-risky")).unwrap();
+        let mut relearned = make_pattern("later-risky");
+        relearned.risky = true;
+        store.insert_batch(&[relearned], 0).unwrap();
+        assert_eq!(store.get_pending_risky().unwrap().len(), 1);
+
+        // A later safe sighting doesn't clear the flag
+        store.insert_fast(&make_pattern("later-risky")).unwrap();
+        assert!(store.get_by_tool("Bash", 10).unwrap()[0].risky);
+    }
+
+    #[test]
+    fn test_quarantined_pattern_is_not_injected_until_reinstated() {
+        let (_tmp, mut store) = setup_store();
+        store.insert_batch(&[make_pattern("a"), make_pattern("b")], 0).unwrap();
+        let id = store.get_by_tool("Bash", 10).unwrap()[0].id;
+
+        assert_eq!(quarantine_patterns(&store.conn, &[id, id]).unwrap(), vec![id]);
+        assert_eq!(store.get_quarantined().unwrap()[0].0.id, id);
+        assert_eq!(store.get_top_by_tool("Bash", None, 10).unwrap().len(), 1);
+        assert!(store.get_top_patterns(10).unwrap().iter().all(|p| p.id != id));
+
+        assert!(store.reinstate(id).unwrap());
+        assert!(!store.reinstate(id).unwrap());
+        assert!(store.get_quarantined().unwrap().is_empty());
+        assert_eq!(store.get_top_by_tool("Bash", None, 10).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+//! Pattern projects
+//!
+//! A pattern is one row no matter how many projects it was learned in, so
+//! the projects it came from live in a link table filled in during learning
+//! from the working directory each session log records. Projects are kept
+//! as hashes of their path, the same ids exports use for their source
+//! workspace.
+//!
+//! `mana patterns purge --project` deletes everything learned in a project,
+//! for projects later excluded with `[learning] exclude_projects`.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+
+use crate::sync::crypto::hash_workspace_id;
+
+/// Create the pattern_projects table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS pattern_projects (
+            pattern_id INTEGER NOT NULL,
+            project_hash TEXT NOT NULL,
+            first_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (pattern_id, project_hash),
+            FOREIGN KEY (pattern_id) REFERENCES patterns(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_pattern_projects_project ON pattern_projects(project_hash);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Stable id for a project directory
+pub fn project_hash(path: &Path) -> String {
+    // Rebuilding from components drops trailing slashes and `.` segments
+    let normalized: PathBuf = path.components().collect();
+    hash_workspace_id(&normalized.to_string_lossy())
+}
+
+/// Resolve a `--project` argument: a directory, or a hash as shown by MANA
+pub fn resolve(value: &str) -> Result<String> {
+    let is_hash = value.len() == 16 && value.chars().all(|c| c.is_ascii_hexdigit());
+    if is_hash && !Path::new(value).exists() {
+        return Ok(value.to_lowercase());
+    }
+    Ok(project_hash(&std::path::absolute(value)?))
+}
+
+/// Link patterns (by hash) to a project, returning how many links were new
+pub fn link<'a>(conn: &Connection, project_hash: &str, pattern_hashes: impl IntoIterator<Item = &'a str>) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "INSERT OR IGNORE INTO pattern_projects (pattern_id, project_hash)
+         SELECT id, ?1 FROM patterns WHERE pattern_hash = ?2",
+    )?;
+    let mut linked = 0;
+    for hash in pattern_hashes {
+        linked += stmt.execute(params![project_hash, hash])?;
+    }
+    Ok(linked)
+}
+
+/// Patterns learned in a project, by id
+pub fn patterns_in(conn: &Connection, project_hash: &str) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT pattern_id FROM pattern_projects WHERE project_hash = ?1 ORDER BY pattern_id")?;
+    let ids = stmt.query_map(params![project_hash], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(ids)
+}
+
+/// Delete every pattern learned in a project, returning their ids
+///
+/// A pattern is deleted even when other projects share it, along with its
+/// history, verdicts and other rows that may quote it.
+pub fn purge(conn: &Connection, project_hash: &str) -> Result<Vec<i64>> {
+    let ids = patterns_in(conn, project_hash)?;
+    let tx = conn.unchecked_transaction()?;
+    crate::storage::patterns::delete_with_dependents(&tx, &ids)?;
+    tx.commit()?;
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_and_resolve() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (1, 'h1', 'Bash', 'cargo build')",
+            [],
+        )
+        .unwrap();
+
+        let project = project_hash(Path::new("/work/app/"));
+        assert_eq!(project, project_hash(Path::new("/work/./app")));
+        assert_eq!(resolve("/work/app").unwrap(), project);
+        assert_eq!(resolve(&project.to_uppercase()).unwrap(), project);
+
+        assert_eq!(link(&conn, &project, ["h1", "missing"]).unwrap(), 1);
+        assert_eq!(link(&conn, &project, ["h1"]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_purge_removes_project_patterns() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query)
+             VALUES (1, 'h1', 'Bash', 'deploy client-x'), (2, 'h2', 'Bash', 'cargo build');
+             INSERT INTO pattern_tags (pattern_id, tag) VALUES (1, 'deploy'), (2, 'rust');
+             INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (1, 2, 2.0, 5);",
+        )
+        .unwrap();
+        let secret = project_hash(Path::new("/work/client-x"));
+        link(&conn, &secret, ["h1"]).unwrap();
+        link(&conn, &project_hash(Path::new("/work/app")), ["h2"]).unwrap();
+
+        assert_eq!(purge(&conn, &secret).unwrap(), vec![1]);
+        let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM patterns"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM pattern_tags"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM causal_edges"), 0);
+        assert_eq!(count("SELECT COUNT(*) FROM pattern_projects"), 1);
+        assert!(patterns_in(&conn, &secret).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! Pattern provenance
+//!
+//! Records which device a synced pattern came from. Patterns learned here
+//! have no row: they belong to this device, whatever it's called now. A
+//! pattern pulled from another machine gets the device its export named, and
+//! keeps it when later syncs merge more counts into it.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+
+/// Create the pattern_devices table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS pattern_devices (
+            pattern_id INTEGER PRIMARY KEY,
+            device TEXT NOT NULL,
+            first_seen DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (pattern_id) REFERENCES patterns(id) ON DELETE CASCADE
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Record `device` as the origin of the pattern with `pattern_hash`, unless it has one
+pub fn record(conn: &Connection, pattern_hash: &str, device: &str) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO pattern_devices (pattern_id, device)
+         SELECT id, ?2 FROM patterns WHERE pattern_hash = ?1",
+        params![pattern_hash, device],
+    )?;
+    Ok(inserted > 0)
+}
+
+/// Device a pattern came from, None if it was learned here
+pub fn device_of(conn: &Connection, pattern_id: i64) -> Result<Option<String>> {
+    Ok(conn
+        .query_row("SELECT device FROM pattern_devices WHERE pattern_id = ?1", params![pattern_id], |row| row.get(0))
+        .optional()?)
+}
+
+/// Origin devices of every synced pattern, by pattern id
+pub fn all(conn: &Connection) -> Result<HashMap<i64, String>> {
+    let mut stmt = conn.prepare("SELECT pattern_id, device FROM pattern_devices")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Pattern counts per device, `local` standing for patterns learned here, most first
+pub fn counts(conn: &Connection, local: &str) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(d.device, ?1), COUNT(*) FROM patterns p
+         LEFT JOIN pattern_devices d ON d.pattern_id = p.id
+         GROUP BY 1 ORDER BY 2 DESC, 1",
+    )?;
+    let rows = stmt.query_map(params![local], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_device_sticks() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query)
+             VALUES (1, 'a', 'Bash', 'cargo build'), (2, 'b', 'Bash', 'cargo test'), (3, 'c', 'Bash', 'npm test');",
+        )
+        .unwrap();
+
+        assert!(record(&conn, "a", "desktop").unwrap());
+        assert!(!record(&conn, "a", "devpod").unwrap());
+        assert!(record(&conn, "b", "desktop").unwrap());
+        assert!(!record(&conn, "missing", "desktop").unwrap());
+
+        assert_eq!(device_of(&conn, 1).unwrap().as_deref(), Some("desktop"));
+        assert_eq!(device_of(&conn, 3).unwrap(), None);
+        assert_eq!(counts(&conn, "laptop").unwrap(), vec![("desktop".to_string(), 2), ("laptop".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,327 @@
+//! Pattern quality for ranking
+//!
+//! Ranking by successes minus failures favours old, heavily used patterns
+//! (`git status`) over newer, more specific ones. `[ranking] formula` picks
+//! how a pattern's quality is scored instead:
+//!
+//! - `wilson`: lower bound of the 95% Wilson score interval of the success
+//!   rate, so a rate backed by few uses counts for less
+//! - `bayesian`: the success rate smoothed with `prior_weight` uses at the
+//!   rate of the pattern's tool and category, so patterns are judged against
+//!   their own category
+//! - `raw`: successes minus failures, the old ranking
+//!
+//! Except with `raw`, the rate is then blended with the pattern's reputation:
+//! reflection verdicts on it, weighted by provenance. Verdicts from this
+//! machine count `own_weight` each, those imported from teammates' bundles
+//! `team_weight` and those from installed packs `public_weight` (see
+//! `sync::verdicts`). Effective verdicts count for the pattern, neutral ones
+//! half, ineffective ones against it and harmful ones twice against it; the
+//! rate itself counts as `score_weight` verdicts. Finally `recency_weight`
+//! of the quality halves every `half_life_days` since the pattern was last
+//! used (or created).
+//!
+//! Every ranking path uses it: the top-pattern cache and snapshot that
+//! injection draws candidates from (quality is computed at refresh time),
+//! the success term of injection scoring, `mana patterns list --sort score`
+//! and text search.
+
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::config::{RankFormula, RankingConfig};
+
+/// Days since a pattern was last used or created, as an SQL expression over `patterns`
+pub const AGE_DAYS: &str = "julianday('now') - julianday(COALESCE(last_used, created_at))";
+
+/// Normal quantile for a 95% interval
+const WILSON_Z: f64 = 1.96;
+
+/// Success rate assumed where there is no data
+const NEUTRAL_RATE: f64 = 0.5;
+
+/// How much more a harmful verdict counts against a pattern than an ineffective one
+const HARMFUL_WEIGHT: f64 = 2.0;
+
+/// Verdicts on a pattern from one provenance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Tally {
+    pub effective: i64,
+    pub neutral: i64,
+    pub ineffective: i64,
+    pub harmful: i64,
+}
+
+impl Tally {
+    pub fn total(&self) -> i64 {
+        self.effective + self.neutral + self.ineffective + self.harmful
+    }
+}
+
+/// Verdicts on a pattern by provenance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Reputation {
+    /// This machine's reflection
+    pub own: Tally,
+    /// Teammates' exports
+    pub team: Tally,
+    /// Installed packs
+    pub public: Tally,
+}
+
+/// Scores pattern quality with a configured formula
+pub struct Ranker {
+    config: RankingConfig,
+    /// Success rate per (tool_type, command_category), '' for the whole tool
+    priors: HashMap<(String, String), f64>,
+    global: f64,
+    reputations: HashMap<i64, Reputation>,
+}
+
+impl Ranker {
+    /// Ranker for `config`; `bayesian` reads category success rates from `conn`
+    pub fn load(conn: &Connection, config: &RankingConfig) -> Result<Self> {
+        let mut ranker =
+            Self { config: config.clone(), priors: HashMap::new(), global: NEUTRAL_RATE, reputations: HashMap::new() };
+        if config.formula == RankFormula::Raw {
+            return Ok(ranker);
+        }
+        ranker.reputations = load_reputations(conn)?;
+        if config.formula != RankFormula::Bayesian {
+            return Ok(ranker);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT tool_type, COALESCE(command_category, ''), SUM(MAX(success_count, 0)), SUM(MAX(failure_count, 0))
+             FROM patterns WHERE status = 'active' GROUP BY 1, 2",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?))
+        })?;
+        let mut totals: HashMap<(String, String), (i64, i64)> = HashMap::new();
+        let mut all = (0, 0);
+        for row in rows {
+            let (tool, category, success, failure) = row?;
+            let tool_wide = totals.entry((tool.clone(), String::new())).or_default();
+            *tool_wide = (tool_wide.0 + success, tool_wide.1 + failure);
+            if !category.is_empty() {
+                totals.insert((tool, category), (success, failure));
+            }
+            all = (all.0 + success, all.1 + failure);
+        }
+        let rate = |(s, f): (i64, i64)| (s + f > 0).then(|| s as f64 / (s + f) as f64);
+        ranker.global = rate(all).unwrap_or(NEUTRAL_RATE);
+        ranker.priors = totals.into_iter().filter_map(|(key, counts)| Some((key, rate(counts)?))).collect();
+        Ok(ranker)
+    }
+
+    /// Ranker for the current data directory's `[ranking]` settings
+    pub fn configured(conn: &Connection) -> Result<Self> {
+        let config = crate::profile::resolve_mana_dir()
+            .map(|dir| crate::config::load_config(&dir).ranking.clone())
+            .unwrap_or_default();
+        Self::load(conn, &config)
+    }
+
+    /// Quality of pattern `id`: 0 to 1, except with `raw` where it's the score over 10
+    pub fn quality(
+        &self,
+        id: i64,
+        tool_type: &str,
+        category: Option<&str>,
+        success: i64,
+        failure: i64,
+        age_days: Option<f64>,
+    ) -> f64 {
+        let (s, n) = (success.max(0) as f64, (success.max(0) + failure.max(0)) as f64);
+        let rate = match self.config.formula {
+            RankFormula::Raw => return (success - failure) as f64 / 10.0,
+            RankFormula::Wilson => wilson_lower_bound(s, n),
+            RankFormula::Bayesian => {
+                let prior = self.prior(tool_type, category.unwrap_or_default());
+                let weight = self.config.prior_weight;
+                if n + weight > 0.0 { (s + weight * prior) / (n + weight) } else { prior }
+            }
+        };
+        self.with_reputation(id, rate) * self.recency(age_days)
+    }
+
+    /// Quality of pattern `id` as stored, None if there's no such pattern
+    pub fn quality_of(&self, conn: &Connection, id: i64) -> Result<Option<f64>> {
+        let row = conn
+            .query_row(
+                &format!("SELECT tool_type, command_category, success_count, failure_count, {} FROM patterns WHERE id = ?1", AGE_DAYS),
+                [id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                        row.get::<_, Option<f64>>(4)?,
+                    ))
+                },
+            )
+            .optional()?;
+        Ok(row.map(|(tool, category, success, failure, age)| self.quality(id, &tool, category.as_deref(), success, failure, age)))
+    }
+
+    /// Verdicts on pattern `id` (none with `raw`)
+    pub fn reputation(&self, id: i64) -> Option<&Reputation> {
+        self.reputations.get(&id)
+    }
+
+    /// `rate` blended with the pattern's weighted verdicts
+    fn with_reputation(&self, id: i64, rate: f64) -> f64 {
+        let Some(reputation) = self.reputations.get(&id) else {
+            return rate;
+        };
+        let (mut good, mut bad) = (0.0, 0.0);
+        for (tally, weight) in [
+            (reputation.own, self.config.own_weight),
+            (reputation.team, self.config.team_weight),
+            (reputation.public, self.config.public_weight),
+        ] {
+            good += weight * (tally.effective as f64 + 0.5 * tally.neutral as f64);
+            bad += weight * (0.5 * tally.neutral as f64 + tally.ineffective as f64 + HARMFUL_WEIGHT * tally.harmful as f64);
+        }
+        let weight = self.config.score_weight;
+        if weight + good + bad > 0.0 { (weight * rate + good) / (weight + good + bad) } else { rate }
+    }
+
+    fn prior(&self, tool_type: &str, category: &str) -> f64 {
+        let lookup = |category: &str| self.priors.get(&(tool_type.to_string(), category.to_string())).copied();
+        lookup(category).or_else(|| lookup("")).unwrap_or(self.global)
+    }
+
+    /// Factor from 1 (used now) down to 1 - recency_weight (long unused)
+    fn recency(&self, age_days: Option<f64>) -> f64 {
+        let (weight, half_life) = (self.config.recency_weight, self.config.half_life_days);
+        match age_days {
+            Some(age) if half_life > 0.0 => 1.0 - weight + weight * 0.5f64.powf(age.max(0.0) / half_life),
+            _ => 1.0,
+        }
+    }
+}
+
+/// Verdicts per pattern from local reflection and imported stats
+fn load_reputations(conn: &Connection) -> Result<HashMap<i64, Reputation>> {
+    let mut reputations: HashMap<i64, Reputation> = HashMap::new();
+
+    // Both tables are created on first use
+    if super::has_column(conn, "reflection_verdicts", "pattern_id") {
+        let mut stmt = conn.prepare(
+            "SELECT pattern_id, UPPER(verdict), COUNT(*) FROM reflection_verdicts
+             WHERE pattern_id IS NOT NULL GROUP BY 1, 2",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))?;
+        for row in rows {
+            let (id, verdict, count) = row?;
+            let own = &mut reputations.entry(id).or_default().own;
+            match verdict.as_str() {
+                "EFFECTIVE" => own.effective += count,
+                "NEUTRAL" => own.neutral += count,
+                "INEFFECTIVE" => own.ineffective += count,
+                "HARMFUL" => own.harmful += count,
+                _ => {}
+            }
+        }
+    }
+    if super::has_column(conn, "shared_verdicts", "provenance") {
+        let mut stmt = conn.prepare(
+            "SELECT pattern_id, provenance, SUM(effective), SUM(neutral), SUM(ineffective), SUM(harmful)
+             FROM shared_verdicts GROUP BY 1, 2",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let tally = Tally { effective: row.get(2)?, neutral: row.get(3)?, ineffective: row.get(4)?, harmful: row.get(5)? };
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, tally))
+        })?;
+        for row in rows {
+            let (id, provenance, tally) = row?;
+            let reputation = reputations.entry(id).or_default();
+            if provenance == "public" {
+                reputation.public = tally;
+            } else {
+                reputation.team = tally;
+            }
+        }
+    }
+    Ok(reputations)
+}
+
+/// Order (quality, successes) pairs best first
+pub fn best_first(a: (f64, i64), b: (f64, i64)) -> Ordering {
+    b.0.total_cmp(&a.0).then(b.1.cmp(&a.1))
+}
+
+fn wilson_lower_bound(successes: f64, n: f64) -> f64 {
+    if n == 0.0 {
+        return 0.0;
+    }
+    let z2 = WILSON_Z * WILSON_Z;
+    let p = successes / n;
+    (p + z2 / (2.0 * n) - WILSON_Z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt()) / (1.0 + z2 / n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formulas_favour_specific_recent_patterns() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (pattern_hash, tool_type, command_category, context_query, success_count, failure_count)
+             VALUES ('a', 'Bash', 'git', 'git status', 200, 60), ('b', 'Bash', 'cargo', 'cargo build', 18, 2);",
+        )
+        .unwrap();
+        let ranker = |formula| Ranker::load(&conn, &RankingConfig { formula, ..Default::default() }).unwrap();
+
+        // Old and busy against new and specific
+        let old = |r: &Ranker| r.quality(1, "Bash", Some("git"), 200, 60, Some(365.0));
+        let new = |r: &Ranker| r.quality(2, "Bash", Some("cargo"), 8, 0, Some(1.0));
+        let raw = ranker(RankFormula::Raw);
+        assert!(old(&raw) > new(&raw));
+        assert_eq!(raw.quality(0, "Bash", None, 2, 7, None), -0.5);
+        let wilson = ranker(RankFormula::Wilson);
+        assert!(new(&wilson) > old(&wilson));
+        assert!(wilson.quality(0, "Bash", None, 50, 0, None) > wilson.quality(0, "Bash", None, 3, 0, None));
+        assert_eq!(wilson.quality(0, "Bash", None, 0, 0, None), 0.0);
+
+        // Untried patterns start at their category's rate
+        let bayesian = ranker(RankFormula::Bayesian);
+        assert!(new(&bayesian) > old(&bayesian));
+        assert!((bayesian.quality(0, "Bash", Some("cargo"), 0, 0, None) - 0.9).abs() < 1e-9);
+        assert!((bayesian.quality(0, "Bash", Some("npm"), 0, 0, None) - 218.0 / 280.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reputation_weighs_provenance() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        crate::reflection::init_reflection_tables(&conn).unwrap();
+        crate::sync::verdicts::create_table(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'cargo build', 9, 1), (2, 'b', 'Bash', 'cargo test', 9, 1), (3, 'c', 'Bash', 'ls', 9, 1);
+             INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence) VALUES ('t1', 1, 'HARMFUL', 0.9), ('t2', 1, 'HARMFUL', 0.9);
+             INSERT INTO shared_verdicts (pattern_id, source, harmful, provenance) VALUES (2, 'laptop', 2, 'team'), (3, 'pack:x', 2, 'public');",
+        )
+        .unwrap();
+        let config = RankingConfig::default();
+        let ranker = Ranker::load(&conn, &config).unwrap();
+        let quality = |id| ranker.quality_of(&conn, id).unwrap().unwrap();
+        let plain = ranker.quality(4, "Bash", None, 9, 1, None);
+
+        // The same harmful verdicts cost more the closer they are to home
+        assert!(quality(1) < quality(2) && quality(2) < quality(3) && quality(3) < plain);
+        assert_eq!(ranker.reputation(1).unwrap().own.harmful, 2);
+        assert_eq!(ranker.reputation(3).unwrap().public.harmful, 2);
+        let raw = Ranker::load(&conn, &RankingConfig { formula: RankFormula::Raw, ..config }).unwrap();
+        assert!(raw.reputation(1).is_none());
+    }
+}
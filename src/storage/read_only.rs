@@ -0,0 +1,88 @@
+//! Read-only mode
+//!
+//! `mana --read-only`, `[storage] read_only = true` or
+//! `MANA_STORAGE_READ_ONLY=true` guarantee that nothing in the data
+//! directory is written: not the database, the embedding index, the
+//! injection snapshot or any state file. This is for shared or audited
+//! machines, or a data directory mounted from elsewhere.
+//!
+//! Injection keeps working from what is already there; the records it would
+//! spool and the repo signal cache are skipped. The session-end hook does
+//! nothing. Commands that learn, reflect, sync or otherwise change the store
+//! fail up front with [`ExitCode::ReadOnly`]. As a backstop, [`db::open`]
+//! hands out read-only connections for databases in the data directory, so a
+//! write that slips past those checks is refused by SQLite instead. (SQLite
+//! may still create the `-wal` and `-shm` files it needs to read a WAL
+//! database; their contents aren't the store's.)
+//!
+//! [`db::open`]: super::db::open
+
+use anyhow::Result;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::ci::{self, ExitCode};
+
+/// Environment variable that turns on read-only mode (`1` or `true`)
+pub const ENV: &str = "MANA_STORAGE_READ_ONLY";
+
+/// Whether read-only mode is on for this process
+///
+/// Decided on first use and cached; `--read-only` is exported before
+/// anything touches the store.
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        std::env::var(ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            || crate::profile::resolve_mana_dir().is_ok_and(|dir| crate::config::load_config(&dir).storage.read_only)
+    })
+}
+
+/// Whether read-only mode is on and `path` is inside the data directory
+///
+/// Files elsewhere, such as an export written to the working directory, are
+/// the user's to write.
+pub fn protects(path: &Path) -> bool {
+    enabled() && crate::profile::resolve_mana_dir().is_ok_and(|dir| path.starts_with(dir))
+}
+
+/// Fail with [`ExitCode::ReadOnly`] if read-only mode is on
+pub fn ensure_writable(what: &str) -> Result<()> {
+    if enabled() {
+        return Err(ci::fail(
+            ExitCode::ReadOnly,
+            format!("{} is disabled in read-only mode (--read-only or [storage] read_only)", what),
+        ));
+    }
+    Ok(())
+}
+
+/// Say why a write was refused when SQLite turned it down
+pub fn explain(err: anyhow::Error) -> anyhow::Error {
+    if ci::exit_code(&err) == ExitCode::ReadOnly && !err.chain().any(|c| c.is::<ci::Failure>()) {
+        return err.context("The database is read-only; this command writes to it");
+    }
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refused_writes_exit_read_only() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        super::super::db::open(&db_path).unwrap().execute_batch("CREATE TABLE t (n INTEGER)").unwrap();
+
+        let conn = super::super::db::open_readonly(&db_path).unwrap();
+        let err: anyhow::Error = conn.execute("INSERT INTO t VALUES (1)", []).unwrap_err().into();
+        let err = explain(err);
+        assert_eq!(ci::exit_code(&err), ExitCode::ReadOnly);
+        assert!(err.to_string().contains("read-only"));
+
+        // Errors that already carry a code are left alone
+        let failure = explain(ci::fail(ExitCode::ReadOnly, "Learning is disabled"));
+        assert_eq!(failure.to_string(), "Learning is disabled");
+    }
+}
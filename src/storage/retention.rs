@@ -0,0 +1,194 @@
+//! Retention policy
+//!
+//! Without limits the database only grows: every session adds patterns,
+//! verdicts and injection records. Consolidation applies `[retention]`:
+//! records older than their age cap are deleted, the lowest-value patterns
+//! are evicted past the pattern cap, and if the file is still over its size
+//! cap it is vacuumed and more patterns go until it fits. Each limit is
+//! disabled with 0; the age caps are off unless set.
+//!
+//! A pattern's value is its score (successes minus failures); among equal
+//! scores the one used least recently goes first. Archived patterns go
+//! before any active one. Quarantined patterns are never evicted: their
+//! rows are what keeps the next learning pass from relearning them as
+//! active.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::Path;
+use tracing::{debug, info};
+
+use crate::config::RetentionConfig;
+
+/// Rounds of evict-and-vacuum before giving up on the size cap
+const SIZE_ROUNDS: usize = 5;
+
+/// What a retention run removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetentionReport {
+    /// Patterns evicted for the count or size cap
+    pub evicted: Vec<i64>,
+    /// Verdicts older than `max_verdict_age_days`
+    pub verdicts_deleted: usize,
+    /// Injection records and cached judgements older than `max_trajectory_age_days`
+    pub trajectories_deleted: usize,
+    pub db_bytes_before: u64,
+    pub db_bytes_after: u64,
+    /// Whether the file was vacuumed to get under `max_db_mb`
+    pub vacuumed: bool,
+    /// Still over `max_db_mb` after the last round
+    pub over_size: bool,
+}
+
+/// Apply the retention policy to the database at `db_path`
+pub fn apply(db_path: &Path, config: &RetentionConfig) -> Result<RetentionReport> {
+    let mut report = RetentionReport { db_bytes_before: db_size(db_path), ..Default::default() };
+    let conn = super::db::open(db_path)?;
+
+    if config.max_verdict_age_days > 0 {
+        let cutoff = format!("-{} days", config.max_verdict_age_days);
+        report.verdicts_deleted = delete_older(&conn, "reflection_verdicts", &cutoff)?;
+    }
+    if config.max_trajectory_age_days > 0 {
+        let cutoff = format!("-{} days", config.max_trajectory_age_days);
+        report.trajectories_deleted = delete_older(&conn, "injection_log", &cutoff)?
+            + delete_older(&conn, "judge_cache", &cutoff)?
+            + delete_older(&conn, "budget_overruns", &cutoff)?;
+    }
+
+    if config.max_patterns > 0 {
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0))?;
+        let excess = (count - config.max_patterns as i64).max(0) as usize;
+        report.evicted.extend(evict(&conn, excess)?);
+    }
+    drop(conn);
+
+    if config.max_db_mb > 0 {
+        let cap = config.max_db_mb * 1024 * 1024;
+        for _ in 0..SIZE_ROUNDS {
+            let size = db_size(db_path);
+            if size <= cap {
+                break;
+            }
+            if !report.vacuumed {
+                // Free pages from the deletions above may be all it takes
+                super::maintenance::vacuum(db_path)?;
+                report.vacuumed = true;
+                continue;
+            }
+            let conn = super::db::open(db_path)?;
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0))?;
+            if count == 0 {
+                break;
+            }
+            // Patterns are most of the file; take the share it's over by, with some margin
+            let share = (size - cap) as f64 / size as f64 * 1.1;
+            let evicted = evict(&conn, ((count as f64 * share).ceil() as usize).max(1))?;
+            debug!("Size cap: evicted {} patterns at {} bytes", evicted.len(), size);
+            report.evicted.extend(evicted);
+            drop(conn);
+            super::maintenance::vacuum(db_path)?;
+        }
+        report.over_size = db_size(db_path) > cap;
+    }
+
+    report.db_bytes_after = db_size(db_path);
+    if !report.evicted.is_empty() || report.verdicts_deleted > 0 || report.trajectories_deleted > 0 {
+        info!(
+            "Retention: evicted {} patterns, deleted {} verdicts and {} trajectory records",
+            report.evicted.len(),
+            report.verdicts_deleted,
+            report.trajectories_deleted
+        );
+    }
+    Ok(report)
+}
+
+/// Delete the `limit` lowest-value patterns, returning their ids
+fn evict(conn: &Connection, limit: usize) -> Result<Vec<i64>> {
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+    let ids: Vec<i64> = {
+        let mut stmt = conn.prepare(
+            "SELECT id FROM patterns
+             WHERE status != 'quarantined'
+             ORDER BY status = 'active', (success_count - failure_count), COALESCE(last_used, created_at), id
+             LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit as i64], |row| row.get(0))?;
+        rows.collect::<Result<_, _>>()?
+    };
+    let tx = conn.unchecked_transaction()?;
+    super::patterns::delete_with_dependents(&tx, &ids)?;
+    tx.commit()?;
+    Ok(ids)
+}
+
+/// Delete rows of `table` created before `now + offset`; missing tables count as empty
+fn delete_older(conn: &Connection, table: &str, offset: &str) -> Result<usize> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok(0);
+    }
+    // injection_log and budget_overruns store RFC 3339, the rest CURRENT_TIMESTAMP; datetime() reads both
+    let sql = format!("DELETE FROM {} WHERE datetime(created_at) < datetime('now', ?1)", table);
+    Ok(conn.execute(&sql, params![offset])?)
+}
+
+/// Size of the database including its WAL
+fn db_size(db_path: &Path) -> u64 {
+    let wal = db_path.with_extension("sqlite-wal");
+    [db_path, wal.as_path()].iter().filter_map(|p| std::fs::metadata(p).ok()).map(|m| m.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_evicts_lowest_value_and_old_records() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let conn = super::super::db::open(&db_path).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        crate::reflection::init_reflection_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, success_count, failure_count, last_used, status)
+             VALUES (1, 'a', 'Bash', 'cargo build', 5, 0, '2026-01-01', 'active'),
+                    (2, 'b', 'Bash', 'cargo test', 1, 0, '2026-01-01', 'active'),
+                    (3, 'c', 'Bash', 'npm test', 1, 0, '2026-03-01', 'active'),
+                    (4, 'd', 'Bash', 'rm -rf', -9, 0, '2026-03-01', 'quarantined'),
+                    (5, 'e', 'Bash', 'make', 9, 0, '2026-03-01', 'archived');
+             INSERT INTO reflection_verdicts (trajectory_hash, verdict, confidence, created_at)
+             VALUES ('t1', 'EFFECTIVE', 0.9, datetime('now', '-400 days')), ('t2', 'EFFECTIVE', 0.9, datetime('now'));",
+        )
+        .unwrap();
+        drop(conn);
+
+        // Age caps are opt-in
+        let config = RetentionConfig { max_patterns: 3, max_db_mb: 0, ..Default::default() };
+        assert_eq!(apply(&db_path, &RetentionConfig { max_patterns: 0, ..config.clone() }).unwrap().verdicts_deleted, 0);
+
+        let config = RetentionConfig { max_verdict_age_days: 180, ..config };
+        let report = apply(&db_path, &config).unwrap();
+        // Archived first, then the older of the two equal scores; quarantined stays
+        assert_eq!(report.evicted, vec![5, 2]);
+        assert_eq!(report.verdicts_deleted, 1);
+        assert!(!report.vacuumed);
+
+        let conn = Connection::open(&db_path).unwrap();
+        let left: Vec<i64> =
+            conn.prepare("SELECT id FROM patterns ORDER BY id").unwrap().query_map([], |r| r.get(0)).unwrap().flatten().collect();
+        assert_eq!(left, vec![1, 3, 4]);
+
+        // Within the caps nothing more goes
+        assert!(apply(&db_path, &config).unwrap().evicted.is_empty());
+    }
+}
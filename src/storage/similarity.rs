@@ -1,153 +1,131 @@
 //! Lightweight text similarity for pattern matching
 //!
-//! Uses TF-IDF style scoring for fast, effective similarity matching
-//! without requiring external ML libraries.
+//! TF-IDF weighted term overlap, without requiring external ML libraries.
+//! Text is normalized into terms: lowercased, split on punctuation, stop
+//! words dropped (English, German, French, Spanish and Portuguese) and common
+//! English suffixes stemmed, so "Running tests" and "run the test" share
+//! their terms. A query's similarity to a text is the share of its term
+//! weight the text covers, scaled by a tech-stack modifier.
+//!
+//! Term weights come from [`TermStats`], document frequencies across stored
+//! patterns (see `terms`), so words found in most patterns count for little.
+//! [`calculate_similarity`] weighs terms by their kind alone, for callers
+//! without a corpus at hand.
 //!
 //! Optimized for sub-millisecond performance on small pattern sets.
 
-use std::collections::HashMap;
-
-/// Calculate similarity between query and patterns using TF-IDF-like scoring
-/// Returns a score between 0.0 and 1.0
-/// Returns a penalty (low score multiplier) for tech stack mismatches
-///
-/// Optimized for sub-millisecond performance with early exits and reduced allocations
-#[inline]
-pub fn calculate_similarity(query: &str, pattern_text: &str) -> f64 {
-    // Fast path: skip empty inputs
-    if query.is_empty() || pattern_text.is_empty() {
-        return 0.0;
-    }
-
-    // Fast path for typical inputs (covers ~95% of real-world cases)
-    // Use simple word overlap instead of full tokenization
-    // Most queries are <80 chars and patterns are <300 chars
-    if query.len() < 100 && pattern_text.len() < 400 {
-        return calculate_similarity_fast(query, pattern_text);
-    }
-
-    // Longer inputs: do lowercase once and reuse for both tech detection and tokenization
-    let query_lower = query.to_lowercase();
-    let pattern_lower = pattern_text.to_lowercase();
-
-    // Tech stack detection on pre-lowercased strings (avoids redundant lowercase)
-    let query_tech = detect_tech_stack_lowered(&query_lower);
-    let pattern_tech = detect_tech_stack_lowered(&pattern_lower);
-
-    // Calculate tech modifier early - can skip expensive tokenization if completely mismatched
-    let tech_modifier = if query_tech != TechStack::Unknown && pattern_tech != TechStack::Unknown {
-        if query_tech == pattern_tech {
-            1.5 // Boost for matching tech stack
-        } else {
-            0.3 // Penalty for mismatched tech stack (but don't completely filter out)
-        }
-    } else {
-        1.0 // Neutral for unknown contexts - allows generic patterns to match
-    };
-
-    // For severe mismatches, apply early exit with minimum score
-    // This saves tokenization time for patterns that won't be selected anyway
-    if tech_modifier < 0.5 && query_tech != TechStack::Unknown {
-        // Quick check: if no common words at all, return minimal score
-        let has_common = query_lower.split_whitespace()
-            .take(5) // Only check first 5 words for speed
-            .any(|w| w.len() > 3 && pattern_lower.contains(w));
-        if !has_common {
-            return 0.05 * tech_modifier; // Very low score for no overlap + tech mismatch
-        }
-    }
+/// Document frequencies of terms across a corpus of patterns
+pub trait TermStats {
+    /// Patterns in the corpus; 0 means no statistics
+    fn documents(&self) -> u64;
 
-    // Tokenize pre-lowercased text directly (avoids third lowercase conversion)
-    let query_tokens = tokenize_lowered(&query_lower);
-    let pattern_tokens = tokenize_lowered(&pattern_lower);
-
-    if query_tokens.is_empty() || pattern_tokens.is_empty() {
-        return 0.0;
-    }
-
-    // Build term frequency maps (with pre-allocated capacity)
-    let query_tf = build_tf_fast(&query_tokens);
-    let pattern_tf = build_tf_fast(&pattern_tokens);
-
-    // Calculate weighted overlap
-    let mut score = 0.0;
-    let mut query_norm = 0.0;
+    /// Patterns containing `term`
+    fn document_frequency(&self, term: &str) -> u64;
+}
 
-    for (term, &count) in &query_tf {
-        let weight = term_weight_fast(term);
-        let weighted_count = count as f64 * weight;
-        query_norm += weighted_count * weighted_count;
+/// No corpus: every term gets the same document frequency
+pub struct Uniform;
 
-        if let Some(&pattern_count) = pattern_tf.get(term) {
-            // Boost for exact matches
-            score += weighted_count * pattern_count as f64 * weight;
-        }
+impl TermStats for Uniform {
+    fn documents(&self) -> u64 {
+        0
     }
 
-    // Normalize and apply tech modifier
-    if query_norm > 0.0 {
-        (score / query_norm.sqrt()) * tech_modifier
-    } else {
-        0.0
+    fn document_frequency(&self, _term: &str) -> u64 {
+        0
     }
 }
 
-/// Ultra-fast similarity for short strings using simple word overlap
-/// Avoids tokenization overhead for common case of short queries
+/// Calculate similarity between query and patterns using TF-IDF-like scoring
+/// Returns a score between 0.0 and 1.0 (up to 1.5 with a matching tech stack)
+/// Returns a penalty (low score multiplier) for tech stack mismatches
 #[inline]
-fn calculate_similarity_fast(query: &str, pattern_text: &str) -> f64 {
-    // Single lowercase conversion per string - reused for both tech detection and matching
-    let query_lower = query.to_lowercase();
-    let pattern_lower = pattern_text.to_lowercase();
-
-    // Check tech stacks using pre-lowercased strings (avoids duplicate lowercase)
-    let query_tech = detect_tech_stack_lowered(&query_lower);
-    let pattern_tech = detect_tech_stack_lowered(&pattern_lower);
+pub fn calculate_similarity(query: &str, pattern_text: &str) -> f64 {
+    Scorer::new(query, &Uniform).score(pattern_text)
+}
 
-    let tech_modifier = if query_tech != TechStack::Unknown && pattern_tech != TechStack::Unknown {
-        if query_tech == pattern_tech { 1.5 } else { 0.3 }
-    } else {
-        1.0
-    };
+/// A query prepared for scoring many texts
+///
+/// Weights are looked up once per query term, so ranking a candidate set
+/// costs one tokenization per candidate.
+pub struct Scorer {
+    /// Query terms and their weights
+    terms: Vec<(String, f64)>,
+    total: f64,
+    tech: TechStack,
+}
 
-    // Early exit for tech mismatches with no common words
-    if tech_modifier < 0.5 {
-        return 0.1 * tech_modifier;
+impl Scorer {
+    /// Prepare `query`, weighing its terms with `stats`
+    pub fn new(query: &str, stats: &dyn TermStats) -> Self {
+        let lower = query.to_lowercase();
+        let documents = stats.documents();
+        let mut terms: Vec<(String, f64)> = Vec::new();
+        for token in tokenize_lowered(&lower) {
+            match terms.iter_mut().find(|(t, _)| *t == token) {
+                Some((_, weight)) => *weight += term_weight(&token),
+                None => {
+                    let weight = term_weight(&token);
+                    terms.push((token, weight));
+                }
+            }
+        }
+        if documents > 0 {
+            // Terms no pattern has can't match anything and don't count against any
+            terms.retain_mut(|(term, weight)| {
+                let df = stats.document_frequency(term);
+                *weight *= idf(documents, df);
+                df > 0
+            });
+        }
+        let total = terms.iter().map(|(_, w)| w).sum();
+        Self { terms, total, tech: detect_tech_stack_lowered(&lower) }
     }
 
-    // Simple word overlap scoring with early termination
-    let mut matches = 0u32;
-    let mut total_weight = 0.0f32;
-
-    for word in query_lower.split_whitespace() {
-        let len = word.len();
-        if len < 2 {
-            continue;
-        }
+    /// Whether the query has no terms to match
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
 
-        // Skip very common words
-        if matches!(word, "the" | "and" | "for" | "to" | "in" | "of" | "is" | "it") {
-            continue;
+    /// Similarity of `text` to the query
+    pub fn score(&self, text: &str) -> f64 {
+        if self.total <= 0.0 || text.is_empty() {
+            return 0.0;
         }
+        let lower = text.to_lowercase();
+        let text_tech = detect_tech_stack_lowered(&lower);
+        let tech_modifier = if self.tech != TechStack::Unknown && text_tech != TechStack::Unknown {
+            if self.tech == text_tech {
+                1.5 // Boost for matching tech stack
+            } else {
+                0.3 // Penalty for mismatched tech stack (but don't completely filter out)
+            }
+        } else {
+            1.0 // Neutral for unknown contexts - allows generic patterns to match
+        };
 
-        let weight = if len >= 4 { 2.0 } else { 1.0 };
-        total_weight += weight;
-
-        if pattern_lower.contains(word) {
-            matches += 1;
-            // Bonus for file extension matches (high signal)
-            if len <= 4 && matches!(word, "rs" | "js" | "ts" | "py" | "go" | "rb" | "sh" | "tsx" | "jsx") {
-                matches += 1;
+        let mut matched = vec![false; self.terms.len()];
+        for token in tokenize_lowered(&lower) {
+            if let Some(i) = self.terms.iter().position(|(t, _)| *t == token) {
+                matched[i] = true;
             }
         }
+        let covered: f64 = self.terms.iter().zip(&matched).filter(|(_, m)| **m).map(|((_, w), _)| w).sum();
+        covered / self.total * tech_modifier
     }
+}
 
-    if total_weight == 0.0 {
-        return 0.0;
-    }
+/// Inverse document frequency; never zero, so a term in every pattern still counts a little
+fn idf(documents: u64, df: u64) -> f64 {
+    (1.0 + documents as f64 / df.max(1) as f64).ln()
+}
 
-    let base_score = (matches as f64 / total_weight as f64).min(1.0);
-    (base_score * tech_modifier).min(2.0)
+/// Distinct normalized terms of `text`
+pub fn terms(text: &str) -> Vec<String> {
+    let mut terms = tokenize(text);
+    terms.sort_unstable();
+    terms.dedup();
+    terms
 }
 
 /// Technology stack detection for context-aware matching
@@ -161,9 +139,13 @@ enum TechStack {
     Unknown,
 }
 
+/// Whether `text` carries enough signals to tell its tech stack
+pub fn has_tech_stack(text: &str) -> bool {
+    detect_tech_stack_fast(text) != TechStack::Unknown
+}
+
 /// Fast tech stack detection directly on the raw string (avoids tokenization)
 /// Uses substring matching for speed - O(n) where n is string length
-#[allow(dead_code)]
 #[inline]
 fn detect_tech_stack_fast(text: &str) -> TechStack {
     // Convert to lowercase bytes for fast comparison
@@ -290,227 +272,115 @@ fn detect_tech_stack(tokens: &[String]) -> TechStack {
     else if shell_signals == max_signals { TechStack::Shell }
     else { TechStack::Unknown }
 }
-
-/// Tokenize text into meaningful terms with basic stemming
-/// Optimized to reduce allocations by reusing a single lowercase buffer
-#[cfg(test)]
+/// Tokenize text into normalized terms, repeats included
 fn tokenize(text: &str) -> Vec<String> {
-    // Pre-allocate with estimated capacity to reduce reallocations
-    let mut tokens = Vec::with_capacity(text.len() / 6);
-
-    // Work with lowercase version once
-    let lower = text.to_lowercase();
-
-    for token in lower.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-') {
-        if token.len() >= 2 && !is_stopword(token) {
-            tokens.push(stem_word(token));
-        }
-    }
-
-    tokens
-}
-
-/// Fast tokenization that avoids String allocation where possible
-/// Returns string slices into a pre-lowercased buffer
-#[allow(dead_code)]
-#[inline]
-fn tokenize_fast(text: &str) -> Vec<String> {
-    // Pre-allocate with estimated capacity
-    let estimated_tokens = (text.len() / 8).max(8);
-    let mut tokens = Vec::with_capacity(estimated_tokens);
-
-    // Work with lowercase version once
-    let lower = text.to_lowercase();
-
-    for token in lower.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-') {
-        if token.len() >= 2 && !is_stopword_fast(token) {
-            // Skip expensive stemming for very short tokens (most common case)
-            if token.len() <= 4 {
-                tokens.push(token.to_string());
-            } else {
-                tokens.push(stem_word_fast(token));
-            }
-        }
-    }
-
-    tokens
+    tokenize_lowered(&text.to_lowercase())
 }
 
 /// Tokenize already-lowercased text (avoids redundant lowercase conversion)
-/// Used by the slow path in calculate_similarity for longer inputs
 #[inline]
 fn tokenize_lowered(lower: &str) -> Vec<String> {
-    let estimated_tokens = (lower.len() / 8).max(8);
-    let mut tokens = Vec::with_capacity(estimated_tokens);
-
-    for token in lower.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-') {
-        if token.len() >= 2 && !is_stopword_fast(token) {
-            if token.len() <= 4 {
-                tokens.push(token.to_string());
-            } else {
-                tokens.push(stem_word_fast(token));
-            }
+    let mut tokens = Vec::with_capacity((lower.len() / 8).max(8));
+    for token in lower.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let token = token.trim_matches('_');
+        // Numbers are line numbers, ports and counts: noise for matching
+        if token.chars().count() < 2 || token.bytes().all(|b| b.is_ascii_digit()) || is_stopword(token) {
+            continue;
         }
+        tokens.push(stem(token));
     }
-
     tokens
 }
 
-/// Fast stopword check using a match (compiler optimizes to jump table)
-#[inline]
-fn is_stopword_fast(term: &str) -> bool {
-    matches!(term, "the" | "a" | "an" | "is" | "are" | "was" | "were" |
-             "be" | "been" | "being" | "have" | "has" | "had" | "do" |
-             "does" | "did" | "will" | "would" | "could" | "should" |
-             "may" | "might" | "must" | "can" | "to" | "of" | "in" |
-             "for" | "on" | "with" | "at" | "by" | "from" | "as" |
-             "it" | "that" | "this" | "these" | "those" | "and" | "or" |
-             "but" | "if" | "then" | "else" | "when" | "where" | "how")
-}
-
-/// Fast stemming that only handles the most common cases
+/// Function words that carry no meaning for matching
+///
+/// Negations are kept: "not found" and "found" are different situations.
 #[inline]
-fn stem_word_fast(word: &str) -> String {
-    let len = word.len();
-
-    // Handle -ing suffix (most common)
-    if len > 5 && word.ends_with("ing") {
-        let stem = &word[..len - 3];
-        // Handle doubled consonants: running -> run
-        let bytes = stem.as_bytes();
-        if bytes.len() >= 2 && bytes[bytes.len()-1] == bytes[bytes.len()-2] {
-            return stem[..stem.len()-1].to_string();
-        }
-        return stem.to_string();
-    }
-
-    // Handle -ed suffix
-    if len > 4 && word.ends_with("ed") {
-        return word[..len - 2].to_string();
-    }
-
-    // Handle -s suffix (but not words ending in ss)
-    if len > 3 && word.ends_with('s') && !word.ends_with("ss") {
-        return word[..len - 1].to_string();
-    }
-
-    word.to_string()
+fn is_stopword(term: &str) -> bool {
+    // English
+    matches!(term, "the" | "an" | "is" | "are" | "was" | "were" | "be" | "been" | "being" |
+             "have" | "has" | "had" | "do" | "does" | "did" | "will" | "would" | "could" |
+             "should" | "may" | "might" | "must" | "can" | "to" | "of" | "in" | "for" | "on" |
+             "with" | "at" | "by" | "from" | "as" | "it" | "its" | "that" | "this" | "these" |
+             "those" | "and" | "or" | "but" | "if" | "then" | "else" | "when" | "where" | "how" |
+             "what" | "which" | "who" | "into" | "onto" | "about" | "so" | "some" | "any" |
+             "all" | "there" | "here" | "we" | "you" | "he" | "she" | "they" | "me" | "my" |
+             "our" | "your" | "their" | "them" | "us" | "also" | "just" | "very" | "than" |
+             "too" | "up" | "out" | "over" | "via" | "per" | "now")
+    // German
+    || matches!(term, "der" | "die" | "das" | "den" | "dem" | "des" | "ein" | "eine" | "einen" |
+             "einem" | "einer" | "und" | "oder" | "aber" | "ist" | "sind" | "war" | "mit" |
+             "für" | "auf" | "zu" | "im" | "von" | "vom" | "zum" | "zur" | "bei" | "aus" |
+             "nach" | "wie" | "wenn" | "als" | "auch" | "noch" | "ich" | "wir" | "sie" | "er" |
+             "es" | "dass" | "sich" | "werden" | "wird" | "hat" | "haben")
+    // French
+    || matches!(term, "le" | "la" | "les" | "un" | "une" | "des" | "du" | "de" | "au" | "aux" |
+             "et" | "ou" | "mais" | "est" | "sont" | "dans" | "pour" | "par" | "sur" | "avec" |
+             "ce" | "cette" | "ces" | "qui" | "que" | "il" | "elle" | "nous" | "vous" | "ils" |
+             "se" | "si" | "en" | "être" | "avoir")
+    // Spanish and Portuguese
+    || matches!(term, "el" | "los" | "las" | "del" | "al" | "una" | "unos" | "unas" | "por" |
+             "para" | "con" | "como" | "pero" | "más" | "este" | "esta" | "estos" | "estas" |
+             "lo" | "su" | "sus" | "ser" | "son" | "um" | "uma" | "os" | "da" | "dos" | "das" |
+             "em" | "na" | "nas" | "nos" | "ao" | "com" | "mas" | "também" | "foi")
 }
 
-/// Basic stemming - remove common suffixes
-#[allow(dead_code)]
-fn stem_word(word: &str) -> String {
-    let word = word.to_string();
-
-    // Handle common verb forms
-    if word.ends_with("ing") && word.len() > 5 {
-        // editing -> edit, running -> run
-        let stem = &word[..word.len() - 3];
-        // Handle doubled consonants: running -> run (not runn)
-        if stem.len() > 2 {
-            let chars: Vec<char> = stem.chars().collect();
-            if chars.len() >= 2 && chars[chars.len()-1] == chars[chars.len()-2] {
-                return stem[..stem.len()-1].to_string();
-            }
+/// Strip common English suffixes so inflections share a term
+///
+/// Deliberately crude: "creating", "created" and "create" all become
+/// "creat", which only has to be consistent, not a word.
+fn stem(word: &str) -> String {
+    if !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    let has_vowel = |s: &str| s.bytes().any(|b| matches!(b, b'a' | b'e' | b'i' | b'o' | b'u' | b'y'));
+    // running -> run, but installing -> install
+    let undouble = |s: &str| {
+        let b = s.as_bytes();
+        if b.len() > 3 && b[b.len() - 1] == b[b.len() - 2] && !matches!(b[b.len() - 1], b'l' | b's' | b'z') {
+            s[..s.len() - 1].to_string()
+        } else {
+            s.to_string()
         }
-        return stem.to_string();
-    }
-
-    // Handle -tion -> -t (configuration -> configurat)
-    if word.ends_with("tion") && word.len() > 6 {
-        return word[..word.len() - 3].to_string();
-    }
-
-    // Handle -ed suffix
-    if word.ends_with("ed") && word.len() > 4 {
-        return word[..word.len() - 2].to_string();
-    }
-
-    // Handle -ly suffix
-    if word.ends_with("ly") && word.len() > 4 {
-        return word[..word.len() - 2].to_string();
-    }
-
-    // Handle -s suffix (but not words like "class")
-    if word.ends_with('s') && word.len() > 3 && !word.ends_with("ss") {
-        return word[..word.len() - 1].to_string();
-    }
-
-    word
-}
+    };
 
-/// Build term frequency map
-/// Pre-allocates HashMap capacity for better performance
-#[allow(dead_code)]
-fn build_tf(tokens: &[String]) -> HashMap<&str, usize> {
-    let mut tf = HashMap::with_capacity(tokens.len());
-    for token in tokens {
-        *tf.entry(token.as_str()).or_insert(0) += 1;
-    }
-    tf
-}
+    let len = word.len();
+    let stem = if len > 4 && word.ends_with("ies") {
+        format!("{}y", &word[..len - 3])
+    } else if len > 7 && word.ends_with("ation") {
+        word[..len - 5].to_string()
+    } else if len > 7 && word.ends_with("ment") {
+        word[..len - 4].to_string()
+    } else if len > 5 && word.ends_with("ing") && has_vowel(&word[..len - 3]) {
+        undouble(&word[..len - 3])
+    } else if len > 4 && word.ends_with("ed") && !word.ends_with("eed") && has_vowel(&word[..len - 2]) {
+        undouble(&word[..len - 2])
+    } else if len > 4 && ["xes", "shes", "sses", "zes"].iter().any(|s| word.ends_with(s)) {
+        word[..len - 2].to_string()
+    } else if len > 3 && word.ends_with('s') && !["ss", "us", "is", "os"].iter().any(|s| word.ends_with(s)) {
+        word[..len - 1].to_string()
+    } else if len > 5 && word.ends_with("ly") {
+        word[..len - 2].to_string()
+    } else {
+        word.to_string()
+    };
 
-/// Fast term frequency map building with minimal overhead
-#[inline]
-fn build_tf_fast(tokens: &[String]) -> HashMap<&str, usize> {
-    let mut tf = HashMap::with_capacity(tokens.len());
-    for token in tokens {
-        *tf.entry(token.as_str()).or_insert(0) += 1;
+    // create / created / creating
+    match stem.strip_suffix('e') {
+        Some(base) if stem.len() >= 6 => base.to_string(),
+        _ => stem,
     }
-    tf
 }
 
-/// Get importance weight for a term
+/// Importance of a term by its kind, before corpus statistics
 /// Technical terms and file extensions get higher weights
-#[allow(dead_code)]
-fn term_weight(term: &str) -> f64 {
-    // File extensions - very specific
-    if is_file_extension(term) {
-        return 3.0;
-    }
-
-    // Programming language names
-    if matches!(term, "rust" | "python" | "javascript" | "typescript" |
-                      "golang" | "java" | "ruby" | "cpp" | "shell") {
-        return 2.5;
-    }
-
-    // Tool names
-    if matches!(term, "bash" | "edit" | "write" | "read" | "grep" |
-                      "task" | "glob" | "npm" | "cargo" | "git") {
-        return 2.0;
-    }
-
-    // Action verbs
-    if matches!(term, "fix" | "add" | "create" | "update" | "delete" |
-                      "implement" | "refactor" | "test" | "build" | "run") {
-        return 1.5;
-    }
-
-    // Error-related terms
-    if matches!(term, "error" | "failed" | "cannot" | "undefined" |
-                      "missing" | "invalid" | "null" | "panic") {
-        return 2.0;
-    }
-
-    // Default weight based on length (longer = more specific = higher weight)
-    if term.len() >= 8 {
-        1.5
-    } else if term.len() >= 5 {
-        1.2
-    } else {
-        1.0
-    }
-}
-
-/// Fast term weight lookup using match (compiler optimized)
 #[inline]
-fn term_weight_fast(term: &str) -> f64 {
+fn term_weight(term: &str) -> f64 {
     // Combined match for all high-weight terms (compiler optimizes to efficient lookup)
     match term {
         // File extensions (3.0)
         "rs" | "js" | "ts" | "tsx" | "jsx" | "py" | "go" | "rb" |
-        "java" | "cpp" | "c" | "h" | "md" | "json" | "yaml" | "yml" |
+        "java" | "cpp" | "md" | "json" | "yaml" | "yml" |
         "toml" | "sh" | "html" | "css" | "sql" | "vue" | "svelte" => 3.0,
 
         // Programming languages (2.5)
@@ -520,12 +390,17 @@ fn term_weight_fast(term: &str) -> f64 {
         // Tool names & error terms (2.0)
         "bash" | "edit" | "write" | "read" | "grep" |
         "task" | "glob" | "npm" | "cargo" | "git" |
-        "error" | "fail" | "cannot" | "undef" |
+        "error" | "fail" | "cannot" | "undefin" |
         "miss" | "invalid" | "null" | "panic" => 2.0,
 
         // Action verbs (1.5)
         "fix" | "add" | "creat" | "updat" | "delet" |
-        "implement" | "refactor" | "test" | "build" | "run" => 1.5,
+        "implement" | "refactor" | "test" | "build" => 1.5,
+
+        // Words nearly every pattern has
+        "file" | "run" | "code" | "command" => 0.5,
+        // Labels of the pattern context format
+        "approach" | "outcom" | "success" | "pitfall" | "advice" => 0.5,
 
         // Default: length-based
         _ => {
@@ -537,26 +412,6 @@ fn term_weight_fast(term: &str) -> f64 {
     }
 }
 
-/// Check if term is a file extension
-#[allow(dead_code)]
-fn is_file_extension(term: &str) -> bool {
-    matches!(term, "rs" | "js" | "ts" | "tsx" | "jsx" | "py" | "go" | "rb" |
-             "java" | "cpp" | "c" | "h" | "md" | "json" | "yaml" | "yml" |
-             "toml" | "sh" | "html" | "css" | "sql" | "vue" | "svelte")
-}
-
-/// Check if term is a common stopword
-#[allow(dead_code)]
-fn is_stopword(term: &str) -> bool {
-    matches!(term, "the" | "a" | "an" | "is" | "are" | "was" | "were" |
-             "be" | "been" | "being" | "have" | "has" | "had" | "do" |
-             "does" | "did" | "will" | "would" | "could" | "should" |
-             "may" | "might" | "must" | "can" | "to" | "of" | "in" |
-             "for" | "on" | "with" | "at" | "by" | "from" | "as" |
-             "it" | "that" | "this" | "these" | "those" | "and" | "or" |
-             "but" | "if" | "then" | "else" | "when" | "where" | "how")
-}
-
 /// Rank patterns by similarity to query
 #[allow(dead_code)]
 pub fn rank_patterns<T: AsRef<str>>(query: &str, patterns: &[(T, T)]) -> Vec<(usize, f64)> {
@@ -701,6 +556,39 @@ mod tests {
         assert!(score >= 0.35, "Shell query should match shell patterns: {}", score);
     }
 
+    /// Document frequencies for a fixed corpus
+    struct Counts(u64, &'static [(&'static str, u64)]);
+
+    impl TermStats for Counts {
+        fn documents(&self) -> u64 {
+            self.0
+        }
+
+        fn document_frequency(&self, term: &str) -> u64 {
+            self.1.iter().find(|(t, _)| *t == term).map_or(0, |(_, df)| *df)
+        }
+    }
+
+    #[test]
+    fn test_normalization_and_idf() {
+        // Inflections share a term; stop words in any of the languages drop out
+        assert_eq!(terms("Running the tests"), terms("run test"));
+        assert_eq!(terms("Created files"), terms("creating a file"));
+        assert_eq!(terms("die Datei und der Test"), terms("le test de la datei"));
+        assert_eq!(terms("installing dependencies"), vec!["dependency", "install"]);
+
+        // "file" and "run" are in nearly every pattern, "deploy" in one
+        let corpus = Counts(100, &[("file", 90), ("run", 80), ("deploy", 2)]);
+        let scorer = Scorer::new("run deployment file", &corpus);
+        let rare = scorer.score("Task: Write deployment");
+        let common = scorer.score("Task: Run tests on the file");
+        assert!(rare > 0.5 && common < 0.5, "rare {} vs common {}", rare, common);
+
+        // Without statistics the common words would win
+        let uniform = Scorer::new("run deployment file", &Uniform);
+        assert!(uniform.score("Task: Run tests on the file") > uniform.score("Task: Write deployment") * 0.5);
+    }
+
     #[test]
     fn test_rust_edit_no_shell_match() {
         // Rust file query should NOT match shell patterns well
@@ -76,7 +76,7 @@ pub struct SkillStore {
 impl SkillStore {
     /// Open skill store at the given database path
     pub fn open(db_path: &Path) -> Result<Self> {
-        let conn = Connection::open(db_path)?;
+        let conn = super::db::open(db_path)?;
 
         // Check if we need to migrate the existing skills table
         let has_tool_type: bool = conn.query_row(
@@ -121,6 +121,7 @@ impl SkillStore {
             db_path,
             rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        super::encryption::unlock(&conn)?;
         Ok(Self { conn })
     }
 
@@ -238,7 +239,7 @@ impl SkillStore {
 /// Groups similar patterns by tool type and command category,
 /// then creates skills from clusters of similar patterns.
 pub fn consolidate_patterns_to_skills(db_path: &Path) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = super::db::open(db_path)?;
 
     // Get all patterns grouped by tool type and command category
     let mut stmt = conn.prepare(
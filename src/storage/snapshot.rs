@@ -0,0 +1,529 @@
+//! Read-only injection snapshot
+//!
+//! The cold inject path (no daemon) used to open SQLite and run several
+//! queries on every tool call. The snapshot is a compact file holding what
+//! injection needs: the `top_patterns` rankings with their pattern fields,
+//! each pattern's embedding vector when it has one, and the causal edges
+//! between those patterns. The hook memory-maps it and never opens the
+//! database.
+//!
+//! It is rewritten whenever the top-pattern cache is refreshed (after
+//! learning and reflection cycles). The new file is renamed into place, so
+//! hooks mapping the old one keep a consistent view. Like the cache,
+//! changes between refreshes (new imports, deletions) show up at the next
+//! refresh. It also carries the corpus term statistics (see `terms`), so
+//! similarity is weighed the same as against the database.
+//!
+//! Layout, all integers little-endian:
+//!
+//! ```text
+//! header   magic "MANASNP3", dims u32, records u32, vectors u32, edges u32,
+//!          strings u32, terms u32, documents u32, model name (offset u32,
+//!          length u32)
+//! records  per ranking entry: id i64, successes i64, failures i64, rank u32,
+//!          flags u32 (bit 0 risky), vector index u32 (u32::MAX without a
+//!          vector), quality f32, then (offset u32, length u32) for tool
+//!          type, command category and context
+//! edges    pattern a i64, pattern b i64, kind u32 (0 conflict, 1 synergy)
+//! terms    sorted by term: (offset u32, length u32) of the term, df u32
+//! vectors  dims f32 each
+//! strings  UTF-8 blob the offsets point into
+//! ```
+//!
+//! Version 3 added the per-record quality, the ranking score from
+//! `ranking` at refresh time, so the hook orders candidates the same way
+//! as the database without recomputing it. Files with another magic are
+//! ignored and rebuilt at the next refresh.
+
+use anyhow::{bail, Result};
+use memmap2::Mmap;
+use rusqlite::{params, Connection};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::similarity::{Scorer, TermStats};
+use super::{has_column, ranking, terms, Pattern};
+use crate::embeddings::{cosine_similarity, EmbeddingModel};
+use tracing::warn;
+
+/// Snapshot file, relative to the MANA data directory
+pub const SNAPSHOT_FILE: &str = "inject-snapshot.bin";
+
+const MAGIC: &[u8; 8] = b"MANASNP3";
+const HEADER_LEN: usize = 44;
+const RECORD_LEN: usize = 64;
+const EDGE_LEN: usize = 20;
+const TERM_LEN: usize = 12;
+/// Vector index of a pattern without an embedding
+const NO_VECTOR: u32 = u32::MAX;
+const FLAG_RISKY: u32 = 1;
+
+/// Causal edge thresholds, matching `CausalStore`
+const MIN_CO_OCCURRENCES: i64 = 3;
+const CONFLICT_LIFT: f64 = 0.5;
+const SYNERGY_LIFT: f64 = 1.5;
+
+/// Where pattern candidates for injection come from
+///
+/// Implemented by the database (warm caches, daemon-less fallback) and by
+/// the snapshot, so ranking is the same whichever one is used.
+pub trait CandidateSource {
+    /// Best patterns for a tool with their quality (see `ranking`), those in `category` first
+    fn top(&self, tool_type: &str, category: Option<&str>, limit: usize) -> Result<Vec<(Pattern, f64)>>;
+
+    /// Keep the best-ranked causally compatible patterns (see `CausalStore::select_compatible`)
+    fn select_compatible(&self, ranked: &[i64], limit: usize) -> Vec<i64>;
+
+    /// Fallback patterns for tools when nothing matched the query, best first
+    fn fallback(&self, tool_types: &[&str], query: &str, limit: usize) -> Result<Vec<Pattern>>;
+
+    /// `query` prepared for similarity scoring with this source's term statistics
+    fn scorer(&self, query: &str) -> Scorer;
+}
+
+/// A memory-mapped snapshot
+pub struct Snapshot {
+    map: Mmap,
+    dims: usize,
+    records: usize,
+    vectors: usize,
+    edges: usize,
+    terms: usize,
+}
+
+impl Snapshot {
+    /// Map the snapshot in the data directory, if there is a usable one
+    pub fn open(mana_dir: &Path) -> Result<Option<Self>> {
+        let path = mana_dir.join(SNAPSHOT_FILE);
+        let Ok(file) = File::open(&path) else {
+            return Ok(None);
+        };
+        // Safety: snapshots are only ever replaced by rename, never modified in place
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_LEN || &map[..8] != MAGIC {
+            return Ok(None);
+        }
+
+        let snapshot = Self {
+            dims: read_u32(&map, 8) as usize,
+            records: read_u32(&map, 12) as usize,
+            vectors: read_u32(&map, 16) as usize,
+            edges: read_u32(&map, 20) as usize,
+            terms: read_u32(&map, 28) as usize,
+            map,
+        };
+        let expected = snapshot.strings_offset() + read_u32(&snapshot.map, 24) as usize;
+        if snapshot.map.len() != expected {
+            bail!("Injection snapshot {:?} is truncated", path);
+        }
+        Ok(Some(snapshot))
+    }
+
+    /// Number of ranking entries
+    pub fn len(&self) -> usize {
+        self.records
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Embedding model the vectors were made with
+    pub fn model(&self) -> &str {
+        self.string(36)
+    }
+
+    fn edges_offset(&self) -> usize {
+        HEADER_LEN + self.records * RECORD_LEN
+    }
+
+    fn terms_offset(&self) -> usize {
+        self.edges_offset() + self.edges * EDGE_LEN
+    }
+
+    fn vectors_offset(&self) -> usize {
+        self.terms_offset() + self.terms * TERM_LEN
+    }
+
+    fn strings_offset(&self) -> usize {
+        self.vectors_offset() + self.vectors * self.dims * 4
+    }
+
+    /// String whose (offset, length) pair is at `at`
+    fn string(&self, at: usize) -> &str {
+        let start = self.strings_offset() + read_u32(&self.map, at) as usize;
+        let len = read_u32(&self.map, at + 4) as usize;
+        let bytes = self.map.get(start..start + len).unwrap_or_default();
+        std::str::from_utf8(bytes).unwrap_or_default()
+    }
+
+    fn record(&self, index: usize) -> Record<'_> {
+        let at = HEADER_LEN + index * RECORD_LEN;
+        Record {
+            id: read_i64(&self.map, at),
+            success_count: read_i64(&self.map, at + 8),
+            failure_count: read_i64(&self.map, at + 16),
+            risky: read_u32(&self.map, at + 28) & FLAG_RISKY != 0,
+            vector: read_u32(&self.map, at + 32),
+            quality: read_f32(&self.map, at + 36) as f64,
+            tool_type: self.string(at + 40),
+            category: self.string(at + 48),
+            context_query: self.string(at + 56),
+        }
+    }
+
+    fn vector(&self, index: u32) -> Option<Vec<f32>> {
+        if index == NO_VECTOR || index as usize >= self.vectors {
+            return None;
+        }
+        let start = self.vectors_offset() + index as usize * self.dims * 4;
+        Some((0..self.dims).map(|i| read_f32(&self.map, start + i * 4)).collect())
+    }
+
+    /// Patterns linked to `id` by an edge of `kind`
+    fn linked(&self, id: i64, kind: u32) -> Vec<i64> {
+        (0..self.edges)
+            .map(|i| self.edges_offset() + i * EDGE_LEN)
+            .filter(|&at| read_u32(&self.map, at + 16) == kind)
+            .filter_map(|at| {
+                let (a, b) = (read_i64(&self.map, at), read_i64(&self.map, at + 8));
+                (a == id).then_some(b).or((b == id).then_some(a))
+            })
+            .collect()
+    }
+}
+
+impl TermStats for Snapshot {
+    fn documents(&self) -> u64 {
+        read_u32(&self.map, 32) as u64
+    }
+
+    fn document_frequency(&self, term: &str) -> u64 {
+        let at = |i: usize| self.terms_offset() + i * TERM_LEN;
+        let (mut low, mut high) = (0, self.terms);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.string(at(mid)).cmp(term) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return read_u32(&self.map, at(mid) + 8) as u64,
+            }
+        }
+        0
+    }
+}
+
+impl CandidateSource for Snapshot {
+    fn top(&self, tool_type: &str, category: Option<&str>, limit: usize) -> Result<Vec<(Pattern, f64)>> {
+        // Records are stored in rank order within each (tool_type, category), like the cache table
+        let tool: Vec<Record> = (0..self.records).map(|i| self.record(i)).filter(|r| r.tool_type == tool_type).collect();
+        let in_category = tool.iter().filter(|r| category.is_some_and(|c| !c.is_empty() && r.category == c));
+        let tool_wide = tool.iter().filter(|r| r.category.is_empty());
+
+        let mut patterns: Vec<(Pattern, f64)> = Vec::with_capacity(limit);
+        for record in in_category.chain(tool_wide) {
+            if patterns.len() == limit {
+                break;
+            }
+            if !patterns.iter().any(|(p, _)| p.id == record.id) {
+                patterns.push((record.to_pattern(), record.quality));
+            }
+        }
+        Ok(patterns)
+    }
+
+    fn select_compatible(&self, ranked: &[i64], limit: usize) -> Vec<i64> {
+        let Some(&top) = ranked.first() else {
+            return Vec::new();
+        };
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let synergies = self.linked(top, 1);
+        let (companions, rest): (Vec<i64>, Vec<i64>) = ranked[1..].iter().partition(|id| synergies.contains(id));
+
+        let mut selected = vec![top];
+        let mut blocked = self.linked(top, 0);
+        for id in companions.into_iter().chain(rest) {
+            if selected.len() >= limit {
+                break;
+            }
+            if blocked.contains(&id) || selected.contains(&id) {
+                continue;
+            }
+            blocked.extend(self.linked(id, 0));
+            selected.push(id);
+        }
+        selected
+    }
+
+    /// The tools' best patterns, ordered by embedding similarity to the query when vectors exist
+    fn fallback(&self, tool_types: &[&str], query: &str, limit: usize) -> Result<Vec<Pattern>> {
+        let mut candidates: Vec<(Pattern, u32, f64)> = (0..self.records)
+            .map(|i| self.record(i))
+            .filter(|r| r.category.is_empty() && !r.risky && tool_types.contains(&r.tool_type))
+            .map(|r| (r.to_pattern(), r.vector, r.quality))
+            .collect();
+        candidates.sort_by(|a, b| ranking::best_first((a.2, a.0.success_count), (b.2, b.0.success_count)));
+
+        if self.vectors > 0 && !query.is_empty() {
+            let query_vec = EmbeddingModel::new(self.model())?.embed(query)?;
+            if query_vec.len() == self.dims {
+                let similarity = |vector: u32| {
+                    self.vector(vector).map_or(f32::MIN, |v| cosine_similarity(&query_vec, &v))
+                };
+                // Stable, so patterns without a vector stay in quality order behind the rest
+                candidates.sort_by(|a, b| similarity(b.1).total_cmp(&similarity(a.1)));
+            }
+        }
+
+        Ok(candidates.into_iter().take(limit).map(|(p, ..)| p).collect())
+    }
+
+    fn scorer(&self, query: &str) -> Scorer {
+        Scorer::new(query, self)
+    }
+}
+
+/// One ranking entry, borrowing strings from the map
+struct Record<'a> {
+    id: i64,
+    success_count: i64,
+    failure_count: i64,
+    risky: bool,
+    vector: u32,
+    quality: f64,
+    tool_type: &'a str,
+    category: &'a str,
+    context_query: &'a str,
+}
+
+impl Record<'_> {
+    fn to_pattern(&self) -> Pattern {
+        Pattern {
+            id: self.id,
+            pattern_hash: String::new(),
+            tool_type: self.tool_type.to_string(),
+            command_category: (!self.category.is_empty()).then(|| self.category.to_string()),
+            context_query: self.context_query.to_string(),
+            success_count: self.success_count,
+            failure_count: self.failure_count,
+            embedding_id: None,
+            risky: self.risky,
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap_or_default())
+}
+
+fn read_i64(bytes: &[u8], at: usize) -> i64 {
+    i64::from_le_bytes(bytes[at..at + 8].try_into().unwrap_or_default())
+}
+
+fn read_f32(bytes: &[u8], at: usize) -> f32 {
+    f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap_or_default())
+}
+
+/// Appends strings to the blob, returning (offset, length)
+#[derive(Default)]
+struct Strings(Vec<u8>);
+
+impl Strings {
+    fn push(&mut self, s: &str) -> [u32; 2] {
+        let offset = self.0.len() as u32;
+        self.0.extend_from_slice(s.as_bytes());
+        [offset, s.len() as u32]
+    }
+}
+
+/// Refresh the top-pattern cache and term statistics and rewrite the snapshot from them
+///
+/// The embedding index's metadata sidecar is refreshed along with them.
+///
+/// A snapshot that can't be written is removed so the hook reads the
+/// database rather than stale rankings. So is the snapshot of an encrypted
+/// database, which would hold its patterns in the clear.
+pub fn rebuild(mana_dir: &Path, conn: &Connection) -> Result<()> {
+    super::read_only::ensure_writable("Rebuilding the injection snapshot")?;
+    super::top_patterns::refresh(conn)?;
+    terms::update(conn)?;
+    if let Err(e) = crate::embeddings::meta::refresh(mana_dir, conn) {
+        warn!("Failed to write {}: {}", crate::embeddings::meta::META_FILE, e);
+    }
+    if super::encryption::is_encrypted(&mana_dir.join("metadata.sqlite")) {
+        let _ = std::fs::remove_file(mana_dir.join(SNAPSHOT_FILE));
+        return Ok(());
+    }
+    if let Err(e) = write(mana_dir, conn) {
+        warn!("Failed to write injection snapshot: {}", e);
+        let _ = std::fs::remove_file(mana_dir.join(SNAPSHOT_FILE));
+    }
+    Ok(())
+}
+
+/// Write the snapshot from the top-pattern cache, returning the number of entries
+pub fn write(mana_dir: &Path, conn: &Connection) -> Result<usize> {
+    let with_vectors = has_column(conn, "patterns", "embedding");
+    let embedding = if with_vectors { "p.embedding" } else { "NULL" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.id, p.success_count, p.failure_count, t.rank, (p.risky = 1 AND p.approved_at IS NULL),
+                {embedding}, t.tool_type, t.command_category, p.context_query, t.quality
+         FROM top_patterns t JOIN patterns p ON p.id = t.pattern_id
+         ORDER BY t.tool_type, t.command_category, t.rank"
+    ))?;
+    type Row = (i64, i64, i64, u32, bool, Option<Vec<u8>>, String, String, String, f64);
+    let rows: Vec<Row> = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+                row.get(8)?,
+                row.get(9)?,
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    let model: Option<(String, usize)> = conn
+        .query_row("SELECT model_name, dimensions FROM embedding_meta WHERE id = 1", [], |row| {
+            Ok((row.get(0)?, row.get::<_, i64>(1)? as usize))
+        })
+        .ok();
+    let (model_name, dims) = model.unwrap_or_else(|| ("gte-small".to_string(), crate::embeddings::EMBEDDING_DIM));
+
+    // One vector per pattern, shared by every ranking it appears in
+    let mut vector_index: HashMap<i64, u32> = HashMap::new();
+    let mut vectors: Vec<u8> = Vec::new();
+    for (id, .., blob, _, _, _, _) in &rows {
+        if let Some(blob) = blob.as_ref().filter(|b| b.len() == dims * 4) {
+            if !vector_index.contains_key(id) {
+                vector_index.insert(*id, vector_index.len() as u32);
+                vectors.extend_from_slice(blob);
+            }
+        }
+    }
+
+    let ids: HashSet<i64> = rows.iter().map(|r| r.0).collect();
+    let mut edges: Vec<(i64, i64, u32)> = Vec::new();
+    if let Ok(mut stmt) = conn.prepare(
+        "SELECT pattern_a_id, pattern_b_id, lift < ?1 FROM causal_edges
+         WHERE co_occurrences >= ?2 AND (lift < ?1 OR lift > ?3)",
+    ) {
+        let found = stmt.query_map(params![CONFLICT_LIFT, MIN_CO_OCCURRENCES, SYNERGY_LIFT], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?))
+        })?;
+        for (a, b, conflict) in found.flatten() {
+            if ids.contains(&a) && ids.contains(&b) {
+                edges.push((a, b, if conflict { 0 } else { 1 }));
+            }
+        }
+    }
+
+    // Sorted by the database's BINARY collation, which is byte order like str's Ord
+    let term_df = terms::all(conn)?;
+    let documents = terms::documents(conn) as u32;
+
+    let mut strings = Strings::default();
+    let model_ref = strings.push(&model_name);
+    let tmp = mana_dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+    {
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        let mut records = Vec::with_capacity(rows.len() * RECORD_LEN);
+        for (id, success, failure, rank, risky, _, tool_type, category, context, quality) in &rows {
+            records.extend_from_slice(&id.to_le_bytes());
+            records.extend_from_slice(&success.to_le_bytes());
+            records.extend_from_slice(&failure.to_le_bytes());
+            records.extend_from_slice(&rank.to_le_bytes());
+            records.extend_from_slice(&(if *risky { FLAG_RISKY } else { 0 }).to_le_bytes());
+            records.extend_from_slice(&vector_index.get(id).copied().unwrap_or(NO_VECTOR).to_le_bytes());
+            records.extend_from_slice(&(*quality as f32).to_le_bytes());
+            for [offset, len] in [strings.push(tool_type), strings.push(category), strings.push(context)] {
+                records.extend_from_slice(&offset.to_le_bytes());
+                records.extend_from_slice(&len.to_le_bytes());
+            }
+        }
+
+        let mut term_entries = Vec::with_capacity(term_df.len() * TERM_LEN);
+        for (term, df) in &term_df {
+            let [offset, len] = strings.push(term);
+            term_entries.extend_from_slice(&offset.to_le_bytes());
+            term_entries.extend_from_slice(&len.to_le_bytes());
+            term_entries.extend_from_slice(&df.to_le_bytes());
+        }
+
+        out.write_all(MAGIC)?;
+        for value in [dims as u32, rows.len() as u32, vector_index.len() as u32, edges.len() as u32] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        for value in [strings.0.len() as u32, term_df.len() as u32, documents] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&model_ref[0].to_le_bytes())?;
+        out.write_all(&model_ref[1].to_le_bytes())?;
+        out.write_all(&records)?;
+        for (a, b, kind) in &edges {
+            out.write_all(&a.to_le_bytes())?;
+            out.write_all(&b.to_le_bytes())?;
+            out.write_all(&kind.to_le_bytes())?;
+        }
+        out.write_all(&term_entries)?;
+        out.write_all(&vectors)?;
+        out.write_all(&strings.0)?;
+        out.flush()?;
+    }
+    std::fs::rename(&tmp, mana_dir.join(SNAPSHOT_FILE))?;
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_matches_cache() {
+        let temp = TempDir::new().unwrap();
+        let conn = Connection::open(temp.path().join("metadata.sqlite")).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'cargo', 'cargo build', 2, 0),
+                    (2, 'b', 'Bash', 'npm', 'npm test', 9, 0),
+                    (3, 'c', 'Bash', 'cargo', 'cargo test ✓', 5, 1);
+             INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (3, 1, 0.2, 4);",
+        )
+        .unwrap();
+        crate::storage::top_patterns::refresh(&conn).unwrap();
+        terms::update(&conn).unwrap();
+        assert!(Snapshot::open(temp.path()).unwrap().is_none());
+
+        assert_eq!(write(temp.path(), &conn).unwrap(), 6);
+        let snapshot = Snapshot::open(temp.path()).unwrap().unwrap();
+        assert_eq!(snapshot.model(), "gte-small");
+        assert_eq!(snapshot.documents(), 3);
+        assert_eq!(snapshot.document_frequency("cargo"), 2);
+        assert_eq!(snapshot.document_frequency("test"), 2);
+        assert_eq!(snapshot.document_frequency("yarn"), 0);
+
+        let from_snapshot = snapshot.top("Bash", Some("cargo"), 3).unwrap();
+        let from_cache = crate::storage::top_patterns::lookup(&conn, "Bash", Some("cargo"), 3).unwrap();
+        let ranked = |patterns: &[(Pattern, f64)]| patterns.iter().map(|(p, q)| (p.id, *q as f32)).collect::<Vec<_>>();
+        assert_eq!(ranked(&from_snapshot), ranked(&from_cache));
+        assert_eq!(from_snapshot[0].0.context_query, "cargo test ✓");
+        assert_eq!(from_snapshot[0].0.command_category.as_deref(), Some("cargo"));
+
+        assert_eq!(snapshot.select_compatible(&[3, 1, 2], 3), vec![3, 2]);
+        let ids = |patterns: Vec<Pattern>| patterns.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(snapshot.fallback(&["Bash"], "", 2).unwrap()), vec![2, 3]);
+    }
+}
@@ -0,0 +1,427 @@
+//! Statistics snapshots
+//!
+//! Collects the numbers behind `mana stats` into plain structs so they can
+//! be rendered as text or serialized as JSON.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Everything `mana stats` reports
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatsReport {
+    pub patterns: PatternSummary,
+    /// Patterns and outcomes per language, from their command category
+    pub by_language: Vec<Breakdown>,
+    /// Patterns and outcomes per project they were learned in, most patterns first
+    pub by_project: Vec<Breakdown>,
+    /// Most recent learning events, newest first
+    pub recent_events: Vec<LearningEvent>,
+    pub causal: CausalSummary,
+    pub skills: SkillSummary,
+    pub budget: BudgetSummary,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PatternSummary {
+    pub total: i64,
+    pub by_tool: Vec<ToolCount>,
+    pub successes: i64,
+    pub failures: i64,
+    /// Percentage of successful uses, if any uses were recorded
+    pub success_rate: Option<f64>,
+    /// Outcomes recorded in each of the [`TREND_WINDOWS`]
+    pub trend: Vec<WindowRate>,
+}
+
+/// Patterns and outcomes for one language or project
+#[derive(Debug, Clone, Serialize)]
+pub struct Breakdown {
+    pub key: String,
+    pub patterns: i64,
+    pub successes: i64,
+    pub failures: i64,
+    pub success_rate: Option<f64>,
+    pub trend: Vec<WindowRate>,
+    /// Fewer than [`THIN_USES`] uses: too little data to judge
+    pub thin: bool,
+}
+
+/// Outcomes recorded in the last `days` days
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WindowRate {
+    pub days: i64,
+    pub successes: i64,
+    pub failures: i64,
+    pub success_rate: Option<f64>,
+}
+
+impl WindowRate {
+    /// "82% of 41" style summary, or "-" without uses
+    pub fn label(&self) -> String {
+        match self.success_rate {
+            Some(rate) => format!("{:.0}% of {}", rate, self.successes + self.failures),
+            None => "-".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCount {
+    pub tool: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LearningEvent {
+    pub timestamp: String,
+    pub event_type: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CausalSummary {
+    pub total_edges: i64,
+    /// Edges with lift > 1.5 and at least 3 co-occurrences
+    pub synergies: i64,
+    /// Edges with lift < 0.5 and at least 3 co-occurrences
+    pub conflicts: i64,
+    pub uncertain: i64,
+    pub avg_co_occurrences: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SkillSummary {
+    pub total: i64,
+    pub by_tool: Vec<SkillToolCount>,
+    pub avg_success_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillToolCount {
+    pub tool: String,
+    pub skills: i64,
+    pub patterns: i64,
+}
+
+/// Injections that ran out of latency budget in the last week
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BudgetSummary {
+    pub overruns: i64,
+    /// Overruns per search stage, most frequent first
+    pub by_stage: Vec<StageCount>,
+    pub max_elapsed_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageCount {
+    pub stage: String,
+    pub count: i64,
+}
+
+/// How far back `mana stats` counts budget overruns
+const OVERRUN_WINDOW_DAYS: i64 = 7;
+
+/// Windows (days) success-rate trends are reported over
+pub const TREND_WINDOWS: [i64; 3] = [7, 30, 90];
+
+/// Uses below which a breakdown is flagged as thin
+pub const THIN_USES: i64 = 10;
+
+/// Language a command category belongs to
+pub fn language(category: Option<&str>) -> &'static str {
+    match category.unwrap_or_default() {
+        "rs" | "cargo" => "rust",
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" | "npm" | "json" => "javascript",
+        "py" | "python" => "python",
+        "go" => "go",
+        "rb" => "ruby",
+        "java" | "kt" | "gradle" => "java",
+        "c" | "h" | "cc" | "cpp" | "hpp" | "make" => "c/c++",
+        "sh" | "bash" | "shell" => "shell",
+        "md" => "markdown",
+        "" => "unknown",
+        _ => "other",
+    }
+}
+
+fn rate(successes: i64, failures: i64) -> Option<f64> {
+    let uses = successes + failures;
+    (uses > 0).then(|| successes as f64 / uses as f64 * 100.0)
+}
+
+/// (successes, failures) gained within each of the [`TREND_WINDOWS`]
+type Windows = [(i64, i64); TREND_WINDOWS.len()];
+
+/// A pattern's counts, total and gained within each trend window
+struct PatternCounts {
+    language: &'static str,
+    successes: i64,
+    failures: i64,
+    windows: Windows,
+}
+
+/// Every pattern's counts with their gains per window
+///
+/// Counts are cumulative, so a window's outcomes are the current counts
+/// minus those at its start: the counts `pattern_history` saved when they
+/// were first changed after it. Count changes are saved at most once a day
+/// and only so many are kept, so a busy pattern's windows can come out short.
+fn pattern_counts(conn: &Connection) -> Result<(Vec<PatternCounts>, HashMap<i64, usize>)> {
+    let now = chrono::Utc::now();
+    // The same format as CURRENT_TIMESTAMP, so the columns compare as text
+    let starts: Vec<String> = TREND_WINDOWS
+        .iter()
+        .map(|days| (now - chrono::Duration::days(*days)).format("%Y-%m-%d %H:%M:%S").to_string())
+        .collect();
+    let at_start = |column: &str, param: usize| {
+        format!(
+            "CASE WHEN p.created_at > ?{param} THEN 0 ELSE COALESCE(
+                (SELECT h.{column} FROM pattern_history h WHERE h.pattern_id = p.id AND h.created_at > ?{param}
+                 ORDER BY h.version LIMIT 1), p.{column}) END"
+        )
+    };
+    let columns: Vec<String> = (1..=TREND_WINDOWS.len())
+        .flat_map(|param| [at_start("success_count", param), at_start("failure_count", param)])
+        .collect();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT p.id, p.command_category, COALESCE(p.success_count, 0), COALESCE(p.failure_count, 0), {}
+         FROM patterns p",
+        columns.join(", ")
+    ))?;
+
+    let mut index = HashMap::new();
+    let mut counts = Vec::new();
+    let mut rows = stmt.query(rusqlite::params_from_iter(&starts))?;
+    while let Some(row) = rows.next()? {
+        let category: Option<String> = row.get(1)?;
+        let (successes, failures): (i64, i64) = (row.get(2)?, row.get(3)?);
+        let mut windows: Windows = Default::default();
+        for (i, window) in windows.iter_mut().enumerate() {
+            let (s, f): (Option<i64>, Option<i64>) = (row.get(4 + i * 2)?, row.get(5 + i * 2)?);
+            *window = ((successes - s.unwrap_or(0)).max(0), (failures - f.unwrap_or(0)).max(0));
+        }
+        index.insert(row.get::<_, i64>(0)?, counts.len());
+        counts.push(PatternCounts { language: language(category.as_deref()), successes, failures, windows });
+    }
+    Ok((counts, index))
+}
+
+/// Sum pattern counts into one breakdown per key, most patterns first
+fn group<'a>(groups: impl IntoIterator<Item = (String, &'a PatternCounts)>) -> Vec<Breakdown> {
+    let mut by_key: HashMap<String, (i64, i64, i64, Windows)> = HashMap::new();
+    for (key, counts) in groups {
+        let entry = by_key.entry(key).or_default();
+        entry.0 += 1;
+        entry.1 += counts.successes;
+        entry.2 += counts.failures;
+        for (sum, window) in entry.3.iter_mut().zip(&counts.windows) {
+            sum.0 += window.0;
+            sum.1 += window.1;
+        }
+    }
+    let mut breakdowns: Vec<Breakdown> = by_key
+        .into_iter()
+        .map(|(key, (patterns, successes, failures, windows))| Breakdown {
+            key,
+            patterns,
+            successes,
+            failures,
+            success_rate: rate(successes, failures),
+            trend: trend(&windows),
+            thin: successes + failures < THIN_USES,
+        })
+        .collect();
+    breakdowns.sort_by(|a, b| b.patterns.cmp(&a.patterns).then_with(|| a.key.cmp(&b.key)));
+    breakdowns
+}
+
+fn trend(windows: &[(i64, i64)]) -> Vec<WindowRate> {
+    TREND_WINDOWS
+        .iter()
+        .zip(windows)
+        .map(|(&days, &(successes, failures))| WindowRate {
+            days,
+            successes,
+            failures,
+            success_rate: rate(successes, failures),
+        })
+        .collect()
+}
+
+/// Overall trend plus breakdowns by language and by project
+pub fn breakdowns(conn: &Connection) -> Result<(Vec<WindowRate>, Vec<Breakdown>, Vec<Breakdown>)> {
+    let (counts, index) = pattern_counts(conn)?;
+
+    let mut overall: Windows = Default::default();
+    for counts in &counts {
+        for (sum, window) in overall.iter_mut().zip(&counts.windows) {
+            sum.0 += window.0;
+            sum.1 += window.1;
+        }
+    }
+    let by_language = group(counts.iter().map(|c| (c.language.to_string(), c)));
+
+    let mut stmt = conn.prepare("SELECT pattern_id, project_hash FROM pattern_projects")?;
+    let links: Vec<(i64, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.flatten().collect();
+    let by_project = group(links.into_iter().filter_map(|(id, project)| Some((project, &counts[*index.get(&id)?]))));
+
+    Ok((trend(&overall), by_language, by_project))
+}
+
+/// Gather statistics from an open database
+pub fn collect_stats(conn: &Connection, recent_events: usize) -> Result<StatsReport> {
+    let count = |sql: &str| conn.query_row(sql, [], |row| row.get::<_, i64>(0)).unwrap_or(0);
+
+    let mut patterns = PatternSummary { total: count("SELECT COUNT(*) FROM patterns"), ..Default::default() };
+    let mut stmt =
+        conn.prepare("SELECT tool_type, COUNT(*) FROM patterns GROUP BY tool_type ORDER BY COUNT(*) DESC")?;
+    patterns.by_tool = stmt
+        .query_map([], |row| Ok(ToolCount { tool: row.get(0)?, count: row.get(1)? }))?
+        .flatten()
+        .collect();
+
+    (patterns.successes, patterns.failures) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(success_count), 0), COALESCE(SUM(failure_count), 0) FROM patterns",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, 0));
+    patterns.success_rate = rate(patterns.successes, patterns.failures);
+    let (trend, by_language, by_project) = breakdowns(conn)?;
+    patterns.trend = trend;
+
+    let mut stmt = conn.prepare("SELECT timestamp, event_type FROM learning_log ORDER BY timestamp DESC LIMIT ?1")?;
+    let recent_events = stmt
+        .query_map([recent_events as i64], |row| {
+            Ok(LearningEvent { timestamp: row.get(0)?, event_type: row.get(1)? })
+        })?
+        .flatten()
+        .collect();
+
+    let mut causal = CausalSummary { total_edges: count("SELECT COUNT(*) FROM causal_edges"), ..Default::default() };
+    if causal.total_edges > 0 {
+        causal.synergies = count("SELECT COUNT(*) FROM causal_edges WHERE lift > 1.5 AND co_occurrences >= 3");
+        causal.conflicts = count("SELECT COUNT(*) FROM causal_edges WHERE lift < 0.5 AND co_occurrences >= 3");
+        causal.uncertain = causal.total_edges - causal.synergies - causal.conflicts;
+        causal.avg_co_occurrences = conn
+            .query_row("SELECT AVG(co_occurrences) FROM causal_edges", [], |row| row.get(0))
+            .unwrap_or(0.0);
+    }
+
+    let mut skills = SkillSummary { total: count("SELECT COUNT(*) FROM skills"), ..Default::default() };
+    if skills.total > 0 {
+        let mut stmt = conn.prepare(
+            "SELECT tool_type, COUNT(*), SUM(pattern_count) FROM skills GROUP BY tool_type ORDER BY COUNT(*) DESC",
+        )?;
+        skills.by_tool = stmt
+            .query_map([], |row| Ok(SkillToolCount { tool: row.get(0)?, skills: row.get(1)?, patterns: row.get(2)? }))?
+            .flatten()
+            .collect();
+        skills.avg_success_rate = conn
+            .query_row(
+                "SELECT AVG(CAST(total_success AS REAL) / NULLIF(total_success + total_failure, 0) * 100) FROM skills",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap_or(0.0);
+    }
+
+    let since = (chrono::Utc::now() - chrono::Duration::days(OVERRUN_WINDOW_DAYS)).to_rfc3339();
+    let mut budget = BudgetSummary::default();
+    (budget.overruns, budget.max_elapsed_ms) = conn
+        .query_row(
+            "SELECT COUNT(*), COALESCE(MAX(elapsed_us), 0) / 1000.0 FROM budget_overruns WHERE created_at >= ?1",
+            [&since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, 0.0));
+    if budget.overruns > 0 {
+        let mut stmt = conn.prepare(
+            "SELECT stage, COUNT(*) FROM budget_overruns WHERE created_at >= ?1
+             GROUP BY stage ORDER BY COUNT(*) DESC",
+        )?;
+        budget.by_stage = stmt
+            .query_map([&since], |row| Ok(StageCount { stage: row.get(0)?, count: row.get(1)? }))?
+            .flatten()
+            .collect();
+    }
+
+    Ok(StatsReport { patterns, by_language, by_project, recent_events, causal, skills, budget })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::injection_log::BudgetOverrun;
+
+    #[test]
+    fn test_collect_stats_counts_patterns() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (pattern_hash, tool_type, context_query, success_count, failure_count)
+             VALUES ('a', 'Bash', 'cargo build', 3, 1), ('b', 'Bash', 'cargo test', 0, 0), ('c', 'Edit', 'fix', 0, 0);
+             INSERT INTO learning_log (event_type) VALUES ('learn');",
+        )
+        .unwrap();
+        let late = std::time::Duration::from_millis(12);
+        let mut overruns = ["fetch", "similarity", "similarity"].map(|stage| BudgetOverrun::new("Bash", stage, late, 10));
+        overruns[0].timestamp -= chrono::Duration::days(30);
+        crate::storage::injection_log::insert_overruns(&mut conn, &overruns).unwrap();
+
+        let report = collect_stats(&conn, 5).unwrap();
+        assert_eq!(report.patterns.total, 3);
+        assert_eq!(report.patterns.by_tool[0].tool, "Bash");
+        assert_eq!(report.patterns.by_tool[0].count, 2);
+        assert_eq!(report.patterns.success_rate, Some(75.0));
+        assert_eq!(report.recent_events.len(), 1);
+        assert_eq!(report.causal.total_edges, 0);
+        assert_eq!(report.budget.overruns, 2);
+        assert_eq!(report.budget.by_stage[0].stage, "similarity");
+        assert_eq!(report.budget.max_elapsed_ms, 12.0);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["patterns"]["total"], 3);
+    }
+
+    #[test]
+    fn test_breakdowns_and_trends() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        let old = (chrono::Utc::now() - chrono::Duration::days(200)).format("%Y-%m-%d %H:%M:%S").to_string();
+        let recent = (chrono::Utc::now() - chrono::Duration::days(20)).format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, created_at)
+             VALUES (1, 'a', 'Bash', 'cargo', 'cargo build', 2, 0, ?1), (2, 'b', 'Edit', 'rs', 'fix main.rs', 1, 1, ?1),
+                    (3, 'c', 'Bash', 'npm', 'npm test', 4, 0, ?1)",
+            [&old],
+        )
+        .unwrap();
+        // Pattern 3 had 1 success 20 days ago and gained the rest since; pattern 1 is untouched for 200 days
+        conn.execute_batch("UPDATE patterns SET success_count = 5 WHERE id = 3").unwrap();
+        conn.execute(
+            "UPDATE pattern_history SET success_count = 1, created_at = ?1 WHERE pattern_id = 3",
+            [&recent],
+        )
+        .unwrap();
+        conn.execute_batch(
+            "INSERT INTO pattern_projects (pattern_id, project_hash) VALUES (1, 'p1'), (2, 'p1'), (3, 'p2');",
+        )
+        .unwrap();
+
+        let report = collect_stats(&conn, 5).unwrap();
+        let windows: Vec<(i64, i64)> = report.patterns.trend.iter().map(|w| (w.days, w.successes)).collect();
+        assert_eq!(windows, vec![(7, 0), (30, 4), (90, 4)]);
+
+        assert_eq!(report.by_language[0].key, "rust");
+        assert_eq!(report.by_language[0].patterns, 2);
+        assert_eq!(report.by_language[0].success_rate, Some(75.0));
+        assert!(report.by_language[0].thin);
+        let js = report.by_language.iter().find(|b| b.key == "javascript").unwrap();
+        assert_eq!(js.trend[1].success_rate, Some(100.0));
+
+        assert_eq!(report.by_project.iter().map(|b| (b.key.as_str(), b.patterns)).collect::<Vec<_>>(), vec![("p1", 2), ("p2", 1)]);
+    }
+}
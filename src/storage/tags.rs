@@ -0,0 +1,99 @@
+//! Pattern tags
+//!
+//! Free-form labels (`team`, `ci`, `onboarding`, ...) attached with
+//! `mana patterns tag` and used to pick subsets for listing and export.
+
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+
+/// Create the pattern_tags table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS pattern_tags (
+            pattern_id INTEGER NOT NULL,
+            tag TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (pattern_id, tag),
+            FOREIGN KEY (pattern_id) REFERENCES patterns(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_pattern_tags_tag ON pattern_tags(tag);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Lowercase a tag and check it only uses letters, digits, '-', '_', ':' or '/'
+pub fn normalize(tag: &str) -> Result<String> {
+    let tag = tag.trim().to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.len() <= 64
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '/'));
+    if !valid {
+        bail!("Invalid tag {:?}: use up to 64 letters, digits, '-', '_', ':' or '/'", tag);
+    }
+    Ok(tag)
+}
+
+/// Attach tags to a pattern, returning how many were new
+pub fn add(conn: &Connection, pattern_id: i64, tags: &[String]) -> Result<usize> {
+    let exists: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM patterns WHERE id = ?1)", params![pattern_id], |row| row.get(0))?;
+    if !exists {
+        bail!("Pattern {} not found", pattern_id);
+    }
+
+    let mut added = 0;
+    for tag in tags {
+        added += conn.execute(
+            "INSERT OR IGNORE INTO pattern_tags (pattern_id, tag) VALUES (?1, ?2)",
+            params![pattern_id, normalize(tag)?],
+        )?;
+    }
+    Ok(added)
+}
+
+/// Detach tags from a pattern, returning how many were removed
+pub fn remove(conn: &Connection, pattern_id: i64, tags: &[String]) -> Result<usize> {
+    let mut removed = 0;
+    for tag in tags {
+        removed += conn.execute(
+            "DELETE FROM pattern_tags WHERE pattern_id = ?1 AND tag = ?2",
+            params![pattern_id, normalize(tag)?],
+        )?;
+    }
+    Ok(removed)
+}
+
+/// Tags on a pattern, sorted
+pub fn for_pattern(conn: &Connection, pattern_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT tag FROM pattern_tags WHERE pattern_id = ?1 ORDER BY tag")?;
+    let tags = stmt.query_map(params![pattern_id], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_remove_tags() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (1, 'h', 'Bash', 'cargo build')",
+            [],
+        )
+        .unwrap();
+
+        let tags = vec!["CI".to_string(), "team".to_string()];
+        assert_eq!(add(&conn, 1, &tags).unwrap(), 2);
+        assert_eq!(add(&conn, 1, &tags).unwrap(), 0);
+        assert_eq!(for_pattern(&conn, 1).unwrap(), vec!["ci", "team"]);
+        assert!(add(&conn, 2, &tags).is_err());
+        assert!(add(&conn, 1, &["no spaces".to_string()]).is_err());
+
+        assert_eq!(remove(&conn, 1, &["ci".to_string()]).unwrap(), 1);
+        assert_eq!(for_pattern(&conn, 1).unwrap(), vec!["team"]);
+    }
+}
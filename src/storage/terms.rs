@@ -0,0 +1,170 @@
+//! Corpus statistics for similarity scoring
+//!
+//! Similarity weighs query terms by inverse document frequency, so a word
+//! found in most patterns ("file", "run") counts for little and a rare one
+//! for a lot. `term_df` holds the number of patterns each normalized term
+//! (see [`similarity::terms`]) appears in; `pattern_terms` records which
+//! patterns have been counted and with which terms, so the counts can be
+//! taken back out. The empty term is counted once per pattern, which makes
+//! its df the corpus size.
+//!
+//! Counting is incremental: [`update`] adds patterns that aren't counted
+//! yet and runs with every snapshot rebuild. Triggers uncount a pattern
+//! when it's deleted or its context changes, so an edited pattern is simply
+//! counted again at the next update.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::similarity::{self, TermStats};
+
+/// Create the term statistics tables and the triggers that keep them honest
+pub fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS term_df (
+            term TEXT PRIMARY KEY,
+            df INTEGER NOT NULL
+        ) WITHOUT ROWID;
+
+        CREATE TABLE IF NOT EXISTS pattern_terms (
+            pattern_id INTEGER NOT NULL,
+            term TEXT NOT NULL,
+            PRIMARY KEY (pattern_id, term)
+        ) WITHOUT ROWID;
+
+        CREATE TRIGGER IF NOT EXISTS pattern_terms_on_delete AFTER DELETE ON patterns
+        BEGIN
+            UPDATE term_df SET df = df - 1
+            WHERE term IN (SELECT term FROM pattern_terms WHERE pattern_id = old.id);
+            DELETE FROM pattern_terms WHERE pattern_id = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS pattern_terms_on_update AFTER UPDATE OF context_query ON patterns
+        WHEN old.context_query IS NOT new.context_query
+        BEGIN
+            UPDATE term_df SET df = df - 1
+            WHERE term IN (SELECT term FROM pattern_terms WHERE pattern_id = old.id);
+            DELETE FROM pattern_terms WHERE pattern_id = old.id;
+        END;
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Count patterns that aren't counted yet, returning how many were added
+pub fn update(conn: &Connection) -> Result<usize> {
+    let pending: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, context_query FROM patterns p
+             WHERE NOT EXISTS (SELECT 1 FROM pattern_terms t WHERE t.pattern_id = p.id AND t.term = '')",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_, _>>()?
+    };
+
+    // A savepoint rather than a transaction so this also runs inside migrations
+    conn.execute_batch("SAVEPOINT term_stats_update;")?;
+    let result = (|| -> Result<()> {
+        let mut mark = conn.prepare_cached("INSERT OR IGNORE INTO pattern_terms (pattern_id, term) VALUES (?1, ?2)")?;
+        let mut count = conn.prepare_cached(
+            "INSERT INTO term_df (term, df) VALUES (?1, 1) ON CONFLICT(term) DO UPDATE SET df = df + 1",
+        )?;
+        for (id, context) in &pending {
+            for term in similarity::terms(context).iter().map(String::as_str).chain([""]) {
+                if mark.execute(params![id, term])? == 1 {
+                    count.execute(params![term])?;
+                }
+            }
+        }
+        conn.execute("DELETE FROM term_df WHERE df <= 0", [])?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => {
+            conn.execute_batch("RELEASE term_stats_update;")?;
+            Ok(pending.len())
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO term_stats_update; RELEASE term_stats_update;")?;
+            Err(e)
+        }
+    }
+}
+
+/// Every counted term with its document frequency, sorted by term
+pub fn all(conn: &Connection) -> Result<Vec<(String, u32)>> {
+    let mut stmt = conn.prepare("SELECT term, df FROM term_df WHERE df > 0 AND term != '' ORDER BY term")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Patterns counted so far (0 if the tables don't exist yet)
+pub fn documents(conn: &Connection) -> u64 {
+    conn.query_row("SELECT df FROM term_df WHERE term = ''", [], |row| row.get::<_, i64>(0))
+        .optional()
+        .ok()
+        .flatten()
+        .map_or(0, |df| df.max(0) as u64)
+}
+
+/// Term statistics read from the database as they're needed
+pub struct Corpus<'a> {
+    conn: &'a Connection,
+    documents: u64,
+}
+
+impl<'a> Corpus<'a> {
+    pub fn new(conn: &'a Connection) -> Self {
+        Self { conn, documents: documents(conn) }
+    }
+}
+
+impl TermStats for Corpus<'_> {
+    fn documents(&self) -> u64 {
+        self.documents
+    }
+
+    fn document_frequency(&self, term: &str) -> u64 {
+        self.conn
+            .prepare_cached("SELECT df FROM term_df WHERE term = ?1")
+            .and_then(|mut stmt| stmt.query_row(params![term], |row| row.get::<_, i64>(0)).optional())
+            .ok()
+            .flatten()
+            .map_or(0, |df| df.max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_follow_patterns() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query)
+             VALUES (1, 'a', 'Bash', 'Running cargo build'),
+                    (2, 'b', 'Bash', 'running npm test');",
+        )
+        .unwrap();
+
+        assert_eq!(update(&conn).unwrap(), 2);
+        assert_eq!(update(&conn).unwrap(), 0);
+        let corpus = Corpus::new(&conn);
+        assert_eq!(corpus.documents(), 2);
+        assert_eq!(corpus.document_frequency("run"), 2);
+        assert_eq!(corpus.document_frequency("cargo"), 1);
+
+        // Edits are counted again at the next update, deletions are gone at once
+        conn.execute("UPDATE patterns SET context_query = 'npm install' WHERE id = 1", []).unwrap();
+        conn.execute("DELETE FROM patterns WHERE id = 2", []).unwrap();
+        assert_eq!(Corpus::new(&conn).documents(), 0);
+        assert_eq!(update(&conn).unwrap(), 1);
+        let corpus = Corpus::new(&conn);
+        assert_eq!(corpus.documents(), 1);
+        assert_eq!(corpus.document_frequency("cargo"), 0);
+        assert_eq!(all(&conn).unwrap(), vec![("install".to_string(), 1), ("npm".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Precomputed top patterns per tool
+//!
+//! Most injections for a tool end up with the same few best-scored
+//! patterns, but finding them means scoring every pattern of that tool. The
+//! `top_patterns` table keeps the ranking ready: the best [`PER_KEY`]
+//! pattern ids for each (tool_type, command_category), plus a per-tool list
+//! under the empty category, with their quality (see `ranking`). Injection
+//! reads candidates with one primary-key lookup.
+//!
+//! The table is rebuilt after learning and reflection cycles, together with
+//! the injection snapshot (see `snapshot`). Counts and approval are read
+//! from `patterns` at lookup time, so only the ranking and quality can go
+//! stale between refreshes, and deleted patterns simply drop out.
+//!
+//! Only active patterns are ranked; quarantined and archived ones are never
+//! offered for injection.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+use super::ranking::{self, Ranker};
+use super::Pattern;
+
+/// Patterns kept per (tool_type, command_category)
+pub const PER_KEY: usize = 20;
+
+/// (id, successes, quality) per (tool_type, command_category)
+type Rankings = HashMap<(String, String), Vec<(i64, i64, f64)>>;
+
+/// Create the top_patterns table
+///
+/// Filled by the `top_pattern_quality` migration, which adds the quality
+/// column after the `pattern_status` one the ranking filters on.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        -- command_category '' holds the ranking across all categories of a tool
+        CREATE TABLE IF NOT EXISTS top_patterns (
+            tool_type TEXT NOT NULL,
+            command_category TEXT NOT NULL,
+            rank INTEGER NOT NULL,
+            pattern_id INTEGER NOT NULL,
+            quality REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (tool_type, command_category, rank)
+        ) WITHOUT ROWID;
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Rebuild the cache from current pattern quality, returning the rows written
+pub fn refresh(conn: &Connection) -> Result<usize> {
+    let ranker = Ranker::configured(conn)?;
+    // A savepoint rather than a transaction so this also runs inside migrations
+    conn.execute_batch("SAVEPOINT top_patterns_refresh; DELETE FROM top_patterns;")?;
+    match rank_into_cache(conn, &ranker) {
+        Ok(rows) => {
+            conn.execute_batch("RELEASE top_patterns_refresh;")?;
+            Ok(rows)
+        }
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO top_patterns_refresh; RELEASE top_patterns_refresh;")?;
+            Err(e)
+        }
+    }
+}
+
+fn rank_into_cache(conn: &Connection, ranker: &Ranker) -> Result<usize> {
+    let mut rankings: Rankings = HashMap::new();
+    {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, tool_type, COALESCE(command_category, ''), success_count, failure_count, {}
+             FROM patterns WHERE status = 'active'",
+            ranking::AGE_DAYS
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, Option<f64>>(5)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, tool_type, category, success, failure, age) = row?;
+            let quality = ranker.quality(id, &tool_type, Some(category.as_str()).filter(|c| !c.is_empty()), success, failure, age);
+            if !category.is_empty() {
+                rankings.entry((tool_type.clone(), category)).or_default().push((id, success, quality));
+            }
+            rankings.entry((tool_type, String::new())).or_default().push((id, success, quality));
+        }
+    }
+
+    let mut insert = conn.prepare(
+        "INSERT INTO top_patterns (tool_type, command_category, rank, pattern_id, quality) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut written = 0;
+    for ((tool_type, category), mut ranked) in rankings {
+        ranked.sort_by(|a, b| ranking::best_first((a.2, a.1), (b.2, b.1)).then(a.0.cmp(&b.0)));
+        for (rank, (id, _, quality)) in ranked.into_iter().take(PER_KEY).enumerate() {
+            written += insert.execute(params![tool_type, category, rank as i64 + 1, id, quality])?;
+        }
+    }
+    Ok(written)
+}
+
+/// Best patterns for a tool with their quality, those in `category` first
+///
+/// Tops the category's list up from the tool-wide one. Returns nothing when
+/// the tool has no cached ranking or `limit` is more than the cache holds,
+/// so callers fall back to ranking patterns themselves.
+pub fn lookup(conn: &Connection, tool_type: &str, category: Option<&str>, limit: usize) -> Result<Vec<(Pattern, f64)>> {
+    if limit > PER_KEY {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn.prepare_cached(
+        r#"
+        SELECT p.id, p.pattern_hash, p.tool_type, p.command_category, p.context_query,
+               p.success_count, p.failure_count, p.embedding_id, (p.risky = 1 AND p.approved_at IS NULL), t.quality
+        FROM top_patterns t JOIN patterns p ON p.id = t.pattern_id
+        WHERE t.tool_type = ?1 AND t.command_category IN (?2, '') AND p.status = 'active'
+        ORDER BY t.command_category = '', t.rank
+        "#,
+    )?;
+    let rows = stmt.query_map(params![tool_type, category.unwrap_or_default()], |row| {
+        Ok((
+            Pattern {
+                id: row.get(0)?,
+                pattern_hash: row.get(1)?,
+                tool_type: row.get(2)?,
+                command_category: row.get(3)?,
+                context_query: row.get(4)?,
+                success_count: row.get(5)?,
+                failure_count: row.get(6)?,
+                embedding_id: row.get(7)?,
+                risky: row.get(8)?,
+            },
+            row.get(9)?,
+        ))
+    })?;
+
+    let mut patterns: Vec<(Pattern, f64)> = Vec::with_capacity(limit);
+    for pattern in rows {
+        let pattern = pattern?;
+        if !patterns.iter().any(|(p, _)| p.id == pattern.0.id) {
+            patterns.push(pattern);
+        }
+        if patterns.len() == limit {
+            break;
+        }
+    }
+    Ok(patterns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_prefers_category_then_tool() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count)
+             VALUES (1, 'a', 'Bash', 'cargo', 'cargo build', 2, 0),
+                    (2, 'b', 'Bash', 'npm', 'npm test', 9, 0),
+                    (3, 'c', 'Bash', 'cargo', 'cargo test', 5, 1),
+                    (4, 'd', 'Edit', 'rs', 'fix', 1, 0);",
+        )
+        .unwrap();
+        assert!(lookup(&conn, "Bash", Some("cargo"), 3).unwrap().is_empty());
+
+        assert_eq!(refresh(&conn).unwrap(), 8);
+        let ids = |category, limit| -> Vec<i64> {
+            lookup(&conn, "Bash", category, limit).unwrap().iter().map(|(p, _)| p.id).collect()
+        };
+        assert_eq!(ids(Some("cargo"), 3), vec![3, 1, 2]);
+        assert_eq!(ids(Some("go"), 2), vec![2, 3]);
+        assert_eq!(ids(None, 3), vec![2, 3, 1]);
+        assert!(ids(None, PER_KEY + 1).is_empty());
+
+        conn.execute("DELETE FROM patterns WHERE id = 3", []).unwrap();
+        assert_eq!(ids(Some("cargo"), 3), vec![1, 2]);
+
+        conn.execute("UPDATE patterns SET status = 'quarantined' WHERE id = 2", []).unwrap();
+        assert_eq!(ids(Some("cargo"), 3), vec![1]);
+        refresh(&conn).unwrap();
+        assert_eq!(ids(None, 3), vec![1]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Action plans for a task (`mana suggest`)
+//!
+//! Finds the patterns closest to a free-text description of what's about to
+//! be done: semantically when an embedding index exists, by term similarity
+//! otherwise. Patterns that tend to succeed alongside them (synergy edges in
+//! the causal graph) join the plan at a discount, and a step that conflicts
+//! with a better one is dropped. Each step lists the skills its pattern was
+//! consolidated into, or else the best skill for its tool and category.
+//! Risky patterns awaiting approval and inactive ones are never suggested.
+//!
+//! `mana suggest --json` prints the [`Plan`] for integrations, and the daemon
+//! answers `{"command": "suggest", "context": "..."}` with the same JSON.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::embeddings::EmbeddingStore;
+use crate::storage::terms::Corpus;
+use crate::storage::{CausalStore, Scorer};
+
+/// Share of a pattern's relevance passed on to its synergy partners
+const SYNERGY_DISCOUNT: f64 = 0.5;
+
+/// Least term similarity for a text match to count
+const MIN_TEXT_SIMILARITY: f64 = 0.2;
+
+/// Skills listed per step
+const SKILLS_PER_STEP: usize = 2;
+
+/// A ranked plan for a task
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    pub context: String,
+    /// "semantic" or "text", how the first patterns were found
+    pub mode: &'static str,
+    pub steps: Vec<Step>,
+}
+
+/// One suggested pattern
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    pub id: i64,
+    pub tool_type: String,
+    pub category: Option<String>,
+    pub context: String,
+    /// Match to the task, 0 to 1; discounted for synergy partners
+    pub relevance: f64,
+    pub success_rate: f64,
+    /// Ranking score: relevance weighed by the pattern's track record
+    pub score: f64,
+    /// Pattern this one was added for through a synergy edge
+    pub via: Option<i64>,
+    pub skills: Vec<SkillRef>,
+}
+
+/// A skill associated with a step
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillRef {
+    pub id: i64,
+    pub name: String,
+    pub success_rate: f64,
+}
+
+struct Candidate {
+    relevance: f64,
+    via: Option<i64>,
+}
+
+/// Build a plan of up to `limit` steps for `context`
+///
+/// `embeddings` is used when given; `causal` adds synergy partners and drops
+/// conflicts.
+pub fn plan(
+    conn: &Connection,
+    embeddings: Option<&EmbeddingStore>,
+    causal: Option<&CausalStore>,
+    context: &str,
+    limit: usize,
+) -> Result<Plan> {
+    let limit = limit.max(1);
+    let (mode, seeds) = match embeddings {
+        Some(store) => ("semantic", store.search(context, limit * 2)?.into_iter().map(|(id, s)| (id, s as f64)).collect()),
+        None => ("text", text_matches(conn, context, limit * 2)?),
+    };
+
+    let mut candidates: HashMap<i64, Candidate> = HashMap::new();
+    for &(id, relevance) in seeds.iter().filter(|(_, r)| *r > 0.0) {
+        candidates.insert(id, Candidate { relevance: relevance.min(1.0), via: None });
+    }
+    if let Some(causal) = causal {
+        for &(id, relevance) in seeds.iter().filter(|(_, r)| *r > 0.0) {
+            let relevance = relevance.min(1.0) * SYNERGY_DISCOUNT;
+            for partner in causal.get_synergies(id)? {
+                let known = candidates.get(&partner).map_or(0.0, |c| c.relevance);
+                if relevance > known {
+                    candidates.insert(partner, Candidate { relevance, via: Some(id) });
+                }
+            }
+        }
+    }
+
+    let mut steps = Vec::new();
+    for (id, candidate) in candidates {
+        if let Some(step) = load_step(conn, id, candidate)? {
+            steps.push(step);
+        }
+    }
+    steps.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+
+    if let Some(causal) = causal {
+        let ranked: Vec<i64> = steps.iter().map(|s| s.id).collect();
+        let keep = causal.select_compatible(&ranked, limit)?;
+        steps.retain(|s| keep.contains(&s.id));
+    }
+    steps.truncate(limit);
+
+    let skills = load_skills(conn)?;
+    for step in &mut steps {
+        step.skills = skills_for(&skills, step);
+    }
+
+    Ok(Plan { context: context.to_string(), mode, steps })
+}
+
+/// Active patterns by term similarity to `context`, best first
+fn text_matches(conn: &Connection, context: &str, limit: usize) -> Result<Vec<(i64, f64)>> {
+    let scorer = Scorer::new(context, &Corpus::new(conn));
+    if scorer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare("SELECT id, context_query FROM patterns WHERE status = 'active'")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    let mut matches = Vec::new();
+    for row in rows {
+        let (id, text) = row?;
+        let similarity = scorer.score(&text);
+        if similarity >= MIN_TEXT_SIMILARITY {
+            matches.push((id, similarity));
+        }
+    }
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+/// The step for pattern `id`, None if it's missing or not to be suggested
+fn load_step(conn: &Connection, id: i64, candidate: Candidate) -> Result<Option<Step>> {
+    let row = conn
+        .query_row(
+            "SELECT tool_type, command_category, context_query, success_count, failure_count
+             FROM patterns
+             WHERE id = ?1 AND status = 'active' AND NOT (risky = 1 AND approved_at IS NULL)",
+            params![id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((tool_type, category, context, success, failure)) = row else {
+        return Ok(None);
+    };
+
+    let success_rate = if success + failure > 0 { success as f64 / (success + failure) as f64 } else { 0.0 };
+    // Smoothed, so an untried pattern ranks by relevance at half weight
+    let confidence = (success as f64 + 1.0) / ((success + failure) as f64 + 2.0);
+    Ok(Some(Step {
+        id,
+        tool_type,
+        category,
+        context,
+        relevance: candidate.relevance,
+        success_rate,
+        score: candidate.relevance * confidence,
+        via: candidate.via,
+        skills: Vec::new(),
+    }))
+}
+
+struct SkillRow {
+    skill: SkillRef,
+    pattern_ids: Vec<i64>,
+    tool_type: Option<String>,
+    category: Option<String>,
+}
+
+/// Every skill, best first
+fn load_skills(conn: &Connection) -> Result<Vec<SkillRow>> {
+    // The full skills table appears with the first consolidation
+    if !crate::storage::has_column(conn, "skills", "tool_type") {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, name, pattern_ids, total_success, total_failure, tool_type, command_category
+         FROM skills ORDER BY (total_success - total_failure) DESC, id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let pattern_ids: Option<String> = row.get(2)?;
+        let (success, failure): (i64, i64) = (row.get(3)?, row.get(4)?);
+        Ok(SkillRow {
+            skill: SkillRef {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                success_rate: if success + failure > 0 { success as f64 / (success + failure) as f64 } else { 0.0 },
+            },
+            pattern_ids: pattern_ids.unwrap_or_default().split(',').filter_map(|id| id.trim().parse().ok()).collect(),
+            tool_type: row.get(5)?,
+            category: row.get(6)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// Skills holding the step's pattern, or else the best for its tool and category
+fn skills_for(skills: &[SkillRow], step: &Step) -> Vec<SkillRef> {
+    let holding: Vec<SkillRef> = skills
+        .iter()
+        .filter(|s| s.pattern_ids.contains(&step.id))
+        .take(SKILLS_PER_STEP)
+        .map(|s| s.skill.clone())
+        .collect();
+    if !holding.is_empty() {
+        return holding;
+    }
+    skills
+        .iter()
+        .filter(|s| s.tool_type.as_deref() == Some(step.tool_type.as_str()) && s.category == step.category)
+        .take(1)
+        .map(|s| s.skill.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_follows_synergies_and_drops_conflicts() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let conn = crate::storage::db::open(&db_path).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        crate::storage::SkillStore::open(&db_path).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count, risky)
+             VALUES (1, 'a', 'Bash', 'cargo', 'cargo build release crate', 8, 1, 0),
+                    (2, 'b', 'Bash', 'cargo', 'run clippy lints', 5, 0, 0),
+                    (3, 'c', 'Bash', 'cargo', 'cargo build with features', 1, 4, 0),
+                    (4, 'd', 'Bash', 'rm', 'cargo build after rm -rf target', 9, 0, 1);
+             INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences)
+             VALUES (1, 2, 2.0, 5), (1, 3, 0.2, 5);
+             INSERT INTO skills (name, description, pattern_ids, total_success, total_failure, pattern_count, tool_type, command_category)
+             VALUES ('Cargo builds', '', '1,3', 9, 5, 2, 'Bash', 'cargo');",
+        )
+        .unwrap();
+        let causal = CausalStore::open(&db_path).unwrap();
+
+        let plan = plan(&conn, None, Some(&causal), "cargo build the crate", 5).unwrap();
+        assert_eq!(plan.mode, "text");
+        let ids: Vec<i64> = plan.steps.iter().map(|s| s.id).collect();
+        // 2 comes in with 1, 3 conflicts with 1, 4 is risky
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(plan.steps[1].via, Some(1));
+        assert_eq!(plan.steps[0].skills[0].name, "Cargo builds");
+        assert_eq!(plan.steps[1].skills[0].name, "Cargo builds");
+    }
+}
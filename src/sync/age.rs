@@ -0,0 +1,301 @@
+//! Public-key encryption for shared exports
+//!
+//! A `MANA_SYNC_KEY` passphrase has to be handed to everyone who needs it.
+//! With age each teammate keeps a private identity and publishes an
+//! `age1...` public key, and exports encrypted to those keys open only for
+//! their holders. The work is delegated to the `age` and `age-keygen` tools
+//! so files and keys interoperate with the rest of the age ecosystem.
+//!
+//! Identities live in `<mana_dir>/keys/`: `identity.txt` is the current one
+//! and `mana keys rotate` moves it to `keys/retired/` so exports made for
+//! the old key still decrypt.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Extra identity file tried when decrypting (e.g. `~/.config/age/keys.txt`)
+pub const IDENTITY_ENV: &str = "MANA_AGE_IDENTITY";
+
+const KEYS_DIR: &str = "keys";
+const IDENTITY_FILE: &str = "identity.txt";
+const RETIRED_DIR: &str = "retired";
+
+/// Start of binary and armored age files
+const BINARY_HEADER: &[u8] = b"age-encryption.org/v1\n";
+const ARMOR_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Characters allowed in the data part of a bech32 string
+const BECH32_CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// An age identity and its public key
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyInfo {
+    pub public_key: String,
+    pub path: PathBuf,
+    pub created: Option<String>,
+    /// Used for new exports (retired keys only decrypt)
+    pub current: bool,
+}
+
+/// Check that a recipient looks like an age (`age1...`) or SSH public key
+pub fn validate_recipient(recipient: &str) -> Result<()> {
+    let recipient = recipient.trim();
+    if recipient.starts_with("ssh-ed25519 ") || recipient.starts_with("ssh-rsa ") {
+        return Ok(());
+    }
+    let valid = recipient.len() == 62
+        && recipient.starts_with("age1")
+        && recipient[4..].chars().all(|c| BECH32_CHARSET.contains(c));
+    if !valid {
+        bail!("Invalid recipient {:?}: expected an age public key (age1...) or an SSH public key", recipient);
+    }
+    Ok(())
+}
+
+/// Whether data is an age-encrypted file (binary or armored)
+pub fn is_encrypted(data: &[u8]) -> bool {
+    let start = data.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(data.len());
+    data.starts_with(BINARY_HEADER) || data[start..].starts_with(ARMOR_HEADER)
+}
+
+/// Encrypt to one or more recipients (armored, so exports stay text files)
+pub fn encrypt(plaintext: &[u8], recipients: &[String]) -> Result<Vec<u8>> {
+    if recipients.is_empty() {
+        bail!("At least one recipient is required");
+    }
+    let mut args = vec!["--encrypt".to_string(), "--armor".to_string()];
+    for recipient in recipients {
+        validate_recipient(recipient)?;
+        args.push("--recipient".to_string());
+        args.push(recipient.trim().to_string());
+    }
+    run("age", &args, plaintext)
+}
+
+/// Decrypt with whichever of the identities the file was encrypted to
+pub fn decrypt(ciphertext: &[u8], identities: &[PathBuf]) -> Result<Vec<u8>> {
+    if identities.is_empty() {
+        bail!("No age identity found. Generate one with 'mana keys generate' or set {}", IDENTITY_ENV);
+    }
+    let mut args = vec!["--decrypt".to_string()];
+    for identity in identities {
+        args.push("--identity".to_string());
+        args.push(identity.to_string_lossy().into_owned());
+    }
+    run("age", &args, ciphertext).context("Could not decrypt: the file was not encrypted to any local key")
+}
+
+/// Encrypt a file in place
+///
+/// The plaintext is removed if encryption fails so it isn't shared by mistake.
+pub fn encrypt_file(path: &Path, recipients: &[String]) -> Result<()> {
+    let ciphertext = match encrypt(&std::fs::read(path)?, recipients) {
+        Ok(ciphertext) => ciphertext,
+        Err(e) => {
+            let _ = std::fs::remove_file(path);
+            return Err(e);
+        }
+    };
+    let tmp = path.with_extension("age-tmp");
+    std::fs::write(&tmp, ciphertext)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Plaintext of a decrypted import, removed when dropped
+pub struct DecryptedFile {
+    path: PathBuf,
+}
+
+impl DecryptedFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for DecryptedFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Decrypt `input` into the data directory if it is age-encrypted
+///
+/// Returns `None` for files that aren't, so callers can import whichever
+/// path they end up with.
+pub fn decrypt_if_encrypted(mana_dir: &Path, input: &Path) -> Result<Option<DecryptedFile>> {
+    let data = std::fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+    if !is_encrypted(&data) {
+        return Ok(None);
+    }
+    let plaintext = decrypt(&data, &identities(mana_dir))?;
+
+    let file = DecryptedFile { path: mana_dir.join(format!(".import-{}.tmp", std::process::id())) };
+    write_private(&file.path, &plaintext)?;
+    Ok(Some(file))
+}
+
+/// Identities to try when decrypting: current, retired, then `MANA_AGE_IDENTITY`
+pub fn identities(mana_dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = list(mana_dir).unwrap_or_default().into_iter().map(|k| k.path).collect();
+    if let Some(extra) = std::env::var_os(IDENTITY_ENV).filter(|v| !v.is_empty()) {
+        paths.push(PathBuf::from(extra));
+    }
+    paths
+}
+
+/// Current identity, if one has been generated
+pub fn current(mana_dir: &Path) -> Result<Option<KeyInfo>> {
+    let path = mana_dir.join(KEYS_DIR).join(IDENTITY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    read_key_info(&path, true).map(Some)
+}
+
+/// All identities, current first, then retired ones newest first
+pub fn list(mana_dir: &Path) -> Result<Vec<KeyInfo>> {
+    let mut keys: Vec<KeyInfo> = current(mana_dir)?.into_iter().collect();
+
+    let retired_dir = mana_dir.join(KEYS_DIR).join(RETIRED_DIR);
+    if retired_dir.is_dir() {
+        let mut retired: Vec<PathBuf> = std::fs::read_dir(&retired_dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+            .collect();
+        retired.sort();
+        for path in retired.into_iter().rev() {
+            keys.push(read_key_info(&path, false)?);
+        }
+    }
+    Ok(keys)
+}
+
+/// Generate the current identity with `age-keygen`
+pub fn generate(mana_dir: &Path) -> Result<KeyInfo> {
+    let path = mana_dir.join(KEYS_DIR).join(IDENTITY_FILE);
+    if path.exists() {
+        bail!("An identity already exists at {}. Use 'mana keys rotate' to replace it", path.display());
+    }
+    let identity = run("age-keygen", &[], &[])?;
+    std::fs::create_dir_all(mana_dir.join(KEYS_DIR))?;
+    write_private(&path, &identity)?;
+    read_key_info(&path, true)
+}
+
+/// Retire the current identity and generate a new one
+///
+/// Returns the new key and where the old one was moved.
+pub fn rotate(mana_dir: &Path) -> Result<(KeyInfo, PathBuf)> {
+    let path = mana_dir.join(KEYS_DIR).join(IDENTITY_FILE);
+    if !path.exists() {
+        bail!("No identity to rotate. Generate one with 'mana keys generate'");
+    }
+
+    let retired_dir = mana_dir.join(KEYS_DIR).join(RETIRED_DIR);
+    std::fs::create_dir_all(&retired_dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut retired = retired_dir.join(format!("identity-{}.txt", stamp));
+    let mut n = 1;
+    while retired.exists() {
+        retired = retired_dir.join(format!("identity-{}-{}.txt", stamp, n));
+        n += 1;
+    }
+
+    // Generate first so a missing age-keygen leaves the current key in place
+    let identity = run("age-keygen", &[], &[])?;
+    std::fs::rename(&path, &retired)?;
+    write_private(&path, &identity)?;
+    Ok((read_key_info(&path, true)?, retired))
+}
+
+/// Read the public key and creation time from age-keygen's comment lines
+fn read_key_info(path: &Path, current: bool) -> Result<KeyInfo> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let comment = |prefix: &str| {
+        content.lines().find_map(|line| line.strip_prefix(prefix).map(|v| v.trim().to_string()))
+    };
+    let public_key = comment("# public key:")
+        .ok_or_else(|| anyhow!("{} has no '# public key:' line", path.display()))?;
+    Ok(KeyInfo { public_key, path: path.to_path_buf(), created: comment("# created:"), current })
+}
+
+/// Write a file readable only by the owner
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).with_context(|| format!("Failed to write {}", path.display()))?;
+    file.write_all(contents)?;
+    Ok(())
+}
+
+/// Run an age tool, feeding `input` on stdin and returning stdout
+fn run(program: &str, args: &[String], input: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                anyhow!("'{}' not found. Install age (https://age-encryption.org) to use key-based encryption", program)
+            }
+            _ => anyhow!("Failed to run {}: {}", program, e),
+        })?;
+
+    // Feed stdin from another thread so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("{} stdin unavailable", program))?;
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    if !output.status.success() {
+        bail!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recipients_and_headers() {
+        assert!(validate_recipient("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p").is_ok());
+        assert!(validate_recipient("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIG user@host").is_ok());
+        assert!(validate_recipient("age1short").is_err());
+        assert!(validate_recipient("AGE1QL3Z7HJY54PW3HYWW5AYYFG7ZQGVC7W3J2ELW8ZMRJ2KG5SFN9AQMCAC8P").is_err());
+
+        assert!(is_encrypted(b"age-encryption.org/v1\n-> X25519 abc\n"));
+        assert!(is_encrypted(b"\n-----BEGIN AGE ENCRYPTED FILE-----\nYWdl\n"));
+        assert!(!is_encrypted(b"{\"metadata\": {}}"));
+    }
+
+    #[test]
+    fn test_list_identities() {
+        let temp = TempDir::new().unwrap();
+        let keys = temp.path().join(KEYS_DIR);
+        std::fs::create_dir_all(keys.join(RETIRED_DIR)).unwrap();
+        let identity = |key: &str| format!("# created: 2024-05-01T00:00:00Z\n# public key: {}\nAGE-SECRET-KEY-1XYZ\n", key);
+        std::fs::write(keys.join(IDENTITY_FILE), identity("age1new")).unwrap();
+        std::fs::write(keys.join(RETIRED_DIR).join("identity-20240101-000000.txt"), identity("age1old")).unwrap();
+
+        let listed = list(temp.path()).unwrap();
+        let summary: Vec<(&str, bool)> = listed.iter().map(|k| (k.public_key.as_str(), k.current)).collect();
+        assert_eq!(summary, vec![("age1new", true), ("age1old", false)]);
+        assert_eq!(listed[0].created.as_deref(), Some("2024-05-01T00:00:00Z"));
+        assert_eq!(identities(temp.path()).len(), 2);
+        assert!(generate(temp.path()).is_err());
+    }
+}
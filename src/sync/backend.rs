@@ -0,0 +1,203 @@
+//! Common interface over the sync backends
+//!
+//! `mana sync push`, `pull`, `sync` and `status` go through [`SyncBackendImpl`], so
+//! the CLI doesn't need to know which backends exist. Each backend module
+//! implements the trait, and [`backend_for`] picks the implementation for
+//! the configured backend. Adding a backend means a new `SyncBackend`
+//! variant, its implementation, and one line in the factory.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+use crate::sync::export::{ImportResult, MergeStrategy};
+use crate::sync::shards::PatternConflict;
+use crate::sync::{load_sync_config, SyncBackend, SyncConfig};
+
+/// Future returned by backend operations
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + 'a>>;
+
+/// Where a sync operation runs and how sync is configured there
+pub struct SyncContext<'a> {
+    pub mana_dir: &'a Path,
+    pub db_path: &'a Path,
+    pub config: SyncConfig,
+}
+
+impl<'a> SyncContext<'a> {
+    /// Load the sync configuration from the data directory
+    pub fn load(mana_dir: &'a Path, db_path: &'a Path) -> Result<Self> {
+        let config = load_sync_config(&mana_dir.join("sync.toml"))?;
+        Ok(Self { mana_dir, db_path, config })
+    }
+}
+
+/// Options for `mana sync push`
+#[derive(Debug, Clone, Default)]
+pub struct PushOptions {
+    /// Encryption passphrase
+    pub passphrase: Option<String>,
+    /// Commit message, for backends that keep history
+    pub message: Option<String>,
+    /// Key to sign the export with
+    pub sign_key: Option<PathBuf>,
+}
+
+/// Options for `mana sync pull`
+#[derive(Debug, Clone, Default)]
+pub struct PullOptions {
+    /// Decryption passphrase
+    pub passphrase: Option<String>,
+    pub merge_strategy: MergeStrategy,
+    /// Refuse unsigned exports
+    pub require_signed: bool,
+}
+
+/// What a pull brought in
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PullReport {
+    /// Remote patterns that changed since the last sync
+    pub changed: usize,
+    pub imported: usize,
+    pub merged: usize,
+    pub skipped: usize,
+    /// Patterns changed on both sides, for backends that track a merge base
+    pub conflicts: Vec<PatternConflict>,
+}
+
+impl From<ImportResult> for PullReport {
+    fn from(result: ImportResult) -> Self {
+        Self {
+            changed: result.total,
+            imported: result.imported,
+            merged: result.merged,
+            skipped: result.skipped,
+            conflicts: Vec::new(),
+        }
+    }
+}
+
+impl PullReport {
+    /// Whether conflicts must be looked at before pushing
+    ///
+    /// The `add` strategy keeps both sides' increments and `--interactive`
+    /// already asked, so their conflicts are resolved. With `replace` or
+    /// `keep-best` one side won, and pushing would hand that choice to every
+    /// other machine.
+    pub fn needs_review(&self, strategy: MergeStrategy) -> bool {
+        matches!(strategy, MergeStrategy::Replace | MergeStrategy::KeepBest) && !self.conflicts.is_empty()
+    }
+}
+
+/// Outcome of `mana sync sync`
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncReport {
+    pub backend: &'static str,
+    pub pulled: PullReport,
+    /// False when the push was held back for conflict review
+    pub pushed: bool,
+}
+
+/// A sync backend: moves patterns between the local database and a remote
+///
+/// Push and pull report progress to the user as they go.
+pub trait SyncBackendImpl {
+    /// Backend name shown in status output
+    fn name(&self) -> &'static str;
+
+    /// Whether pushes can be signed and pulls checked against trusted signers
+    fn supports_signing(&self) -> bool {
+        false
+    }
+
+    /// Send local patterns to the remote
+    fn push<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PushOptions) -> BackendFuture<'a, ()>;
+
+    /// Merge remote patterns into the local database
+    fn pull<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PullOptions) -> BackendFuture<'a, PullReport>;
+
+    /// Pull, then push unless the pull left conflicts to review
+    ///
+    /// Nothing is pushed if the pull fails.
+    fn sync<'a>(
+        &'a self,
+        ctx: &'a SyncContext<'a>,
+        pull: &'a PullOptions,
+        push: &'a PushOptions,
+    ) -> BackendFuture<'a, SyncReport> {
+        Box::pin(async move {
+            let pulled = self.pull(ctx, pull).await?;
+            let pushed = !pulled.needs_review(pull.merge_strategy);
+            if pushed {
+                self.push(ctx, push).await?;
+            }
+            Ok(SyncReport { backend: self.name(), pulled, pushed })
+        })
+    }
+
+    /// Backend status for `--json`
+    fn status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, serde_json::Value>;
+
+    /// Print backend status for people
+    fn print_status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, ()>;
+}
+
+/// The implementation for a configured backend
+pub fn backend_for(backend: &SyncBackend) -> Box<dyn SyncBackendImpl> {
+    match backend {
+        SyncBackend::Git { .. } => Box::new(crate::sync::git_backend::GitSync),
+        SyncBackend::S3 { .. } => Box::new(crate::sync::s3_backend::S3Sync),
+        SyncBackend::Supabase { .. } => Box::new(crate::sync::supabase_backend::SupabaseSync),
+        SyncBackend::Ssh { .. } => Box::new(crate::sync::ssh_backend::SshSync),
+        SyncBackend::P2P { .. } => Box::new(crate::sync::p2p_backend::P2PSync),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_for_matches_config() {
+        let backends = [
+            (SyncBackend::Git { remote: String::new(), branch: "main".to_string() }, "git"),
+            (
+                SyncBackend::S3 {
+                    bucket: "b".to_string(),
+                    prefix: "mana".to_string(),
+                    region: "us-east-1".to_string(),
+                    endpoint: Default::default(),
+                },
+                "s3",
+            ),
+            (SyncBackend::Supabase { url: "https://x.supabase.co".to_string() }, "supabase"),
+            (SyncBackend::Ssh { host: "me@box".to_string(), path: "/srv/mana".to_string() }, "ssh"),
+            (SyncBackend::P2P { discovery: "static".to_string(), listen_port: 4222, peers: Vec::new() }, "p2p"),
+        ];
+        for (config, name) in backends {
+            let backend = backend_for(&config);
+            assert_eq!(backend.name(), name);
+            assert_eq!(backend.supports_signing(), name == "git");
+        }
+    }
+
+    #[test]
+    fn test_only_unresolved_conflicts_need_review() {
+        let mut report = PullReport::default();
+        assert!(!report.needs_review(MergeStrategy::KeepBest));
+
+        report.conflicts.push(PatternConflict {
+            pattern_hash: "abc".to_string(),
+            tool_type: "Bash".to_string(),
+            local: (3, 0),
+            remote: (1, 2),
+            merged: (3, 2),
+        });
+        assert!(!report.needs_review(MergeStrategy::Add));
+        assert!(report.needs_review(MergeStrategy::KeepBest));
+        assert!(report.needs_review(MergeStrategy::Replace));
+        assert!(!report.needs_review(MergeStrategy::Interactive));
+    }
+}
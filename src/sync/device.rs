@@ -0,0 +1,59 @@
+//! Device identity
+//!
+//! Every machine that syncs has a name: `[device] name` in config.toml, or
+//! its hostname. It's stamped into exports, P2P node metadata and the
+//! provenance of pulled patterns (see `storage::provenance`), so
+//! `patterns show` and `sync status` can tell laptop from devpod.
+
+use std::path::Path;
+
+use crate::config::{load_config, ManaConfig};
+
+/// Name used when neither config nor the system has one
+pub const UNKNOWN: &str = "unknown";
+
+/// This device's name, from the config in `mana_dir`
+pub fn name(mana_dir: &Path) -> String {
+    from_config(&load_config(mana_dir))
+}
+
+/// This device's name: the configured one, else the short hostname
+pub fn from_config(config: &ManaConfig) -> String {
+    config
+        .device
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .or_else(hostname)
+        .unwrap_or_else(|| UNKNOWN.to_string())
+}
+
+/// Hostname without its domain (`laptop.local` → `laptop`)
+pub fn hostname() -> Option<String> {
+    let full = ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })?;
+    full.trim().split('.').next().filter(|short| !short.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_name_wins() {
+        let mut config = ManaConfig::default();
+        config.device.name = Some("  devpod-1 ".to_string());
+        assert_eq!(from_config(&config), "devpod-1");
+
+        config.device.name = Some(String::new());
+        let fallback = from_config(&config);
+        assert!(!fallback.is_empty() && !fallback.contains('.'));
+    }
+}
@@ -6,13 +6,17 @@
 use anyhow::{Result, anyhow};
 use chrono::Utc;
 use std::path::Path;
-use tracing::info;
+use tracing::{info, instrument};
 
-use crate::storage::{Pattern, PatternStore};
+use crate::config::{load_config, SyncSettings};
+use crate::storage::{db, provenance, Pattern, PatternFilter, PatternStore};
 use crate::sync::{
     ExportBundle, ExportMetadata, ExportablePattern, SecurityConfig,
     crypto::{encrypt_string, decrypt_string, hash_workspace_id, EncryptedData},
-    sanitize::sanitize_pattern,
+    device,
+    resolve::{import_interactive, TerminalPrompt},
+    sanitize::{self, sanitize_pattern},
+    verdicts::{self, VerdictStats},
 };
 
 /// Export format options
@@ -28,24 +32,21 @@ pub enum ExportFormat {
 /// Export patterns to a file
 ///
 /// Applies sanitization based on security config and optionally encrypts.
+/// `with_verdicts` adds each pattern's reflection verdict stats.
+#[instrument(level = "debug", name = "export", skip_all)]
 pub fn export_patterns(
     db_path: &Path,
     output_path: &Path,
     security: &SecurityConfig,
     passphrase: Option<&str>,
+    filter: &PatternFilter,
+    with_verdicts: bool,
 ) -> Result<usize> {
-    let store = PatternStore::open_readonly(db_path)?;
-
-    // Get all patterns
-    let patterns = get_all_patterns(&store)?;
+    let patterns = select_patterns(db_path, filter)?;
     let pattern_count = patterns.len();
 
-    if pattern_count == 0 {
-        return Err(anyhow!("No patterns to export"));
-    }
-
     // Sanitize patterns
-    let sanitized: Vec<ExportablePattern> = patterns
+    let mut sanitized: Vec<ExportablePattern> = patterns
         .iter()
         .map(|p| {
             if security.sanitize_paths || security.redact_secrets {
@@ -59,38 +60,28 @@ pub fn export_patterns(
                     context_query: p.context_query.clone(),
                     success_count: p.success_count,
                     failure_count: p.failure_count,
+                    device: None,
+                    risky: p.risky,
                 }
             }
         })
         .collect();
 
-    // Create export bundle
-    let workspace_id = hash_workspace_id(&std::env::current_dir()?.to_string_lossy());
-    let encrypted = security.encrypt && passphrase.is_some();
-
-    let bundle = ExportBundle {
-        metadata: ExportMetadata {
-            version: "1.0".to_string(),
-            exported_at: Utc::now().to_rfc3339(),
-            source_workspace: workspace_id,
-            pattern_count,
-            encrypted,
-        },
-        patterns: sanitized,
+    let local = stamp_devices(db_path, &patterns, &mut sanitized);
+    let verdicts = if with_verdicts {
+        let sanitizing = security.sanitize_paths || security.redact_secrets;
+        let clean = |text: &str| if sanitizing { sanitize::sanitize_context(text) } else { text.to_string() };
+        verdicts::collect(&db::open_readonly(db_path)?, &patterns, &sanitized, clean)?
+    } else {
+        Vec::new()
     };
 
-    // Serialize to JSON
-    let json = serde_json::to_string_pretty(&bundle)?;
-
-    // Encrypt if requested
-    if encrypted {
-        let passphrase = passphrase.ok_or_else(|| anyhow!("Passphrase required for encryption"))?;
-        let encrypted_data = encrypt_string(&json, passphrase)?;
-        let encrypted_json = serde_json::to_string_pretty(&encrypted_data)?;
-        std::fs::write(output_path, encrypted_json)?;
+    let workspace_id = hash_workspace_id(&std::env::current_dir()?.to_string_lossy());
+    let passphrase = passphrase.filter(|_| security.encrypt);
+    std::fs::write(output_path, render_bundle(sanitized, verdicts, passphrase, &workspace_id, &local)?)?;
+    if passphrase.is_some() {
         info!("Exported {} patterns (encrypted) to {:?}", pattern_count, output_path);
     } else {
-        std::fs::write(output_path, json)?;
         info!("Exported {} patterns to {:?}", pattern_count, output_path);
     }
 
@@ -100,7 +91,9 @@ pub fn export_patterns(
 /// Import patterns from a file
 ///
 /// Supports both plain JSON and encrypted JSON formats.
-/// Merges imported patterns with existing ones.
+/// Merges imported patterns with existing ones, then any verdict stats the
+/// bundle carries.
+#[instrument(level = "debug", name = "import", skip_all)]
 pub fn import_patterns(
     db_path: &Path,
     input_path: &Path,
@@ -108,16 +101,7 @@ pub fn import_patterns(
     merge_strategy: MergeStrategy,
 ) -> Result<ImportResult> {
     let content = std::fs::read_to_string(input_path)?;
-
-    // Try to parse as encrypted data first
-    let bundle: ExportBundle = if let Ok(encrypted) = serde_json::from_str::<EncryptedData>(&content) {
-        let passphrase = passphrase.ok_or_else(|| anyhow!("Passphrase required to decrypt import file"))?;
-        let decrypted = decrypt_string(&encrypted, passphrase)?;
-        serde_json::from_str(&decrypted)?
-    } else {
-        // Try plain JSON
-        serde_json::from_str(&content)?
-    };
+    let bundle = parse_bundle(&content, passphrase)?;
 
     info!("Importing {} patterns from {} (exported at {})",
         bundle.patterns.len(),
@@ -125,6 +109,24 @@ pub fn import_patterns(
         bundle.metadata.exported_at
     );
 
+    let mut result = if merge_strategy == MergeStrategy::Interactive {
+        let mana_dir = db_path.parent().unwrap_or(Path::new("."));
+        let mut prompt = TerminalPrompt::stdio()?;
+        import_interactive(mana_dir, db_path, &bundle.patterns, &bundle.metadata.source_workspace, &mut prompt)?
+    } else {
+        merge_bundle(db_path, &bundle, merge_strategy)?
+    };
+    // Our own verdicts are already here as reflection_verdicts
+    let source = bundle.metadata.device.as_deref().unwrap_or(&bundle.metadata.source_workspace);
+    let local = device::name(db_path.parent().unwrap_or(Path::new(".")));
+    if !bundle.verdicts.is_empty() && source != local {
+        result.verdicts = verdicts::merge(&db::open(db_path)?, &bundle.verdicts, source, verdicts::Provenance::Team)?;
+    }
+    Ok(result)
+}
+
+/// Merge a bundle's patterns without prompting
+fn merge_bundle(db_path: &Path, bundle: &ExportBundle, merge_strategy: MergeStrategy) -> Result<ImportResult> {
     // Open store for writing
     let store = PatternStore::open(db_path)?;
 
@@ -134,6 +136,7 @@ pub fn import_patterns(
     let mut skipped = 0;
 
     for exportable in &bundle.patterns {
+        let device = exportable.device.as_deref().or(bundle.metadata.device.as_deref());
         let pattern = Pattern {
             id: 0, // Will be assigned by database
             pattern_hash: exportable.pattern_hash.clone(),
@@ -143,39 +146,13 @@ pub fn import_patterns(
             success_count: exportable.success_count,
             failure_count: exportable.failure_count,
             embedding_id: None,
+            risky: exportable.risky,
         };
 
-        match merge_strategy {
-            MergeStrategy::Add => {
-                // Use insert_fast which handles duplicates via hash
-                let id = store.insert_fast(&pattern)?;
-                if id > 0 {
-                    imported += 1;
-                } else {
-                    merged += 1;
-                }
-            }
-            MergeStrategy::Replace => {
-                // Force insert, replacing existing
-                store.insert_fast(&pattern)?;
-                imported += 1;
-            }
-            MergeStrategy::KeepBest => {
-                // Only import if better success rate
-                if let Some(existing) = find_by_hash(&store, &pattern.pattern_hash)? {
-                    let existing_rate = success_rate(&existing);
-                    let new_rate = success_rate(&pattern);
-                    if new_rate > existing_rate {
-                        store.insert_fast(&pattern)?;
-                        imported += 1;
-                    } else {
-                        skipped += 1;
-                    }
-                } else {
-                    store.insert_fast(&pattern)?;
-                    imported += 1;
-                }
-            }
+        match merge_pattern(&store, &pattern, merge_strategy, device)? {
+            MergeOutcome::Imported => imported += 1,
+            MergeOutcome::Merged => merged += 1,
+            MergeOutcome::Skipped => skipped += 1,
         }
     }
 
@@ -184,12 +161,108 @@ pub fn import_patterns(
         imported,
         merged,
         skipped,
-        source_workspace: bundle.metadata.source_workspace,
+        verdicts: 0,
+        source_workspace: bundle.metadata.source_workspace.clone(),
     })
 }
 
-/// Strategy for handling duplicate patterns during import
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Serialize patterns as an export bundle, encrypted when a passphrase is given
+pub(crate) fn render_bundle(
+    patterns: Vec<ExportablePattern>,
+    verdicts: Vec<VerdictStats>,
+    passphrase: Option<&str>,
+    source_workspace: &str,
+    device: &str,
+) -> Result<String> {
+    let bundle = ExportBundle {
+        metadata: ExportMetadata {
+            version: "1.0".to_string(),
+            exported_at: Utc::now().to_rfc3339(),
+            source_workspace: source_workspace.to_string(),
+            pattern_count: patterns.len(),
+            encrypted: passphrase.is_some(),
+            device: Some(device.to_string()),
+        },
+        patterns,
+        verdicts,
+    };
+    let json = serde_json::to_string_pretty(&bundle)?;
+    match passphrase {
+        Some(passphrase) => Ok(serde_json::to_string_pretty(&encrypt_string(&json, passphrase)?)?),
+        None => Ok(json),
+    }
+}
+
+/// Parse an export file's contents, decrypting it if needed
+pub(crate) fn parse_bundle(content: &str, passphrase: Option<&str>) -> Result<ExportBundle> {
+    // Try to parse as encrypted data first
+    if let Ok(encrypted) = serde_json::from_str::<EncryptedData>(content) {
+        let passphrase = passphrase.ok_or_else(|| anyhow!("Passphrase required to decrypt import file"))?;
+        let decrypted = decrypt_string(&encrypted, passphrase)?;
+        Ok(serde_json::from_str(&decrypted)?)
+    } else {
+        // Try plain JSON
+        Ok(serde_json::from_str(content)?)
+    }
+}
+
+/// What happened to one imported pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MergeOutcome {
+    Imported,
+    Merged,
+    Skipped,
+}
+
+/// Apply a merge strategy to one incoming pattern from `device`
+///
+/// A pattern that's new here (or replaces the local copy) records the
+/// device as its origin.
+pub(crate) fn merge_pattern(
+    store: &PatternStore,
+    pattern: &Pattern,
+    merge_strategy: MergeStrategy,
+    device: Option<&str>,
+) -> Result<MergeOutcome> {
+    let outcome = apply_merge(store, pattern, merge_strategy)?;
+    if let (MergeOutcome::Imported, Some(device)) = (outcome, device) {
+        store.record_device(&pattern.pattern_hash, device)?;
+    }
+    Ok(outcome)
+}
+
+fn apply_merge(store: &PatternStore, pattern: &Pattern, merge_strategy: MergeStrategy) -> Result<MergeOutcome> {
+    // $HOME/$USER placeholders from the exporting machine become local paths
+    let context_query = sanitize::localize(&pattern.context_query);
+    let risky = sanitize::is_risky_import(pattern.risky, &context_query);
+    let pattern = &Pattern { context_query, risky, ..pattern.clone() };
+    match merge_strategy {
+        MergeStrategy::Add => {
+            // Use insert_fast which handles duplicates via hash
+            let id = store.insert_fast(pattern)?;
+            Ok(if id > 0 { MergeOutcome::Imported } else { MergeOutcome::Merged })
+        }
+        MergeStrategy::Replace => {
+            // Force insert, replacing existing
+            store.insert_fast(pattern)?;
+            Ok(MergeOutcome::Imported)
+        }
+        // Without a prompt, fall back to the rule the user would be overriding
+        MergeStrategy::KeepBest | MergeStrategy::Interactive => {
+            // Only import if better success rate
+            if let Some(existing) = find_by_hash(store, &pattern.pattern_hash)? {
+                if success_rate(pattern) <= success_rate(&existing) {
+                    return Ok(MergeOutcome::Skipped);
+                }
+            }
+            store.insert_fast(pattern)?;
+            Ok(MergeOutcome::Imported)
+        }
+    }
+}
+
+/// Strategy for handling duplicate patterns during import (`--merge`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
 pub enum MergeStrategy {
     /// Add new patterns, merge counts for existing (default)
     #[default]
@@ -198,6 +271,20 @@ pub enum MergeStrategy {
     Replace,
     /// Keep whichever has better success rate
     KeepBest,
+    /// Keep-best with the user picking each winner (`--interactive`)
+    #[value(skip)]
+    Interactive,
+}
+
+impl std::fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeStrategy::Add => write!(f, "add"),
+            MergeStrategy::Replace => write!(f, "replace"),
+            MergeStrategy::KeepBest => write!(f, "keep-best"),
+            MergeStrategy::Interactive => write!(f, "interactive"),
+        }
+    }
 }
 
 /// Result of import operation
@@ -211,19 +298,26 @@ pub struct ImportResult {
     pub merged: usize,
     /// Patterns skipped (KeepBest strategy)
     pub skipped: usize,
+    /// Patterns that received verdict stats from the bundle
+    pub verdicts: usize,
     /// Source workspace identifier
     pub source_workspace: String,
 }
 
 /// Export patterns to a vector (for API-based backends like Supabase)
+#[instrument(level = "debug", name = "export", skip_all)]
 pub fn export_patterns_to_vec(
     db_path: &Path,
     security: &SecurityConfig,
+    filter: &PatternFilter,
 ) -> Result<Vec<ExportablePattern>> {
-    let store = PatternStore::open_readonly(db_path)?;
-    let patterns = get_all_patterns(&store)?;
+    let patterns = if filter.is_empty() {
+        get_all_patterns(&PatternStore::open_readonly(db_path)?)?
+    } else {
+        select_patterns(db_path, filter)?
+    };
 
-    let sanitized: Vec<ExportablePattern> = patterns
+    let mut sanitized: Vec<ExportablePattern> = patterns
         .iter()
         .map(|p| {
             if security.sanitize_paths || security.redact_secrets {
@@ -236,20 +330,119 @@ pub fn export_patterns_to_vec(
                     context_query: p.context_query.clone(),
                     success_count: p.success_count,
                     failure_count: p.failure_count,
+                    device: None,
+                    risky: p.risky,
                 }
             }
         })
         .collect();
+    stamp_devices(db_path, &patterns, &mut sanitized);
 
     Ok(sanitized)
 }
 
+/// Stamp each export with the device it came from, returning this device's name
+///
+/// Patterns pulled from elsewhere keep their origin; the rest were learned here.
+fn stamp_devices(db_path: &Path, patterns: &[Pattern], exports: &mut [ExportablePattern]) -> String {
+    let local = device::name(db_path.parent().unwrap_or(Path::new(".")));
+    // Databases from before provenance was recorded have no origins
+    let origins = db::open_readonly(db_path).and_then(|conn| provenance::all(&conn)).unwrap_or_default();
+    for (pattern, export) in patterns.iter().zip(exports.iter_mut()) {
+        export.device = Some(origins.get(&pattern.id).cloned().unwrap_or_else(|| local.clone()));
+    }
+    local
+}
+
+/// Patterns held back from a push by the `[sync]` quality gate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Withheld {
+    /// Score below `min_score`
+    pub low_score: usize,
+    /// Tool listed in `exclude_tools`
+    pub excluded_tool: usize,
+    /// Beyond `max_patterns`
+    pub over_limit: usize,
+}
+
+impl Withheld {
+    pub fn total(&self) -> usize {
+        self.low_score + self.excluded_tool + self.over_limit
+    }
+}
+
+impl std::fmt::Display for Withheld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reasons: Vec<String> = [
+            (self.excluded_tool, "excluded tool"),
+            (self.low_score, "below min_score"),
+            (self.over_limit, "over max_patterns"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| format!("{} {}", count, reason))
+        .collect();
+        write!(f, "Withheld {} patterns by the [sync] quality gate ({})", self.total(), reasons.join(", "))
+    }
+}
+
+/// Drop patterns that fail the `[sync]` quality gate
+///
+/// `max_patterns` keeps the best-scoring patterns among those that passed
+/// the other checks.
+pub fn apply_sync_gate(patterns: Vec<ExportablePattern>, settings: &SyncSettings) -> (Vec<ExportablePattern>, Withheld) {
+    let mut withheld = Withheld::default();
+    let mut kept: Vec<ExportablePattern> = patterns
+        .into_iter()
+        .filter(|p| {
+            if settings.exclude_tools.iter().any(|t| t.eq_ignore_ascii_case(&p.tool_type)) {
+                withheld.excluded_tool += 1;
+                false
+            } else if settings.min_score.is_some_and(|min| p.success_count - p.failure_count < min) {
+                withheld.low_score += 1;
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
+
+    if let Some(max) = settings.max_patterns.filter(|max| kept.len() > *max) {
+        kept.sort_by_key(|p| (std::cmp::Reverse(p.success_count - p.failure_count), std::cmp::Reverse(p.success_count)));
+        withheld.over_limit = kept.len() - max;
+        kept.truncate(max);
+    }
+
+    (kept, withheld)
+}
+
+/// Export the patterns a push may share
+///
+/// [`export_patterns_to_vec`] followed by the `[sync]` quality gate from the
+/// data directory's config.toml; prints how many patterns were withheld.
+pub fn export_for_push(db_path: &Path, security: &SecurityConfig) -> Result<Vec<ExportablePattern>> {
+    let patterns = export_patterns_to_vec(db_path, security, &PatternFilter::default())?;
+    let settings = db_path.parent().map(|dir| load_config(dir).sync).unwrap_or_default();
+    let (kept, withheld) = apply_sync_gate(patterns, &settings);
+    if withheld.total() > 0 {
+        println!("   {}", withheld);
+    }
+    Ok(kept)
+}
+
 /// Import patterns from a vector (for API-based backends like Supabase)
+#[instrument(level = "debug", name = "import", skip_all, fields(patterns = patterns.len()))]
 pub fn import_patterns_from_vec(
     db_path: &Path,
     patterns: Vec<ExportablePattern>,
     merge_strategy: MergeStrategy,
 ) -> Result<ImportResult> {
+    if merge_strategy == MergeStrategy::Interactive {
+        let mana_dir = db_path.parent().unwrap_or(Path::new("."));
+        let mut prompt = TerminalPrompt::stdio()?;
+        return import_interactive(mana_dir, db_path, &patterns, "remote", &mut prompt);
+    }
+
     let store = PatternStore::open(db_path)?;
 
     let mut imported = 0;
@@ -266,36 +459,13 @@ pub fn import_patterns_from_vec(
             success_count: exportable.success_count,
             failure_count: exportable.failure_count,
             embedding_id: None,
+            risky: exportable.risky,
         };
 
-        match merge_strategy {
-            MergeStrategy::Add => {
-                let id = store.insert_fast(&pattern)?;
-                if id > 0 {
-                    imported += 1;
-                } else {
-                    merged += 1;
-                }
-            }
-            MergeStrategy::Replace => {
-                store.insert_fast(&pattern)?;
-                imported += 1;
-            }
-            MergeStrategy::KeepBest => {
-                if let Some(existing) = find_by_hash(&store, &pattern.pattern_hash)? {
-                    let existing_rate = success_rate(&existing);
-                    let new_rate = success_rate(&pattern);
-                    if new_rate > existing_rate {
-                        store.insert_fast(&pattern)?;
-                        imported += 1;
-                    } else {
-                        skipped += 1;
-                    }
-                } else {
-                    store.insert_fast(&pattern)?;
-                    imported += 1;
-                }
-            }
+        match merge_pattern(&store, &pattern, merge_strategy, exportable.device.as_deref())? {
+            MergeOutcome::Imported => imported += 1,
+            MergeOutcome::Merged => merged += 1,
+            MergeOutcome::Skipped => skipped += 1,
         }
     }
 
@@ -304,11 +474,30 @@ pub fn import_patterns_from_vec(
         imported,
         merged,
         skipped,
+        verdicts: 0,
         source_workspace: "api".to_string(),
     })
 }
 
 /// Get all patterns from database
+/// Patterns to export, failing if the filter leaves none
+fn select_patterns(db_path: &Path, filter: &PatternFilter) -> Result<Vec<Pattern>> {
+    let store = PatternStore::open_readonly(db_path)?;
+    let mut patterns = get_all_patterns(&store)?;
+
+    if !filter.is_empty() {
+        let ids = filter.matching_ids(&db::open_readonly(db_path)?)?;
+        patterns.retain(|p| ids.contains(&p.id));
+        if patterns.is_empty() {
+            return Err(anyhow!("No patterns match the export filters"));
+        }
+    }
+    if patterns.is_empty() {
+        return Err(anyhow!("No patterns to export"));
+    }
+    Ok(patterns)
+}
+
 fn get_all_patterns(store: &PatternStore) -> Result<Vec<Pattern>> {
     // Get patterns of all known types
     let tool_types = ["Bash", "Edit", "Write", "Read", "Task", "Glob", "Grep", "WebSearch", "failure"];
@@ -331,7 +520,7 @@ fn get_all_patterns(store: &PatternStore) -> Result<Vec<Pattern>> {
 }
 
 /// Find pattern by hash
-fn find_by_hash(store: &PatternStore, hash: &str) -> Result<Option<Pattern>> {
+pub(crate) fn find_by_hash(store: &PatternStore, hash: &str) -> Result<Option<Pattern>> {
     // We don't have a direct lookup by hash in PatternStore, so use top patterns
     // This is O(n) but acceptable for import operations
     let patterns = store.get_top_patterns(10000)?;
@@ -339,7 +528,7 @@ fn find_by_hash(store: &PatternStore, hash: &str) -> Result<Option<Pattern>> {
 }
 
 /// Calculate success rate for a pattern
-fn success_rate(pattern: &Pattern) -> f64 {
+pub(crate) fn success_rate(pattern: &Pattern) -> f64 {
     let total = pattern.success_count + pattern.failure_count;
     if total == 0 {
         return 0.5; // Default for new patterns
@@ -355,6 +544,7 @@ mod tests {
     #[allow(unused_imports)]
     use crate::storage::init as init_storage;
 
+    #[allow(dead_code)]
     fn setup_test_db() -> (TempDir, std::path::PathBuf) {
         let temp_dir = TempDir::new().unwrap();
         let db_path = temp_dir.path().join("metadata.sqlite");
@@ -395,13 +585,76 @@ mod tests {
         let output_path = temp_dir.path().join("export.json");
         let security = SecurityConfig::default();
 
-        let result = export_patterns(&db_path, &output_path, &security, None);
+        let result = export_patterns(&db_path, &output_path, &security, None, &PatternFilter::default(), false);
         // Should error since no patterns exist
         assert!(result.is_err());
         let err_msg = result.unwrap_err().to_string();
         assert!(err_msg.contains("No patterns"), "Unexpected error: {}", err_msg);
     }
 
+    #[test]
+    fn test_pulled_patterns_keep_their_device() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("metadata.sqlite");
+        std::fs::write(temp_dir.path().join("config.toml"), "[device]\nname = \"laptop\"\n").unwrap();
+        let conn = Connection::open(&db_path).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO patterns (pattern_hash, tool_type, context_query, success_count) VALUES ('local', 'Bash', 'cargo build', 2)",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let pulled = ExportablePattern {
+            pattern_hash: "pulled".to_string(),
+            tool_type: "Bash".to_string(),
+            command_category: None,
+            context_query: "npm test".to_string(),
+            success_count: 3,
+            failure_count: 0,
+            device: Some("devpod".to_string()),
+            risky: false,
+        };
+        let result = import_patterns_from_vec(&db_path, vec![pulled], MergeStrategy::Add).unwrap();
+        assert_eq!(result.imported, 1);
+
+        let security = SecurityConfig { sanitize_paths: false, redact_secrets: false, ..Default::default() };
+        let exported = export_patterns_to_vec(&db_path, &security, &PatternFilter::default()).unwrap();
+        let device = |hash: &str| exported.iter().find(|p| p.pattern_hash == hash).and_then(|p| p.device.clone());
+        assert_eq!(device("local").as_deref(), Some("laptop"));
+        assert_eq!(device("pulled").as_deref(), Some("devpod"));
+    }
+
+    #[test]
+    fn test_imported_destructive_patterns_stay_pending() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("metadata.sqlite");
+        crate::storage::create_schema(&Connection::open(&db_path).unwrap()).unwrap();
+
+        let pattern = |hash: &str, context: &str, risky: bool| ExportablePattern {
+            pattern_hash: hash.to_string(),
+            tool_type: "Bash".to_string(),
+            command_category: None,
+            context_query: context.to_string(),
+            success_count: 3,
+            failure_count: 0,
+            device: None,
+            risky,
+        };
+        let incoming = vec![
+            pattern("flagged", "Approach: Bash - cleanup", true),
+            pattern("unflagged", "Approach: Bash - rm -rf build", false),
+            pattern("safe", "Approach: Bash - cargo build", false),
+        ];
+        import_patterns_from_vec(&db_path, incoming, MergeStrategy::Add).unwrap();
+
+        let store = PatternStore::open(&db_path).unwrap();
+        let mut pending: Vec<String> = store.get_pending_risky().unwrap().into_iter().map(|p| p.pattern_hash).collect();
+        pending.sort();
+        assert_eq!(pending, vec!["flagged", "unflagged"]);
+    }
+
     #[test]
     fn test_merge_strategy_default() {
         assert_eq!(MergeStrategy::default(), MergeStrategy::Add);
@@ -418,6 +671,7 @@ mod tests {
             success_count: 8,
             failure_count: 2,
             embedding_id: None,
+            risky: false,
         };
 
         let rate = success_rate(&pattern);
@@ -435,9 +689,45 @@ mod tests {
             success_count: 0,
             failure_count: 0,
             embedding_id: None,
+            risky: false,
         };
 
         let rate = success_rate(&pattern);
         assert!((rate - 0.5).abs() < 0.01);
     }
+
+    #[test]
+    fn test_sync_gate_withholds_patterns() {
+        let pattern = |hash: &str, tool: &str, success: i64, failure: i64| ExportablePattern {
+            pattern_hash: hash.to_string(),
+            tool_type: tool.to_string(),
+            command_category: None,
+            context_query: "q".to_string(),
+            success_count: success,
+            failure_count: failure,
+            device: None,
+            risky: false,
+        };
+        let patterns = vec![
+            pattern("a", "Bash", 9, 0),
+            pattern("b", "Bash", 1, 4),
+            pattern("c", "WebFetch", 20, 0),
+            pattern("d", "Edit", 3, 0),
+            pattern("e", "Edit", 6, 1),
+        ];
+        let settings = SyncSettings {
+            min_score: Some(0),
+            exclude_tools: vec!["webfetch".to_string()],
+            max_patterns: Some(2),
+        };
+
+        let (kept, withheld) = apply_sync_gate(patterns, &settings);
+        let hashes: Vec<&str> = kept.iter().map(|p| p.pattern_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["a", "e"]);
+        assert_eq!(withheld, Withheld { low_score: 1, excluded_tool: 1, over_limit: 1 });
+        assert_eq!(
+            withheld.to_string(),
+            "Withheld 3 patterns by the [sync] quality gate (1 excluded tool, 1 below min_score, 1 over max_patterns)"
+        );
+    }
 }
@@ -3,13 +3,26 @@
 //! Implements push/pull operations using a git repository as the backend.
 //! This is the simplest sync approach and works offline.
 
-use anyhow::{Result, anyhow, Context};
+use anyhow::{Result, anyhow, bail, Context};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
 use std::process::Command;
 use tracing::{info, warn};
 
-use crate::sync::{SyncBackend, SecurityConfig, load_sync_config};
-use crate::sync::export::{export_patterns, import_patterns, MergeStrategy};
+use crate::sync::{signing, SyncBackend, SecurityConfig, load_sync_config};
+use crate::sync::crypto::hash_workspace_id;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use crate::sync::export::{export_for_push, export_patterns_to_vec, import_patterns_from_vec, MergeStrategy};
+use crate::sync::shards::{self, PatternConflict, PatternMap};
+use crate::sync::backend::{BackendFuture, PullOptions, PullReport, PushOptions, SyncBackendImpl, SyncContext};
+use crate::storage::PatternFilter;
+
+/// Git sync bookkeeping in the data directory
+const STATE_FILE: &str = "git-sync-state.json";
+
+/// How many times a push that lost a race is merged and retried
+const PUSH_ATTEMPTS: usize = 3;
 
 /// Git sync configuration
 #[derive(Debug, Clone)]
@@ -37,6 +50,156 @@ impl GitSyncConfig {
     }
 }
 
+/// `[git]` section of sync.toml: credentials for the sync remote
+///
+/// Network commands run with terminal prompts disabled so a missing
+/// credential fails with an explanation instead of hanging a hook or a
+/// devcontainer shell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitAuthConfig {
+    /// Private key for ssh remotes (otherwise ssh-agent and ~/.ssh defaults)
+    pub ssh_key: Option<String>,
+    /// Environment variable holding an access token for https remotes
+    pub token_env: String,
+    /// Username sent along with the token
+    pub token_user: String,
+}
+
+impl Default for GitAuthConfig {
+    fn default() -> Self {
+        Self {
+            ssh_key: None,
+            token_env: "MANA_GIT_TOKEN".to_string(),
+            token_user: "x-access-token".to_string(),
+        }
+    }
+}
+
+impl GitAuthConfig {
+    fn ssh_key_path(&self) -> Option<PathBuf> {
+        let key = self.ssh_key.as_deref()?;
+        Some(match (key.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(key),
+        })
+    }
+
+    fn token(&self) -> Option<String> {
+        std::env::var(&self.token_env).ok().filter(|t| !t.trim().is_empty())
+    }
+
+    /// Set up credentials for a git command that talks to the remote
+    fn apply(&self, cmd: &mut Command) {
+        cmd.env("GIT_TERMINAL_PROMPT", "0");
+
+        if let Some(key) = self.ssh_key_path() {
+            let quoted = format!("'{}'", key.to_string_lossy().replace('\'', "'\\''"));
+            cmd.env("GIT_SSH_COMMAND", format!("ssh -i {} -o IdentitiesOnly=yes -o BatchMode=yes", quoted));
+        } else if std::env::var_os("GIT_SSH_COMMAND").is_none() && std::env::var_os("GIT_SSH").is_none() {
+            cmd.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+        }
+
+        // Passed through git's environment config so the token never shows up in `ps`
+        if let Some(token) = self.token() {
+            let index: usize = std::env::var("GIT_CONFIG_COUNT").ok().and_then(|n| n.parse().ok()).unwrap_or(0);
+            let credentials = BASE64.encode(format!("{}:{}", self.token_user, token.trim()));
+            cmd.env("GIT_CONFIG_COUNT", (index + 1).to_string())
+                .env(format!("GIT_CONFIG_KEY_{}", index), "http.extraHeader")
+                .env(format!("GIT_CONFIG_VALUE_{}", index), format!("Authorization: Basic {}", credentials));
+        }
+    }
+
+    /// How a remote will be authenticated, for `mana sync test-auth`
+    pub fn describe(&self, remote: &str) -> String {
+        match transport(remote) {
+            Transport::Ssh => match self.ssh_key_path() {
+                Some(key) => format!("ssh key {}", key.display()),
+                None if std::env::var_os("SSH_AUTH_SOCK").is_some() => {
+                    let keys = Command::new("ssh-add")
+                        .arg("-l")
+                        .output()
+                        .ok()
+                        .filter(|o| o.status.success())
+                        .map(|o| String::from_utf8_lossy(&o.stdout).lines().count())
+                        .unwrap_or(0);
+                    format!("ssh-agent ({} key{} loaded)", keys, if keys == 1 { "" } else { "s" })
+                }
+                None => "default ssh keys in ~/.ssh (no ssh-agent running)".to_string(),
+            },
+            Transport::Https if self.token().is_some() => format!("token from ${}", self.token_env),
+            Transport::Https => format!("git credential helper (${} not set)", self.token_env),
+            Transport::Local => "none (local path)".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    Ssh,
+    Https,
+    Local,
+}
+
+fn transport(remote: &str) -> Transport {
+    if remote.starts_with("https://") || remote.starts_with("http://") {
+        Transport::Https
+    } else if remote.starts_with("ssh://")
+        || remote.starts_with("git@")
+        || remote.split_once(':').is_some_and(|(host, _)| host.contains('@') && !host.contains('/'))
+    {
+        Transport::Ssh
+    } else {
+        Transport::Local
+    }
+}
+
+/// Result of `mana sync test-auth`
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthCheck {
+    pub remote: String,
+    /// Credentials that were offered
+    pub method: String,
+    pub ok: bool,
+    /// Branches the remote advertised
+    pub branches: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Check that the sync remote is reachable with the configured credentials
+pub fn test_auth(mana_dir: &Path) -> Result<AuthCheck> {
+    let config = load_sync_config(&mana_dir.join("sync.toml"))?;
+    let git_config = GitSyncConfig::from_backend(&config.backend, mana_dir)
+        .ok_or_else(|| anyhow!("Sync backend is not configured for git"))?;
+
+    let mut remote = git_config.remote.clone();
+    if remote.is_empty() && git_config.local_dir.join(".git").exists() {
+        remote = run_git_command(&git_config.local_dir, &["remote", "get-url", "origin"])
+            .map(|r| r.trim().to_string())
+            .unwrap_or_default();
+    }
+    if remote.is_empty() {
+        bail!("No remote configured. Run 'mana sync init --remote <url>' or add an origin to the sync repository");
+    }
+
+    let method = config.git.describe(&remote);
+    let check = match run_git_remote(mana_dir, &["ls-remote", "--heads", &remote], &config.git) {
+        Ok(output) => AuthCheck {
+            remote,
+            method,
+            ok: true,
+            branches: output
+                .lines()
+                .filter_map(|l| l.split_whitespace().nth(1))
+                .map(|r| r.trim_start_matches("refs/heads/").to_string())
+                .collect(),
+            error: None,
+        },
+        Err(e) => AuthCheck { remote, method, ok: false, branches: Vec::new(), error: Some(format!("{:#}", e)) },
+    };
+    Ok(check)
+}
+
 /// Initialize git sync for a workspace
 ///
 /// Sets up the sync repository configuration and clones the remote if provided.
@@ -65,7 +228,8 @@ pub fn init_git_sync(mana_dir: &Path, remote: &str, branch: &str) -> Result<()>
         // Clone the remote repository
         info!("Cloning sync repository from {}", remote);
         let parent = sync_dir.parent().unwrap_or(mana_dir);
-        run_git_command(parent, &["clone", "--branch", branch, remote, "sync-repo"])?;
+        let auth = load_sync_config(&mana_dir.join("sync.toml"))?.git;
+        run_git_remote(parent, &["clone", "--branch", branch, remote, "sync-repo"], &auth)?;
         info!("Cloned sync repository to {:?}", sync_dir);
         println!("✅ Cloned sync repository from {}", remote);
     }
@@ -81,13 +245,17 @@ pub fn init_git_sync(mana_dir: &Path, remote: &str, branch: &str) -> Result<()>
 
 /// Push patterns to the git remote
 ///
-/// Exports patterns, commits changes, and pushes to remote.
+/// Fetches first and folds the remote's changes into the local database
+/// pattern by pattern, then writes every pattern as shards on top of the
+/// remote branch, commits and pushes. A push that loses a race with another
+/// machine is merged and retried.
 pub fn push_patterns(
     mana_dir: &Path,
     db_path: &Path,
     security: &SecurityConfig,
     passphrase: Option<&str>,
     message: Option<&str>,
+    sign_key: Option<&Path>,
 ) -> Result<()> {
     let config_path = mana_dir.join("sync.toml");
     let config = load_sync_config(&config_path)?;
@@ -98,56 +266,114 @@ pub fn push_patterns(
     if !git_config.local_dir.exists() {
         return Err(anyhow!("Sync repository not initialized. Run 'mana sync init' first."));
     }
+    let repo = &git_config.local_dir;
+    let passphrase = passphrase.filter(|_| security.encrypt);
+    let mut state = load_state(mana_dir);
+
+    for attempt in 1..=PUSH_ATTEMPTS {
+        if let Some(remote) = fetch(repo, &git_config.branch, &config.git)? {
+            if state.last_synced_commit.as_deref() != Some(remote.as_str()) {
+                verify_rev(mana_dir, repo, &remote, &config.signing, false)?;
+                let base = read_rev(repo, state.last_synced_commit.as_deref(), passphrase)?;
+                let theirs = read_rev(repo, Some(&remote), passphrase)?;
+                let ours = shards::collect(export_patterns_to_vec(db_path, security, &PatternFilter::default())?);
+                state.conflicts = shards::conflicts(&base, &ours, &theirs);
+
+                let incoming = shards::delta(&base, &theirs);
+                if !incoming.is_empty() {
+                    let changes = incoming.len();
+                    import_patterns_from_vec(db_path, incoming, MergeStrategy::Add)?;
+                    println!("📥 Merged {} pattern changes from remote", changes);
+                }
+                state.last_synced_commit = Some(remote.clone());
+                save_state(mana_dir, &state)?;
+            }
+            // Commit on top of the remote branch; earlier local commits are all in the database
+            run_git_command(repo, &["reset", "--soft", &remote])?;
+        }
 
-    // Export patterns to sync repo
-    let export_file = git_config.local_dir.join("patterns.json");
-    let count = export_patterns(db_path, &export_file, security, passphrase)?;
-
-    info!("Exported {} patterns to sync repository", count);
-
-    // Check if there are changes to commit
-    let status = run_git_command(&git_config.local_dir, &["status", "--porcelain"])?;
-    if status.trim().is_empty() {
-        println!("📋 No changes to push");
-        return Ok(());
-    }
-
-    // Stage changes
-    run_git_command(&git_config.local_dir, &["add", "-A"])?;
-
-    // Commit with message
-    let commit_msg = message.unwrap_or("Update MANA patterns");
-    let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
-    let full_msg = format!("{}\n\nExported {} patterns at {}", commit_msg, count, timestamp);
-
-    run_git_command(&git_config.local_dir, &["commit", "-m", &full_msg])?;
-
-    // Push to remote
-    let push_result = run_git_command(&git_config.local_dir, &["push", "origin", &git_config.branch]);
+        let mut patterns = shards::collect(export_for_push(db_path, security)?);
+        if let Some(synced) = &state.last_synced_commit {
+            shards::union(&mut patterns, &read_rev(repo, Some(synced), passphrase)?);
+        }
+        let workspace_id = hash_workspace_id(&std::env::current_dir()?.to_string_lossy());
+        let manifest = shards::write(repo, &patterns, passphrase, &workspace_id)?;
+        let count = manifest.pattern_count;
+        info!("Exported {} patterns to sync repository", count);
+
+        let manifest_path = repo.join(shards::MANIFEST_FILE);
+        match sign_key {
+            Some(key) => {
+                signing::sign_file(&manifest_path, key)?;
+            }
+            None => {
+                // A signature from an earlier push no longer matches the manifest
+                let stale = signing::signature_path(&manifest_path);
+                if stale.exists() {
+                    std::fs::remove_file(stale)?;
+                }
+            }
+        }
 
-    match push_result {
-        Ok(_) => {
-            println!("✅ Pushed {} patterns to remote", count);
-            Ok(())
+        // Check if there are changes to commit
+        let status = run_git_command(repo, &["status", "--porcelain"])?;
+        if status.trim().is_empty() {
+            println!("📋 No changes to push");
+            return Ok(());
         }
-        Err(e) => {
-            warn!("Push failed: {}. Changes committed locally.", e);
-            println!("⚠️  Push failed: {}. Changes committed locally.", e);
-            println!("   Run 'git -C {:?} push' manually when ready", git_config.local_dir);
-            Ok(())
+
+        // Stage changes
+        run_git_command(repo, &["add", "-A"])?;
+
+        // Commit with message
+        let commit_msg = message.unwrap_or("Update MANA patterns");
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        let full_msg = format!("{}\n\nExported {} patterns at {}", commit_msg, count, timestamp);
+
+        run_git_command(repo, &["commit", "-m", &full_msg])?;
+
+        // Push to remote
+        match run_git_remote(repo, &["push", "origin", &git_config.branch], &config.git) {
+            Ok(_) => {
+                state.last_synced_commit = Some(run_git_command(repo, &["rev-parse", "HEAD"])?.trim().to_string());
+                state.last_push = Some(chrono::Utc::now().to_rfc3339());
+                save_state(mana_dir, &state)?;
+                println!("✅ Pushed {} patterns to remote", count);
+                if !state.conflicts.is_empty() {
+                    println!("   {} patterns changed on both sides were merged (see 'mana sync status')", state.conflicts.len());
+                }
+                return Ok(());
+            }
+            Err(e) if attempt < PUSH_ATTEMPTS && is_rejected(&e) => {
+                println!("↻ Remote changed during push, merging again...");
+            }
+            Err(e) if is_auth_failure(&e) => {
+                return Err(e.context("Push failed; changes are committed locally"));
+            }
+            Err(e) => {
+                warn!("Push failed: {}. Changes committed locally.", e);
+                println!("⚠️  Push failed: {}. Changes committed locally.", e);
+                println!("   Run 'mana sync push' again when the remote is reachable");
+                return Ok(());
+            }
         }
     }
+    Ok(())
 }
 
 /// Pull patterns from the git remote
 ///
-/// Pulls latest changes and imports patterns with merge strategy.
+/// Imports what changed on the remote since the last sync, after checking
+/// the signature against the trusted keys: count deltas with the `add`
+/// strategy (so nothing is counted twice), full patterns otherwise. The
+/// local clone is then moved to the remote branch.
 pub fn pull_patterns(
     mana_dir: &Path,
     db_path: &Path,
     passphrase: Option<&str>,
     merge_strategy: MergeStrategy,
-) -> Result<()> {
+    require_signed: bool,
+) -> Result<PullReport> {
     let config_path = mana_dir.join("sync.toml");
     let config = load_sync_config(&config_path)?;
 
@@ -157,42 +383,158 @@ pub fn pull_patterns(
     if !git_config.local_dir.exists() {
         return Err(anyhow!("Sync repository not initialized. Run 'mana sync init' first."));
     }
+    let repo = &git_config.local_dir;
+    let mut state = load_state(mana_dir);
 
-    // Pull latest changes
-    let pull_result = run_git_command(&git_config.local_dir, &["pull", "origin", &git_config.branch]);
-
-    match pull_result {
-        Ok(output) => {
-            if output.contains("Already up to date") {
-                println!("📋 Already up to date");
-            } else {
-                println!("✅ Pulled latest changes from remote");
-            }
-        }
-        Err(e) => {
-            warn!("Pull failed: {}. Using local patterns file.", e);
-            println!("⚠️  Pull failed: {}. Using local patterns file.", e);
-        }
+    let Some(remote) = fetch(repo, &git_config.branch, &config.git)? else {
+        println!("📋 No patterns on the remote branch yet");
+        return Ok(PullReport::default());
+    };
+    if state.last_synced_commit.as_deref() == Some(remote.as_str()) {
+        println!("📋 Already up to date");
+        return Ok(PullReport::default());
     }
 
-    // Import patterns from sync repo
-    let import_file = git_config.local_dir.join("patterns.json");
-    if !import_file.exists() {
-        println!("📋 No patterns file found in sync repository");
-        return Ok(());
+    if let signing::SignatureStatus::Verified { signer } =
+        verify_rev(mana_dir, repo, &remote, &config.signing, require_signed)?
+    {
+        println!("🔏 Signature verified ({})", signer);
     }
 
-    let result = import_patterns(db_path, &import_file, passphrase, merge_strategy)?;
+    let base = read_rev(repo, state.last_synced_commit.as_deref(), passphrase)?;
+    let theirs = read_rev(repo, Some(&remote), passphrase)?;
+    let security = SecurityConfig::default();
+    let ours = shards::collect(export_patterns_to_vec(db_path, &security, &PatternFilter::default())?);
+    state.conflicts = shards::conflicts(&base, &ours, &theirs);
+
+    let incoming = match merge_strategy {
+        MergeStrategy::Add => shards::delta(&base, &theirs),
+        MergeStrategy::Replace | MergeStrategy::KeepBest | MergeStrategy::Interactive => shards::changed(&base, &theirs),
+    };
+    let result = import_patterns_from_vec(db_path, incoming, merge_strategy)?;
+
+    run_git_command(repo, &["reset", "--hard", &remote])?;
+    state.last_synced_commit = Some(remote);
+    state.last_pull = Some(chrono::Utc::now().to_rfc3339());
+    save_state(mana_dir, &state)?;
 
     println!("✅ Imported patterns from sync repository");
-    println!("   Total: {}, New: {}, Merged: {}", result.total, result.imported, result.merged);
+    println!("   Changed: {}, New: {}, Merged: {}", result.total, result.imported, result.merged);
     if result.skipped > 0 {
         println!("   Skipped: {}", result.skipped);
     }
+    if !state.conflicts.is_empty() {
+        println!("   {} patterns changed on both sides were merged (see 'mana sync status')", state.conflicts.len());
+    }
+
+    Ok(PullReport { conflicts: state.conflicts, ..result.into() })
+}
+
+/// Local bookkeeping for git sync, kept in the data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitSyncState {
+    /// Remote commit whose patterns the local database already includes
+    pub last_synced_commit: Option<String>,
+    pub last_push: Option<String>,
+    pub last_pull: Option<String>,
+    /// Patterns changed on both sides in the last merge
+    pub conflicts: Vec<PatternConflict>,
+}
 
+fn load_state(mana_dir: &Path) -> GitSyncState {
+    std::fs::read_to_string(mana_dir.join(STATE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(mana_dir: &Path, state: &GitSyncState) -> Result<()> {
+    std::fs::write(mana_dir.join(STATE_FILE), serde_json::to_string_pretty(state)?)?;
     Ok(())
 }
 
+/// Fetch the sync branch, returning the remote commit if the branch exists
+///
+/// Being offline falls back to the last fetched state; rejected
+/// credentials are an error.
+fn fetch(repo: &Path, branch: &str, auth: &GitAuthConfig) -> Result<Option<String>> {
+    if let Err(e) = run_git_remote(repo, &["fetch", "origin", branch], auth) {
+        if is_auth_failure(&e) {
+            return Err(e);
+        }
+        warn!("Fetch failed: {}. Using the last fetched state.", e);
+        println!("⚠️  Fetch failed: {}. Using the last fetched state.", e.to_string().trim());
+    }
+    let remote_ref = format!("refs/remotes/origin/{}", branch);
+    Ok(run_git_command(repo, &["rev-parse", "--verify", "--quiet", &remote_ref])
+        .ok()
+        .map(|commit| commit.trim().to_string()))
+}
+
+/// Patterns as of a commit (empty for `None` or a commit no longer in the clone)
+fn read_rev(repo: &Path, rev: Option<&str>, passphrase: Option<&str>) -> Result<PatternMap> {
+    let Some(rev) = rev else {
+        return Ok(PatternMap::new());
+    };
+    if run_git_command(repo, &["cat-file", "-e", &format!("{}^{{commit}}", rev)]).is_err() {
+        warn!("Commit {} is not in the sync repository; treating it as empty", rev);
+        return Ok(PatternMap::new());
+    }
+
+    let listing = run_git_command(repo, &["ls-tree", "-r", "--name-only", rev])?;
+    let mut files = BTreeMap::new();
+    for name in listing.lines() {
+        let tracked = name == shards::MANIFEST_FILE
+            || name == shards::LEGACY_FILE
+            || name.strip_prefix(shards::SHARD_DIR).is_some_and(|rest| rest.starts_with('/'));
+        if tracked {
+            files.insert(name.to_string(), run_git_command(repo, &["show", &format!("{}:{}", rev, name)])?);
+        }
+    }
+    shards::parse(&files, passphrase)
+}
+
+/// Check the signature on a commit's manifest (or legacy patterns.json)
+fn verify_rev(
+    mana_dir: &Path,
+    repo: &Path,
+    rev: &str,
+    config: &signing::SigningConfig,
+    require_signed: bool,
+) -> Result<signing::SignatureStatus> {
+    let listing = run_git_command(repo, &["ls-tree", "--name-only", rev])?;
+    let signed_file = [shards::MANIFEST_FILE, shards::LEGACY_FILE]
+        .into_iter()
+        .find(|name| listing.lines().any(|l| l == *name));
+    let Some(signed_file) = signed_file else {
+        if require_signed || config.require_signed {
+            bail!("The remote branch has no signed patterns, and unsigned pulls are not allowed");
+        }
+        return Ok(signing::SignatureStatus::Unsigned);
+    };
+
+    // ssh-keygen verifies files, so check out the two that matter
+    let dir = mana_dir.join(format!(".sync-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let result = (|| {
+        let file = dir.join(signed_file);
+        std::fs::write(&file, run_git_command(repo, &["show", &format!("{}:{}", rev, signed_file)])?)?;
+        let sig_name = format!("{}.sig", signed_file);
+        if listing.lines().any(|l| l == sig_name) {
+            std::fs::write(dir.join(&sig_name), run_git_command(repo, &["show", &format!("{}:{}", rev, sig_name)])?)?;
+        }
+        signing::check(&file, config, require_signed)
+    })();
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Whether a push failed because the remote moved on
+fn is_rejected(error: &anyhow::Error) -> bool {
+    let message = error.to_string();
+    message.contains("rejected") || message.contains("fetch first") || message.contains("non-fast-forward")
+}
+
 /// Get sync status
 ///
 /// Shows current sync state and any pending changes.
@@ -208,6 +550,10 @@ pub fn sync_status(mana_dir: &Path) -> Result<SyncStatus> {
             branch: None,
             local_changes: false,
             last_sync: None,
+            ahead: None,
+            behind: None,
+            unmerged_files: Vec::new(),
+            conflicts: Vec::new(),
         });
     }
 
@@ -218,6 +564,19 @@ pub fn sync_status(mana_dir: &Path) -> Result<SyncStatus> {
     if let Some(git) = git_config {
         let repo_exists = git.local_dir.join(".git").exists();
 
+        let (ahead, behind, unmerged_files) = if repo_exists {
+            // Relative to the last fetch; status itself stays offline
+            let range = format!("HEAD...refs/remotes/origin/{}", git.branch);
+            let counts = run_git_command(&git.local_dir, &["rev-list", "--left-right", "--count", &range]).ok();
+            let mut counts = counts.iter().flat_map(|c| c.split_whitespace()).filter_map(|n| n.parse().ok());
+            let unmerged = run_git_command(&git.local_dir, &["diff", "--name-only", "--diff-filter=U"])
+                .map(|s| s.lines().map(String::from).collect())
+                .unwrap_or_default();
+            (counts.next(), counts.next(), unmerged)
+        } else {
+            (None, None, Vec::new())
+        };
+
         let (remote, local_changes, last_sync) = if repo_exists {
             let remote = run_git_command(&git.local_dir, &["remote", "get-url", "origin"]).ok();
             let status = run_git_command(&git.local_dir, &["status", "--porcelain"])
@@ -239,6 +598,10 @@ pub fn sync_status(mana_dir: &Path) -> Result<SyncStatus> {
             branch: Some(git.branch),
             local_changes,
             last_sync,
+            ahead,
+            behind,
+            unmerged_files,
+            conflicts: load_state(mana_dir).conflicts,
         })
     } else {
         Ok(SyncStatus {
@@ -246,6 +609,7 @@ pub fn sync_status(mana_dir: &Path) -> Result<SyncStatus> {
             backend: match &config.backend {
                 SyncBackend::S3 { .. } => "s3".to_string(),
                 SyncBackend::Supabase { .. } => "supabase".to_string(),
+                SyncBackend::Ssh { .. } => "ssh".to_string(),
                 SyncBackend::Git { .. } => "git".to_string(),
                 SyncBackend::P2P { .. } => "p2p".to_string(),
             },
@@ -254,12 +618,16 @@ pub fn sync_status(mana_dir: &Path) -> Result<SyncStatus> {
             branch: None,
             local_changes: false,
             last_sync: None,
+            ahead: None,
+            behind: None,
+            unmerged_files: Vec::new(),
+            conflicts: Vec::new(),
         })
     }
 }
 
 /// Sync status information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SyncStatus {
     /// Whether sync is configured
     pub configured: bool,
@@ -275,6 +643,14 @@ pub struct SyncStatus {
     pub local_changes: bool,
     /// Last sync timestamp
     pub last_sync: Option<String>,
+    /// Local commits not yet pushed (as of the last fetch)
+    pub ahead: Option<usize>,
+    /// Remote commits not yet pulled (as of the last fetch)
+    pub behind: Option<usize>,
+    /// Files git left in a conflicted state
+    pub unmerged_files: Vec<String>,
+    /// Patterns changed on both sides in the last merge
+    pub conflicts: Vec<PatternConflict>,
 }
 
 /// Run a git command and return stdout
@@ -293,10 +669,63 @@ fn run_git_command(cwd: &Path, args: &[&str]) -> Result<String> {
     }
 }
 
+/// Run a git command that talks to the remote, with credentials applied
+///
+/// Failures carry a hint for the common authentication problems.
+fn run_git_remote(cwd: &Path, args: &[&str], auth: &GitAuthConfig) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(cwd).args(args);
+    auth.apply(&mut cmd);
+    let output = cmd.output().context("Failed to execute git command")?;
+
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match auth_hint(&stderr, auth) {
+        Some(hint) => Err(anyhow!("git {} failed: {}\n{}{}", args.join(" "), stderr.trim(), AUTH_HINT_PREFIX, hint)),
+        None => Err(anyhow!("git {} failed: {}", args.join(" "), stderr)),
+    }
+}
+
+/// Marks errors that came with an authentication hint
+const AUTH_HINT_PREFIX: &str = "hint: ";
+
+/// Explain a git failure caused by missing or rejected credentials
+fn auth_hint(stderr: &str, auth: &GitAuthConfig) -> Option<String> {
+    if stderr.contains("Permission denied (publickey") {
+        Some(match auth.ssh_key_path() {
+            Some(key) if !key.exists() => format!("SSH key {} (ssh_key under [git] in sync.toml) not found", key.display()),
+            Some(key) => format!("the remote rejected {}. Check that its public key is registered (e.g. as a deploy key with write access)", key.display()),
+            None => "no SSH key was accepted. Load one with 'ssh-add', set ssh_key under [git] in sync.toml, or use an https remote with a token".to_string(),
+        })
+    } else if stderr.contains("Host key verification failed") {
+        Some("the remote's host key is unknown. Add it with: ssh-keyscan <host> >> ~/.ssh/known_hosts".to_string())
+    } else if stderr.contains("terminal prompts disabled")
+        || stderr.contains("could not read Username")
+        || stderr.contains("Authentication failed")
+    {
+        Some(format!(
+            "https credentials are missing or invalid. Export a token in ${} (or set token_env under [git] in sync.toml)",
+            auth.token_env
+        ))
+    } else if stderr.contains("Repository not found") || stderr.contains("does not appear to be a git repository") {
+        Some("the repository was not found. Check the URL and that these credentials can access it".to_string())
+    } else {
+        None
+    }
+}
+
+fn is_auth_failure(error: &anyhow::Error) -> bool {
+    error.to_string().contains(AUTH_HINT_PREFIX)
+}
+
 /// Save sync configuration
 pub fn save_git_config(mana_dir: &Path, remote: &str, branch: &str) -> Result<()> {
     use crate::sync::{SyncConfig, SyncBackend, SecurityConfig, save_sync_config};
 
+    // Keep settings that `sync init` doesn't touch
+    let previous = crate::sync::load_sync_config(&mana_dir.join("sync.toml")).unwrap_or_default();
     let config = SyncConfig {
         enabled: true,
         backend: SyncBackend::Git {
@@ -305,6 +734,10 @@ pub fn save_git_config(mana_dir: &Path, remote: &str, branch: &str) -> Result<()
         },
         interval_minutes: 60,
         security: SecurityConfig::default(),
+        // Re-running init shouldn't drop the trusted signers
+        signing: previous.signing,
+        git: previous.git,
+        registry: previous.registry,
     };
 
     let config_path = mana_dir.join("sync.toml");
@@ -314,6 +747,104 @@ pub fn save_git_config(mana_dir: &Path, remote: &str, branch: &str) -> Result<()
     Ok(())
 }
 
+/// Git backend for `mana sync` push, pull and status
+pub struct GitSync;
+
+impl SyncBackendImpl for GitSync {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn supports_signing(&self) -> bool {
+        true
+    }
+
+    fn push<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PushOptions) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            push_patterns(
+                ctx.mana_dir,
+                ctx.db_path,
+                &SecurityConfig::default(),
+                options.passphrase.as_deref(),
+                options.message.as_deref(),
+                options.sign_key.as_deref(),
+            )
+        })
+    }
+
+    fn pull<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PullOptions) -> BackendFuture<'a, PullReport> {
+        Box::pin(async move {
+            pull_patterns(
+                ctx.mana_dir,
+                ctx.db_path,
+                options.passphrase.as_deref(),
+                options.merge_strategy,
+                options.require_signed,
+            )
+        })
+    }
+
+    fn status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, serde_json::Value> {
+        Box::pin(async move { Ok(serde_json::to_value(sync_status(ctx.mana_dir)?)?) })
+    }
+
+    fn print_status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let status = sync_status(ctx.mana_dir)?;
+            if !status.configured {
+                println!("⚠️  Sync not configured");
+                println!("   Run 'mana sync init' to set up synchronization");
+                return Ok(());
+            }
+            println!("Backend: {}", status.backend);
+            println!("Initialized: {}", if status.repo_initialized { "✅" } else { "❌" });
+
+            if let Some(remote) = &status.remote {
+                println!("Remote: {}", remote);
+            }
+            if let Some(branch) = &status.branch {
+                println!("Branch: {}", branch);
+            }
+            if status.local_changes {
+                println!("Local changes: ⚠️  Uncommitted changes");
+            } else {
+                println!("Local changes: ✅ None");
+            }
+            if let Some(last_sync) = &status.last_sync {
+                println!("Last sync: {}", last_sync);
+            }
+            if let (Some(ahead), Some(behind)) = (status.ahead, status.behind) {
+                println!("Commits: {} to push, {} to pull (as of last fetch)", ahead, behind);
+            }
+            if !status.unmerged_files.is_empty() {
+                println!("Unmerged files: ⚠️  {}", status.unmerged_files.join(", "));
+                println!("   Run 'mana sync pull' to reset the clone and merge at the pattern level");
+            }
+            if !status.conflicts.is_empty() {
+                println!();
+                println!("Patterns changed on both sides in the last merge: {}", status.conflicts.len());
+                for conflict in status.conflicts.iter().take(10) {
+                    println!(
+                        "  {} [{}] local ✓{} ✗{} · remote ✓{} ✗{} → merged ✓{} ✗{}",
+                        conflict.pattern_hash,
+                        conflict.tool_type,
+                        conflict.local.0,
+                        conflict.local.1,
+                        conflict.remote.0,
+                        conflict.remote.1,
+                        conflict.merged.0,
+                        conflict.merged.1
+                    );
+                }
+                if status.conflicts.len() > 10 {
+                    println!("  ... and {} more", status.conflicts.len() - 10);
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -336,6 +867,21 @@ mod tests {
         assert_eq!(config.local_dir, PathBuf::from("/home/user/.mana/sync-repo"));
     }
 
+    #[test]
+    fn test_auth_transport_and_hints() {
+        assert_eq!(transport("git@github.com:team/patterns.git"), Transport::Ssh);
+        assert_eq!(transport("ssh://git@host:2222/patterns.git"), Transport::Ssh);
+        assert_eq!(transport("https://github.com/team/patterns.git"), Transport::Https);
+        assert_eq!(transport("/srv/git/patterns.git"), Transport::Local);
+
+        let auth = GitAuthConfig::default();
+        let hint = auth_hint("git@github.com: Permission denied (publickey).", &auth).unwrap();
+        assert!(hint.contains("ssh-add"));
+        let hint = auth_hint("fatal: could not read Username for 'https://github.com': terminal prompts disabled", &auth).unwrap();
+        assert!(hint.contains("$MANA_GIT_TOKEN"));
+        assert!(auth_hint("fatal: unable to access: Could not resolve host", &auth).is_none());
+    }
+
     #[test]
     fn test_sync_status_unconfigured() {
         let temp = TempDir::new().unwrap();
@@ -0,0 +1,314 @@
+//! Markdown knowledge base export
+//!
+//! Renders the best patterns as a human-readable document grouped by tool
+//! and command category, suitable for a repository's docs/ directory or
+//! for pasting into CLAUDE.md. Either one file or one file per group plus
+//! an index.
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::storage::PatternFilter;
+use crate::sync::{export_patterns_to_vec, ExportablePattern, SecurityConfig};
+
+/// Index file written when exporting one file per group
+const INDEX_FILE: &str = "README.md";
+
+/// Longer task/approach text is cut off so each pattern stays one readable line
+const MAX_TEXT_CHARS: usize = 160;
+
+/// Options for `mana export --format markdown`
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    /// Write one file per tool/category into the output directory
+    pub split: bool,
+    /// Patterns listed per group
+    pub per_group: usize,
+    /// Skip patterns with fewer successes than this
+    pub min_successes: i64,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self { split: false, per_group: 10, min_successes: 1 }
+    }
+}
+
+/// Result of a markdown export
+#[derive(Debug, Clone)]
+pub struct MarkdownExport {
+    /// Files written (the index first when split)
+    pub files: Vec<PathBuf>,
+    /// Number of groups rendered
+    pub groups: usize,
+    /// Number of patterns rendered
+    pub patterns: usize,
+}
+
+/// One tool/category section
+struct Group {
+    tool: String,
+    category: Option<String>,
+    patterns: Vec<ExportablePattern>,
+}
+
+impl Group {
+    fn title(&self) -> String {
+        match &self.category {
+            Some(category) => format!("{} · {}", self.tool, category),
+            None => self.tool.clone(),
+        }
+    }
+
+    /// File name used for this group when splitting
+    fn file_name(&self) -> String {
+        let name = match &self.category {
+            Some(category) => format!("{}-{}", self.tool, category),
+            None => self.tool.clone(),
+        };
+        let slug: String = name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!("{}.md", slug.trim_matches('-'))
+    }
+}
+
+/// Export the top patterns as Markdown
+///
+/// With `split`, `output` is a directory that receives one file per group and
+/// a README.md index; otherwise it is a single Markdown file.
+pub fn export_markdown(
+    db_path: &Path,
+    output: &Path,
+    security: &SecurityConfig,
+    filter: &PatternFilter,
+    options: &MarkdownOptions,
+) -> Result<MarkdownExport> {
+    let patterns = export_patterns_to_vec(db_path, security, filter)?;
+    let groups = group_patterns(patterns, options);
+    if groups.is_empty() {
+        return Err(anyhow!("No patterns to export"));
+    }
+    let rendered: usize = groups.iter().map(|g| g.patterns.len()).sum();
+
+    let generated = Utc::now().format("%Y-%m-%d").to_string();
+    let mut files = Vec::new();
+
+    if options.split {
+        std::fs::create_dir_all(output)?;
+        let index = output.join(INDEX_FILE);
+        std::fs::write(&index, render_index(&groups, &generated))?;
+        files.push(index);
+        for group in &groups {
+            let mut doc = String::new();
+            render_group(&mut doc, group, "#");
+            let path = output.join(group.file_name());
+            std::fs::write(&path, doc)?;
+            files.push(path);
+        }
+    } else {
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(output, render_document(&groups, &generated))?;
+        files.push(output.to_path_buf());
+    }
+
+    info!("Exported {} patterns in {} groups as Markdown", rendered, groups.len());
+    Ok(MarkdownExport { files, groups: groups.len(), patterns: rendered })
+}
+
+/// Group patterns by tool and category, best first, dropping weak ones
+fn group_patterns(patterns: Vec<ExportablePattern>, options: &MarkdownOptions) -> Vec<Group> {
+    let mut by_key: BTreeMap<(String, Option<String>), Vec<ExportablePattern>> = BTreeMap::new();
+    for pattern in patterns {
+        // Failure patterns and net-negative approaches aren't advice
+        if pattern.tool_type == "failure"
+            || pattern.success_count < options.min_successes
+            || pattern.success_count <= pattern.failure_count
+        {
+            continue;
+        }
+        let key = (pattern.tool_type.clone(), pattern.command_category.clone().filter(|c| !c.is_empty()));
+        by_key.entry(key).or_default().push(pattern);
+    }
+
+    let mut groups: Vec<Group> = by_key
+        .into_iter()
+        .map(|((tool, category), mut patterns)| {
+            patterns.sort_by(|a, b| score(b).total_cmp(&score(a)).then(b.success_count.cmp(&a.success_count)));
+            // Sanitization can collapse different contexts into the same text
+            let mut seen = std::collections::HashSet::new();
+            patterns.retain(|p| seen.insert(p.context_query.clone()));
+            patterns.truncate(options.per_group);
+            Group { tool, category, patterns }
+        })
+        .collect();
+
+    // Busiest groups first
+    groups.sort_by_key(|g| std::cmp::Reverse(g.patterns.iter().map(|p| p.success_count).sum::<i64>()));
+    groups
+}
+
+/// Net successes weighted by success rate
+fn score(pattern: &ExportablePattern) -> f64 {
+    let total = (pattern.success_count + pattern.failure_count).max(1) as f64;
+    let rate = pattern.success_count as f64 / total;
+    (pattern.success_count - pattern.failure_count) as f64 * rate
+}
+
+/// Split a stored context into its task and approach lines
+fn describe(context: &str) -> (String, Option<String>) {
+    let mut task = None;
+    let mut approach = None;
+    for line in context.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Task:") {
+            task.get_or_insert_with(|| rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("Approach:") {
+            approach.get_or_insert_with(|| rest.trim().to_string());
+        }
+    }
+    let task = task.unwrap_or_else(|| context.lines().next().unwrap_or_default().trim().to_string());
+    (task, approach)
+}
+
+/// Escape text so it renders literally inside a Markdown list item
+fn inline(text: &str) -> String {
+    let truncated = text.chars().nth(MAX_TEXT_CHARS).is_some();
+    let mut text: String =
+        text.chars().take(MAX_TEXT_CHARS).map(|c| if c.is_control() { ' ' } else { c }).collect();
+    if truncated {
+        text.push('…');
+    }
+    if text.contains('`') {
+        text.replace('*', "\\*").replace('_', "\\_")
+    } else if text.contains(['*', '_', '<', '[']) {
+        format!("`{}`", text)
+    } else {
+        text
+    }
+}
+
+fn render_group(doc: &mut String, group: &Group, heading: &str) {
+    let _ = writeln!(doc, "{} {}\n", heading, group.title());
+    for pattern in &group.patterns {
+        let (task, approach) = describe(&pattern.context_query);
+        let _ = write!(doc, "- **{}**", inline(&task));
+        if let Some(approach) = approach {
+            let _ = write!(doc, " — {}", inline(&approach));
+        }
+        let _ = writeln!(doc, " _(✓ {} / ✗ {})_", pattern.success_count, pattern.failure_count);
+    }
+    doc.push('\n');
+}
+
+fn render_document(groups: &[Group], generated: &str) -> String {
+    let mut doc = String::new();
+    let _ = writeln!(doc, "# Learned Patterns\n");
+    let _ = writeln!(
+        doc,
+        "Approaches that have worked in this project, learned by MANA. Generated {}.\n",
+        generated
+    );
+    for group in groups {
+        render_group(&mut doc, group, "##");
+    }
+    doc
+}
+
+fn render_index(groups: &[Group], generated: &str) -> String {
+    let mut doc = String::new();
+    let _ = writeln!(doc, "# Learned Patterns\n");
+    let _ = writeln!(doc, "Generated by MANA on {}.\n", generated);
+    for group in groups {
+        let count = group.patterns.len();
+        let noun = if count == 1 { "pattern" } else { "patterns" };
+        let _ = writeln!(doc, "- [{}]({}) ({} {})", group.title(), group.file_name(), count, noun);
+    }
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(tool: &str, category: Option<&str>, context: &str, success: i64, failure: i64) -> ExportablePattern {
+        ExportablePattern {
+            pattern_hash: context.to_string(),
+            tool_type: tool.to_string(),
+            command_category: category.map(String::from),
+            context_query: context.to_string(),
+            success_count: success,
+            failure_count: failure,
+            device: None,
+            risky: false,
+        }
+    }
+
+    #[test]
+    fn test_group_and_render() {
+        let patterns = vec![
+            pattern("Bash", Some("cargo"), "Task: Run tests\nApproach: Bash - running 'cargo test'", 3, 1),
+            pattern("Bash", Some("cargo"), "Task: Build\nApproach: Bash - running 'cargo build'", 9, 0),
+            pattern("Bash", Some("cargo"), "Task: Flaky\nApproach: Bash - running 'cargo bench'", 1, 4),
+            pattern("Edit", Some("rs"), "Task: Fix my_func\nApproach: Edit", 2, 0),
+            pattern("failure", None, "Task: anything", 5, 0),
+        ];
+        let groups = group_patterns(patterns, &MarkdownOptions::default());
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].title(), "Bash · cargo");
+        assert_eq!(groups[0].file_name(), "bash-cargo.md");
+        let tasks: Vec<String> = groups[0].patterns.iter().map(|p| describe(&p.context_query).0).collect();
+        assert_eq!(tasks, vec!["Build", "Run tests"]);
+
+        let doc = render_document(&groups, "2026-01-01");
+        assert!(doc.contains("## Bash · cargo"));
+        assert!(doc.contains("- **Build** — Bash - running 'cargo build' _(✓ 9 / ✗ 0)_"));
+        assert!(doc.contains("- **`Fix my_func`** — Edit"));
+        assert!(!doc.contains("Flaky"));
+    }
+
+    #[test]
+    fn test_split_export_writes_index() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let store = crate::storage::PatternStore::open(&db_path).unwrap();
+        store
+            .insert_fast(&crate::storage::Pattern {
+                id: 0,
+                pattern_hash: "h1".to_string(),
+                tool_type: "Bash".to_string(),
+                command_category: Some("git".to_string()),
+                context_query: "Task: Commit\nApproach: Bash - running 'git commit'".to_string(),
+                success_count: 2,
+                failure_count: 0,
+                embedding_id: None,
+                risky: false,
+            })
+            .unwrap();
+        drop(store);
+
+        let security = SecurityConfig {
+            sanitize_paths: true,
+            redact_secrets: true,
+            encrypt: false,
+            visibility: crate::sync::Visibility::Private,
+        };
+        let out = temp.path().join("docs");
+        let options = MarkdownOptions { split: true, ..Default::default() };
+        let result = export_markdown(&db_path, &out, &security, &PatternFilter::default(), &options).unwrap();
+
+        assert_eq!(result.files, vec![out.join("README.md"), out.join("bash-git.md")]);
+        let index = std::fs::read_to_string(out.join("README.md")).unwrap();
+        assert!(index.contains("- [Bash · git](bash-git.md) (1 pattern)"));
+        assert!(std::fs::read_to_string(out.join("bash-git.md")).unwrap().starts_with("# Bash · git"));
+    }
+}
@@ -7,45 +7,73 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+pub mod backend;
 pub mod sanitize;
 pub mod export;
+pub mod markdown;
+pub mod packs;
+pub mod registry;
+pub mod snapshot;
+pub mod shards;
+pub mod resolve;
 pub mod crypto;
+pub mod device;
+pub mod age;
+pub mod signing;
 pub mod git_backend;
 pub mod s3_backend;
+pub mod supabase_auth;
 pub mod supabase_backend;
+pub mod supabase_realtime;
+pub mod ssh_backend;
 pub mod p2p_backend;
+pub mod verdicts;
 
 // Public API exports - some are used internally, some by main.rs
+pub use backend::{backend_for, PullOptions, PushOptions, SyncContext};
 #[allow(unused_imports)]
 pub use export::{export_patterns, import_patterns, export_patterns_to_vec, import_patterns_from_vec};
-pub use git_backend::{init_git_sync, push_patterns, pull_patterns, sync_status, save_git_config};
-pub use s3_backend::{init_s3_sync, push_patterns_s3, pull_patterns_s3, s3_status, save_s3_config, is_s3_available};
+pub use markdown::{export_markdown, MarkdownOptions};
+pub use snapshot::{export_snapshot, import_snapshot, is_snapshot};
+pub use git_backend::{init_git_sync, sync_status, save_git_config, test_auth};
+pub use s3_backend::{init_s3_sync, s3_status, save_s3_config, is_s3_available, S3Endpoint};
 #[allow(unused_imports)]
 pub use supabase_backend::{
     init_supabase_sync, push_patterns_supabase, pull_patterns_supabase,
     supabase_status, save_supabase_config, is_supabase_available, get_schema_sql,
     create_team, list_teams, invite_to_team, join_team, share_pattern,
-    Team, TeamMember, SupabaseStatus, PullResult,
+    request_login_code, login_with_code, login_with_token, logout,
+    list_invites, revoke_invite,
+    Team, TeamMember, TeamInvite, SupabaseStatus, PullResult,
 };
+pub use supabase_realtime::{listen, ListenEvent};
+pub use ssh_backend::{init_ssh_sync, ssh_status, SshTarget};
 #[allow(unused_imports)]
 pub use p2p_backend::{
     init_p2p_sync, sync_with_peer, sync_with_all_peers, p2p_status,
-    add_peer, remove_peer, list_peers, is_p2p_available,
+    add_peer, remove_peer, list_peers, is_p2p_available, serve as serve_p2p,
     P2PConfig, P2PStatus, PeerInfo, DiscoveryMethod, CrdtMergeStrategy,
     CRDTMap, CRDTEntry,
 };
 
 /// Configuration for sync operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SyncConfig {
     /// Whether sync is enabled
     pub enabled: bool,
-    /// Backend type: git, s3, or supabase
+    /// Backend type: git, s3, supabase, ssh or p2p
     pub backend: SyncBackend,
     /// Sync interval in minutes (for daemon mode)
     pub interval_minutes: u32,
     /// Security settings
     pub security: SecurityConfig,
+    /// Export signing and verification
+    pub signing: signing::SigningConfig,
+    /// Community pack registry
+    pub registry: registry::RegistryConfig,
+    /// Git remote credentials
+    pub git: git_backend::GitAuthConfig,
 }
 
 impl Default for SyncConfig {
@@ -58,10 +86,24 @@ impl Default for SyncConfig {
             },
             interval_minutes: 60,
             security: SecurityConfig::default(),
+            signing: signing::SigningConfig::default(),
+            registry: registry::RegistryConfig::default(),
+            git: git_backend::GitAuthConfig::default(),
         }
     }
 }
 
+/// Backend chosen with `mana sync init --backend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum BackendKind {
+    #[default]
+    Git,
+    S3,
+    Supabase,
+    Ssh,
+    P2p,
+}
+
 /// Supported sync backends
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -76,12 +118,21 @@ pub enum SyncBackend {
         bucket: String,
         prefix: String,
         region: String,
+        /// Custom endpoint for MinIO, R2, B2 and other S3-compatible services
+        #[serde(default)]
+        endpoint: s3_backend::S3Endpoint,
     },
     /// Supabase/PostgreSQL (team features, real-time)
     Supabase {
         url: String,
         // Key stored in MANA_SUPABASE_KEY env var
     },
+    /// Export bundle in a directory on an SSH host (homelab, no cloud)
+    Ssh {
+        /// `user@host` or a Host alias from ~/.ssh/config
+        host: String,
+        path: String,
+    },
     /// P2P sync (decentralized, no central server)
     P2P {
         discovery: String,
@@ -134,7 +185,7 @@ impl std::fmt::Display for Visibility {
 }
 
 /// Exportable pattern format (sanitized for sharing)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExportablePattern {
     /// Hash for deduplication (recalculated from sanitized content)
     pub pattern_hash: String,
@@ -148,6 +199,13 @@ pub struct ExportablePattern {
     pub success_count: i64,
     /// Failure count
     pub failure_count: i64,
+    /// Device the pattern was learned on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Destructive and not approved on the exporting device; the importer
+    /// keeps it pending until `mana patterns approve-risky`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub risky: bool,
 }
 
 /// Export metadata
@@ -163,6 +221,9 @@ pub struct ExportMetadata {
     pub pattern_count: usize,
     /// Whether data is encrypted
     pub encrypted: bool,
+    /// Device that wrote the export
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
 }
 
 /// Complete export bundle
@@ -172,6 +233,9 @@ pub struct ExportBundle {
     pub metadata: ExportMetadata,
     /// Exported patterns
     pub patterns: Vec<ExportablePattern>,
+    /// Reflection verdict stats per exported pattern (`--with-verdicts`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub verdicts: Vec<verdicts::VerdictStats>,
 }
 
 /// Load sync configuration from file
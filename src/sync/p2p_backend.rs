@@ -27,6 +27,29 @@
 //! - Each entry has a timestamp and node_id for ordering
 //! - Concurrent writes are resolved by (timestamp, node_id) ordering
 //!
+//! # Delta Sync
+//!
+//! Each node numbers its own changes with a local clock and stamps the
+//! entries it writes with that number. A map's version vector (the highest
+//! number seen from each node) summarizes what it holds, so a peer only
+//! needs the entries stamped later than the other side's vector. A sync
+//! exchanges vectors and then only those missing entries in each direction.
+//! A node answers its peers' syncs while `mana sync listen` runs.
+//!
+//! # Authentication
+//!
+//! Peers share a key (`shared_key` in `p2p.toml`, generated by `mana sync
+//! init`). Before any patterns are exchanged, each side proves it holds the
+//! key by MACing a nonce the other side picked, so neither an unknown
+//! requester nor an impostor answering at a peer's address gets or sends
+//! anything. The listener binds to `listen_addr`, loopback by default.
+//!
+//! # Node Metadata
+//!
+//! Node ids are random; each map also carries the device name behind every
+//! node it has heard of (`[device] name`, or the hostname), exchanged with
+//! each sync, and remembers which node answered at each peer address.
+//!
 //! # Discovery Methods
 //!
 //! - **mDNS**: Local network discovery (default)
@@ -35,15 +58,18 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::{SocketAddr, TcpStream};
+use blake2::digest::Mac;
+use blake2::{Blake2s256, Blake2sMac256, Digest};
+use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::Path;
 use std::io::{Read, Write};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::{info, warn};
 
-use crate::sync::ExportablePattern;
-use crate::sync::export::{export_patterns_to_vec, import_patterns_from_vec, MergeStrategy};
-use crate::sync::SecurityConfig;
+use crate::sync::{device, ExportablePattern};
+use crate::sync::export::{export_for_push, import_patterns_from_vec, MergeStrategy};
+use crate::sync::{SecurityConfig, SyncBackend};
+use crate::sync::backend::{BackendFuture, PullOptions, PullReport, PushOptions, SyncReport, SyncBackendImpl, SyncContext};
 
 /// P2P Sync Configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +80,12 @@ pub struct P2PConfig {
     pub discovery: DiscoveryMethod,
     /// Port to listen on
     pub listen_port: u16,
+    /// Interface `mana sync listen` binds to
+    #[serde(default = "default_listen_addr")]
+    pub listen_addr: String,
+    /// Key every peer shares; syncs with anyone who can't prove it are refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_key: Option<String>,
     /// Static peer list (for manual discovery)
     pub static_peers: Vec<String>,
     /// This node's unique identifier
@@ -68,6 +100,8 @@ impl Default for P2PConfig {
             enabled: false,
             discovery: DiscoveryMethod::Static,
             listen_port: 4222,
+            listen_addr: default_listen_addr(),
+            shared_key: None,
             static_peers: Vec::new(),
             node_id: generate_node_id(),
             merge_strategy: CrdtMergeStrategy::Lww,
@@ -75,8 +109,12 @@ impl Default for P2PConfig {
     }
 }
 
+fn default_listen_addr() -> String {
+    "127.0.0.1".to_string()
+}
+
 /// Discovery method for finding peers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum DiscoveryMethod {
@@ -204,6 +242,8 @@ impl CRDTEntry {
             },
             success_count: self.pattern.success_count.max(other.pattern.success_count),
             failure_count: self.pattern.failure_count.max(other.pattern.failure_count),
+            device: self.pattern.device.clone().or_else(|| other.pattern.device.clone()),
+            risky: self.pattern.risky || other.pattern.risky,
         };
 
         CRDTEntry {
@@ -229,6 +269,15 @@ pub struct CRDTMap {
     pub node_id: String,
     /// Merge strategy
     pub strategy: CrdtMergeStrategy,
+    /// Number of the last change this node made
+    #[serde(default)]
+    pub clock: u64,
+    /// Device name of each known node, this one included
+    #[serde(default)]
+    pub devices: HashMap<String, String>,
+    /// Node last seen at each peer address
+    #[serde(default)]
+    pub peers: HashMap<String, PeerInfo>,
 }
 
 impl CRDTMap {
@@ -238,46 +287,97 @@ impl CRDTMap {
             entries: HashMap::new(),
             node_id,
             strategy,
+            clock: 0,
+            devices: HashMap::new(),
+            peers: HashMap::new(),
         }
     }
 
+    /// Learn device names of other nodes; this node's own name isn't taken from peers
+    pub fn merge_devices(&mut self, devices: HashMap<String, String>) {
+        for (node, device) in devices {
+            if node != self.node_id {
+                self.devices.insert(node, device);
+            }
+        }
+    }
+
+    /// Peer info for an address, with what the last sync learned about it
+    pub fn peer(&self, address: &str) -> PeerInfo {
+        self.peers.get(address).cloned().unwrap_or_else(|| PeerInfo {
+            node_id: "unknown".to_string(),
+            address: address.to_string(),
+            last_seen: 0,
+            online: false,
+            device: None,
+        })
+    }
+
     /// Insert or update a pattern
+    ///
+    /// Re-inserting an unchanged pattern is a no-op, so it isn't sent to
+    /// peers again.
     pub fn insert(&mut self, pattern: ExportablePattern) {
         let hash = pattern.pattern_hash.clone();
         let new_entry = CRDTEntry::new(pattern, &self.node_id);
 
-        if let Some(existing) = self.entries.get(&hash) {
-            let merged = match self.strategy {
-                CrdtMergeStrategy::Lww => existing.merge_lww(&new_entry),
-                CrdtMergeStrategy::AddOnly => existing.merge_add_only(&new_entry),
+        let mut entry = match self.entries.get(&hash) {
+            Some(existing) => {
+                let merged = self.combine(existing, &new_entry);
+                if merged.pattern == existing.pattern && !existing.deleted {
+                    return;
+                }
+                merged
+            }
+            None => new_entry,
+        };
+        self.clock += 1;
+        entry.version.insert(self.node_id.clone(), self.clock);
+        self.entries.insert(hash, entry);
+    }
+
+    /// Merge entries received from a peer, returning the hashes whose
+    /// state changed here
+    pub fn merge_entries(&mut self, entries: impl IntoIterator<Item = CRDTEntry>) -> Vec<String> {
+        let mut changed = Vec::new();
+        for other_entry in entries {
+            // Our own changes can come back from a restored backup; never reuse their numbers
+            if let Some(&own) = other_entry.version.get(&self.node_id) {
+                self.clock = self.clock.max(own);
+            }
+            let hash = other_entry.pattern.pattern_hash.clone();
+            let merged = match self.entries.get(&hash) {
+                Some(existing) => {
+                    let merged = self.combine(existing, &other_entry);
+                    if merged.pattern != existing.pattern || merged.deleted != existing.deleted {
+                        changed.push(hash.clone());
+                    }
+                    merged
+                }
+                None => {
+                    changed.push(hash.clone());
+                    other_entry
+                }
             };
             self.entries.insert(hash, merged);
-        } else {
-            self.entries.insert(hash, new_entry);
         }
+        changed
     }
 
-    /// Merge another CRDT map into this one
-    pub fn merge(&mut self, other: &CRDTMap) {
-        for (hash, other_entry) in &other.entries {
-            if let Some(existing) = self.entries.get(hash) {
-                let merged = match self.strategy {
-                    CrdtMergeStrategy::Lww => existing.merge_lww(other_entry),
-                    CrdtMergeStrategy::AddOnly => existing.merge_add_only(other_entry),
-                };
-                self.entries.insert(hash.clone(), merged);
-            } else {
-                self.entries.insert(hash.clone(), other_entry.clone());
-            }
+    fn combine(&self, existing: &CRDTEntry, other: &CRDTEntry) -> CRDTEntry {
+        match self.strategy {
+            CrdtMergeStrategy::Lww => existing.merge_lww(other),
+            CrdtMergeStrategy::AddOnly => existing.merge_add_only(other),
         }
     }
 
-    /// Get all non-deleted patterns
-    pub fn patterns(&self) -> Vec<ExportablePattern> {
+    /// Entries the holder of `version` hasn't seen: those carrying a change
+    /// from some node numbered later than the vector's entry for it
+    pub fn delta_since(&self, version: &HashMap<String, u64>) -> Vec<CRDTEntry> {
         self.entries
             .values()
-            .filter(|e| !e.deleted)
-            .map(|e| e.pattern.clone())
+            .filter(|e| e.version.iter().any(|(node, ver)| *ver > version.get(node).copied().unwrap_or(0)))
+            .cloned()
             .collect()
     }
 
@@ -290,8 +390,8 @@ impl CRDTMap {
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64;
-            let ver = entry.version.get(&self.node_id).copied().unwrap_or(0);
-            entry.version.insert(self.node_id.clone(), ver + 1);
+            self.clock += 1;
+            entry.version.insert(self.node_id.clone(), self.clock);
         }
     }
 
@@ -312,10 +412,32 @@ impl CRDTMap {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum P2PMessage {
-    /// Request to sync (includes version vector for delta sync)
-    SyncRequest { version: HashMap<String, u64> },
-    /// Response with patterns newer than requested version
-    SyncResponse { crdt_map: CRDTMap },
+    /// Request to sync (includes version vector for delta sync, and the
+    /// device names the requester knows)
+    SyncRequest {
+        version: HashMap<String, u64>,
+        #[serde(default)]
+        devices: HashMap<String, String>,
+    },
+    /// Response with entries newer than the requested version, plus the
+    /// responder's own vector so the requester can send back what it lacks
+    SyncResponse {
+        entries: Vec<CRDTEntry>,
+        version: HashMap<String, u64>,
+        /// Responder's node id
+        #[serde(default)]
+        node_id: String,
+        #[serde(default)]
+        devices: HashMap<String, String>,
+    },
+    /// Entries the responder was missing, sent by the requester
+    SyncDelta { entries: Vec<CRDTEntry> },
+    /// Responder's nonce, sent first on every connection
+    Challenge { nonce: String },
+    /// Requester's proof of the shared key, and its own nonce for the responder
+    Auth { mac: String, nonce: String },
+    /// Responder's proof of the shared key
+    Authenticated { mac: String },
     /// Ping to check if peer is alive
     Ping { node_id: String },
     /// Pong response
@@ -335,10 +457,13 @@ pub struct PeerInfo {
     pub last_seen: u64,
     /// Whether the peer is currently online
     pub online: bool,
+    /// Peer's device name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
 }
 
 /// P2P sync status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 #[allow(dead_code)]
 pub struct P2PStatus {
     /// Whether P2P sync is configured
@@ -347,6 +472,8 @@ pub struct P2PStatus {
     pub discovery: String,
     /// This node's ID
     pub node_id: String,
+    /// This node's device name
+    pub device: String,
     /// Listen port
     pub listen_port: u16,
     /// Known peers
@@ -386,6 +513,73 @@ fn generate_node_id() -> String {
     format!("mana-{:016x}", hasher.finish())
 }
 
+/// 32 random bytes, base64-encoded; used for nonces and generated keys
+fn generate_nonce() -> String {
+    use base64::Engine;
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Keyed MAC over a nonce; `role` keeps one side's proof from being replayed as the other's
+fn auth_mac(key: &str, role: &str, nonce: &str) -> Blake2sMac256 {
+    let key = Blake2s256::digest(key.as_bytes());
+    let mut mac = <Blake2sMac256 as Mac>::new_from_slice(&key).expect("32-byte key");
+    mac.update(role.as_bytes());
+    mac.update(b"\0");
+    mac.update(nonce.as_bytes());
+    mac
+}
+
+fn sign_nonce(key: &str, role: &str, nonce: &str) -> String {
+    auth_mac(key, role, nonce).finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn verify_nonce(key: &str, role: &str, nonce: &str, mac: &str) -> Result<()> {
+    let bytes: Vec<u8> = (0..mac.len())
+        .step_by(2)
+        .filter_map(|i| mac.get(i..i + 2).and_then(|h| u8::from_str_radix(h, 16).ok()))
+        .collect();
+    auth_mac(key, role, nonce)
+        .verify_slice(&bytes)
+        .map_err(|_| anyhow!("Peer failed to prove the shared key"))
+}
+
+/// The configured shared key; P2P sync refuses to run without one
+fn shared_key(config: &P2PConfig) -> Result<String> {
+    config
+        .shared_key
+        .clone()
+        .filter(|k| !k.is_empty())
+        .ok_or_else(|| anyhow!("P2P sync needs a shared key: set shared_key in p2p.toml (the same on every peer)"))
+}
+
+/// Requester side of the handshake: prove the key, then check the responder's proof
+fn authenticate_to_peer(stream: &TcpStream, key: &str) -> Result<()> {
+    let P2PMessage::Challenge { nonce } = receive_message(stream)? else {
+        return Err(anyhow!("Peer did not start the handshake"));
+    };
+    let ours = generate_nonce();
+    send_message(stream, &P2PMessage::Auth { mac: sign_nonce(key, "requester", &nonce), nonce: ours.clone() })?;
+    match receive_message(stream)? {
+        P2PMessage::Authenticated { mac } => verify_nonce(key, "responder", &ours, &mac),
+        _ => Err(anyhow!("Peer rejected the shared key")),
+    }
+}
+
+/// Responder side of the handshake
+fn authenticate_peer(stream: &TcpStream, key: &str) -> Result<()> {
+    let nonce = generate_nonce();
+    send_message(stream, &P2PMessage::Challenge { nonce: nonce.clone() })?;
+    let P2PMessage::Auth { mac, nonce: theirs } = receive_message(stream)? else {
+        return Err(anyhow!("Peer did not answer the handshake"));
+    };
+    verify_nonce(key, "requester", &nonce, &mac)?;
+    send_message(stream, &P2PMessage::Authenticated { mac: sign_nonce(key, "responder", &theirs) })
+}
+
 /// Initialize P2P sync
 pub fn init_p2p_sync(
     mana_dir: &Path,
@@ -397,6 +591,8 @@ pub fn init_p2p_sync(
         enabled: true,
         discovery,
         listen_port,
+        listen_addr: default_listen_addr(),
+        shared_key: Some(generate_nonce()),
         static_peers,
         node_id: generate_node_id(),
         merge_strategy: CrdtMergeStrategy::Lww,
@@ -419,6 +615,8 @@ pub fn init_p2p_sync(
     if !config.static_peers.is_empty() {
         println!("   Static peers: {}", config.static_peers.join(", "));
     }
+    println!("   Shared key: {}", config.shared_key.as_deref().unwrap_or_default());
+    println!("   Set the same shared_key in each peer's p2p.toml; peers without it are refused.");
 
     Ok(())
 }
@@ -446,13 +644,19 @@ pub fn save_p2p_config(mana_dir: &Path, config: &P2PConfig) -> Result<()> {
 /// Load CRDT state
 pub fn load_crdt_state(mana_dir: &Path) -> Result<CRDTMap> {
     let crdt_path = mana_dir.join("p2p-crdt.json");
-    if !crdt_path.exists() {
+    let mut crdt_map = if crdt_path.exists() {
+        let content = std::fs::read_to_string(&crdt_path)?;
+        let mut crdt_map: CRDTMap = serde_json::from_str(&content)?;
+        // State saved before the clock existed numbered every change 1
+        let own = crdt_map.version_vector().get(&crdt_map.node_id).copied().unwrap_or(0);
+        crdt_map.clock = crdt_map.clock.max(own);
+        crdt_map
+    } else {
         let config = load_p2p_config(mana_dir)?;
-        return Ok(CRDTMap::new(config.node_id, config.merge_strategy));
-    }
-
-    let content = std::fs::read_to_string(&crdt_path)?;
-    let crdt_map: CRDTMap = serde_json::from_str(&content)?;
+        CRDTMap::new(config.node_id, config.merge_strategy)
+    };
+    // The device may have been renamed since the state was saved
+    crdt_map.devices.insert(crdt_map.node_id.clone(), device::name(mana_dir));
     Ok(crdt_map)
 }
 
@@ -470,16 +674,17 @@ pub fn sync_with_peer(
     db_path: &Path,
     peer_address: &str,
     security: &SecurityConfig,
+    merge_strategy: MergeStrategy,
     timeout_secs: u64,
 ) -> Result<SyncResult> {
     info!("Syncing with peer: {}", peer_address);
 
     // Load local CRDT state
     let mut local_crdt = load_crdt_state(mana_dir)?;
-    let _config = load_p2p_config(mana_dir)?;
+    let key = shared_key(&load_p2p_config(mana_dir)?)?;
 
     // Export current patterns to CRDT
-    let local_patterns = export_patterns_to_vec(db_path, security)?;
+    let local_patterns = export_for_push(db_path, security)?;
     for pattern in local_patterns {
         local_crdt.insert(pattern);
     }
@@ -493,10 +698,12 @@ pub fn sync_with_peer(
 
     stream.set_read_timeout(Some(Duration::from_secs(timeout_secs)))?;
     stream.set_write_timeout(Some(Duration::from_secs(timeout_secs)))?;
+    authenticate_to_peer(&stream, &key)?;
 
     // Send sync request with our version vector
     let request = P2PMessage::SyncRequest {
         version: local_crdt.version_vector(),
+        devices: local_crdt.devices.clone(),
     };
 
     send_message(&stream, &request)?;
@@ -505,34 +712,38 @@ pub fn sync_with_peer(
     let response: P2PMessage = receive_message(&stream)?;
 
     match response {
-        P2PMessage::SyncResponse { crdt_map: remote_crdt } => {
-            let remote_count = remote_crdt.entries.len();
+        P2PMessage::SyncResponse { entries, version, node_id, devices } => {
+            let received = entries.len();
             let local_count_before = local_crdt.entries.len();
 
-            // Merge remote CRDT into local
-            local_crdt.merge(&remote_crdt);
-
-            let local_count_after = local_crdt.entries.len();
-            let new_patterns = local_count_after - local_count_before;
+            // Send back only what the peer's vector says it's missing
+            let outgoing = local_crdt.delta_since(&version);
+            let sent = outgoing.len();
+            send_message(&stream, &P2PMessage::SyncDelta { entries: outgoing })?;
+
+            // Merge the peer's changes into local state
+            let changed = local_crdt.merge_entries(entries);
+            let new_patterns = local_crdt.entries.len() - local_count_before;
+            local_crdt.merge_devices(devices);
+            if !node_id.is_empty() {
+                let device = local_crdt.devices.get(&node_id).cloned();
+                let last_seen = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                let peer = PeerInfo { node_id, address: peer_address.to_string(), last_seen, online: true, device };
+                local_crdt.peers.insert(peer_address.to_string(), peer);
+            }
 
             // Save merged CRDT state
             save_crdt_state(mana_dir, &local_crdt)?;
 
-            // Import merged patterns back to database
-            let patterns = local_crdt.patterns();
-            let import_result = import_patterns_from_vec(
-                db_path,
-                patterns,
-                MergeStrategy::Add,
-            )?;
+            let import_result = import_changed(db_path, &local_crdt, &changed, merge_strategy)?;
 
-            info!("Sync complete: received {} entries, {} new", remote_count, new_patterns);
+            info!("Sync complete: received {} entries, sent {}, {} new", received, sent, new_patterns);
 
             Ok(SyncResult {
                 peer: peer_address.to_string(),
-                received: remote_count,
-                sent: local_count_before,
-                merged: import_result.merged,
+                received,
+                sent,
+                merged: import_result,
                 new_patterns,
                 success: true,
             })
@@ -541,17 +752,35 @@ pub fn sync_with_peer(
     }
 }
 
+/// Write patterns whose CRDT state changed back to the database
+///
+/// Entries hold full counts rather than increments: `replace` takes the
+/// peer's counts, while `add` counts what both sides share twice.
+fn import_changed(db_path: &Path, crdt: &CRDTMap, changed: &[String], merge_strategy: MergeStrategy) -> Result<usize> {
+    let patterns: Vec<ExportablePattern> = changed
+        .iter()
+        .filter_map(|hash| crdt.entries.get(hash))
+        .filter(|e| !e.deleted)
+        .map(|e| e.pattern.clone())
+        .collect();
+    if patterns.is_empty() {
+        return Ok(0);
+    }
+    let result = import_patterns_from_vec(db_path, patterns, merge_strategy)?;
+    Ok(result.imported + result.merged)
+}
+
 /// Sync result
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SyncResult {
     /// Peer address
     pub peer: String,
-    /// Patterns received from peer
+    /// Entries received from peer
     pub received: usize,
-    /// Patterns sent to peer
+    /// Entries sent to peer
     pub sent: usize,
-    /// Patterns merged
+    /// Patterns written to the local database
     pub merged: usize,
     /// New patterns added
     pub new_patterns: usize,
@@ -564,6 +793,7 @@ pub fn sync_with_all_peers(
     mana_dir: &Path,
     db_path: &Path,
     security: &SecurityConfig,
+    merge_strategy: MergeStrategy,
 ) -> Result<Vec<SyncResult>> {
     let config = load_p2p_config(mana_dir)?;
 
@@ -574,7 +804,7 @@ pub fn sync_with_all_peers(
     let mut results = Vec::new();
 
     for peer in &config.static_peers {
-        match sync_with_peer(mana_dir, db_path, peer, security, 30) {
+        match sync_with_peer(mana_dir, db_path, peer, security, merge_strategy, 30) {
             Ok(result) => {
                 println!("✅ Synced with {}: +{} patterns", peer, result.new_patterns);
                 results.push(result);
@@ -598,23 +828,115 @@ pub fn sync_with_all_peers(
 }
 
 /// Handle incoming sync request (for running as a server)
-#[allow(dead_code)]
+///
+/// Replies with the entries newer than the requester's vector; the
+/// requester answers with a `SyncDelta` for `apply_sync_delta`.
 pub fn handle_sync_request(
     mana_dir: &Path,
     db_path: &Path,
     security: &SecurityConfig,
-    _request_version: HashMap<String, u64>,
-) -> Result<CRDTMap> {
+    request_version: HashMap<String, u64>,
+    request_devices: HashMap<String, String>,
+) -> Result<P2PMessage> {
     let mut local_crdt = load_crdt_state(mana_dir)?;
+    local_crdt.merge_devices(request_devices);
 
     // Export current patterns to CRDT
-    let local_patterns = export_patterns_to_vec(db_path, security)?;
+    let local_patterns = export_for_push(db_path, security)?;
     for pattern in local_patterns {
         local_crdt.insert(pattern);
     }
+    save_crdt_state(mana_dir, &local_crdt)?;
 
-    // For now, return full state (future: delta based on version)
-    Ok(local_crdt)
+    Ok(P2PMessage::SyncResponse {
+        entries: local_crdt.delta_since(&request_version),
+        version: local_crdt.version_vector(),
+        node_id: local_crdt.node_id.clone(),
+        devices: local_crdt.devices.clone(),
+    })
+}
+
+/// Merge the entries a requester sent back after our `SyncResponse`
+pub fn apply_sync_delta(
+    mana_dir: &Path,
+    db_path: &Path,
+    entries: Vec<CRDTEntry>,
+    merge_strategy: MergeStrategy,
+) -> Result<usize> {
+    let mut local_crdt = load_crdt_state(mana_dir)?;
+    let changed = local_crdt.merge_entries(entries);
+    save_crdt_state(mana_dir, &local_crdt)?;
+    import_changed(db_path, &local_crdt, &changed, merge_strategy)
+}
+
+/// Answer one peer connection: a sync, or a ping
+///
+/// The peer must first prove it holds `key`. For a sync, it then gets what
+/// its vector lacks and sends back what ours lacks. Returns the patterns
+/// written to the local database.
+pub fn handle_connection(
+    mana_dir: &Path,
+    db_path: &Path,
+    security: &SecurityConfig,
+    merge_strategy: MergeStrategy,
+    key: &str,
+    stream: &TcpStream,
+) -> Result<usize> {
+    authenticate_peer(stream, key)?;
+    match receive_message(stream)? {
+        P2PMessage::SyncRequest { version, devices } => {
+            let response = handle_sync_request(mana_dir, db_path, security, version, devices)?;
+            send_message(stream, &response)?;
+            match receive_message(stream)? {
+                P2PMessage::SyncDelta { entries } => apply_sync_delta(mana_dir, db_path, entries, merge_strategy),
+                _ => Err(anyhow!("Expected a sync delta from peer")),
+            }
+        }
+        P2PMessage::Ping { .. } => {
+            let node_id = load_p2p_config(mana_dir)?.node_id;
+            send_message(stream, &P2PMessage::Pong { node_id })?;
+            Ok(0)
+        }
+        _ => Err(anyhow!("Unexpected message from peer")),
+    }
+}
+
+/// Accept peers' syncs on `listen_addr` and `listen_port` until interrupted
+///
+/// Connections are answered one at a time; `on_sync` gets each peer's
+/// address with the patterns written, or why its sync failed (including
+/// peers that couldn't prove the shared key).
+pub fn serve(
+    mana_dir: &Path,
+    db_path: &Path,
+    security: &SecurityConfig,
+    merge_strategy: MergeStrategy,
+    listen_port: u16,
+    mut on_sync: impl FnMut(&str, Result<usize>),
+) -> Result<()> {
+    let config = load_p2p_config(mana_dir)?;
+    let key = shared_key(&config)?;
+    let listener = TcpListener::bind((config.listen_addr.as_str(), listen_port))
+        .map_err(|e| anyhow!("Failed to listen on {}:{}: {}", config.listen_addr, listen_port, e))?;
+    info!("Listening for P2P sync on {}:{}", config.listen_addr, listen_port);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept peer: {}", e);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "unknown".to_string());
+        let result = stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .and_then(|_| stream.set_write_timeout(Some(Duration::from_secs(30))))
+            .map_err(Into::into)
+            .and_then(|_| handle_connection(mana_dir, db_path, security, merge_strategy, &key, &stream));
+        on_sync(&peer, result);
+    }
+    Ok(())
 }
 
 /// Get P2P sync status
@@ -626,6 +948,7 @@ pub fn p2p_status(mana_dir: &Path) -> Result<P2PStatus> {
             configured: false,
             discovery: "none".to_string(),
             node_id: String::new(),
+            device: device::name(mana_dir),
             listen_port: 0,
             peers: Vec::new(),
             entry_count: 0,
@@ -635,20 +958,14 @@ pub fn p2p_status(mana_dir: &Path) -> Result<P2PStatus> {
 
     let crdt = load_crdt_state(mana_dir)?;
 
-    // Build peer info from static peers
-    let peers: Vec<PeerInfo> = config.static_peers.iter().map(|addr| {
-        PeerInfo {
-            node_id: "unknown".to_string(),
-            address: addr.clone(),
-            last_seen: 0,
-            online: false,
-        }
-    }).collect();
+    // Static peers, with what the last sync with each learned
+    let peers: Vec<PeerInfo> = config.static_peers.iter().map(|addr| crdt.peer(addr)).collect();
 
     Ok(P2PStatus {
         configured: true,
         discovery: config.discovery.to_string(),
         node_id: config.node_id,
+        device: crdt.devices.get(&crdt.node_id).cloned().unwrap_or_else(|| device::name(mana_dir)),
         listen_port: config.listen_port,
         peers,
         entry_count: crdt.entries.len(),
@@ -695,17 +1012,8 @@ pub fn remove_peer(mana_dir: &Path, peer_address: &str) -> Result<()> {
 /// List all configured peers
 pub fn list_peers(mana_dir: &Path) -> Result<Vec<PeerInfo>> {
     let config = load_p2p_config(mana_dir)?;
-
-    let peers: Vec<PeerInfo> = config.static_peers.iter().map(|addr| {
-        PeerInfo {
-            node_id: "unknown".to_string(),
-            address: addr.clone(),
-            last_seen: 0,
-            online: false,
-        }
-    }).collect();
-
-    Ok(peers)
+    let crdt = load_crdt_state(mana_dir)?;
+    Ok(config.static_peers.iter().map(|addr| crdt.peer(addr)).collect())
 }
 
 /// Check if P2P sync is available (has peers configured)
@@ -751,6 +1059,82 @@ fn receive_message(stream: &TcpStream) -> Result<P2PMessage> {
     Ok(msg)
 }
 
+/// P2P backend for `mana sync`: push, pull and sync all sync with every peer
+pub struct P2PSync;
+
+impl P2PSync {
+    fn sync_all(ctx: &SyncContext<'_>, merge_strategy: MergeStrategy) -> Result<PullReport> {
+        let results = sync_with_all_peers(ctx.mana_dir, ctx.db_path, &SecurityConfig::default(), merge_strategy)?;
+        let total_new: usize = results.iter().map(|r| r.new_patterns).sum();
+        let successful = results.iter().filter(|r| r.success).count();
+        println!("✅ P2P sync complete: {} peers, +{} patterns", successful, total_new);
+        let changed: usize = results.iter().map(|r| r.merged).sum();
+        Ok(PullReport {
+            changed,
+            imported: total_new,
+            merged: changed.saturating_sub(total_new),
+            ..Default::default()
+        })
+    }
+}
+
+impl SyncBackendImpl for P2PSync {
+    fn name(&self) -> &'static str {
+        "p2p"
+    }
+
+    fn push<'a>(&'a self, ctx: &'a SyncContext<'a>, _options: &'a PushOptions) -> BackendFuture<'a, ()> {
+        // Push has no --merge; peers' entries carry full counts, so take theirs
+        Box::pin(async move { Self::sync_all(ctx, MergeStrategy::Replace).map(drop) })
+    }
+
+    fn pull<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PullOptions) -> BackendFuture<'a, PullReport> {
+        Box::pin(async move { Self::sync_all(ctx, options.merge_strategy) })
+    }
+
+    fn sync<'a>(
+        &'a self,
+        ctx: &'a SyncContext<'a>,
+        pull: &'a PullOptions,
+        _push: &'a PushOptions,
+    ) -> BackendFuture<'a, SyncReport> {
+        // One exchange with each peer already goes both ways
+        Box::pin(async move { Ok(SyncReport { backend: self.name(), pulled: Self::sync_all(ctx, pull.merge_strategy)?, pushed: true }) })
+    }
+
+    fn status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, serde_json::Value> {
+        Box::pin(async move { Ok(serde_json::to_value(p2p_status(ctx.mana_dir)?)?) })
+    }
+
+    fn print_status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let status = p2p_status(ctx.mana_dir)?;
+            println!("Backend: p2p");
+            let SyncBackend::P2P { discovery, listen_port, .. } = &ctx.config.backend else {
+                return Ok(());
+            };
+            println!("Discovery: {}", discovery);
+            println!("Listen port: {}", listen_port);
+            println!("Node ID: {} ({})", status.node_id, status.device);
+            println!("CRDT entries: {}", status.entry_count);
+            println!();
+            println!("Configured peers: {}", status.peers.len());
+            for peer in &status.peers {
+                match &peer.device {
+                    Some(device) => println!("  - {} ({}, {})", peer.address, device, peer.node_id),
+                    None => println!("  - {} (not synced yet)", peer.address),
+                }
+            }
+            if status.peers.is_empty() {
+                println!("   (none configured)");
+                println!();
+                println!("   Add peers with: mana sync peer add <address>");
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -764,6 +1148,8 @@ mod tests {
             context_query: "Build project".to_string(),
             success_count: 5,
             failure_count: 1,
+            device: None,
+            risky: false,
         };
 
         let entry1 = CRDTEntry::new(pattern.clone(), "node1");
@@ -795,6 +1181,8 @@ mod tests {
             context_query: "Build project".to_string(),
             success_count: 5,
             failure_count: 1,
+            device: None,
+            risky: false,
         };
 
         let entry1 = CRDTEntry::new(pattern.clone(), "node1");
@@ -824,6 +1212,8 @@ mod tests {
             context_query: "Pattern 1".to_string(),
             success_count: 1,
             failure_count: 0,
+            device: None,
+            risky: false,
         };
 
         let pattern2 = ExportablePattern {
@@ -833,18 +1223,148 @@ mod tests {
             context_query: "Pattern 2".to_string(),
             success_count: 2,
             failure_count: 0,
+            device: None,
+            risky: false,
         };
 
         map1.insert(pattern1);
         map2.insert(pattern2);
 
-        map1.merge(&map2);
+        map1.merge_entries(map2.entries.values().cloned());
 
         assert_eq!(map1.entries.len(), 2);
         assert!(map1.entries.contains_key("hash1"));
         assert!(map1.entries.contains_key("hash2"));
     }
 
+    #[test]
+    fn test_delta_sync_sends_only_missing_entries() {
+        let pattern = |hash: &str, success: i64| ExportablePattern {
+            pattern_hash: hash.to_string(),
+            tool_type: "Bash".to_string(),
+            command_category: None,
+            context_query: format!("Pattern {}", hash),
+            success_count: success,
+            failure_count: 0,
+            device: None,
+            risky: false,
+        };
+        let mut map1 = CRDTMap::new("node1".to_string(), CrdtMergeStrategy::Lww);
+        let mut map2 = CRDTMap::new("node2".to_string(), CrdtMergeStrategy::Lww);
+        for i in 0..50 {
+            map1.insert(pattern(&format!("shared{}", i), 1));
+        }
+        map2.merge_entries(map1.delta_since(&map2.version_vector()));
+        assert_eq!(map2.entries.len(), 50);
+
+        // Unchanged patterns don't count as changes
+        map1.insert(pattern("shared0", 1));
+        assert_eq!(map1.clock, 50);
+        assert!(map1.delta_since(&map2.version_vector()).is_empty());
+
+        map1.insert(pattern("shared7", 4));
+        map2.insert(pattern("local", 2));
+
+        // Each side gets just the other's one change
+        let to_node2 = map1.delta_since(&map2.version_vector());
+        let to_node1 = map2.delta_since(&map1.version_vector());
+        assert_eq!(to_node2.len(), 1);
+        assert_eq!(to_node1.len(), 1);
+        assert_eq!(map2.merge_entries(to_node2), vec!["shared7".to_string()]);
+        assert_eq!(map1.merge_entries(to_node1), vec!["local".to_string()]);
+
+        assert_eq!(map2.entries["shared7"].pattern.success_count, 4);
+        assert!(map1.delta_since(&map2.version_vector()).is_empty());
+        assert!(map2.delta_since(&map1.version_vector()).is_empty());
+    }
+
+    #[test]
+    fn test_sync_over_tcp_goes_both_ways() {
+        let node = |name: &str, context: &str| {
+            let dir = tempfile::TempDir::new().unwrap();
+            let db_path = dir.path().join("metadata.sqlite");
+            crate::storage::create_schema(&rusqlite::Connection::open(&db_path).unwrap()).unwrap();
+            let config = P2PConfig {
+                enabled: true,
+                node_id: name.to_string(),
+                shared_key: Some("team-key".to_string()),
+                ..Default::default()
+            };
+            save_p2p_config(dir.path(), &config).unwrap();
+            let pattern = ExportablePattern {
+                pattern_hash: format!("{}-hash", name),
+                tool_type: "Bash".to_string(),
+                command_category: None,
+                context_query: context.to_string(),
+                success_count: 5,
+                failure_count: 0,
+                device: None,
+                risky: false,
+            };
+            import_patterns_from_vec(&db_path, vec![pattern], MergeStrategy::Add).unwrap();
+            (dir, db_path)
+        };
+        let (responder, responder_db) = node("responder", "Approach: Bash - cargo build");
+        let (requester, requester_db) = node("requester", "Approach: Bash - cargo test");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (mana_dir, db_path) = (responder.path().to_path_buf(), responder_db.clone());
+        let server = std::thread::spawn(move || {
+            let mut results = Vec::new();
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let security = SecurityConfig::default();
+                results.push(handle_connection(&mana_dir, &db_path, &security, MergeStrategy::Replace, "team-key", &stream));
+            }
+            results
+        });
+        let sync = || {
+            sync_with_peer(requester.path(), &requester_db, &address, &SecurityConfig::default(), MergeStrategy::Replace, 5)
+        };
+
+        // Without the key nothing is exchanged
+        let mut config = load_p2p_config(requester.path()).unwrap();
+        config.shared_key = Some("guess".to_string());
+        save_p2p_config(requester.path(), &config).unwrap();
+        assert!(sync().is_err());
+
+        config.shared_key = Some("team-key".to_string());
+        save_p2p_config(requester.path(), &config).unwrap();
+        let result = sync().unwrap();
+        assert_eq!((result.received, result.sent), (1, 1));
+        let results = server.join().unwrap();
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap(), &1);
+
+        let contexts = |db_path: &Path| {
+            let conn = rusqlite::Connection::open(db_path).unwrap();
+            let mut stmt = conn.prepare("SELECT context_query FROM patterns ORDER BY context_query").unwrap();
+            stmt.query_map([], |row| row.get::<_, String>(0)).unwrap().collect::<Result<Vec<_>, _>>().unwrap()
+        };
+        let both = vec!["Approach: Bash - cargo build", "Approach: Bash - cargo test"];
+        assert_eq!(contexts(&responder_db), both);
+        assert_eq!(contexts(&requester_db), both);
+    }
+
+    #[test]
+    fn test_device_names_from_peers() {
+        let mut map = CRDTMap::new("node1".to_string(), CrdtMergeStrategy::Lww);
+        map.devices.insert("node1".to_string(), "laptop".to_string());
+        let heard = HashMap::from([
+            ("node1".to_string(), "impostor".to_string()),
+            ("node2".to_string(), "devpod".to_string()),
+        ]);
+        map.merge_devices(heard);
+        assert_eq!(map.devices["node1"], "laptop");
+        assert_eq!(map.devices["node2"], "devpod");
+        assert_eq!(map.peer("10.0.0.2:4222").node_id, "unknown");
+
+        // Maps saved before device metadata still load
+        let old = r#"{"entries": {}, "node_id": "node1", "strategy": "lww"}"#;
+        assert!(serde_json::from_str::<CRDTMap>(old).unwrap().devices.is_empty());
+    }
+
     #[test]
     fn test_generate_node_id() {
         let id1 = generate_node_id();
@@ -0,0 +1,239 @@
+//! Starter pattern packs
+//!
+//! A pack is an export bundle of curated patterns for one kind of project
+//! (`rust-cargo`, `node-monorepo`, `k8s-ops`, ...). A few ship inside the
+//! binary; any other export bundle can be installed from a file. Patterns a
+//! pack adds are tagged `pack:<name>`, so they can be listed with
+//! `mana patterns list --tag pack:<name>` and removed together with
+//! `mana patterns uninstall <name>`.
+//!
+//! Pack hashes are recomputed on install, so bundled packs leave
+//! `pattern_hash` empty and stay readable. Verdict stats in a pack's bundle
+//! are kept as public experience (see `sync::verdicts`).
+
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::storage::{db, tags, Pattern, PatternStore};
+use crate::sync::export::parse_bundle;
+use crate::sync::sanitize::{calculate_hash, is_risky_import, localize};
+use crate::sync::verdicts::{self, Provenance, VerdictStats};
+use crate::sync::ExportBundle;
+
+/// Packs compiled into the binary as (name, description, bundle JSON)
+const BUNDLED: &[(&str, &str, &str)] = &[
+    (
+        "rust-cargo",
+        "Cargo build, test, lint and dependency workflows for Rust projects",
+        include_str!("packs/rust-cargo.json"),
+    ),
+    (
+        "node-monorepo",
+        "npm workspace installs, builds, tests and linting for JS/TS monorepos",
+        include_str!("packs/node-monorepo.json"),
+    ),
+    (
+        "k8s-ops",
+        "kubectl debugging, rollout and apply workflows for Kubernetes",
+        include_str!("packs/k8s-ops.json"),
+    ),
+];
+
+/// A pack that ships with MANA
+#[derive(Debug, Clone, Serialize)]
+pub struct BundledPack {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub patterns: usize,
+    /// Patterns from this pack currently in the database
+    pub installed: usize,
+}
+
+/// Outcome of [`install`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PackInstall {
+    pub name: String,
+    /// Provenance tag on the pack's new patterns
+    pub tag: String,
+    pub total: usize,
+    /// Patterns added and tagged with the pack
+    pub added: usize,
+    /// Patterns already present; their counts are merged but they stay untagged
+    pub merged: usize,
+    /// Destructive patterns, held back from injection until `approve-risky`
+    pub risky: usize,
+}
+
+/// Tag marking patterns that came from a pack
+pub fn provenance_tag(name: &str) -> Result<String> {
+    tags::normalize(&format!("pack:{}", name))
+}
+
+/// Bundled packs with how many of their patterns are installed
+pub fn bundled(conn: &Connection) -> Result<Vec<BundledPack>> {
+    BUNDLED
+        .iter()
+        .map(|(name, description, json)| {
+            let bundle: ExportBundle = serde_json::from_str(json)?;
+            Ok(BundledPack {
+                name,
+                description,
+                patterns: bundle.patterns.len(),
+                installed: installed_count(conn, name)?,
+            })
+        })
+        .collect()
+}
+
+/// Load a pack by bundled name, or from an export bundle file
+///
+/// Packs loaded from a file are named after the file stem.
+pub fn load(pack: &str) -> Result<(String, ExportBundle)> {
+    if let Some((name, _, json)) = BUNDLED.iter().find(|(name, _, _)| *name == pack) {
+        return Ok((name.to_string(), serde_json::from_str(json)?));
+    }
+
+    let path = Path::new(pack);
+    if !path.is_file() {
+        let names: Vec<&str> = BUNDLED.iter().map(|(name, _, _)| *name).collect();
+        bail!("Unknown pack {:?}; bundled packs are {}, or pass an export file", pack, names.join(", "));
+    }
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let bundle = parse_bundle(&content, None)?;
+    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| pack.to_string());
+    Ok((name, bundle))
+}
+
+/// Import a pack's patterns and tag the new ones with [`provenance_tag`]
+///
+/// Installing twice only merges counts; nothing is duplicated.
+pub fn install(db_path: &Path, pack: &str) -> Result<PackInstall> {
+    let (name, bundle) = load(pack)?;
+    install_bundle(db_path, &name, &bundle)
+}
+
+/// Import an already loaded pack under `name`
+pub fn install_bundle(db_path: &Path, name: &str, bundle: &ExportBundle) -> Result<PackInstall> {
+    let tag = provenance_tag(name)?;
+
+    let store = PatternStore::open(db_path)?;
+    let conn = db::open(db_path)?;
+    let mut result = PackInstall { name: name.to_string(), tag, total: bundle.patterns.len(), added: 0, merged: 0, risky: 0 };
+    // Bundle hash to the recomputed local one, for the verdict stats
+    let mut rehashed: HashMap<&str, String> = HashMap::new();
+    for exportable in &bundle.patterns {
+        let context_query = localize(&exportable.context_query);
+        let pattern_hash = calculate_hash(&context_query);
+        if !exportable.pattern_hash.is_empty() {
+            rehashed.insert(&exportable.pattern_hash, pattern_hash.clone());
+        }
+        let existing: Option<i64> = conn
+            .query_row("SELECT id FROM patterns WHERE pattern_hash = ?1", params![pattern_hash], |row| row.get(0))
+            .optional()?;
+
+        // Packs are untrusted: whatever the bundle says, destructive patterns wait for approval
+        let risky = is_risky_import(exportable.risky, &context_query);
+        result.risky += usize::from(risky);
+        store.insert_fast(&Pattern {
+            id: 0,
+            pattern_hash: pattern_hash.clone(),
+            tool_type: exportable.tool_type.clone(),
+            command_category: exportable.command_category.clone(),
+            risky,
+            context_query,
+            success_count: exportable.success_count,
+            failure_count: exportable.failure_count,
+            embedding_id: None,
+        })?;
+
+        if existing.is_some() {
+            result.merged += 1;
+            continue;
+        }
+        let id: i64 =
+            conn.query_row("SELECT id FROM patterns WHERE pattern_hash = ?1", params![pattern_hash], |row| row.get(0))?;
+        tags::add(&conn, id, std::slice::from_ref(&result.tag))?;
+        result.added += 1;
+    }
+
+    let stats: Vec<VerdictStats> = bundle
+        .verdicts
+        .iter()
+        .filter_map(|s| Some(VerdictStats { pattern_hash: rehashed.get(s.pattern_hash.as_str())?.clone(), ..s.clone() }))
+        .collect();
+    if !stats.is_empty() {
+        verdicts::merge(&conn, &stats, &result.tag, Provenance::Public)?;
+    }
+    Ok(result)
+}
+
+/// Delete every pattern a pack added, returning their ids
+pub fn uninstall(conn: &Connection, name: &str) -> Result<Vec<i64>> {
+    let tag = provenance_tag(name)?;
+    let mut stmt = conn.prepare("SELECT pattern_id FROM pattern_tags WHERE tag = ?1 ORDER BY pattern_id")?;
+    let ids: Vec<i64> = stmt.query_map(params![tag], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+
+    let tx = conn.unchecked_transaction()?;
+    crate::storage::patterns::delete_with_dependents(&tx, &ids)?;
+    tx.commit()?;
+    Ok(ids)
+}
+
+/// Patterns in the database that came from a pack
+fn installed_count(conn: &Connection, name: &str) -> Result<usize> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pattern_tags WHERE tag = ?1",
+        params![provenance_tag(name)?],
+        |row| row.get(0),
+    )?;
+    Ok(count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bundled_packs_parse() {
+        for (name, _, json) in BUNDLED {
+            let bundle: ExportBundle = serde_json::from_str(json).unwrap();
+            assert!(!bundle.patterns.is_empty(), "{} is empty", name);
+            assert_eq!(bundle.metadata.pattern_count, bundle.patterns.len(), "{} count is stale", name);
+        }
+    }
+
+    #[test]
+    fn test_install_tags_and_uninstall_removes_group() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        let conn = db::open(&db_path).unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+
+        let first = install(&db_path, "rust-cargo").unwrap();
+        assert_eq!((first.added, first.merged), (first.total, 0));
+        assert_eq!(first.tag, "pack:rust-cargo");
+        let again = install(&db_path, "rust-cargo").unwrap();
+        assert_eq!((again.added, again.merged), (0, again.total));
+
+        let packs = bundled(&conn).unwrap();
+        let rust = packs.iter().find(|p| p.name == "rust-cargo").unwrap();
+        assert_eq!(rust.installed, rust.patterns);
+        assert!(install(&db_path, "no-such-pack").is_err());
+
+        // A pattern the user already had isn't removed with the pack
+        conn.execute(
+            "INSERT INTO patterns (pattern_hash, tool_type, context_query) VALUES ('mine', 'Bash', 'kubectl get pods')",
+            [],
+        )
+        .unwrap();
+        let removed = uninstall(&conn, "rust-cargo").unwrap();
+        assert_eq!(removed.len(), first.total);
+        let left: i64 = conn.query_row("SELECT COUNT(*) FROM patterns", [], |row| row.get(0)).unwrap();
+        assert_eq!(left, 1);
+        assert!(uninstall(&conn, "rust-cargo").unwrap().is_empty());
+    }
+}
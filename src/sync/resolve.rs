@@ -0,0 +1,262 @@
+//! Interactive conflict resolution for keep-best merges
+//!
+//! `--interactive` on `mana import` and `mana sync pull` shows each incoming
+//! pattern that differs from the local copy next to it and lets the user pick
+//! which one to keep. Choices are remembered in merge-decisions.json, keyed by
+//! pattern hash and the incoming counts, so importing the same file or pulling
+//! the same remote state again doesn't ask twice. A new remote version of the
+//! pattern asks again.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use crate::storage::{Pattern, PatternStore};
+use crate::sync::export::{find_by_hash, merge_pattern, success_rate, ImportResult, MergeStrategy};
+use crate::sync::sanitize;
+use crate::sync::ExportablePattern;
+
+/// Remembered choices, in the data directory
+pub const DECISIONS_FILE: &str = "merge-decisions.json";
+
+/// Which side of a conflict to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Choice {
+    Local,
+    Incoming,
+}
+
+/// A stored choice and the incoming counts it was made for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Decision {
+    choice: Choice,
+    incoming: (i64, i64),
+}
+
+/// A local pattern and the incoming pattern with the same hash
+pub struct ConflictPair<'a> {
+    pub local: &'a Pattern,
+    pub incoming: &'a Pattern,
+    /// Where the incoming pattern came from
+    pub source: &'a str,
+}
+
+/// Asks the user to pick a side
+pub trait ConflictPrompt {
+    /// `None` keeps the local copy of this and every remaining conflict
+    fn choose(&mut self, pair: &ConflictPair) -> Result<Option<Choice>>;
+}
+
+/// Prompt on the terminal
+pub struct TerminalPrompt<R, W> {
+    input: R,
+    output: W,
+}
+
+impl TerminalPrompt<std::io::StdinLock<'static>, std::io::Stdout> {
+    /// Prompt on stdin/stdout, which must be a terminal
+    pub fn stdio() -> Result<Self> {
+        crate::ci::ensure_interactive("--interactive")?;
+        if !std::io::stdin().is_terminal() {
+            return Err(crate::ci::fail(
+                crate::ci::ExitCode::InputRequired,
+                "--interactive needs a terminal to ask which patterns to keep",
+            ));
+        }
+        Ok(Self { input: std::io::stdin().lock(), output: std::io::stdout() })
+    }
+}
+
+impl<R: BufRead, W: Write> ConflictPrompt for TerminalPrompt<R, W> {
+    fn choose(&mut self, pair: &ConflictPair) -> Result<Option<Choice>> {
+        let (local, incoming) = (pair.local, pair.incoming);
+        let out = &mut self.output;
+        writeln!(out)?;
+        writeln!(out, "⚖️  {} pattern {}", local.tool_type, &local.pattern_hash[..local.pattern_hash.len().min(12)])?;
+        writeln!(out, "   {:<14} {:<22} [i] incoming ({})", "", "[l] local", pair.source)?;
+        let row = |label: &str, l: String, i: String| format!("   {:<14} {:<22} {}", label, l, i);
+        writeln!(out, "{}", row("score", format!("{:+}", score(local)), format!("{:+}", score(incoming))))?;
+        writeln!(out, "{}", row("successes", local.success_count.to_string(), incoming.success_count.to_string()))?;
+        writeln!(out, "{}", row("failures", local.failure_count.to_string(), incoming.failure_count.to_string()))?;
+        writeln!(
+            out,
+            "{}",
+            row(
+                "success rate",
+                format!("{:.0}%", success_rate(local) * 100.0),
+                format!("{:.0}%", success_rate(incoming) * 100.0)
+            )
+        )?;
+        writeln!(out, "   local:    {}", preview(&local.context_query))?;
+        if incoming.context_query != local.context_query {
+            writeln!(out, "   incoming: {}", preview(&incoming.context_query))?;
+        }
+
+        loop {
+            write!(out, "Keep [l]ocal or [i]ncoming? ([q] keeps local for the rest) ")?;
+            out.flush()?;
+            let mut answer = String::new();
+            if self.input.read_line(&mut answer)? == 0 {
+                return Ok(None);
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "l" | "local" => return Ok(Some(Choice::Local)),
+                "i" | "incoming" => return Ok(Some(Choice::Incoming)),
+                "q" | "quit" => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn score(pattern: &Pattern) -> i64 {
+    pattern.success_count - pattern.failure_count
+}
+
+/// First line of a context, cut to fit a terminal row
+fn preview(context: &str) -> String {
+    let line = context.lines().next().unwrap_or_default();
+    if line.chars().count() > 100 {
+        format!("{}…", line.chars().take(99).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn load_decisions(mana_dir: &Path) -> BTreeMap<String, Decision> {
+    std::fs::read_to_string(mana_dir.join(DECISIONS_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_decisions(mana_dir: &Path, decisions: &BTreeMap<String, Decision>) -> Result<()> {
+    std::fs::write(mana_dir.join(DECISIONS_FILE), serde_json::to_string_pretty(decisions)?)?;
+    Ok(())
+}
+
+/// Import patterns, asking which side wins wherever a local copy differs
+pub fn import_interactive(
+    mana_dir: &Path,
+    db_path: &Path,
+    patterns: &[ExportablePattern],
+    source: &str,
+    prompt: &mut dyn ConflictPrompt,
+) -> Result<ImportResult> {
+    let store = PatternStore::open(db_path)?;
+    let mut decisions = load_decisions(mana_dir);
+    let mut asking = true;
+    let (mut imported, mut merged, mut skipped) = (0, 0, 0);
+
+    for exportable in patterns {
+        let context_query = sanitize::localize(&exportable.context_query);
+        let incoming = Pattern {
+            id: 0,
+            pattern_hash: exportable.pattern_hash.clone(),
+            tool_type: exportable.tool_type.clone(),
+            command_category: exportable.command_category.clone(),
+            risky: sanitize::is_risky_import(exportable.risky, &context_query),
+            context_query,
+            success_count: exportable.success_count,
+            failure_count: exportable.failure_count,
+            embedding_id: None,
+        };
+        let Some(local) = find_by_hash(&store, &incoming.pattern_hash)? else {
+            merge_pattern(&store, &incoming, MergeStrategy::Add, exportable.device.as_deref())?;
+            imported += 1;
+            continue;
+        };
+        let counts = (incoming.success_count, incoming.failure_count);
+        if counts == (local.success_count, local.failure_count) {
+            skipped += 1;
+            continue;
+        }
+
+        let remembered = decisions
+            .get(&incoming.pattern_hash)
+            .filter(|d| d.incoming == counts)
+            .map(|d| d.choice);
+        let choice = match remembered {
+            Some(choice) => choice,
+            None if asking => match prompt.choose(&ConflictPair { local: &local, incoming: &incoming, source })? {
+                Some(choice) => {
+                    decisions.insert(incoming.pattern_hash.clone(), Decision { choice, incoming: counts });
+                    choice
+                }
+                None => {
+                    asking = false;
+                    Choice::Local
+                }
+            },
+            None => Choice::Local,
+        };
+
+        match choice {
+            Choice::Incoming => {
+                store.overwrite(&incoming)?;
+                merged += 1;
+            }
+            Choice::Local => skipped += 1,
+        }
+    }
+
+    save_decisions(mana_dir, &decisions)?;
+    Ok(ImportResult {
+        total: patterns.len(),
+        imported,
+        merged,
+        skipped,
+        verdicts: 0,
+        source_workspace: source.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn exportable(hash: &str, success: i64, failure: i64) -> ExportablePattern {
+        ExportablePattern {
+            pattern_hash: hash.to_string(),
+            tool_type: "Bash".to_string(),
+            command_category: None,
+            context_query: format!("context {}", hash),
+            success_count: success,
+            failure_count: failure,
+            device: None,
+            risky: false,
+        }
+    }
+
+    #[test]
+    fn test_choices_are_applied_and_remembered() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.path().join("metadata.sqlite");
+        crate::storage::create_schema(&crate::storage::db::open(&db_path).unwrap()).unwrap();
+        let local = [exportable("a", 5, 0), exportable("b", 5, 0), exportable("c", 2, 0)];
+        import_interactive(temp.path(), &db_path, &local, "local", &mut TerminalPrompt { input: &b""[..], output: Vec::new() })
+            .unwrap();
+
+        let incoming = [exportable("a", 1, 4), exportable("b", 9, 1), exportable("c", 2, 0), exportable("d", 1, 0)];
+        let mut prompt = TerminalPrompt { input: &b"x\nl\ni\n"[..], output: Vec::new() };
+        let result = import_interactive(temp.path(), &db_path, &incoming, "laptop", &mut prompt).unwrap();
+        assert_eq!((result.imported, result.merged, result.skipped), (1, 1, 2));
+        assert!(String::from_utf8(prompt.output).unwrap().contains("incoming (laptop)"));
+
+        let store = PatternStore::open(&db_path).unwrap();
+        let counts = |hash| find_by_hash(&store, hash).unwrap().map(|p| (p.success_count, p.failure_count));
+        assert_eq!(counts("a"), Some((5, 0)));
+        assert_eq!(counts("b"), Some((9, 1)));
+        assert_eq!(counts("d"), Some((1, 0)));
+
+        // Same incoming counts: answered from the stored decisions, no input needed
+        let mut silent = TerminalPrompt { input: &b""[..], output: Vec::new() };
+        let again = import_interactive(temp.path(), &db_path, &incoming[..1], "laptop", &mut silent).unwrap();
+        assert_eq!(again.skipped, 1);
+        assert!(silent.output.is_empty());
+    }
+}
@@ -1,18 +1,31 @@
 //! Pattern sanitization for secure sharing
 //!
 //! Sanitizes patterns before export to:
-//! 1. Strip absolute paths to relative
+//! 1. Map absolute paths to repo-relative or `$HOME`-relative ones
 //! 2. Redact secrets/tokens (API keys, passwords)
 //! 3. Hash sensitive identifiers
 //! 4. Generalize user-specific context
+//!
+//! `$HOME` and `$USER` are stable placeholders: [`localize`] swaps them back
+//! for this machine's values on import, so shared patterns read naturally
+//! in the new workspace and a round trip on one machine is lossless.
 
 use crate::storage::Pattern;
 use crate::sync::ExportablePattern;
 use regex::Regex;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
+/// Placeholder for the home directory in exported text
+const HOME_PLACEHOLDER: &str = "$HOME";
+/// Placeholder for the local username in exported text
+const USER_PLACEHOLDER: &str = "$USER";
+
+/// Account names too generic to replace wherever they appear as a word
+const GENERIC_USERS: &[&str] = &["root", "user", "admin", "ubuntu", "vscode", "node", "runner", "codespace", "dev"];
+
 /// Regex patterns for secret detection
 static SECRET_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
     vec![
@@ -37,22 +50,84 @@ static SECRET_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
     ]
 });
 
-/// Common home directory patterns to strip
-static HOME_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+/// Windows home directory forms, replaced with the `$HOME` placeholder
+static WINDOWS_HOME_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
     vec![
-        // Unix home directories
-        Regex::new(r#"/home/[^/\s]+/"#).unwrap(),
-        Regex::new(r#"/Users/[^/\s]+/"#).unwrap(),
-        Regex::new(r#"~/?"#).unwrap(),
-        // Windows home directories
-        Regex::new(r#"C:\\Users\\[^\\]+\\"#).unwrap(),
+        Regex::new(r#"(?i)C:\\Users\\[^\\\s]+"#).unwrap(),
         Regex::new(r#"%USERPROFILE%"#).unwrap(),
-        // Devpod/container paths
-        Regex::new(r#"/workspaces/[^/\s]+/"#).unwrap(),
-        Regex::new(r#"/workspace/"#).unwrap(),
     ]
 });
 
+/// Unix absolute paths and `~/` paths, not preceded by a word character,
+/// ':' or '/' (so URLs are left alone)
+static PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(^|[\s'"`=(\[<>,;|&])(~/(?:[\w.@+-]+/?)*|(?:/[\w.@+-]+)+/?)"#).unwrap()
+});
+
+/// Home directories of any user: /home/<name>, /Users/<name>
+static HOME_DIR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^/(?:home|Users)/[^/]+(/.*)?$"#).unwrap());
+
+/// Devpod/container workspace roots: /workspaces/<name>, /workspace
+static WORKSPACE_DIR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^/(?:workspaces/[^/]+|workspace)(?:/(.*))?$"#).unwrap());
+
+/// This machine's values behind the `$HOME` and `$USER` placeholders
+#[derive(Debug, Clone, Default)]
+pub struct PathAliases {
+    pub home: Option<PathBuf>,
+    pub user: Option<String>,
+}
+
+impl PathAliases {
+    /// Aliases for the current user
+    pub fn local() -> &'static PathAliases {
+        static LOCAL: LazyLock<PathAliases> = LazyLock::new(|| {
+            let home = dirs::home_dir();
+            let user = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .ok()
+                .or_else(|| home.as_ref().and_then(|h| h.file_name()).map(|n| n.to_string_lossy().into_owned()));
+            PathAliases { home, user }
+        });
+        &LOCAL
+    }
+
+    /// Username, if it is distinctive enough to substitute as a word
+    fn distinctive_user(&self) -> Option<&str> {
+        self.user.as_deref().filter(|u| u.len() >= 3 && !GENERIC_USERS.contains(&u.to_lowercase().as_str()))
+    }
+
+    /// Replace this machine's home and username with placeholders
+    fn to_placeholders(&self, text: &str) -> String {
+        let mut result = map_paths(text, self);
+        for pattern in WINDOWS_HOME_PATTERNS.iter() {
+            result = pattern.replace_all(&result, regex::NoExpand(HOME_PLACEHOLDER)).to_string();
+        }
+        if let Some(user) = self.distinctive_user() {
+            let word = Regex::new(&format!(r"\b{}\b", regex::escape(user))).unwrap();
+            result = word.replace_all(&result, regex::NoExpand(USER_PLACEHOLDER)).to_string();
+        }
+        result
+    }
+
+    /// Replace placeholders with this machine's home and username
+    pub fn localize(&self, text: &str) -> String {
+        let mut result = text.to_string();
+        if let Some(home) = &self.home {
+            result = result.replace(HOME_PLACEHOLDER, &home.to_string_lossy());
+        }
+        if let Some(user) = &self.user {
+            result = result.replace(USER_PLACEHOLDER, user);
+        }
+        result
+    }
+}
+
+/// Swap export placeholders for the local home directory and username
+pub fn localize(text: &str) -> String {
+    PathAliases::local().localize(text)
+}
+
 /// Sanitize a pattern for export
 ///
 /// Applies the following transformations:
@@ -73,22 +148,33 @@ pub fn sanitize_pattern(pattern: &Pattern) -> ExportablePattern {
         context_query: sanitized_context,
         success_count: pattern.success_count,
         failure_count: pattern.failure_count,
+        device: None,
+        risky: pattern.risky,
     }
 }
 
+/// Whether an incoming pattern must wait for `approve-risky`
+///
+/// Flagged by the exporting device, or destructive by this machine's
+/// classifier: a bundle can't vouch for its own patterns, so imports are
+/// classified whatever their tool or flag, and never arrive approved.
+pub(crate) fn is_risky_import(flagged: bool, context: &str) -> bool {
+    flagged || crate::learning::risk::classify_command_risk(context).is_some()
+}
+
 /// Sanitize context query text
-fn sanitize_context(context: &str) -> String {
+pub(crate) fn sanitize_context(context: &str) -> String {
     let mut result = context.to_string();
 
     // 1. Redact secrets first (before path manipulation might affect them)
     result = redact_secrets(&result);
 
-    // 2. Strip absolute paths to relative
-    result = strip_absolute_paths(&result);
-
-    // 3. Generalize user-specific patterns
+    // 2. Generalize user-specific patterns (emails before the username becomes $USER)
     result = generalize_user_context(&result);
 
+    // 3. Map absolute paths to relative ones and placeholders
+    result = strip_absolute_paths(&result);
+
     result
 }
 
@@ -116,22 +202,67 @@ fn redact_secrets(text: &str) -> String {
 
 /// Strip absolute paths to relative
 fn strip_absolute_paths(text: &str) -> String {
-    let mut result = text.to_string();
+    PathAliases::local().to_placeholders(text)
+}
 
-    // Replace home directory patterns with empty string
-    for pattern in HOME_PATTERNS.iter() {
-        result = pattern.replace_all(&result, "").to_string();
-    }
+/// Rewrite every absolute path in `text` with [`map_path`]
+fn map_paths(text: &str, aliases: &PathAliases) -> String {
+    PATH_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            let path = &caps[2];
+            let (path, slash) = match path.strip_suffix('/') {
+                Some(trimmed) if !trimmed.is_empty() && trimmed != "~" => (trimmed, "/"),
+                _ => (path, ""),
+            };
+            format!("{}{}{}", &caps[1], map_path(path, aliases), slash)
+        })
+        .to_string()
+}
 
-    // Replace remaining absolute Unix paths with just the filename
-    let abs_unix = Regex::new(r#"/[a-zA-Z0-9_.-]+(/[a-zA-Z0-9_.-]+)+"#).unwrap();
-    result = abs_unix.replace_all(&result, |caps: &regex::Captures| {
-        let path = &caps[0];
-        // Keep just the last component
-        path.rsplit('/').next().unwrap_or(path).to_string()
-    }).to_string();
+/// Map one absolute path, most specific first:
+/// 1. inside a git checkout: relative to its root
+/// 2. a devpod workspace: relative to the workspace
+/// 3. under a home directory: `$HOME/...`
+/// 4. anything else: just the file name
+fn map_path(path: &str, aliases: &PathAliases) -> String {
+    let expanded = match (path.strip_prefix("~/"), &aliases.home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    };
+
+    if let Some(root) = git_root(&expanded, aliases.home.as_deref()) {
+        let relative = expanded.strip_prefix(&root).unwrap_or(&expanded);
+        return if relative.as_os_str().is_empty() { ".".to_string() } else { relative.to_string_lossy().into_owned() };
+    }
+    if let Some(caps) = WORKSPACE_DIR_RE.captures(path) {
+        return caps.get(1).map_or(".", |m| m.as_str()).to_string();
+    }
+    if let Some(rest) = path.strip_prefix("~") {
+        return format!("{}{}", HOME_PLACEHOLDER, rest);
+    }
+    if let Some(rest) = aliases.home.as_deref().and_then(|home| expanded.strip_prefix(home).ok()) {
+        return if rest.as_os_str().is_empty() {
+            HOME_PLACEHOLDER.to_string()
+        } else {
+            format!("{}/{}", HOME_PLACEHOLDER, rest.display())
+        };
+    }
+    if let Some(caps) = HOME_DIR_RE.captures(path) {
+        return format!("{}{}", HOME_PLACEHOLDER, caps.get(1).map_or("", |m| m.as_str()));
+    }
+    if path.matches('/').count() > 1 {
+        return path.rsplit('/').next().unwrap_or(path).to_string();
+    }
+    path.to_string()
+}
 
-    result
+/// Closest enclosing git checkout, ignoring the filesystem root and the home
+/// directory itself (a dotfiles repo there would swallow everything)
+fn git_root(path: &Path, home: Option<&Path>) -> Option<PathBuf> {
+    path.ancestors()
+        .filter(|dir| dir.parent().is_some() && Some(*dir) != home)
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
 }
 
 /// Generalize user-specific context
@@ -154,7 +285,7 @@ fn generalize_user_context(text: &str) -> String {
 }
 
 /// Calculate hash for deduplication
-fn calculate_hash(content: &str) -> String {
+pub(crate) fn calculate_hash(content: &str) -> String {
     let mut hasher = DefaultHasher::new();
     content.hash(&mut hasher);
     format!("{:016x}", hasher.finish())
@@ -224,6 +355,29 @@ mod tests {
         assert!(result.contains("lib.rs"));
     }
 
+    #[test]
+    fn test_repo_relative_paths_and_placeholders() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let repo = temp.path().join("projects/app");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        let aliases = PathAliases { home: Some(PathBuf::from("/home/alice")), user: Some("alice".to_string()) };
+
+        let text = format!("cargo test --manifest-path {}/crates/core/Cargo.toml", repo.display());
+        assert_eq!(aliases.to_placeholders(&text), "cargo test --manifest-path crates/core/Cargo.toml");
+        assert_eq!(aliases.to_placeholders(&format!("cd {}", repo.display())), "cd .");
+
+        let text = "cat /home/alice/.config/tool.toml ~/notes.md; ls /home/bob/src/ https://example.com/a/b";
+        let exported = aliases.to_placeholders(text);
+        assert_eq!(exported, "cat $HOME/.config/tool.toml $HOME/notes.md; ls $HOME/src/ https://example.com/a/b");
+        assert_eq!(aliases.to_placeholders("chown alice:staff out"), "chown $USER:staff out");
+        assert_eq!(aliases.to_placeholders("/opt/tools/bin/fmt --check"), "fmt --check");
+
+        let bob = PathAliases { home: Some(PathBuf::from("/Users/bob")), user: Some("bob".to_string()) };
+        assert_eq!(bob.localize("cat $HOME/.config/tool.toml as $USER"), "cat /Users/bob/.config/tool.toml as bob");
+        let round_trip = "edit /home/alice/.bashrc";
+        assert_eq!(aliases.localize(&aliases.to_placeholders(round_trip)), round_trip);
+    }
+
     #[test]
     fn test_generalize_email() {
         let input = "Contact: user@example.com for support";
@@ -251,6 +405,7 @@ mod tests {
             success_count: 5,
             failure_count: 1,
             embedding_id: None,
+            risky: false,
         };
 
         let sanitized = sanitize_pattern(&pattern);
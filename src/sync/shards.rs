@@ -0,0 +1,358 @@
+//! Sharded pattern layout for the git and S3 backends
+//!
+//! Rather than one patterns.json that every push rewrites, the sync repo
+//! (or bucket) keeps `patterns/<tool>-<category>.jsonl` files with one pattern per line,
+//! sorted by hash, plus a manifest.json listing each shard's content hash.
+//! Pushes from different machines rarely touch the same lines, and merging
+//! happens per pattern instead of per file: each side's changes since the
+//! last synced commit are applied as count deltas, so nothing is clobbered
+//! or counted twice.
+
+use anyhow::{anyhow, bail, Result};
+use blake2::{Blake2s256, Digest};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::sync::crypto::{decrypt_string, encrypt_string, EncryptedData};
+use crate::sync::export::parse_bundle;
+use crate::sync::p2p_backend::CRDTEntry;
+use crate::sync::ExportablePattern;
+
+/// Directory holding the shard files
+pub const SHARD_DIR: &str = "patterns";
+/// Manifest listing shards and their hashes (what `--sign` signs)
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Single-file layout used before sharding, still read on pull
+pub const LEGACY_FILE: &str = "patterns.json";
+
+const LAYOUT_VERSION: &str = "2.0";
+
+/// Patterns keyed by hash
+pub type PatternMap = BTreeMap<String, ExportablePattern>;
+
+/// Contents of manifest.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardManifest {
+    pub version: String,
+    pub exported_at: String,
+    pub source_workspace: String,
+    pub pattern_count: usize,
+    pub encrypted: bool,
+    /// Shard path (relative to the repo) -> BLAKE2s-256 of its contents
+    pub shards: BTreeMap<String, String>,
+}
+
+/// A pattern both sides changed since the last sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternConflict {
+    pub pattern_hash: String,
+    pub tool_type: String,
+    /// (successes, failures) on each side and after merging
+    pub local: (i64, i64),
+    pub remote: (i64, i64),
+    pub merged: (i64, i64),
+}
+
+/// Key patterns by hash, summing counts of rows that sanitize to the same text
+pub fn collect(patterns: Vec<ExportablePattern>) -> PatternMap {
+    let mut map = PatternMap::new();
+    for pattern in patterns {
+        match map.get_mut(&pattern.pattern_hash) {
+            Some(existing) => {
+                existing.success_count += pattern.success_count;
+                existing.failure_count += pattern.failure_count;
+            }
+            None => {
+                map.insert(pattern.pattern_hash.clone(), pattern);
+            }
+        }
+    }
+    map
+}
+
+/// Write the shards and manifest into `repo`, replacing any previous layout
+pub fn write(repo: &Path, patterns: &PatternMap, passphrase: Option<&str>, source_workspace: &str) -> Result<ShardManifest> {
+    let (manifest, files) = render(patterns, passphrase, source_workspace, &load(repo)?)?;
+    store(repo, &files)?;
+    Ok(manifest)
+}
+
+/// Lay patterns out as shard files plus manifest.json (path -> contents)
+///
+/// Encryption is randomized, so an encrypted shard whose patterns didn't
+/// change keeps its `previous` ciphertext instead of showing up as changed.
+pub fn render(
+    patterns: &PatternMap,
+    passphrase: Option<&str>,
+    source_workspace: &str,
+    previous: &BTreeMap<String, String>,
+) -> Result<(ShardManifest, BTreeMap<String, String>)> {
+    let encrypted = passphrase.is_some();
+    let mut files: BTreeMap<String, String> = BTreeMap::new();
+    for pattern in patterns.values() {
+        let content = files.entry(shard_file(pattern, encrypted)).or_default();
+        content.push_str(&serde_json::to_string(pattern)?);
+        content.push('\n');
+    }
+    if let Some(passphrase) = passphrase {
+        for (name, content) in files.iter_mut() {
+            let unchanged = previous
+                .get(name)
+                .and_then(|old| serde_json::from_str::<EncryptedData>(old).ok())
+                .and_then(|old| decrypt_string(&old, passphrase).ok())
+                .is_some_and(|old| old == *content);
+            *content = if unchanged {
+                previous[name].clone()
+            } else {
+                serde_json::to_string_pretty(&encrypt_string(content, passphrase)?)?
+            };
+        }
+    }
+
+    let manifest = ShardManifest {
+        version: LAYOUT_VERSION.to_string(),
+        exported_at: Utc::now().to_rfc3339(),
+        source_workspace: source_workspace.to_string(),
+        pattern_count: patterns.len(),
+        encrypted,
+        shards: files.iter().map(|(name, content)| (name.clone(), content_hash(content.as_bytes()))).collect(),
+    };
+    files.insert(MANIFEST_FILE.to_string(), serde_json::to_string_pretty(&manifest)? + "\n");
+    Ok((manifest, files))
+}
+
+/// Write rendered files into `dir`, removing shards (and the pre-shard file) they no longer include
+pub fn store(dir: &Path, files: &BTreeMap<String, String>) -> Result<()> {
+    let shard_dir = dir.join(SHARD_DIR);
+    if shard_dir.is_dir() {
+        for entry in std::fs::read_dir(&shard_dir)? {
+            let path = entry?.path();
+            let name = format!("{}/{}", SHARD_DIR, path.file_name().unwrap_or_default().to_string_lossy());
+            if !files.contains_key(&name) {
+                std::fs::remove_file(&path)?;
+            }
+        }
+    }
+    if !files.contains_key(MANIFEST_FILE) {
+        let _ = std::fs::remove_file(dir.join(MANIFEST_FILE));
+    }
+    if !files.contains_key(LEGACY_FILE) {
+        for legacy in [LEGACY_FILE.to_string(), format!("{}.sig", LEGACY_FILE)] {
+            let _ = std::fs::remove_file(dir.join(legacy));
+        }
+    }
+
+    std::fs::create_dir_all(&shard_dir)?;
+    for (name, content) in files {
+        std::fs::write(dir.join(name), content)?;
+    }
+    Ok(())
+}
+
+/// Read the manifest, shards and any pre-shard file from `dir`
+pub fn load(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for name in [MANIFEST_FILE, LEGACY_FILE] {
+        if let Ok(content) = std::fs::read_to_string(dir.join(name)) {
+            files.insert(name.to_string(), content);
+        }
+    }
+    let shard_dir = dir.join(SHARD_DIR);
+    if shard_dir.is_dir() {
+        for entry in std::fs::read_dir(&shard_dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                let name = format!("{}/{}", SHARD_DIR, path.file_name().unwrap_or_default().to_string_lossy());
+                files.insert(name, std::fs::read_to_string(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Parse a repo's files (path -> contents) back into patterns
+///
+/// Shards are checked against the manifest's hashes; a repo still using
+/// the single-file layout is read from patterns.json.
+pub fn parse(files: &BTreeMap<String, String>, passphrase: Option<&str>) -> Result<PatternMap> {
+    let Some(manifest) = files.get(MANIFEST_FILE) else {
+        return match files.get(LEGACY_FILE) {
+            Some(content) => Ok(collect(parse_bundle(content, passphrase)?.patterns)),
+            None => Ok(PatternMap::new()),
+        };
+    };
+    let manifest: ShardManifest = serde_json::from_str(manifest)?;
+
+    let mut patterns = Vec::new();
+    for (name, hash) in &manifest.shards {
+        let content = files.get(name).ok_or_else(|| anyhow!("Shard {} listed in the manifest is missing", name))?;
+        if content_hash(content.as_bytes()) != *hash {
+            bail!("Shard {} doesn't match the manifest hash", name);
+        }
+        let lines = if manifest.encrypted {
+            let passphrase = passphrase.ok_or_else(|| anyhow!("Passphrase required to decrypt synced patterns"))?;
+            decrypt_string(&serde_json::from_str::<EncryptedData>(content)?, passphrase)?
+        } else {
+            content.clone()
+        };
+        for line in lines.lines().filter(|l| !l.trim().is_empty()) {
+            patterns.push(serde_json::from_str::<ExportablePattern>(line)?);
+        }
+    }
+    Ok(collect(patterns))
+}
+
+/// What changed from `base` to `theirs`, as counts to add on top of `base`
+pub fn delta(base: &PatternMap, theirs: &PatternMap) -> Vec<ExportablePattern> {
+    theirs
+        .values()
+        .filter_map(|pattern| {
+            let (s, f) = base.get(&pattern.pattern_hash).map_or((0, 0), counts);
+            let added = ExportablePattern {
+                success_count: (pattern.success_count - s).max(0),
+                failure_count: (pattern.failure_count - f).max(0),
+                ..pattern.clone()
+            };
+            (added.success_count > 0 || added.failure_count > 0).then_some(added)
+        })
+        .collect()
+}
+
+/// Patterns that are new or different in `theirs`, with their full counts
+pub fn changed(base: &PatternMap, theirs: &PatternMap) -> Vec<ExportablePattern> {
+    theirs
+        .values()
+        .filter(|p| base.get(&p.pattern_hash).map(counts) != Some(counts(p)))
+        .cloned()
+        .collect()
+}
+
+/// Patterns changed on both sides since `base` and now different
+pub fn conflicts(base: &PatternMap, ours: &PatternMap, theirs: &PatternMap) -> Vec<PatternConflict> {
+    ours.values()
+        .filter_map(|local| {
+            let remote = theirs.get(&local.pattern_hash)?;
+            let before = base.get(&local.pattern_hash).map_or((0, 0), counts);
+            let (l, r) = (counts(local), counts(remote));
+            if l == r || l == before || r == before {
+                return None;
+            }
+            Some(PatternConflict {
+                pattern_hash: local.pattern_hash.clone(),
+                tool_type: local.tool_type.clone(),
+                local: l,
+                remote: r,
+                // Both sides' increments are kept
+                merged: (l.0 + r.0 - before.0, l.1 + r.1 - before.1),
+            })
+        })
+        .collect()
+}
+
+/// Fold `theirs` into `ours`, keeping the higher counts for shared patterns
+pub fn union(ours: &mut PatternMap, theirs: &PatternMap) {
+    for (hash, remote) in theirs {
+        let merged = match ours.get(hash) {
+            Some(local) => {
+                let local = CRDTEntry::new(local.clone(), "local");
+                local.merge_add_only(&CRDTEntry::new(remote.clone(), "remote")).pattern
+            }
+            None => remote.clone(),
+        };
+        ours.insert(hash.clone(), merged);
+    }
+}
+
+fn counts(pattern: &ExportablePattern) -> (i64, i64) {
+    (pattern.success_count, pattern.failure_count)
+}
+
+/// Shard path for a pattern: `patterns/<tool>-<category>.jsonl`
+fn shard_file(pattern: &ExportablePattern, encrypted: bool) -> String {
+    let name = match pattern.command_category.as_deref().filter(|c| !c.is_empty()) {
+        Some(category) => format!("{}-{}", pattern.tool_type, category),
+        None => pattern.tool_type.clone(),
+    };
+    let slug: String =
+        name.to_lowercase().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '-' }).collect();
+    let slug = slug.trim_matches('-');
+    let slug = if slug.is_empty() { "other" } else { slug };
+    format!("{}/{}.jsonl{}", SHARD_DIR, slug, if encrypted { ".enc" } else { "" })
+}
+
+/// BLAKE2s-256 of a shard, as listed in the manifest
+pub fn content_hash(bytes: &[u8]) -> String {
+    Blake2s256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(hash: &str, tool: &str, success: i64, failure: i64) -> ExportablePattern {
+        ExportablePattern {
+            pattern_hash: hash.to_string(),
+            tool_type: tool.to_string(),
+            command_category: Some("cargo".to_string()),
+            context_query: format!("Task: {}", hash),
+            success_count: success,
+            failure_count: failure,
+            device: None,
+            risky: false,
+        }
+    }
+
+    #[test]
+    fn test_write_and_parse_round_trip() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::write(temp.path().join(LEGACY_FILE), "{}").unwrap();
+        let patterns = collect(vec![pattern("a", "Bash", 2, 0), pattern("b", "Edit", 1, 1), pattern("a", "Bash", 1, 0)]);
+        assert_eq!(patterns["a"].success_count, 3);
+
+        for passphrase in [None, Some("secret")] {
+            let manifest = write(temp.path(), &patterns, passphrase, "ws").unwrap();
+            assert_eq!(manifest.shards.len(), 2);
+            // Rewriting the same patterns leaves the shards untouched, encrypted or not
+            assert_eq!(write(temp.path(), &patterns, passphrase, "ws").unwrap().shards, manifest.shards);
+            assert!(!temp.path().join(LEGACY_FILE).exists());
+
+            let mut files = BTreeMap::new();
+            for name in manifest.shards.keys().map(String::as_str).chain([MANIFEST_FILE]) {
+                files.insert(name.to_string(), std::fs::read_to_string(temp.path().join(name)).unwrap());
+            }
+            let parsed = parse(&files, passphrase).unwrap();
+            assert_eq!(parsed.len(), 2);
+            assert_eq!(counts(&parsed["b"]), (1, 1));
+
+            let shard = manifest.shards.keys().next().unwrap().clone();
+            files.insert(shard, "tampered".to_string());
+            assert!(parse(&files, passphrase).is_err());
+        }
+        // Switching to encrypted shards removed the plain ones
+        assert_eq!(std::fs::read_dir(temp.path().join(SHARD_DIR)).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_delta_and_conflicts() {
+        let base = collect(vec![pattern("a", "Bash", 5, 1), pattern("b", "Bash", 2, 0)]);
+        let ours = collect(vec![pattern("a", "Bash", 7, 1), pattern("b", "Bash", 2, 0)]);
+        let theirs = collect(vec![pattern("a", "Bash", 6, 2), pattern("b", "Bash", 2, 0), pattern("c", "Edit", 1, 0)]);
+
+        let incoming = delta(&base, &theirs);
+        let summary: Vec<(&str, i64, i64)> =
+            incoming.iter().map(|p| (p.pattern_hash.as_str(), p.success_count, p.failure_count)).collect();
+        assert_eq!(summary, vec![("a", 1, 1), ("c", 1, 0)]);
+        assert_eq!(changed(&base, &theirs).len(), 2);
+
+        let found = conflicts(&base, &ours, &theirs);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].local, found[0].remote, found[0].merged), ((7, 1), (6, 2), (8, 2)));
+
+        let mut merged = ours.clone();
+        union(&mut merged, &theirs);
+        assert_eq!(counts(&merged["a"]), (7, 2));
+        assert!(merged.contains_key("c"));
+    }
+}
@@ -0,0 +1,227 @@
+//! Signed exports
+//!
+//! Patterns end up steering what an assistant runs in your shell, so teams
+//! may want to accept only exports from known people. `--sign` signs an
+//! export with an Ed25519 SSH key through `ssh-keygen -Y sign`, which writes
+//! a detached `<file>.sig` next to it. Imports and pulls verify that
+//! signature against the `trusted_keys` in the `[signing]` section of
+//! sync.toml, and `require_signed` turns unsigned files away.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::io::Write;
+
+/// Signature namespace, so a MANA signature can't be replayed as e.g. a git one
+pub const NAMESPACE: &str = "mana-export";
+
+/// Signing key used when neither `--sign-key` nor the config names one
+const DEFAULT_KEY: &str = "~/.ssh/id_ed25519";
+
+/// `[signing]` section of sync.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigningConfig {
+    /// Private key used by `--sign` (defaults to ~/.ssh/id_ed25519)
+    pub key: Option<String>,
+    /// Public keys (`ssh-ed25519 AAAA... comment`) whose signatures are accepted
+    pub trusted_keys: Vec<String>,
+    /// Refuse unsigned imports and pulls
+    pub require_signed: bool,
+}
+
+impl SigningConfig {
+    /// Private key to sign with
+    pub fn signing_key(&self, explicit: Option<&str>) -> PathBuf {
+        let raw = explicit.or(self.key.as_deref()).unwrap_or(DEFAULT_KEY);
+        match (raw.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(raw),
+        }
+    }
+}
+
+/// Outcome of checking a file's signature
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureStatus {
+    Unsigned,
+    /// Signed by a trusted key, named by its comment
+    Verified { signer: String },
+}
+
+/// Detached signature path for a file (`<file>.sig`)
+pub fn signature_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+/// Sign a file, writing `<file>.sig`
+pub fn sign_file(file: &Path, key: &Path) -> Result<PathBuf> {
+    if !key.exists() {
+        bail!("Signing key {} not found. Create one with: ssh-keygen -t ed25519", key.display());
+    }
+    // ssh-keygen won't overwrite an existing signature
+    let sig = signature_path(file);
+    if sig.exists() {
+        std::fs::remove_file(&sig)?;
+    }
+
+    let mut cmd = Command::new("ssh-keygen");
+    cmd.args(["-Y", "sign", "-n", NAMESPACE, "-f"]).arg(key).arg(file);
+    run(cmd, "sign")?;
+    Ok(sig)
+}
+
+/// Verify `<file>.sig` against the trusted keys
+///
+/// Files without a signature are reported as unsigned; a signature that
+/// doesn't verify, or that no trusted key made, is an error.
+pub fn verify_file(file: &Path, trusted_keys: &[String]) -> Result<SignatureStatus> {
+    verify_in_namespace(file, trusted_keys, NAMESPACE)
+}
+
+/// [`verify_file`] for signatures made in another namespace
+pub fn verify_in_namespace(file: &Path, trusted_keys: &[String], namespace: &str) -> Result<SignatureStatus> {
+    let sig = signature_path(file);
+    if !sig.exists() {
+        return Ok(SignatureStatus::Unsigned);
+    }
+    if trusted_keys.is_empty() {
+        bail!(
+            "{} is signed but no trusted keys are configured. Add signers to trusted_keys under [signing] in sync.toml",
+            file.display()
+        );
+    }
+
+    // Created with O_EXCL and mode 0600 under a random name, so no other user can
+    // plant or swap the list ssh-keygen trusts; removed when dropped
+    let mut allowed = tempfile::Builder::new().prefix("mana-allowed-signers-").tempfile()?;
+    allowed.write_all(allowed_signers(trusted_keys, namespace)?.as_bytes())?;
+    allowed.flush()?;
+    let principal = verify_with(file, &sig, allowed.path(), namespace)?;
+
+    let index: usize = principal.trim_start_matches("signer-").parse().unwrap_or(0);
+    Ok(SignatureStatus::Verified { signer: describe_key(&trusted_keys[index.min(trusted_keys.len() - 1)]) })
+}
+
+/// Find which allowed signer made `sig` and check it covers `file`
+fn verify_with(file: &Path, sig: &Path, allowed: &Path, namespace: &str) -> Result<String> {
+    let mut cmd = Command::new("ssh-keygen");
+    cmd.args(["-Y", "find-principals", "-s"]).arg(sig).arg("-f").arg(allowed);
+    let principal = run(cmd, "find-principals")
+        .map_err(|_| crate::ci::fail(crate::ci::ExitCode::Verification, format!("{} was not signed by a trusted key", file.display())))?;
+    let principal = String::from_utf8_lossy(&principal.stdout).lines().next().unwrap_or_default().trim().to_string();
+
+    let mut cmd = Command::new("ssh-keygen");
+    cmd.args(["-Y", "verify", "-n", namespace, "-I", &principal, "-s"])
+        .arg(sig)
+        .arg("-f")
+        .arg(allowed)
+        .stdin(std::fs::File::open(file)?);
+    run(cmd, "verify").map_err(|_| {
+        crate::ci::fail(crate::ci::ExitCode::Verification, format!("Signature check failed for {}: the file was modified after signing", file.display()))
+    })?;
+    Ok(principal)
+}
+
+/// Verify a file and apply the signing policy
+pub fn check(file: &Path, config: &SigningConfig, require_signed: bool) -> Result<SignatureStatus> {
+    let status = verify_file(file, &config.trusted_keys)?;
+    if status == SignatureStatus::Unsigned && (require_signed || config.require_signed) {
+        return Err(crate::ci::fail(crate::ci::ExitCode::Verification, format!("{} is not signed, and unsigned imports are not allowed", file.display())));
+    }
+    Ok(status)
+}
+
+/// Build an allowed_signers file with one `signer-N` principal per key
+fn allowed_signers(trusted_keys: &[String], namespace: &str) -> Result<String> {
+    let mut lines = String::new();
+    for (i, key) in trusted_keys.iter().enumerate() {
+        let mut parts = key.split_whitespace();
+        let (Some("ssh-ed25519"), Some(blob)) = (parts.next(), parts.next()) else {
+            bail!("Trusted key {:?} is not an Ed25519 public key (ssh-ed25519 AAAA...)", key);
+        };
+        lines.push_str(&format!("signer-{} namespaces=\"{}\" ssh-ed25519 {}\n", i, namespace, blob));
+    }
+    Ok(lines)
+}
+
+/// A key's comment, or the start of its key material when it has none
+fn describe_key(key: &str) -> String {
+    let parts: Vec<&str> = key.split_whitespace().collect();
+    match parts.as_slice() {
+        [_, _, comment @ ..] if !comment.is_empty() => comment.join(" "),
+        [_, blob] => format!("{}…", &blob[..blob.len().min(20)]),
+        _ => key.to_string(),
+    }
+}
+
+fn run(mut cmd: Command, action: &str) -> Result<Output> {
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow!("'ssh-keygen' not found. Install OpenSSH 8.1 or later to sign exports"),
+            _ => anyhow!("Failed to run ssh-keygen: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()))
+            .with_context(|| format!("ssh-keygen -Y {} failed", action));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_allowed_signers() {
+        let keys = vec![
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIA alice@example".to_string(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIB".to_string(),
+        ];
+        let allowed = allowed_signers(&keys, NAMESPACE).unwrap();
+        assert!(allowed.starts_with("signer-0 namespaces=\"mana-export\" ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIA\n"));
+        assert!(allowed.contains("signer-1 "));
+        assert!(allowed_signers(&["ssh-rsa AAAAB3Nza bob".to_string()], NAMESPACE).is_err());
+
+        assert_eq!(describe_key(&keys[0]), "alice@example");
+        assert_eq!(signature_path(Path::new("out/patterns.json")), PathBuf::from("out/patterns.json.sig"));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let temp = TempDir::new().unwrap();
+        let key = temp.path().join("id_ed25519");
+        let generated = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "alice@example", "-f"])
+            .arg(&key)
+            .output();
+        if !generated.is_ok_and(|o| o.status.success()) {
+            return; // no ssh-keygen on this machine
+        }
+        let public = std::fs::read_to_string(key.with_extension("pub")).unwrap().trim().to_string();
+
+        let file = temp.path().join("patterns.json");
+        std::fs::write(&file, "{}").unwrap();
+        let config = SigningConfig { trusted_keys: vec![public], ..Default::default() };
+        assert_eq!(check(&file, &config, false).unwrap(), SignatureStatus::Unsigned);
+        assert!(check(&file, &config, true).is_err());
+
+        sign_file(&file, &key).unwrap();
+        assert_eq!(
+            check(&file, &config, true).unwrap(),
+            SignatureStatus::Verified { signer: "alice@example".to_string() }
+        );
+
+        std::fs::write(&file, "{\"tampered\": true}").unwrap();
+        assert!(verify_file(&file, &config.trusted_keys).is_err());
+        let stranger = SigningConfig { trusted_keys: vec!["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOm6 bob".to_string()], ..Default::default() };
+        assert!(verify_file(&file, &stranger.trusted_keys).is_err());
+    }
+}
@@ -0,0 +1,653 @@
+//! SQLite snapshot export and import
+//!
+//! JSON exports only carry pattern text and counts. A snapshot is a small
+//! standalone SQLite database with the patterns, the causal edges between
+//! them and optionally their embedding vectors, plus a manifest of content
+//! hashes that import checks before merging anything.
+//!
+//! Rows are keyed by pattern hash rather than id so edges and vectors can
+//! be re-attached to whatever ids the patterns get in the receiving database.
+
+use anyhow::{anyhow, bail, Context, Result};
+use blake2::{Blake2s256, Digest};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+use crate::embeddings::{EmbeddingConfig, VectorIndex};
+use crate::storage::{self, db, has_column, Pattern, PatternFilter, PatternStore};
+use crate::sync::crypto::hash_workspace_id;
+use crate::sync::export::{merge_pattern, ImportResult, MergeOutcome, MergeStrategy};
+use crate::sync::sanitize::sanitize_pattern;
+use crate::sync::SecurityConfig;
+
+/// Snapshot format version written to the manifest
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// First bytes of every SQLite database file
+const SQLITE_MAGIC: &[u8; 16] = b"SQLite format 3\0";
+
+const SNAPSHOT_SCHEMA: &str = r#"
+    CREATE TABLE manifest (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        data TEXT NOT NULL
+    );
+    CREATE TABLE patterns (
+        pattern_hash TEXT PRIMARY KEY,
+        tool_type TEXT NOT NULL,
+        command_category TEXT,
+        context_query TEXT NOT NULL,
+        success_count INTEGER NOT NULL,
+        failure_count INTEGER NOT NULL
+    );
+    CREATE TABLE causal_edges (
+        pattern_a_hash TEXT NOT NULL,
+        pattern_b_hash TEXT NOT NULL,
+        lift REAL NOT NULL,
+        co_occurrences INTEGER NOT NULL,
+        PRIMARY KEY (pattern_a_hash, pattern_b_hash)
+    );
+    CREATE TABLE vectors (
+        pattern_hash TEXT PRIMARY KEY,
+        vector BLOB NOT NULL
+    );
+"#;
+
+/// Describes a snapshot and pins its contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Snapshot format version
+    pub version: u32,
+    /// Export timestamp
+    pub exported_at: String,
+    /// Source workspace identifier (hashed)
+    pub source_workspace: String,
+    /// MANA version that wrote the snapshot
+    pub mana_version: String,
+    pub pattern_count: usize,
+    pub edge_count: usize,
+    pub vector_count: usize,
+    /// Embedding model the vectors were produced with
+    pub embedding_model: Option<String>,
+    pub dimensions: Option<usize>,
+    /// Content hash of each table
+    pub hashes: ContentHashes,
+    /// Device that wrote the snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+}
+
+/// BLAKE2s-256 of each table's rows in a canonical order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentHashes {
+    pub patterns: String,
+    pub causal_edges: String,
+    pub vectors: String,
+}
+
+/// Result of importing a snapshot
+#[derive(Debug, Clone)]
+pub struct SnapshotImport {
+    pub manifest: SnapshotManifest,
+    pub patterns: ImportResult,
+    /// Causal edges created locally
+    pub edges_imported: usize,
+    /// Causal edges merged into existing ones
+    pub edges_merged: usize,
+    /// Vectors added to the local index
+    pub vectors_imported: usize,
+    /// Vectors not used (model mismatch or pattern already embedded)
+    pub vectors_skipped: usize,
+}
+
+/// Whether a file is a SQLite database (and so possibly a snapshot)
+pub fn is_snapshot(path: &Path) -> bool {
+    let mut magic = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| &magic == SQLITE_MAGIC)
+}
+
+/// Export patterns, causal edges and optionally vectors to a snapshot database
+///
+/// Risky patterns still awaiting approval are left out. Sanitization
+/// follows `security` like the JSON export. Vectors are only
+/// kept for patterns whose text sanitization left unchanged, since the
+/// vector would otherwise describe text that isn't in the snapshot.
+pub fn export_snapshot(
+    mana_dir: &Path,
+    output: &Path,
+    security: &SecurityConfig,
+    filter: &PatternFilter,
+    include_vectors: bool,
+) -> Result<SnapshotManifest> {
+    let source = db::open_readonly(&mana_dir.join("metadata.sqlite"))?;
+    let sanitize = security.sanitize_paths || security.redact_secrets;
+    let selected = if filter.is_empty() { None } else { Some(filter.matching_ids(&source)?) };
+
+    // Patterns by (possibly sanitized) hash; sanitization can collapse several into one
+    let risky_filter = match (has_column(&source, "patterns", "risky"), has_column(&source, "patterns", "approved_at")) {
+        (true, true) => "WHERE risky = 0 OR approved_at IS NOT NULL",
+        (true, false) => "WHERE risky = 0",
+        _ => "",
+    };
+    let mut stmt = source.prepare(&format!(
+        "SELECT id, pattern_hash, tool_type, command_category, context_query, success_count, failure_count
+         FROM patterns {}",
+        risky_filter
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Pattern {
+            id: row.get(0)?,
+            pattern_hash: row.get(1)?,
+            tool_type: row.get(2)?,
+            command_category: row.get(3)?,
+            context_query: row.get(4)?,
+            success_count: row.get(5)?,
+            failure_count: row.get(6)?,
+            embedding_id: None,
+            risky: false,
+        })
+    })?;
+
+    let mut patterns: BTreeMap<String, Pattern> = BTreeMap::new();
+    let mut hash_of: HashMap<i64, String> = HashMap::new();
+    let mut text_unchanged: HashSet<i64> = HashSet::new();
+    for row in rows {
+        let original = row?;
+        if selected.as_ref().is_some_and(|ids| !ids.contains(&original.id)) {
+            continue;
+        }
+        let pattern = if sanitize {
+            let exportable = sanitize_pattern(&original);
+            Pattern {
+                pattern_hash: exportable.pattern_hash,
+                context_query: exportable.context_query,
+                ..original.clone()
+            }
+        } else {
+            original.clone()
+        };
+        if pattern.context_query == original.context_query {
+            text_unchanged.insert(original.id);
+        }
+        hash_of.insert(original.id, pattern.pattern_hash.clone());
+        match patterns.get_mut(&pattern.pattern_hash) {
+            Some(existing) => {
+                existing.success_count += pattern.success_count;
+                existing.failure_count += pattern.failure_count;
+            }
+            None => {
+                patterns.insert(pattern.pattern_hash.clone(), pattern);
+            }
+        }
+    }
+    drop(stmt);
+
+    if patterns.is_empty() && selected.is_some() {
+        return Err(anyhow!("No patterns match the export filters"));
+    }
+    if patterns.is_empty() {
+        return Err(anyhow!("No patterns to export"));
+    }
+
+    // Causal edges, re-keyed by hash with the smaller hash first
+    let mut edges: BTreeMap<(String, String), (f64, i64)> = BTreeMap::new();
+    let mut stmt = source.prepare("SELECT pattern_a_id, pattern_b_id, lift, co_occurrences FROM causal_edges")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, f64>(2)?, row.get::<_, i64>(3)?)))?;
+    for row in rows {
+        let (a, b, lift, co) = row?;
+        let (Some(a), Some(b)) = (hash_of.get(&a), hash_of.get(&b)) else {
+            continue;
+        };
+        if a == b {
+            continue;
+        }
+        let key = if a < b { (a.clone(), b.clone()) } else { (b.clone(), a.clone()) };
+        let entry = edges.entry(key).or_insert((0.0, 0));
+        let total = entry.1 + co.max(1);
+        entry.0 = (entry.0 * entry.1 as f64 + lift * co.max(1) as f64) / total as f64;
+        entry.1 = total;
+    }
+    drop(stmt);
+
+    // Vectors stored alongside the patterns
+    let mut vectors: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut embedding: Option<(String, usize)> = None;
+    if include_vectors && has_column(&source, "patterns", "embedding") {
+        embedding = Some(embedding_model(&source));
+        let mut stmt = source.prepare("SELECT id, embedding FROM patterns WHERE embedding IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+        for row in rows {
+            let (id, blob) = row?;
+            if let (true, Some(hash)) = (text_unchanged.contains(&id), hash_of.get(&id)) {
+                vectors.entry(hash.clone()).or_insert(blob);
+            }
+        }
+    }
+
+    // Build the snapshot beside the target and move it into place when complete
+    let file_name = output.file_name().ok_or_else(|| anyhow!("Invalid output path: {}", output.display()))?;
+    let tmp = output.with_file_name(format!("{}.tmp", file_name.to_string_lossy()));
+    if tmp.exists() {
+        std::fs::remove_file(&tmp)?;
+    }
+    let mut conn = Connection::open(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+    conn.execute_batch(SNAPSHOT_SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO patterns (pattern_hash, tool_type, command_category, context_query, success_count, failure_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for p in patterns.values() {
+            insert.execute(params![p.pattern_hash, p.tool_type, p.command_category, p.context_query, p.success_count, p.failure_count])?;
+        }
+        let mut insert = tx.prepare(
+            "INSERT INTO causal_edges (pattern_a_hash, pattern_b_hash, lift, co_occurrences) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for ((a, b), (lift, co)) in &edges {
+            insert.execute(params![a, b, lift, co])?;
+        }
+        let mut insert = tx.prepare("INSERT INTO vectors (pattern_hash, vector) VALUES (?1, ?2)")?;
+        for (hash, blob) in &vectors {
+            insert.execute(params![hash, blob])?;
+        }
+    }
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        source_workspace: hash_workspace_id(&std::env::current_dir()?.to_string_lossy()),
+        mana_version: env!("CARGO_PKG_VERSION").to_string(),
+        pattern_count: patterns.len(),
+        edge_count: edges.len(),
+        vector_count: vectors.len(),
+        dimensions: embedding.as_ref().filter(|_| !vectors.is_empty()).map(|(_, dims)| *dims),
+        embedding_model: embedding.filter(|_| !vectors.is_empty()).map(|(model, _)| model),
+        hashes: content_hashes(&tx)?,
+        device: Some(crate::sync::device::name(mana_dir)),
+    };
+    tx.execute("INSERT INTO manifest (id, data) VALUES (1, ?1)", params![serde_json::to_string(&manifest)?])?;
+    tx.commit()?;
+    drop(conn);
+
+    std::fs::rename(&tmp, output)?;
+    info!(
+        "Exported snapshot with {} patterns, {} edges, {} vectors to {:?}",
+        manifest.pattern_count, manifest.edge_count, manifest.vector_count, output
+    );
+    Ok(manifest)
+}
+
+/// Read a snapshot's manifest and check its contents against it
+pub fn verify_snapshot(snapshot: &Connection) -> Result<SnapshotManifest> {
+    let data: String = snapshot
+        .query_row("SELECT data FROM manifest WHERE id = 1", [], |row| row.get(0))
+        .context("Not a MANA snapshot (no manifest)")?;
+    let manifest: SnapshotManifest = serde_json::from_str(&data).context("Invalid snapshot manifest")?;
+    if manifest.version > SNAPSHOT_VERSION {
+        bail!(
+            "Snapshot format v{} is newer than this MANA supports (v{}). Run 'mana update'",
+            manifest.version,
+            SNAPSHOT_VERSION
+        );
+    }
+
+    let actual = content_hashes(snapshot)?;
+    let checks = [
+        ("patterns", &manifest.hashes.patterns, &actual.patterns),
+        ("causal_edges", &manifest.hashes.causal_edges, &actual.causal_edges),
+        ("vectors", &manifest.hashes.vectors, &actual.vectors),
+    ];
+    for (table, expected, found) in checks {
+        if expected != found {
+            bail!("Snapshot integrity check failed: {} content does not match the manifest", table);
+        }
+    }
+    Ok(manifest)
+}
+
+/// Merge a snapshot into the local database
+///
+/// Patterns go through the usual merge strategy. Edges are attached by
+/// pattern hash; with `add` their lifts are averaged by co-occurrence,
+/// `replace` overwrites and `keep-best` keeps the better-observed edge.
+/// Vectors are only used if they come from the local embedding model.
+pub fn import_snapshot(mana_dir: &Path, input: &Path, merge_strategy: MergeStrategy) -> Result<SnapshotImport> {
+    let snapshot = db::open_readonly(input)?;
+    let manifest = verify_snapshot(&snapshot)?;
+    info!(
+        "Importing snapshot of {} patterns from {} (exported at {})",
+        manifest.pattern_count, manifest.source_workspace, manifest.exported_at
+    );
+
+    let db_path = mana_dir.join("metadata.sqlite");
+    let mut conn = db::open(&db_path)?;
+    storage::create_schema(&conn)?;
+
+    // Patterns
+    let mut stmt = snapshot.prepare(
+        "SELECT pattern_hash, tool_type, command_category, context_query, success_count, failure_count FROM patterns",
+    )?;
+    let incoming: Vec<Pattern> = stmt
+        .query_map([], |row| {
+            Ok(Pattern {
+                id: 0,
+                pattern_hash: row.get(0)?,
+                tool_type: row.get(1)?,
+                command_category: row.get(2)?,
+                context_query: row.get(3)?,
+                success_count: row.get(4)?,
+                failure_count: row.get(5)?,
+                embedding_id: None,
+                risky: false,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    drop(stmt);
+
+    let mut result = ImportResult {
+        total: incoming.len(),
+        imported: 0,
+        merged: 0,
+        skipped: 0,
+        verdicts: 0,
+        source_workspace: manifest.source_workspace.clone(),
+    };
+    let store = PatternStore::open(&db_path)?;
+    for pattern in &incoming {
+        match merge_pattern(&store, pattern, merge_strategy, manifest.device.as_deref())? {
+            MergeOutcome::Imported => result.imported += 1,
+            MergeOutcome::Merged => result.merged += 1,
+            MergeOutcome::Skipped => result.skipped += 1,
+        }
+    }
+    drop(store);
+
+    let mut local_ids: HashMap<String, i64> = HashMap::new();
+    {
+        let mut lookup = conn.prepare("SELECT id FROM patterns WHERE pattern_hash = ?1")?;
+        for pattern in &incoming {
+            if let Some(id) = lookup.query_row(params![pattern.pattern_hash], |row| row.get(0)).optional()? {
+                local_ids.insert(pattern.pattern_hash.clone(), id);
+            }
+        }
+    }
+
+    // Causal edges
+    let mut stmt = snapshot.prepare("SELECT pattern_a_hash, pattern_b_hash, lift, co_occurrences FROM causal_edges")?;
+    let edges: Vec<(String, String, f64, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    drop(stmt);
+
+    let (mut edges_imported, mut edges_merged) = (0, 0);
+    let tx = db::write_transaction(&mut conn)?;
+    for (a, b, lift, co) in edges {
+        let (Some(&a), Some(&b)) = (local_ids.get(&a), local_ids.get(&b)) else {
+            continue;
+        };
+        if a == b {
+            continue;
+        }
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        let co = co.max(1);
+        let existing: Option<(i64, f64, i64)> = tx
+            .query_row(
+                "SELECT id, lift, co_occurrences FROM causal_edges WHERE pattern_a_id = ?1 AND pattern_b_id = ?2",
+                params![a, b],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        match existing {
+            None => {
+                tx.execute(
+                    "INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (?1, ?2, ?3, ?4)",
+                    params![a, b, lift, co],
+                )?;
+                edges_imported += 1;
+            }
+            Some((id, local_lift, local_co)) => {
+                let local_co = local_co.max(1);
+                let (lift, co) = match merge_strategy {
+                    MergeStrategy::Add => {
+                        let total = local_co + co;
+                        ((local_lift * local_co as f64 + lift * co as f64) / total as f64, total)
+                    }
+                    MergeStrategy::Replace => (lift, co),
+                    MergeStrategy::KeepBest | MergeStrategy::Interactive if co > local_co => (lift, co),
+                    MergeStrategy::KeepBest | MergeStrategy::Interactive => continue,
+                };
+                tx.execute(
+                    "UPDATE causal_edges SET lift = ?1, co_occurrences = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+                    params![lift, co, id],
+                )?;
+                edges_merged += 1;
+            }
+        }
+    }
+    tx.commit()?;
+
+    let (vectors_imported, vectors_skipped) = import_vectors(mana_dir, &conn, &snapshot, &manifest, &local_ids)?;
+
+    Ok(SnapshotImport { manifest, patterns: result, edges_imported, edges_merged, vectors_imported, vectors_skipped })
+}
+
+/// Copy snapshot vectors into the local index for patterns that have none
+fn import_vectors(
+    mana_dir: &Path,
+    conn: &Connection,
+    snapshot: &Connection,
+    manifest: &SnapshotManifest,
+    local_ids: &HashMap<String, i64>,
+) -> Result<(usize, usize)> {
+    if manifest.vector_count == 0 {
+        return Ok((0, 0));
+    }
+    let (model, dimensions) = embedding_model(conn);
+    if manifest.embedding_model.as_deref() != Some(model.as_str()) || manifest.dimensions != Some(dimensions) {
+        info!("Skipping snapshot vectors: produced by {:?}, local model is {}", manifest.embedding_model, model);
+        return Ok((0, manifest.vector_count));
+    }
+
+    let index_path = mana_dir.join("vectors.usearch");
+    let mut index = if index_path.exists() { VectorIndex::load(&index_path)? } else { VectorIndex::new(dimensions) };
+    if index.dimensions() != dimensions {
+        return Ok((0, manifest.vector_count));
+    }
+    let indexed: HashSet<i64> = index.ids().iter().copied().collect();
+
+    let mut stmt = snapshot.prepare("SELECT pattern_hash, vector FROM vectors")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))?;
+    let (mut imported, mut skipped) = (0, 0);
+    for row in rows {
+        let (hash, blob) = row?;
+        let Some(&id) = local_ids.get(&hash) else {
+            skipped += 1;
+            continue;
+        };
+        if indexed.contains(&id) || blob.len() != dimensions * 4 {
+            skipped += 1;
+            continue;
+        }
+        let updated = conn.execute(
+            "UPDATE patterns SET embedding = ?1, embedding_version = 1 WHERE id = ?2 AND embedding IS NULL",
+            params![blob, id],
+        )?;
+        if updated == 0 {
+            skipped += 1;
+            continue;
+        }
+        let vector: Vec<f32> = blob.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        index.add(id, &vector)?;
+        imported += 1;
+    }
+
+    if imported > 0 {
+        index.save(&index_path)?;
+    }
+    Ok((imported, skipped))
+}
+
+/// Embedding model and dimensions recorded for a database
+fn embedding_model(conn: &Connection) -> (String, usize) {
+    conn.query_row(
+        "SELECT model_name, dimensions FROM embedding_meta ORDER BY id DESC LIMIT 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .unwrap_or_else(|_| {
+        let config = EmbeddingConfig::default();
+        (config.model, config.dimensions)
+    })
+}
+
+/// Hash every table in a fixed row order
+fn content_hashes(conn: &Connection) -> Result<ContentHashes> {
+    let mut hasher = Blake2s256::new();
+    let mut stmt = conn.prepare(
+        "SELECT pattern_hash, tool_type, command_category, context_query, success_count, failure_count
+         FROM patterns ORDER BY pattern_hash",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        feed(&mut hasher, row.get::<_, String>(0)?.as_bytes());
+        feed(&mut hasher, row.get::<_, String>(1)?.as_bytes());
+        match row.get::<_, Option<String>>(2)? {
+            Some(category) => feed(&mut hasher, category.as_bytes()),
+            None => hasher.update([0xff]),
+        }
+        feed(&mut hasher, row.get::<_, String>(3)?.as_bytes());
+        hasher.update(row.get::<_, i64>(4)?.to_le_bytes());
+        hasher.update(row.get::<_, i64>(5)?.to_le_bytes());
+    }
+    let patterns = hex(hasher.finalize_reset().as_slice());
+
+    let mut stmt = conn.prepare(
+        "SELECT pattern_a_hash, pattern_b_hash, lift, co_occurrences
+         FROM causal_edges ORDER BY pattern_a_hash, pattern_b_hash",
+    )?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        feed(&mut hasher, row.get::<_, String>(0)?.as_bytes());
+        feed(&mut hasher, row.get::<_, String>(1)?.as_bytes());
+        hasher.update(row.get::<_, f64>(2)?.to_bits().to_le_bytes());
+        hasher.update(row.get::<_, i64>(3)?.to_le_bytes());
+    }
+    let causal_edges = hex(hasher.finalize_reset().as_slice());
+
+    let mut stmt = conn.prepare("SELECT pattern_hash, vector FROM vectors ORDER BY pattern_hash")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        feed(&mut hasher, row.get::<_, String>(0)?.as_bytes());
+        feed(&mut hasher, &row.get::<_, Vec<u8>>(1)?);
+    }
+    let vectors = hex(hasher.finalize().as_slice());
+
+    Ok(ContentHashes { patterns, causal_edges, vectors })
+}
+
+/// Length-prefixed so adjacent fields can't run into each other
+fn feed(hasher: &mut Blake2s256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn seed(mana_dir: &Path) -> Vec<i64> {
+        std::fs::create_dir_all(mana_dir).unwrap();
+        let conn = db::open(&mana_dir.join("metadata.sqlite")).unwrap();
+        storage::create_schema(&conn).unwrap();
+        let mut ids = Vec::new();
+        for (hash, context, success) in [("a", "Task: Build\nApproach: cargo build", 4), ("b", "Task: Test\nApproach: cargo test", 2)] {
+            conn.execute(
+                "INSERT INTO patterns (pattern_hash, tool_type, command_category, context_query, success_count, failure_count, embedding)
+                 VALUES (?1, 'Bash', 'cargo', ?2, ?3, 0, ?4)",
+                params![hash, context, success, vec![0u8; 384 * 4]],
+            )
+            .unwrap();
+            ids.push(conn.last_insert_rowid());
+        }
+        conn.execute(
+            "INSERT INTO causal_edges (pattern_a_id, pattern_b_id, lift, co_occurrences) VALUES (?1, ?2, 1.4, 3)",
+            params![ids[0], ids[1]],
+        )
+        .unwrap();
+        ids
+    }
+
+    fn security(sanitize: bool) -> SecurityConfig {
+        SecurityConfig {
+            sanitize_paths: sanitize,
+            redact_secrets: sanitize,
+            encrypt: false,
+            visibility: crate::sync::Visibility::Private,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let ids = seed(&source);
+        // An approved risky pattern is exported like any other
+        db::open(&source.join("metadata.sqlite"))
+            .unwrap()
+            .execute("UPDATE patterns SET risky = 1, approved_at = CURRENT_TIMESTAMP WHERE id = ?1", [ids[1]])
+            .unwrap();
+        let snapshot_path = temp.path().join("snapshot.sqlite");
+
+        let manifest = export_snapshot(&source, &snapshot_path, &security(false), &PatternFilter::default(), true).unwrap();
+        assert!(is_snapshot(&snapshot_path));
+        assert_eq!((manifest.pattern_count, manifest.edge_count, manifest.vector_count), (2, 1, 2));
+
+        let target = temp.path().join("target");
+        std::fs::create_dir_all(&target).unwrap();
+        let result = import_snapshot(&target, &snapshot_path, MergeStrategy::Add).unwrap();
+        assert_eq!(result.patterns.imported, 2);
+        assert_eq!(result.edges_imported, 1);
+        assert_eq!(result.vectors_imported, 2);
+        assert_eq!(VectorIndex::load(&target.join("vectors.usearch")).unwrap().len(), 2);
+
+        // Importing again merges the edge instead of duplicating it
+        let again = import_snapshot(&target, &snapshot_path, MergeStrategy::Add).unwrap();
+        assert_eq!((again.edges_imported, again.edges_merged, again.vectors_imported), (0, 1, 0));
+        let conn = db::open(&target.join("metadata.sqlite")).unwrap();
+        let co: i64 = conn.query_row("SELECT co_occurrences FROM causal_edges", [], |row| row.get(0)).unwrap();
+        assert_eq!(co, 6);
+    }
+
+    #[test]
+    fn test_tampered_snapshot_is_rejected() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        seed(&source);
+        let snapshot_path = temp.path().join("snapshot.sqlite");
+        export_snapshot(&source, &snapshot_path, &security(true), &PatternFilter::default(), false).unwrap();
+
+        let conn = Connection::open(&snapshot_path).unwrap();
+        conn.execute("UPDATE patterns SET success_count = 999", []).unwrap();
+        drop(conn);
+
+        let target = temp.path().join("target");
+        std::fs::create_dir_all(&target).unwrap();
+        let err = import_snapshot(&target, &snapshot_path, MergeStrategy::Add).unwrap_err();
+        assert!(err.to_string().contains("integrity check failed"));
+        assert!(!is_snapshot(&temp.path().join("missing.sqlite")));
+    }
+}
@@ -0,0 +1,364 @@
+//! SSH file backend for sharing patterns through a plain server
+//!
+//! Keeps one export bundle (`patterns.json`, encrypted when a passphrase is
+//! set) in a directory on any host reachable over SSH, so a homelab box or
+//! a shared VM is enough. Transfers shell out to the system `ssh`, which
+//! picks up keys, agents, ports and jump hosts from `~/.ssh/config`; the
+//! remote side needs nothing but a POSIX shell.
+//!
+//! A copy of the bundle as last seen is kept in the data directory as the
+//! merge base: pulls add only what changed remotely since then, and pushes
+//! merge the remote bundle before replacing it. Uploads are written to a
+//! temporary file and renamed, so readers never see half a bundle.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use crate::sync::backend::{BackendFuture, PullOptions, PullReport, PushOptions, SyncBackendImpl, SyncContext};
+use crate::sync::crypto::hash_workspace_id;
+use crate::sync::export::{export_for_push, import_patterns_from_vec, parse_bundle, render_bundle, MergeStrategy};
+use crate::sync::shards::{self, PatternMap};
+use crate::sync::{load_sync_config, save_sync_config, SecurityConfig, SyncBackend, SyncConfig};
+
+/// Bundle file in the remote directory
+const BUNDLE_FILE: &str = "patterns.json";
+/// The remote bundle as of the last push or pull (the merge base)
+const CACHE_FILE: &str = "ssh-sync-patterns.json";
+/// Exit code the fetch script uses for "no bundle yet"
+const MISSING_EXIT: i32 = 3;
+
+/// Where the bundle lives: an SSH destination and a directory on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshTarget {
+    /// `user@host`, or a Host alias from ~/.ssh/config
+    pub host: String,
+    /// Directory on the host; relative paths are under the login's home
+    pub path: String,
+}
+
+impl SshTarget {
+    /// Parse `user@host:/path` (scp syntax)
+    pub fn parse(target: &str) -> Result<Self> {
+        let (host, path) = target
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Expected an SSH target like user@host:/srv/mana, got '{}'", target))?;
+        if host.is_empty() || host.starts_with('-') {
+            bail!("Missing or invalid host in SSH target '{}'", target);
+        }
+        let path = path.trim_end_matches('/');
+        Ok(Self { host: host.to_string(), path: if path.is_empty() { ".".to_string() } else { path.to_string() } })
+    }
+
+    fn from_backend(backend: &SyncBackend) -> Option<Self> {
+        match backend {
+            SyncBackend::Ssh { host, path } => Some(Self { host: host.clone(), path: path.clone() }),
+            _ => None,
+        }
+    }
+
+    fn bundle_path(&self) -> String {
+        format!("{}/{}", self.path, BUNDLE_FILE)
+    }
+}
+
+impl std::fmt::Display for SshTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.path)
+    }
+}
+
+/// Quote a path for the remote shell, leaving a leading `~/` to expand
+fn shell_quote(path: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("~/{}", quote(rest)),
+        None => quote(path),
+    }
+}
+
+/// Run a script on the host, feeding it `input` on stdin
+fn run_ssh(target: &SshTarget, script: &str, input: Option<&[u8]>) -> Result<std::process::Output> {
+    let mut child = Command::new("ssh")
+        // Never prompt: a sync run may have no terminal
+        .args(["-o", "BatchMode=yes", "-o", "ConnectTimeout=15", "--", &target.host, script])
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run ssh. Is OpenSSH installed?")?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input)?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+fn ssh_error(target: &SshTarget, action: &str, output: &std::process::Output) -> anyhow::Error {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    anyhow!("Failed to {} {}: {}", action, target, stderr.trim())
+}
+
+/// Download the remote bundle, or None if nothing has been pushed yet
+fn fetch(target: &SshTarget) -> Result<Option<String>> {
+    let bundle = shell_quote(&target.bundle_path());
+    let script = format!("test -f {} || exit {}; cat {}", bundle, MISSING_EXIT, bundle);
+    let output = run_ssh(target, &script, None)?;
+    match output.status.code() {
+        Some(0) => Ok(Some(String::from_utf8(output.stdout).context("Remote bundle is not valid UTF-8")?)),
+        Some(MISSING_EXIT) => Ok(None),
+        _ => Err(ssh_error(target, "read patterns from", &output)),
+    }
+}
+
+/// Replace the remote bundle
+fn upload(target: &SshTarget, content: &str) -> Result<()> {
+    let dir = shell_quote(&target.path);
+    let bundle = shell_quote(&target.bundle_path());
+    let temp = shell_quote(&format!("{}/.{}.tmp", target.path, BUNDLE_FILE));
+    let script = format!("mkdir -p {dir} && cat > {temp} && mv -f {temp} {bundle}");
+    let output = run_ssh(target, &script, Some(content.as_bytes()))?;
+    if !output.status.success() {
+        return Err(ssh_error(target, "write patterns to", &output));
+    }
+    Ok(())
+}
+
+fn load_cache(mana_dir: &Path) -> Option<String> {
+    std::fs::read_to_string(mana_dir.join(CACHE_FILE)).ok()
+}
+
+fn parse(content: Option<&str>, passphrase: Option<&str>) -> Result<PatternMap> {
+    match content {
+        Some(content) => Ok(shards::collect(parse_bundle(content, passphrase)?.patterns)),
+        None => Ok(PatternMap::new()),
+    }
+}
+
+fn configured_target(mana_dir: &Path) -> Result<SshTarget> {
+    let config = load_sync_config(&mana_dir.join("sync.toml"))?;
+    SshTarget::from_backend(&config.backend).ok_or_else(|| anyhow!("Sync backend is not configured for ssh"))
+}
+
+/// Initialize SSH sync: check the host is reachable and create the directory
+pub fn init_ssh_sync(mana_dir: &Path, target: &SshTarget) -> Result<()> {
+    save_ssh_config(mana_dir, target)?;
+
+    let output = run_ssh(target, &format!("mkdir -p {}", shell_quote(&target.path)), None)?;
+    if !output.status.success() {
+        return Err(ssh_error(target, "create the sync directory on", &output)
+            .context("Check that 'ssh <host>' works without a password prompt (use ssh-agent or a key in ~/.ssh/config)"));
+    }
+
+    println!("✅ SSH sync initialized");
+    println!("   Host: {}", target.host);
+    println!("   Path: {}", target.path);
+    Ok(())
+}
+
+/// Push patterns to the host, merging what's there first
+pub fn push_patterns_ssh(
+    mana_dir: &Path,
+    db_path: &Path,
+    security: &SecurityConfig,
+    passphrase: Option<&str>,
+) -> Result<()> {
+    let target = configured_target(mana_dir)?;
+    let passphrase = passphrase.filter(|_| security.encrypt);
+
+    let remote = fetch(&target)?;
+    let cached = load_cache(mana_dir);
+    let theirs = parse(remote.as_deref(), passphrase)?;
+    if remote != cached {
+        let base = parse(cached.as_deref(), passphrase)?;
+        let incoming = shards::delta(&base, &theirs);
+        if !incoming.is_empty() {
+            let changes = incoming.len();
+            import_patterns_from_vec(db_path, incoming, MergeStrategy::Add)?;
+            println!("📥 Merged {} pattern changes from {}", changes, target);
+        }
+    }
+
+    let mut patterns = shards::collect(export_for_push(db_path, security)?);
+    shards::union(&mut patterns, &theirs);
+    if remote.is_some() && patterns == theirs {
+        std::fs::write(mana_dir.join(CACHE_FILE), remote.unwrap_or_default())?;
+        println!("📋 No changes to push");
+        return Ok(());
+    }
+
+    let count = patterns.len();
+    let workspace_id = hash_workspace_id(&std::env::current_dir()?.to_string_lossy());
+    let device = crate::sync::device::name(mana_dir);
+    let content = render_bundle(patterns.into_values().collect(), Vec::new(), passphrase, &workspace_id, &device)?;
+    upload(&target, &content)?;
+    std::fs::write(mana_dir.join(CACHE_FILE), &content)?;
+
+    println!("✅ Pushed {} patterns to {}/{}", count, target, BUNDLE_FILE);
+    if passphrase.is_none() {
+        println!("   ⚠️  Not encrypted: set MANA_SYNC_KEY or pass --passphrase");
+    }
+    Ok(())
+}
+
+/// Pull patterns from the host
+pub fn pull_patterns_ssh(
+    mana_dir: &Path,
+    db_path: &Path,
+    passphrase: Option<&str>,
+    merge_strategy: MergeStrategy,
+) -> Result<PullReport> {
+    let target = configured_target(mana_dir)?;
+
+    let Some(remote) = fetch(&target)? else {
+        println!("📋 No patterns found at {}", target);
+        return Ok(PullReport::default());
+    };
+    let cached = load_cache(mana_dir);
+    if cached.as_deref() == Some(remote.as_str()) {
+        println!("📋 Already up to date");
+        return Ok(PullReport::default());
+    }
+
+    let base = parse(cached.as_deref(), passphrase)?;
+    let theirs = parse(Some(&remote), passphrase)?;
+    let incoming = match merge_strategy {
+        MergeStrategy::Add => shards::delta(&base, &theirs),
+        MergeStrategy::Replace | MergeStrategy::KeepBest | MergeStrategy::Interactive => shards::changed(&base, &theirs),
+    };
+    let import_result = import_patterns_from_vec(db_path, incoming, merge_strategy)?;
+    std::fs::write(mana_dir.join(CACHE_FILE), &remote)?;
+
+    println!("✅ Pulled patterns from {}", target);
+    println!("   Changed: {}, New: {}, Merged: {}",
+        import_result.total, import_result.imported, import_result.merged);
+    if import_result.skipped > 0 {
+        println!("   Skipped: {}", import_result.skipped);
+    }
+    Ok(import_result.into())
+}
+
+/// SSH sync status
+#[derive(Debug, Clone, Serialize)]
+pub struct SshSyncStatus {
+    pub host: String,
+    pub path: String,
+    /// Whether ssh could connect and read the directory
+    pub reachable: bool,
+    pub latency_ms: Option<u128>,
+    /// Whether a bundle has been pushed
+    pub bundle_exists: bool,
+    /// Whether the local database has everything in the remote bundle
+    pub up_to_date: bool,
+    pub error: Option<String>,
+}
+
+/// Get SSH sync status, connecting to the host
+pub fn ssh_status(mana_dir: &Path) -> Result<SshSyncStatus> {
+    let target = configured_target(mana_dir)?;
+    let started = Instant::now();
+    let (remote, error) = match fetch(&target) {
+        Ok(remote) => (Some(remote), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    Ok(SshSyncStatus {
+        host: target.host,
+        path: target.path,
+        reachable: remote.is_some(),
+        latency_ms: remote.is_some().then(|| started.elapsed().as_millis()),
+        bundle_exists: matches!(remote, Some(Some(_))),
+        up_to_date: matches!(&remote, Some(Some(bundle)) if load_cache(mana_dir).as_ref() == Some(bundle)),
+        error,
+    })
+}
+
+/// Save SSH sync configuration
+pub fn save_ssh_config(mana_dir: &Path, target: &SshTarget) -> Result<()> {
+    // Keep settings that `sync init` doesn't touch
+    let previous = load_sync_config(&mana_dir.join("sync.toml")).unwrap_or_default();
+    let config = SyncConfig {
+        enabled: true,
+        backend: SyncBackend::Ssh {
+            host: target.host.clone(),
+            path: target.path.clone(),
+        },
+        interval_minutes: 60,
+        security: SecurityConfig::default(),
+        signing: previous.signing,
+        git: previous.git,
+        registry: previous.registry,
+    };
+    save_sync_config(&config, &mana_dir.join("sync.toml"))
+}
+
+/// SSH backend for `mana sync` push, pull and status
+pub struct SshSync;
+
+impl SyncBackendImpl for SshSync {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn push<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PushOptions) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            push_patterns_ssh(ctx.mana_dir, ctx.db_path, &SecurityConfig::default(), options.passphrase.as_deref())
+        })
+    }
+
+    fn pull<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PullOptions) -> BackendFuture<'a, PullReport> {
+        Box::pin(async move {
+            pull_patterns_ssh(ctx.mana_dir, ctx.db_path, options.passphrase.as_deref(), options.merge_strategy)
+        })
+    }
+
+    fn status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, serde_json::Value> {
+        Box::pin(async move { Ok(serde_json::to_value(ssh_status(ctx.mana_dir)?)?) })
+    }
+
+    fn print_status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            let status = ssh_status(ctx.mana_dir)?;
+            println!("Backend: ssh");
+            println!("Host: {}", status.host);
+            println!("Path: {}", status.path);
+            match (&status.error, status.latency_ms) {
+                (None, Some(ms)) => println!("Connectivity: ✅ Reachable ({} ms)", ms),
+                (Some(error), _) => println!("Connectivity: ❌ {}", error),
+                _ => {}
+            }
+            if status.reachable {
+                println!("Bundle: {}", if status.bundle_exists { "✅ Exists" } else { "❌ Not pushed yet" });
+            }
+            if status.bundle_exists {
+                println!("Local database: {}", if status.up_to_date { "up to date" } else { "behind (run 'mana sync pull')" });
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        let target = SshTarget::parse("me@box:/srv/mana/").unwrap();
+        assert_eq!(target, SshTarget { host: "me@box".to_string(), path: "/srv/mana".to_string() });
+        assert_eq!(target.bundle_path(), "/srv/mana/patterns.json");
+        assert_eq!(SshTarget::parse("homelab:").unwrap().path, ".");
+
+        assert!(SshTarget::parse("me@box").is_err());
+        assert!(SshTarget::parse(":/srv").is_err());
+        assert!(SshTarget::parse("-oProxyCommand=x:/srv").is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/srv/mana"), "'/srv/mana'");
+        assert_eq!(shell_quote("~/mana dir"), "~/'mana dir'");
+        assert_eq!(shell_quote("/srv/it's"), r"'/srv/it'\''s'");
+    }
+}
@@ -0,0 +1,258 @@
+//! Supabase user sessions
+//!
+//! Rows in Supabase belong to the signed-in user: `mana login` signs in
+//! with a one-time code sent by email (or takes an existing access token,
+//! e.g. in CI), and every request then carries the user's JWT so the
+//! row-level security policies keyed to its `sub` claim apply. The session
+//! is kept in the data directory, readable only by the current user, and
+//! refreshed shortly before it expires.
+
+// Only login and logout use this module when Supabase support is compiled out
+#![cfg_attr(not(feature = "supabase"), allow(dead_code))]
+
+use anyhow::{anyhow, Context, Result};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::sync::age::write_private;
+
+/// Session file in the data directory
+pub const SESSION_FILE: &str = "supabase-session.json";
+
+/// Refresh the access token this many seconds before it expires
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// A signed-in Supabase user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub access_token: String,
+    /// Absent for sessions from `mana login --token`, which can't be refreshed
+    pub refresh_token: Option<String>,
+    /// Unix time the access token expires
+    pub expires_at: i64,
+    /// Supabase user id (the JWT `sub`), used as owner_id
+    pub user_id: String,
+    pub email: Option<String>,
+}
+
+impl Session {
+    /// Build a session from an access token's claims
+    ///
+    /// The signature isn't checked here; Supabase verifies it on every
+    /// request, so a forged token gets nothing.
+    pub fn from_token(access_token: &str, refresh_token: Option<String>) -> Result<Self> {
+        let claims = decode_claims(access_token)?;
+        Ok(Self {
+            access_token: access_token.to_string(),
+            refresh_token,
+            expires_at: claims.exp,
+            user_id: claims.sub,
+            email: claims.email.filter(|e| !e.is_empty()),
+        })
+    }
+
+    /// Whether the access token is expired or about to be
+    pub fn needs_refresh(&self, now: i64) -> bool {
+        self.expires_at - REFRESH_MARGIN_SECS <= now
+    }
+
+    /// How to refer to the user in messages
+    pub fn display_name(&self) -> &str {
+        self.email.as_deref().unwrap_or(&self.user_id)
+    }
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    exp: i64,
+    email: Option<String>,
+}
+
+fn decode_claims(token: &str) -> Result<Claims> {
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| anyhow!("Not a Supabase access token (expected a JWT)"))?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .context("Access token payload is not valid base64")?;
+    serde_json::from_slice(&bytes).context("Access token is missing the sub or exp claim")
+}
+
+/// Load the stored session, if any
+pub fn load(mana_dir: &Path) -> Result<Option<Session>> {
+    let path = mana_dir.join(SESSION_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&content).with_context(|| format!("Corrupt session file {}", path.display()))?))
+}
+
+/// Store the session, readable only by the current user
+pub fn save(mana_dir: &Path, session: &Session) -> Result<()> {
+    write_private(&mana_dir.join(SESSION_FILE), serde_json::to_string_pretty(session)?.as_bytes())
+}
+
+/// Remove the stored session, returning whether there was one
+pub fn clear(mana_dir: &Path) -> Result<bool> {
+    let path = mana_dir.join(SESSION_FILE);
+    if !path.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_file(path)?;
+    Ok(true)
+}
+
+/// Response from the Supabase auth endpoints that issue sessions
+#[cfg(feature = "supabase")]
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[cfg(feature = "supabase")]
+fn auth_url(url: &str, path: &str) -> String {
+    format!("{}/auth/v1/{}", url.trim_end_matches('/'), path)
+}
+
+#[cfg(feature = "supabase")]
+async fn check(response: reqwest::Response, action: &str) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(anyhow!("Failed to {}: {} - {}", action, status, body))
+}
+
+/// Email a one-time sign-in code
+#[cfg(feature = "supabase")]
+pub async fn send_code(url: &str, api_key: &str, email: &str) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(auth_url(url, "otp"))
+        .header("apikey", api_key)
+        .json(&serde_json::json!({ "email": email, "create_user": true }))
+        .send()
+        .await?;
+    check(response, "send sign-in code").await?;
+    Ok(())
+}
+
+/// Exchange an emailed code for a session
+#[cfg(feature = "supabase")]
+pub async fn verify_code(url: &str, api_key: &str, email: &str, code: &str) -> Result<Session> {
+    let response = reqwest::Client::new()
+        .post(auth_url(url, "verify"))
+        .header("apikey", api_key)
+        .json(&serde_json::json!({ "type": "email", "email": email, "token": code }))
+        .send()
+        .await?;
+    let tokens: TokenResponse = check(response, "verify sign-in code").await?.json().await?;
+    Session::from_token(&tokens.access_token, tokens.refresh_token)
+}
+
+/// Check an existing access token with Supabase and build a session from it
+#[cfg(feature = "supabase")]
+pub async fn verify_token(url: &str, api_key: &str, access_token: &str) -> Result<Session> {
+    let response = reqwest::Client::new()
+        .get(auth_url(url, "user"))
+        .header("apikey", api_key)
+        .header("Authorization", format!("Bearer {}", access_token))
+        .send()
+        .await?;
+    check(response, "verify access token").await?;
+    Session::from_token(access_token, None)
+}
+
+/// Get a fresh access token for a session
+#[cfg(feature = "supabase")]
+pub async fn refresh(url: &str, api_key: &str, session: &Session) -> Result<Session> {
+    let refresh_token = session
+        .refresh_token
+        .as_deref()
+        .ok_or_else(|| anyhow!("Supabase session expired. Run 'mana login' again"))?;
+    let response = reqwest::Client::new()
+        .post(auth_url(url, "token?grant_type=refresh_token"))
+        .header("apikey", api_key)
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await?;
+    let tokens: TokenResponse = check(response, "refresh Supabase session")
+        .await
+        .context("Run 'mana login' again")?
+        .json()
+        .await?;
+    Session::from_token(&tokens.access_token, tokens.refresh_token)
+}
+
+/// The stored session, refreshed (and saved) if it's about to expire
+#[cfg(feature = "supabase")]
+pub async fn current(mana_dir: &Path, url: &str, api_key: &str) -> Result<Option<Session>> {
+    let Some(session) = load(mana_dir)? else {
+        return Ok(None);
+    };
+    if !session.needs_refresh(chrono::Utc::now().timestamp()) {
+        return Ok(Some(session));
+    }
+    let session = refresh(url, api_key, &session).await?;
+    save(mana_dir, &session)?;
+    Ok(Some(session))
+}
+
+/// Revoke the session's refresh token on the server
+#[cfg(feature = "supabase")]
+pub async fn revoke(url: &str, api_key: &str, session: &Session) -> Result<()> {
+    let response = reqwest::Client::new()
+        .post(auth_url(url, "logout"))
+        .header("apikey", api_key)
+        .header("Authorization", format!("Bearer {}", session.access_token))
+        .send()
+        .await?;
+    check(response, "sign out").await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(claims: serde_json::Value) -> String {
+        format!("eyJhbGciOiJIUzI1NiJ9.{}.c2ln", URL_SAFE_NO_PAD.encode(claims.to_string()))
+    }
+
+    #[test]
+    fn test_session_from_token() {
+        let access = token(serde_json::json!({ "sub": "3f1c-user", "exp": 2_000, "email": "dev@example.com" }));
+        let session = Session::from_token(&access, Some("refresh".to_string())).unwrap();
+        assert_eq!(session.user_id, "3f1c-user");
+        assert_eq!(session.display_name(), "dev@example.com");
+        assert!(!session.needs_refresh(1_000));
+        assert!(session.needs_refresh(1_950));
+
+        assert!(Session::from_token("not-a-jwt", None).is_err());
+        assert!(Session::from_token(&token(serde_json::json!({ "role": "anon" })), None).is_err());
+    }
+
+    #[test]
+    fn test_session_storage() {
+        let temp = tempfile::TempDir::new().unwrap();
+        assert!(load(temp.path()).unwrap().is_none());
+
+        let session = Session::from_token(&token(serde_json::json!({ "sub": "u1", "exp": 10 })), None).unwrap();
+        save(temp.path(), &session).unwrap();
+        assert_eq!(load(temp.path()).unwrap().unwrap().user_id, "u1");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(temp.path().join(SESSION_FILE)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(clear(temp.path()).unwrap());
+        assert!(!clear(temp.path()).unwrap());
+    }
+}
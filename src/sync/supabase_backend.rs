@@ -5,21 +5,28 @@
 //! - Row-level security for access control
 //! - Team creation and membership management
 //! - Pattern sharing with visibility levels
+//!
+//! Requests carry the signed-in user's JWT (see `supabase_auth`), and the
+//! user's Supabase id is the owner of everything they push.
 
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 use crate::sync::ExportablePattern;
+use crate::sync::supabase_auth::{self, Session};
+use crate::sync::{SyncBackend, Visibility};
+use crate::sync::backend::{BackendFuture, PullOptions, PullReport, PushOptions, SyncBackendImpl, SyncContext};
 
 #[cfg(feature = "supabase")]
 use tracing::info;
 
 #[cfg(feature = "supabase")]
-use crate::sync::{SyncBackend, SecurityConfig, load_sync_config};
+use crate::sync::{SecurityConfig, load_sync_config};
 #[cfg(feature = "supabase")]
-use crate::sync::export::{export_patterns_to_vec, import_patterns_from_vec, MergeStrategy};
+use crate::sync::export::{export_for_push, import_patterns_from_vec, MergeStrategy};
 
+#[cfg(not(feature = "supabase"))]
 pub use crate::sync::export::MergeStrategy as SupabaseMergeStrategy;
 
 #[cfg(not(feature = "supabase"))]
@@ -35,7 +42,10 @@ pub struct SupabaseConfig {
     pub api_key: String,
     /// Current user ID (set after authentication)
     pub user_id: Option<String>,
+    /// Signed-in user's access token (set after authentication)
+    pub access_token: Option<String>,
     /// Current team ID (if any)
+    #[allow(dead_code)]
     pub team_id: Option<String>,
 }
 
@@ -50,6 +60,7 @@ impl SupabaseConfig {
                     url: url.clone(),
                     api_key,
                     user_id: None,
+                    access_token: None,
                     team_id: None,
                 })
             }
@@ -61,6 +72,32 @@ impl SupabaseConfig {
     fn rest_url(&self, table: &str) -> String {
         format!("{}/rest/v1/{}", self.url.trim_end_matches('/'), table)
     }
+
+    /// Attach the signed-in user's session, refreshing it if needed
+    async fn authenticate(mut self, mana_dir: &Path) -> Result<Self> {
+        let session = supabase_auth::current(mana_dir, &self.url, &self.api_key)
+            .await?
+            .ok_or_else(|| anyhow!("Not logged in to Supabase. Run 'mana login --email <address>'"))?;
+        self.user_id = Some(session.user_id);
+        self.access_token = Some(session.access_token);
+        Ok(self)
+    }
+
+    /// Token for the Authorization header: the user's JWT once signed in
+    pub(crate) fn bearer(&self) -> &str {
+        self.access_token.as_deref().unwrap_or(&self.api_key)
+    }
+
+    /// Owner of rows this user writes
+    pub(crate) fn owner_id(&self) -> String {
+        self.user_id.clone().unwrap_or_default()
+    }
+}
+
+/// Load the Supabase config with the signed-in user's session attached
+#[cfg(feature = "supabase")]
+pub(crate) async fn connect(mana_dir: &Path) -> Result<SupabaseConfig> {
+    backend_config(mana_dir)?.authenticate(mana_dir).await
 }
 
 /// Team information
@@ -82,8 +119,41 @@ pub struct TeamMember {
     pub joined_at: String,
 }
 
-/// Shared pattern stored in Supabase
+/// Single-use team invite stored in `mana_team_invites`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamInvite {
+    pub code: String,
+    pub team_id: String,
+    /// Only this address can redeem the invite
+    pub email: Option<String>,
+    /// Role granted on joining: member or admin
+    pub role: String,
+    pub created_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    pub expires_at: String,
+    pub redeemed_by: Option<String>,
+    pub redeemed_at: Option<String>,
+}
+
+impl TeamInvite {
+    /// pending, redeemed or expired
+    pub fn status(&self, now: chrono::DateTime<chrono::Utc>) -> &'static str {
+        let expired = chrono::DateTime::parse_from_rfc3339(&self.expires_at).is_ok_and(|t| t < now);
+        match (&self.redeemed_at, expired) {
+            (Some(_), _) => "redeemed",
+            (None, true) => "expired",
+            (None, false) => "pending",
+        }
+    }
+}
+
+/// Roles an invite can grant
+#[cfg(feature = "supabase")]
+const INVITE_ROLES: &[&str] = &["member", "admin"];
+
+/// Shared pattern stored in Supabase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SharedPattern {
     pub id: Option<String>,
     pub pattern_hash: String,
@@ -127,6 +197,8 @@ impl From<SharedPattern> for ExportablePattern {
             context_query: p.context_query,
             success_count: p.success_count,
             failure_count: p.failure_count,
+            device: None,
+            risky: false,
         }
     }
 }
@@ -141,7 +213,7 @@ pub async fn init_supabase_sync(mana_dir: &Path, url: &str) -> Result<()> {
     // Test connection by checking if we can reach the API
     let client = reqwest::Client::new();
     let response = client
-        .get(&format!("{}/rest/v1/", url.trim_end_matches('/')))
+        .get(format!("{}/rest/v1/", url.trim_end_matches('/')))
         .header("apikey", &api_key)
         .header("Authorization", format!("Bearer {}", api_key))
         .send()
@@ -180,16 +252,12 @@ pub async fn push_patterns_supabase(
     mana_dir: &Path,
     db_path: &Path,
     security: &SecurityConfig,
-    visibility: &str,
+    visibility: Visibility,
 ) -> Result<usize> {
-    let config_path = mana_dir.join("sync.toml");
-    let config = load_sync_config(&config_path)?;
-
-    let supabase_config = SupabaseConfig::from_backend(&config.backend)
-        .ok_or_else(|| anyhow!("Sync backend is not configured for Supabase or MANA_SUPABASE_KEY not set"))?;
+    let supabase_config = connect(mana_dir).await?;
 
     // Export patterns to vec
-    let patterns = export_patterns_to_vec(db_path, security)?;
+    let patterns = export_for_push(db_path, security)?;
     let count = patterns.len();
 
     if count == 0 {
@@ -199,8 +267,7 @@ pub async fn push_patterns_supabase(
 
     info!("Pushing {} patterns to Supabase", count);
 
-    // Get or create user ID (using a hash of the workspace for now)
-    let user_id = get_workspace_id(mana_dir);
+    let user_id = supabase_config.owner_id();
 
     // Convert to shared patterns
     let shared_patterns: Vec<SharedPattern> = patterns
@@ -220,7 +287,7 @@ pub async fn push_patterns_supabase(
     let response = client
         .post(&url)
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .header("Content-Type", "application/json")
         .header("Prefer", "resolution=merge-duplicates")
         .json(&shared_patterns)
@@ -243,7 +310,7 @@ pub async fn push_patterns_supabase(
     _mana_dir: &Path,
     _db_path: &Path,
     _security: &SecurityConfig,
-    _visibility: &str,
+    _visibility: Visibility,
 ) -> Result<usize> {
     Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
 }
@@ -257,37 +324,23 @@ pub async fn pull_patterns_supabase(
     include_team: bool,
     include_public: bool,
 ) -> Result<PullResult> {
-    let config_path = mana_dir.join("sync.toml");
-    let config = load_sync_config(&config_path)?;
-
-    let supabase_config = SupabaseConfig::from_backend(&config.backend)
-        .ok_or_else(|| anyhow!("Sync backend is not configured for Supabase or MANA_SUPABASE_KEY not set"))?;
+    let supabase_config = connect(mana_dir).await?;
 
-    let user_id = get_workspace_id(mana_dir);
+    let user_id = supabase_config.owner_id();
 
     // Build query to fetch patterns
     let client = reqwest::Client::new();
-    let mut url = supabase_config.rest_url("mana_patterns");
-
-    // Filter: own patterns OR team patterns (if member) OR public patterns
-    let mut filters = vec![format!("owner_id.eq.{}", user_id)];
-
-    if include_public {
-        filters.push("visibility.eq.public".to_string());
-    }
-
-    // Note: For team filtering, we'd need to join with team_members table
-    // For now, include team patterns if include_team is true
-    if include_team {
-        filters.push("visibility.eq.team".to_string());
-    }
-
-    url = format!("{}?or=({})", url, filters.join(","));
+    let team_ids = if include_team { member_team_ids(&supabase_config, &client).await? } else { Vec::new() };
+    let url = format!(
+        "{}?{}",
+        supabase_config.rest_url("mana_patterns"),
+        pattern_filter(&user_id, &team_ids, include_public)
+    );
 
     let response = client
         .get(&url)
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .send()
         .await?;
 
@@ -339,13 +392,9 @@ pub struct PullResult {
 /// Create a new team
 #[cfg(feature = "supabase")]
 pub async fn create_team(mana_dir: &Path, name: &str) -> Result<Team> {
-    let config_path = mana_dir.join("sync.toml");
-    let config = load_sync_config(&config_path)?;
-
-    let supabase_config = SupabaseConfig::from_backend(&config.backend)
-        .ok_or_else(|| anyhow!("Supabase not configured"))?;
+    let supabase_config = connect(mana_dir).await?;
 
-    let user_id = get_workspace_id(mana_dir);
+    let user_id = supabase_config.owner_id();
     let team_id = uuid::Uuid::new_v4().to_string();
 
     let team = Team {
@@ -359,9 +408,9 @@ pub async fn create_team(mana_dir: &Path, name: &str) -> Result<Team> {
 
     // Create team
     let response = client
-        .post(&supabase_config.rest_url("mana_teams"))
+        .post(supabase_config.rest_url("mana_teams"))
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .header("Content-Type", "application/json")
         .json(&team)
         .send()
@@ -381,9 +430,9 @@ pub async fn create_team(mana_dir: &Path, name: &str) -> Result<Team> {
     };
 
     let response = client
-        .post(&supabase_config.rest_url("mana_team_members"))
+        .post(supabase_config.rest_url("mana_team_members"))
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .header("Content-Type", "application/json")
         .json(&member)
         .send()
@@ -407,42 +456,17 @@ pub async fn create_team(_mana_dir: &Path, _name: &str) -> Result<Team> {
 /// List teams the user belongs to
 #[cfg(feature = "supabase")]
 pub async fn list_teams(mana_dir: &Path) -> Result<Vec<Team>> {
-    let config_path = mana_dir.join("sync.toml");
-    let config = load_sync_config(&config_path)?;
-
-    let supabase_config = SupabaseConfig::from_backend(&config.backend)
-        .ok_or_else(|| anyhow!("Supabase not configured"))?;
-
-    let user_id = get_workspace_id(mana_dir);
+    let supabase_config = connect(mana_dir).await?;
 
     // First get team IDs from memberships
     let client = reqwest::Client::new();
-    let url = format!(
-        "{}?user_id=eq.{}",
-        supabase_config.rest_url("mana_team_members"),
-        user_id
-    );
+    let team_ids = member_team_ids(&supabase_config, &client).await?;
 
-    let response = client
-        .get(&url)
-        .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let body = response.text().await.unwrap_or_default();
-        return Err(anyhow!("Failed to list teams: {}", body));
-    }
-
-    let memberships: Vec<TeamMember> = response.json().await?;
-
-    if memberships.is_empty() {
+    if team_ids.is_empty() {
         return Ok(vec![]);
     }
 
     // Fetch team details
-    let team_ids: Vec<String> = memberships.iter().map(|m| m.team_id.clone()).collect();
     let team_filter = team_ids.iter()
         .map(|id| format!("id.eq.{}", id))
         .collect::<Vec<_>>()
@@ -457,7 +481,7 @@ pub async fn list_teams(mana_dir: &Path) -> Result<Vec<Team>> {
     let response = client
         .get(&url)
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .send()
         .await?;
 
@@ -470,76 +494,222 @@ pub async fn list_teams(mana_dir: &Path) -> Result<Vec<Team>> {
     Ok(teams)
 }
 
+/// IDs of the teams the signed-in user is a member of
+#[cfg(feature = "supabase")]
+pub(crate) async fn member_team_ids(supabase_config: &SupabaseConfig, client: &reqwest::Client) -> Result<Vec<String>> {
+    let url = format!(
+        "{}?user_id=eq.{}",
+        supabase_config.rest_url("mana_team_members"),
+        supabase_config.owner_id()
+    );
+
+    let response = client
+        .get(&url)
+        .header("apikey", &supabase_config.api_key)
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to list team memberships: {}", body));
+    }
+
+    let memberships: Vec<TeamMember> = response.json().await?;
+    Ok(memberships.into_iter().map(|m| m.team_id).collect())
+}
+
+/// PostgREST filter for a pull: own patterns, team patterns from the given
+/// teams only, and optionally public ones
+///
+/// Row-level security enforces the same rule on the server; filtering here
+/// keeps a permissive or service key from pulling other teams' patterns.
+#[cfg_attr(not(feature = "supabase"), allow(dead_code))]
+fn pattern_filter(user_id: &str, team_ids: &[String], include_public: bool) -> String {
+    let mut filters = vec![format!("owner_id.eq.{}", user_id)];
+    if !team_ids.is_empty() {
+        filters.push(format!("and(visibility.eq.team,team_id.in.({}))", team_ids.join(",")));
+    }
+    if include_public {
+        filters.push("visibility.eq.public".to_string());
+    }
+    format!("or=({})", filters.join(","))
+}
+
 /// List teams (stub when feature disabled)
 #[cfg(not(feature = "supabase"))]
 pub async fn list_teams(_mana_dir: &Path) -> Result<Vec<Team>> {
     Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
 }
 
-/// Invite a user to a team (generates invite code)
+/// Invite a user to a team
+///
+/// Stores a random single-use code in `mana_team_invites`; joining redeems
+/// it through the `mana_redeem_invite` function, which checks expiry, the
+/// invitee's email and that it hasn't been used.
 #[cfg(feature = "supabase")]
-pub async fn invite_to_team(mana_dir: &Path, team_id: &str, invitee_email: &str) -> Result<String> {
-    // For now, we generate an invite code that the user can share
-    // In a full implementation, this would send an email
-    let invite_code = format!("{}:{}", team_id, uuid::Uuid::new_v4());
-
-    info!("Generated invite for {} to team {}", invitee_email, team_id);
-    println!("Invite code generated for {}:", invitee_email);
-    println!("  {}", invite_code);
+pub async fn invite_to_team(
+    mana_dir: &Path,
+    team_id: &str,
+    invitee_email: &str,
+    role: &str,
+    expires_in: chrono::Duration,
+) -> Result<TeamInvite> {
+    if !INVITE_ROLES.contains(&role) {
+        return Err(anyhow!("Invalid role '{}'. Use one of: {}", role, INVITE_ROLES.join(", ")));
+    }
+    let supabase_config = connect(mana_dir).await?;
+
+    let invite = TeamInvite {
+        code: uuid::Uuid::new_v4().simple().to_string(),
+        team_id: team_id.to_string(),
+        email: Some(invitee_email.to_lowercase()),
+        role: role.to_string(),
+        created_by: supabase_config.owner_id(),
+        created_at: None,
+        expires_at: (chrono::Utc::now() + expires_in).to_rfc3339(),
+        redeemed_by: None,
+        redeemed_at: None,
+    };
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(supabase_config.rest_url("mana_team_invites"))
+        .header("apikey", &supabase_config.api_key)
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
+        .header("Content-Type", "application/json")
+        .json(&invite)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to create invite (only team owners and admins can invite): {}", body));
+    }
+
+    info!("Created invite for {} to team {}", invitee_email, team_id);
+    println!("Invite code for {} ({}, expires {}):", invitee_email, role, invite.expires_at);
+    println!("  {}", invite.code);
     println!();
     println!("Share this code with the invitee. They can join with:");
-    println!("  mana team join {}", invite_code);
+    println!("  mana team join {}", invite.code);
 
-    Ok(invite_code)
+    Ok(invite)
 }
 
 /// Invite to team (stub when feature disabled)
 #[cfg(not(feature = "supabase"))]
-pub async fn invite_to_team(_mana_dir: &Path, _team_id: &str, _invitee_email: &str) -> Result<String> {
+pub async fn invite_to_team(
+    _mana_dir: &Path,
+    _team_id: &str,
+    _invitee_email: &str,
+    _role: &str,
+    _expires_in: chrono::Duration,
+) -> Result<TeamInvite> {
     Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
 }
 
-/// Join a team using an invite code
+/// List invites for the teams you administer
 #[cfg(feature = "supabase")]
-pub async fn join_team(mana_dir: &Path, invite_code: &str) -> Result<()> {
-    let parts: Vec<&str> = invite_code.split(':').collect();
-    if parts.len() != 2 {
-        return Err(anyhow!("Invalid invite code format"));
+pub async fn list_invites(mana_dir: &Path, team_id: Option<&str>) -> Result<Vec<TeamInvite>> {
+    let supabase_config = connect(mana_dir).await?;
+
+    let mut url = format!("{}?order=created_at.desc", supabase_config.rest_url("mana_team_invites"));
+    if let Some(team_id) = team_id {
+        url.push_str(&format!("&team_id=eq.{}", team_id));
     }
-    let team_id = parts[0];
 
-    let config_path = mana_dir.join("sync.toml");
-    let config = load_sync_config(&config_path)?;
+    let client = reqwest::Client::new();
+    let response = client
+        .get(&url)
+        .header("apikey", &supabase_config.api_key)
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
+        .send()
+        .await?;
 
-    let supabase_config = SupabaseConfig::from_backend(&config.backend)
-        .ok_or_else(|| anyhow!("Supabase not configured"))?;
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to list invites: {}", body));
+    }
 
-    let user_id = get_workspace_id(mana_dir);
+    Ok(response.json().await?)
+}
 
-    let member = TeamMember {
-        team_id: team_id.to_string(),
-        user_id: user_id.clone(),
-        role: "member".to_string(),
-        joined_at: chrono::Utc::now().to_rfc3339(),
-    };
+/// List invites (stub when feature disabled)
+#[cfg(not(feature = "supabase"))]
+pub async fn list_invites(_mana_dir: &Path, _team_id: Option<&str>) -> Result<Vec<TeamInvite>> {
+    Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
+}
+
+/// Revoke an invite so it can no longer be redeemed
+#[cfg(feature = "supabase")]
+pub async fn revoke_invite(mana_dir: &Path, code: &str) -> Result<()> {
+    let supabase_config = connect(mana_dir).await?;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .delete(format!("{}?code=eq.{}", supabase_config.rest_url("mana_team_invites"), code))
+        .header("apikey", &supabase_config.api_key)
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
+        .header("Prefer", "return=representation")
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(anyhow!("Failed to revoke invite: {}", body));
+    }
+
+    // Row-level security hides invites you can't manage, so nothing is deleted
+    let deleted: Vec<TeamInvite> = response.json().await?;
+    if deleted.is_empty() {
+        return Err(anyhow!("No invite {} found among the teams you administer", code));
+    }
+
+    info!("Revoked invite {}", code);
+    Ok(())
+}
+
+/// Revoke an invite (stub when feature disabled)
+#[cfg(not(feature = "supabase"))]
+pub async fn revoke_invite(_mana_dir: &Path, _code: &str) -> Result<()> {
+    Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
+}
+
+/// Join a team using an invite code
+#[cfg(feature = "supabase")]
+pub async fn join_team(mana_dir: &Path, invite_code: &str) -> Result<()> {
+    let supabase_config = connect(mana_dir).await?;
 
     let client = reqwest::Client::new();
     let response = client
-        .post(&supabase_config.rest_url("mana_team_members"))
+        .post(supabase_config.rest_url("rpc/mana_redeem_invite"))
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .header("Content-Type", "application/json")
-        .json(&member)
+        .json(&serde_json::json!({ "invite_code": invite_code.trim() }))
         .send()
         .await?;
 
     if !response.status().is_success() {
+        // Errors raised by the function come back as {"message": ...}
         let body = response.text().await.unwrap_or_default();
-        return Err(anyhow!("Failed to join team: {}", body));
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(str::to_string))
+            .unwrap_or(body);
+        return Err(anyhow!("Failed to join team: {}", message));
     }
 
-    info!("Joined team {}", team_id);
-    println!("Successfully joined team!");
+    #[derive(Deserialize)]
+    struct Redeemed {
+        team_id: String,
+        role: String,
+    }
+    let redeemed: Redeemed = response.json().await?;
+
+    info!("Joined team {} as {}", redeemed.team_id, redeemed.role);
+    println!("Successfully joined team {} as {}!", redeemed.team_id, redeemed.role);
 
     Ok(())
 }
@@ -553,13 +723,9 @@ pub async fn join_team(_mana_dir: &Path, _invite_code: &str) -> Result<()> {
 /// Share a pattern with a team
 #[cfg(feature = "supabase")]
 pub async fn share_pattern(mana_dir: &Path, pattern_hash: &str, team_id: &str) -> Result<()> {
-    let config_path = mana_dir.join("sync.toml");
-    let config = load_sync_config(&config_path)?;
-
-    let supabase_config = SupabaseConfig::from_backend(&config.backend)
-        .ok_or_else(|| anyhow!("Supabase not configured"))?;
+    let supabase_config = connect(mana_dir).await?;
 
-    let user_id = get_workspace_id(mana_dir);
+    let user_id = supabase_config.owner_id();
 
     let client = reqwest::Client::new();
 
@@ -580,7 +746,7 @@ pub async fn share_pattern(mana_dir: &Path, pattern_hash: &str, team_id: &str) -
     let response = client
         .patch(&url)
         .header("apikey", &supabase_config.api_key)
-        .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+        .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
         .header("Content-Type", "application/json")
         .json(&UpdatePayload {
             team_id: team_id.to_string(),
@@ -618,7 +784,17 @@ pub async fn supabase_status(mana_dir: &Path) -> Result<SupabaseStatus> {
     let config = load_sync_config(&config_path)?;
 
     if let Some(supabase_config) = SupabaseConfig::from_backend(&config.backend) {
-        let user_id = get_workspace_id(mana_dir);
+        let session = supabase_auth::current(mana_dir, &supabase_config.url, &supabase_config.api_key).await?;
+        let Some(session) = session else {
+            return Ok(SupabaseStatus {
+                configured: true,
+                url: Some(supabase_config.url),
+                connected: true,
+                ..Default::default()
+            });
+        };
+        let supabase_config = supabase_config.authenticate(mana_dir).await?;
+        let user_id = supabase_config.owner_id();
 
         // Count patterns
         let client = reqwest::Client::new();
@@ -631,7 +807,7 @@ pub async fn supabase_status(mana_dir: &Path) -> Result<SupabaseStatus> {
         let response = client
             .get(&url)
             .header("apikey", &supabase_config.api_key)
-            .header("Authorization", format!("Bearer {}", supabase_config.api_key))
+            .header("Authorization", format!("Bearer {}", supabase_config.bearer()))
             .header("Prefer", "count=exact")
             .send()
             .await?;
@@ -640,7 +816,7 @@ pub async fn supabase_status(mana_dir: &Path) -> Result<SupabaseStatus> {
             .headers()
             .get("content-range")
             .and_then(|v| v.to_str().ok())
-            .and_then(|s| s.split('/').last())
+            .and_then(|s| s.rsplit('/').next())
             .and_then(|n| n.parse().ok())
             .unwrap_or(0);
 
@@ -649,6 +825,7 @@ pub async fn supabase_status(mana_dir: &Path) -> Result<SupabaseStatus> {
             url: Some(supabase_config.url),
             connected: true,
             user_id: Some(user_id),
+            email: session.email,
             pattern_count: Some(pattern_count),
         })
     } else {
@@ -663,13 +840,15 @@ pub async fn supabase_status(_mana_dir: &Path) -> Result<SupabaseStatus> {
 }
 
 /// Supabase sync status information
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[allow(dead_code)]
 pub struct SupabaseStatus {
     pub configured: bool,
     pub url: Option<String>,
     pub connected: bool,
+    /// Signed-in Supabase user, None until `mana login`
     pub user_id: Option<String>,
+    pub email: Option<String>,
     pub pattern_count: Option<i64>,
 }
 
@@ -699,6 +878,19 @@ CREATE TABLE IF NOT EXISTS mana_team_members (
     PRIMARY KEY (team_id, user_id)
 );
 
+-- Team invites: single-use codes that expire
+CREATE TABLE IF NOT EXISTS mana_team_invites (
+    code TEXT PRIMARY KEY,
+    team_id UUID NOT NULL REFERENCES mana_teams(id) ON DELETE CASCADE,
+    email TEXT,
+    role TEXT NOT NULL DEFAULT 'member' CHECK (role IN ('member', 'admin')),
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    redeemed_by TEXT,
+    redeemed_at TIMESTAMPTZ
+);
+
 -- Shared patterns table
 CREATE TABLE IF NOT EXISTS mana_patterns (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
@@ -722,24 +914,37 @@ CREATE INDEX IF NOT EXISTS idx_patterns_team ON mana_patterns(team_id);
 CREATE INDEX IF NOT EXISTS idx_patterns_visibility ON mana_patterns(visibility);
 CREATE INDEX IF NOT EXISTS idx_patterns_tool ON mana_patterns(tool_type);
 CREATE INDEX IF NOT EXISTS idx_team_members_user ON mana_team_members(user_id);
+CREATE INDEX IF NOT EXISTS idx_team_invites_team ON mana_team_invites(team_id);
 
 -- Row Level Security policies
 ALTER TABLE mana_teams ENABLE ROW LEVEL SECURITY;
 ALTER TABLE mana_team_members ENABLE ROW LEVEL SECURITY;
 ALTER TABLE mana_patterns ENABLE ROW LEVEL SECURITY;
+ALTER TABLE mana_team_invites ENABLE ROW LEVEL SECURITY;
+
+-- Teams the caller belongs to (bypasses RLS so policies on mana_team_members
+-- can use it without recursing into themselves)
+CREATE OR REPLACE FUNCTION mana_my_team_ids()
+RETURNS SETOF UUID
+LANGUAGE sql STABLE SECURITY DEFINER SET search_path = public
+AS $$
+    SELECT team_id FROM mana_team_members
+    WHERE user_id = current_setting('request.jwt.claims', true)::json->>'sub'
+$$;
 
 -- Teams: owners can manage their teams
 CREATE POLICY teams_owner_policy ON mana_teams
     FOR ALL USING (owner_id = current_setting('request.jwt.claims')::json->>'sub');
 
+-- Teams: members can see the teams they belong to
+CREATE POLICY teams_member_select_policy ON mana_teams
+    FOR SELECT USING (id IN (SELECT mana_my_team_ids()));
+
 -- Team members: members can see their memberships
 CREATE POLICY team_members_select_policy ON mana_team_members
     FOR SELECT USING (
         user_id = current_setting('request.jwt.claims')::json->>'sub'
-        OR team_id IN (
-            SELECT team_id FROM mana_team_members
-            WHERE user_id = current_setting('request.jwt.claims')::json->>'sub'
-        )
+        OR team_id IN (SELECT mana_my_team_ids())
     );
 
 -- Patterns: complex visibility rules
@@ -750,16 +955,76 @@ CREATE POLICY patterns_select_policy ON mana_patterns
         -- Public patterns
         OR visibility = 'public'
         -- Team patterns where user is a member
-        OR (visibility = 'team' AND team_id IN (
-            SELECT team_id FROM mana_team_members
-            WHERE user_id = current_setting('request.jwt.claims')::json->>'sub'
-        ))
+        OR (visibility = 'team' AND team_id IN (SELECT mana_my_team_ids()))
     );
 
 -- Patterns: only owner can modify
 CREATE POLICY patterns_modify_policy ON mana_patterns
     FOR ALL USING (owner_id = current_setting('request.jwt.claims')::json->>'sub');
 
+-- Team members: owners add themselves when creating a team; everyone else joins via an invite
+CREATE POLICY team_members_owner_insert_policy ON mana_team_members
+    FOR INSERT WITH CHECK (
+        team_id IN (
+            SELECT id FROM mana_teams
+            WHERE owner_id = current_setting('request.jwt.claims')::json->>'sub'
+        )
+    );
+
+-- Caller's role in a team (bypasses RLS so policies can use it without recursing)
+CREATE OR REPLACE FUNCTION mana_team_role(team UUID)
+RETURNS TEXT
+LANGUAGE sql STABLE SECURITY DEFINER SET search_path = public
+AS $$
+    SELECT role FROM mana_team_members
+    WHERE team_id = team
+      AND user_id = current_setting('request.jwt.claims', true)::json->>'sub'
+$$;
+
+-- Invites: team owners and admins create, list and revoke them
+CREATE POLICY team_invites_admin_policy ON mana_team_invites
+    FOR ALL USING (mana_team_role(team_id) IN ('owner', 'admin'))
+    WITH CHECK (
+        mana_team_role(team_id) IN ('owner', 'admin')
+        AND created_by = current_setting('request.jwt.claims')::json->>'sub'
+    );
+
+-- Redeem an invite: checks it exists, hasn't expired or been used, and
+-- was issued to the caller's email, then adds the caller to the team
+CREATE OR REPLACE FUNCTION mana_redeem_invite(invite_code TEXT)
+RETURNS json
+LANGUAGE plpgsql SECURITY DEFINER SET search_path = public
+AS $$
+DECLARE
+    invite mana_team_invites%ROWTYPE;
+    claims json := current_setting('request.jwt.claims', true)::json;
+BEGIN
+    IF claims->>'sub' IS NULL THEN
+        RAISE EXCEPTION 'Sign in with mana login to redeem an invite';
+    END IF;
+
+    SELECT * INTO invite FROM mana_team_invites WHERE code = invite_code FOR UPDATE;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Invite code not found or revoked';
+    ELSIF invite.redeemed_at IS NOT NULL THEN
+        RAISE EXCEPTION 'Invite code has already been used';
+    ELSIF invite.expires_at < NOW() THEN
+        RAISE EXCEPTION 'Invite code has expired';
+    ELSIF invite.email IS NOT NULL AND lower(invite.email) <> lower(coalesce(claims->>'email', '')) THEN
+        RAISE EXCEPTION 'Invite code was issued to a different email address';
+    END IF;
+
+    INSERT INTO mana_team_members (team_id, user_id, role)
+        VALUES (invite.team_id, claims->>'sub', invite.role)
+        ON CONFLICT (team_id, user_id) DO NOTHING;
+    UPDATE mana_team_invites
+        SET redeemed_by = claims->>'sub', redeemed_at = NOW()
+        WHERE code = invite_code;
+
+    RETURN json_build_object('team_id', invite.team_id, 'role', invite.role);
+END;
+$$;
+
 -- Function to update updated_at on pattern changes
 CREATE OR REPLACE FUNCTION update_updated_at_column()
 RETURNS TRIGGER AS $$
@@ -773,6 +1038,11 @@ CREATE TRIGGER update_patterns_updated_at
     BEFORE UPDATE ON mana_patterns
     FOR EACH ROW
     EXECUTE FUNCTION update_updated_at_column();
+
+-- Realtime (mana sync listen): broadcast pattern changes, with the previous
+-- counts on updates so listeners can merge just the difference
+ALTER TABLE mana_patterns REPLICA IDENTITY FULL;
+ALTER PUBLICATION supabase_realtime ADD TABLE mana_patterns;
 "#
 }
 
@@ -783,6 +1053,8 @@ CREATE TRIGGER update_patterns_updated_at
 pub fn save_supabase_config(mana_dir: &Path, url: &str) -> Result<()> {
     use crate::sync::{SyncConfig, SyncBackend, SecurityConfig as SyncSecurityConfig, save_sync_config};
 
+    // Keep settings that `sync init` doesn't touch
+    let previous = crate::sync::load_sync_config(&mana_dir.join("sync.toml")).unwrap_or_default();
     let config = SyncConfig {
         enabled: true,
         backend: SyncBackend::Supabase {
@@ -790,6 +1062,10 @@ pub fn save_supabase_config(mana_dir: &Path, url: &str) -> Result<()> {
         },
         interval_minutes: 60,
         security: SyncSecurityConfig::default(),
+        // Re-running init shouldn't drop the trusted signers
+        signing: previous.signing,
+        git: previous.git,
+        registry: previous.registry,
     };
 
     let config_path = mana_dir.join("sync.toml");
@@ -799,15 +1075,70 @@ pub fn save_supabase_config(mana_dir: &Path, url: &str) -> Result<()> {
     Ok(())
 }
 
-/// Get a deterministic workspace ID from the MANA directory
-#[allow(dead_code)]
-fn get_workspace_id(mana_dir: &Path) -> String {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+/// Send a one-time sign-in code to an email address
+#[cfg(feature = "supabase")]
+pub async fn request_login_code(mana_dir: &Path, email: &str) -> Result<()> {
+    let supabase_config = backend_config(mana_dir)?;
+    supabase_auth::send_code(&supabase_config.url, &supabase_config.api_key, email).await
+}
+
+/// Send a sign-in code (stub when feature disabled)
+#[cfg(not(feature = "supabase"))]
+pub async fn request_login_code(_mana_dir: &Path, _email: &str) -> Result<()> {
+    Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
+}
+
+/// Sign in with an emailed code and store the session
+#[cfg(feature = "supabase")]
+pub async fn login_with_code(mana_dir: &Path, email: &str, code: &str) -> Result<Session> {
+    let supabase_config = backend_config(mana_dir)?;
+    let session = supabase_auth::verify_code(&supabase_config.url, &supabase_config.api_key, email, code).await?;
+    supabase_auth::save(mana_dir, &session)?;
+    info!("Logged in to Supabase as {}", session.user_id);
+    Ok(session)
+}
+
+/// Sign in with a code (stub when feature disabled)
+#[cfg(not(feature = "supabase"))]
+pub async fn login_with_code(_mana_dir: &Path, _email: &str, _code: &str) -> Result<Session> {
+    Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
+}
+
+/// Sign in with an existing access token and store the session
+#[cfg(feature = "supabase")]
+pub async fn login_with_token(mana_dir: &Path, access_token: &str) -> Result<Session> {
+    let supabase_config = backend_config(mana_dir)?;
+    let session = supabase_auth::verify_token(&supabase_config.url, &supabase_config.api_key, access_token).await?;
+    supabase_auth::save(mana_dir, &session)?;
+    info!("Logged in to Supabase as {}", session.user_id);
+    Ok(session)
+}
 
-    let mut hasher = DefaultHasher::new();
-    mana_dir.to_string_lossy().hash(&mut hasher);
-    format!("ws-{:016x}", hasher.finish())
+/// Sign in with a token (stub when feature disabled)
+#[cfg(not(feature = "supabase"))]
+pub async fn login_with_token(_mana_dir: &Path, _access_token: &str) -> Result<Session> {
+    Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
+}
+
+/// Sign out: revoke the session on the server if possible and forget it locally
+///
+/// Returns whether a session was stored.
+pub async fn logout(mana_dir: &Path) -> Result<bool> {
+    #[cfg(feature = "supabase")]
+    if let (Ok(supabase_config), Some(session)) = (backend_config(mana_dir), supabase_auth::load(mana_dir)?) {
+        if let Err(e) = supabase_auth::revoke(&supabase_config.url, &supabase_config.api_key, &session).await {
+            tracing::warn!("Could not revoke Supabase session: {}", e);
+        }
+    }
+    supabase_auth::clear(mana_dir)
+}
+
+/// Supabase config from sync.toml, without a user session
+#[cfg(feature = "supabase")]
+fn backend_config(mana_dir: &Path) -> Result<SupabaseConfig> {
+    let config = load_sync_config(&mana_dir.join("sync.toml"))?;
+    SupabaseConfig::from_backend(&config.backend)
+        .ok_or_else(|| anyhow!("Sync backend is not configured for Supabase or MANA_SUPABASE_KEY not set"))
 }
 
 /// Check if Supabase feature is available
@@ -815,30 +1146,108 @@ pub fn is_supabase_available() -> bool {
     cfg!(feature = "supabase")
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Supabase backend for `mana sync` push, pull and status
+pub struct SupabaseSync;
 
-    #[test]
-    fn test_workspace_id_deterministic() {
-        let path1 = Path::new("/home/user/.mana");
-        let path2 = Path::new("/home/user/.mana");
-        let path3 = Path::new("/home/other/.mana");
+impl SupabaseSync {
+    fn require_feature() -> Result<()> {
+        if !is_supabase_available() {
+            return Err(anyhow!(
+                "Supabase sync not available. Rebuild MANA with: cargo build --release --features supabase"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl SyncBackendImpl for SupabaseSync {
+    fn name(&self) -> &'static str {
+        "supabase"
+    }
 
-        let id1 = get_workspace_id(path1);
-        let id2 = get_workspace_id(path2);
-        let id3 = get_workspace_id(path3);
+    fn push<'a>(&'a self, ctx: &'a SyncContext<'a>, _options: &'a PushOptions) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            Self::require_feature()?;
+            let count = push_patterns_supabase(
+                ctx.mana_dir,
+                ctx.db_path,
+                &SecurityConfig::default(),
+                ctx.config.security.visibility,
+            ).await?;
+            println!("✅ Pushed {} patterns to Supabase", count);
+            Ok(())
+        })
+    }
 
-        assert_eq!(id1, id2);
-        assert_ne!(id1, id3);
-        assert!(id1.starts_with("ws-"));
+    fn pull<'a>(&'a self, ctx: &'a SyncContext<'a>, options: &'a PullOptions) -> BackendFuture<'a, PullReport> {
+        Box::pin(async move {
+            Self::require_feature()?;
+            let result = pull_patterns_supabase(
+                ctx.mana_dir,
+                ctx.db_path,
+                options.merge_strategy,
+                true,   // include team patterns
+                false,  // don't include public by default
+            ).await?;
+            println!("✅ Pulled patterns from Supabase");
+            println!("   Total: {}, New: {}, Merged: {}",
+                result.total, result.imported, result.merged);
+            if result.skipped > 0 {
+                println!("   Skipped: {}", result.skipped);
+            }
+            Ok(PullReport {
+                changed: result.total,
+                imported: result.imported,
+                merged: result.merged,
+                skipped: result.skipped,
+                conflicts: Vec::new(),
+            })
+        })
     }
 
+    fn status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, serde_json::Value> {
+        Box::pin(async move { Ok(serde_json::to_value(supabase_status(ctx.mana_dir).await?)?) })
+    }
+
+    fn print_status<'a>(&'a self, ctx: &'a SyncContext<'a>) -> BackendFuture<'a, ()> {
+        Box::pin(async move {
+            println!("Backend: supabase");
+            if let SyncBackend::Supabase { url } = &ctx.config.backend {
+                println!("URL: {}", url);
+            }
+            if !is_supabase_available() {
+                println!("Status: ⚠️  Feature not compiled");
+                println!("Rebuild with: cargo build --release --features supabase");
+                return Ok(());
+            }
+            let status = supabase_status(ctx.mana_dir).await?;
+            if status.connected {
+                println!("Connected: ✅");
+                match (&status.email, &status.user_id) {
+                    (Some(email), Some(id)) => println!("User: {} ({})", email, id),
+                    (None, Some(id)) => println!("User: {}", id),
+                    _ => println!("User: ⚠️  Not logged in (run 'mana login --email <address>')"),
+                }
+                if let Some(count) = status.pattern_count {
+                    println!("Remote patterns: {}", count);
+                }
+            } else {
+                println!("Connected: ❌");
+                println!("Check MANA_SUPABASE_KEY environment variable");
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
     #[test]
     fn test_is_supabase_available() {
         let available = is_supabase_available();
-        // Just verify it compiles and returns a bool
-        assert!(available || !available);
+        assert_eq!(available, cfg!(feature = "supabase"));
     }
 
     #[test]
@@ -848,5 +1257,42 @@ mod tests {
         assert!(sql.contains("mana_patterns"));
         assert!(sql.contains("mana_teams"));
         assert!(sql.contains("mana_team_members"));
+        assert!(sql.contains("mana_team_invites"));
+        assert!(sql.contains("FUNCTION mana_redeem_invite(invite_code TEXT)"));
+    }
+
+    #[test]
+    fn test_pattern_filter_limits_team_patterns_to_memberships() {
+        assert_eq!(pattern_filter("u1", &[], false), "or=(owner_id.eq.u1)");
+
+        let teams = vec!["t1".to_string(), "t2".to_string()];
+        assert_eq!(
+            pattern_filter("u1", &teams, true),
+            "or=(owner_id.eq.u1,and(visibility.eq.team,team_id.in.(t1,t2)),visibility.eq.public)"
+        );
+    }
+
+    #[test]
+    fn test_invite_status() {
+        let now = chrono::Utc::now();
+        let mut invite = TeamInvite {
+            code: "c0de".to_string(),
+            team_id: "team".to_string(),
+            email: Some("dev@example.com".to_string()),
+            role: "member".to_string(),
+            created_by: "owner".to_string(),
+            created_at: None,
+            expires_at: (now + chrono::Duration::days(7)).to_rfc3339(),
+            redeemed_by: None,
+            redeemed_at: None,
+        };
+        assert_eq!(invite.status(now), "pending");
+        assert_eq!(invite.status(now + chrono::Duration::days(8)), "expired");
+
+        invite.redeemed_at = Some(now.to_rfc3339());
+        assert_eq!(invite.status(now + chrono::Duration::days(8)), "redeemed");
+
+        // Rows skip created_at so the database default applies
+        assert!(!serde_json::to_string(&invite).unwrap().contains("created_at"));
     }
 }
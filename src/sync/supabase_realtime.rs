@@ -0,0 +1,395 @@
+//! Live team pattern updates over Supabase Realtime
+//!
+//! `mana sync listen` keeps a websocket open to the project's Realtime
+//! server and joins a channel subscribed to Postgres changes on
+//! `mana_patterns` for the user's teams. Each inserted or updated row is
+//! merged into the local database as it arrives, so teammates' patterns
+//! show up without polling `mana sync pull`.
+//!
+//! Realtime applies the same row-level security as the REST API, using the
+//! signed-in user's token. Updates carry the previous row (the schema sets
+//! `REPLICA IDENTITY FULL`), so with the default `add` merge only the change
+//! in counts is added. Changes made while disconnected are not replayed;
+//! the next `mana sync pull` picks them up.
+
+// The protocol helpers are only called by the listener when Supabase is compiled in
+#![cfg_attr(not(feature = "supabase"), allow(dead_code))]
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::sync::export::MergeStrategy;
+use crate::sync::supabase_backend::SharedPattern;
+use crate::sync::ExportablePattern;
+
+/// Channel the listener joins; the name is local to this connection
+const CHANNEL_TOPIC: &str = "realtime:mana-team-patterns";
+
+/// Phoenix closes sockets that stay silent for 60 seconds
+#[cfg(feature = "supabase")]
+const HEARTBEAT_SECS: u64 = 25;
+
+/// Longest wait between reconnect attempts
+#[cfg(feature = "supabase")]
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// What happened while listening, reported as it happens
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ListenEvent {
+    /// Subscribed to pattern changes for these teams
+    Subscribed { teams: Vec<String> },
+    /// A teammate's pattern was merged into the local database
+    Merged {
+        pattern_hash: String,
+        tool_type: String,
+        team_id: Option<String>,
+        /// "imported", "merged" or "skipped"
+        outcome: String,
+    },
+    /// The connection dropped; retrying after a delay
+    Reconnecting { error: String, delay_secs: u64 },
+}
+
+/// A row change delivered by Realtime
+#[derive(Debug, Clone, PartialEq)]
+struct PatternChange {
+    /// INSERT or UPDATE (deletes aren't applied locally)
+    kind: String,
+    record: SharedPattern,
+    /// Counts before an update, when the table sends the old row
+    previous_counts: Option<(i64, i64)>,
+}
+
+/// A decoded Realtime frame
+#[derive(Debug, PartialEq)]
+enum Frame {
+    /// Reply to our channel join
+    Joined,
+    /// The server rejected the join or the Postgres subscription
+    Rejected(String),
+    Change(Box<PatternChange>),
+    /// Heartbeat replies, presence and other traffic we don't act on
+    Other,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    topic: String,
+    event: String,
+    #[serde(default)]
+    payload: Value,
+    #[serde(rename = "ref")]
+    reference: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OldCounts {
+    success_count: Option<i64>,
+    failure_count: Option<i64>,
+}
+
+/// Realtime websocket endpoint for a project URL
+fn websocket_url(project_url: &str, api_key: &str) -> String {
+    let base = project_url.trim_end_matches('/');
+    let base = if let Some(host) = base.strip_prefix("https://") {
+        format!("wss://{}", host)
+    } else if let Some(host) = base.strip_prefix("http://") {
+        format!("ws://{}", host)
+    } else {
+        base.to_string()
+    };
+    format!("{}/realtime/v1/websocket?apikey={}&vsn=1.0.0", base, api_key)
+}
+
+/// Join the pattern channel, subscribed to changes for the given teams
+fn join_message(team_ids: &[String], access_token: &str, reference: &str) -> Value {
+    json!({
+        "topic": CHANNEL_TOPIC,
+        "event": "phx_join",
+        "payload": {
+            "config": {
+                "postgres_changes": [{
+                    "event": "*",
+                    "schema": "public",
+                    "table": "mana_patterns",
+                    "filter": format!("team_id=in.({})", team_ids.join(",")),
+                }],
+            },
+            "access_token": access_token,
+        },
+        "ref": reference,
+        "join_ref": reference,
+    })
+}
+
+/// Keep the socket alive
+fn heartbeat_message(reference: &str) -> Value {
+    json!({ "topic": "phoenix", "event": "heartbeat", "payload": {}, "ref": reference })
+}
+
+/// Hand the channel a refreshed access token so RLS keeps applying
+fn access_token_message(access_token: &str, reference: &str) -> Value {
+    json!({
+        "topic": CHANNEL_TOPIC,
+        "event": "access_token",
+        "payload": { "access_token": access_token },
+        "ref": reference,
+    })
+}
+
+/// Decode a frame from the server
+fn parse_frame(text: &str, join_ref: &str) -> Result<Frame> {
+    let envelope: Envelope = serde_json::from_str(text)?;
+    if envelope.topic != CHANNEL_TOPIC {
+        return Ok(Frame::Other);
+    }
+    let status = envelope.payload.get("status").and_then(Value::as_str);
+    match envelope.event.as_str() {
+        "phx_reply" if envelope.reference.as_deref() == Some(join_ref) => Ok(match status {
+            Some("ok") => Frame::Joined,
+            _ => Frame::Rejected(envelope.payload["response"].to_string()),
+        }),
+        // Sent when the Postgres subscription itself fails, e.g. the table
+        // isn't in the supabase_realtime publication
+        "system" if status == Some("error") => Ok(Frame::Rejected(
+            envelope.payload["message"].as_str().unwrap_or("subscription failed").to_string(),
+        )),
+        "phx_error" | "phx_close" => Ok(Frame::Rejected(format!("channel closed by server ({})", envelope.event))),
+        "postgres_changes" => {
+            let data = &envelope.payload["data"];
+            let kind = data["type"].as_str().unwrap_or_default().to_string();
+            if kind != "INSERT" && kind != "UPDATE" {
+                return Ok(Frame::Other);
+            }
+            let record: SharedPattern = serde_json::from_value(data["record"].clone())?;
+            let previous_counts = serde_json::from_value::<OldCounts>(data["old_record"].clone())
+                .ok()
+                .and_then(|old| Some((old.success_count?, old.failure_count?)));
+            Ok(Frame::Change(Box::new(PatternChange { kind, record, previous_counts })))
+        }
+        _ => Ok(Frame::Other),
+    }
+}
+
+/// The pattern to merge for a change, or None if there's nothing new
+///
+/// Rows hold running totals, so adding an updated row as-is would count
+/// everything before the update twice. With the `add` strategy an update
+/// contributes only its increase; the other strategies compare whole rows.
+fn pattern_to_merge(change: PatternChange, strategy: MergeStrategy) -> Option<ExportablePattern> {
+    let mut pattern = ExportablePattern::from(change.record);
+    if change.kind == "UPDATE" && strategy == MergeStrategy::Add {
+        let (success, failure) = change.previous_counts?;
+        pattern.success_count = (pattern.success_count - success).max(0);
+        pattern.failure_count = (pattern.failure_count - failure).max(0);
+        if pattern.success_count == 0 && pattern.failure_count == 0 {
+            return None;
+        }
+    }
+    Some(pattern)
+}
+
+/// Listen for team pattern changes until interrupted
+///
+/// Reconnects with exponential backoff when the connection drops, and
+/// rereads team memberships each time so teams joined meanwhile are
+/// included.
+#[cfg(feature = "supabase")]
+pub async fn listen(
+    mana_dir: &Path,
+    db_path: &Path,
+    merge_strategy: MergeStrategy,
+    mut on_event: impl FnMut(&ListenEvent),
+) -> Result<()> {
+    use crate::sync::supabase_backend::{connect, member_team_ids};
+
+    let mut backoff = 1;
+    loop {
+        let config = connect(mana_dir).await?;
+        let team_ids = member_team_ids(&config, &reqwest::Client::new()).await?;
+        if team_ids.is_empty() {
+            return Err(anyhow!("Not a member of any team. Join one with 'mana team join <code>'"));
+        }
+
+        let result = tokio::select! {
+            result = run_session(mana_dir, db_path, &config, &team_ids, merge_strategy, &mut on_event, &mut backoff) => result,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        // Only connection problems are retried; a rejected subscription won't fix itself
+        let error = match result {
+            Err(e) if e.downcast_ref::<tokio_tungstenite::tungstenite::Error>().is_some() => e,
+            other => return other,
+        };
+
+        on_event(&ListenEvent::Reconnecting { error: error.to_string(), delay_secs: backoff });
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(backoff)) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+    }
+}
+
+/// Listen for team pattern changes (stub when feature disabled)
+#[cfg(not(feature = "supabase"))]
+pub async fn listen(
+    _mana_dir: &Path,
+    _db_path: &Path,
+    _merge_strategy: MergeStrategy,
+    _on_event: impl FnMut(&ListenEvent),
+) -> Result<()> {
+    Err(anyhow!("Supabase sync not available. Rebuild with --features supabase"))
+}
+
+/// One websocket connection: join, then merge changes until it drops
+#[cfg(feature = "supabase")]
+async fn run_session(
+    mana_dir: &Path,
+    db_path: &Path,
+    config: &crate::sync::supabase_backend::SupabaseConfig,
+    team_ids: &[String],
+    merge_strategy: MergeStrategy,
+    on_event: &mut impl FnMut(&ListenEvent),
+    backoff: &mut u64,
+) -> Result<()> {
+    use crate::sync::export::import_patterns_from_vec;
+    use crate::sync::supabase_auth;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(websocket_url(&config.url, &config.api_key)).await?;
+    let mut access_token = config.bearer().to_string();
+    let mut next_ref = 1u64;
+    let join_ref = next_ref.to_string();
+    socket.send(Message::Text(join_message(team_ids, &access_token, &join_ref).to_string())).await?;
+
+    let mut heartbeat = tokio::time::interval(std::time::Duration::from_secs(HEARTBEAT_SECS));
+    heartbeat.tick().await;
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                next_ref += 1;
+                socket.send(Message::Text(heartbeat_message(&next_ref.to_string()).to_string())).await?;
+                // Sessions last about an hour; keep the channel's token current
+                if let Some(session) = supabase_auth::current(mana_dir, &config.url, &config.api_key).await? {
+                    if session.access_token != access_token {
+                        access_token = session.access_token;
+                        next_ref += 1;
+                        socket.send(Message::Text(access_token_message(&access_token, &next_ref.to_string()).to_string())).await?;
+                    }
+                }
+            }
+            message = socket.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err(tokio_tungstenite::tungstenite::Error::ConnectionClosed.into());
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                match parse_frame(&text, &join_ref)? {
+                    Frame::Joined => {
+                        *backoff = 1;
+                        on_event(&ListenEvent::Subscribed { teams: team_ids.to_vec() });
+                    }
+                    Frame::Rejected(reason) => {
+                        return Err(anyhow!("Realtime subscription failed: {}. Is mana_patterns in the supabase_realtime publication? See 'mana team setup-schema'", reason));
+                    }
+                    // Our own pushes echo back; they're already in the local database
+                    Frame::Change(change) if change.record.owner_id == config.owner_id() => {}
+                    Frame::Change(change) => {
+                        let (hash, tool, team) = (
+                            change.record.pattern_hash.clone(),
+                            change.record.tool_type.clone(),
+                            change.record.team_id.clone(),
+                        );
+                        let Some(pattern) = pattern_to_merge(*change, merge_strategy) else {
+                            continue;
+                        };
+                        let result = import_patterns_from_vec(db_path, vec![pattern], merge_strategy)?;
+                        let outcome = if result.imported > 0 {
+                            "imported"
+                        } else if result.merged > 0 {
+                            "merged"
+                        } else {
+                            "skipped"
+                        };
+                        on_event(&ListenEvent::Merged {
+                            pattern_hash: hash,
+                            tool_type: tool,
+                            team_id: team,
+                            outcome: outcome.to_string(),
+                        });
+                    }
+                    Frame::Other => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change_frame(kind: &str, success: i64, old: Value) -> String {
+        json!({
+            "topic": CHANNEL_TOPIC,
+            "event": "postgres_changes",
+            "payload": { "data": {
+                "type": kind,
+                "record": {
+                    "id": "p1", "pattern_hash": "abc", "tool_type": "Bash", "command_category": "cargo",
+                    "context_query": "cargo test", "success_count": success, "failure_count": 1,
+                    "owner_id": "teammate", "team_id": "t1", "visibility": "team",
+                    "created_at": null, "updated_at": null,
+                },
+                "old_record": old,
+            }},
+            "ref": null,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_join_and_websocket_url() {
+        assert_eq!(
+            websocket_url("https://abc.supabase.co/", "anon"),
+            "wss://abc.supabase.co/realtime/v1/websocket?apikey=anon&vsn=1.0.0"
+        );
+        let join = join_message(&["t1".to_string(), "t2".to_string()], "jwt", "1");
+        assert_eq!(join["payload"]["config"]["postgres_changes"][0]["filter"], "team_id=in.(t1,t2)");
+        assert_eq!(join["payload"]["access_token"], "jwt");
+
+        let reply = json!({ "topic": CHANNEL_TOPIC, "event": "phx_reply", "payload": { "status": "ok", "response": {} }, "ref": "1" });
+        assert_eq!(parse_frame(&reply.to_string(), "1").unwrap(), Frame::Joined);
+        let heartbeat = json!({ "topic": "phoenix", "event": "phx_reply", "payload": { "status": "ok" }, "ref": "2" });
+        assert_eq!(parse_frame(&heartbeat.to_string(), "1").unwrap(), Frame::Other);
+    }
+
+    #[test]
+    fn test_updates_merge_only_new_counts() {
+        let Frame::Change(insert) = parse_frame(&change_frame("INSERT", 5, Value::Null), "1").unwrap() else {
+            panic!("expected a change");
+        };
+        assert_eq!(pattern_to_merge(*insert, MergeStrategy::Add).unwrap().success_count, 5);
+
+        let old = json!({ "id": "p1", "success_count": 3, "failure_count": 1 });
+        let Frame::Change(update) = parse_frame(&change_frame("UPDATE", 5, old), "1").unwrap() else {
+            panic!("expected a change");
+        };
+        let delta = pattern_to_merge((*update).clone(), MergeStrategy::Add).unwrap();
+        assert_eq!((delta.success_count, delta.failure_count), (2, 0));
+        assert_eq!(pattern_to_merge(*update, MergeStrategy::Replace).unwrap().success_count, 5);
+
+        // Without the old row there's no way to tell what's new
+        let Frame::Change(bare) = parse_frame(&change_frame("UPDATE", 5, json!({ "id": "p1" })), "1").unwrap() else {
+            panic!("expected a change");
+        };
+        assert!(pattern_to_merge(*bare, MergeStrategy::Add).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//! Shared verdict statistics
+//!
+//! `mana export --with-verdicts` adds what reflection learned about each
+//! exported pattern to the bundle: how many verdicts found it effective,
+//! neutral, ineffective or harmful, and the most common root causes given.
+//! Importing a bundle keeps those stats in `shared_verdicts`, one row per
+//! pattern and source device, so a pattern arrives with its reputation.
+//! Importing a newer bundle from the same device replaces its stats rather
+//! than adding to them, since each bundle carries the device's totals.
+//! Stats from bundle imports count as team experience and those from
+//! installed packs as public; `storage::ranking` weighs them accordingly.
+//!
+//! Local verdicts are never mixed in: `reflection_verdicts` only ever holds
+//! judgments of sessions on this machine.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::storage::Pattern;
+use crate::sync::ExportablePattern;
+
+/// Root causes listed per pattern
+const MAX_ROOT_CAUSES: usize = 3;
+
+/// Verdicts on one pattern, as carried in an export bundle
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VerdictStats {
+    /// Hash of the exported pattern
+    pub pattern_hash: String,
+    pub effective: i64,
+    pub neutral: i64,
+    pub ineffective: i64,
+    pub harmful: i64,
+    /// Most common root causes, most frequent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub root_causes: Vec<RootCause>,
+}
+
+/// A root cause and how many verdicts gave it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootCause {
+    pub cause: String,
+    pub count: i64,
+}
+
+/// Where shared stats came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provenance {
+    /// An export bundle from a teammate's device
+    Team,
+    /// A pack or registry install
+    Public,
+}
+
+impl Provenance {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Provenance::Team => "team",
+            Provenance::Public => "public",
+        }
+    }
+}
+
+impl VerdictStats {
+    pub fn total(&self) -> i64 {
+        self.effective + self.neutral + self.ineffective + self.harmful
+    }
+}
+
+/// Create the shared_verdicts table
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS shared_verdicts (
+            pattern_id INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            effective INTEGER NOT NULL DEFAULT 0,
+            neutral INTEGER NOT NULL DEFAULT 0,
+            ineffective INTEGER NOT NULL DEFAULT 0,
+            harmful INTEGER NOT NULL DEFAULT 0,
+            root_causes TEXT NOT NULL DEFAULT '[]',
+            provenance TEXT NOT NULL DEFAULT 'team',
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (pattern_id, source),
+            FOREIGN KEY (pattern_id) REFERENCES patterns(id) ON DELETE CASCADE
+        );
+        "#,
+    )?;
+    if !crate::storage::has_column(conn, "shared_verdicts", "provenance") {
+        conn.execute_batch("ALTER TABLE shared_verdicts ADD COLUMN provenance TEXT NOT NULL DEFAULT 'team'")?;
+    }
+    Ok(())
+}
+
+/// Local verdict stats for exported patterns, keyed by their exported hash
+///
+/// `patterns` and `exports` are parallel; patterns nobody has judged are
+/// left out. Root causes are passed through `clean` (sanitization).
+pub fn collect(
+    conn: &Connection,
+    patterns: &[Pattern],
+    exports: &[ExportablePattern],
+    clean: impl Fn(&str) -> String,
+) -> Result<Vec<VerdictStats>> {
+    // Reflection tables appear with the first reflection cycle
+    if !crate::storage::has_column(conn, "reflection_verdicts", "pattern_id") {
+        return Ok(Vec::new());
+    }
+    let mut counts_stmt = conn.prepare_cached(
+        "SELECT UPPER(verdict), COUNT(*) FROM reflection_verdicts WHERE pattern_id = ?1 GROUP BY 1",
+    )?;
+    let mut causes_stmt = conn.prepare_cached(
+        "SELECT root_cause, COUNT(*) FROM reflection_verdicts
+         WHERE pattern_id = ?1 AND root_cause IS NOT NULL AND root_cause != ''
+         GROUP BY root_cause ORDER BY COUNT(*) DESC, root_cause LIMIT ?2",
+    )?;
+
+    let mut all = Vec::new();
+    for (pattern, export) in patterns.iter().zip(exports) {
+        let mut stats = VerdictStats { pattern_hash: export.pattern_hash.clone(), ..Default::default() };
+        let rows = counts_stmt.query_map(params![pattern.id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (verdict, count) = row?;
+            match verdict.as_str() {
+                "EFFECTIVE" => stats.effective += count,
+                "NEUTRAL" => stats.neutral += count,
+                "INEFFECTIVE" => stats.ineffective += count,
+                "HARMFUL" => stats.harmful += count,
+                _ => {}
+            }
+        }
+        if stats.total() == 0 {
+            continue;
+        }
+        let causes = causes_stmt.query_map(params![pattern.id, MAX_ROOT_CAUSES as i64], |row| {
+            Ok(RootCause { cause: row.get(0)?, count: row.get(1)? })
+        })?;
+        stats.root_causes = causes
+            .map(|c| c.map(|c| RootCause { cause: clean(&c.cause), ..c }))
+            .collect::<rusqlite::Result<_>>()?;
+        all.push(stats);
+    }
+    Ok(all)
+}
+
+/// Store imported verdict stats from `source`, returning how many patterns got some
+///
+/// Stats for patterns that aren't here (skipped on import) are dropped.
+pub fn merge(conn: &Connection, stats: &[VerdictStats], source: &str, provenance: Provenance) -> Result<usize> {
+    create_table(conn)?;
+    let ids: HashMap<String, i64> = {
+        let mut stmt = conn.prepare("SELECT pattern_hash, id FROM patterns")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    let mut merged = 0;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO shared_verdicts (pattern_id, source, effective, neutral, ineffective, harmful, root_causes, provenance)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT(pattern_id, source) DO UPDATE SET
+                effective = excluded.effective,
+                neutral = excluded.neutral,
+                ineffective = excluded.ineffective,
+                harmful = excluded.harmful,
+                root_causes = excluded.root_causes,
+                provenance = excluded.provenance,
+                updated_at = CURRENT_TIMESTAMP",
+        )?;
+        for s in stats {
+            let Some(id) = ids.get(&s.pattern_hash) else {
+                continue;
+            };
+            stmt.execute(params![
+                id,
+                source,
+                s.effective.max(0),
+                s.neutral.max(0),
+                s.ineffective.max(0),
+                s.harmful.max(0),
+                serde_json::to_string(&s.root_causes)?,
+                provenance.as_str()
+            ])?;
+            merged += 1;
+        }
+    }
+    tx.commit()?;
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_and_merge_verdict_stats() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        crate::reflection::init_reflection_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query) VALUES (1, 'local', 'Bash', 'cargo build'), (2, 'quiet', 'Bash', 'ls');
+             INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence, root_cause) VALUES
+                ('t1', 1, 'EFFECTIVE', 0.9, NULL),
+                ('t2', 1, 'HARMFUL', 0.8, 'wrong path /home/alice/app'),
+                ('t3', 1, 'HARMFUL', 0.8, 'wrong path /home/alice/app'),
+                ('t4', 1, 'INEFFECTIVE', 0.6, 'stale flag');",
+        )
+        .unwrap();
+        let pattern = |id: i64, hash: &str| Pattern {
+            id,
+            pattern_hash: hash.to_string(),
+            tool_type: "Bash".to_string(),
+            command_category: None,
+            context_query: String::new(),
+            success_count: 0,
+            failure_count: 0,
+            embedding_id: None,
+            risky: false,
+        };
+        let patterns = vec![pattern(1, "local"), pattern(2, "quiet")];
+        let exports: Vec<ExportablePattern> = patterns
+            .iter()
+            .map(|p| ExportablePattern {
+                pattern_hash: format!("exported-{}", p.pattern_hash),
+                tool_type: p.tool_type.clone(),
+                command_category: None,
+                context_query: p.context_query.clone(),
+                success_count: 0,
+                failure_count: 0,
+                device: None,
+                risky: false,
+            })
+            .collect();
+
+        let stats = collect(&conn, &patterns, &exports, |c| c.replace("/home/alice", "$HOME")).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].effective, stats[0].ineffective, stats[0].harmful), (1, 1, 2));
+        assert_eq!(stats[0].root_causes[0], RootCause { cause: "wrong path $HOME/app".into(), count: 2 });
+
+        // Imported under the exported hash; a newer bundle replaces the source's stats
+        conn.execute("UPDATE patterns SET pattern_hash = 'exported-local' WHERE id = 1", []).unwrap();
+        assert_eq!(merge(&conn, &stats, "laptop", Provenance::Team).unwrap(), 1);
+        let newer = VerdictStats { effective: 5, ..stats[0].clone() };
+        assert_eq!(merge(&conn, &[newer, VerdictStats { pattern_hash: "missing".into(), ..Default::default() }], "laptop", Provenance::Team).unwrap(), 1);
+        let (rows, effective): (i64, i64) =
+            conn.query_row("SELECT COUNT(*), SUM(effective) FROM shared_verdicts", [], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+        assert_eq!((rows, effective), (1, 5));
+    }
+}
@@ -0,0 +1,246 @@
+//! Terminal tables and paging for listings
+//!
+//! `mana patterns list`, `patterns search` and `reflect verdicts` print
+//! aligned columns sized to the terminal: the text columns (pattern context,
+//! root cause) get whatever width is left and are cut with `…`. `--wide`
+//! ignores the terminal width and `--full-context` wraps the text columns
+//! instead of cutting them. Output that isn't going to a terminal is never
+//! cut.
+//!
+//! A listing taller than the terminal goes through `$PAGER` (`less -FRX` by
+//! default), except in CI mode or when `PAGER` is empty or `cat`.
+
+use anyhow::Result;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Pager used when `$PAGER` is unset
+const DEFAULT_PAGER: &str = "less -FRX";
+
+/// Columns between cells
+const GAP: &str = "  ";
+
+/// Narrowest a text column gets before the terminal width is given up on
+const MIN_TEXT_WIDTH: usize = 20;
+
+/// How text columns fit the width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fit {
+    /// Cut to the terminal width
+    #[default]
+    Truncate,
+    /// Wrap onto continuation lines
+    Wrap,
+    /// Never cut (`--wide`)
+    Wide,
+}
+
+impl Fit {
+    /// From the `--wide` and `--full-context` flags
+    pub fn from_flags(wide: bool, full_context: bool) -> Self {
+        match (wide, full_context) {
+            (_, true) => Fit::Wrap,
+            (true, false) => Fit::Wide,
+            _ => Fit::Truncate,
+        }
+    }
+}
+
+/// Rows of cells under a header line
+#[derive(Debug, Default)]
+pub struct Table {
+    headers: Vec<&'static str>,
+    /// Columns right-aligned (numbers)
+    right: Vec<bool>,
+    /// Columns that give up width to fit (free text)
+    text: Vec<bool>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// A table with these columns; a header starting with `>` is right-aligned
+    /// and one starting with `~` is free text
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            headers: headers.iter().map(|h| h.trim_start_matches(['>', '~'])).collect(),
+            right: headers.iter().map(|h| h.starts_with('>')).collect(),
+            text: headers.iter().map(|h| h.starts_with('~')).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        debug_assert_eq!(cells.len(), self.headers.len());
+        // Cells are one line each; newlines would break alignment
+        self.rows.push(cells.into_iter().map(|c| c.replace(['\n', '\r', '\t'], " ")).collect());
+    }
+
+    /// Lay the table out within `width` columns (None: no limit)
+    pub fn render(&self, width: Option<usize>, fit: Fit) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|h| h.chars().count()).collect();
+        for row in &self.rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        if let (Some(width), false) = (width, fit == Fit::Wide) {
+            self.shrink_text(&mut widths, width);
+        }
+
+        let mut out = String::new();
+        let headers: Vec<String> = self.headers.iter().map(|h| h.to_string()).collect();
+        self.push_line(&mut out, &headers, &widths);
+        for row in &self.rows {
+            if fit != Fit::Wrap {
+                let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, &w)| truncate(cell, w)).collect();
+                self.push_line(&mut out, &cells, &widths);
+                continue;
+            }
+            let wrapped: Vec<Vec<String>> = row.iter().zip(&widths).map(|(cell, &w)| wrap(cell, w)).collect();
+            let height = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+            for line in 0..height {
+                let cells: Vec<String> = wrapped.iter().map(|lines| lines.get(line).cloned().unwrap_or_default()).collect();
+                self.push_line(&mut out, &cells, &widths);
+            }
+        }
+        out
+    }
+
+    /// Narrow the text columns, widest first, until the table fits
+    fn shrink_text(&self, widths: &mut [usize], width: usize) {
+        let total = |widths: &[usize]| widths.iter().sum::<usize>() + GAP.len() * widths.len().saturating_sub(1);
+        while total(widths) > width {
+            let widest = (0..widths.len()).filter(|&i| self.text[i] && widths[i] > MIN_TEXT_WIDTH).max_by_key(|&i| widths[i]);
+            let Some(i) = widest else { break };
+            let excess = total(widths) - width;
+            widths[i] = widths[i].saturating_sub(excess).max(MIN_TEXT_WIDTH);
+        }
+    }
+
+    fn push_line(&self, out: &mut String, cells: &[String], widths: &[usize]) {
+        let mut line = String::new();
+        for (i, (cell, &w)) in cells.iter().zip(widths).enumerate() {
+            if i > 0 {
+                line.push_str(GAP);
+            }
+            let pad = " ".repeat(w.saturating_sub(cell.chars().count()));
+            if self.right[i] {
+                line.push_str(&pad);
+                line.push_str(cell);
+            } else {
+                line.push_str(cell);
+                line.push_str(&pad);
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+}
+
+/// `text` cut to `width` characters, ending in `…` if anything was cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// `text` broken into lines of at most `width` characters, at spaces where possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let mut word = word.to_string();
+        let len = |s: &str| s.chars().count();
+        if !line.is_empty() && len(&line) + 1 + len(&word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        // Words longer than a line are split
+        while len(&word) > width {
+            let head: String = word.chars().take(width).collect();
+            word = word.chars().skip(width).collect();
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(head);
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// The terminal's (columns, rows), None when stdout isn't a terminal
+///
+/// `COLUMNS` overrides the width.
+pub fn terminal_size() -> Option<(usize, usize)> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let (cols, rows) = ratatui::crossterm::terminal::size().map(|(c, r)| (c as usize, r as usize)).unwrap_or((100, 40));
+    let cols = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(cols);
+    Some((cols, rows))
+}
+
+/// Width tables should fit in
+pub fn width() -> Option<usize> {
+    terminal_size().map(|(cols, _)| cols)
+}
+
+/// Print `output`, through the pager if it's taller than the terminal
+pub fn page(output: &str) -> Result<()> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let tall = terminal_size().is_some_and(|(_, rows)| output.lines().count() >= rows);
+    if !tall || crate::ci::enabled() || pager.trim().is_empty() || pager.trim() == "cat" {
+        print!("{}", output);
+        return Ok(());
+    }
+
+    let child = Command::new("sh").arg("-c").arg(&pager).stdin(Stdio::piped()).spawn();
+    let Ok(mut child) = child else {
+        print!("{}", output);
+        return Ok(());
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager quitting early closes the pipe; that's not an error
+        let _ = stdin.write_all(output.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_fits_width() {
+        let mut table = Table::new(&[">ID", "TOOL", "~CONTEXT"]);
+        table.row(vec!["7".into(), "Bash".into(), "cargo build --release --workspace and then run all the tests".into()]);
+        table.row(vec!["12".into(), "Edit".into(), "short".into()]);
+
+        let cut = table.render(Some(40), Fit::Truncate);
+        assert!(cut.lines().all(|l| l.chars().count() <= 40), "{}", cut);
+        assert_eq!(cut.lines().nth(1).unwrap(), " 7  Bash  cargo build --release --works…");
+        assert_eq!(cut.lines().nth(2).unwrap(), "12  Edit  short");
+
+        let wrapped = table.render(Some(40), Fit::Wrap);
+        assert_eq!(wrapped.lines().count(), 5);
+        assert!(wrapped.lines().all(|l| l.chars().count() <= 40));
+        assert_eq!(wrapped.lines().nth(3).unwrap(), "          the tests");
+
+        // Far too narrow: text columns stop at their minimum
+        assert!(table.render(Some(5), Fit::Truncate).lines().nth(1).unwrap().ends_with("cargo build --relea…"));
+
+        let wide = table.render(Some(40), Fit::Wide);
+        assert!(wide.contains("run all the tests"));
+        assert_eq!(table.render(None, Fit::Truncate), wide);
+    }
+}
@@ -0,0 +1,205 @@
+//! Opt-in anonymous telemetry (`mana telemetry`)
+//!
+//! Off unless `mana telemetry enable` is run with an endpoint. The daemon
+//! then POSTs one report per `[telemetry] interval_hours`: the mana
+//! version, OS and architecture, pattern and vector counts, the share of
+//! recent reflection verdicts that were effective, neutral or harmful, and
+//! injection latency percentiles. Reports are aggregates only: no pattern
+//! text, paths, project names, hostnames or ids of any kind.
+//! `mana telemetry preview` prints exactly what would be sent.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::info;
+
+use crate::config::TelemetryConfig;
+
+/// When the last report was sent, inside the MANA data directory
+pub const STATE_FILE: &str = "telemetry-state.json";
+
+/// Bumped when fields change meaning
+const SCHEMA: u32 = 1;
+
+/// Verdicts older than this don't count towards the ratios
+const VERDICT_WINDOW_DAYS: i64 = 30;
+
+/// Latencies older than this don't count towards the percentiles
+const LATENCY_WINDOW_DAYS: i64 = 7;
+
+const SEND_TIMEOUT_SECS: u64 = 10;
+
+/// Everything a report contains
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub schema: u32,
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub patterns: PatternCounts,
+    pub verdicts: VerdictRatios,
+    pub latency: Latency,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PatternCounts {
+    pub total: usize,
+    pub active: usize,
+    pub archived: usize,
+    pub quarantined: usize,
+    /// Patterns in the embedding index
+    pub embedded: usize,
+}
+
+/// Shares of the last [`VERDICT_WINDOW_DAYS`] days' verdicts, to three places
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerdictRatios {
+    pub total: usize,
+    pub effective: f64,
+    pub neutral: f64,
+    pub harmful: f64,
+}
+
+/// Injection latency over the last [`LATENCY_WINDOW_DAYS`] days
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Latency {
+    pub samples: usize,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    last_sent: Option<DateTime<Utc>>,
+}
+
+/// Build the report from the database and embedding index
+pub fn collect(conn: &Connection, mana_dir: &Path) -> Result<Report> {
+    let mut patterns = PatternCounts::default();
+    // Databases from before pattern lifecycles have no status: all active
+    let status = if crate::storage::has_column(conn, "patterns", "status") { "status" } else { "NULL" };
+    let mut stmt = conn.prepare(&format!("SELECT {0}, COUNT(*) FROM patterns GROUP BY {0}", status))?;
+    for row in stmt.query_map([], |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, i64>(1)?)))? {
+        let (status, count) = row?;
+        let count = count as usize;
+        patterns.total += count;
+        match status.as_deref() {
+            Some("archived") => patterns.archived += count,
+            Some("quarantined") => patterns.quarantined += count,
+            _ => patterns.active += count,
+        }
+    }
+    patterns.embedded = crate::embeddings::VectorIndex::load(&mana_dir.join("vectors.usearch")).map(|i| i.len()).unwrap_or(0);
+
+    let since = Utc::now() - Duration::days(VERDICT_WINDOW_DAYS);
+    let mut counts = [0usize; 3];
+    // Before `mana reflect init` there's no verdict table; that's zero verdicts
+    if crate::storage::has_column(conn, "reflection_verdicts", "verdict") {
+        let mut stmt =
+            conn.prepare("SELECT verdict, COUNT(*) FROM reflection_verdicts WHERE created_at >= ?1 GROUP BY verdict")?;
+        let rows = stmt.query_map([since.format("%Y-%m-%d %H:%M:%S").to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (verdict, count) = row?;
+            match verdict.as_str() {
+                "EFFECTIVE" => counts[0] += count as usize,
+                "NEUTRAL" => counts[1] += count as usize,
+                "HARMFUL" => counts[2] += count as usize,
+                _ => {}
+            }
+        }
+    }
+    let total: usize = counts.iter().sum();
+    let share = |n: usize| if total == 0 { 0.0 } else { (n as f64 / total as f64 * 1000.0).round() / 1000.0 };
+    let verdicts = VerdictRatios { total, effective: share(counts[0]), neutral: share(counts[1]), harmful: share(counts[2]) };
+
+    let summary = crate::storage::injection_log::latency_summary(conn, Utc::now() - Duration::days(LATENCY_WINDOW_DAYS), 0)?;
+    let latency = Latency { samples: summary.samples, p50_us: summary.p50_us, p95_us: summary.p95_us, max_us: summary.max_us };
+
+    Ok(Report {
+        schema: SCHEMA,
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        patterns,
+        verdicts,
+        latency,
+    })
+}
+
+/// When the last report went out, if ever
+pub fn last_sent(mana_dir: &Path) -> Option<DateTime<Utc>> {
+    let text = std::fs::read_to_string(mana_dir.join(STATE_FILE)).ok()?;
+    serde_json::from_str::<State>(&text).ok()?.last_sent
+}
+
+/// Whether a report is due under `config`
+pub fn due(mana_dir: &Path, config: &TelemetryConfig, now: DateTime<Utc>) -> bool {
+    config.enabled
+        && config.endpoint.is_some()
+        && last_sent(mana_dir).is_none_or(|at| now - at >= Duration::hours(config.interval_hours as i64))
+}
+
+/// POST a report to `endpoint` and record that it was sent
+pub fn send(mana_dir: &Path, endpoint: &str, report: &Report) -> Result<()> {
+    crate::net::post(endpoint, &[], report, std::time::Duration::from_secs(SEND_TIMEOUT_SECS))
+        .with_context(|| format!("Failed to send telemetry to {}", endpoint))?;
+
+    let state = State { last_sent: Some(Utc::now()) };
+    std::fs::write(mana_dir.join(STATE_FILE), serde_json::to_string_pretty(&state)?)?;
+    info!("Sent telemetry to {}", endpoint);
+    Ok(())
+}
+
+/// Send a report if telemetry is enabled and one is due; true if one was sent
+pub fn send_if_due(mana_dir: &Path) -> Result<bool> {
+    let config = crate::config::load_config(mana_dir).telemetry;
+    let Some(endpoint) = config.endpoint.as_deref().filter(|_| due(mana_dir, &config, Utc::now())) else {
+        return Ok(false);
+    };
+    let conn = crate::storage::db::open_readonly(&mana_dir.join("metadata.sqlite"))?;
+    let report = collect(&conn, mana_dir)?;
+    send(mana_dir, endpoint, &report)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_is_aggregate_and_due_follows_interval() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::storage::create_schema(&conn).unwrap();
+        crate::reflection::init_reflection_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO patterns (id, pattern_hash, tool_type, context_query, status)
+             VALUES (1, 'a', 'Bash', 'cargo build /home/alice/secret', 'active'), (2, 'b', 'Bash', 'ls', 'archived');
+             INSERT INTO reflection_verdicts (trajectory_hash, pattern_id, verdict, confidence)
+             VALUES ('t1', 1, 'EFFECTIVE', 0.9), ('t2', 1, 'EFFECTIVE', 0.9), ('t3', 2, 'HARMFUL', 0.8);",
+        )
+        .unwrap();
+
+        let report = collect(&conn, temp.path()).unwrap();
+        assert_eq!((report.patterns.total, report.patterns.active, report.patterns.archived), (2, 1, 1));
+        assert_eq!((report.verdicts.total, report.verdicts.effective, report.verdicts.harmful), (3, 0.667, 0.333));
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("alice") && !json.contains("cargo"), "{}", json);
+
+        let mut config = TelemetryConfig::default();
+        let now = Utc::now();
+        assert!(!due(temp.path(), &config, now));
+        config.enabled = true;
+        config.endpoint = Some("https://telemetry.example.com/v1".into());
+        assert!(due(temp.path(), &config, now));
+        let state = State { last_sent: Some(now - Duration::hours(2)) };
+        std::fs::write(temp.path().join(STATE_FILE), serde_json::to_string(&state).unwrap()).unwrap();
+        assert!(!due(temp.path(), &config, now));
+        assert!(due(temp.path(), &config, now + Duration::hours(23)));
+    }
+}
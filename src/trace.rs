@@ -0,0 +1,75 @@
+//! Span recording for `--trace-file`
+//!
+//! Injection, learning, reflection and sync run inside tracing spans, one per
+//! stage. `--trace-file <path>` records them: a path ending in `.folded` gets
+//! folded stacks for inferno or flamegraph.pl, anything else Chrome trace
+//! JSON for chrome://tracing, Perfetto or speedscope. Hooks run with a fixed
+//! command line, so `MANA_TRACE_FILE` does the same for them.
+//!
+//! Without a trace file the spans cost a disabled-callsite check, so the
+//! injection hot path keeps its budget.
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Environment variable naming a trace file
+pub const ENV: &str = "MANA_TRACE_FILE";
+
+/// Writes out the trace when dropped; keep it alive until the command ends
+#[allow(dead_code)] // Held only for its Drop
+pub enum TraceGuard {
+    Chrome(tracing_chrome::FlushGuard),
+    Flame(tracing_flame::FlushGuard<BufWriter<File>>),
+}
+
+/// The trace file from `--trace-file`, or from [`ENV`]
+pub fn path(flag: Option<PathBuf>) -> Option<PathBuf> {
+    flag.or_else(|| std::env::var_os(ENV).filter(|v| !v.is_empty()).map(PathBuf::from))
+}
+
+/// Whether `path` asks for folded stacks rather than a Chrome trace
+fn is_folded(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "folded")
+}
+
+/// A layer recording MANA's spans (not dependencies') into `path`
+pub fn layer<S>(path: &Path) -> Result<(Box<dyn Layer<S> + Send + Sync>, TraceGuard)>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    let ours = Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::TRACE);
+    if is_folded(path) {
+        let (layer, guard) = tracing_flame::FlameLayer::with_file(path)?;
+        Ok((layer.with_filter(ours).boxed(), TraceGuard::Flame(guard)))
+    } else {
+        let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new().file(path).include_args(true).build();
+        Ok((layer.with_filter(ours).boxed(), TraceGuard::Chrome(guard)))
+    }
+}
+
+/// Record spans to `path` with no other logging (the inject hook)
+pub fn init(path: &Path) -> Result<TraceGuard> {
+    let (layer, guard) = layer(path)?;
+    tracing_subscriber::registry().with(layer).try_init()?;
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_path_and_format() {
+        assert_eq!(path(Some(PathBuf::from("t.json"))), Some(PathBuf::from("t.json")));
+        assert!(is_folded(Path::new("/tmp/inject.folded")));
+        assert!(!is_folded(Path::new("/tmp/inject.json")));
+    }
+}
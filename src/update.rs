@@ -2,25 +2,97 @@
 //!
 //! Checks GitHub releases for updates and downloads new binary.
 //! Supports both automatic update and manual download.
+//!
+//! Every release carries a `SHA256SUMS` manifest, and the downloaded binary
+//! must match its entry before it is installed. When `[update] release_keys`
+//! is set, the manifest must also be signed by one of those keys
+//! (`ssh-keygen -Y sign -n mana-release -f <key> SHA256SUMS`). The replaced
+//! binary is kept as `mana.bak` for `mana update --rollback`.
+//!
+//! `mana update --assets` installs the embedding model's files from the
+//! release's `assets.json`, verified the same way.
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tracing::{debug, info, warn};
 
+use crate::ci::{fail, ExitCode::Verification};
+use crate::config::UpdateSettings;
+use crate::embeddings::{self, assets::{self, AssetManifest}, EmbeddingStore};
+use crate::sync::signing::{self, SignatureStatus};
+
 const GITHUB_REPO: &str = "jedarden/MANA";
 const BINARY_NAME: &str = "mana";
 
+/// Checksum manifest attached to every release
+const CHECKSUMS: &str = "SHA256SUMS";
+
+/// Model asset manifest attached to releases that ship weights
+const ASSETS: &str = "assets.json";
+
+/// Signature namespace for release manifests
+pub const RELEASE_NAMESPACE: &str = "mana-release";
+
+/// Which releases to update to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Latest full release
+    Stable,
+    /// Newest release, pre-releases included
+    Beta,
+}
+
+impl Channel {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "stable" => Ok(Self::Stable),
+            "beta" => Ok(Self::Beta),
+            other => bail!("Unknown channel {:?}: use stable or beta", other),
+        }
+    }
+}
+
+/// Newest release tag including pre-releases
+fn newest_tag() -> Result<Option<String>> {
+    let output = Command::new("gh")
+        .args(["release", "list", "--repo", GITHUB_REPO, "--limit", "30", "--json", "tagName", "--jq", ".[].tagName"])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to list releases: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let tags = String::from_utf8_lossy(&output.stdout);
+    Ok(tags
+        .lines()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .fold(None, |best: Option<&str>, tag| match best {
+            Some(b) if !is_newer_version(tag.trim_start_matches('v'), b.trim_start_matches('v')) => Some(b),
+            _ => Some(tag),
+        })
+        .map(str::to_string))
+}
+
 /// Check for available updates from GitHub releases
-pub async fn check_for_updates() -> Result<Option<UpdateInfo>> {
+pub async fn check_for_updates(channel: Channel) -> Result<Option<UpdateInfo>> {
     let current_version = env!("CARGO_PKG_VERSION");
     info!("Current version: {}", current_version);
 
-    // Use gh CLI to fetch latest release info
-    let output = Command::new("gh")
-        .args(["release", "view", "--repo", GITHUB_REPO, "--json", "tagName,name,publishedAt,body"])
-        .output();
+    // Stable is whatever GitHub marks latest; beta picks the newest tag itself
+    let mut args = vec!["release".to_string(), "view".to_string()];
+    if channel == Channel::Beta {
+        match newest_tag() {
+            Ok(Some(tag)) => args.push(tag),
+            Ok(None) => return Ok(None),
+            Err(e) => debug!("Falling back to the latest release: {}", e),
+        }
+    }
+    args.extend(["--repo", GITHUB_REPO, "--json", "tagName,name,publishedAt,body"].map(String::from));
+
+    // Use gh CLI to fetch release info
+    let output = Command::new("gh").args(&args).output();
 
     match output {
         Ok(out) if out.status.success() => {
@@ -68,6 +140,22 @@ pub async fn check_for_updates() -> Result<Option<UpdateInfo>> {
     }
 }
 
+/// Latest released version (without the leading 'v'), via the GitHub CLI
+///
+/// Unlike [`check_for_updates`] this prints nothing and fails if `gh` is
+/// missing, so callers can report the problem themselves.
+pub fn latest_version() -> Result<String> {
+    let output = Command::new("gh")
+        .args(["release", "view", "--repo", GITHUB_REPO, "--json", "tagName", "--jq", ".tagName"])
+        .output()
+        .map_err(|e| anyhow!("GitHub CLI (gh) not available: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().trim_start_matches('v').to_string())
+}
+
 /// Information about an available update
 #[derive(Debug)]
 pub struct UpdateInfo {
@@ -80,42 +168,98 @@ pub struct UpdateInfo {
 }
 
 /// Compare version strings (semver-like comparison)
-fn is_newer_version(latest: &str, current: &str) -> bool {
-    let parse_version = |v: &str| -> (u32, u32, u32) {
-        let parts: Vec<u32> = v
+///
+/// A pre-release (`0.3.0-beta.1`) is older than its release (`0.3.0`).
+/// Pre-releases of the same version are ordered as semver does: by their
+/// dot-separated identifiers, numerically when both are numbers.
+pub fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parse_version = |v: &str| -> ((u32, u32, u32), Option<String>) {
+        let (core, pre) = match v.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (v, None),
+        };
+        let parts: Vec<u32> = core
             .split('.')
             .filter_map(|s| s.parse().ok())
             .collect();
         (
-            parts.first().copied().unwrap_or(0),
-            parts.get(1).copied().unwrap_or(0),
-            parts.get(2).copied().unwrap_or(0),
+            (
+                parts.first().copied().unwrap_or(0),
+                parts.get(1).copied().unwrap_or(0),
+                parts.get(2).copied().unwrap_or(0),
+            ),
+            pre,
         )
     };
 
-    let (l_major, l_minor, l_patch) = parse_version(latest);
-    let (c_major, c_minor, c_patch) = parse_version(current);
+    let (latest_core, latest_pre) = parse_version(latest);
+    let (current_core, current_pre) = parse_version(current);
 
-    (l_major, l_minor, l_patch) > (c_major, c_minor, c_patch)
+    match latest_core.cmp(&current_core) {
+        std::cmp::Ordering::Equal => match (latest_pre, current_pre) {
+            (None, Some(_)) => true,
+            (Some(l), Some(c)) => compare_prerelease(&l, &c).is_gt(),
+            _ => false,
+        },
+        ordering => ordering.is_gt(),
+    }
+}
+
+/// Order two pre-release tags (semver §11)
+///
+/// Numeric identifiers rank below alphanumeric ones, and a tag that is a
+/// prefix of another ranks below it (`beta` < `beta.1`).
+fn compare_prerelease(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let mut a_ids = a.split('.');
+    let mut b_ids = b.split('.');
+    loop {
+        let ordering = match (a_ids.next(), b_ids.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
 }
 
 /// Perform the update by downloading new binary from GitHub release
-pub async fn perform_update(info: &UpdateInfo) -> Result<()> {
+///
+/// The release is downloaded to a staging directory and only installed once
+/// its checksum (and signature, with `release_keys` set) checks out and the
+/// binary runs. The binary it replaces is kept as `mana.bak`.
+pub async fn perform_update(info: &UpdateInfo, settings: &UpdateSettings) -> Result<()> {
     info!("Updating from {} to {}", info.current_version, info.latest_version);
 
     // Determine install location
     let install_dir = get_install_dir()?;
-    let temp_path = install_dir.join(format!("{}-new", BINARY_NAME));
+    let staging = install_dir.join(format!("{}-update", BINARY_NAME));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
 
     println!("Downloading MANA {}...", info.latest_version);
 
     // Download using gh CLI
+    let checksums_sig = format!("{}.sig", CHECKSUMS);
     let download_result = Command::new("gh")
         .args([
             "release", "download", &info.tag,
             "--repo", GITHUB_REPO,
             "--pattern", BINARY_NAME,
-            "--dir", install_dir.to_str().unwrap(),
+            "--pattern", CHECKSUMS,
+            "--pattern", &checksums_sig,
+            "--dir", staging.to_str().unwrap(),
             "--clobber",
         ])
         .output();
@@ -126,6 +270,7 @@ pub async fn perform_update(info: &UpdateInfo) -> Result<()> {
             info!("Downloaded new binary");
         }
         Ok(out) => {
+            let _ = fs::remove_dir_all(&staging);
             let stderr = String::from_utf8_lossy(&out.stderr);
             if stderr.contains("no assets match") || stderr.contains("no assets found") {
                 // No binary in release - suggest building from source
@@ -142,16 +287,26 @@ pub async fn perform_update(info: &UpdateInfo) -> Result<()> {
             return Err(anyhow!("Download failed: {}", stderr));
         }
         Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
             return Err(anyhow!("Failed to run gh download: {}", e));
         }
     }
 
+    let result = verify_and_install(&install_dir, &staging, settings);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Check a staged release and move its binary into `install_dir`
+fn verify_and_install(install_dir: &Path, staging: &Path, settings: &UpdateSettings) -> Result<()> {
     // Check if download created the file
-    let downloaded_path = install_dir.join(BINARY_NAME);
+    let downloaded_path = staging.join(BINARY_NAME);
     if !downloaded_path.exists() {
         return Err(anyhow!("Download completed but binary not found at {:?}", downloaded_path));
     }
 
+    verify_release_file(staging, BINARY_NAME, settings)?;
+
     // Make executable
     #[cfg(unix)]
     {
@@ -169,21 +324,112 @@ pub async fn perform_update(info: &UpdateInfo) -> Result<()> {
     match verify {
         Ok(out) if out.status.success() => {
             let version_output = String::from_utf8_lossy(&out.stdout);
+            install_binary(install_dir, &downloaded_path)?;
             println!("Successfully updated to: {}", version_output.trim());
+            println!("Previous version kept; undo with 'mana update --rollback'");
             info!("Update complete");
         }
         _ => {
-            // Rollback: remove potentially corrupted download
+            // Nothing was replaced, so the current version stays in place
             warn!("New binary verification failed, keeping current version");
             return Err(anyhow!("Downloaded binary failed verification"));
         }
     }
 
-    // Clean up temp file if it exists
-    if temp_path.exists() {
-        let _ = fs::remove_file(&temp_path);
+    Ok(())
+}
+
+/// Check a downloaded release file against the release's signed `SHA256SUMS`
+///
+/// The signature is only required when `[update] release_keys` is set.
+fn verify_release_file(staging: &Path, name: &str, settings: &UpdateSettings) -> Result<()> {
+    let manifest = staging.join(CHECKSUMS);
+    if !manifest.exists() {
+        return Err(fail(Verification, format!("Release has no {} manifest; refusing to install unverified files", CHECKSUMS)));
     }
+    if settings.release_keys.is_empty() {
+        println!("Note: no [update] release_keys configured; checking the checksum only");
+    } else {
+        match signing::verify_in_namespace(&manifest, &settings.release_keys, RELEASE_NAMESPACE)? {
+            SignatureStatus::Verified { signer } => println!("🔏 Release manifest signed by {}", signer),
+            SignatureStatus::Unsigned => {
+                return Err(fail(Verification, format!("Release {} is not signed; refusing to install", CHECKSUMS)))
+            }
+        }
+    }
+    verify_checksum(&staging.join(name), name, &fs::read_to_string(&manifest)?)?;
+    println!("Checksum verified for {}", name);
+    Ok(())
+}
+
+/// SHA-256 of a file as lowercase hex
+pub(crate) fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = fs::File::open(path)?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Check a file against its `name` line in a `sha256sum`-style manifest
+fn verify_checksum(file: &Path, name: &str, manifest: &str) -> Result<()> {
+    let expected = manifest
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, entry)| entry.trim().trim_start_matches('*') == name)
+        .map(|(hash, _)| hash.to_lowercase())
+        .ok_or_else(|| anyhow!("{} has no entry for {}", CHECKSUMS, name))?;
+    let actual = sha256_file(file)?;
+    if actual != expected {
+        return Err(fail(Verification, format!("Checksum mismatch for {}: expected {}, got {}", name, expected, actual)));
+    }
+    Ok(())
+}
+
+/// Move `new_binary` into place, keeping the current one as `mana.bak`
+fn install_binary(install_dir: &Path, new_binary: &Path) -> Result<()> {
+    let target = install_dir.join(BINARY_NAME);
+    if target.exists() {
+        fs::rename(&target, backup_path(install_dir)).context("Failed to back up the current binary")?;
+    }
+    fs::rename(new_binary, &target).context("Failed to install the new binary")?;
+    Ok(())
+}
 
+fn backup_path(install_dir: &Path) -> PathBuf {
+    install_dir.join(format!("{}.bak", BINARY_NAME))
+}
+
+/// Swap the installed binary with `mana.bak`
+///
+/// Rolling back twice returns to the updated version.
+fn rollback_in(install_dir: &Path) -> Result<()> {
+    let backup = backup_path(install_dir);
+    if !backup.exists() {
+        bail!("No previous version to roll back to ({} not found)", backup.display());
+    }
+    let target = install_dir.join(BINARY_NAME);
+    let swap = install_dir.join(format!("{}.rollback", BINARY_NAME));
+    if target.exists() {
+        fs::rename(&target, &swap)?;
+    }
+    fs::rename(&backup, &target)?;
+    if swap.exists() {
+        fs::rename(&swap, &backup)?;
+    }
+    Ok(())
+}
+
+/// Restore the binary replaced by the last update
+pub fn rollback_command() -> Result<()> {
+    let install_dir = get_install_dir()?;
+    rollback_in(&install_dir)?;
+    let restored = Command::new(install_dir.join(BINARY_NAME))
+        .arg("--version")
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_default();
+    println!("Rolled back to: {}", if restored.is_empty() { "previous version" } else { &restored });
+    println!("Run 'mana update --rollback' again to return to the newer version.");
     Ok(())
 }
 
@@ -202,11 +448,89 @@ fn get_install_dir() -> Result<PathBuf> {
     Ok(home.join(".mana"))
 }
 
+/// Asset manifest for `channel`, from `[update] assets_url` or the release
+fn fetch_asset_manifest(staging: &Path, channel: Channel, settings: &UpdateSettings) -> Result<AssetManifest> {
+    if let Some(url) = &settings.assets_url {
+        let path = staging.join(ASSETS);
+        assets::download(url, &path)?;
+        return AssetManifest::parse(&fs::read_to_string(path)?);
+    }
+
+    let mut args = vec!["release".to_string(), "download".to_string()];
+    if channel == Channel::Beta {
+        if let Some(tag) = newest_tag()? {
+            args.push(tag);
+        }
+    }
+    let checksums_sig = format!("{}.sig", CHECKSUMS);
+    args.extend(
+        ["--repo", GITHUB_REPO, "--pattern", ASSETS, "--pattern", CHECKSUMS, "--pattern", &checksums_sig, "--clobber"]
+            .map(String::from),
+    );
+    args.extend(["--dir".to_string(), staging.display().to_string()]);
+    let output = Command::new("gh")
+        .args(&args)
+        .output()
+        .map_err(|e| anyhow!("GitHub CLI (gh) not available: {}", e))?;
+    if !output.status.success() {
+        bail!("Failed to download {}: {}", ASSETS, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    verify_release_file(staging, ASSETS, settings)?;
+    AssetManifest::parse(&fs::read_to_string(staging.join(ASSETS))?)
+}
+
+/// Install or upgrade the embedding model's assets (`mana update --assets`)
+///
+/// When the model version changes, embeddings made by the old version are
+/// rebuilt so the index never mixes vectors from two models.
+pub fn assets_command(mana_dir: &Path, channel: Option<&str>, settings: &UpdateSettings) -> Result<()> {
+    let channel = Channel::parse(channel.unwrap_or(&settings.channel))?;
+    let model = EmbeddingStore::open(mana_dir)?.model().name().to_string();
+
+    let staging = mana_dir.join("models").join(".manifest");
+    fs::create_dir_all(&staging)?;
+    let manifest = fetch_asset_manifest(&staging, channel, settings);
+    let _ = fs::remove_dir_all(&staging);
+    let manifest = manifest?;
+
+    let Some(asset) = manifest.find(&model) else {
+        println!("No assets published for model {}; nothing to install.", model);
+        return Ok(());
+    };
+    println!("Installing {} {} ({} files)...", asset.name, asset.version, asset.files.len());
+    let installed = assets::install(mana_dir, asset)?;
+    if installed.downloaded == 0 {
+        println!("Assets for {} {} already installed and verified.", installed.name, installed.version);
+    } else {
+        println!("Verified and installed {} files under {}", installed.downloaded,
+            assets::model_dir(mana_dir, &installed.name).join(&installed.version).display());
+    }
+
+    if installed.changed() {
+        if let Some(previous) = &installed.previous {
+            println!("Model version changed ({} -> {})", previous, installed.version);
+        }
+        if embeddings::is_available(mana_dir) {
+            println!("Rebuilding embeddings index...");
+            let count = EmbeddingStore::open(mana_dir)?.rebuild()?;
+            let db_path = mana_dir.join("metadata.sqlite");
+            crate::storage::ensure_schema(&db_path)?;
+            let conn = crate::storage::db::open(&db_path)?;
+            crate::storage::snapshot::rebuild(mana_dir, &conn)?;
+            println!("Rebuilt embeddings for {} patterns", count);
+        }
+    }
+    Ok(())
+}
+
 /// Main update command handler
-pub async fn update_command(force: bool) -> Result<()> {
-    println!("Checking for updates...");
+///
+/// `channel` overrides `[update] channel`.
+pub async fn update_command(force: bool, channel: Option<&str>, settings: &UpdateSettings) -> Result<()> {
+    let channel = Channel::parse(channel.unwrap_or(&settings.channel))?;
+    println!("Checking for updates{}...", if channel == Channel::Beta { " (beta channel)" } else { "" });
 
-    match check_for_updates().await? {
+    match check_for_updates(channel).await? {
         Some(info) => {
             println!();
             println!("Update available!");
@@ -227,7 +551,7 @@ pub async fn update_command(force: bool) -> Result<()> {
             }
 
             if force {
-                perform_update(&info).await?;
+                perform_update(&info, settings).await?;
             } else {
                 println!("Run 'mana update --force' to install the update.");
                 println!("Or manually: gh release download {} --repo {} -p mana", info.tag, GITHUB_REPO);
@@ -260,5 +584,41 @@ mod tests {
         // Test with various version formats
         assert!(is_newer_version("0.2", "0.1.0"));
         assert!(is_newer_version("1", "0.9.9"));
+        assert!(is_newer_version("0.3.0", "0.3.0-beta.1"));
+        assert!(is_newer_version("0.3.0-beta.2", "0.3.0-beta.1"));
+        assert!(!is_newer_version("0.3.0-beta.1", "0.3.0"));
+        assert!(is_newer_version("0.3.0-beta.1", "0.2.9"));
+        assert!(is_newer_version("0.3.0-beta.10", "0.3.0-beta.9"));
+        assert!(!is_newer_version("0.3.0-beta.9", "0.3.0-beta.10"));
+        assert!(is_newer_version("0.3.0-beta.1", "0.3.0-beta"));
+        assert!(is_newer_version("0.3.0-rc.1", "0.3.0-beta.11"));
+        assert!(is_newer_version("0.3.0-alpha.beta", "0.3.0-alpha.1"));
+    }
+
+    #[test]
+    fn test_checksum_install_and_rollback() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dir = temp.path();
+        fs::write(dir.join(BINARY_NAME), "old").unwrap();
+        let staged = dir.join("staged");
+        fs::write(&staged, "new").unwrap();
+
+        let hash = sha256_file(&staged).unwrap();
+        assert_eq!(hash, "11507a0e2f5e69d5dfa40a62a1bd7b6ee57e6bcd85c67c9b8431b36fff21c437");
+        verify_checksum(&staged, BINARY_NAME, &format!("{}  *{}\nabc  other\n", hash, BINARY_NAME)).unwrap();
+        assert!(verify_checksum(&staged, BINARY_NAME, &format!("{}  {}\n", "0".repeat(64), BINARY_NAME)).is_err());
+        assert!(verify_checksum(&staged, BINARY_NAME, "abc  other\n").is_err());
+
+        install_binary(dir, &staged).unwrap();
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!((read("mana"), read("mana.bak")), ("new".to_string(), "old".to_string()));
+
+        rollback_in(dir).unwrap();
+        assert_eq!((read("mana"), read("mana.bak")), ("old".to_string(), "new".to_string()));
+        rollback_in(dir).unwrap();
+        assert_eq!(read("mana"), "new");
+
+        fs::remove_file(dir.join("mana.bak")).unwrap();
+        assert!(rollback_in(dir).is_err());
     }
 }
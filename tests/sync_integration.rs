@@ -63,7 +63,7 @@ fn test_export_basic() {
     let temp = TempDir::new().expect("Failed to create temp dir");
     let output_path = temp.path().join("patterns.json");
 
-    let (success, stdout, stderr) = run_mana(&[
+    let (success, _stdout, stderr) = run_mana(&[
         "export",
         "--output", output_path.to_str().unwrap(),
     ]);